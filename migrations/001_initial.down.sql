@@ -0,0 +1,4 @@
+DROP TABLE IF EXISTS marks;
+DROP TABLE IF EXISTS media;
+DROP TABLE IF EXISTS sessions;
+DROP TABLE IF EXISTS users;
@@ -0,0 +1,32 @@
+-- Builds before persistent media cannot see the permanent dirs, so items kept
+-- there are marked gone; a later scan re-activates any that are moved back.
+PRAGMA foreign_keys = OFF;
+
+CREATE TABLE IF NOT EXISTS media_old (
+    id         INTEGER PRIMARY KEY AUTOINCREMENT,
+    media_type TEXT NOT NULL CHECK(media_type IN ('movie', 'tv_season')),
+    title      TEXT NOT NULL,
+    year       INTEGER,
+    season     INTEGER,
+    path       TEXT NOT NULL UNIQUE,
+    size_bytes INTEGER NOT NULL DEFAULT 0,
+    status     TEXT NOT NULL DEFAULT 'active' CHECK(status IN ('active', 'trashed', 'gone')),
+    trashed_at TEXT,
+    first_seen TEXT NOT NULL DEFAULT (datetime('now')),
+    last_seen  TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+INSERT INTO media_old (
+    id, media_type, title, year, season, path, size_bytes, status, trashed_at, first_seen, last_seen
+)
+SELECT
+    id, media_type, title, year, season, path, size_bytes,
+    CASE status WHEN 'permanent' THEN 'gone' ELSE status END,
+    trashed_at, first_seen, last_seen
+FROM media;
+
+DROP TABLE media;
+ALTER TABLE media_old RENAME TO media;
+DROP TABLE IF EXISTS persistent_media;
+
+PRAGMA foreign_keys = ON;
@@ -0,0 +1 @@
+ALTER TABLE media ADD COLUMN dir_mtime INTEGER;
@@ -0,0 +1,11 @@
+CREATE TABLE IF NOT EXISTS scan_summaries (
+    id            INTEGER PRIMARY KEY AUTOINCREMENT,
+    scope         TEXT NOT NULL,
+    duration_secs INTEGER NOT NULL DEFAULT 0,
+    items_seen    INTEGER NOT NULL DEFAULT 0,
+    added         INTEGER NOT NULL DEFAULT 0,
+    reactivated   INTEGER NOT NULL DEFAULT 0,
+    marked_gone   INTEGER NOT NULL DEFAULT 0,
+    size_delta    INTEGER NOT NULL DEFAULT 0,
+    finished_at   TEXT NOT NULL DEFAULT (datetime('now'))
+);
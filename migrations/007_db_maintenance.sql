@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS db_maintenance (
+    task        TEXT PRIMARY KEY CHECK(task IN ('optimize', 'vacuum')),
+    last_run    TEXT NOT NULL DEFAULT (datetime('now')),
+    duration_ms INTEGER NOT NULL DEFAULT 0,
+    freed_bytes INTEGER NOT NULL DEFAULT 0
+);
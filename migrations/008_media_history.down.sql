@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_media_history_media;
+DROP TABLE IF EXISTS media_history;
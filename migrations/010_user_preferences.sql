@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS user_preferences (
+    user_id     INTEGER PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    sort        TEXT NOT NULL DEFAULT 'name',
+    sort_dir    TEXT NOT NULL DEFAULT 'asc',
+    show_marked INTEGER NOT NULL DEFAULT 0,
+    page_size   INTEGER NOT NULL DEFAULT 0,
+    theme       TEXT NOT NULL DEFAULT 'dark'
+);
@@ -0,0 +1,3 @@
+ALTER TABLE users ADD COLUMN feed_token TEXT;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_users_feed_token ON users(feed_token);
@@ -0,0 +1 @@
+ALTER TABLE media ADD COLUMN size_pending INTEGER NOT NULL DEFAULT 0;
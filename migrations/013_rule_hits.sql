@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS rule_hits (
+    rule     TEXT NOT NULL,
+    media_id INTEGER NOT NULL REFERENCES media(id) ON DELETE CASCADE,
+    action   TEXT NOT NULL,
+    hit_at   TEXT NOT NULL DEFAULT (datetime('now')),
+    PRIMARY KEY (rule, media_id)
+);
@@ -0,0 +1 @@
+ALTER TABLE media ADD COLUMN protected INTEGER NOT NULL DEFAULT 0;
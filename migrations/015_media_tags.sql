@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS media_tags (
+    media_id  INTEGER NOT NULL REFERENCES media(id) ON DELETE CASCADE,
+    tag       TEXT NOT NULL COLLATE NOCASE,
+    tagged_at TEXT NOT NULL DEFAULT (datetime('now')),
+    PRIMARY KEY (media_id, tag)
+);
+
+CREATE INDEX IF NOT EXISTS idx_media_tags_tag ON media_tags(tag);
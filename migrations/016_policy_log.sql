@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS policy_log (
+    id        INTEGER PRIMARY KEY AUTOINCREMENT,
+    policy    TEXT NOT NULL,
+    media_id  INTEGER NOT NULL REFERENCES media(id) ON DELETE CASCADE,
+    action    TEXT NOT NULL,
+    detail    TEXT NOT NULL,
+    logged_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_policy_log_media ON policy_log(media_id);
@@ -0,0 +1 @@
+ALTER TABLE media ADD COLUMN manual INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS deletion_approvals;
+DROP TABLE IF EXISTS pending_deletions;
@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS pending_deletions (
+    media_id     INTEGER PRIMARY KEY REFERENCES media(id) ON DELETE CASCADE,
+    requested_by TEXT NOT NULL,
+    requested_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE TABLE IF NOT EXISTS deletion_approvals (
+    media_id    INTEGER NOT NULL REFERENCES media(id) ON DELETE CASCADE,
+    user_id     INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    approved_at TEXT NOT NULL DEFAULT (datetime('now')),
+    PRIMARY KEY (media_id, user_id)
+);
@@ -0,0 +1,6 @@
+-- A media dir with rows here is only visible to, and voted on by, those users.
+CREATE TABLE IF NOT EXISTS library_access (
+    media_dir TEXT NOT NULL,
+    user_id   INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    PRIMARY KEY (media_dir, user_id)
+);
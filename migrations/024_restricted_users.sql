@@ -0,0 +1 @@
+ALTER TABLE users ADD COLUMN restricted INTEGER NOT NULL DEFAULT 0;
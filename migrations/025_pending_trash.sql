@@ -0,0 +1,5 @@
+CREATE TABLE IF NOT EXISTS pending_trash (
+    media_id     INTEGER PRIMARY KEY REFERENCES media(id) ON DELETE CASCADE,
+    requested_by TEXT NOT NULL,
+    requested_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
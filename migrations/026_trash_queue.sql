@@ -0,0 +1,5 @@
+CREATE TABLE IF NOT EXISTS trash_queue (
+    media_id  INTEGER PRIMARY KEY REFERENCES media(id) ON DELETE CASCADE,
+    queued_by TEXT NOT NULL,
+    queued_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
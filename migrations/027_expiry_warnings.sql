@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS expiry_warnings (
+    media_id   INTEGER NOT NULL REFERENCES media(id) ON DELETE CASCADE,
+    user_id    INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    -- Trashing the warning is about; a later trashing warns afresh.
+    trashed_at TEXT NOT NULL,
+    deletes_at TEXT NOT NULL,
+    dismissed  INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (media_id, user_id, trashed_at)
+);
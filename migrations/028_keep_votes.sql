@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS keep_votes (
+    user_id    INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    media_id   INTEGER NOT NULL REFERENCES media(id) ON DELETE CASCADE,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    PRIMARY KEY (user_id, media_id)
+);
@@ -0,0 +1,40 @@
+-- Older builds cannot see the archive, so archived items are marked gone. The
+-- table is rebuilt the way migrations 002 to 019 left it.
+PRAGMA foreign_keys = OFF;
+
+CREATE TABLE IF NOT EXISTS media_old (
+    id         INTEGER PRIMARY KEY AUTOINCREMENT,
+    media_type TEXT NOT NULL CHECK(media_type IN ('movie', 'tv_season')),
+    title      TEXT NOT NULL,
+    year       INTEGER,
+    season     INTEGER,
+    path       TEXT NOT NULL UNIQUE,
+    size_bytes INTEGER NOT NULL DEFAULT 0,
+    status     TEXT NOT NULL DEFAULT 'active' CHECK(status IN ('active', 'trashed', 'gone', 'permanent')),
+    trashed_at TEXT,
+    first_seen TEXT NOT NULL DEFAULT (datetime('now')),
+    last_seen  TEXT NOT NULL DEFAULT (datetime('now'))
+);
+ALTER TABLE media_old ADD COLUMN poster_path TEXT;
+ALTER TABLE media_old ADD COLUMN dir_mtime INTEGER;
+ALTER TABLE media_old ADD COLUMN size_pending INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE media_old ADD COLUMN protected INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE media_old ADD COLUMN trash_name TEXT;
+ALTER TABLE media_old ADD COLUMN trash_path TEXT;
+ALTER TABLE media_old ADD COLUMN manual INTEGER NOT NULL DEFAULT 0;
+
+INSERT INTO media_old (
+    id, media_type, title, year, season, path, size_bytes, status, trashed_at, first_seen,
+    last_seen, poster_path, dir_mtime, size_pending, protected, trash_name, trash_path, manual
+)
+SELECT
+    id, media_type, title, year, season, path, size_bytes,
+    CASE status WHEN 'archived' THEN 'gone' ELSE status END,
+    trashed_at, first_seen, last_seen, poster_path, dir_mtime, size_pending, protected,
+    trash_name, trash_path, manual
+FROM media;
+
+DROP TABLE media;
+ALTER TABLE media_old RENAME TO media;
+
+PRAGMA foreign_keys = ON;
@@ -0,0 +1,40 @@
+-- Expired trash moved to the archive_dir gets status 'archived', which the
+-- status check has to allow, so the table is rebuilt.
+PRAGMA foreign_keys = OFF;
+
+CREATE TABLE IF NOT EXISTS media_new (
+    id           INTEGER PRIMARY KEY AUTOINCREMENT,
+    media_type   TEXT NOT NULL CHECK(media_type IN ('movie', 'tv_season')),
+    title        TEXT NOT NULL,
+    year         INTEGER,
+    season       INTEGER,
+    path         TEXT NOT NULL UNIQUE,
+    size_bytes   INTEGER NOT NULL DEFAULT 0,
+    status       TEXT NOT NULL DEFAULT 'active'
+                 CHECK(status IN ('active', 'trashed', 'gone', 'permanent', 'archived')),
+    trashed_at   TEXT,
+    first_seen   TEXT NOT NULL DEFAULT (datetime('now')),
+    last_seen    TEXT NOT NULL DEFAULT (datetime('now')),
+    poster_path  TEXT,
+    dir_mtime    INTEGER,
+    size_pending INTEGER NOT NULL DEFAULT 0,
+    protected    INTEGER NOT NULL DEFAULT 0,
+    trash_name   TEXT,
+    trash_path   TEXT,
+    manual       INTEGER NOT NULL DEFAULT 0,
+    archive_path TEXT
+);
+
+INSERT INTO media_new (
+    id, media_type, title, year, season, path, size_bytes, status, trashed_at, first_seen,
+    last_seen, poster_path, dir_mtime, size_pending, protected, trash_name, trash_path, manual
+)
+SELECT
+    id, media_type, title, year, season, path, size_bytes, status, trashed_at, first_seen,
+    last_seen, poster_path, dir_mtime, size_pending, protected, trash_name, trash_path, manual
+FROM media;
+
+DROP TABLE media;
+ALTER TABLE media_new RENAME TO media;
+
+PRAGMA foreign_keys = ON;
@@ -0,0 +1 @@
+ALTER TABLE media ADD COLUMN cloud_key TEXT;
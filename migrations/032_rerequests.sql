@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS rerequests (
+    media_id     INTEGER NOT NULL REFERENCES media(id) ON DELETE CASCADE,
+    user_id      INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    requested_at TEXT NOT NULL DEFAULT (datetime('now')),
+    forwarded_to TEXT,
+    PRIMARY KEY (media_id, user_id)
+);
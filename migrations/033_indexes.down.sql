@@ -0,0 +1,4 @@
+DROP INDEX IF EXISTS idx_sessions_expires;
+DROP INDEX IF EXISTS idx_marks_media;
+DROP INDEX IF EXISTS idx_media_trashed_at;
+DROP INDEX IF EXISTS idx_media_status_type;
@@ -0,0 +1,6 @@
+-- The list pages, trash cleanup, quorum checks and session expiry filter on
+-- these columns, which without an index meant a full table scan each time.
+CREATE INDEX IF NOT EXISTS idx_media_status_type ON media(status, media_type);
+CREATE INDEX IF NOT EXISTS idx_media_trashed_at ON media(trashed_at);
+CREATE INDEX IF NOT EXISTS idx_marks_media ON marks(media_id);
+CREATE INDEX IF NOT EXISTS idx_sessions_expires ON sessions(expires_at);
@@ -0,0 +1 @@
+ALTER TABLE media ADD COLUMN poster_hash TEXT;
@@ -0,0 +1,5 @@
+CREATE TABLE IF NOT EXISTS trash_journal (
+    media_id INTEGER PRIMARY KEY REFERENCES media(id) ON DELETE CASCADE,
+    src      TEXT NOT NULL,
+    dest     TEXT NOT NULL
+);
@@ -0,0 +1,5 @@
+CREATE TABLE IF NOT EXISTS persistent_series (
+    title        TEXT PRIMARY KEY,
+    user_id      INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    persisted_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
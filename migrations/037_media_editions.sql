@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS media_editions (
+    media_id   INTEGER PRIMARY KEY REFERENCES media(id) ON DELETE CASCADE,
+    -- Id of the edition the group was started from; shared by all editions.
+    group_id   INTEGER NOT NULL,
+    linked_at  TEXT NOT NULL DEFAULT (datetime('now'))
+);
+CREATE INDEX IF NOT EXISTS idx_media_editions_group ON media_editions(group_id);
@@ -0,0 +1,3 @@
+-- Lets scans of case-insensitive libraries find an item renamed only in case
+-- without reading the whole table.
+CREATE INDEX IF NOT EXISTS idx_media_path_nocase ON media(path COLLATE NOCASE);
@@ -0,0 +1,344 @@
+//! Optional links to Overseerr, Radarr and Sonarr, which re-requests of
+//! deleted items are forwarded to so they get downloaded again.
+//!
+//! Overseerr takes both movies and seasons when it is configured; otherwise
+//! movies go to Radarr and seasons to Sonarr. Without any of them a
+//! re-request only shows up for the admins.
+
+use reqwest::Method;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+use crate::config::AppConfig;
+use crate::models::media::Media;
+use crate::s3::uri_encode;
+
+/// The user waits on the forward, so it must not hang on a dead service.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The `[overseerr]` config table.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+pub struct OverseerrConfig {
+    /// Base URL, e.g. "http://overseerr:5055".
+    pub url: String,
+    pub api_key: String,
+}
+
+/// The `[radarr]` and `[sonarr]` config tables.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+pub struct ArrConfig {
+    /// Base URL, e.g. "http://radarr:7878".
+    pub url: String,
+    pub api_key: String,
+    /// Folder that titles the service does not have yet are added to.
+    pub root_folder: String,
+    /// Quality profile for titles the service does not have yet.
+    pub quality_profile_id: i64,
+    /// Language profile for new series; only Sonarr v3 needs one.
+    pub language_profile_id: Option<i64>,
+}
+
+fn validate_service(url: &str, api_key: &str) -> Result<(), String> {
+    if !(url.starts_with("http://") || url.starts_with("https://")) {
+        return Err(format!("url {url:?} must start with http:// or https://"));
+    }
+    if api_key.trim().is_empty() {
+        return Err("api_key must not be empty".to_string());
+    }
+    Ok(())
+}
+
+impl OverseerrConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        validate_service(&self.url, &self.api_key)
+    }
+}
+
+impl ArrConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        validate_service(&self.url, &self.api_key)?;
+        if self.root_folder.trim().is_empty() {
+            return Err("root_folder must not be empty".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Where a re-request goes.
+enum Target<'a> {
+    Overseerr(&'a OverseerrConfig),
+    Radarr(&'a ArrConfig),
+    Sonarr(&'a ArrConfig),
+}
+
+impl<'a> Target<'a> {
+    fn for_item(config: &'a AppConfig, item: &Media) -> Option<Self> {
+        if let Some(overseerr) = &config.overseerr {
+            Some(Target::Overseerr(overseerr))
+        } else if item.media_type == "movie" {
+            config.radarr.as_ref().map(Target::Radarr)
+        } else {
+            config.sonarr.as_ref().map(Target::Sonarr)
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Target::Overseerr(_) => "overseerr",
+            Target::Radarr(_) => "radarr",
+            Target::Sonarr(_) => "sonarr",
+        }
+    }
+}
+
+struct Service<'a> {
+    client: reqwest::Client,
+    url: &'a str,
+    api_key: &'a str,
+}
+
+impl<'a> Service<'a> {
+    fn new(url: &'a str, api_key: &'a str) -> Self {
+        Self {
+            client: reqwest::Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+            url,
+            api_key,
+        }
+    }
+
+    async fn call(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&Value>,
+    ) -> Result<Value, Box<dyn std::error::Error + Send + Sync>> {
+        let mut request = self
+            .client
+            .request(method, format!("{}{path}", self.url.trim_end_matches('/')))
+            .header("X-Api-Key", self.api_key);
+        if let Some(body) = body {
+            request = request.json(body);
+        }
+        let response = request.send().await?;
+        let status = response.status();
+        let text = response.text().await?;
+        if !status.is_success() {
+            return Err(format!("{path}: {status}: {}", text.trim()).into());
+        }
+        if text.trim().is_empty() {
+            return Ok(Value::Null);
+        }
+        Ok(serde_json::from_str(&text)?)
+    }
+}
+
+/// The year a search result came out, from whichever date field it has.
+fn result_year(result: &Value) -> Option<i64> {
+    ["year", "releaseDate", "firstAirDate"]
+        .iter()
+        .find_map(|field| match result.get(field)? {
+            Value::Number(n) => n.as_i64(),
+            Value::String(s) => s.get(..4)?.parse().ok(),
+            _ => None,
+        })
+}
+
+/// The result that best matches an item from `year`: the first from that
+/// year, or else simply the first.
+fn best_match(results: &[Value], year: Option<i64>) -> Option<&Value> {
+    year.and_then(|year| results.iter().find(|r| result_year(r) == Some(year)))
+        .or_else(|| results.first())
+}
+
+fn search_term(item: &Media) -> String {
+    match (item.media_type.as_str(), item.year) {
+        ("movie", Some(year)) => format!("{} {year}", item.title),
+        _ => item.title.clone(),
+    }
+}
+
+async fn request_from_overseerr(
+    config: &OverseerrConfig,
+    item: &Media,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let service = Service::new(&config.url, &config.api_key);
+    let media_type = if item.media_type == "movie" {
+        "movie"
+    } else {
+        "tv"
+    };
+    // Overseerr rejects queries with spaces encoded as "+".
+    let found = service
+        .call(
+            Method::GET,
+            &format!(
+                "/api/v1/search?query={}&page=1",
+                uri_encode(&item.title, false)
+            ),
+            None,
+        )
+        .await?;
+    let results: Vec<Value> = found["results"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|r| r["mediaType"] == media_type)
+        .cloned()
+        .collect();
+    let tmdb_id = best_match(&results, item.year)
+        .and_then(|r| r["id"].as_i64())
+        .ok_or_else(|| format!("Overseerr found no {media_type} titled {:?}", item.title))?;
+    let mut body = json!({ "mediaType": media_type, "mediaId": tmdb_id });
+    if let Some(season) = item.season {
+        body["seasons"] = json!([season]);
+    }
+    service
+        .call(Method::POST, "/api/v1/request", Some(&body))
+        .await?;
+    Ok(())
+}
+
+async fn request_from_radarr(
+    config: &ArrConfig,
+    item: &Media,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let service = Service::new(&config.url, &config.api_key);
+    let found = service
+        .call(
+            Method::GET,
+            &format!(
+                "/api/v3/movie/lookup?term={}",
+                uri_encode(&search_term(item), false)
+            ),
+            None,
+        )
+        .await?;
+    let movie = best_match(
+        found.as_array().map(Vec::as_slice).unwrap_or_default(),
+        item.year,
+    )
+    .ok_or_else(|| format!("Radarr found no movie titled {:?}", item.title))?;
+
+    // A movie Radarr still has just needs searching for again.
+    if let Some(id) = movie["id"].as_i64().filter(|id| *id > 0) {
+        let command = json!({ "name": "MoviesSearch", "movieIds": [id] });
+        service
+            .call(Method::POST, "/api/v3/command", Some(&command))
+            .await?;
+        return Ok(());
+    }
+    let mut movie = movie.clone();
+    movie["qualityProfileId"] = json!(config.quality_profile_id);
+    movie["rootFolderPath"] = json!(config.root_folder);
+    movie["monitored"] = json!(true);
+    movie["addOptions"] = json!({ "searchForMovie": true });
+    service
+        .call(Method::POST, "/api/v3/movie", Some(&movie))
+        .await?;
+    Ok(())
+}
+
+async fn request_from_sonarr(
+    config: &ArrConfig,
+    item: &Media,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let service = Service::new(&config.url, &config.api_key);
+    let season = item.season.unwrap_or(0);
+    let found = service
+        .call(
+            Method::GET,
+            &format!(
+                "/api/v3/series/lookup?term={}",
+                uri_encode(&search_term(item), false)
+            ),
+            None,
+        )
+        .await?;
+    let series = best_match(
+        found.as_array().map(Vec::as_slice).unwrap_or_default(),
+        item.year,
+    )
+    .ok_or_else(|| format!("Sonarr found no series titled {:?}", item.title))?;
+
+    // Only the requested season is monitored, so Sonarr does not fetch the rest.
+    let monitor = |series: &mut Value| {
+        if let Some(seasons) = series["seasons"].as_array_mut() {
+            for s in seasons {
+                if s["seasonNumber"].as_i64() == Some(season) {
+                    s["monitored"] = json!(true);
+                }
+            }
+        }
+    };
+    if let Some(id) = series["id"].as_i64().filter(|id| *id > 0) {
+        let mut series = service
+            .call(Method::GET, &format!("/api/v3/series/{id}"), None)
+            .await?;
+        monitor(&mut series);
+        service
+            .call(Method::PUT, &format!("/api/v3/series/{id}"), Some(&series))
+            .await?;
+        let command = json!({ "name": "SeasonSearch", "seriesId": id, "seasonNumber": season });
+        service
+            .call(Method::POST, "/api/v3/command", Some(&command))
+            .await?;
+        return Ok(());
+    }
+    let mut series = series.clone();
+    if let Some(seasons) = series["seasons"].as_array_mut() {
+        for s in seasons {
+            s["monitored"] = json!(false);
+        }
+    }
+    monitor(&mut series);
+    series["qualityProfileId"] = json!(config.quality_profile_id);
+    if let Some(id) = config.language_profile_id {
+        series["languageProfileId"] = json!(id);
+    }
+    series["rootFolderPath"] = json!(config.root_folder);
+    series["monitored"] = json!(true);
+    series["seasonFolder"] = json!(true);
+    series["addOptions"] = json!({ "searchForMissingEpisodes": true });
+    service
+        .call(Method::POST, "/api/v3/series", Some(&series))
+        .await?;
+    Ok(())
+}
+
+/// Ask the configured service to download `item` again. Returns the name of
+/// the service, or `None` when none is configured for its type.
+pub async fn forward(
+    config: &AppConfig,
+    item: &Media,
+) -> Result<Option<&'static str>, Box<dyn std::error::Error + Send + Sync>> {
+    let Some(target) = Target::for_item(config, item) else {
+        return Ok(None);
+    };
+    match target {
+        Target::Overseerr(overseerr) => request_from_overseerr(overseerr, item).await?,
+        Target::Radarr(radarr) => request_from_radarr(radarr, item).await?,
+        Target::Sonarr(sonarr) => request_from_sonarr(sonarr, item).await?,
+    }
+    Ok(Some(target.name()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefers_results_from_the_items_year() {
+        let results = vec![
+            json!({ "id": 1, "title": "Heat", "releaseDate": "1986-01-01" }),
+            json!({ "id": 2, "title": "Heat", "year": 1995 }),
+        ];
+        assert_eq!(best_match(&results, Some(1995)).unwrap()["id"], 2);
+        assert_eq!(best_match(&results, Some(1986)).unwrap()["id"], 1);
+        assert_eq!(best_match(&results, Some(2024)).unwrap()["id"], 1);
+        assert!(best_match(&[], None).is_none());
+    }
+}
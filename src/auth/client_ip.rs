@@ -0,0 +1,228 @@
+//! The address of the browser behind a request, looking through reverse
+//! proxies listed in `trusted_proxies`.
+
+use axum::extract::{ConnectInfo, FromRequestParts};
+use axum::http::request::Parts;
+use axum::http::HeaderMap;
+use serde::Deserialize;
+use std::convert::Infallible;
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+
+use crate::routes::AppState;
+
+/// A single address ("10.0.0.1") or a CIDR block ("10.0.0.0/8", "fd00::/8").
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct IpRange {
+    network: IpAddr,
+    prefix_len: u8,
+}
+
+impl IpRange {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.network, canonical(ip)) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => mask_bits(
+                u32::from(net).into(),
+                u32::from(ip).into(),
+                32,
+                self.prefix_len,
+            ),
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                mask_bits(u128::from(net), u128::from(ip), 128, self.prefix_len)
+            }
+            _ => false,
+        }
+    }
+}
+
+fn mask_bits(net: u128, ip: u128, width: u8, prefix_len: u8) -> bool {
+    let shift = width - prefix_len;
+    shift >= 128 || (net >> shift) == (ip >> shift)
+}
+
+/// IPv4 clients reach a dual-stack listener as `::ffff:a.b.c.d`.
+fn canonical(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+        v4 => v4,
+    }
+}
+
+impl FromStr for IpRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid trusted proxy {s:?}, expected an IP or CIDR block");
+        let (addr, prefix) = match s.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (s, None),
+        };
+        let network = canonical(addr.trim().parse().map_err(|_| invalid())?);
+        let width = if network.is_ipv4() { 32 } else { 128 };
+        let prefix_len = match prefix {
+            Some(p) => p.trim().parse().map_err(|_| invalid())?,
+            None => width,
+        };
+        if prefix_len > width {
+            return Err(invalid());
+        }
+        Ok(Self {
+            network,
+            prefix_len,
+        })
+    }
+}
+
+impl TryFrom<String> for IpRange {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl fmt::Display for IpRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix_len)
+    }
+}
+
+/// Resolve the client address of a request that arrived from `peer`.
+///
+/// Forwarding headers are only believed when `peer` is a trusted proxy. The
+/// X-Forwarded-For chain is walked from the right, skipping further trusted
+/// proxies, so a client cannot spoof its address by sending the header itself.
+pub fn resolve(peer: IpAddr, headers: &HeaderMap, trusted: &[IpRange]) -> IpAddr {
+    let peer = canonical(peer);
+    let is_trusted = |ip: IpAddr| trusted.iter().any(|range| range.contains(ip));
+    if !is_trusted(peer) {
+        return peer;
+    }
+
+    let forwarded: Vec<IpAddr> = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .filter_map(|ip| ip.trim().parse().ok())
+        .map(canonical)
+        .collect();
+    if let Some(client) = forwarded.iter().rev().find(|ip| !is_trusted(**ip)) {
+        return *client;
+    }
+    if let Some(first) = forwarded.first() {
+        return *first;
+    }
+
+    headers
+        .get("x-real-ip")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse().ok())
+        .map(canonical)
+        .unwrap_or(peer)
+}
+
+/// Extractor for the real client address. `None` when the server was not
+/// started with connection info, as in tests.
+pub struct ClientIp(pub Option<IpAddr>);
+
+impl fmt::Display for ClientIp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(ip) => write!(f, "{ip}"),
+            None => write!(f, "unknown"),
+        }
+    }
+}
+
+impl FromRequestParts<AppState> for ClientIp {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let Ok(ConnectInfo(peer)) =
+            ConnectInfo::<SocketAddr>::from_request_parts(parts, state).await
+        else {
+            return Ok(ClientIp(None));
+        };
+        let config = state.config.current();
+        Ok(ClientIp(Some(resolve(
+            peer.ip(),
+            &parts.headers,
+            &config.trusted_proxies,
+        ))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranges(list: &[&str]) -> Vec<IpRange> {
+        list.iter().map(|s| s.parse().unwrap()).collect()
+    }
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for (name, value) in pairs {
+            map.append(*name, value.parse().unwrap());
+        }
+        map
+    }
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_addresses_and_cidr_blocks() {
+        let lan: IpRange = "192.168.1.0/24".parse().unwrap();
+        assert!(lan.contains(ip("192.168.1.77")));
+        assert!(lan.contains(ip("::ffff:192.168.1.77")));
+        assert!(!lan.contains(ip("192.168.2.1")));
+
+        let single: IpRange = "::1".parse().unwrap();
+        assert!(single.contains(ip("::1")));
+        assert!(!single.contains(ip("127.0.0.1")));
+
+        let all: IpRange = "0.0.0.0/0".parse().unwrap();
+        assert!(all.contains(ip("8.8.8.8")));
+
+        assert!("10.0.0.0/33".parse::<IpRange>().is_err());
+        assert!("proxy.lan".parse::<IpRange>().is_err());
+    }
+
+    #[test]
+    fn headers_are_ignored_from_untrusted_peers() {
+        let trusted = ranges(&["10.0.0.2"]);
+        let h = headers(&[("x-forwarded-for", "1.2.3.4"), ("x-real-ip", "1.2.3.4")]);
+        assert_eq!(resolve(ip("203.0.113.9"), &h, &trusted), ip("203.0.113.9"));
+        assert_eq!(resolve(ip("203.0.113.9"), &h, &[]), ip("203.0.113.9"));
+    }
+
+    #[test]
+    fn forwarded_chain_is_walked_from_the_right() {
+        let trusted = ranges(&["10.0.0.0/8"]);
+        // The client prepended a fake address; the proxy appended the real one.
+        let h = headers(&[("x-forwarded-for", "6.6.6.6, 198.51.100.7, 10.0.0.3")]);
+        assert_eq!(resolve(ip("10.0.0.2"), &h, &trusted), ip("198.51.100.7"));
+
+        let h = headers(&[("x-forwarded-for", "10.0.0.5")]);
+        assert_eq!(resolve(ip("10.0.0.2"), &h, &trusted), ip("10.0.0.5"));
+    }
+
+    #[test]
+    fn real_ip_header_is_used_without_forwarded_for() {
+        let trusted = ranges(&["10.0.0.2"]);
+        let h = headers(&[("x-real-ip", "198.51.100.7")]);
+        assert_eq!(resolve(ip("10.0.0.2"), &h, &trusted), ip("198.51.100.7"));
+        assert_eq!(
+            resolve(ip("10.0.0.2"), &HeaderMap::new(), &trusted),
+            ip("10.0.0.2")
+        );
+    }
+}
@@ -3,19 +3,36 @@ use axum::http::request::Parts;
 use axum::response::{IntoResponse, Redirect, Response};
 use axum_extra::extract::CookieJar;
 use sqlx::SqlitePool;
+use std::sync::{Arc, OnceLock};
 
 use crate::auth::session;
-use crate::models::user;
-use crate::routes::AppState;
+use crate::banners::RequestAnnouncements;
+use crate::locale::{RequestTime, UserTime};
+use crate::models::{announcement, expiry_warning, preferences, rerequest, user};
+use crate::routes::{url, AppState};
+use crate::theme::RequestTheme;
 
 pub struct AuthUser {
     pub id: i64,
     pub username: String,
     pub is_admin: bool,
+    /// Marks are only a wish and persisting is off limits.
+    pub restricted: bool,
 }
 
 pub struct AdminUser(pub AuthUser);
 
+/// Slot the request logger puts into the request so the auth extractors can
+/// report who made the request.
+#[derive(Clone, Default)]
+pub struct RequestUser(Arc<OnceLock<String>>);
+
+impl RequestUser {
+    pub fn username(&self) -> Option<&str> {
+        self.0.get().map(String::as_str)
+    }
+}
+
 impl std::ops::Deref for AdminUser {
     type Target = AuthUser;
     fn deref(&self) -> &Self::Target {
@@ -44,22 +61,48 @@ async fn extract_auth_user(
     let token = jar
         .get("session")
         .map(|c| c.value().to_string())
-        .ok_or(AuthRejection::Redirect(Redirect::to("/login")))?;
+        .ok_or(AuthRejection::Redirect(Redirect::to(&url("/login"))))?;
 
     let user_id = session::validate(pool, &token)
         .await
-        .map_err(|_| AuthRejection::Redirect(Redirect::to("/login")))?
-        .ok_or(AuthRejection::Redirect(Redirect::to("/login")))?;
+        .map_err(|_| AuthRejection::Redirect(Redirect::to(&url("/login"))))?
+        .ok_or(AuthRejection::Redirect(Redirect::to(&url("/login"))))?;
 
     let u = user::get_by_id(pool, user_id)
         .await
-        .map_err(|_| AuthRejection::Redirect(Redirect::to("/login")))?
-        .ok_or(AuthRejection::Redirect(Redirect::to("/login")))?;
+        .map_err(|_| AuthRejection::Redirect(Redirect::to(&url("/login"))))?
+        .ok_or(AuthRejection::Redirect(Redirect::to(&url("/login"))))?;
+
+    if let Some(slot) = parts.extensions.get::<RequestUser>() {
+        let _ = slot.0.set(u.username.clone());
+    }
+    if let Some(slot) = parts.extensions.get::<RequestTime>() {
+        slot.set(UserTime::new(u.locale.as_deref(), u.timezone.as_deref()));
+    }
+    if let Some(slot) = parts.extensions.get::<RequestTheme>() {
+        if let Ok(Some(theme)) = preferences::theme(pool, u.id).await {
+            slot.set(theme);
+        }
+    }
+    if let Some(slot) = parts.extensions.get::<RequestAnnouncements>() {
+        if let Ok(announcements) = announcement::undismissed(pool, u.id).await {
+            slot.set(announcements);
+        }
+        if let Ok(warnings) = expiry_warning::undismissed(pool, u.id).await {
+            slot.set_expiring(warnings);
+        }
+        if u.is_admin {
+            if let Ok(count) = rerequest::open_count(pool).await {
+                slot.set_rerequests(count);
+            }
+        }
+    }
 
     Ok(AuthUser {
         id: u.id,
         username: u.username,
         is_admin: u.is_admin,
+        restricted: u.restricted,
     })
 }
 
@@ -83,7 +126,7 @@ impl FromRequestParts<AppState> for AdminUser {
     ) -> Result<Self, Self::Rejection> {
         let user = AuthUser::from_request_parts(parts, state).await?;
         if !user.is_admin {
-            return Err(AuthRejection::Redirect(Redirect::to("/")));
+            return Err(AuthRejection::Redirect(Redirect::to(&url("/"))));
         }
         Ok(AdminUser(user))
     }
@@ -1,6 +1,7 @@
 use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
 use rand::RngCore;
 use sqlx::SqlitePool;
+use std::net::IpAddr;
 
 pub const DEFAULT_SESSION_TTL_HOURS: u64 = 720;
 
@@ -10,18 +11,22 @@ pub fn generate_token() -> String {
     URL_SAFE_NO_PAD.encode(bytes)
 }
 
+/// Start a session; `client_ip` is the address the login came from, if known.
 pub async fn create(
     pool: &SqlitePool,
     user_id: i64,
     ttl_hours: u64,
+    client_ip: Option<IpAddr>,
 ) -> Result<String, sqlx::Error> {
     let token = generate_token();
     sqlx::query(
-        "INSERT INTO sessions (token, user_id, expires_at) VALUES (?, ?, datetime('now', ? || ' hours'))",
+        "INSERT INTO sessions (token, user_id, expires_at, client_ip)
+         VALUES (?, ?, datetime('now', ? || ' hours'), ?)",
     )
     .bind(&token)
     .bind(user_id)
     .bind(ttl_hours as i64)
+    .bind(client_ip.map(|ip| ip.to_string()))
     .execute(pool)
     .await?;
     Ok(token)
@@ -45,6 +50,15 @@ pub async fn delete(pool: &SqlitePool, token: &str) -> Result<(), sqlx::Error> {
     Ok(())
 }
 
+/// Log a user out everywhere, e.g. after their password was reset.
+pub async fn delete_for_user(pool: &SqlitePool, user_id: i64) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM sessions WHERE user_id = ?")
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected())
+}
+
 pub async fn cleanup_expired(pool: &SqlitePool) -> Result<(), sqlx::Error> {
     sqlx::query("DELETE FROM sessions WHERE expires_at <= datetime('now')")
         .execute(pool)
@@ -0,0 +1,85 @@
+//! Banners shown at the top of every page: modes that change what Rewinder
+//! does behind the users' backs, the announcements admins post, and for
+//! admins the deleted items users asked back.
+
+use std::future::Future;
+use std::sync::{Arc, OnceLock};
+
+use crate::models::announcement::Announcement;
+use crate::models::expiry_warning::ExpiryWarning;
+
+/// Which banners the page being rendered shows.
+#[derive(Clone, Copy, Default)]
+pub struct Banners {
+    pub dry_run: bool,
+    pub maintenance: bool,
+}
+
+/// Slot the auth extractor fills with the announcements and expiry warnings
+/// the user has not dismissed, and for admins the open re-requests.
+#[derive(Clone, Default)]
+pub struct RequestAnnouncements {
+    announcements: Arc<OnceLock<Vec<Announcement>>>,
+    expiring: Arc<OnceLock<Vec<ExpiryWarning>>>,
+    rerequests: Arc<OnceLock<i64>>,
+}
+
+impl RequestAnnouncements {
+    pub fn set(&self, announcements: Vec<Announcement>) {
+        let _ = self.announcements.set(announcements);
+    }
+
+    pub fn set_expiring(&self, warnings: Vec<ExpiryWarning>) {
+        let _ = self.expiring.set(warnings);
+    }
+
+    pub fn set_rerequests(&self, count: i64) {
+        let _ = self.rerequests.set(count);
+    }
+}
+
+tokio::task_local! {
+    static PAGE_BANNERS: Banners;
+    static REQUEST_ANNOUNCEMENTS: RequestAnnouncements;
+}
+
+/// Run `f` with `banners` and `slot` as what [`current`] and
+/// [`announcements`] report.
+pub async fn scope<F: Future>(banners: Banners, slot: RequestAnnouncements, f: F) -> F::Output {
+    PAGE_BANNERS
+        .scope(banners, REQUEST_ANNOUNCEMENTS.scope(slot, f))
+        .await
+}
+
+/// The banners for the page being rendered. For templates.
+pub fn current() -> Banners {
+    PAGE_BANNERS.try_with(|b| *b).unwrap_or_default()
+}
+
+/// Announcements to show the user behind the current page. For templates.
+pub fn announcements() -> Vec<Announcement> {
+    REQUEST_ANNOUNCEMENTS
+        .try_with(|slot| slot.announcements.get().cloned())
+        .ok()
+        .flatten()
+        .unwrap_or_default()
+}
+
+/// Trash the user did not mark that is deleted soon. For templates.
+pub fn expiring() -> Vec<ExpiryWarning> {
+    REQUEST_ANNOUNCEMENTS
+        .try_with(|slot| slot.expiring.get().cloned())
+        .ok()
+        .flatten()
+        .unwrap_or_default()
+}
+
+/// Deleted items users asked back that an admin has not dismissed; only
+/// filled in for admins. For templates.
+pub fn rerequests() -> i64 {
+    REQUEST_ANNOUNCEMENTS
+        .try_with(|slot| slot.rerequests.get().copied())
+        .ok()
+        .flatten()
+        .unwrap_or_default()
+}
@@ -0,0 +1,218 @@
+//! `rewinder check-config`: validate a config file and everything it points at
+//! without starting the server or changing anything on disk.
+
+use std::fmt;
+use std::path::Path;
+
+use crate::config::AppConfig;
+use crate::db;
+use crate::storage::ensure_dir_readable_and_writable;
+
+/// Outcome of a single check, printed as one line.
+pub struct CheckLine {
+    pub ok: bool,
+    pub subject: String,
+    pub detail: String,
+}
+
+#[derive(Default)]
+pub struct CheckReport {
+    pub lines: Vec<CheckLine>,
+}
+
+impl CheckReport {
+    fn pass(&mut self, subject: impl Into<String>, detail: impl Into<String>) {
+        self.push(true, subject, detail);
+    }
+
+    fn fail(&mut self, subject: impl Into<String>, detail: impl Into<String>) {
+        self.push(false, subject, detail);
+    }
+
+    fn push(&mut self, ok: bool, subject: impl Into<String>, detail: impl Into<String>) {
+        self.lines.push(CheckLine {
+            ok,
+            subject: subject.into(),
+            detail: detail.into(),
+        });
+    }
+
+    pub fn problems(&self) -> usize {
+        self.lines.iter().filter(|l| !l.ok).count()
+    }
+}
+
+impl fmt::Display for CheckReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for line in &self.lines {
+            let status = if line.ok { "ok  " } else { "FAIL" };
+            writeln!(f, "{status}  {}: {}", line.subject, line.detail)?;
+        }
+        match self.problems() {
+            0 => write!(f, "Config is valid"),
+            1 => write!(f, "1 problem found"),
+            n => write!(f, "{n} problems found"),
+        }
+    }
+}
+
+pub async fn check_config(path: &str) -> CheckReport {
+    let mut report = CheckReport::default();
+    let config = match AppConfig::load(path) {
+        Ok(config) => {
+            report.pass("config", format!("{path} parsed"));
+            config
+        }
+        Err(e) => {
+            report.fail("config", e.to_string());
+            return report;
+        }
+    };
+
+    for media_dir in &config.media_dirs {
+        check_media_dir(&mut report, media_dir);
+    }
+
+    match db::pending_migrations(&config.database_url).await {
+        Ok(None) => report.pass("database", "does not exist yet, created on first start"),
+        Ok(Some(0)) => report.pass("database", "reachable, schema up to date"),
+        Ok(Some(n)) => report.pass("database", format!("reachable, {n} migrations to apply")),
+        Err(e) => report.fail("database", format!("{}: {e}", config.database_url)),
+    }
+
+    report
+}
+
+fn check_media_dir(report: &mut CheckReport, media_dir: &Path) {
+    let subject = format!("media_dir {}", media_dir.display());
+    if let Err(e) = ensure_dir_readable_and_writable(media_dir) {
+        report.fail(subject, e.to_string());
+        return;
+    }
+    report.pass(subject, "readable and writable");
+
+    let derived = [
+        ("trash dir", AppConfig::trash_dir_for_media_dir(media_dir)),
+        (
+            "permanent dir",
+            AppConfig::permanent_dir_for_media_dir(media_dir),
+        ),
+    ];
+    for (kind, dir) in derived {
+        // AppConfig::load already rejects media dirs without a derivable sibling.
+        let Some(dir) = dir else { continue };
+        let subject = format!("{kind} {}", dir.display());
+        let (target, detail) = if dir.exists() {
+            (dir.as_path(), "readable and writable")
+        } else {
+            match dir.parent() {
+                Some(parent) => (parent, "missing, created on first start"),
+                None => continue,
+            }
+        };
+        if let Err(e) = ensure_dir_readable_and_writable(target) {
+            report.fail(subject, e.to_string());
+            continue;
+        }
+        match same_filesystem(media_dir, target) {
+            Ok(true) => report.pass(subject, detail),
+            Ok(false) => report.fail(
+                subject,
+                format!(
+                    "on a different filesystem than {}; moves would copy instead of rename",
+                    media_dir.display()
+                ),
+            ),
+            Err(e) => report.fail(subject, format!("failed to stat: {e}")),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn same_filesystem(a: &Path, b: &Path) -> std::io::Result<bool> {
+    use std::os::unix::fs::MetadataExt;
+    Ok(std::fs::metadata(a)?.dev() == std::fs::metadata(b)?.dev())
+}
+
+/// Without device ids, paths on the same drive are taken to share a filesystem.
+#[cfg(not(unix))]
+fn same_filesystem(a: &Path, b: &Path) -> std::io::Result<bool> {
+    let drive = |p: &Path| -> std::io::Result<_> {
+        let resolved = std::fs::canonicalize(p)?;
+        Ok(resolved
+            .components()
+            .next()
+            .map(|c| c.as_os_str().to_owned()))
+    };
+    Ok(drive(a)? == drive(b)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_config(dir: &Path, media_dirs: &[&Path]) -> String {
+        let path = dir.join("rewinder.toml");
+        let db = dir.join("rewinder.db");
+        std::fs::write(
+            &path,
+            format!(
+                "database_url = \"sqlite://{}\"\nlisten_addr = \"127.0.0.1:0\"\nmedia_dirs = {:?}\n",
+                db.display(),
+                media_dirs
+            ),
+        )
+        .unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    #[tokio::test]
+    async fn valid_config_passes_without_touching_disk() {
+        let base = tempfile::tempdir().unwrap();
+        let movies = base.path().join("Movies");
+        std::fs::create_dir(&movies).unwrap();
+        let path = write_config(base.path(), &[&movies]);
+
+        let report = check_config(&path).await;
+        assert_eq!(report.problems(), 0, "{report}");
+        assert!(!base.path().join("Movies_trash").exists());
+        assert!(!base.path().join("rewinder.db").exists());
+    }
+
+    #[tokio::test]
+    async fn missing_media_dir_and_bad_config_are_reported() {
+        let base = tempfile::tempdir().unwrap();
+        let missing = base.path().join("Missing");
+        let path = write_config(base.path(), &[&missing]);
+
+        let report = check_config(&path).await;
+        assert_eq!(report.problems(), 1);
+        assert!(report.to_string().contains("FAIL  media_dir"));
+
+        std::fs::write(&path, "media_dirs = ").unwrap();
+        let report = check_config(&path).await;
+        assert_eq!(report.problems(), 1);
+        assert_eq!(report.lines.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn existing_database_reports_pending_migrations() {
+        let base = tempfile::tempdir().unwrap();
+        let movies = base.path().join("Movies");
+        std::fs::create_dir(&movies).unwrap();
+        let path = write_config(base.path(), &[&movies]);
+        let url = format!(
+            "sqlite://{}?mode=rwc",
+            base.path().join("rewinder.db").display()
+        );
+        db::init_pool(&url, &db::PoolSettings::default())
+            .await
+            .unwrap()
+            .close()
+            .await;
+
+        let report = check_config(&path).await;
+        assert_eq!(report.problems(), 0, "{report}");
+        assert!(report.to_string().contains("schema up to date"));
+    }
+}
@@ -0,0 +1,152 @@
+//! One-shot admin commands run from the command line against the database,
+//! e.g. to get back in after being locked out of the web UI.
+//!
+//! Each command returns the text to print.
+
+use sqlx::SqlitePool;
+use std::path::Path;
+
+use crate::auth::{self, session};
+use crate::config::AppConfig;
+use crate::db;
+use crate::doctor;
+use crate::dump;
+use crate::models::user;
+use crate::scanner::{self, ScanMode, ScanStatus};
+use crate::tmdb::TmdbClient;
+
+type CommandResult = Result<String, Box<dyn std::error::Error + Send + Sync>>;
+
+pub async fn scan(pool: &SqlitePool, config: &AppConfig) -> CommandResult {
+    let tmdb = config
+        .tmdb_api_key
+        .as_ref()
+        .map(|key| TmdbClient::new(key.clone()));
+    let summary = scanner::full_scan(
+        pool,
+        &config.media_dirs,
+        tmdb.as_ref(),
+        ScanMode::Full,
+        &ScanStatus::default(),
+    )
+    .await?
+    .unwrap_or_default();
+    Ok(format!(
+        "Scanned {} items: {} added, {} re-activated, {} marked gone",
+        summary.items_seen, summary.added, summary.reactivated, summary.marked_gone
+    ))
+}
+
+pub async fn cleanup(pool: &SqlitePool, config: &AppConfig, dry_run: bool) -> CommandResult {
+    crate::maintenance::run_cleanup(pool, config, dry_run).await;
+    Ok("Cleanup finished".to_string())
+}
+
+/// Undo the `steps` most recent schema migrations.
+pub async fn migrate_down(pool: &SqlitePool, steps: usize) -> CommandResult {
+    let reverted = db::revert_migrations(pool, steps).await?;
+    if reverted.is_empty() {
+        return Ok("No migrations to revert".to_string());
+    }
+    Ok(reverted
+        .iter()
+        .map(|version| format!("Reverted {version}"))
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+/// Revert the most recent migration and apply it again, along with any
+/// that were still pending.
+pub async fn migrate_redo(pool: &SqlitePool) -> CommandResult {
+    let reverted = db::revert_migrations(pool, 1).await?;
+    let applied = db::run_migrations(pool).await?;
+    let lines: Vec<String> = reverted
+        .iter()
+        .map(|version| format!("Reverted {version}"))
+        .chain(applied.iter().map(|version| format!("Applied {version}")))
+        .collect();
+    if lines.is_empty() {
+        return Ok("No migrations to redo".to_string());
+    }
+    Ok(lines.join("\n"))
+}
+
+/// Report where the database and the disk disagree, optionally fixing the database.
+pub async fn doctor(pool: &SqlitePool, config: &AppConfig, repair: bool) -> CommandResult {
+    let mut report = doctor::diagnose(pool, config).await?;
+    if repair {
+        doctor::repair(pool, &mut report).await?;
+    }
+    Ok(report.to_string())
+}
+
+pub async fn export(pool: &SqlitePool, out: &Path) -> CommandResult {
+    let dump = dump::export(pool).await?;
+    let json = serde_json::to_string_pretty(&dump)?;
+    std::fs::write(out, json).map_err(|e| format!("failed to write {}: {e}", out.display()))?;
+    Ok(format!(
+        "Exported {} users, {} media items, {} marks and {} persisted items to {}",
+        dump.users.len(),
+        dump.media.len(),
+        dump.marks.len(),
+        dump.persistent.len(),
+        out.display()
+    ))
+}
+
+pub async fn import(pool: &SqlitePool, file: &Path, replace: bool) -> CommandResult {
+    let json = std::fs::read_to_string(file)
+        .map_err(|e| format!("failed to read {}: {e}", file.display()))?;
+    let dump: dump::Dump =
+        serde_json::from_str(&json).map_err(|e| format!("invalid dump {}: {e}", file.display()))?;
+    dump::import(pool, &dump, replace).await?;
+    Ok(format!(
+        "Imported {} users, {} media items, {} marks and {} persisted items",
+        dump.users.len(),
+        dump.media.len(),
+        dump.marks.len(),
+        dump.persistent.len()
+    ))
+}
+
+/// Create a user who sets their password through an invite link.
+pub async fn user_add(pool: &SqlitePool, username: &str, is_admin: bool) -> CommandResult {
+    if user::get_by_username(pool, username).await?.is_some() {
+        return Err(format!("user '{username}' already exists").into());
+    }
+    let token = session::generate_token();
+    user::create(pool, username, is_admin, Some(&token)).await?;
+    Ok(format!(
+        "Created user '{username}'. Invite link: {}",
+        crate::routes::url(&format!("/invite/{token}"))
+    ))
+}
+
+/// Set a new random password and log the user out of all sessions.
+pub async fn user_reset_password(pool: &SqlitePool, username: &str) -> CommandResult {
+    let user = find_user(pool, username).await?;
+    let password = session::generate_token();
+    user::set_password(pool, user.id, &auth::hash_password(&password)?).await?;
+    session::delete_for_user(pool, user.id).await?;
+    Ok(format!(
+        "New password for '{username}': {password}\nPlease change it after logging in"
+    ))
+}
+
+pub async fn user_promote(pool: &SqlitePool, username: &str) -> CommandResult {
+    let user = find_user(pool, username).await?;
+    if user.is_admin {
+        return Ok(format!("'{username}' is already an admin"));
+    }
+    user::set_admin(pool, user.id, true).await?;
+    Ok(format!("'{username}' is now an admin"))
+}
+
+async fn find_user(
+    pool: &SqlitePool,
+    username: &str,
+) -> Result<user::User, Box<dyn std::error::Error + Send + Sync>> {
+    user::get_by_username(pool, username)
+        .await?
+        .ok_or_else(|| format!("no user named '{username}'").into())
+}
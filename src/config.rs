@@ -1,28 +1,303 @@
 use serde::Deserialize;
+use std::collections::BTreeMap;
 use std::ffi::OsString;
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::sync::watch;
+
+use crate::arr::{ArrConfig, OverseerrConfig};
+use crate::auth::client_ip::IpRange;
+use crate::mediaserver::MediaServerConfig;
+use crate::policies::TagPolicy;
+use crate::rclone::RcloneConfig;
+use crate::rules::Rule;
+use crate::s3::CloudArchiveConfig;
+use crate::torrent::TorrentClientConfig;
+use crate::trash::queue::TrashWindow;
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct AppConfig {
     pub database_url: String,
-    pub listen_addr: String,
+    pub listen_addr: ListenAddrs,
     pub media_dirs: Vec<PathBuf>,
     #[serde(default = "default_grace_period")]
     pub grace_period_days: u64,
+    /// Days a user's keep vote holds off trashing an item everyone else marked.
+    #[serde(default = "default_keep_vote_days")]
+    pub keep_vote_days: u64,
+    /// Leave expired trash with hardlinked files for an admin to delete by
+    /// hand instead of deleting it with a warning.
+    #[serde(default)]
+    pub keep_hardlinked_trash: bool,
+    /// Permanent deletions of trash larger than this many GB, in the
+    /// configured `size_units`, wait until two admins approve them.
+    pub deletion_approval_gb: Option<u64>,
+    /// Days before a trashed item is deleted to warn the users who did not
+    /// mark it, so they can still ask for a rescue.
+    pub expiry_warning_days: Option<u64>,
+    /// Expired trash is moved here, e.g. onto a slow external disk, instead
+    /// of being deleted.
+    pub archive_dir: Option<PathBuf>,
+    /// Every permanent deletion is appended to a monthly JSON Lines file here.
+    pub deletion_manifest_dir: Option<PathBuf>,
+    /// Items everyone marked wait for an admin to confirm before they are
+    /// moved to the trash.
+    #[serde(default)]
+    pub trash_approval: bool,
+    /// An admin has to mark an item too before the users' quorum trashes it.
+    #[serde(default)]
+    pub require_admin_mark: bool,
+    /// Off-peak local time, e.g. "01:00-05:00", in which trash moves run;
+    /// unset moves items right after the final mark.
+    pub trash_window: Option<TrashWindow>,
+    /// Queued trash moves carried out per minute inside the window.
+    #[serde(default = "default_trash_batch_size")]
+    pub trash_batch_size: usize,
+    /// Pause scans, watcher handling, trashing and cleanup, e.g. while the
+    /// disks underneath are being reorganized.
+    #[serde(default)]
+    pub maintenance: bool,
+    /// What a rescue does when something new occupies the original path.
+    #[serde(default)]
+    pub rescue_conflict: RescueConflict,
     #[serde(default = "default_cleanup_interval")]
     pub cleanup_interval_hours: u64,
     pub initial_admin_user: Option<String>,
     pub tmdb_api_key: Option<String>,
+    #[serde(default = "default_watch_debounce")]
+    pub watch_debounce_secs: u64,
+    /// Media dirs watched by polling instead of inotify (e.g. NFS/SMB mounts).
+    #[serde(default)]
+    pub poll_dirs: Vec<PathBuf>,
+    #[serde(default = "default_poll_interval")]
+    pub poll_interval_secs: u64,
+    /// Regex for season folder names the built-in rules miss; group 1 is the number.
+    pub season_pattern: Option<String>,
+    /// Regexes for files and folders left out of item sizes, e.g. samples,
+    /// trailers and disc backups; matched against paths inside the item.
+    #[serde(default)]
+    pub size_exclude: Vec<String>,
+    /// What scans make of "Specials" or "Season 0" folders in a show.
+    #[serde(default)]
+    pub specials: SpecialsPolicy,
+    /// URL prefix when served from a subpath behind a reverse proxy, e.g. "/rewinder".
+    #[serde(default)]
+    pub base_path: String,
+    /// Reverse proxies whose X-Forwarded-For/X-Real-IP headers are believed.
+    #[serde(default)]
+    pub trusted_proxies: Vec<IpRange>,
+    /// Log method, path, status, latency and user of every request.
+    #[serde(default)]
+    pub log_requests: bool,
+    /// OTLP/HTTP endpoint for trace export, e.g. "http://tempo:4318/v1/traces".
+    /// Needs a build with the `otel` feature.
+    pub otlp_endpoint: Option<String>,
+    #[serde(default)]
+    pub log_format: LogFormat,
+    /// Upper bound on open SQLite connections.
+    #[serde(default = "default_db_max_connections")]
+    pub db_max_connections: u32,
+    /// How long a write waits for a lock held by another connection.
+    #[serde(default = "default_db_busy_timeout_ms")]
+    pub db_busy_timeout_ms: u64,
+    /// Use write-ahead logging so readers do not block writers. Disable for
+    /// databases on network filesystems, where WAL is unsupported.
+    #[serde(default = "default_db_wal")]
+    pub db_wal: bool,
+    /// Rebuild the database file with VACUUM this often; 0 disables it.
+    #[serde(default)]
+    pub db_vacuum_interval_days: u64,
+    /// Forget deleted media this many days after it disappeared; 0 keeps it forever.
+    #[serde(default)]
+    pub deleted_retention_days: u64,
+    #[serde(default)]
+    pub size_units: SizeUnits,
+    /// Secret for the read-only guest link `/guest/<token>`; unset disables it.
+    pub guest_token: Option<String>,
+    /// Bearer token Prometheus scrapes `/metrics` with; unset disables it.
+    pub metrics_token: Option<String>,
+    /// State-changing requests (marks, persists, admin actions) allowed per
+    /// minute for each client IP and each session; 0 disables the limit.
+    #[serde(default = "default_rate_limit")]
+    pub rate_limit_per_minute: u32,
+    /// Login and invite form submissions allowed per minute for each client IP;
+    /// 0 disables the limit.
+    #[serde(default = "default_login_rate_limit")]
+    pub login_rate_limit_per_minute: u32,
+    /// Seconds a client may take to send a request body before it is refused.
+    #[serde(default = "default_request_timeout")]
+    pub request_timeout_secs: u64,
+    /// Largest request body accepted, in KiB.
+    #[serde(default = "default_max_body_kb")]
+    pub max_body_kb: u64,
+    /// Applied to the active library by every cleanup run, see [`crate::rules`].
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+    /// Per-tag retention policies, see [`crate::policies`].
+    #[serde(default)]
+    pub tag_policies: Vec<TagPolicy>,
+    /// Plex or Jellyfin server asked before trashing, see [`crate::mediaserver`].
+    pub media_server: Option<MediaServerConfig>,
+    /// qBittorrent or Transmission client asked before trashing, see [`crate::torrent`].
+    pub torrent_client: Option<TorrentClientConfig>,
+    /// S3-compatible bucket that expired trash is uploaded to before it is
+    /// deleted, see [`crate::trash::cloud`].
+    pub cloud_archive: Option<CloudArchiveConfig>,
+    /// rclone remote used in place of `cloud_archive`, see [`crate::rclone`].
+    pub rclone_archive: Option<RcloneConfig>,
+    /// Overseerr that re-requests of deleted items are forwarded to, see [`crate::arr`].
+    pub overseerr: Option<OverseerrConfig>,
+    /// Radarr that re-requested movies go to when Overseerr is not set.
+    pub radarr: Option<ArrConfig>,
+    /// Sonarr that re-requested seasons go to when Overseerr is not set.
+    pub sonarr: Option<ArrConfig>,
+}
+
+/// How log lines are written to stdout.
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Compact human-readable lines.
+    #[default]
+    Text,
+    /// One JSON object per line with event fields at the top level.
+    Json,
+}
+
+/// What a rescue does when the item's original path is taken, e.g. by a new
+/// download of the same release.
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RescueConflict {
+    /// Restore next to it as "<name> (restored)" and track the item there.
+    #[default]
+    Sibling,
+    /// Refuse the rescue and leave the item in the trash.
+    Fail,
+}
+
+/// Where the web server listens: one address, or a list served by the same
+/// router, e.g. `["[::]:3000", "192.168.1.5:3000"]`.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+#[serde(from = "OneOrMany")]
+pub struct ListenAddrs(Vec<String>);
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OneOrMany {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl From<OneOrMany> for ListenAddrs {
+    fn from(value: OneOrMany) -> Self {
+        match value {
+            OneOrMany::One(addr) => Self(vec![addr]),
+            OneOrMany::Many(addrs) => Self(addrs),
+        }
+    }
+}
+
+impl From<&str> for ListenAddrs {
+    fn from(addr: &str) -> Self {
+        Self(vec![addr.to_string()])
+    }
+}
+
+impl ListenAddrs {
+    pub fn addrs(&self) -> &[String] {
+        &self.0
+    }
+}
+
+/// How a show's specials ("Specials" or "Season 0" folders) are tracked. A
+/// folder holding nothing but specials is taken for a movie either way.
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SpecialsPolicy {
+    /// Their own season 0, marked and trashed like any other season.
+    #[default]
+    Season,
+    /// Tracked, but not marked on their own: they go to the trash with the
+    /// show's last season.
+    Fold,
+    /// Not tracked; they stay on disk whatever happens to the show.
+    Ignore,
+}
+
+/// How sizes are shown on pages.
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SizeUnits {
+    /// Powers of 1024, labelled MB/GB/TB like most file managers do.
+    #[default]
+    Binary,
+    /// Powers of 1000, as on drive labels.
+    Decimal,
+}
+
+impl SizeUnits {
+    /// Bytes in what pages label "1 GB".
+    pub fn gigabyte(self) -> i64 {
+        match self {
+            SizeUnits::Binary => 1 << 30,
+            SizeUnits::Decimal => 1_000_000_000,
+        }
+    }
 }
 
 fn default_grace_period() -> u64 {
     7
 }
 
+fn default_keep_vote_days() -> u64 {
+    30
+}
+
+fn default_trash_batch_size() -> usize {
+    10
+}
+
 fn default_cleanup_interval() -> u64 {
     1
 }
 
+fn default_watch_debounce() -> u64 {
+    2
+}
+
+fn default_poll_interval() -> u64 {
+    60
+}
+
+fn default_db_max_connections() -> u32 {
+    5
+}
+
+fn default_db_busy_timeout_ms() -> u64 {
+    5000
+}
+
+fn default_db_wal() -> bool {
+    true
+}
+
+fn default_rate_limit() -> u32 {
+    120
+}
+
+fn default_login_rate_limit() -> u32 {
+    10
+}
+
+fn default_request_timeout() -> u64 {
+    30
+}
+
+fn default_max_body_kb() -> u64 {
+    256
+}
+
 impl AppConfig {
     pub fn trash_dir_for_media_dir(media_dir: &std::path::Path) -> Option<PathBuf> {
         let parent = media_dir.parent()?;
@@ -34,11 +309,7 @@ impl AppConfig {
 
     pub fn trash_dir_for_media_path(&self, media_path: &std::path::Path) -> Option<PathBuf> {
         // Pick the most specific matching media dir in case of nested paths.
-        let best_match = self
-            .media_dirs
-            .iter()
-            .filter(|dir| media_path.starts_with(dir))
-            .max_by_key(|dir| dir.components().count())?;
+        let best_match = crate::paths::library_of(self, media_path)?;
         Self::trash_dir_for_media_dir(best_match)
     }
 
@@ -62,11 +333,7 @@ impl AppConfig {
     }
 
     pub fn permanent_dir_for_media_path(&self, media_path: &std::path::Path) -> Option<PathBuf> {
-        let best_match = self
-            .media_dirs
-            .iter()
-            .filter(|dir| media_path.starts_with(dir))
-            .max_by_key(|dir| dir.components().count())?;
+        let best_match = crate::paths::library_of(self, media_path)?;
         Self::permanent_dir_for_media_dir(best_match)
     }
 
@@ -84,7 +351,16 @@ impl AppConfig {
     pub fn load(path: &str) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
         let content = std::fs::read_to_string(path)
             .map_err(|e| format!("failed to read config file '{path}': {e}"))?;
-        let config: AppConfig = toml::from_str(&content)?;
+        let mut config: AppConfig = toml::from_str(&content)?;
+
+        // Stored paths use the resolved form too; see `paths::canonicalize_stored`.
+        for dir in config
+            .media_dirs
+            .iter_mut()
+            .chain(config.poll_dirs.iter_mut())
+        {
+            *dir = crate::paths::canonical(dir);
+        }
 
         // Validate each media_dir can produce a sibling trash directory name.
         for media_dir in &config.media_dirs {
@@ -104,6 +380,499 @@ impl AppConfig {
             }
         }
 
+        for poll_dir in &config.poll_dirs {
+            if !config.media_dirs.contains(poll_dir) {
+                return Err(format!("poll_dir {:?} is not listed in media_dirs", poll_dir).into());
+            }
+        }
+
+        if let Some(pattern) = &config.season_pattern {
+            let re = regex::Regex::new(pattern)
+                .map_err(|e| format!("invalid season_pattern {pattern:?}: {e}"))?;
+            if re.captures_len() < 2 {
+                return Err(format!(
+                    "season_pattern {pattern:?} needs a capture group for the season number"
+                )
+                .into());
+            }
+        }
+
+        if let Err(e) = regex::RegexSet::new(&config.size_exclude) {
+            return Err(format!("invalid size_exclude pattern: {e}").into());
+        }
+
+        if config.request_timeout_secs == 0 {
+            return Err("request_timeout_secs must be at least 1".into());
+        }
+        if config.max_body_kb == 0 {
+            return Err("max_body_kb must be at least 1".into());
+        }
+
+        if config.listen_addr.addrs().is_empty() {
+            return Err("listen_addr needs at least one address".into());
+        }
+
+        if config.db_max_connections == 0 {
+            return Err("db_max_connections must be at least 1".into());
+        }
+
+        if let Some(token) = &config.guest_token {
+            let valid = token.len() >= 16
+                && token
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'));
+            if !valid {
+                return Err("guest_token must be at least 16 letters, digits, '-' or '_'".into());
+            }
+        }
+        if config
+            .metrics_token
+            .as_deref()
+            .is_some_and(|token| token.trim().len() < 16)
+        {
+            return Err("metrics_token must be at least 16 characters".into());
+        }
+
+        for (i, rule) in config.rules.iter().enumerate() {
+            rule.validate()
+                .map_err(|e| format!("rule {:?}: {e}", rule.name))?;
+            if config.rules[..i].iter().any(|r| r.name == rule.name) {
+                return Err(format!("rule name {:?} is used twice", rule.name).into());
+            }
+        }
+        for (i, policy) in config.tag_policies.iter().enumerate() {
+            policy
+                .validate()
+                .map_err(|e| format!("tag policy {:?}: {e}", policy.tag))?;
+            if config.tag_policies[..i]
+                .iter()
+                .any(|p| p.tag.eq_ignore_ascii_case(&policy.tag))
+            {
+                return Err(format!("tag {:?} has two policies", policy.tag).into());
+            }
+        }
+
+        if let Some(server) = &config.media_server {
+            server
+                .validate()
+                .map_err(|e| format!("media_server: {e}"))?;
+        }
+        if let Some(client) = &config.torrent_client {
+            client
+                .validate()
+                .map_err(|e| format!("torrent_client: {e}"))?;
+        }
+        if let Some(cloud) = &config.cloud_archive {
+            cloud
+                .validate()
+                .map_err(|e| format!("cloud_archive: {e}"))?;
+        }
+        if let Some(rclone) = &config.rclone_archive {
+            if config.cloud_archive.is_some() {
+                return Err("set either cloud_archive or rclone_archive, not both".into());
+            }
+            rclone
+                .validate()
+                .map_err(|e| format!("rclone_archive: {e}"))?;
+        }
+        if let Some(overseerr) = &config.overseerr {
+            overseerr
+                .validate()
+                .map_err(|e| format!("overseerr: {e}"))?;
+        }
+        if let Some(radarr) = &config.radarr {
+            radarr.validate().map_err(|e| format!("radarr: {e}"))?;
+        }
+        if let Some(sonarr) = &config.sonarr {
+            sonarr.validate().map_err(|e| format!("sonarr: {e}"))?;
+        }
+
+        config.base_path = config.base_path.trim_end_matches('/').to_string();
+        if !config.base_path.is_empty() {
+            let valid = config.base_path.starts_with('/')
+                && config
+                    .base_path
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '/' | '-' | '_' | '.'));
+            if !valid {
+                return Err(format!(
+                    "base_path {:?} must start with '/' and contain only letters, digits, '-', '_', '.' and '/'",
+                    config.base_path
+                )
+                .into());
+            }
+        }
+
         Ok(config)
     }
 }
+
+/// What the live configuration is built from.
+struct Layers {
+    /// The config file as last loaded.
+    file: Arc<AppConfig>,
+    /// Settings saved in the database, laid over the file by [`crate::settings`].
+    overrides: BTreeMap<String, String>,
+}
+
+/// The live configuration, replaced in place by [`SharedConfig::reload`].
+///
+/// Clones are cheap and all of them see reloads. Long-running tasks can
+/// [`subscribe`](SharedConfig::subscribe) to be woken when the config changes.
+#[derive(Clone)]
+pub struct SharedConfig {
+    path: Option<Arc<PathBuf>>,
+    layers: Arc<Mutex<Layers>>,
+    tx: Arc<watch::Sender<Arc<AppConfig>>>,
+}
+
+impl SharedConfig {
+    /// `path` is the file reloads read from; `None` disables reloading.
+    pub fn new(config: AppConfig, path: Option<PathBuf>) -> Self {
+        let config = Arc::new(config);
+        let (tx, _) = watch::channel(config.clone());
+        Self {
+            path: path.map(Arc::new),
+            layers: Arc::new(Mutex::new(Layers {
+                file: config,
+                overrides: BTreeMap::new(),
+            })),
+            tx: Arc::new(tx),
+        }
+    }
+
+    /// The config as the file has it, without settings saved in the database.
+    pub fn file_config(&self) -> Arc<AppConfig> {
+        self.layers().file.clone()
+    }
+
+    /// Replace the settings saved in the database and publish the result.
+    pub fn set_overrides(&self, overrides: BTreeMap<String, String>) {
+        let mut layers = self.layers();
+        layers.overrides = overrides;
+        self.publish(&layers);
+    }
+
+    fn layers(&self) -> std::sync::MutexGuard<'_, Layers> {
+        self.layers.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn publish(&self, layers: &Layers) {
+        let config = if layers.overrides.is_empty() {
+            layers.file.clone()
+        } else {
+            Arc::new(crate::settings::overlay(&layers.file, &layers.overrides))
+        };
+        self.tx.send_replace(config);
+    }
+
+    pub fn current(&self) -> Arc<AppConfig> {
+        self.tx.borrow().clone()
+    }
+
+    pub fn subscribe(&self) -> watch::Receiver<Arc<AppConfig>> {
+        self.tx.subscribe()
+    }
+
+    /// Re-read and validate the config file, then publish it.
+    ///
+    /// On error the running config stays in place. Returns the names of changed
+    /// settings that only take effect after a restart.
+    pub fn reload(&self) -> Result<Vec<&'static str>, Box<dyn std::error::Error + Send + Sync>> {
+        let path = self
+            .path
+            .as_deref()
+            .ok_or("no config file to reload from")?;
+        let new = AppConfig::load(&path.to_string_lossy())?;
+        crate::storage::validate_storage_access(&new)?;
+
+        let mut layers = self.layers();
+        let needs_restart = layers.file.restart_only_changes(&new);
+        for name in &needs_restart {
+            tracing::warn!("Config setting {name} changed; restart rewinder to apply it");
+        }
+        layers.file = Arc::new(new);
+        self.publish(&layers);
+        tracing::info!("Reloaded config from {}", path.display());
+        Ok(needs_restart)
+    }
+}
+
+impl AppConfig {
+    /// Settings that are only read at startup and that differ in `new`.
+    fn restart_only_changes(&self, new: &AppConfig) -> Vec<&'static str> {
+        let mut changed = Vec::new();
+        if self.database_url != new.database_url {
+            changed.push("database_url");
+        }
+        if self.listen_addr != new.listen_addr {
+            changed.push("listen_addr");
+        }
+        if self.tmdb_api_key != new.tmdb_api_key {
+            changed.push("tmdb_api_key");
+        }
+        if self.season_pattern != new.season_pattern {
+            changed.push("season_pattern");
+        }
+        if self.size_exclude != new.size_exclude {
+            changed.push("size_exclude");
+        }
+        if self.specials != new.specials {
+            changed.push("specials");
+        }
+        if self.base_path != new.base_path {
+            changed.push("base_path");
+        }
+        if self.otlp_endpoint != new.otlp_endpoint {
+            changed.push("otlp_endpoint");
+        }
+        if self.log_format != new.log_format {
+            changed.push("log_format");
+        }
+        if self.size_units != new.size_units {
+            changed.push("size_units");
+        }
+        if self.request_timeout_secs != new.request_timeout_secs {
+            changed.push("request_timeout_secs");
+        }
+        if self.max_body_kb != new.max_body_kb {
+            changed.push("max_body_kb");
+        }
+        if self.db_max_connections != new.db_max_connections {
+            changed.push("db_max_connections");
+        }
+        if self.db_busy_timeout_ms != new.db_busy_timeout_ms {
+            changed.push("db_busy_timeout_ms");
+        }
+        if self.db_wal != new.db_wal {
+            changed.push("db_wal");
+        }
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load_str(content: &str) -> Result<AppConfig, Box<dyn std::error::Error + Send + Sync>> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rewinder.toml");
+        std::fs::write(&path, content).unwrap();
+        AppConfig::load(path.to_str().unwrap())
+    }
+
+    #[test]
+    fn poll_dirs_default_to_empty() {
+        let config = load_str(
+            r#"
+            database_url = "sqlite::memory:"
+            listen_addr = "127.0.0.1:0"
+            media_dirs = ["/media/Movies"]
+            "#,
+        )
+        .unwrap();
+        assert!(config.poll_dirs.is_empty());
+        assert_eq!(config.poll_interval_secs, 60);
+    }
+
+    #[test]
+    fn poll_dir_must_be_a_media_dir() {
+        let err = load_str(
+            r#"
+            database_url = "sqlite::memory:"
+            listen_addr = "127.0.0.1:0"
+            media_dirs = ["/media/Movies"]
+            poll_dirs = ["/media/TV Shows"]
+            "#,
+        )
+        .expect_err("expected unknown poll_dir to be rejected");
+        assert!(err.to_string().contains("not listed in media_dirs"));
+    }
+
+    #[test]
+    fn base_path_is_normalized_and_validated() {
+        let with_base = |base: &str| {
+            load_str(&format!(
+                r#"
+                database_url = "sqlite::memory:"
+                listen_addr = "127.0.0.1:0"
+                media_dirs = ["/media/Movies"]
+                base_path = "{base}"
+                "#
+            ))
+        };
+        assert_eq!(with_base("/rewinder/").unwrap().base_path, "/rewinder");
+        assert_eq!(with_base("/").unwrap().base_path, "");
+        assert!(with_base("rewinder").is_err());
+        assert!(with_base("/re winder").is_err());
+    }
+
+    #[test]
+    fn guest_token_must_be_long_and_url_safe() {
+        let with_token = |token: &str| {
+            load_str(&format!(
+                r#"
+                database_url = "sqlite::memory:"
+                listen_addr = "127.0.0.1:0"
+                media_dirs = ["/media/Movies"]
+                guest_token = "{token}"
+                "#
+            ))
+        };
+        assert!(with_token("family-movie-night-2024").is_ok());
+        assert!(with_token("short").is_err());
+        assert!(with_token("has/a/slash/in/it/too").is_err());
+    }
+
+    #[test]
+    fn request_limits_must_be_positive() {
+        let with = |setting: &str| {
+            load_str(&format!(
+                r#"
+                database_url = "sqlite::memory:"
+                listen_addr = "127.0.0.1:0"
+                media_dirs = ["/media/Movies"]
+                {setting}
+                "#
+            ))
+        };
+        assert!(with("max_body_kb = 1").is_ok());
+        let err = with("max_body_kb = 0").expect_err("expected an empty body limit to be rejected");
+        assert!(err.to_string().contains("max_body_kb"));
+        assert!(with("request_timeout_secs = 0").is_err());
+    }
+
+    #[test]
+    fn season_pattern_needs_a_capture_group() {
+        let err = load_str(
+            r#"
+            database_url = "sqlite::memory:"
+            listen_addr = "127.0.0.1:0"
+            media_dirs = ["/media/TV Shows"]
+            season_pattern = "^Volume \\d+$"
+            "#,
+        )
+        .expect_err("expected pattern without a group to be rejected");
+        assert!(err.to_string().contains("capture group"));
+    }
+
+    #[test]
+    fn size_exclude_patterns_must_compile() {
+        let err = load_str(
+            r#"
+            database_url = "sqlite::memory:"
+            listen_addr = "127.0.0.1:0"
+            media_dirs = ["/media/Movies"]
+            size_exclude = ["(?i)sample(", "BDMV/BACKUP"]
+            "#,
+        )
+        .expect_err("expected an unbalanced pattern to be rejected");
+        assert!(err.to_string().contains("size_exclude"));
+    }
+
+    #[test]
+    fn listen_addr_takes_one_address_or_a_list() {
+        let config = load_str(
+            r#"
+            database_url = "sqlite::memory:"
+            listen_addr = ["[::]:3000", "192.168.1.5:3000"]
+            media_dirs = ["/media/Movies"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.listen_addr.addrs(),
+            ["[::]:3000", "192.168.1.5:3000"]
+        );
+
+        let err = load_str(
+            r#"
+            database_url = "sqlite::memory:"
+            listen_addr = []
+            media_dirs = ["/media/Movies"]
+            "#,
+        )
+        .expect_err("expected an empty list to be rejected");
+        assert!(err.to_string().contains("listen_addr"));
+    }
+
+    const BASE: &str = r#"
+        database_url = "sqlite::memory:"
+        listen_addr = "127.0.0.1:0"
+    "#;
+
+    #[test]
+    fn reload_publishes_new_config_and_reports_restart_only_settings() {
+        let base = tempfile::tempdir().unwrap();
+        let movies = base.path().join("Movies");
+        std::fs::create_dir(&movies).unwrap();
+        let path = base.path().join("rewinder.toml");
+        let write = |extra: &str| {
+            let content = format!("{BASE}\nmedia_dirs = [{movies:?}]\n{extra}");
+            std::fs::write(&path, content).unwrap();
+        };
+        write("grace_period_days = 7");
+        let shared = SharedConfig::new(
+            AppConfig::load(path.to_str().unwrap()).unwrap(),
+            Some(path.clone()),
+        );
+        let rx = shared.subscribe();
+
+        write("grace_period_days = 3\nsize_units = \"decimal\"");
+        let needs_restart = shared.reload().unwrap();
+        assert_eq!(needs_restart, vec!["size_units"]);
+        assert_eq!(shared.current().grace_period_days, 3);
+        assert!(rx.has_changed().unwrap());
+    }
+
+    #[test]
+    fn reload_keeps_settings_saved_in_the_database() {
+        let base = tempfile::tempdir().unwrap();
+        let movies = base.path().join("Movies");
+        std::fs::create_dir(&movies).unwrap();
+        let path = base.path().join("rewinder.toml");
+        let write = |grace: u64| {
+            let content = format!(
+                "{BASE}\nmedia_dirs = [{movies:?}]\ngrace_period_days = {grace}\ndeleted_retention_days = 30"
+            );
+            std::fs::write(&path, content).unwrap();
+        };
+        write(7);
+        let shared = SharedConfig::new(
+            AppConfig::load(path.to_str().unwrap()).unwrap(),
+            Some(path.clone()),
+        );
+        shared.set_overrides(BTreeMap::from([(
+            "grace_period_days".to_string(),
+            "2".to_string(),
+        )]));
+
+        write(9);
+        shared.reload().unwrap();
+        assert_eq!(shared.current().grace_period_days, 2);
+        assert_eq!(shared.current().deleted_retention_days, 30);
+        assert_eq!(shared.file_config().grace_period_days, 9);
+    }
+
+    #[test]
+    fn failed_reload_keeps_running_config() {
+        let base = tempfile::tempdir().unwrap();
+        let movies = base.path().join("Movies");
+        std::fs::create_dir(&movies).unwrap();
+        let path = base.path().join("rewinder.toml");
+        std::fs::write(&path, format!("{BASE}\nmedia_dirs = [{movies:?}]")).unwrap();
+        let shared = SharedConfig::new(
+            AppConfig::load(path.to_str().unwrap()).unwrap(),
+            Some(path.clone()),
+        );
+
+        let missing = base.path().join("Missing");
+        std::fs::write(&path, format!("{BASE}\nmedia_dirs = [{missing:?}]")).unwrap();
+        assert!(shared.reload().is_err());
+        assert_eq!(shared.current().media_dirs, vec![movies]);
+
+        let detached = SharedConfig::new(shared.current().as_ref().clone(), None);
+        assert!(detached.reload().is_err());
+    }
+}
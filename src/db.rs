@@ -1,20 +1,98 @@
-use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteSynchronous};
 use sqlx::SqlitePool;
 use std::str::FromStr;
+use std::time::Duration;
 
-const MIGRATIONS: [(&str, &str); 3] = [
-    ("001_initial", include_str!("../migrations/001_initial.sql")),
-    (
-        "002_add_permanent_media",
-        include_str!("../migrations/002_add_permanent_media.sql"),
-    ),
-    (
-        "003_poster_path",
-        include_str!("../migrations/003_poster_path.sql"),
-    ),
+use crate::config::AppConfig;
+
+/// Connection pool and pragma settings, taken from the `db_*` config options.
+#[derive(Debug, Clone, Copy)]
+pub struct PoolSettings {
+    pub max_connections: u32,
+    pub busy_timeout: Duration,
+    pub wal: bool,
+}
+
+impl Default for PoolSettings {
+    fn default() -> Self {
+        Self {
+            max_connections: 5,
+            busy_timeout: Duration::from_secs(5),
+            wal: true,
+        }
+    }
+}
+
+impl From<&AppConfig> for PoolSettings {
+    fn from(config: &AppConfig) -> Self {
+        Self {
+            max_connections: config.db_max_connections,
+            busy_timeout: Duration::from_millis(config.db_busy_timeout_ms),
+            wal: config.db_wal,
+        }
+    }
+}
+
+/// A schema change and the script that undoes it.
+struct Migration {
+    version: &'static str,
+    up: &'static str,
+    down: &'static str,
+}
+
+/// Every migration needs a `<version>.down.sql` next to `<version>.sql`.
+macro_rules! migration {
+    ($version:literal) => {
+        Migration {
+            version: $version,
+            up: include_str!(concat!("../migrations/", $version, ".sql")),
+            down: include_str!(concat!("../migrations/", $version, ".down.sql")),
+        }
+    };
+}
+
+const MIGRATIONS: [Migration; 38] = [
+    migration!("001_initial"),
+    migration!("002_add_permanent_media"),
+    migration!("003_poster_path"),
+    migration!("004_dir_mtime"),
+    migration!("005_scan_summaries"),
+    migration!("006_session_client_ip"),
+    migration!("007_db_maintenance"),
+    migration!("008_media_history"),
+    migration!("009_user_locale"),
+    migration!("010_user_preferences"),
+    migration!("011_feed_token"),
+    migration!("012_size_pending"),
+    migration!("013_rule_hits"),
+    migration!("014_protected"),
+    migration!("015_media_tags"),
+    migration!("016_policy_log"),
+    migration!("017_trash_name"),
+    migration!("018_trash_path"),
+    migration!("019_manual_media"),
+    migration!("020_deletion_approvals"),
+    migration!("021_settings"),
+    migration!("022_announcements"),
+    migration!("023_library_access"),
+    migration!("024_restricted_users"),
+    migration!("025_pending_trash"),
+    migration!("026_trash_queue"),
+    migration!("027_expiry_warnings"),
+    migration!("028_keep_votes"),
+    migration!("029_archive_path"),
+    migration!("030_cloud_key"),
+    migration!("031_comments"),
+    migration!("032_rerequests"),
+    migration!("033_indexes"),
+    migration!("034_poster_hash"),
+    migration!("035_trash_journal"),
+    migration!("036_persistent_series"),
+    migration!("037_media_editions"),
+    migration!("038_media_path_nocase"),
 ];
 
-pub async fn run_migrations(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+async fn create_migrations_table(pool: &SqlitePool) -> Result<(), sqlx::Error> {
     sqlx::query(
         "CREATE TABLE IF NOT EXISTS schema_migrations (
             version TEXT PRIMARY KEY,
@@ -23,38 +101,301 @@ pub async fn run_migrations(pool: &SqlitePool) -> Result<(), sqlx::Error> {
     )
     .execute(pool)
     .await?;
+    Ok(())
+}
 
-    for (version, sql) in MIGRATIONS {
-        let already_applied: Option<(String,)> =
-            sqlx::query_as("SELECT version FROM schema_migrations WHERE version = ?")
-                .bind(version)
-                .fetch_optional(pool)
-                .await?;
-        if already_applied.is_some() {
+async fn applied_versions(pool: &SqlitePool) -> Result<Vec<String>, sqlx::Error> {
+    let rows: Vec<(String,)> = sqlx::query_as("SELECT version FROM schema_migrations")
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.into_iter().map(|(v,)| v).collect())
+}
+
+/// Apply pending migrations in order. Returns the versions that were applied.
+pub async fn run_migrations(pool: &SqlitePool) -> Result<Vec<&'static str>, sqlx::Error> {
+    create_migrations_table(pool).await?;
+    let applied = applied_versions(pool).await?;
+
+    let mut ran = Vec::new();
+    for migration in &MIGRATIONS {
+        if applied.iter().any(|v| v == migration.version) {
             continue;
         }
 
-        sqlx::raw_sql(sql).execute(pool).await?;
+        sqlx::raw_sql(migration.up).execute(pool).await?;
         sqlx::query("INSERT INTO schema_migrations (version) VALUES (?)")
-            .bind(version)
+            .bind(migration.version)
             .execute(pool)
             .await?;
+        ran.push(migration.version);
     }
 
-    Ok(())
+    Ok(ran)
+}
+
+/// Undo the `steps` most recently applied migrations, newest first. Returns
+/// the versions that were reverted.
+///
+/// Refuses to touch a database migrated by a newer build, since this build
+/// does not have the down scripts for its newer migrations.
+pub async fn revert_migrations(
+    pool: &SqlitePool,
+    steps: usize,
+) -> Result<Vec<&'static str>, Box<dyn std::error::Error + Send + Sync>> {
+    create_migrations_table(pool).await?;
+    let applied = applied_versions(pool).await?;
+    if let Some(unknown) = applied
+        .iter()
+        .find(|v| !MIGRATIONS.iter().any(|m| m.version == v.as_str()))
+    {
+        return Err(format!(
+            "database has migration {unknown}, which this build does not know; \
+             revert it with the build that applied it"
+        )
+        .into());
+    }
+
+    let mut reverted = Vec::new();
+    for migration in MIGRATIONS
+        .iter()
+        .rev()
+        .filter(|m| applied.iter().any(|v| v == m.version))
+        .take(steps)
+    {
+        sqlx::raw_sql(migration.down).execute(pool).await?;
+        sqlx::query("DELETE FROM schema_migrations WHERE version = ?")
+            .bind(migration.version)
+            .execute(pool)
+            .await?;
+        reverted.push(migration.version);
+    }
+
+    Ok(reverted)
 }
 
-pub async fn init_pool(database_url: &str) -> Result<SqlitePool, sqlx::Error> {
+/// Open the database and bring its schema up to date.
+pub async fn init_pool(
+    database_url: &str,
+    settings: &PoolSettings,
+) -> Result<SqlitePool, sqlx::Error> {
+    let pool = open_pool(database_url, settings).await?;
+    run_migrations(&pool).await?;
+    Ok(pool)
+}
+
+/// Open the database without applying migrations, for `rewinder migrate`.
+pub async fn open_pool(
+    database_url: &str,
+    settings: &PoolSettings,
+) -> Result<SqlitePool, sqlx::Error> {
+    // In WAL mode NORMAL only risks the last commits on power loss, never corruption.
+    let (journal_mode, synchronous) = if settings.wal {
+        (SqliteJournalMode::Wal, SqliteSynchronous::Normal)
+    } else {
+        (SqliteJournalMode::Delete, SqliteSynchronous::Full)
+    };
     let options = SqliteConnectOptions::from_str(database_url)?
         .create_if_missing(true)
-        .foreign_keys(true);
+        .foreign_keys(true)
+        .journal_mode(journal_mode)
+        .synchronous(synchronous)
+        .busy_timeout(settings.busy_timeout);
 
     let pool = SqlitePoolOptions::new()
-        .max_connections(5)
+        .max_connections(settings.max_connections)
         .connect_with(options)
         .await?;
 
-    run_migrations(&pool).await?;
-
     Ok(pool)
 }
+
+/// Number of migrations a start would apply, checked without modifying the database.
+///
+/// Returns `None` if the database file does not exist yet.
+pub async fn pending_migrations(database_url: &str) -> Result<Option<usize>, sqlx::Error> {
+    let options = SqliteConnectOptions::from_str(database_url)?;
+    let filename = options.get_filename();
+    if filename != std::path::Path::new(":memory:") && !filename.exists() {
+        return Ok(None);
+    }
+
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect_with(options.read_only(true))
+        .await?;
+    let has_table: (i64,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'schema_migrations'",
+    )
+    .fetch_one(&pool)
+    .await?;
+    let applied: Vec<(String,)> = if has_table.0 > 0 {
+        sqlx::query_as("SELECT version FROM schema_migrations")
+            .fetch_all(&pool)
+            .await?
+    } else {
+        Vec::new()
+    };
+    pool.close().await;
+
+    Ok(Some(
+        MIGRATIONS
+            .iter()
+            .filter(|m| !applied.iter().any(|(v,)| v == m.version))
+            .count(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn pragma(pool: &SqlitePool, name: &str) -> String {
+        let (value,): (String,) =
+            sqlx::query_as(&format!("SELECT '' || (SELECT * FROM pragma_{name})"))
+                .fetch_one(pool)
+                .await
+                .unwrap();
+        value
+    }
+
+    /// Table definitions; SQLite quotes the name of a renamed table, so quotes are dropped.
+    async fn schema(pool: &SqlitePool) -> Vec<(String, String)> {
+        sqlx::query_as(
+            "SELECT name, replace(sql, '\"', '') FROM sqlite_master
+             WHERE sql IS NOT NULL AND name NOT IN ('schema_migrations', 'sqlite_sequence')
+             ORDER BY name",
+        )
+        .fetch_all(pool)
+        .await
+        .unwrap()
+    }
+
+    async fn memory_pool() -> SqlitePool {
+        let options = SqliteConnectOptions::from_str("sqlite::memory:")
+            .unwrap()
+            .foreign_keys(true);
+        SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect_with(options)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn every_migration_reverts_to_the_previous_schema() {
+        let pool = memory_pool().await;
+        let mut schemas = vec![schema(&pool).await];
+        for migration in &MIGRATIONS {
+            sqlx::raw_sql(migration.up).execute(&pool).await.unwrap();
+            schemas.push(schema(&pool).await);
+        }
+        for (i, migration) in MIGRATIONS.iter().enumerate().rev() {
+            sqlx::raw_sql(migration.down).execute(&pool).await.unwrap();
+            assert_eq!(schema(&pool).await, schemas[i], "{}", migration.version);
+        }
+    }
+
+    #[tokio::test]
+    async fn revert_keeps_data_of_older_tables() {
+        let pool = memory_pool().await;
+        run_migrations(&pool).await.unwrap();
+        sqlx::query("INSERT INTO users (username) VALUES ('alice')")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO media (media_type, title, path) VALUES ('movie', 'Heat', '/m/Heat')",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query("INSERT INTO marks (user_id, media_id) VALUES (1, 1)")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let reverted = revert_migrations(&pool, 37).await.unwrap();
+        assert_eq!(reverted.first(), Some(&"038_media_path_nocase"));
+        assert_eq!(reverted.last(), Some(&"002_add_permanent_media"));
+        let (marks,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM marks")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(marks, 1);
+
+        let applied = run_migrations(&pool).await.unwrap();
+        assert_eq!(applied.len(), 37);
+        assert!(run_migrations(&pool).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn hot_queries_use_indexes() {
+        let pool = memory_pool().await;
+        run_migrations(&pool).await.unwrap();
+        for (query, index) in [
+            (
+                "SELECT * FROM media WHERE status IN ('active', 'permanent') AND media_type = 'movie'",
+                "idx_media_status_type",
+            ),
+            (
+                "SELECT * FROM media WHERE trashed_at <= datetime('now', '-30 days')",
+                "idx_media_trashed_at",
+            ),
+            (
+                "SELECT COUNT(*) FROM marks WHERE media_id = 1",
+                "idx_marks_media",
+            ),
+            (
+                "DELETE FROM sessions WHERE expires_at <= datetime('now')",
+                "idx_sessions_expires",
+            ),
+        ] {
+            let plan: Vec<(i64, i64, i64, String)> =
+                sqlx::query_as(&format!("EXPLAIN QUERY PLAN {query}"))
+                    .fetch_all(&pool)
+                    .await
+                    .unwrap();
+            assert!(
+                plan.iter().any(|(_, _, _, detail)| detail.contains(index)),
+                "{query}: {plan:?}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn revert_refuses_unknown_migrations() {
+        let pool = memory_pool().await;
+        run_migrations(&pool).await.unwrap();
+        sqlx::query("INSERT INTO schema_migrations (version) VALUES ('999_future')")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let err = revert_migrations(&pool, 1).await.unwrap_err();
+        assert!(err.to_string().contains("999_future"));
+    }
+
+    #[tokio::test]
+    async fn pool_applies_pragmas_from_settings() {
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("sqlite://{}", dir.path().join("wal.db").display());
+        let settings = PoolSettings {
+            busy_timeout: Duration::from_millis(1234),
+            ..PoolSettings::default()
+        };
+        let pool = init_pool(&url, &settings).await.unwrap();
+        assert_eq!(pragma(&pool, "journal_mode").await, "wal");
+        assert_eq!(pragma(&pool, "synchronous").await, "1");
+        assert_eq!(pragma(&pool, "busy_timeout").await, "1234");
+        pool.close().await;
+
+        let url = format!("sqlite://{}", dir.path().join("delete.db").display());
+        let settings = PoolSettings {
+            wal: false,
+            ..PoolSettings::default()
+        };
+        let pool = init_pool(&url, &settings).await.unwrap();
+        assert_eq!(pragma(&pool, "journal_mode").await, "delete");
+        assert_eq!(pragma(&pool, "synchronous").await, "2");
+    }
+}
@@ -0,0 +1,289 @@
+//! `rewinder doctor`: cross-check the database against what is actually on disk.
+//!
+//! Every problem found is reported with the fix `--repair` would apply. Fixes
+//! only ever update the database to match the disk; files are never moved or
+//! deleted, so anything that would need that is left to be resolved by hand.
+
+use sqlx::SqlitePool;
+use std::collections::HashSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::config::AppConfig;
+use crate::models::{mark, media, media_history, persistent};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fix {
+    /// The item is nowhere on disk: mark it gone and drop its marks.
+    MarkGone,
+    /// The item is back at its original path: make it active again.
+    Reactivate,
+    /// The item sits in the trash but the database still lists it as active.
+    MarkTrashed,
+}
+
+impl fmt::Display for Fix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Fix::MarkGone => write!(f, "mark it gone"),
+            Fix::Reactivate => write!(f, "mark it active"),
+            Fix::MarkTrashed => write!(f, "mark it trashed (restarts its grace period)"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Finding {
+    pub media_id: Option<i64>,
+    pub path: PathBuf,
+    pub problem: String,
+    /// `None` when there is no safe automatic fix.
+    pub fix: Option<Fix>,
+}
+
+#[derive(Debug, Default)]
+pub struct DoctorReport {
+    pub findings: Vec<Finding>,
+    pub repaired: usize,
+}
+
+impl DoctorReport {
+    fn push(
+        &mut self,
+        media_id: Option<i64>,
+        path: &Path,
+        problem: impl Into<String>,
+        fix: Option<Fix>,
+    ) {
+        self.findings.push(Finding {
+            media_id,
+            path: path.to_path_buf(),
+            problem: problem.into(),
+            fix,
+        });
+    }
+
+    pub fn fixable(&self) -> usize {
+        self.findings.iter().filter(|f| f.fix.is_some()).count()
+    }
+}
+
+impl fmt::Display for DoctorReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for finding in &self.findings {
+            writeln!(f, "{}: {}", finding.path.display(), finding.problem)?;
+            match finding.fix {
+                Some(fix) => writeln!(f, "  fix: {fix}")?,
+                None => writeln!(f, "  fix: none, resolve by hand")?,
+            }
+        }
+        match (self.findings.len(), self.repaired) {
+            (0, _) => write!(f, "No problems found"),
+            (n, 0) => write!(
+                f,
+                "{n} problems found, {} can be fixed with --repair",
+                self.fixable()
+            ),
+            (n, repaired) => write!(f, "{n} problems found, {repaired} repaired"),
+        }
+    }
+}
+
+/// Where `path` would live after being moved into `target_root`, the
+/// permanent dir of `media_dir`.
+fn relocated(media_dir: &Path, target_root: Option<PathBuf>, path: &Path) -> Option<PathBuf> {
+    Some(target_root?.join(crate::paths::relative_to(media_dir, path)?))
+}
+
+pub async fn diagnose(pool: &SqlitePool, config: &AppConfig) -> Result<DoctorReport, sqlx::Error> {
+    let mut report = DoctorReport::default();
+    let mut known_trash = HashSet::new();
+    let mut known_permanent = HashSet::new();
+
+    for dir in &config.media_dirs {
+        if !dir.is_dir() {
+            report.push(
+                None,
+                dir,
+                "media dir is not reachable; items under it were not checked",
+                None,
+            );
+        }
+    }
+
+    for item in media::list_tracked(pool).await? {
+        let path = Path::new(&item.path);
+        let media_dir = crate::paths::library_of(config, path);
+        // Trashed and archived items with a recorded path do not need their media dir.
+        if media_dir.is_none() && item.trash_path.is_none() && item.archive_path.is_none() {
+            report.push(
+                Some(item.id),
+                path,
+                format!("{} item is not under any configured media dir", item.status),
+                None,
+            );
+            continue;
+        }
+        if media_dir.is_some_and(|dir| !dir.is_dir()) {
+            continue;
+        }
+        let trash_path = crate::trash::trash_location_for(config, &item);
+        let permanent_path = media_dir
+            .and_then(|dir| relocated(dir, AppConfig::permanent_dir_for_media_dir(dir), path));
+        let in_trash = trash_path.as_ref().is_some_and(|p| p.exists());
+        let in_permanent = permanent_path.as_ref().is_some_and(|p| p.exists());
+        let at_original = path.exists();
+
+        match item.status.as_str() {
+            "active" if !at_original => {
+                // Already reported here, so keep it out of the orphan list.
+                if in_trash {
+                    known_trash.extend(trash_path);
+                    report.push(
+                        Some(item.id),
+                        path,
+                        "active item was found in the trash",
+                        Some(Fix::MarkTrashed),
+                    );
+                } else if in_permanent {
+                    known_permanent.extend(permanent_path);
+                    report.push(
+                        Some(item.id),
+                        path,
+                        "active item was found in the permanent dir, but has no owner",
+                        None,
+                    );
+                } else {
+                    report.push(
+                        Some(item.id),
+                        path,
+                        "active item is missing from disk",
+                        Some(Fix::MarkGone),
+                    );
+                }
+            }
+            "trashed" => {
+                if let Some(p) = trash_path.filter(|_| in_trash) {
+                    known_trash.insert(p);
+                } else if at_original {
+                    report.push(
+                        Some(item.id),
+                        path,
+                        "trashed item is back at its original path",
+                        Some(Fix::Reactivate),
+                    );
+                } else {
+                    report.push(
+                        Some(item.id),
+                        path,
+                        "trashed item is missing from the trash",
+                        Some(Fix::MarkGone),
+                    );
+                }
+            }
+            "permanent" => {
+                if let Some(p) = permanent_path.filter(|_| in_permanent) {
+                    known_permanent.insert(p);
+                } else if at_original {
+                    report.push(
+                        Some(item.id),
+                        path,
+                        "persisted item is back at its original path",
+                        Some(Fix::Reactivate),
+                    );
+                } else {
+                    report.push(
+                        Some(item.id),
+                        path,
+                        "persisted item is missing from the permanent dir",
+                        None,
+                    );
+                }
+            }
+            "archived" => {
+                if item
+                    .archive_path
+                    .as_ref()
+                    .is_some_and(|p| Path::new(p).exists())
+                {
+                    continue;
+                }
+                if at_original {
+                    report.push(
+                        Some(item.id),
+                        path,
+                        "archived item is back at its original path",
+                        Some(Fix::Reactivate),
+                    );
+                } else {
+                    report.push(
+                        Some(item.id),
+                        path,
+                        "archived item is missing from the archive",
+                        Some(Fix::MarkGone),
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for dir in config.all_trash_dirs() {
+        for orphan in find_orphans(&dir, &known_trash) {
+            report.push(None, &orphan, "not a known trashed item", None);
+        }
+    }
+    for dir in config.all_permanent_dirs() {
+        for orphan in find_orphans(&dir, &known_permanent) {
+            report.push(None, &orphan, "not a known persisted item", None);
+        }
+    }
+
+    Ok(report)
+}
+
+/// Entries under `root` that are neither a known item nor a folder leading to one,
+/// such as a show folder holding a known season.
+fn find_orphans(root: &Path, known: &HashSet<PathBuf>) -> Vec<PathBuf> {
+    let mut orphans = Vec::new();
+    let Ok(entries) = std::fs::read_dir(root) else {
+        return orphans;
+    };
+    let mut paths: Vec<PathBuf> = entries.flatten().map(|e| e.path()).collect();
+    paths.sort();
+    for path in paths {
+        if known.contains(&path) {
+            continue;
+        }
+        if path.is_dir() && known.iter().any(|k| k.starts_with(&path)) {
+            orphans.extend(find_orphans(&path, known));
+        } else {
+            orphans.push(path);
+        }
+    }
+    orphans
+}
+
+/// Apply every fix in `report` to the database.
+pub async fn repair(pool: &SqlitePool, report: &mut DoctorReport) -> Result<(), sqlx::Error> {
+    for finding in &report.findings {
+        let (Some(id), Some(fix)) = (finding.media_id, finding.fix) else {
+            continue;
+        };
+        match fix {
+            Fix::MarkGone => {
+                media::set_gone(pool, id, media_history::DOCTOR).await?;
+                mark::clear_marks(pool, id).await?;
+            }
+            Fix::Reactivate => {
+                media::set_active(pool, id, media_history::DOCTOR).await?;
+                persistent::clear_owner(pool, id).await?;
+                mark::clear_marks(pool, id).await?;
+            }
+            Fix::MarkTrashed => media::set_trashed(pool, id, media_history::DOCTOR).await?,
+        }
+        tracing::info!("Repaired {}: {fix}", finding.path.display());
+        report.repaired += 1;
+    }
+    Ok(())
+}
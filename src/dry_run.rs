@@ -0,0 +1,26 @@
+//! Dry-run mode, in which moves and deletions are only logged.
+//!
+//! `--dry-run` sets it at startup and admins can switch it from the settings
+//! page, e.g. to demo Rewinder and then go live. Every page shows a banner
+//! while it is on.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// The switch shared by the routes and the periodic cleanup.
+#[derive(Clone, Default)]
+pub struct DryRun(Arc<AtomicBool>);
+
+impl DryRun {
+    pub fn new(on: bool) -> Self {
+        Self(Arc::new(AtomicBool::new(on)))
+    }
+
+    pub fn get(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    pub fn set(&self, on: bool) {
+        self.0.store(on, Ordering::Relaxed);
+    }
+}
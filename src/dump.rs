@@ -0,0 +1,587 @@
+//! Export and import of the database contents as JSON, for moving an instance
+//! to another machine or rebuilding it after SQLite corruption.
+//!
+//! Users and their display preferences and library grants, media and their
+//! status history, tags, comments and editions, marks, keep votes, re-download
+//! requests, persistent ownership of items and series, what rules already did
+//! to items, and announcements along with who dismissed them are kept with
+//! their ids, so references between them survive the round trip. Settings
+//! changed from the admin dashboard come along too. Sessions are not exported;
+//! everyone logs in again after an import.
+
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+/// Bumped when the dump layout changes incompatibly.
+pub const FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, PartialEq)]
+pub struct UserRecord {
+    pub id: i64,
+    pub username: String,
+    pub password_hash: Option<String>,
+    pub is_admin: bool,
+    pub invite_token: Option<String>,
+    pub created_at: String,
+    #[serde(default)]
+    pub locale: Option<String>,
+    #[serde(default)]
+    pub timezone: Option<String>,
+    #[serde(default)]
+    pub feed_token: Option<String>,
+    #[serde(default)]
+    pub restricted: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, PartialEq)]
+pub struct MediaRecord {
+    pub id: i64,
+    pub media_type: String,
+    pub title: String,
+    pub year: Option<i64>,
+    pub season: Option<i64>,
+    pub path: String,
+    pub size_bytes: i64,
+    pub status: String,
+    pub trashed_at: Option<String>,
+    pub first_seen: String,
+    pub last_seen: String,
+    pub poster_path: Option<String>,
+    pub dir_mtime: Option<i64>,
+    #[serde(default)]
+    pub protected: bool,
+    #[serde(default)]
+    pub trash_name: Option<String>,
+    #[serde(default)]
+    pub trash_path: Option<String>,
+    #[serde(default)]
+    pub manual: bool,
+    #[serde(default)]
+    pub archive_path: Option<String>,
+    #[serde(default)]
+    pub cloud_key: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, PartialEq)]
+pub struct MarkRecord {
+    pub user_id: i64,
+    pub media_id: i64,
+    pub marked_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, PartialEq)]
+pub struct TagRecord {
+    pub media_id: i64,
+    pub tag: String,
+    pub tagged_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, PartialEq)]
+pub struct CommentRecord {
+    pub id: i64,
+    pub media_id: i64,
+    pub user_id: i64,
+    pub body: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, PartialEq)]
+pub struct PersistentRecord {
+    pub media_id: i64,
+    pub user_id: i64,
+    pub persisted_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, PartialEq)]
+pub struct PreferencesRecord {
+    pub user_id: i64,
+    pub sort: String,
+    pub sort_dir: String,
+    pub show_marked: bool,
+    pub page_size: i64,
+    pub theme: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, PartialEq)]
+pub struct LibraryAccessRecord {
+    pub media_dir: String,
+    pub user_id: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, PartialEq)]
+pub struct PersistentSeriesRecord {
+    pub title: String,
+    pub user_id: i64,
+    pub persisted_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, PartialEq)]
+pub struct KeepVoteRecord {
+    pub user_id: i64,
+    pub media_id: i64,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, PartialEq)]
+pub struct EditionRecord {
+    pub media_id: i64,
+    pub group_id: i64,
+    pub linked_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, PartialEq)]
+pub struct RuleHitRecord {
+    pub rule: String,
+    pub media_id: i64,
+    pub action: String,
+    pub hit_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, PartialEq)]
+pub struct HistoryRecord {
+    pub id: i64,
+    pub media_id: i64,
+    pub from_status: String,
+    pub to_status: String,
+    pub actor: String,
+    pub changed_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, PartialEq)]
+pub struct SettingRecord {
+    pub key: String,
+    pub value: String,
+    pub updated_by: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, PartialEq)]
+pub struct AnnouncementRecord {
+    pub id: i64,
+    pub message: String,
+    pub created_by: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, PartialEq)]
+pub struct DismissalRecord {
+    pub announcement_id: i64,
+    pub user_id: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, PartialEq)]
+pub struct RerequestRecord {
+    pub media_id: i64,
+    pub user_id: i64,
+    pub requested_at: String,
+    pub forwarded_to: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct Dump {
+    pub version: u32,
+    pub users: Vec<UserRecord>,
+    pub media: Vec<MediaRecord>,
+    pub marks: Vec<MarkRecord>,
+    pub persistent: Vec<PersistentRecord>,
+    #[serde(default)]
+    pub preferences: Vec<PreferencesRecord>,
+    #[serde(default)]
+    pub tags: Vec<TagRecord>,
+    #[serde(default)]
+    pub comments: Vec<CommentRecord>,
+    #[serde(default)]
+    pub library_access: Vec<LibraryAccessRecord>,
+    #[serde(default)]
+    pub persistent_series: Vec<PersistentSeriesRecord>,
+    #[serde(default)]
+    pub keep_votes: Vec<KeepVoteRecord>,
+    #[serde(default)]
+    pub media_editions: Vec<EditionRecord>,
+    #[serde(default)]
+    pub rule_hits: Vec<RuleHitRecord>,
+    #[serde(default)]
+    pub media_history: Vec<HistoryRecord>,
+    #[serde(default)]
+    pub settings: Vec<SettingRecord>,
+    #[serde(default)]
+    pub announcements: Vec<AnnouncementRecord>,
+    #[serde(default)]
+    pub announcement_dismissals: Vec<DismissalRecord>,
+    #[serde(default)]
+    pub rerequests: Vec<RerequestRecord>,
+}
+
+pub async fn export(pool: &SqlitePool) -> Result<Dump, sqlx::Error> {
+    let users = sqlx::query_as::<_, UserRecord>(
+        "SELECT id, username, password_hash, is_admin, invite_token, created_at, locale, timezone,
+                feed_token, restricted
+         FROM users ORDER BY id",
+    )
+    .fetch_all(pool)
+    .await?;
+    let media = sqlx::query_as::<_, MediaRecord>(
+        "SELECT id, media_type, title, year, season, path, size_bytes, status, trashed_at,
+                first_seen, last_seen, poster_path, dir_mtime, protected, trash_name,
+                trash_path, manual, archive_path, cloud_key
+         FROM media ORDER BY id",
+    )
+    .fetch_all(pool)
+    .await?;
+    let marks = sqlx::query_as::<_, MarkRecord>(
+        "SELECT user_id, media_id, marked_at FROM marks ORDER BY user_id, media_id",
+    )
+    .fetch_all(pool)
+    .await?;
+    let persistent = sqlx::query_as::<_, PersistentRecord>(
+        "SELECT media_id, user_id, persisted_at FROM persistent_media ORDER BY media_id",
+    )
+    .fetch_all(pool)
+    .await?;
+    let preferences = sqlx::query_as::<_, PreferencesRecord>(
+        "SELECT user_id, sort, sort_dir, show_marked, page_size, theme
+         FROM user_preferences ORDER BY user_id",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let tags = sqlx::query_as::<_, TagRecord>(
+        "SELECT media_id, tag, tagged_at FROM media_tags ORDER BY media_id, tag",
+    )
+    .fetch_all(pool)
+    .await?;
+    let comments = sqlx::query_as::<_, CommentRecord>(
+        "SELECT id, media_id, user_id, body, created_at FROM comments ORDER BY id",
+    )
+    .fetch_all(pool)
+    .await?;
+    let library_access = sqlx::query_as::<_, LibraryAccessRecord>(
+        "SELECT media_dir, user_id FROM library_access ORDER BY media_dir, user_id",
+    )
+    .fetch_all(pool)
+    .await?;
+    let persistent_series = sqlx::query_as::<_, PersistentSeriesRecord>(
+        "SELECT title, user_id, persisted_at FROM persistent_series ORDER BY title",
+    )
+    .fetch_all(pool)
+    .await?;
+    let keep_votes = sqlx::query_as::<_, KeepVoteRecord>(
+        "SELECT user_id, media_id, created_at FROM keep_votes ORDER BY user_id, media_id",
+    )
+    .fetch_all(pool)
+    .await?;
+    let media_editions = sqlx::query_as::<_, EditionRecord>(
+        "SELECT media_id, group_id, linked_at FROM media_editions ORDER BY media_id",
+    )
+    .fetch_all(pool)
+    .await?;
+    let rule_hits = sqlx::query_as::<_, RuleHitRecord>(
+        "SELECT rule, media_id, action, hit_at FROM rule_hits ORDER BY rule, media_id",
+    )
+    .fetch_all(pool)
+    .await?;
+    let media_history = sqlx::query_as::<_, HistoryRecord>(
+        "SELECT id, media_id, from_status, to_status, actor, changed_at
+         FROM media_history ORDER BY id",
+    )
+    .fetch_all(pool)
+    .await?;
+    let settings = sqlx::query_as::<_, SettingRecord>(
+        "SELECT key, value, updated_by, updated_at FROM settings ORDER BY key",
+    )
+    .fetch_all(pool)
+    .await?;
+    let announcements = sqlx::query_as::<_, AnnouncementRecord>(
+        "SELECT id, message, created_by, created_at FROM announcements ORDER BY id",
+    )
+    .fetch_all(pool)
+    .await?;
+    let announcement_dismissals = sqlx::query_as::<_, DismissalRecord>(
+        "SELECT announcement_id, user_id FROM announcement_dismissals
+         ORDER BY announcement_id, user_id",
+    )
+    .fetch_all(pool)
+    .await?;
+    let rerequests = sqlx::query_as::<_, RerequestRecord>(
+        "SELECT media_id, user_id, requested_at, forwarded_to FROM rerequests
+         ORDER BY media_id, user_id",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(Dump {
+        version: FORMAT_VERSION,
+        users,
+        media,
+        marks,
+        persistent,
+        preferences,
+        tags,
+        comments,
+        library_access,
+        persistent_series,
+        keep_votes,
+        media_editions,
+        rule_hits,
+        media_history,
+        settings,
+        announcements,
+        announcement_dismissals,
+        rerequests,
+    })
+}
+
+/// Load `dump` into the database in a single transaction.
+///
+/// Refuses to touch a database that already has users or media unless
+/// `replace` is set, in which case the existing contents are dropped first.
+pub async fn import(
+    pool: &SqlitePool,
+    dump: &Dump,
+    replace: bool,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if dump.version > FORMAT_VERSION {
+        return Err(format!(
+            "dump format version {} is newer than this rewinder supports ({FORMAT_VERSION})",
+            dump.version
+        )
+        .into());
+    }
+
+    let mut tx = pool.begin().await?;
+    let (existing,): (i64,) =
+        sqlx::query_as("SELECT (SELECT COUNT(*) FROM users) + (SELECT COUNT(*) FROM media)")
+            .fetch_one(&mut *tx)
+            .await?;
+    if existing > 0 && !replace {
+        return Err("database is not empty; pass --replace to overwrite it".into());
+    }
+    for table in [
+        "comments",
+        "library_access",
+        "marks",
+        "media_tags",
+        "persistent_media",
+        "sessions",
+        "user_preferences",
+        "persistent_series",
+        "keep_votes",
+        "media_editions",
+        "rule_hits",
+        "media_history",
+        "settings",
+        "announcements",
+        "announcement_dismissals",
+        "rerequests",
+        "media",
+        "users",
+    ] {
+        sqlx::query(&format!("DELETE FROM {table}"))
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    for u in &dump.users {
+        sqlx::query(
+            "INSERT INTO users (id, username, password_hash, is_admin, invite_token, created_at,
+                                locale, timezone, feed_token, restricted)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(u.id)
+        .bind(&u.username)
+        .bind(&u.password_hash)
+        .bind(u.is_admin)
+        .bind(&u.invite_token)
+        .bind(&u.created_at)
+        .bind(&u.locale)
+        .bind(&u.timezone)
+        .bind(&u.feed_token)
+        .bind(u.restricted)
+        .execute(&mut *tx)
+        .await?;
+    }
+    for m in &dump.media {
+        sqlx::query(
+            "INSERT INTO media (id, media_type, title, year, season, path, size_bytes, status,
+                                trashed_at, first_seen, last_seen, poster_path, dir_mtime,
+                                protected, trash_name, trash_path, manual, archive_path,
+                                cloud_key)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(m.id)
+        .bind(&m.media_type)
+        .bind(&m.title)
+        .bind(m.year)
+        .bind(m.season)
+        .bind(&m.path)
+        .bind(m.size_bytes)
+        .bind(&m.status)
+        .bind(&m.trashed_at)
+        .bind(&m.first_seen)
+        .bind(&m.last_seen)
+        .bind(&m.poster_path)
+        .bind(m.dir_mtime)
+        .bind(m.protected)
+        .bind(&m.trash_name)
+        .bind(&m.trash_path)
+        .bind(m.manual)
+        .bind(&m.archive_path)
+        .bind(&m.cloud_key)
+        .execute(&mut *tx)
+        .await?;
+    }
+    for mark in &dump.marks {
+        sqlx::query("INSERT INTO marks (user_id, media_id, marked_at) VALUES (?, ?, ?)")
+            .bind(mark.user_id)
+            .bind(mark.media_id)
+            .bind(&mark.marked_at)
+            .execute(&mut *tx)
+            .await?;
+    }
+    for t in &dump.tags {
+        sqlx::query("INSERT INTO media_tags (media_id, tag, tagged_at) VALUES (?, ?, ?)")
+            .bind(t.media_id)
+            .bind(&t.tag)
+            .bind(&t.tagged_at)
+            .execute(&mut *tx)
+            .await?;
+    }
+    for c in &dump.comments {
+        sqlx::query(
+            "INSERT INTO comments (id, media_id, user_id, body, created_at) VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(c.id)
+        .bind(c.media_id)
+        .bind(c.user_id)
+        .bind(&c.body)
+        .bind(&c.created_at)
+        .execute(&mut *tx)
+        .await?;
+    }
+    for p in &dump.persistent {
+        sqlx::query(
+            "INSERT INTO persistent_media (media_id, user_id, persisted_at) VALUES (?, ?, ?)",
+        )
+        .bind(p.media_id)
+        .bind(p.user_id)
+        .bind(&p.persisted_at)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    for p in &dump.preferences {
+        sqlx::query(
+            "INSERT INTO user_preferences (user_id, sort, sort_dir, show_marked, page_size, theme)
+             VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(p.user_id)
+        .bind(&p.sort)
+        .bind(&p.sort_dir)
+        .bind(p.show_marked)
+        .bind(p.page_size)
+        .bind(&p.theme)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    for a in &dump.library_access {
+        sqlx::query("INSERT INTO library_access (media_dir, user_id) VALUES (?, ?)")
+            .bind(&a.media_dir)
+            .bind(a.user_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    for s in &dump.persistent_series {
+        sqlx::query(
+            "INSERT INTO persistent_series (title, user_id, persisted_at) VALUES (?, ?, ?)",
+        )
+        .bind(&s.title)
+        .bind(s.user_id)
+        .bind(&s.persisted_at)
+        .execute(&mut *tx)
+        .await?;
+    }
+    for k in &dump.keep_votes {
+        sqlx::query("INSERT INTO keep_votes (user_id, media_id, created_at) VALUES (?, ?, ?)")
+            .bind(k.user_id)
+            .bind(k.media_id)
+            .bind(&k.created_at)
+            .execute(&mut *tx)
+            .await?;
+    }
+    for e in &dump.media_editions {
+        sqlx::query("INSERT INTO media_editions (media_id, group_id, linked_at) VALUES (?, ?, ?)")
+            .bind(e.media_id)
+            .bind(e.group_id)
+            .bind(&e.linked_at)
+            .execute(&mut *tx)
+            .await?;
+    }
+    for h in &dump.rule_hits {
+        sqlx::query("INSERT INTO rule_hits (rule, media_id, action, hit_at) VALUES (?, ?, ?, ?)")
+            .bind(&h.rule)
+            .bind(h.media_id)
+            .bind(&h.action)
+            .bind(&h.hit_at)
+            .execute(&mut *tx)
+            .await?;
+    }
+    for h in &dump.media_history {
+        sqlx::query(
+            "INSERT INTO media_history (id, media_id, from_status, to_status, actor, changed_at)
+             VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(h.id)
+        .bind(h.media_id)
+        .bind(&h.from_status)
+        .bind(&h.to_status)
+        .bind(&h.actor)
+        .bind(&h.changed_at)
+        .execute(&mut *tx)
+        .await?;
+    }
+    for s in &dump.settings {
+        sqlx::query(
+            "INSERT INTO settings (key, value, updated_by, updated_at) VALUES (?, ?, ?, ?)",
+        )
+        .bind(&s.key)
+        .bind(&s.value)
+        .bind(&s.updated_by)
+        .bind(&s.updated_at)
+        .execute(&mut *tx)
+        .await?;
+    }
+    for a in &dump.announcements {
+        sqlx::query(
+            "INSERT INTO announcements (id, message, created_by, created_at) VALUES (?, ?, ?, ?)",
+        )
+        .bind(a.id)
+        .bind(&a.message)
+        .bind(&a.created_by)
+        .bind(&a.created_at)
+        .execute(&mut *tx)
+        .await?;
+    }
+    for d in &dump.announcement_dismissals {
+        sqlx::query("INSERT INTO announcement_dismissals (announcement_id, user_id) VALUES (?, ?)")
+            .bind(d.announcement_id)
+            .bind(d.user_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    for r in &dump.rerequests {
+        sqlx::query(
+            "INSERT INTO rerequests (media_id, user_id, requested_at, forwarded_to)
+             VALUES (?, ?, ?, ?)",
+        )
+        .bind(r.media_id)
+        .bind(r.user_id)
+        .bind(&r.requested_at)
+        .bind(&r.forwarded_to)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+    Ok(())
+}
@@ -6,6 +6,7 @@ pub enum AppError {
     Database(sqlx::Error),
     NotFound,
     Forbidden,
+    BadRequest(String),
     Internal(String),
 }
 
@@ -15,6 +16,7 @@ impl std::fmt::Display for AppError {
             AppError::Database(e) => write!(f, "Database error: {e}"),
             AppError::NotFound => write!(f, "Not found"),
             AppError::Forbidden => write!(f, "Forbidden"),
+            AppError::BadRequest(msg) => write!(f, "Bad request: {msg}"),
             AppError::Internal(msg) => write!(f, "Internal error: {msg}"),
         }
     }
@@ -26,6 +28,7 @@ impl IntoResponse for AppError {
             AppError::Database(_) => (StatusCode::INTERNAL_SERVER_ERROR, "database"),
             AppError::NotFound => (StatusCode::NOT_FOUND, "not_found"),
             AppError::Forbidden => (StatusCode::FORBIDDEN, "forbidden"),
+            AppError::BadRequest(_) => (StatusCode::BAD_REQUEST, "bad_request"),
             AppError::Internal(_) => (StatusCode::INTERNAL_SERVER_ERROR, "internal"),
         };
         tracing::error!(
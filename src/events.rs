@@ -0,0 +1,31 @@
+//! In-process bus for media status changes. The web UI streams it to open
+//! pages over server-sent events; other subsystems can [`subscribe`] as well.
+
+use serde::Serialize;
+use std::sync::LazyLock;
+use tokio::sync::broadcast;
+
+/// Events a slow subscriber may fall behind by before it starts missing them.
+const CAPACITY: usize = 256;
+
+static BUS: LazyLock<broadcast::Sender<MediaEvent>> =
+    LazyLock::new(|| broadcast::channel(CAPACITY).0);
+
+/// A media item moved to a new status, e.g. into the trash.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MediaEvent {
+    pub media_id: i64,
+    pub status: String,
+    /// Same as the `actor` of the media history entry.
+    pub actor: String,
+}
+
+pub fn publish(event: MediaEvent) {
+    // Nobody listening is fine.
+    let _ = BUS.send(event);
+}
+
+/// Receive every event published from now on.
+pub fn subscribe() -> broadcast::Receiver<MediaEvent> {
+    BUS.subscribe()
+}
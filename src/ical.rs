@@ -0,0 +1,103 @@
+//! Just enough iCalendar (RFC 5545) to publish all-day events, for the
+//! calendar of scheduled deletions.
+
+use jiff::civil::Date;
+use jiff::Timestamp;
+
+/// An all-day event.
+pub struct Event {
+    /// Stable across requests, so calendar apps update instead of duplicating.
+    pub uid: String,
+    pub date: Date,
+    pub summary: String,
+    pub description: String,
+    pub url: Option<String>,
+}
+
+/// A whole VCALENDAR with `events`, stamped with `now`.
+pub fn calendar(name: &str, events: &[Event], now: Timestamp) -> String {
+    let stamp = now.strftime("%Y%m%dT%H%M%SZ").to_string();
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//rewinder//deletions//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+        format!("X-WR-CALNAME:{}", escape(name)),
+    ];
+    for event in events {
+        lines.push("BEGIN:VEVENT".to_string());
+        lines.push(format!("UID:{}", escape(&event.uid)));
+        lines.push(format!("DTSTAMP:{stamp}"));
+        lines.push(format!(
+            "DTSTART;VALUE=DATE:{}",
+            event.date.strftime("%Y%m%d")
+        ));
+        let end = event.date.tomorrow().unwrap_or(event.date);
+        lines.push(format!("DTEND;VALUE=DATE:{}", end.strftime("%Y%m%d")));
+        lines.push(format!("SUMMARY:{}", escape(&event.summary)));
+        lines.push(format!("DESCRIPTION:{}", escape(&event.description)));
+        if let Some(url) = &event.url {
+            lines.push(format!("URL:{url}"));
+        }
+        lines.push("TRANSP:TRANSPARENT".to_string());
+        lines.push("END:VEVENT".to_string());
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    lines.iter().map(|line| fold(line) + "\r\n").collect()
+}
+
+/// Escape a TEXT value.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Split lines longer than 75 octets, continuing them with a space.
+fn fold(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len());
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            width = 1;
+        }
+        folded.push(c);
+        width += c.len_utf8();
+    }
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_all_day_events() {
+        let event = Event {
+            uid: "media-7@rewinder".to_string(),
+            date: "2024-04-05".parse().unwrap(),
+            summary: "Deleted: Heat, Director's Cut; 1995".to_string(),
+            description: "Rescue it first.\nOr not.".to_string(),
+            url: Some("http://nas/media/7".to_string()),
+        };
+        let now = "2024-03-31T12:00:00Z".parse().unwrap();
+        let ics = calendar("Rewinder", &[event], now);
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+        assert!(ics.contains("DTSTAMP:20240331T120000Z\r\n"));
+        assert!(ics.contains("DTSTART;VALUE=DATE:20240405\r\nDTEND;VALUE=DATE:20240406\r\n"));
+        assert!(ics.contains("SUMMARY:Deleted: Heat\\, Director's Cut\\; 1995\r\n"));
+        assert!(ics.contains("DESCRIPTION:Rescue it first.\\nOr not.\r\n"));
+    }
+
+    #[test]
+    fn folds_long_lines() {
+        let line = format!("SUMMARY:{}", "é".repeat(60));
+        let folded = fold(&line);
+        assert!(folded.split("\r\n").all(|part| part.len() <= 75));
+        assert_eq!(folded.replace("\r\n ", ""), line);
+    }
+}
@@ -1,15 +1,44 @@
 #[cfg(not(any(target_os = "linux", target_os = "macos")))]
 compile_error!("rewinder supports only Linux and macOS targets.");
 
+pub mod arr;
 pub mod auth;
+pub mod banners;
+pub mod check;
+pub mod commands;
 pub mod config;
 pub mod db;
+pub mod doctor;
+pub mod dry_run;
+pub mod dump;
 pub mod error;
+pub mod events;
+pub mod ical;
+pub mod listen;
+pub mod locale;
+pub mod maintenance;
+pub mod manifest;
+pub mod mediaserver;
 pub mod models;
+pub mod paths;
 pub mod persistent;
+pub mod policies;
+pub mod posters;
+pub mod rate_limit;
+pub mod rclone;
+pub mod relocate;
+pub mod rename;
 pub mod routes;
+pub mod rules;
+pub mod s3;
 pub mod scanner;
+pub mod settings;
+pub mod shutdown;
+pub mod storage;
+pub mod telemetry;
 pub mod templates;
+pub mod theme;
 pub mod tmdb;
+pub mod torrent;
 pub mod trash;
 pub mod watcher;
@@ -0,0 +1,57 @@
+//! Binding the web server's sockets, one per configured address.
+
+use socket2::{Domain, Socket, Type};
+use std::net::SocketAddr;
+use tokio::net::TcpListener;
+
+/// Listen on every address in `addrs`; a host name binds each address it
+/// resolves to.
+///
+/// An IPv6 wildcard accepts IPv4 connections too on most systems, which would
+/// take the port from an IPv4 address listed next to it. So when the list has
+/// both families, IPv6 sockets are made IPv6-only.
+pub async fn bind_all(addrs: &[String]) -> std::io::Result<Vec<TcpListener>> {
+    let mut resolved: Vec<SocketAddr> = Vec::new();
+    for addr in addrs {
+        for socket_addr in tokio::net::lookup_host(addr).await? {
+            if !resolved.contains(&socket_addr) {
+                resolved.push(socket_addr);
+            }
+        }
+    }
+    let mixed =
+        resolved.iter().any(SocketAddr::is_ipv4) && resolved.iter().any(SocketAddr::is_ipv6);
+    resolved.into_iter().map(|addr| bind(addr, mixed)).collect()
+}
+
+fn bind(addr: SocketAddr, v6_only: bool) -> std::io::Result<TcpListener> {
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, None)?;
+    if addr.is_ipv6() && v6_only {
+        socket.set_only_v6(true)?;
+    }
+    socket.set_reuse_address(true)?;
+    socket.set_nonblocking(true)?;
+    socket
+        .bind(&addr.into())
+        .map_err(|e| std::io::Error::new(e.kind(), format!("cannot listen on {addr}: {e}")))?;
+    socket.listen(1024)?;
+    TcpListener::from_std(socket.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn every_address_gets_a_listener() {
+        let listeners = bind_all(&["127.0.0.1:0".to_string(), "127.0.0.2:0".to_string()])
+            .await
+            .unwrap();
+        let ips: Vec<_> = listeners
+            .iter()
+            .map(|l| l.local_addr().unwrap().ip().to_string())
+            .collect();
+        assert_eq!(ips, ["127.0.0.1", "127.0.0.2"]);
+        assert!(bind_all(&["not an address".to_string()]).await.is_err());
+    }
+}
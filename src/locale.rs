@@ -0,0 +1,227 @@
+//! Per-user date formatting. SQLite stores UTC timestamps as "YYYY-MM-DD HH:MM:SS";
+//! pages show them in the viewer's timezone and date format.
+
+use jiff::civil::{Date, DateTime};
+use jiff::tz::TimeZone;
+use jiff::{SignedDuration, Timestamp};
+use std::future::Future;
+use std::sync::{Arc, OnceLock};
+
+/// Date formats users can pick from: code, label and strftime pattern.
+pub const LOCALES: &[(&str, &str, &str)] = &[
+    ("iso", "ISO (2024-03-31 14:05)", "%Y-%m-%d %H:%M"),
+    (
+        "en-US",
+        "English, US (03/31/2024 2:05 PM)",
+        "%m/%d/%Y %-I:%M %p",
+    ),
+    ("en-GB", "English, UK (31/03/2024 14:05)", "%d/%m/%Y %H:%M"),
+    ("de-DE", "Deutsch (31.03.2024 14:05)", "%d.%m.%Y %H:%M"),
+    ("fr-FR", "Français (31/03/2024 14:05)", "%d/%m/%Y %H:%M"),
+    ("nl-NL", "Nederlands (31-03-2024 14:05)", "%d-%m-%Y %H:%M"),
+    ("ja-JP", "日本語 (2024/03/31 14:05)", "%Y/%m/%d %H:%M"),
+];
+
+pub const DEFAULT_LOCALE: &str = "iso";
+pub const DEFAULT_TIMEZONE: &str = "UTC";
+
+pub fn is_known_locale(code: &str) -> bool {
+    LOCALES.iter().any(|(c, _, _)| *c == code)
+}
+
+pub fn is_known_timezone(name: &str) -> bool {
+    jiff::tz::db().get(name).is_ok()
+}
+
+/// Every timezone name the bundled database knows, sorted.
+pub fn timezones() -> Vec<String> {
+    let mut names: Vec<String> = jiff::tz::db()
+        .available()
+        .map(|name| name.to_string())
+        .collect();
+    names.sort();
+    names
+}
+
+/// How one user wants to see dates.
+#[derive(Debug, Clone)]
+pub struct UserTime {
+    pattern: &'static str,
+    tz: TimeZone,
+}
+
+impl Default for UserTime {
+    fn default() -> Self {
+        Self::new(None, None)
+    }
+}
+
+impl UserTime {
+    /// Unknown or unset settings fall back to ISO dates in UTC.
+    pub fn new(locale: Option<&str>, timezone: Option<&str>) -> Self {
+        let locale = locale.unwrap_or(DEFAULT_LOCALE);
+        let pattern = LOCALES
+            .iter()
+            .find(|(code, _, _)| *code == locale)
+            .map_or(LOCALES[0].2, |(_, _, pattern)| pattern);
+        let tz = timezone
+            .and_then(|name| jiff::tz::db().get(name).ok())
+            .unwrap_or(TimeZone::UTC);
+        Self { pattern, tz }
+    }
+
+    /// Format a UTC timestamp from the database; anything unparsable is shown as is.
+    pub fn format(&self, utc: &str) -> String {
+        let Ok(datetime) = utc.parse::<DateTime>() else {
+            return utc.to_string();
+        };
+        match datetime.to_zoned(TimeZone::UTC) {
+            Ok(zoned) => zoned
+                .with_time_zone(self.tz.clone())
+                .strftime(self.pattern)
+                .to_string(),
+            Err(_) => utc.to_string(),
+        }
+    }
+
+    /// The user's calendar date `days` days after the UTC timestamp `utc`.
+    pub fn date_after(&self, utc: &str, days: u64) -> Option<Date> {
+        let due = parse_utc(utc)?
+            .checked_add(SignedDuration::from_hours(24 * days as i64))
+            .ok()?;
+        Some(due.to_zoned(self.tz.clone()).date())
+    }
+}
+
+/// Parse a UTC timestamp as SQLite's `datetime()` writes it.
+fn parse_utc(utc: &str) -> Option<Timestamp> {
+    let datetime = utc.parse::<DateTime>().ok()?;
+    Some(datetime.to_zoned(TimeZone::UTC).ok()?.timestamp())
+}
+
+/// A duration in its largest sensible unit, e.g. "3 weeks" or "1 day".
+fn humanize(duration: SignedDuration) -> String {
+    let secs = duration.as_secs().unsigned_abs();
+    let (count, unit) = match secs {
+        0..60 => return "less than a minute".to_string(),
+        60..3_600 => (secs / 60, "minute"),
+        3_600..86_400 => (secs / 3_600, "hour"),
+        86_400..1_209_600 => (secs / 86_400, "day"),
+        1_209_600..5_184_000 => (secs / 604_800, "week"),
+        5_184_000..31_536_000 => (secs / 2_592_000, "month"),
+        _ => (secs / 31_536_000, "year"),
+    };
+    let plural = if count == 1 { "" } else { "s" };
+    format!("{count} {unit}{plural}")
+}
+
+/// How long before `now` the UTC timestamp `utc` was, e.g. "3 weeks ago".
+pub fn ago(utc: &str, now: Timestamp) -> Option<String> {
+    let then = parse_utc(utc)?;
+    Some(format!("{} ago", humanize(now.duration_since(then))))
+}
+
+/// How long after `now` the moment `days` after `utc` is, e.g. "in 5 days";
+/// `None` once it has passed.
+pub fn in_days(utc: &str, days: u64, now: Timestamp) -> Option<String> {
+    let then = parse_utc(utc)?;
+    let due = then
+        .checked_add(SignedDuration::from_hours(24 * days as i64))
+        .ok()?;
+    let left = due.duration_since(now);
+    (left > SignedDuration::ZERO).then(|| format!("in {}", humanize(left)))
+}
+
+/// Slot for the settings of the user behind the current request. The auth
+/// extractor fills it, so pages rendered for that request can use them.
+#[derive(Clone, Default)]
+pub struct RequestTime(Arc<OnceLock<UserTime>>);
+
+impl RequestTime {
+    pub fn set(&self, time: UserTime) {
+        let _ = self.0.set(time);
+    }
+}
+
+tokio::task_local! {
+    static REQUEST_TIME: RequestTime;
+}
+
+/// Run `f` with `slot` as the settings [`local_time`] uses.
+pub async fn scope<F: Future>(slot: RequestTime, f: F) -> F::Output {
+    REQUEST_TIME.scope(slot, f).await
+}
+
+/// A database timestamp as the current user wants to see it. For templates.
+pub fn local_time(utc: &str) -> String {
+    REQUEST_TIME
+        .try_with(|slot| slot.0.get().cloned())
+        .ok()
+        .flatten()
+        .unwrap_or_default()
+        .format(utc)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_in_the_users_zone_and_style() {
+        let berlin = UserTime::new(Some("de-DE"), Some("Europe/Berlin"));
+        assert_eq!(berlin.format("2024-03-31 12:05:00"), "31.03.2024 14:05");
+        let us = UserTime::new(Some("en-US"), Some("America/New_York"));
+        assert_eq!(us.format("2024-01-15 17:30:00"), "01/15/2024 12:30 PM");
+    }
+
+    #[test]
+    fn falls_back_to_iso_utc() {
+        let time = UserTime::new(Some("xx"), Some("Mars/Olympus"));
+        assert_eq!(time.format("2024-03-31 12:05:00"), "2024-03-31 12:05");
+        assert_eq!(time.format("not a date"), "not a date");
+    }
+
+    #[test]
+    fn relative_times() {
+        let now: Timestamp = "2024-03-31T12:00:00Z".parse().unwrap();
+        assert_eq!(ago("2024-03-10 12:00:00", now).unwrap(), "3 weeks ago");
+        assert_eq!(ago("2024-03-30 11:00:00", now).unwrap(), "1 day ago");
+        assert_eq!(
+            ago("2024-03-31 11:59:30", now).unwrap(),
+            "less than a minute ago"
+        );
+        assert_eq!(ago("2022-01-01 00:00:00", now).unwrap(), "2 years ago");
+        assert_eq!(ago("garbage", now), None);
+
+        assert_eq!(in_days("2024-03-31 00:00:00", 5, now).unwrap(), "in 4 days");
+        assert_eq!(
+            in_days("2024-03-31 11:00:00", 1, now).unwrap(),
+            "in 23 hours"
+        );
+        assert_eq!(in_days("2024-03-01 00:00:00", 7, now), None);
+    }
+
+    #[test]
+    fn date_after_uses_the_users_zone() {
+        let tokyo = UserTime::new(None, Some("Asia/Tokyo"));
+        let utc = UserTime::default();
+        let trashed = "2024-03-24 20:00:00";
+        assert_eq!(
+            tokyo.date_after(trashed, 7).unwrap().to_string(),
+            "2024-04-01"
+        );
+        assert_eq!(
+            utc.date_after(trashed, 7).unwrap().to_string(),
+            "2024-03-31"
+        );
+    }
+
+    #[tokio::test]
+    async fn local_time_uses_the_request_slot() {
+        let slot = RequestTime::default();
+        slot.set(UserTime::new(Some("en-GB"), Some("Asia/Tokyo")));
+        let shown = scope(slot, async { local_time("2024-03-31 20:00:00") }).await;
+        assert_eq!(shown, "01/04/2024 05:00");
+        assert_eq!(local_time("2024-03-31 20:00:00"), "2024-03-31 20:00");
+    }
+}
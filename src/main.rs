@@ -1,175 +1,188 @@
-use clap::Parser;
-use std::fs::OpenOptions;
-use std::path::Path;
-use std::sync::Arc;
+use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+use tokio::signal::unix::{signal, SignalKind};
 use tower_http::services::ServeDir;
 
-use rewinder::config::AppConfig;
+use rewinder::config::{AppConfig, SharedConfig};
+use rewinder::dry_run::DryRun;
 use rewinder::routes::AppState;
+use rewinder::scanner::{ScanMode, ScanStatus};
+use rewinder::shutdown::{self, MoveTracker};
+use rewinder::storage::validate_storage_access;
 use rewinder::tmdb::TmdbClient;
-use rewinder::{auth, db, models, scanner, trash, watcher};
+use rewinder::{auth, commands, db, maintenance, scanner, telemetry, trash, watcher};
 
 #[derive(Parser)]
 #[command(name = "rewinder", about = "Plex media storage manager")]
 struct Cli {
     /// Path to config file
-    #[arg(long, env = "REWINDER_CONFIG", default_value = "rewinder.toml")]
+    #[arg(
+        long,
+        global = true,
+        env = "REWINDER_CONFIG",
+        default_value = "rewinder.toml"
+    )]
     config: String,
 
     /// Dry-run mode: scan and mark as usual, but never move or delete files on disk
-    #[arg(long)]
+    #[arg(long, global = true)]
     dry_run: bool,
-}
 
-fn ensure_dir_readable_and_writable(
-    path: &Path,
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    if !path.is_dir() {
-        return Err(format!("path is not a directory: {}", path.display()).into());
-    }
+    #[command(subcommand)]
+    command: Option<Command>,
+}
 
-    // Readability check.
-    std::fs::read_dir(path)
-        .map_err(|e| format!("directory not readable ({}): {e}", path.display()))?;
+#[derive(Subcommand)]
+enum Command {
+    /// Validate the config, its media/trash/permanent dirs and the database, then exit
+    CheckConfig,
+    /// Scan all media dirs once and exit
+    Scan,
+    /// Run the periodic cleanup once (expired trash, stale marks and sessions) and exit
+    Cleanup,
+    /// Compare the database with the files on disk and report mismatches
+    Doctor {
+        /// Update the database to match the disk where that is safe
+        #[arg(long)]
+        repair: bool,
+    },
+    /// Manage users directly in the database
+    #[command(subcommand)]
+    User(UserCommand),
+    /// Write users, media, marks and persistent ownership to a JSON file
+    Export {
+        #[arg(long)]
+        out: PathBuf,
+    },
+    /// Load a file written by `export` into the database
+    Import {
+        file: PathBuf,
+        /// Drop the existing database contents first
+        #[arg(long)]
+        replace: bool,
+    },
+    /// Roll back schema migrations, e.g. before downgrading
+    #[command(subcommand)]
+    Migrate(MigrateCommand),
+}
 
-    // Writability check.
-    let unique = format!(
-        ".rewinder_perm_check_{}_{}",
-        std::process::id(),
-        std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .map_err(|e| format!("system clock error: {e}"))?
-            .as_nanos()
-    );
-    let probe = path.join(unique);
-    OpenOptions::new()
-        .create_new(true)
-        .write(true)
-        .open(&probe)
-        .map_err(|e| format!("directory not writable ({}): {e}", path.display()))?;
-    std::fs::remove_file(&probe).map_err(|e| {
-        format!(
-            "failed to clean up permission probe {}: {e}",
-            probe.display()
-        )
-    })?;
+#[derive(Subcommand)]
+enum MigrateCommand {
+    /// Revert the most recently applied migrations
+    Down {
+        /// How many migrations to revert
+        #[arg(long, default_value_t = 1)]
+        steps: usize,
+    },
+    /// Revert the most recent migration and apply it again
+    Redo,
+}
 
-    Ok(())
+#[derive(Subcommand)]
+enum UserCommand {
+    /// Create a user and print their invite link
+    Add {
+        name: String,
+        /// Make the new user an admin
+        #[arg(long)]
+        admin: bool,
+    },
+    /// Set a new random password and log the user out everywhere
+    ResetPassword { name: String },
+    /// Give an existing user admin rights
+    Promote { name: String },
 }
 
-fn validate_storage_access(
+/// Run a one-shot admin command against the database instead of starting the server.
+async fn run_command(
+    command: Command,
+    pool: &sqlx::SqlitePool,
     config: &AppConfig,
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    for media_dir in &config.media_dirs {
-        ensure_dir_readable_and_writable(media_dir)?;
-    }
-
-    for trash_dir in config.all_trash_dirs() {
-        if !trash_dir.exists() {
-            std::fs::create_dir_all(&trash_dir).map_err(|e| {
-                format!(
-                    "failed to create derived trash directory {}: {e}",
-                    trash_dir.display()
-                )
-            })?;
+    dry_run: bool,
+) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    match command {
+        // Handled before the database is opened.
+        Command::CheckConfig => unreachable!(),
+        Command::Scan => commands::scan(pool, config).await,
+        Command::Cleanup => commands::cleanup(pool, config, dry_run).await,
+        Command::Doctor { repair } => commands::doctor(pool, config, repair).await,
+        Command::User(UserCommand::Add { name, admin }) => {
+            commands::user_add(pool, &name, admin).await
         }
-        ensure_dir_readable_and_writable(&trash_dir)?;
-    }
-
-    for permanent_dir in config.all_permanent_dirs() {
-        if !permanent_dir.exists() {
-            std::fs::create_dir_all(&permanent_dir).map_err(|e| {
-                format!(
-                    "failed to create derived permanent directory {}: {e}",
-                    permanent_dir.display()
-                )
-            })?;
+        Command::User(UserCommand::ResetPassword { name }) => {
+            commands::user_reset_password(pool, &name).await
+        }
+        Command::User(UserCommand::Promote { name }) => commands::user_promote(pool, &name).await,
+        Command::Export { out } => commands::export(pool, &out).await,
+        Command::Import { file, replace } => commands::import(pool, &file, replace).await,
+        Command::Migrate(MigrateCommand::Down { steps }) => {
+            commands::migrate_down(pool, steps).await
         }
-        ensure_dir_readable_and_writable(&permanent_dir)?;
+        Command::Migrate(MigrateCommand::Redo) => commands::migrate_redo(pool).await,
     }
+}
 
-    #[cfg(unix)]
-    {
-        use std::os::unix::fs::MetadataExt;
-
-        for media_dir in &config.media_dirs {
-            let trash_dir = AppConfig::trash_dir_for_media_dir(media_dir).ok_or_else(|| {
-                format!(
-                    "failed to derive trash directory for media_dir {}",
-                    media_dir.display()
-                )
-            })?;
-            let media_dev = std::fs::metadata(media_dir)
-                .map_err(|e| format!("failed to stat media_dir {}: {e}", media_dir.display()))?
-                .dev();
-            let trash_dev = std::fs::metadata(&trash_dir)
-                .map_err(|e| format!("failed to stat trash_dir {}: {e}", trash_dir.display()))?
-                .dev();
-
-            if media_dev != trash_dev {
-                return Err(format!(
-                    "media_dir {} and trash_dir {} are on different filesystems; refusing to start to avoid ownership changes during cross-device moves",
-                    media_dir.display(),
-                    trash_dir.display()
-                )
-                .into());
-            }
-
-            let permanent_dir =
-                AppConfig::permanent_dir_for_media_dir(media_dir).ok_or_else(|| {
-                    format!(
-                        "failed to derive permanent directory for media_dir {}",
-                        media_dir.display()
-                    )
-                })?;
-            let permanent_dev = std::fs::metadata(&permanent_dir)
-                .map_err(|e| {
-                    format!(
-                        "failed to stat permanent_dir {}: {e}",
-                        permanent_dir.display()
-                    )
-                })?
-                .dev();
-            if media_dev != permanent_dev {
-                return Err(format!(
-                    "media_dir {} and permanent_dir {} are on different filesystems; refusing to start to avoid ownership changes during cross-device moves",
-                    media_dir.display(),
-                    permanent_dir.display()
-                )
-                .into());
+/// Reload the config file whenever the process receives SIGHUP.
+fn spawn_reload_on_sighup(
+    shared_config: SharedConfig,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let mut hangup = signal(SignalKind::hangup())?;
+    tokio::spawn(async move {
+        while hangup.recv().await.is_some() {
+            tracing::info!("Received SIGHUP, reloading config");
+            if let Err(e) = shared_config.reload() {
+                tracing::error!("Config reload failed, keeping the running config: {e}");
             }
         }
-    }
-
+    });
     Ok(())
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    tracing_subscriber::fmt()
-        .compact()
-        .with_target(true)
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "rewinder=info".into()),
-        )
-        .init();
+    let mut cli = Cli::parse();
+    if let Some(Command::CheckConfig) = cli.command {
+        let report = rewinder::check::check_config(&cli.config).await;
+        println!("{report}");
+        std::process::exit(if report.problems() == 0 { 0 } else { 1 });
+    }
 
-    let cli = Cli::parse();
     let config = AppConfig::load(&cli.config)?;
+    let _telemetry = telemetry::init(&config)?;
     validate_storage_access(&config)?;
-    let dry_run = cli.dry_run;
-    if dry_run {
+    let dry_run = DryRun::new(cli.dry_run);
+    if dry_run.get() {
         tracing::warn!("*** DRY-RUN MODE ACTIVE — no files will be moved or deleted ***");
         tracing::warn!(
             "Database state will diverge from disk. Back up your database before using this mode."
         );
     }
     tracing::info!("Loaded config from {}", cli.config);
+    if let Some(pattern) = &config.season_pattern {
+        scanner::set_season_pattern(regex::Regex::new(pattern)?);
+    }
+    scanner::set_specials_policy(config.specials);
+    if !config.size_exclude.is_empty() {
+        scanner::set_size_exclude(regex::RegexSet::new(&config.size_exclude)?);
+    }
+    rewinder::routes::set_base_path(&config.base_path);
+    rewinder::templates::set_size_units(config.size_units);
 
-    let pool = db::init_pool(&config.database_url).await?;
+    let pool_settings = db::PoolSettings::from(&config);
+    let pool = if let Some(Command::Migrate(_)) = cli.command {
+        // Reverting must not first apply whatever this build considers pending.
+        db::open_pool(&config.database_url, &pool_settings).await?
+    } else {
+        db::init_pool(&config.database_url, &pool_settings).await?
+    };
     tracing::info!("Database initialized");
 
+    if let Some(command) = cli.command.take() {
+        let output = run_command(command, &pool, &config, dry_run.get()).await?;
+        println!("{output}");
+        return Ok(());
+    }
+
     // Seed admin user if configured
     if let Some(ref admin_user) = config.initial_admin_user {
         auth::seed_admin(&pool, admin_user).await?;
@@ -184,151 +197,179 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         tracing::info!("TMDB API key configured — poster fetching enabled");
     }
 
-    // Run initial scan
-    scanner::full_scan(&pool, &config.media_dirs, tmdb.as_ref()).await?;
+    let shared_config = SharedConfig::new(config.clone(), Some(PathBuf::from(&cli.config)));
+    rewinder::settings::load(&pool, &shared_config).await?;
+    spawn_reload_on_sighup(shared_config.clone())?;
+
+    let normalized = rewinder::paths::normalize_stored(&pool).await?;
+    if normalized > 0 {
+        tracing::info!("Brought the titles and paths of {normalized} items into NFC");
+    }
+    let rewritten = rewinder::paths::canonicalize_stored(&pool, &config).await?;
+    if rewritten > 0 {
+        tracing::info!("Resolved symlinks in the stored paths of {rewritten} items");
+    }
+
+    // Before the scan, which would take half-moved series for gone
+    let restored = trash::series::recover(&pool).await?;
+    if restored > 0 {
+        tracing::warn!("Rolled back {restored} moves of an interrupted trash batch");
+    }
+
+    // Run initial scan, measuring sizes in the background
+    let scan_status = ScanStatus::default();
+    scanner::start_size_worker(pool.clone(), scan_status.clone()).await?;
+    if shared_config.current().maintenance {
+        tracing::warn!("Maintenance mode is on; skipping the initial scan");
+    } else {
+        scanner::full_scan(
+            &pool,
+            &config.media_dirs,
+            tmdb.as_ref(),
+            ScanMode::Incremental,
+            &scan_status,
+        )
+        .await?;
+    }
 
     // Start filesystem watcher
-    watcher::start(pool.clone(), config.media_dirs.clone()).await?;
+    let watcher_health = watcher::WatcherHealth::default();
+    watcher::start(
+        pool.clone(),
+        shared_config.clone(),
+        watcher_health.clone(),
+        scan_status.clone(),
+    )
+    .await?;
 
     // Start background maintenance task
-    if config.cleanup_interval_hours > 0 {
+    let moves = MoveTracker::default();
+    {
         let cleanup_pool = pool.clone();
-        let grace_period = config.grace_period_days;
-        let cleanup_config = config.clone();
-        let media_dirs = config.media_dirs.clone();
-        let cleanup_interval_hours = config.cleanup_interval_hours;
+        let shared_cleanup_config = shared_config.clone();
         let cleanup_tmdb = tmdb.clone();
+        let cleanup_scan_status = scan_status.clone();
+        let cleanup_moves = moves.clone();
+        let cleanup_dry_run = dry_run.clone();
         tokio::spawn(async move {
-            let mut interval = tokio::time::interval(std::time::Duration::from_secs(
-                cleanup_interval_hours * 3600,
-            ));
+            let mut changes = shared_cleanup_config.subscribe();
+            let mut last_run: Option<tokio::time::Instant> = None;
             loop {
-                interval.tick().await;
-                // Re-scan to detect externally removed directories
-                if let Err(e) =
-                    scanner::full_scan(&cleanup_pool, &media_dirs, cleanup_tmdb.as_ref()).await
-                {
-                    tracing::error!("Periodic scan error: {e}");
+                // The interval can change at runtime; 0 pauses cleanup until it does.
+                let current = shared_cleanup_config.current();
+                let hours = current.cleanup_interval_hours;
+                if hours == 0 || current.maintenance {
+                    if current.maintenance {
+                        tracing::info!("Automatic cleanup paused in maintenance mode");
+                    } else {
+                        tracing::info!("Automatic cleanup disabled (cleanup_interval_hours = 0)");
+                    }
+                    if changes.changed().await.is_err() {
+                        break;
+                    }
+                    continue;
                 }
-                // Clean up marks for items that are gone
-                match models::media::cleanup_gone_marks(&cleanup_pool).await {
-                    Ok(n) if n > 0 => tracing::info!("Cleaned up {n} marks for gone media"),
-                    Err(e) => tracing::error!("Mark cleanup error: {e}"),
-                    _ => {}
+                let next_run = last_run.map_or_else(tokio::time::Instant::now, |last| {
+                    last + std::time::Duration::from_secs(hours * 3600)
+                });
+                tokio::select! {
+                    _ = tokio::time::sleep_until(next_run) => {}
+                    changed = changes.changed() => {
+                        if changed.is_err() {
+                            break;
+                        }
+                        continue;
+                    }
                 }
-                if let Err(e) = trash::cleanup_missing_trash(&cleanup_pool, &cleanup_config).await {
-                    tracing::error!("Missing trash cleanup error: {e}");
-                }
-                if let Err(e) =
-                    trash::cleanup_expired(&cleanup_pool, &cleanup_config, grace_period, dry_run)
-                        .await
+                last_run = Some(tokio::time::Instant::now());
+                // Picks up reloaded media dirs and grace periods.
+                let cleanup_config = shared_cleanup_config.current();
+                // Re-scan to detect externally removed directories
+                if let Err(e) = scanner::full_scan(
+                    &cleanup_pool,
+                    &cleanup_config.media_dirs,
+                    cleanup_tmdb.as_ref(),
+                    ScanMode::Incremental,
+                    &cleanup_scan_status,
+                )
+                .await
                 {
-                    tracing::error!("Trash cleanup error: {e}");
-                }
-                if let Err(e) = auth::session::cleanup_expired(&cleanup_pool).await {
-                    tracing::error!("Session cleanup error: {e}");
+                    tracing::error!("Periodic scan error: {e}");
                 }
+                // Deleting expired trash counts as a move; skip it once shutdown started.
+                let Some(_guard) = cleanup_moves.begin() else {
+                    break;
+                };
+                maintenance::run_cleanup(&cleanup_pool, &cleanup_config, cleanup_dry_run.get())
+                    .await;
             }
         });
-    } else {
-        tracing::info!("Automatic cleanup disabled (cleanup_interval_hours = 0)");
     }
 
+    trash::queue::start_worker(
+        pool.clone(),
+        shared_config.clone(),
+        dry_run.clone(),
+        moves.clone(),
+    );
+
     let state = AppState {
-        pool,
-        config: Arc::new(config.clone()),
+        pool: pool.clone(),
+        config: shared_config,
         dry_run,
+        watcher_health,
+        scan_status,
+        tmdb,
+        moves: moves.clone(),
     };
 
-    let app =
-        rewinder::routes::build_router(state).nest_service("/static", ServeDir::new("static"));
-
-    let listener = tokio::net::TcpListener::bind(&config.listen_addr).await?;
-    tracing::info!("Listening on {}", config.listen_addr);
-    axum::serve(listener, app).await?;
-
-    Ok(())
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use rewinder::config::AppConfig;
-    use tempfile::tempdir;
-
-    fn test_config_with_media_dirs(media_dirs: Vec<std::path::PathBuf>) -> AppConfig {
-        AppConfig {
-            database_url: ":memory:".to_string(),
-            listen_addr: "127.0.0.1:0".to_string(),
-            media_dirs,
-            grace_period_days: 7,
-            cleanup_interval_hours: 1,
-            initial_admin_user: None,
-            tmdb_api_key: None,
-        }
-    }
-
-    #[test]
-    fn storage_validation_fails_for_nonexistent_media_dir() {
-        let base = tempdir().expect("failed to create tempdir");
-        let missing = base.path().join("does-not-exist");
-        let cfg = test_config_with_media_dirs(vec![missing]);
+    let app = rewinder::routes::build_router(state)
+        .nest_service(&rewinder::routes::url("/static"), ServeDir::new("static"));
 
-        let err = validate_storage_access(&cfg).expect_err("expected missing dir failure");
-        let msg = err.to_string();
-        assert!(
-            msg.contains("not a directory") || msg.contains("not readable"),
-            "unexpected error message: {msg}"
+    let mut servers = tokio::task::JoinSet::new();
+    for listener in rewinder::listen::bind_all(config.listen_addr.addrs()).await? {
+        tracing::info!(
+            "Listening on {}{}",
+            listener.local_addr()?,
+            rewinder::routes::base_path()
         );
+        let app = app.clone();
+        let server_moves = moves.clone();
+        servers.spawn(async move {
+            axum::serve(
+                listener,
+                app.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+            )
+            .with_graceful_shutdown(async move { server_moves.closed().await })
+            .await
+        });
     }
-
-    #[test]
-    fn storage_validation_fails_for_non_directory_media_path() {
-        let base = tempdir().expect("failed to create tempdir");
-        let file_path = base.path().join("not-a-directory");
-        std::fs::write(&file_path, "x").expect("failed to create file");
-        let cfg = test_config_with_media_dirs(vec![file_path]);
-
-        let err = validate_storage_access(&cfg).expect_err("expected non-directory failure");
-        assert!(err.to_string().contains("not a directory"));
+    tokio::select! {
+        Some(result) = servers.join_next() => return Ok(result??),
+        result = shutdown::signal() => result?,
     }
 
-    #[cfg(unix)]
-    #[test]
-    fn storage_validation_fails_for_unreadable_and_unwritable_directories() {
-        use std::os::unix::fs::PermissionsExt;
-
-        let unreadable = tempdir().expect("failed to create unreadable tempdir");
-        let unwritable = tempdir().expect("failed to create unwritable tempdir");
-
-        let unreadable_mode = std::fs::Permissions::from_mode(0o333);
-        let unwritable_mode = std::fs::Permissions::from_mode(0o555);
-        std::fs::set_permissions(unreadable.path(), unreadable_mode)
-            .expect("failed to chmod unreadable dir");
-        std::fs::set_permissions(unwritable.path(), unwritable_mode)
-            .expect("failed to chmod unwritable dir");
-
-        let read_err = ensure_dir_readable_and_writable(unreadable.path())
-            .expect_err("expected unreadable dir to fail");
-        let read_msg = read_err.to_string();
-
-        let write_err = ensure_dir_readable_and_writable(unwritable.path())
-            .expect_err("expected unwritable dir to fail");
-        let write_msg = write_err.to_string();
-
-        // Restore permissions so tempdir cleanup can remove directories.
-        std::fs::set_permissions(unreadable.path(), std::fs::Permissions::from_mode(0o755))
-            .expect("failed to restore unreadable dir perms");
-        std::fs::set_permissions(unwritable.path(), std::fs::Permissions::from_mode(0o755))
-            .expect("failed to restore unwritable dir perms");
-
-        assert!(
-            read_msg.contains("not readable"),
-            "unexpected unreadable error message: {read_msg}"
-        );
-        assert!(
-            write_msg.contains("not writable"),
-            "unexpected unwritable error message: {write_msg}"
+    // Stops the listener and turns away new state-changing requests.
+    moves.close();
+    if moves.active() > 0 {
+        tracing::info!("Waiting for {} in-flight moves", moves.active());
+    }
+    if !moves.wait_idle(shutdown::MOVE_TIMEOUT).await {
+        tracing::warn!(
+            "{} moves still running after {}s, exiting anyway; run `rewinder doctor` after restart",
+            moves.active(),
+            shutdown::MOVE_TIMEOUT.as_secs()
         );
     }
+    // Responses of finished requests may still be on their way out.
+    if tokio::time::timeout(std::time::Duration::from_secs(5), servers.join_all())
+        .await
+        .is_err()
+    {
+        tracing::warn!("Closing remaining connections");
+    }
+    pool.close().await;
+    tracing::info!("Shutdown complete");
+
+    Ok(())
 }
@@ -0,0 +1,109 @@
+use sqlx::SqlitePool;
+use std::time::Instant;
+
+use crate::auth::session;
+use crate::config::AppConfig;
+use crate::models::{db_maintenance, media, rerequest};
+use crate::{persistent, policies, rules, trash};
+
+/// Housekeeping that runs after every periodic scan: drop marks on gone media,
+/// close re-requests of media that came back, apply the configured rules and tag policies, trash items that were playing
+/// or seeding when their last mark came in, forget trash entries deleted by
+/// hand, warn users about trash deleted soon, empty expired trash, prune sessions and old deleted media, and keep
+/// the database tidy.
+///
+/// Each step logs its own failure so one broken step does not skip the rest.
+#[tracing::instrument(skip(pool, config))]
+pub async fn run_cleanup(pool: &SqlitePool, config: &AppConfig, dry_run: bool) {
+    match media::cleanup_gone_marks(pool).await {
+        Ok(n) if n > 0 => tracing::info!("Cleaned up {n} marks for gone media"),
+        Err(e) => tracing::error!("Mark cleanup error: {e}"),
+        _ => {}
+    }
+    match rerequest::cleanup_fulfilled(pool).await {
+        Ok(n) if n > 0 => tracing::info!("Closed {n} re-requests for media that came back"),
+        Err(e) => tracing::error!("Re-request cleanup error: {e}"),
+        _ => {}
+    }
+    match persistent::persist_new_seasons(pool, config, dry_run).await {
+        Ok(n) if n > 0 => tracing::info!("Persisted {n} new seasons of persisted shows"),
+        Err(e) => tracing::error!("Series persist error: {e}"),
+        _ => {}
+    }
+    match rules::apply(pool, config, dry_run).await {
+        Ok(n) if n > 0 => tracing::info!("Rules acted on {n} items"),
+        Err(e) => tracing::error!("Rule evaluation error: {e}"),
+        _ => {}
+    }
+    match policies::apply(pool, config, dry_run).await {
+        Ok(n) if n > 0 => tracing::info!("Tag policies marked {n} items"),
+        Err(e) => tracing::error!("Tag policy error: {e}"),
+        _ => {}
+    }
+    match trash::trash_deferred(pool, config, dry_run).await {
+        Ok(n) if n > 0 => tracing::info!("Trashed {n} deferred items"),
+        Err(e) => tracing::error!("Deferred trash error: {e}"),
+        _ => {}
+    }
+    if let Err(e) = trash::cleanup_missing_trash(pool, config).await {
+        tracing::error!("Missing trash cleanup error: {e}");
+    }
+    if let Err(e) = trash::warn_before_expiry(pool, config).await {
+        tracing::error!("Expiry warning error: {e}");
+    }
+    if let Err(e) = trash::cleanup_expired(pool, config, config.grace_period_days, dry_run).await {
+        tracing::error!("Trash cleanup error: {e}");
+    }
+    if let Err(e) = session::cleanup_expired(pool).await {
+        tracing::error!("Session cleanup error: {e}");
+    }
+    if config.deleted_retention_days > 0 {
+        match media::purge_deleted(pool, config.deleted_retention_days).await {
+            Ok(n) if n > 0 => tracing::info!("Forgot {n} media items deleted long ago"),
+            Err(e) => tracing::error!("Deleted media purge error: {e}"),
+            _ => {}
+        }
+    }
+    if let Err(e) = optimize_database(pool, config.db_vacuum_interval_days).await {
+        tracing::error!("Database maintenance error: {e}");
+    }
+}
+
+/// Refresh query planner statistics and, every `vacuum_interval_days`, rebuild
+/// the file to reclaim pages left behind by repeated scan upserts.
+#[tracing::instrument(skip(pool))]
+pub async fn optimize_database(
+    pool: &SqlitePool,
+    vacuum_interval_days: u64,
+) -> Result<(), sqlx::Error> {
+    let started = Instant::now();
+    sqlx::query("PRAGMA optimize").execute(pool).await?;
+    db_maintenance::record(pool, "optimize", elapsed_ms(started), 0).await?;
+
+    if vacuum_interval_days == 0
+        || !db_maintenance::is_due(pool, "vacuum", vacuum_interval_days).await?
+    {
+        return Ok(());
+    }
+    let size_before = database_size(pool).await?;
+    let started = Instant::now();
+    sqlx::query("VACUUM").execute(pool).await?;
+    sqlx::query("ANALYZE").execute(pool).await?;
+    let freed = (size_before - database_size(pool).await?).max(0);
+    db_maintenance::record(pool, "vacuum", elapsed_ms(started), freed).await?;
+    tracing::info!("Vacuumed database, freed {freed} bytes");
+    Ok(())
+}
+
+async fn database_size(pool: &SqlitePool) -> Result<i64, sqlx::Error> {
+    let (size,): (i64,) = sqlx::query_as(
+        "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()",
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(size)
+}
+
+fn elapsed_ms(started: Instant) -> i64 {
+    started.elapsed().as_millis() as i64
+}
@@ -0,0 +1,155 @@
+//! The deletion manifest: with a `deletion_manifest_dir` configured, every
+//! permanent deletion appends a line to that month's JSON Lines file, so it
+//! can be settled later what was deleted, how big it was and who wanted it
+//! gone. Admins download the months as JSONL or CSV from `/admin/manifest`.
+
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use crate::models::media::Media;
+
+/// One user's mark on a deleted item.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, PartialEq)]
+pub struct MarkedBy {
+    pub user: String,
+    pub marked_at: String,
+}
+
+/// One line of the manifest.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ManifestEntry {
+    pub deleted_at: String,
+    /// Who deleted it: an admin, or `cleanup` once the grace period ran out.
+    pub deleted_by: String,
+    pub media_id: i64,
+    pub media_type: String,
+    pub title: String,
+    pub year: Option<i64>,
+    pub season: Option<i64>,
+    pub path: String,
+    pub size_bytes: i64,
+    pub trashed_at: Option<String>,
+    pub marked_by: Vec<MarkedBy>,
+}
+
+/// A month with deletions, as listed on the manifest page.
+#[derive(Debug, Clone)]
+pub struct ManifestMonth {
+    /// "YYYY-MM".
+    pub month: String,
+    pub deletions: usize,
+    pub size_bytes: i64,
+}
+
+fn file_for(dir: &Path, month: &str) -> PathBuf {
+    dir.join(format!("deletions-{month}.jsonl"))
+}
+
+/// Whether `month` has the "YYYY-MM" form manifest files are named by.
+pub fn is_month(month: &str) -> bool {
+    let bytes = month.as_bytes();
+    bytes.len() == 7
+        && bytes[4] == b'-'
+        && bytes
+            .iter()
+            .enumerate()
+            .all(|(i, b)| i == 4 || b.is_ascii_digit())
+}
+
+/// Append the deletion of `item` by `actor` to this month's manifest.
+pub async fn record(
+    pool: &SqlitePool,
+    dir: &Path,
+    item: &Media,
+    actor: &str,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let marked_by = sqlx::query_as::<_, MarkedBy>(
+        "SELECT u.username AS user, mk.marked_at
+         FROM marks mk JOIN users u ON u.id = mk.user_id
+         WHERE mk.media_id = ?
+         ORDER BY mk.marked_at, u.username",
+    )
+    .bind(item.id)
+    .fetch_all(pool)
+    .await?;
+    let now = jiff::Timestamp::now();
+    let entry = ManifestEntry {
+        deleted_at: now.strftime("%Y-%m-%d %H:%M:%S").to_string(),
+        deleted_by: actor.to_string(),
+        media_id: item.id,
+        media_type: item.media_type.clone(),
+        title: item.title.clone(),
+        year: item.year,
+        season: item.season,
+        path: item.path.clone(),
+        size_bytes: item.size_bytes,
+        trashed_at: item.trashed_at.clone(),
+        marked_by,
+    };
+    let mut line = serde_json::to_string(&entry)?;
+    line.push('\n');
+    let path = file_for(dir, &now.strftime("%Y-%m").to_string());
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)?;
+    // One write per line, so concurrent deletions never interleave.
+    file.write_all(line.as_bytes())?;
+    Ok(())
+}
+
+/// Every deletion in the manifest for `month`, oldest first. Lines that do not
+/// parse, e.g. cut short by a crash, are skipped.
+pub fn read_month(dir: &Path, month: &str) -> std::io::Result<Vec<ManifestEntry>> {
+    let file = std::fs::File::open(file_for(dir, month))?;
+    let mut entries = Vec::new();
+    for line in std::io::BufReader::new(file).lines() {
+        if let Ok(entry) = serde_json::from_str(&line?) {
+            entries.push(entry);
+        }
+    }
+    Ok(entries)
+}
+
+/// The raw JSON Lines file for `month`.
+pub fn read_month_raw(dir: &Path, month: &str) -> std::io::Result<String> {
+    std::fs::read_to_string(file_for(dir, month))
+}
+
+/// The months with a manifest in `dir`, newest first.
+pub fn months(dir: &Path) -> std::io::Result<Vec<ManifestMonth>> {
+    let mut months = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let name = entry?.file_name().to_string_lossy().to_string();
+        let Some(month) = name
+            .strip_prefix("deletions-")
+            .and_then(|rest| rest.strip_suffix(".jsonl"))
+            .filter(|month| is_month(month))
+        else {
+            continue;
+        };
+        let entries = read_month(dir, month)?;
+        months.push(ManifestMonth {
+            month: month.to_string(),
+            deletions: entries.len(),
+            size_bytes: entries.iter().map(|e| e.size_bytes).sum(),
+        });
+    }
+    months.sort_by(|a, b| b.month.cmp(&a.month));
+    Ok(months)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn months_must_look_like_year_and_month() {
+        assert!(is_month("2024-05"));
+        assert!(!is_month("2024-5"));
+        assert!(!is_month("../../x"));
+        assert!(!is_month("2024_05"));
+    }
+}
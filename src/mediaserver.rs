@@ -0,0 +1,272 @@
+//! Optional link to the Plex or Jellyfin/Emby server that plays the library.
+//!
+//! Rewinder asks it which files are playing so a season is not moved to the
+//! trash under someone who is watching it, and can ask it to rescan the
+//! folders it moved files out of or into.
+
+use serde::Deserialize;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::config::AppConfig;
+
+/// Queries must not hold up a mark request for long when the server is down.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MediaServerKind {
+    Plex,
+    /// Also covers Emby, which shares the API Rewinder uses.
+    Jellyfin,
+}
+
+/// The `[media_server]` config table.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+pub struct MediaServerConfig {
+    pub kind: MediaServerKind,
+    /// Base URL, e.g. "http://plex:32400".
+    pub url: String,
+    /// Plex token or Jellyfin/Emby API key.
+    pub token: String,
+    /// Rescan the affected folders after trash, rescue, persist and delete so
+    /// the server drops dead entries right away.
+    #[serde(default)]
+    pub refresh_library: bool,
+}
+
+impl MediaServerConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.url.starts_with("http://") || self.url.starts_with("https://")) {
+            return Err(format!(
+                "url {:?} must start with http:// or https://",
+                self.url
+            ));
+        }
+        if self.token.trim().is_empty() {
+            return Err("token must not be empty".to_string());
+        }
+        Ok(())
+    }
+}
+
+pub struct MediaServer {
+    client: reqwest::Client,
+    config: MediaServerConfig,
+}
+
+impl MediaServer {
+    pub fn new(config: &MediaServerConfig) -> Self {
+        Self {
+            client: reqwest::Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+            config: config.clone(),
+        }
+    }
+
+    fn endpoint(&self, path: &str) -> String {
+        format!("{}{path}", self.config.url.trim_end_matches('/'))
+    }
+
+    /// Files currently being played by any client of the server.
+    pub async fn playing_paths(&self) -> Result<Vec<PathBuf>, reqwest::Error> {
+        let json: Value = match self.config.kind {
+            MediaServerKind::Plex => {
+                self.client
+                    .get(self.endpoint("/status/sessions"))
+                    .header("X-Plex-Token", &self.config.token)
+                    .header("Accept", "application/json")
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await?
+            }
+            MediaServerKind::Jellyfin => {
+                self.client
+                    .get(self.endpoint("/Sessions"))
+                    .header("X-Emby-Token", &self.config.token)
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await?
+            }
+        };
+        Ok(match self.config.kind {
+            MediaServerKind::Plex => plex_session_paths(&json),
+            MediaServerKind::Jellyfin => jellyfin_session_paths(&json),
+        })
+    }
+
+    /// Whether any playing file lies at or below `path`.
+    pub async fn is_playing(&self, path: &Path) -> Result<bool, reqwest::Error> {
+        Ok(self
+            .playing_paths()
+            .await?
+            .iter()
+            .any(|playing| playing.starts_with(path)))
+    }
+
+    /// Ask the server to rescan `folder`; folders outside its libraries are
+    /// ignored.
+    pub async fn refresh_folder(&self, folder: &Path) -> Result<(), reqwest::Error> {
+        match self.config.kind {
+            MediaServerKind::Plex => {
+                let sections: Value = self
+                    .client
+                    .get(self.endpoint("/library/sections"))
+                    .header("X-Plex-Token", &self.config.token)
+                    .header("Accept", "application/json")
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await?;
+                let Some(key) = plex_section_for(&sections, folder) else {
+                    return Ok(());
+                };
+                self.client
+                    .get(self.endpoint(&format!("/library/sections/{key}/refresh")))
+                    .header("X-Plex-Token", &self.config.token)
+                    .query(&[("path", folder.to_string_lossy())])
+                    .send()
+                    .await?
+                    .error_for_status()?;
+            }
+            MediaServerKind::Jellyfin => {
+                // Only libraries containing the folder rescan it.
+                let body = serde_json::json!({
+                    "Updates": [{"Path": folder.to_string_lossy(), "UpdateType": "Modified"}]
+                });
+                self.client
+                    .post(self.endpoint("/Library/Media/Updated"))
+                    .header("X-Emby-Token", &self.config.token)
+                    .json(&body)
+                    .send()
+                    .await?
+                    .error_for_status()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Rescan the folders holding `paths` in the background when the configured
+/// media server wants library refreshes.
+pub fn refresh_after_move(config: &AppConfig, paths: &[&Path]) {
+    let Some(server) = config
+        .media_server
+        .as_ref()
+        .filter(|server| server.refresh_library)
+    else {
+        return;
+    };
+    let mut folders: Vec<PathBuf> = paths
+        .iter()
+        .filter_map(|path| path.parent())
+        .map(Path::to_path_buf)
+        .collect();
+    folders.dedup();
+    let server = MediaServer::new(server);
+    tokio::spawn(async move {
+        for folder in folders {
+            if let Err(e) = server.refresh_folder(&folder).await {
+                tracing::warn!("Media server refresh of {} failed: {e}", folder.display());
+            }
+        }
+    });
+}
+
+/// Key of the Plex library section whose location is the longest prefix of
+/// `folder`.
+fn plex_section_for(json: &Value, folder: &Path) -> Option<String> {
+    json["MediaContainer"]["Directory"]
+        .as_array()?
+        .iter()
+        .filter_map(|section| {
+            let key = section["key"].as_str()?;
+            let depth = section["Location"]
+                .as_array()?
+                .iter()
+                .filter_map(|l| l["path"].as_str())
+                .map(Path::new)
+                .filter(|location| folder.starts_with(location))
+                .map(|location| location.components().count())
+                .max()?;
+            Some((depth, key))
+        })
+        .max_by_key(|(depth, _)| *depth)
+        .map(|(_, key)| key.to_string())
+}
+
+fn plex_session_paths(json: &Value) -> Vec<PathBuf> {
+    let Some(sessions) = json["MediaContainer"]["Metadata"].as_array() else {
+        return Vec::new();
+    };
+    sessions
+        .iter()
+        .filter_map(|s| s["Media"].as_array())
+        .flatten()
+        .filter_map(|m| m["Part"].as_array())
+        .flatten()
+        .filter_map(|p| p["file"].as_str())
+        .map(PathBuf::from)
+        .collect()
+}
+
+fn jellyfin_session_paths(json: &Value) -> Vec<PathBuf> {
+    let Some(sessions) = json.as_array() else {
+        return Vec::new();
+    };
+    sessions
+        .iter()
+        .filter_map(|s| s["NowPlayingItem"]["Path"].as_str())
+        .map(PathBuf::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_playing_files_from_both_session_formats() {
+        let plex = serde_json::json!({
+            "MediaContainer": {"Metadata": [
+                {"Media": [{"Part": [{"file": "/media/TV/Show/Season 1/e01.mkv"}]}]}
+            ]}
+        });
+        assert_eq!(
+            plex_session_paths(&plex),
+            vec![PathBuf::from("/media/TV/Show/Season 1/e01.mkv")]
+        );
+        assert!(plex_session_paths(&serde_json::json!({"MediaContainer": {"size": 0}})).is_empty());
+
+        let jellyfin = serde_json::json!([
+            {"NowPlayingItem": {"Path": "/media/Movies/Heat (1995)/heat.mkv"}},
+            {"UserName": "idle"}
+        ]);
+        assert_eq!(
+            jellyfin_session_paths(&jellyfin),
+            vec![PathBuf::from("/media/Movies/Heat (1995)/heat.mkv")]
+        );
+    }
+
+    #[test]
+    fn picks_the_plex_section_holding_a_folder() {
+        let sections = serde_json::json!({
+            "MediaContainer": {"Directory": [
+                {"key": "1", "Location": [{"path": "/media/Movies"}]},
+                {"key": "2", "Location": [{"path": "/media/TV"}, {"path": "/media/TV_permanent"}]}
+            ]}
+        });
+        let section = |folder: &str| plex_section_for(&sections, Path::new(folder));
+        assert_eq!(section("/media/TV_permanent/Show").as_deref(), Some("2"));
+        assert_eq!(section("/media/Movies").as_deref(), Some("1"));
+        assert_eq!(section("/media/Movies_trash"), None);
+    }
+}
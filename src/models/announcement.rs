@@ -0,0 +1,73 @@
+use sqlx::SqlitePool;
+
+/// Longest announcement admins can post; it has to fit in a banner.
+pub const MAX_LEN: usize = 280;
+
+/// A message admins show at the top of every page.
+#[derive(Debug, sqlx::FromRow, Clone)]
+pub struct Announcement {
+    pub id: i64,
+    pub message: String,
+    pub created_by: String,
+    pub created_at: String,
+}
+
+pub async fn create(
+    pool: &SqlitePool,
+    message: &str,
+    created_by: &str,
+) -> Result<i64, sqlx::Error> {
+    let result = sqlx::query("INSERT INTO announcements (message, created_by) VALUES (?, ?)")
+        .bind(message)
+        .bind(created_by)
+        .execute(pool)
+        .await?;
+    Ok(result.last_insert_rowid())
+}
+
+/// Take an announcement down for everyone.
+pub async fn delete(pool: &SqlitePool, id: i64) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM announcements WHERE id = ?")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// All announcements, newest first.
+pub async fn list(pool: &SqlitePool) -> Result<Vec<Announcement>, sqlx::Error> {
+    sqlx::query_as("SELECT * FROM announcements ORDER BY id DESC")
+        .fetch_all(pool)
+        .await
+}
+
+/// Announcements `user_id` has not dismissed yet, newest first.
+pub async fn undismissed(
+    pool: &SqlitePool,
+    user_id: i64,
+) -> Result<Vec<Announcement>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT * FROM announcements a
+         WHERE NOT EXISTS (
+           SELECT 1 FROM announcement_dismissals d
+           WHERE d.announcement_id = a.id AND d.user_id = ?
+         )
+         ORDER BY a.id DESC",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// Hide `id` from `user_id` from now on.
+pub async fn dismiss(pool: &SqlitePool, id: i64, user_id: i64) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT OR IGNORE INTO announcement_dismissals (announcement_id, user_id)
+         SELECT id, ? FROM announcements WHERE id = ?",
+    )
+    .bind(user_id)
+    .bind(id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,143 @@
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+
+/// Longest comment accepted, in characters.
+pub const MAX_COMMENT_LEN: usize = 500;
+
+/// How many of the latest comments a card shows on hover.
+const PREVIEW_LEN: i64 = 3;
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct Comment {
+    pub id: i64,
+    pub media_id: i64,
+    pub user_id: i64,
+    pub username: String,
+    pub body: String,
+    pub created_at: String,
+}
+
+/// The comment as stored, or why it cannot be one.
+pub fn normalize(body: &str) -> Result<String, String> {
+    let body = body.trim();
+    if body.is_empty() {
+        return Err("comment must not be empty".to_string());
+    }
+    if body.chars().count() > MAX_COMMENT_LEN {
+        return Err(format!(
+            "comment must be at most {MAX_COMMENT_LEN} characters"
+        ));
+    }
+    Ok(body.to_string())
+}
+
+pub async fn add(
+    pool: &SqlitePool,
+    media_id: i64,
+    user_id: i64,
+    body: &str,
+) -> Result<i64, sqlx::Error> {
+    let result = sqlx::query("INSERT INTO comments (media_id, user_id, body) VALUES (?, ?, ?)")
+        .bind(media_id)
+        .bind(user_id)
+        .bind(body)
+        .execute(pool)
+        .await?;
+    Ok(result.last_insert_rowid())
+}
+
+pub async fn get(pool: &SqlitePool, id: i64) -> Result<Option<Comment>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT c.id, c.media_id, c.user_id, u.username, c.body, c.created_at
+         FROM comments c JOIN users u ON u.id = c.user_id
+         WHERE c.id = ?",
+    )
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+}
+
+pub async fn delete(pool: &SqlitePool, id: i64) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM comments WHERE id = ?")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// The thread of one item, oldest first.
+pub async fn for_media(pool: &SqlitePool, media_id: i64) -> Result<Vec<Comment>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT c.id, c.media_id, c.user_id, u.username, c.body, c.created_at
+         FROM comments c JOIN users u ON u.id = c.user_id
+         WHERE c.media_id = ?
+         ORDER BY c.id",
+    )
+    .bind(media_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// What a card shows of an item's thread.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CommentPreview {
+    pub count: i64,
+    /// The latest few comments as "user: text", oldest first.
+    pub recent: Vec<String>,
+}
+
+impl CommentPreview {
+    /// The tooltip of the card's comment count.
+    pub fn hover_text(&self) -> String {
+        let mut text = self.recent.join("\n");
+        let older = self.count - self.recent.len() as i64;
+        if older > 0 {
+            text.insert_str(0, &format!("… {older} earlier\n"));
+        }
+        text
+    }
+}
+
+/// Previews of every commented item of `media_type`.
+pub async fn previews(
+    pool: &SqlitePool,
+    media_type: &str,
+) -> Result<HashMap<i64, CommentPreview>, sqlx::Error> {
+    let rows: Vec<(i64, i64, String, String)> = sqlx::query_as(
+        "SELECT media_id, total, username, body FROM (
+             SELECT c.media_id, c.id, u.username, c.body,
+                    COUNT(*) OVER (PARTITION BY c.media_id) AS total,
+                    ROW_NUMBER() OVER (PARTITION BY c.media_id ORDER BY c.id DESC) AS n
+             FROM comments c
+             JOIN users u ON u.id = c.user_id
+             JOIN media m ON m.id = c.media_id
+             WHERE m.media_type = ?
+         )
+         WHERE n <= ?
+         ORDER BY media_id, id",
+    )
+    .bind(media_type)
+    .bind(PREVIEW_LEN)
+    .fetch_all(pool)
+    .await?;
+    let mut previews: HashMap<i64, CommentPreview> = HashMap::new();
+    for (media_id, total, username, body) in rows {
+        let preview = previews.entry(media_id).or_default();
+        preview.count = total;
+        preview.recent.push(format!("{username}: {body}"));
+    }
+    Ok(previews)
+}
+
+/// The preview of one item, for re-rendering a single card.
+pub async fn preview_for(pool: &SqlitePool, media_id: i64) -> Result<CommentPreview, sqlx::Error> {
+    let comments = for_media(pool, media_id).await?;
+    let skip = comments.len().saturating_sub(PREVIEW_LEN as usize);
+    Ok(CommentPreview {
+        count: comments.len() as i64,
+        recent: comments[skip..]
+            .iter()
+            .map(|c| format!("{}: {}", c.username, c.body))
+            .collect(),
+    })
+}
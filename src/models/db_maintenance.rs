@@ -0,0 +1,54 @@
+use sqlx::SqlitePool;
+
+/// The most recent run of a database maintenance task.
+#[derive(Debug, sqlx::FromRow, Clone)]
+pub struct DbMaintenanceRun {
+    pub task: String,
+    pub last_run: String,
+    pub duration_ms: i64,
+    pub freed_bytes: i64,
+}
+
+pub async fn record(
+    pool: &SqlitePool,
+    task: &str,
+    duration_ms: i64,
+    freed_bytes: i64,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO db_maintenance (task, last_run, duration_ms, freed_bytes)
+         VALUES (?, datetime('now'), ?, ?)
+         ON CONFLICT(task) DO UPDATE SET
+           last_run = excluded.last_run,
+           duration_ms = excluded.duration_ms,
+           freed_bytes = excluded.freed_bytes",
+    )
+    .bind(task)
+    .bind(duration_ms)
+    .bind(freed_bytes)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn list(pool: &SqlitePool) -> Result<Vec<DbMaintenanceRun>, sqlx::Error> {
+    sqlx::query_as::<_, DbMaintenanceRun>("SELECT * FROM db_maintenance ORDER BY task")
+        .fetch_all(pool)
+        .await
+}
+
+/// Whether `task` never ran or last ran more than `interval_days` ago.
+pub async fn is_due(
+    pool: &SqlitePool,
+    task: &str,
+    interval_days: u64,
+) -> Result<bool, sqlx::Error> {
+    let row: Option<(bool,)> = sqlx::query_as(
+        "SELECT last_run <= datetime('now', ? || ' days') FROM db_maintenance WHERE task = ?",
+    )
+    .bind(-(interval_days as i64))
+    .bind(task)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.is_none_or(|(due,)| due))
+}
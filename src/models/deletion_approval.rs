@@ -0,0 +1,85 @@
+use sqlx::SqlitePool;
+
+/// Distinct admins who must approve a large permanent deletion.
+pub const REQUIRED_APPROVALS: i64 = 2;
+
+/// A large trashed item waiting for admins to approve deleting it.
+#[derive(Debug, sqlx::FromRow, Clone)]
+pub struct PendingDeletion {
+    pub media_id: i64,
+    pub title: String,
+    pub season: Option<i64>,
+    pub size_bytes: i64,
+    pub requested_by: String,
+    pub requested_at: String,
+    /// Comma-separated usernames of the admins who approved so far.
+    pub approved_by: Option<String>,
+}
+
+/// Queue `media_id` for approval; a request already queued keeps its origin.
+pub async fn request(
+    pool: &SqlitePool,
+    media_id: i64,
+    requested_by: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("INSERT OR IGNORE INTO pending_deletions (media_id, requested_by) VALUES (?, ?)")
+        .bind(media_id)
+        .bind(requested_by)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Record `user_id`'s approval and return how many admins approved so far.
+pub async fn approve(pool: &SqlitePool, media_id: i64, user_id: i64) -> Result<i64, sqlx::Error> {
+    sqlx::query("INSERT OR IGNORE INTO deletion_approvals (media_id, user_id) VALUES (?, ?)")
+        .bind(media_id)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    approval_count(pool, media_id).await
+}
+
+pub async fn approval_count(pool: &SqlitePool, media_id: i64) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar("SELECT COUNT(*) FROM deletion_approvals WHERE media_id = ?")
+        .bind(media_id)
+        .fetch_one(pool)
+        .await
+}
+
+/// Ids of the items `user_id` already approved.
+pub async fn approved_by(pool: &SqlitePool, user_id: i64) -> Result<Vec<i64>, sqlx::Error> {
+    sqlx::query_scalar("SELECT media_id FROM deletion_approvals WHERE user_id = ?")
+        .bind(user_id)
+        .fetch_all(pool)
+        .await
+}
+
+/// Forget the request and its approvals, once the item is deleted or rescued.
+pub async fn clear(pool: &SqlitePool, media_id: i64) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM deletion_approvals WHERE media_id = ?")
+        .bind(media_id)
+        .execute(pool)
+        .await?;
+    sqlx::query("DELETE FROM pending_deletions WHERE media_id = ?")
+        .bind(media_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Requests for items still in the trash, oldest first.
+pub async fn list_pending(pool: &SqlitePool) -> Result<Vec<PendingDeletion>, sqlx::Error> {
+    sqlx::query_as::<_, PendingDeletion>(
+        "SELECT p.media_id, m.title, m.season, m.size_bytes, p.requested_by, p.requested_at,
+                (SELECT group_concat(u.username, ', ')
+                 FROM deletion_approvals a JOIN users u ON u.id = a.user_id
+                 WHERE a.media_id = p.media_id) AS approved_by
+         FROM pending_deletions p
+         JOIN media m ON m.id = p.media_id
+         WHERE m.status = 'trashed'
+         ORDER BY p.requested_at, p.media_id",
+    )
+    .fetch_all(pool)
+    .await
+}
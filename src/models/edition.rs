@@ -0,0 +1,107 @@
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+
+use crate::models::media::Media;
+
+/// Link `media_id` and `other_id` as editions of one title, e.g. a 1080p and
+/// a 4K copy of a movie. Either one's existing editions join the group too.
+pub async fn link(pool: &SqlitePool, media_id: i64, other_id: i64) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let groups: Vec<(i64,)> = sqlx::query_as(
+        "SELECT DISTINCT group_id FROM media_editions WHERE media_id IN (?, ?) ORDER BY group_id",
+    )
+    .bind(media_id)
+    .bind(other_id)
+    .fetch_all(&mut *tx)
+    .await?;
+    let group_id = groups.first().map_or(other_id.min(media_id), |g| g.0);
+    for (old,) in &groups {
+        sqlx::query("UPDATE media_editions SET group_id = ? WHERE group_id = ?")
+            .bind(group_id)
+            .bind(old)
+            .execute(&mut *tx)
+            .await?;
+    }
+    for id in [media_id, other_id] {
+        sqlx::query("INSERT OR IGNORE INTO media_editions (media_id, group_id) VALUES (?, ?)")
+            .bind(id)
+            .bind(group_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await
+}
+
+/// Take `media_id` out of its group; a group left with one edition goes away.
+pub async fn unlink(pool: &SqlitePool, media_id: i64) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM media_editions WHERE media_id = ?")
+        .bind(media_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(
+        "DELETE FROM media_editions
+         WHERE group_id IN (SELECT group_id FROM media_editions
+                            GROUP BY group_id HAVING COUNT(*) < 2)",
+    )
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await
+}
+
+/// Every edition linked with `media_id`, itself included, largest first.
+/// Empty if it is not linked.
+pub async fn editions_of(pool: &SqlitePool, media_id: i64) -> Result<Vec<Media>, sqlx::Error> {
+    sqlx::query_as::<_, Media>(
+        "SELECT m.* FROM media m
+         JOIN media_editions e ON e.media_id = m.id
+         WHERE e.group_id = (SELECT group_id FROM media_editions WHERE media_id = ?)
+         ORDER BY m.size_bytes DESC, m.id",
+    )
+    .bind(media_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// How many other editions are linked with `media_id`.
+pub async fn others(pool: &SqlitePool, media_id: i64) -> Result<i64, sqlx::Error> {
+    let (count,): (i64,) = sqlx::query_as(
+        "SELECT MAX(COUNT(*) - 1, 0) FROM media_editions
+         WHERE group_id = (SELECT group_id FROM media_editions WHERE media_id = ?)",
+    )
+    .bind(media_id)
+    .fetch_one(pool)
+    .await?;
+    Ok(count)
+}
+
+/// How many other editions each linked item has.
+pub async fn others_by_media(pool: &SqlitePool) -> Result<HashMap<i64, i64>, sqlx::Error> {
+    let rows: Vec<(i64, i64)> = sqlx::query_as(
+        "SELECT e.media_id,
+                (SELECT COUNT(*) FROM media_editions o WHERE o.group_id = e.group_id) - 1
+         FROM media_editions e",
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().collect())
+}
+
+/// Movies that could be linked as editions of `media_id`: the same title,
+/// still in the library and not linked with it yet.
+pub async fn candidates(pool: &SqlitePool, media_id: i64) -> Result<Vec<Media>, sqlx::Error> {
+    sqlx::query_as::<_, Media>(
+        "SELECT o.* FROM media m
+         JOIN media o ON o.media_type = m.media_type AND o.title = m.title COLLATE NOCASE
+                     AND o.id != m.id
+         WHERE m.id = ?1 AND m.media_type = 'movie'
+           AND o.status IN ('active', 'permanent')
+           AND o.id NOT IN (SELECT media_id FROM media_editions
+                            WHERE group_id = (SELECT group_id FROM media_editions
+                                              WHERE media_id = ?1))
+         ORDER BY o.year, o.path",
+    )
+    .bind(media_id)
+    .fetch_all(pool)
+    .await
+}
@@ -0,0 +1,64 @@
+use sqlx::SqlitePool;
+
+use super::library_access::may_access;
+
+/// Notice to a user that a trashed item they did not mark is about to be
+/// deleted for good.
+#[derive(Debug, sqlx::FromRow, Clone)]
+pub struct ExpiryWarning {
+    pub media_id: i64,
+    pub title: String,
+    pub season: Option<i64>,
+    pub deletes_at: String,
+}
+
+/// Warn every user who may see trashed `media_id` but did not mark it that
+/// it is deleted at `deletes_at`. Returns how many users were newly warned.
+pub async fn warn_non_markers(
+    pool: &SqlitePool,
+    media_id: i64,
+    deletes_at: &str,
+) -> Result<u64, sqlx::Error> {
+    let query = format!(
+        "INSERT OR IGNORE INTO expiry_warnings (media_id, user_id, trashed_at, deletes_at)
+         SELECT m.id, u.id, m.trashed_at, ?2
+         FROM media m, users u
+         WHERE m.id = ?1 AND m.status = 'trashed' AND m.trashed_at IS NOT NULL
+           AND NOT EXISTS (SELECT 1 FROM marks WHERE media_id = m.id AND user_id = u.id)
+           AND {}",
+        may_access("u.id", "m")
+    );
+    let result = sqlx::query(&query)
+        .bind(media_id)
+        .bind(deletes_at)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+/// Warnings for `user_id` about items still in the trash, soonest first.
+pub async fn undismissed(
+    pool: &SqlitePool,
+    user_id: i64,
+) -> Result<Vec<ExpiryWarning>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT w.media_id, m.title, m.season, w.deletes_at
+         FROM expiry_warnings w
+         JOIN media m ON m.id = w.media_id
+         WHERE w.user_id = ? AND w.dismissed = 0
+           AND m.status = 'trashed' AND m.trashed_at = w.trashed_at
+         ORDER BY w.deletes_at, m.title",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn dismiss(pool: &SqlitePool, media_id: i64, user_id: i64) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE expiry_warnings SET dismissed = 1 WHERE media_id = ? AND user_id = ?")
+        .bind(media_id)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,63 @@
+use sqlx::SqlitePool;
+use std::collections::HashSet;
+
+/// Vote to keep `media_id` for now; replaces the user's mark on it.
+pub async fn keep(pool: &SqlitePool, user_id: i64, media_id: i64) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM marks WHERE user_id = ? AND media_id = ?")
+        .bind(user_id)
+        .bind(media_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(
+        "INSERT INTO keep_votes (user_id, media_id) VALUES (?, ?)
+         ON CONFLICT (user_id, media_id) DO UPDATE SET created_at = datetime('now')",
+    )
+    .bind(user_id)
+    .bind(media_id)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await
+}
+
+pub async fn unkeep(pool: &SqlitePool, user_id: i64, media_id: i64) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM keep_votes WHERE user_id = ? AND media_id = ?")
+        .bind(user_id)
+        .bind(media_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Whether a voter cast a keep vote on `media_id` within the last `days`.
+/// Restricted users' votes are only advisory, like their marks.
+pub async fn is_kept(pool: &SqlitePool, media_id: i64, days: u64) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT EXISTS (
+           SELECT 1 FROM keep_votes k JOIN users u ON u.id = k.user_id
+           WHERE k.media_id = ? AND u.restricted = 0
+             AND k.created_at > datetime('now', ? || ' days')
+         )",
+    )
+    .bind(media_id)
+    .bind(-(days as i64))
+    .fetch_one(pool)
+    .await
+}
+
+/// Items `user_id` voted to keep within the last `days`.
+pub async fn kept_by(
+    pool: &SqlitePool,
+    user_id: i64,
+    days: u64,
+) -> Result<HashSet<i64>, sqlx::Error> {
+    let rows: Vec<(i64,)> = sqlx::query_as(
+        "SELECT media_id FROM keep_votes
+         WHERE user_id = ? AND created_at > datetime('now', ? || ' days')",
+    )
+    .bind(user_id)
+    .bind(-(days as i64))
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().map(|r| r.0).collect())
+}
@@ -0,0 +1,96 @@
+//! Libraries restricted to some users. A media dir without grants is open to
+//! everyone; once it has any, only the granted users see its items, vote on
+//! them and count towards their quorum.
+
+use sqlx::SqlitePool;
+use std::collections::BTreeMap;
+
+/// SQL condition that the user with id `user` may access the media item
+/// aliased `media`, for embedding in queries.
+pub fn may_access(user: &str, media: &str) -> String {
+    format!(
+        "(NOT EXISTS (SELECT 1 FROM library_access la
+                      WHERE substr({media}.path, 1, length(la.media_dir) + 1) = la.media_dir || '/')
+          OR EXISTS (SELECT 1 FROM library_access la
+                     WHERE la.user_id = {user}
+                       AND substr({media}.path, 1, length(la.media_dir) + 1) = la.media_dir || '/'))"
+    )
+}
+
+pub async fn grant(pool: &SqlitePool, media_dir: &str, user_id: i64) -> Result<(), sqlx::Error> {
+    sqlx::query("INSERT OR IGNORE INTO library_access (media_dir, user_id) VALUES (?, ?)")
+        .bind(media_dir)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+pub async fn revoke(pool: &SqlitePool, media_dir: &str, user_id: i64) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM library_access WHERE media_dir = ? AND user_id = ?")
+        .bind(media_dir)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Move the grants of `from` over to `to`, the same library under another
+/// spelling; grants `to` already has are kept once.
+pub async fn rename_dir(pool: &SqlitePool, from: &str, to: &str) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("UPDATE OR IGNORE library_access SET media_dir = ? WHERE media_dir = ?")
+        .bind(to)
+        .bind(from)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM library_access WHERE media_dir = ?")
+        .bind(from)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await
+}
+
+/// Granted user ids per restricted media dir.
+pub async fn grants(pool: &SqlitePool) -> Result<BTreeMap<String, Vec<i64>>, sqlx::Error> {
+    let rows: Vec<(String, i64)> =
+        sqlx::query_as("SELECT media_dir, user_id FROM library_access ORDER BY media_dir, user_id")
+            .fetch_all(pool)
+            .await?;
+    let mut grants: BTreeMap<String, Vec<i64>> = BTreeMap::new();
+    for (media_dir, user_id) in rows {
+        grants.entry(media_dir).or_default().push(user_id);
+    }
+    Ok(grants)
+}
+
+/// Whether `user_id` may see and vote on `media_id`.
+pub async fn can_access(
+    pool: &SqlitePool,
+    user_id: i64,
+    media_id: i64,
+) -> Result<bool, sqlx::Error> {
+    let query = format!(
+        "SELECT EXISTS (SELECT 1 FROM media m WHERE m.id = ? AND {})",
+        may_access("?", "m")
+    );
+    sqlx::query_scalar(&query)
+        .bind(media_id)
+        .bind(user_id)
+        .fetch_one(pool)
+        .await
+}
+
+/// How many users vote on `media_id`: everyone, or the granted users of its
+/// restricted library, leaving out restricted users.
+pub async fn voter_count(pool: &SqlitePool, media_id: i64) -> Result<i64, sqlx::Error> {
+    let query = format!(
+        "SELECT COUNT(*) FROM users u, media m
+         WHERE m.id = ? AND u.restricted = 0 AND {}",
+        may_access("u.id", "m")
+    );
+    sqlx::query_scalar(&query)
+        .bind(media_id)
+        .fetch_one(pool)
+        .await
+}
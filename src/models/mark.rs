@@ -1,8 +1,48 @@
 use sqlx::SqlitePool;
 
+use crate::models::library_access;
+
+/// Mark `media_id` as done for `user_id`; replaces the user's keep vote on it.
 pub async fn mark(pool: &SqlitePool, user_id: i64, media_id: i64) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM keep_votes WHERE user_id = ? AND media_id = ?")
+        .bind(user_id)
+        .bind(media_id)
+        .execute(&mut *tx)
+        .await?;
     sqlx::query("INSERT OR IGNORE INTO marks (user_id, media_id) VALUES (?, ?)")
         .bind(user_id)
+        .bind(media_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await
+}
+
+/// Mark every item in `media_ids` for `user_id`, all or none.
+pub async fn mark_all(
+    pool: &SqlitePool,
+    user_id: i64,
+    media_ids: &[i64],
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    for &media_id in media_ids {
+        sqlx::query("DELETE FROM keep_votes WHERE user_id = ? AND media_id = ?")
+            .bind(user_id)
+            .bind(media_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("INSERT OR IGNORE INTO marks (user_id, media_id) VALUES (?, ?)")
+            .bind(user_id)
+            .bind(media_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await
+}
+
+/// Mark `media_id` on behalf of every user.
+pub async fn mark_for_all(pool: &SqlitePool, media_id: i64) -> Result<(), sqlx::Error> {
+    sqlx::query("INSERT OR IGNORE INTO marks (user_id, media_id) SELECT id, ? FROM users")
         .bind(media_id)
         .execute(pool)
         .await?;
@@ -18,23 +58,47 @@ pub async fn unmark(pool: &SqlitePool, user_id: i64, media_id: i64) -> Result<()
     Ok(())
 }
 
+/// Marks on `media_id` that count towards its quorum; restricted users' do not.
 pub async fn mark_count(pool: &SqlitePool, media_id: i64) -> Result<i64, sqlx::Error> {
-    let row: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM marks WHERE media_id = ?")
-        .bind(media_id)
-        .fetch_one(pool)
-        .await?;
-    Ok(row.0)
-}
-
-pub async fn all_users_marked(pool: &SqlitePool, media_id: i64) -> Result<bool, sqlx::Error> {
     let row: (i64,) = sqlx::query_as(
-        "SELECT COUNT(*) FROM users
-         WHERE id NOT IN (SELECT user_id FROM marks WHERE media_id = ?)",
+        "SELECT COUNT(*) FROM marks k JOIN users u ON u.id = k.user_id
+         WHERE k.media_id = ? AND u.restricted = 0",
     )
     .bind(media_id)
     .fetch_one(pool)
     .await?;
-    Ok(row.0 == 0)
+    Ok(row.0)
+}
+
+/// SQL condition that an admin marked the media row `media`.
+fn admin_marked(media: &str) -> String {
+    format!(
+        "EXISTS (SELECT 1 FROM marks am JOIN users a ON a.id = am.user_id
+                 WHERE am.media_id = {media}.id AND a.is_admin = 1)"
+    )
+}
+
+/// Whether everyone who votes on `media_id` marked it. Restricted users do
+/// not vote, so their marks alone never trash anything. With `require_admin`
+/// an admin must have marked it as well, whether or not they vote on it.
+pub async fn all_users_marked(
+    pool: &SqlitePool,
+    media_id: i64,
+    require_admin: bool,
+) -> Result<bool, sqlx::Error> {
+    let query = format!(
+        "SELECT COUNT(*), COALESCE(SUM(u.id NOT IN (SELECT user_id FROM marks WHERE media_id = ?1)), 0),
+                (SELECT {} FROM media m WHERE m.id = ?1)
+         FROM users u, media m
+         WHERE m.id = ?1 AND u.restricted = 0 AND {}",
+        admin_marked("m"),
+        library_access::may_access("u.id", "m")
+    );
+    let (voters, unmarked, by_admin): (i64, i64, bool) = sqlx::query_as(&query)
+        .bind(media_id)
+        .fetch_one(pool)
+        .await?;
+    Ok(voters > 0 && unmarked == 0 && (by_admin || !require_admin))
 }
 
 pub async fn clear_marks(pool: &SqlitePool, media_id: i64) -> Result<(), sqlx::Error> {
@@ -54,17 +118,30 @@ pub async fn user_marks(pool: &SqlitePool, user_id: i64) -> Result<Vec<i64>, sql
     Ok(rows.into_iter().map(|r| r.0).collect())
 }
 
-/// After a user is deleted, check all media for auto-trash eligibility
-pub async fn media_ids_with_all_marked(pool: &SqlitePool) -> Result<Vec<i64>, sqlx::Error> {
-    let rows: Vec<(i64,)> = sqlx::query_as(
+/// Active items everyone who votes on them marked, e.g. after a user was
+/// deleted or lost access to a library. See [`all_users_marked`] for
+/// `require_admin`.
+pub async fn media_ids_with_all_marked(
+    pool: &SqlitePool,
+    require_admin: bool,
+) -> Result<Vec<i64>, sqlx::Error> {
+    let voter = library_access::may_access("u.id", "m");
+    let admin = if require_admin {
+        format!("AND {}", admin_marked("m"))
+    } else {
+        String::new()
+    };
+    let query = format!(
         "SELECT m.id FROM media m
-         WHERE m.status = 'active'
+         WHERE m.status = 'active' AND m.protected = 0
+         AND EXISTS (SELECT 1 FROM users u WHERE u.restricted = 0 AND {voter})
          AND NOT EXISTS (
              SELECT 1 FROM users u
              WHERE u.id NOT IN (SELECT mk.user_id FROM marks mk WHERE mk.media_id = m.id)
-         )",
-    )
-    .fetch_all(pool)
-    .await?;
+               AND u.restricted = 0 AND {voter}
+         )
+         {admin}"
+    );
+    let rows: Vec<(i64,)> = sqlx::query_as(&query).fetch_all(pool).await?;
     Ok(rows.into_iter().map(|r| r.0).collect())
 }
@@ -1,4 +1,10 @@
-use sqlx::SqlitePool;
+use sqlx::{Connection, SqliteConnection, SqliteExecutor, SqlitePool};
+
+use crate::models::{library_access, media_history};
+
+/// SQL condition for items that are not gone. Gone items pile up over the
+/// years, and unlike `status != 'gone'` this can use the status index.
+const NOT_GONE: &str = "status IN ('active', 'trashed', 'permanent', 'archived')";
 
 #[allow(dead_code)] // fields used by sqlx::FromRow deserialization
 #[derive(Debug, sqlx::FromRow, Clone)]
@@ -15,15 +21,41 @@ pub struct Media {
     pub first_seen: String,
     pub last_seen: String,
     pub poster_path: Option<String>,
+    pub dir_mtime: Option<i64>,
+    /// The size is still being measured in the background; `size_bytes` is stale.
+    pub size_pending: bool,
+    /// Pinned by an admin: never marked, trashed or acted on by rules.
+    pub protected: bool,
+    /// File name in the trash, unique per trash operation; only read for
+    /// items trashed before `trash_path` was recorded.
+    pub trash_name: Option<String>,
+    /// Where the item was moved in the trash, so rescue and cleanup still find
+    /// it after `media_dirs` change. `path` keeps the original location.
+    pub trash_path: Option<String>,
+    /// Added or corrected by an admin: scans keep it while its path exists and
+    /// leave its metadata alone.
+    pub manual: bool,
+    /// Where expired trash was moved in the `archive_dir`, while archived.
+    pub archive_path: Option<String>,
+    /// Object key of the copy uploaded to the `[cloud_archive]` before the
+    /// item was deleted.
+    pub cloud_key: Option<String>,
+    /// Blurhash of the poster, shown blurred while the image loads.
+    pub poster_hash: Option<String>,
 }
 
+/// Active items of `media_type` in libraries open to everyone.
 pub async fn list_by_type(pool: &SqlitePool, media_type: &str) -> Result<Vec<Media>, sqlx::Error> {
-    sqlx::query_as::<_, Media>(
-        "SELECT * FROM media WHERE media_type = ? AND status = 'active' ORDER BY title, season",
-    )
-    .bind(media_type)
-    .fetch_all(pool)
-    .await
+    // No user id matches NULL, so only unrestricted libraries pass.
+    let query = format!(
+        "SELECT * FROM media m WHERE m.media_type = ? AND m.status = 'active' AND {}
+         ORDER BY m.title, m.season",
+        library_access::may_access("NULL", "m")
+    );
+    sqlx::query_as::<_, Media>(&query)
+        .bind(media_type)
+        .fetch_all(pool)
+        .await
 }
 
 pub async fn list_visible_for_user(
@@ -31,21 +63,76 @@ pub async fn list_visible_for_user(
     media_type: &str,
     user_id: i64,
 ) -> Result<Vec<Media>, sqlx::Error> {
-    sqlx::query_as::<_, Media>(
+    let query = format!(
         "SELECT m.*
          FROM media m
          LEFT JOIN persistent_media pm ON pm.media_id = m.id
-         WHERE m.media_type = ?
-           AND (
-                m.status = 'active'
-                OR (m.status = 'permanent' AND pm.user_id = ?)
-           )
+         WHERE m.status IN ('active', 'permanent') AND m.media_type = ?1
+           AND (m.status = 'active' OR pm.user_id = ?2)
+           AND {}
          ORDER BY m.title, m.season",
-    )
-    .bind(media_type)
-    .bind(user_id)
-    .fetch_all(pool)
-    .await
+        library_access::may_access("?2", "m")
+    );
+    sqlx::query_as::<_, Media>(&query)
+        .bind(media_type)
+        .bind(user_id)
+        .fetch_all(pool)
+        .await
+}
+
+/// An item of the Movies or TV page, with the votes on it and what the
+/// viewing user did to it.
+#[derive(Debug, sqlx::FromRow)]
+pub struct ListRow {
+    #[sqlx(flatten)]
+    pub media: Media,
+    /// Marks that count towards the quorum; restricted users' do not.
+    pub mark_count: i64,
+    /// Users who vote on the item.
+    pub total_users: i64,
+    /// The user marked it, and it is not persisted.
+    pub marked: bool,
+    pub persisted_by_me: bool,
+    /// The user voted to keep it within the last `keep_vote_days`.
+    pub kept: bool,
+}
+
+/// What the Movies or TV page shows of every item [`list_visible_for_user`]
+/// returns, in a single query. Mark and voter counts come along with the
+/// rows, so there is nothing left for a separate count cache to save.
+pub async fn list_page_rows(
+    pool: &SqlitePool,
+    media_type: &str,
+    user_id: i64,
+    keep_vote_days: u64,
+) -> Result<Vec<ListRow>, sqlx::Error> {
+    let query = format!(
+        "SELECT m.*,
+                COALESCE(SUM(u.restricted = 0), 0) AS mark_count,
+                (SELECT COUNT(*) FROM users v WHERE v.restricted = 0 AND {}) AS total_users,
+                m.status != 'permanent' AND COALESCE(MAX(k.user_id = ?2), 0) AS marked,
+                COALESCE(pm.user_id = ?2, 0) AS persisted_by_me,
+                kv.user_id IS NOT NULL AS kept
+         FROM media m
+         LEFT JOIN persistent_media pm ON pm.media_id = m.id
+         LEFT JOIN keep_votes kv ON kv.media_id = m.id AND kv.user_id = ?2
+              AND kv.created_at > datetime('now', ?3 || ' days')
+         LEFT JOIN marks k ON k.media_id = m.id
+         LEFT JOIN users u ON u.id = k.user_id
+         WHERE m.status IN ('active', 'permanent') AND m.media_type = ?1
+           AND (m.status = 'active' OR pm.user_id = ?2)
+           AND {}
+         GROUP BY m.id
+         ORDER BY m.title, m.season",
+        library_access::may_access("v.id", "m"),
+        library_access::may_access("?2", "m")
+    );
+    sqlx::query_as::<_, ListRow>(&query)
+        .bind(media_type)
+        .bind(user_id)
+        .bind(-(keep_vote_days as i64))
+        .fetch_all(pool)
+        .await
 }
 
 pub async fn get_by_id(pool: &SqlitePool, id: i64) -> Result<Option<Media>, sqlx::Error> {
@@ -55,6 +142,216 @@ pub async fn get_by_id(pool: &SqlitePool, id: i64) -> Result<Option<Media>, sqlx
         .await
 }
 
+/// Find the active media item whose directory contains `path` (or is `path`).
+pub async fn find_containing(pool: &SqlitePool, path: &str) -> Result<Option<Media>, sqlx::Error> {
+    sqlx::query_as::<_, Media>(
+        "SELECT * FROM media
+         WHERE status = 'active'
+           AND (path = ? OR substr(?, 1, length(path) + 1) = path || '/')
+         ORDER BY length(path) DESC
+         LIMIT 1",
+    )
+    .bind(path)
+    .bind(path)
+    .fetch_optional(pool)
+    .await
+}
+
+pub async fn set_size(pool: &SqlitePool, id: i64, size_bytes: i64) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE media SET size_bytes = ?, size_pending = 0 WHERE id = ?")
+        .bind(size_bytes)
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Flag the item's size as stale until the background worker measured it.
+pub async fn set_size_pending(db: impl SqliteExecutor<'_>, id: i64) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE media SET size_pending = 1 WHERE id = ?")
+        .bind(id)
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+/// Protect or unprotect an item. Protecting drops its marks, so the item
+/// does not go straight to the trash once the flag is lifted again.
+pub async fn set_protected(pool: &SqlitePool, id: i64, protected: bool) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("UPDATE media SET protected = ? WHERE id = ?")
+        .bind(protected)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+    if protected {
+        sqlx::query("DELETE FROM marks WHERE media_id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await
+}
+
+/// Ids of items whose size was never measured, e.g. because the process
+/// stopped before the background worker got to them.
+pub async fn size_pending_ids(pool: &SqlitePool) -> Result<Vec<i64>, sqlx::Error> {
+    sqlx::query_scalar(&format!(
+        "SELECT id FROM media WHERE size_pending = 1 AND {NOT_GONE} ORDER BY id"
+    ))
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn update_metadata(
+    pool: &SqlitePool,
+    id: i64,
+    title: &str,
+    year: Option<i64>,
+    season: Option<i64>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE media SET title = ?, year = ?, season = ? WHERE id = ?")
+        .bind(title)
+        .bind(year)
+        .bind(season)
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Replace the title alone, keeping the item's manual flag as it is.
+pub async fn set_title(pool: &SqlitePool, id: i64, title: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE media SET title = ? WHERE id = ?")
+        .bind(title)
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Correct the title, year and season parsed from the folder name; the item
+/// becomes manual so scans do not undo the correction.
+pub async fn correct_metadata(
+    pool: &SqlitePool,
+    id: i64,
+    title: &str,
+    year: Option<i64>,
+    season: Option<i64>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE media SET title = ?, year = ?, season = ?, manual = 1 WHERE id = ?")
+        .bind(title)
+        .bind(year)
+        .bind(season)
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Track `path` as media the scanner could not make sense of. Its size stays
+/// pending until it is measured.
+pub async fn insert_manual(
+    pool: &SqlitePool,
+    media_type: &str,
+    title: &str,
+    year: Option<i64>,
+    season: Option<i64>,
+    path: &str,
+) -> Result<i64, sqlx::Error> {
+    let result = sqlx::query(
+        "INSERT INTO media (media_type, title, year, season, path, size_bytes, size_pending, manual)
+         VALUES (?, ?, ?, ?, ?, 0, 1, 1)",
+    )
+    .bind(media_type)
+    .bind(title)
+    .bind(year)
+    .bind(season)
+    .bind(path)
+    .execute(pool)
+    .await?;
+    Ok(result.last_insert_rowid())
+}
+
+/// Correct whether `id` is a movie or a TV season; the item becomes manual so
+/// scans do not undo the correction.
+pub async fn set_media_type(
+    pool: &SqlitePool,
+    id: i64,
+    media_type: &str,
+    season: Option<i64>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE media SET media_type = ?, season = ?, manual = 1 WHERE id = ?")
+        .bind(media_type)
+        .bind(season)
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Active manual items at `prefix` or below it, or everywhere for `None`.
+pub async fn manual_paths(
+    pool: &SqlitePool,
+    prefix: Option<&str>,
+) -> Result<Vec<String>, sqlx::Error> {
+    let paths = match prefix {
+        Some(prefix) => {
+            let child_prefix = format!("{}/", prefix.trim_end_matches('/'));
+            sqlx::query_scalar(
+                "SELECT path FROM media
+                 WHERE status = 'active' AND manual = 1
+                   AND (path = ? OR substr(path, 1, length(?)) = ?)",
+            )
+            .bind(prefix)
+            .bind(&child_prefix)
+            .bind(&child_prefix)
+            .fetch_all(pool)
+            .await?
+        }
+        None => {
+            sqlx::query_scalar("SELECT path FROM media WHERE status = 'active' AND manual = 1")
+                .fetch_all(pool)
+                .await?
+        }
+    };
+    Ok(paths)
+}
+
+/// What the scanner needs to know about an item it has seen before.
+#[derive(Debug, sqlx::FromRow, Clone)]
+pub struct ScanState {
+    pub status: String,
+    pub size_bytes: i64,
+    pub dir_mtime: Option<i64>,
+    pub size_pending: bool,
+}
+
+pub async fn get_scan_state(
+    db: impl SqliteExecutor<'_>,
+    path: &str,
+) -> Result<Option<ScanState>, sqlx::Error> {
+    sqlx::query_as::<_, ScanState>(
+        "SELECT status, size_bytes, dir_mtime, size_pending FROM media WHERE path = ?",
+    )
+    .bind(path)
+    .fetch_optional(db)
+    .await
+}
+
+pub async fn set_dir_mtime(
+    db: impl SqliteExecutor<'_>,
+    id: i64,
+    dir_mtime: Option<i64>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE media SET dir_mtime = ? WHERE id = ?")
+        .bind(dir_mtime)
+        .bind(id)
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
 pub async fn upsert(
     pool: &SqlitePool,
     media_type: &str,
@@ -64,14 +361,48 @@ pub async fn upsert(
     path: &str,
     size_bytes: i64,
 ) -> Result<i64, sqlx::Error> {
-    // Try insert first
-    let result = sqlx::query(
+    let mut tx = pool.begin().await?;
+    let (id, reactivated) =
+        upsert_on(&mut tx, media_type, title, year, season, path, size_bytes).await?;
+    tx.commit().await?;
+    if reactivated {
+        media_history::announce(&[id], "active", media_history::SCANNER);
+    }
+    Ok(id)
+}
+
+/// [`upsert`] inside the caller's transaction, which must announce the
+/// reactivation once committed. Returns the id and whether the item came back.
+pub async fn upsert_on(
+    conn: &mut SqliteConnection,
+    media_type: &str,
+    title: &str,
+    year: Option<i64>,
+    season: Option<i64>,
+    path: &str,
+    size_bytes: i64,
+) -> Result<(i64, bool), sqlx::Error> {
+    // A rescan brings back media that was trashed or gone by hand.
+    let reactivated = sqlx::query(
+        "INSERT INTO media_history (media_id, from_status, to_status, actor)
+         SELECT id, status, 'active', ? FROM media WHERE path = ? AND status != 'active'",
+    )
+    .bind(media_history::SCANNER)
+    .bind(path)
+    .execute(&mut *conn)
+    .await?
+    .rows_affected()
+        > 0;
+
+    let id: i64 = sqlx::query_scalar(
         "INSERT INTO media (media_type, title, year, season, path, size_bytes)
          VALUES (?, ?, ?, ?, ?, ?)
          ON CONFLICT(path) DO UPDATE SET
            last_seen = datetime('now'),
            status = 'active',
-           size_bytes = excluded.size_bytes",
+           size_bytes = excluded.size_bytes,
+           size_pending = 0
+         RETURNING id",
     )
     .bind(media_type)
     .bind(title)
@@ -79,27 +410,39 @@ pub async fn upsert(
     .bind(season)
     .bind(path)
     .bind(size_bytes)
-    .execute(pool)
+    .fetch_one(&mut *conn)
     .await?;
-
-    if result.last_insert_rowid() != 0 {
-        Ok(result.last_insert_rowid())
-    } else {
-        // Was an update, fetch the id
-        let row: (i64,) = sqlx::query_as("SELECT id FROM media WHERE path = ?")
-            .bind(path)
-            .fetch_one(pool)
-            .await?;
-        Ok(row.0)
-    }
+    Ok((id, reactivated))
 }
 
-pub async fn mark_gone_except(pool: &SqlitePool, seen_paths: &[String]) -> Result<(), sqlx::Error> {
+/// Mark every active item not in `seen_paths` as gone.
+///
+/// Returns how many items were marked and their combined size.
+pub async fn mark_gone_except(
+    pool: &SqlitePool,
+    seen_paths: &[String],
+) -> Result<(u64, i64), sqlx::Error> {
     if seen_paths.is_empty() {
+        let (count, size): (i64, i64) = sqlx::query_as(
+            "SELECT COUNT(*), COALESCE(SUM(size_bytes), 0) FROM media WHERE status = 'active'",
+        )
+        .fetch_one(pool)
+        .await?;
+        let mut tx = pool.begin().await?;
+        let gone: Vec<i64> = sqlx::query_scalar(
+            "INSERT INTO media_history (media_id, from_status, to_status, actor)
+             SELECT id, status, 'gone', ? FROM media WHERE status = 'active'
+             RETURNING media_id",
+        )
+        .bind(media_history::SCANNER)
+        .fetch_all(&mut *tx)
+        .await?;
         sqlx::query("UPDATE media SET status = 'gone' WHERE status = 'active'")
-            .execute(pool)
+            .execute(&mut *tx)
             .await?;
-        return Ok(());
+        tx.commit().await?;
+        media_history::announce(&gone, "gone", media_history::SCANNER);
+        return Ok((count as u64, size));
     }
 
     // Use a temp table to avoid hitting SQLITE_MAX_VARIABLE_NUMBER with large libraries.
@@ -125,50 +468,257 @@ pub async fn mark_gone_except(pool: &SqlitePool, seen_paths: &[String]) -> Resul
         q.execute(&mut *conn).await?;
     }
 
+    let (count, size): (i64, i64) = sqlx::query_as(
+        "SELECT COUNT(*), COALESCE(SUM(size_bytes), 0) FROM media WHERE status = 'active' AND path NOT IN (SELECT path FROM _seen_paths)",
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+    let mut tx = conn.begin().await?;
+    let gone: Vec<i64> = sqlx::query_scalar(
+        "INSERT INTO media_history (media_id, from_status, to_status, actor)
+         SELECT id, status, 'gone', ? FROM media
+         WHERE status = 'active' AND path NOT IN (SELECT path FROM _seen_paths)
+         RETURNING media_id",
+    )
+    .bind(media_history::SCANNER)
+    .fetch_all(&mut *tx)
+    .await?;
     sqlx::query(
         "UPDATE media SET status = 'gone' WHERE status = 'active' AND path NOT IN (SELECT path FROM _seen_paths)",
     )
-    .execute(&mut *conn)
+    .execute(&mut *tx)
     .await?;
+    tx.commit().await?;
+    media_history::announce(&gone, "gone", media_history::SCANNER);
 
     sqlx::query("DELETE FROM _seen_paths")
         .execute(&mut *conn)
         .await?;
-    Ok(())
+    Ok((count as u64, size))
 }
 
 pub async fn mark_gone_by_path(pool: &SqlitePool, path: &str) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let gone: Vec<i64> = sqlx::query_scalar(
+        "INSERT INTO media_history (media_id, from_status, to_status, actor)
+         SELECT id, status, 'gone', ? FROM media WHERE path = ? AND status = 'active'
+         RETURNING media_id",
+    )
+    .bind(media_history::SCANNER)
+    .bind(path)
+    .fetch_all(&mut *tx)
+    .await?;
     sqlx::query("UPDATE media SET status = 'gone' WHERE path = ? AND status = 'active'")
         .bind(path)
-        .execute(pool)
+        .execute(&mut *tx)
         .await?;
+    tx.commit().await?;
+    media_history::announce(&gone, "gone", media_history::SCANNER);
+    Ok(())
+}
+
+/// Mark active media at `prefix` or below it as gone, except for `seen_paths`.
+///
+/// Returns how many items were marked and their combined size.
+pub async fn mark_gone_under_except(
+    pool: &SqlitePool,
+    prefix: &str,
+    seen_paths: &[String],
+) -> Result<(u64, i64), sqlx::Error> {
+    let child_prefix = format!("{}/", prefix.trim_end_matches('/'));
+    let candidates: Vec<(i64, String, i64)> = sqlx::query_as(
+        "SELECT id, path, size_bytes FROM media
+         WHERE status = 'active'
+           AND (path = ? OR substr(path, 1, length(?)) = ?)",
+    )
+    .bind(prefix)
+    .bind(&child_prefix)
+    .bind(&child_prefix)
+    .fetch_all(pool)
+    .await?;
+
+    let mut marked = 0;
+    let mut size = 0;
+    for (id, path, size_bytes) in candidates {
+        if seen_paths.contains(&path) {
+            continue;
+        }
+        set_gone(pool, id, media_history::SCANNER).await?;
+        marked += 1;
+        size += size_bytes;
+    }
+    Ok((marked, size))
+}
+
+/// Paths of active media at `prefix` or below it.
+pub async fn active_paths_under(
+    pool: &SqlitePool,
+    prefix: &str,
+) -> Result<Vec<String>, sqlx::Error> {
+    let child_prefix = format!("{}/", prefix.trim_end_matches('/'));
+    let rows: Vec<(String,)> = sqlx::query_as(
+        "SELECT path FROM media
+         WHERE status = 'active'
+           AND (path = ? OR substr(path, 1, length(?)) = ?)",
+    )
+    .bind(prefix)
+    .bind(&child_prefix)
+    .bind(&child_prefix)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().map(|(path,)| path).collect())
+}
+
+pub async fn set_trashed(pool: &SqlitePool, id: i64, actor: &str) -> Result<(), sqlx::Error> {
+    set_trashed_to(pool, id, actor, None).await
+}
+
+/// Like [`set_trashed`], recording where the item now sits in the trash.
+pub async fn set_trashed_to(
+    pool: &SqlitePool,
+    id: i64,
+    actor: &str,
+    trash_path: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let changed = set_trashed_on(&mut tx, id, actor, trash_path).await?;
+    tx.commit().await?;
+    if changed {
+        media_history::announce(&[id], "trashed", actor);
+    }
     Ok(())
 }
 
-pub async fn set_trashed(pool: &SqlitePool, id: i64) -> Result<(), sqlx::Error> {
-    sqlx::query("UPDATE media SET status = 'trashed', trashed_at = datetime('now') WHERE id = ?")
+/// [`set_trashed_to`] on `conn`, returning whether the status changed; the
+/// caller announces the change once it is committed.
+pub async fn set_trashed_on(
+    conn: &mut SqliteConnection,
+    id: i64,
+    actor: &str,
+    trash_path: Option<&str>,
+) -> Result<bool, sqlx::Error> {
+    let changed = media_history::record(conn, id, "trashed", actor).await?;
+    sqlx::query(
+        "UPDATE media SET status = 'trashed', trashed_at = datetime('now'), trash_name = NULL,
+                          trash_path = ?
+         WHERE id = ?",
+    )
+    .bind(trash_path)
+    .bind(id)
+    .execute(conn)
+    .await?;
+    Ok(changed)
+}
+
+/// Track `id` at a new path, e.g. after a rescue restored it elsewhere.
+pub async fn set_path(pool: &SqlitePool, id: i64, path: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE media SET path = ? WHERE id = ?")
+        .bind(path)
         .bind(id)
         .execute(pool)
         .await?;
     Ok(())
 }
 
-pub async fn set_active(pool: &SqlitePool, id: i64) -> Result<(), sqlx::Error> {
-    sqlx::query("UPDATE media SET status = 'active', trashed_at = NULL WHERE id = ?")
+/// Take over the row of an item whose folder was renamed only in case, on a
+/// case-insensitive filesystem, so it keeps its marks rather than going gone
+/// while `path` shows up as new.
+pub async fn adopt_case(db: impl SqliteExecutor<'_>, path: &str) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "UPDATE media SET path = ?1
+         WHERE id = (SELECT id FROM media WHERE path = ?1 COLLATE NOCASE
+                     ORDER BY status != 'active', id LIMIT 1)
+           AND NOT EXISTS (SELECT 1 FROM media WHERE path = ?1)",
+    )
+    .bind(path)
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+/// Record where a trashed item sits, for items trashed before that was
+/// recorded.
+pub async fn set_trash_path(
+    pool: &SqlitePool,
+    id: i64,
+    trash_path: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE media SET trash_path = ? WHERE id = ?")
+        .bind(trash_path)
         .bind(id)
         .execute(pool)
         .await?;
     Ok(())
 }
 
-pub async fn set_permanent(pool: &SqlitePool, id: i64) -> Result<(), sqlx::Error> {
+pub async fn set_active(pool: &SqlitePool, id: i64, actor: &str) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let changed = media_history::record(&mut tx, id, "active", actor).await?;
+    sqlx::query(
+        "UPDATE media SET status = 'active', trashed_at = NULL, trash_name = NULL,
+                          trash_path = NULL, archive_path = NULL
+         WHERE id = ?",
+    )
+    .bind(id)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    if changed {
+        media_history::announce(&[id], "active", actor);
+    }
+    Ok(())
+}
+
+pub async fn set_permanent(pool: &SqlitePool, id: i64, actor: &str) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let changed = media_history::record(&mut tx, id, "permanent", actor).await?;
     sqlx::query("UPDATE media SET status = 'permanent', trashed_at = NULL WHERE id = ?")
         .bind(id)
-        .execute(pool)
+        .execute(&mut *tx)
         .await?;
+    tx.commit().await?;
+    if changed {
+        media_history::announce(&[id], "permanent", actor);
+    }
     Ok(())
 }
 
+/// Move `id` from the trash to the archive at `archive_path`.
+pub async fn set_archived(
+    pool: &SqlitePool,
+    id: i64,
+    actor: &str,
+    archive_path: &str,
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let changed = media_history::record(&mut tx, id, "archived", actor).await?;
+    sqlx::query(
+        "UPDATE media SET status = 'archived', trashed_at = NULL, trash_name = NULL,
+                          trash_path = NULL, archive_path = ?
+         WHERE id = ?",
+    )
+    .bind(archive_path)
+    .bind(id)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    if changed {
+        media_history::announce(&[id], "archived", actor);
+    }
+    Ok(())
+}
+
+/// Archived items, most recently archived first.
+pub async fn list_archived(pool: &SqlitePool) -> Result<Vec<Media>, sqlx::Error> {
+    sqlx::query_as::<_, Media>(
+        "SELECT m.* FROM media m
+         WHERE m.status = 'archived'
+         ORDER BY (SELECT MAX(h.id) FROM media_history h WHERE h.media_id = m.id) DESC",
+    )
+    .fetch_all(pool)
+    .await
+}
+
 pub async fn list_trashed(pool: &SqlitePool) -> Result<Vec<Media>, sqlx::Error> {
     sqlx::query_as::<_, Media>(
         "SELECT * FROM media WHERE status = 'trashed' ORDER BY trashed_at DESC",
@@ -177,6 +727,69 @@ pub async fn list_trashed(pool: &SqlitePool) -> Result<Vec<Media>, sqlx::Error>
     .await
 }
 
+/// Summary of everything the Movies and TV pages show to `user_id`: media,
+/// status changes, marks, keep votes, persisted items, tags, editions, the user
+/// count, the announcements the user has not dismissed and the user's own
+/// display settings. Changes whenever one of them does, cheaply.
+pub async fn list_page_version(pool: &SqlitePool, user_id: i64) -> Result<String, sqlx::Error> {
+    let (version,): (String,) = sqlx::query_as(
+        "SELECT
+           (SELECT COUNT(*) || ':' || IFNULL(MAX(id), 0) || ':' || IFNULL(MAX(last_seen), '')
+                   || ':' || TOTAL(size_bytes) || ':' || TOTAL(length(poster_path))
+                   || ':' || TOTAL(length(title)) || ':' || TOTAL(size_pending)
+                   || ':' || TOTAL(protected)
+            FROM media)
+           || '|' || (SELECT IFNULL(MAX(id), 0) FROM media_history)
+           || '|' || (SELECT COUNT(*) || ':' || TOTAL(user_id * 1000003 + media_id) FROM marks)
+           || '|' || (SELECT COUNT(*) || ':' || TOTAL(user_id * 1000003 + media_id)
+                             || ':' || IFNULL(MAX(created_at), '')
+                      FROM keep_votes)
+           || '|' || (SELECT COUNT(*) || ':' || TOTAL(user_id * 1000003 + media_id)
+                      FROM persistent_media)
+           || '|' || (SELECT COUNT(*) || ':' || TOTAL(user_id) || ':' || IFNULL(MAX(persisted_at), '')
+                      FROM persistent_series)
+           || '|' || (SELECT COUNT(*) || ':' || TOTAL(media_id) || ':' || TOTAL(length(tag))
+                             || ':' || IFNULL(MAX(tagged_at), '')
+                      FROM media_tags)
+           || '|' || (SELECT COUNT(*) || ':' || IFNULL(MAX(id), 0) FROM comments)
+           || '|' || (SELECT COUNT(*) || ':' || TOTAL(restricted) FROM users)
+           || '|' || (SELECT COUNT(*) || ':' || TOTAL(user_id) || ':' || TOTAL(length(media_dir))
+                      FROM library_access)
+           || '|' || (SELECT COUNT(*) || ':' || TOTAL(group_id) || ':' || IFNULL(MAX(linked_at), '')
+                      FROM media_editions)
+           || '|' || (SELECT COUNT(*) || ':' || IFNULL(MAX(id), 0) FROM announcements a
+                      WHERE NOT EXISTS (
+                        SELECT 1 FROM announcement_dismissals d
+                        WHERE d.announcement_id = a.id AND d.user_id = ?
+                      ))
+           || '|' || (SELECT IFNULL(locale, '') || ':' || IFNULL(timezone, '')
+                      FROM users WHERE id = ?)
+           || '|' || IFNULL((SELECT theme FROM user_preferences WHERE user_id = ?), '')",
+    )
+    .bind(user_id)
+    .bind(user_id)
+    .bind(user_id)
+    .fetch_one(pool)
+    .await?;
+    Ok(version)
+}
+
+/// Every media row, including gone ones, for the inventory export.
+pub async fn list_all(pool: &SqlitePool) -> Result<Vec<Media>, sqlx::Error> {
+    sqlx::query_as::<_, Media>("SELECT * FROM media ORDER BY media_type, title, season")
+        .fetch_all(pool)
+        .await
+}
+
+/// All media that is not gone, i.e. everything expected to exist somewhere on disk.
+pub async fn list_tracked(pool: &SqlitePool) -> Result<Vec<Media>, sqlx::Error> {
+    sqlx::query_as::<_, Media>(&format!(
+        "SELECT * FROM media WHERE {NOT_GONE} ORDER BY path"
+    ))
+    .fetch_all(pool)
+    .await
+}
+
 pub async fn list_expired_trash(
     pool: &SqlitePool,
     grace_period_days: u64,
@@ -190,14 +803,190 @@ pub async fn list_expired_trash(
     .await
 }
 
-pub async fn set_gone(pool: &SqlitePool, id: i64) -> Result<(), sqlx::Error> {
+pub async fn set_gone(pool: &SqlitePool, id: i64, actor: &str) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let changed = media_history::record(&mut tx, id, "gone", actor).await?;
     sqlx::query("UPDATE media SET status = 'gone' WHERE id = ?")
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    if changed {
+        media_history::announce(&[id], "gone", actor);
+    }
+    Ok(())
+}
+
+/// Remember where the copy of `id` in the cloud archive was uploaded to.
+pub async fn set_cloud_key(pool: &SqlitePool, id: i64, key: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE media SET cloud_key = ? WHERE id = ?")
+        .bind(key)
         .bind(id)
         .execute(pool)
         .await?;
     Ok(())
 }
 
+/// A gone item with when and how it disappeared, from its last history entry.
+#[derive(Debug, sqlx::FromRow, Clone)]
+pub struct DeletedMedia {
+    pub id: i64,
+    pub media_type: String,
+    pub title: String,
+    pub year: Option<i64>,
+    pub season: Option<i64>,
+    pub size_bytes: i64,
+    pub removed_at: String,
+    pub from_status: Option<String>,
+    pub actor: Option<String>,
+    pub cloud_key: Option<String>,
+}
+
+impl DeletedMedia {
+    /// Why the item is gone; unknown for items that disappeared before history was kept.
+    pub fn reason(&self) -> String {
+        match (self.from_status.as_deref(), self.actor.as_deref()) {
+            (Some("trashed"), Some(media_history::CLEANUP)) => "Deleted from trash".to_string(),
+            (Some(_), Some(media_history::SCANNER)) => "Missing from library".to_string(),
+            (Some(_), Some(media_history::DOCTOR)) => "Missing on disk (doctor)".to_string(),
+            (Some(_), Some(actor)) => format!("Removed by {actor}"),
+            _ => "Unknown".to_string(),
+        }
+    }
+}
+
+/// When a gone item disappeared: its last move to gone, or the last time a scan saw it.
+const REMOVED_AT: &str = "COALESCE(
+    (SELECT MAX(changed_at) FROM media_history h
+     WHERE h.media_id = m.id AND h.to_status = 'gone'),
+    m.last_seen)";
+
+/// Gone media, most recently removed first, optionally filtered by title and
+/// limited to the libraries `visible_to` may access.
+pub async fn list_deleted(
+    pool: &SqlitePool,
+    title_filter: Option<&str>,
+    visible_to: Option<i64>,
+) -> Result<Vec<DeletedMedia>, sqlx::Error> {
+    let query = format!(
+        "SELECT m.id, m.media_type, m.title, m.year, m.season, m.size_bytes,
+                {REMOVED_AT} AS removed_at, h.from_status, h.actor, m.cloud_key
+         FROM media m
+         LEFT JOIN media_history h ON h.id = (
+             SELECT MAX(id) FROM media_history WHERE media_id = m.id AND to_status = 'gone'
+         )
+         WHERE m.status = 'gone'
+           AND (?1 IS NULL OR m.title LIKE '%' || ?1 || '%')
+           AND (?2 IS NULL OR {})
+         ORDER BY removed_at DESC, m.title",
+        library_access::may_access("?2", "m")
+    );
+    sqlx::query_as::<_, DeletedMedia>(&query)
+        .bind(title_filter)
+        .bind(visible_to)
+        .fetch_all(pool)
+        .await
+}
+
+/// Drop gone media removed more than `retention_days` ago, with its marks and history.
+pub async fn purge_deleted(pool: &SqlitePool, retention_days: u64) -> Result<u64, sqlx::Error> {
+    let query = format!(
+        "DELETE FROM media AS m
+         WHERE m.status = 'gone' AND {REMOVED_AT} <= datetime('now', ? || ' days')"
+    );
+    let result = sqlx::query(&query)
+        .bind(-(retention_days as i64))
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+/// The `limit` largest items with `status`, biggest first.
+pub async fn list_largest(
+    pool: &SqlitePool,
+    status: &str,
+    limit: i64,
+) -> Result<Vec<Media>, sqlx::Error> {
+    sqlx::query_as::<_, Media>(
+        "SELECT * FROM media WHERE status = ? ORDER BY size_bytes DESC, title, season LIMIT ?",
+    )
+    .bind(status)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+/// Conditions an active item must meet to match a rule; unset ones always hold.
+#[derive(Debug, Default, Clone)]
+pub struct MediaFilter<'a> {
+    pub media_type: Option<&'a str>,
+    pub older_than_days: Option<u64>,
+    pub larger_than_bytes: Option<i64>,
+    pub marked_by_percent: Option<u8>,
+    pub tag: Option<&'a str>,
+}
+
+/// Active items matching `filter`, largest first.
+pub async fn list_active_matching(
+    pool: &SqlitePool,
+    filter: &MediaFilter<'_>,
+) -> Result<Vec<Media>, sqlx::Error> {
+    let query = format!(
+        "SELECT m.* FROM media m
+         WHERE m.status = 'active' AND m.protected = 0
+           AND (?1 IS NULL OR m.media_type = ?1)
+           AND (?2 IS NULL OR m.first_seen <= datetime('now', '-' || ?2 || ' days'))
+           AND (?3 IS NULL OR m.size_bytes > ?3)
+           AND (?4 IS NULL OR
+                (SELECT COUNT(*) FROM marks k JOIN users u ON u.id = k.user_id
+                 WHERE k.media_id = m.id AND u.restricted = 0) * 100
+                  >= ?4 * (SELECT COUNT(*) FROM users u WHERE u.restricted = 0 AND {}))
+           AND (?5 IS NULL OR EXISTS
+                (SELECT 1 FROM media_tags t WHERE t.media_id = m.id AND t.tag = ?5))
+         ORDER BY m.size_bytes DESC, m.title, m.season",
+        library_access::may_access("u.id", "m")
+    );
+    sqlx::query_as::<_, Media>(&query)
+        .bind(filter.media_type)
+        .bind(filter.older_than_days.map(|d| d as i64))
+        .bind(filter.larger_than_bytes)
+        .bind(filter.marked_by_percent.map(i64::from))
+        .bind(filter.tag)
+        .fetch_all(pool)
+        .await
+}
+
+/// Space a user could free by getting the library cleaned up.
+#[derive(Debug, Clone, Copy)]
+pub struct Reclaimable {
+    /// Active items the user marked, waiting on the other users.
+    pub marked: i64,
+    /// Everything already in the trash, waiting for cleanup.
+    pub trashed: i64,
+    pub total: i64,
+}
+
+pub async fn reclaimable_for_user(
+    pool: &SqlitePool,
+    user_id: i64,
+) -> Result<Reclaimable, sqlx::Error> {
+    let (marked, trashed): (i64, i64) = sqlx::query_as(
+        "SELECT
+           (SELECT COALESCE(SUM(m.size_bytes), 0) FROM media m
+            JOIN marks k ON k.media_id = m.id
+            WHERE k.user_id = ? AND m.status = 'active'),
+           (SELECT COALESCE(SUM(size_bytes), 0) FROM media WHERE status = 'trashed')",
+    )
+    .bind(user_id)
+    .fetch_one(pool)
+    .await?;
+    Ok(Reclaimable {
+        marked,
+        trashed,
+        total: marked + trashed,
+    })
+}
+
 pub async fn total_active_size(pool: &SqlitePool) -> Result<i64, sqlx::Error> {
     let row: (i64,) =
         sqlx::query_as("SELECT COALESCE(SUM(size_bytes), 0) FROM media WHERE status = 'active'")
@@ -214,6 +1003,15 @@ pub async fn total_trashed_size(pool: &SqlitePool) -> Result<i64, sqlx::Error> {
     Ok(row.0)
 }
 
+/// Path, status and size of every item not gone, for per-library totals.
+pub async fn list_sizes(pool: &SqlitePool) -> Result<Vec<(String, String, i64)>, sqlx::Error> {
+    sqlx::query_as(&format!(
+        "SELECT path, status, size_bytes FROM media WHERE {NOT_GONE}"
+    ))
+    .fetch_all(pool)
+    .await
+}
+
 pub async fn count_by_status(pool: &SqlitePool, status: &str) -> Result<i64, sqlx::Error> {
     let row: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM media WHERE status = ?")
         .bind(status)
@@ -222,23 +1020,47 @@ pub async fn count_by_status(pool: &SqlitePool, status: &str) -> Result<i64, sql
     Ok(row.0)
 }
 
-pub async fn needs_poster(pool: &SqlitePool, id: i64) -> Result<bool, sqlx::Error> {
+pub async fn needs_poster(db: impl SqliteExecutor<'_>, id: i64) -> Result<bool, sqlx::Error> {
     let row: (bool,) = sqlx::query_as("SELECT poster_path IS NULL FROM media WHERE id = ?")
         .bind(id)
-        .fetch_one(pool)
+        .fetch_one(db)
         .await?;
     Ok(row.0)
 }
 
-pub async fn set_poster(pool: &SqlitePool, id: i64, poster_path: &str) -> Result<(), sqlx::Error> {
-    sqlx::query("UPDATE media SET poster_path = ? WHERE id = ?")
+pub async fn set_poster(
+    pool: &SqlitePool,
+    id: i64,
+    poster_path: &str,
+    poster_hash: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE media SET poster_path = ?, poster_hash = ? WHERE id = ?")
         .bind(poster_path)
+        .bind(poster_hash)
         .bind(id)
         .execute(pool)
         .await?;
     Ok(())
 }
 
+/// Active specials (season 0) of the show `title` once none of its regular
+/// seasons are left in the library or persisted.
+pub async fn orphaned_specials(pool: &SqlitePool, title: &str) -> Result<Vec<i64>, sqlx::Error> {
+    let rows: Vec<(i64,)> = sqlx::query_as(
+        "SELECT id FROM media
+         WHERE media_type = 'tv_season' AND title = ?1 AND season = 0
+           AND status = 'active' AND NOT protected
+           AND NOT EXISTS (
+               SELECT 1 FROM media
+               WHERE media_type = 'tv_season' AND title = ?1 AND IFNULL(season, -1) != 0
+                 AND status IN ('active', 'permanent'))",
+    )
+    .bind(title)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().map(|r| r.0).collect())
+}
+
 pub async fn cleanup_gone_marks(pool: &SqlitePool) -> Result<u64, sqlx::Error> {
     let result = sqlx::query(
         "DELETE FROM marks WHERE media_id IN (SELECT id FROM media WHERE status = 'gone')",
@@ -0,0 +1,158 @@
+use serde::Serialize;
+use sqlx::SqliteConnection;
+use sqlx::SqlitePool;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::events::{self, MediaEvent};
+
+/// Actors recorded for status changes that no user asked for.
+pub const SCANNER: &str = "scanner";
+pub const CLEANUP: &str = "cleanup";
+pub const DOCTOR: &str = "doctor";
+
+/// One status change of a media item, e.g. active → trashed.
+#[derive(Debug, sqlx::FromRow, Clone)]
+pub struct MediaHistoryEntry {
+    pub id: i64,
+    pub media_id: i64,
+    pub from_status: String,
+    pub to_status: String,
+    /// Username of the user who caused the change, or one of the constants above.
+    pub actor: String,
+    pub changed_at: String,
+}
+
+/// Record that `media_id` is about to move to `to_status`. Must run before the
+/// status update, in the same transaction; does nothing and returns false if
+/// the status is unchanged.
+pub async fn record(
+    conn: &mut SqliteConnection,
+    media_id: i64,
+    to_status: &str,
+    actor: &str,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        "INSERT INTO media_history (media_id, from_status, to_status, actor)
+         SELECT id, status, ?, ? FROM media WHERE id = ? AND status != ?",
+    )
+    .bind(to_status)
+    .bind(actor)
+    .bind(media_id)
+    .bind(to_status)
+    .execute(conn)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Tell event subscribers about recorded changes, once they are committed.
+pub fn announce(media_ids: &[i64], to_status: &str, actor: &str) {
+    for &media_id in media_ids {
+        events::publish(MediaEvent {
+            media_id,
+            status: to_status.to_string(),
+            actor: actor.to_string(),
+        });
+    }
+}
+
+/// An item entering the trash or being deleted from it, for the trash feed.
+#[derive(Debug, sqlx::FromRow, Clone)]
+pub struct Removal {
+    pub id: i64,
+    pub media_id: i64,
+    pub media_type: String,
+    pub title: String,
+    pub season: Option<i64>,
+    pub to_status: String,
+    pub actor: String,
+    pub changed_at: String,
+}
+
+/// The `limit` most recent removals, newest first.
+pub async fn list_removals(pool: &SqlitePool, limit: i64) -> Result<Vec<Removal>, sqlx::Error> {
+    sqlx::query_as::<_, Removal>(
+        "SELECT h.id, h.media_id, m.media_type, m.title, m.season, h.to_status, h.actor,
+                h.changed_at
+         FROM media_history h JOIN media m ON m.id = h.media_id
+         WHERE h.to_status = 'trashed' OR (h.from_status = 'trashed' AND h.to_status = 'gone')
+         ORDER BY h.changed_at DESC, h.id DESC
+         LIMIT ?",
+    )
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+/// Oldest change first.
+pub async fn list_for_media(
+    pool: &SqlitePool,
+    media_id: i64,
+) -> Result<Vec<MediaHistoryEntry>, sqlx::Error> {
+    sqlx::query_as::<_, MediaHistoryEntry>(
+        "SELECT * FROM media_history WHERE media_id = ? ORDER BY changed_at, id",
+    )
+    .bind(media_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// Bytes freed by items deleted from the trash in one month.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct FreedMonth {
+    /// "YYYY-MM".
+    pub month: String,
+    pub bytes: i64,
+    pub items: i64,
+    /// Bytes per media dir the items were in, "other" for paths outside them.
+    pub by_library: BTreeMap<String, i64>,
+    /// Bytes per user who sent the items to the trash.
+    pub by_user: BTreeMap<String, i64>,
+}
+
+/// Space freed per month over the last `months` calendar months, newest
+/// first; months without deletions are left out. Only covers gone items
+/// still kept in the deleted history.
+pub async fn freed_by_month(
+    pool: &SqlitePool,
+    media_dirs: &[PathBuf],
+    months: i64,
+) -> Result<Vec<FreedMonth>, sqlx::Error> {
+    let rows: Vec<(String, String, i64, Option<String>)> = sqlx::query_as(
+        "SELECT strftime('%Y-%m', h.changed_at), m.path, m.size_bytes,
+                (SELECT t.actor FROM media_history t
+                 WHERE t.media_id = h.media_id AND t.to_status = 'trashed' AND t.id < h.id
+                 ORDER BY t.id DESC LIMIT 1)
+         FROM media_history h JOIN media m ON m.id = h.media_id
+         WHERE h.from_status = 'trashed' AND h.to_status = 'gone'
+           AND h.changed_at >= date('now', 'start of month', ? || ' months')
+         ORDER BY h.changed_at DESC",
+    )
+    .bind(-(months - 1))
+    .fetch_all(pool)
+    .await?;
+
+    let mut freed: Vec<FreedMonth> = Vec::new();
+    for (month, path, bytes, trashed_by) in rows {
+        if freed.last().is_none_or(|f| f.month != month) {
+            freed.push(FreedMonth {
+                month,
+                bytes: 0,
+                items: 0,
+                by_library: BTreeMap::new(),
+                by_user: BTreeMap::new(),
+            });
+        }
+        let entry = freed.last_mut().expect("pushed above");
+        let library = crate::paths::deepest_dir(media_dirs, Path::new(&path))
+            .map_or_else(|| "other".to_string(), |dir| dir.display().to_string());
+        entry.bytes += bytes;
+        entry.items += 1;
+        *entry.by_library.entry(library).or_default() += bytes;
+        *entry
+            .by_user
+            .entry(trashed_by.unwrap_or_else(|| "unknown".to_string()))
+            .or_default() += bytes;
+    }
+    Ok(freed)
+}
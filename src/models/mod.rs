@@ -1,4 +1,23 @@
+pub mod announcement;
+pub mod comment;
+pub mod db_maintenance;
+pub mod deletion_approval;
+pub mod edition;
+pub mod expiry_warning;
+pub mod keep_vote;
+pub mod library_access;
 pub mod mark;
 pub mod media;
+pub mod media_history;
+pub mod pending_trash;
 pub mod persistent;
+pub mod policy_log;
+pub mod preferences;
+pub mod rerequest;
+pub mod rule_hit;
+pub mod scan_summary;
+pub mod setting;
+pub mod tag;
+pub mod trash_journal;
+pub mod trash_queue;
 pub mod user;
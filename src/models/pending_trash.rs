@@ -0,0 +1,56 @@
+use sqlx::SqlitePool;
+
+/// An item everyone marked, waiting for an admin to confirm trashing it.
+#[derive(Debug, sqlx::FromRow, Clone)]
+pub struct PendingTrash {
+    pub media_id: i64,
+    pub title: String,
+    pub season: Option<i64>,
+    pub size_bytes: i64,
+    /// Whoever marked it last.
+    pub requested_by: String,
+    pub requested_at: String,
+}
+
+/// Queue `media_id` for confirmation; returns false if it already was.
+pub async fn request(
+    pool: &SqlitePool,
+    media_id: i64,
+    requested_by: &str,
+) -> Result<bool, sqlx::Error> {
+    let result =
+        sqlx::query("INSERT OR IGNORE INTO pending_trash (media_id, requested_by) VALUES (?, ?)")
+            .bind(media_id)
+            .bind(requested_by)
+            .execute(pool)
+            .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn is_pending(pool: &SqlitePool, media_id: i64) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM pending_trash WHERE media_id = ?)")
+        .bind(media_id)
+        .fetch_one(pool)
+        .await
+}
+
+pub async fn clear(pool: &SqlitePool, media_id: i64) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM pending_trash WHERE media_id = ?")
+        .bind(media_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Queued items that are still active, oldest first.
+pub async fn list(pool: &SqlitePool) -> Result<Vec<PendingTrash>, sqlx::Error> {
+    sqlx::query_as::<_, PendingTrash>(
+        "SELECT p.media_id, m.title, m.season, m.size_bytes, p.requested_by, p.requested_at
+         FROM pending_trash p
+         JOIN media m ON m.id = p.media_id
+         WHERE m.status = 'active'
+         ORDER BY p.requested_at, p.media_id",
+    )
+    .fetch_all(pool)
+    .await
+}
@@ -40,58 +40,101 @@ pub async fn get_owner(
         .await
 }
 
-pub async fn owner_for_media_ids(
+pub async fn list_media_ids_by_owner(
     pool: &SqlitePool,
-    media_ids: &[i64],
-) -> Result<Vec<PersistentOwner>, sqlx::Error> {
-    if media_ids.is_empty() {
-        return Ok(Vec::new());
-    }
-
-    let mut conn = pool.acquire().await?;
-    sqlx::query("CREATE TEMP TABLE IF NOT EXISTS _owner_ids (id INTEGER NOT NULL)")
-        .execute(&mut *conn)
-        .await?;
-    sqlx::query("DELETE FROM _owner_ids")
-        .execute(&mut *conn)
-        .await?;
+    user_id: i64,
+) -> Result<Vec<i64>, sqlx::Error> {
+    let rows: Vec<(i64,)> =
+        sqlx::query_as("SELECT media_id FROM persistent_media WHERE user_id = ?")
+            .bind(user_id)
+            .fetch_all(pool)
+            .await?;
+    Ok(rows.into_iter().map(|r| r.0).collect())
+}
 
-    for chunk in media_ids.chunks(500) {
-        let placeholders: Vec<&str> = chunk.iter().map(|_| "(?)").collect();
-        let query = format!(
-            "INSERT INTO _owner_ids (id) VALUES {}",
-            placeholders.join(",")
-        );
-        let mut q = sqlx::query(&query);
-        for id in chunk {
-            q = q.bind(id);
-        }
-        q.execute(&mut *conn).await?;
-    }
+/// A show persisted as a whole: its seasons are unpersisted together, and
+/// seasons that appear later are persisted for the same owner.
+#[derive(Debug, sqlx::FromRow, Clone)]
+pub struct SeriesOwner {
+    pub title: String,
+    pub user_id: i64,
+    pub persisted_at: String,
+}
 
-    let rows = sqlx::query_as::<_, PersistentOwner>(
-        "SELECT pm.media_id, pm.user_id, pm.persisted_at
-         FROM persistent_media pm
-         JOIN _owner_ids t ON t.id = pm.media_id",
+pub async fn set_series_owner(
+    pool: &SqlitePool,
+    title: &str,
+    user_id: i64,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO persistent_series (title, user_id)
+         VALUES (?, ?)
+         ON CONFLICT(title) DO UPDATE SET
+           user_id = excluded.user_id,
+           persisted_at = datetime('now')",
     )
-    .fetch_all(&mut *conn)
+    .bind(title)
+    .bind(user_id)
+    .execute(pool)
     .await?;
+    Ok(())
+}
 
-    sqlx::query("DELETE FROM _owner_ids")
-        .execute(&mut *conn)
+pub async fn clear_series_owner(pool: &SqlitePool, title: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM persistent_series WHERE title = ?")
+        .bind(title)
+        .execute(pool)
         .await?;
+    Ok(())
+}
 
-    Ok(rows)
+pub async fn get_series_owner(
+    pool: &SqlitePool,
+    title: &str,
+) -> Result<Option<SeriesOwner>, sqlx::Error> {
+    sqlx::query_as::<_, SeriesOwner>("SELECT * FROM persistent_series WHERE title = ?")
+        .bind(title)
+        .fetch_optional(pool)
+        .await
 }
 
-pub async fn list_media_ids_by_owner(
+/// Titles of the shows `user_id` persisted as a whole.
+pub async fn series_titles_by_owner(
     pool: &SqlitePool,
     user_id: i64,
-) -> Result<Vec<i64>, sqlx::Error> {
-    let rows: Vec<(i64,)> =
-        sqlx::query_as("SELECT media_id FROM persistent_media WHERE user_id = ?")
+) -> Result<Vec<String>, sqlx::Error> {
+    let rows: Vec<(String,)> =
+        sqlx::query_as("SELECT title FROM persistent_series WHERE user_id = ?")
             .bind(user_id)
             .fetch_all(pool)
             .await?;
     Ok(rows.into_iter().map(|r| r.0).collect())
 }
+
+/// Active seasons first seen after their show was persisted, with the owner
+/// they are to be persisted for.
+const NEW_SEASONS: &str = "FROM media m
+     JOIN persistent_series ps ON ps.title = m.title
+     WHERE m.media_type = 'tv_season' AND m.status = 'active' AND NOT m.protected
+       AND m.first_seen > ps.persisted_at";
+
+pub async fn new_seasons_of_persisted_series(
+    pool: &SqlitePool,
+) -> Result<Vec<(i64, i64)>, sqlx::Error> {
+    sqlx::query_as(&format!(
+        "SELECT m.id, ps.user_id {NEW_SEASONS} ORDER BY m.id"
+    ))
+    .fetch_all(pool)
+    .await
+}
+
+/// Whether `media_id` is a new season waiting to be persisted with its show.
+pub async fn awaits_series_persist(pool: &SqlitePool, media_id: i64) -> Result<bool, sqlx::Error> {
+    let (waiting,): (bool,) = sqlx::query_as(&format!(
+        "SELECT EXISTS(SELECT 1 {NEW_SEASONS} AND m.id = ?)"
+    ))
+    .bind(media_id)
+    .fetch_one(pool)
+    .await?;
+    Ok(waiting)
+}
@@ -0,0 +1,60 @@
+use sqlx::SqlitePool;
+
+/// Something a tag policy did to an item, see [`crate::policies`].
+#[derive(Debug, sqlx::FromRow, Clone)]
+pub struct PolicyLogEntry {
+    pub id: i64,
+    /// The tag of the policy.
+    pub policy: String,
+    pub media_id: i64,
+    pub action: String,
+    pub detail: String,
+    pub logged_at: String,
+    pub title: String,
+    pub season: Option<i64>,
+}
+
+/// Log that `policy` did `action` to `media_id`, unless it already did since
+/// the UTC timestamp `since`. Returns false if it had.
+pub async fn record_once(
+    pool: &SqlitePool,
+    policy: &str,
+    media_id: i64,
+    action: &str,
+    detail: &str,
+    since: &str,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        "INSERT INTO policy_log (policy, media_id, action, detail)
+         SELECT ?1, ?2, ?3, ?4
+         WHERE NOT EXISTS (
+             SELECT 1 FROM policy_log
+             WHERE policy = ?1 AND media_id = ?2 AND action = ?3 AND logged_at >= ?5
+         )",
+    )
+    .bind(policy)
+    .bind(media_id)
+    .bind(action)
+    .bind(detail)
+    .bind(since)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// The most recent entries of all policies, newest first.
+pub async fn list_recent(
+    pool: &SqlitePool,
+    limit: i64,
+) -> Result<Vec<PolicyLogEntry>, sqlx::Error> {
+    sqlx::query_as::<_, PolicyLogEntry>(
+        "SELECT l.id, l.policy, l.media_id, l.action, l.detail, l.logged_at, m.title, m.season
+         FROM policy_log l
+         JOIN media m ON m.id = l.media_id
+         ORDER BY l.logged_at DESC, l.id DESC
+         LIMIT ?",
+    )
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
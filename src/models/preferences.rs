@@ -0,0 +1,77 @@
+use sqlx::SqlitePool;
+
+/// Sort orders a user can pick as their default; both list pages know them.
+pub const SORTS: &[(&str, &str)] = &[("name", "Title"), ("added", "Added")];
+
+/// Page sizes offered on the account page; 0 shows everything on one page.
+pub const PAGE_SIZES: &[i64] = &[0, 24, 48, 96];
+
+/// Themes and their labels; "auto" follows the browser's light/dark setting.
+pub const THEMES: &[(&str, &str)] = &[("dark", "Dark"), ("light", "Light"), ("auto", "System")];
+
+/// How a user wants the Movies and TV pages to look when opened without
+/// query parameters.
+#[derive(Debug, sqlx::FromRow, Clone, PartialEq)]
+pub struct Preferences {
+    pub sort: String,
+    pub sort_dir: String,
+    pub show_marked: bool,
+    pub page_size: i64,
+    pub theme: String,
+}
+
+impl Default for Preferences {
+    fn default() -> Self {
+        Self {
+            sort: "name".to_string(),
+            sort_dir: "asc".to_string(),
+            show_marked: false,
+            page_size: 0,
+            theme: crate::theme::DEFAULT_THEME.to_string(),
+        }
+    }
+}
+
+/// The user's saved preferences, or the defaults if they never saved any.
+pub async fn get(pool: &SqlitePool, user_id: i64) -> Result<Preferences, sqlx::Error> {
+    let prefs = sqlx::query_as::<_, Preferences>(
+        "SELECT sort, sort_dir, show_marked, page_size, theme
+         FROM user_preferences WHERE user_id = ?",
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(prefs.unwrap_or_default())
+}
+
+pub async fn save(pool: &SqlitePool, user_id: i64, prefs: &Preferences) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO user_preferences (user_id, sort, sort_dir, show_marked, page_size, theme)
+         VALUES (?, ?, ?, ?, ?, ?)
+         ON CONFLICT(user_id) DO UPDATE SET
+           sort = excluded.sort,
+           sort_dir = excluded.sort_dir,
+           show_marked = excluded.show_marked,
+           page_size = excluded.page_size,
+           theme = excluded.theme",
+    )
+    .bind(user_id)
+    .bind(&prefs.sort)
+    .bind(&prefs.sort_dir)
+    .bind(prefs.show_marked)
+    .bind(prefs.page_size)
+    .bind(&prefs.theme)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Just the theme, which every page needs.
+pub async fn theme(pool: &SqlitePool, user_id: i64) -> Result<Option<String>, sqlx::Error> {
+    let row: Option<(String,)> =
+        sqlx::query_as("SELECT theme FROM user_preferences WHERE user_id = ?")
+            .bind(user_id)
+            .fetch_optional(pool)
+            .await?;
+    Ok(row.map(|(theme,)| theme))
+}
@@ -0,0 +1,100 @@
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+
+/// A user's request to bring a deleted item back.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct Rerequest {
+    pub media_id: i64,
+    pub username: String,
+    pub requested_at: String,
+    /// The service that was asked to download it again, if one was.
+    pub forwarded_to: Option<String>,
+}
+
+/// Record that `user_id` wants `media_id` back. False when they already asked.
+pub async fn request(pool: &SqlitePool, media_id: i64, user_id: i64) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("INSERT OR IGNORE INTO rerequests (media_id, user_id) VALUES (?, ?)")
+        .bind(media_id)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn set_forwarded(
+    pool: &SqlitePool,
+    media_id: i64,
+    user_id: i64,
+    service: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE rerequests SET forwarded_to = ? WHERE media_id = ? AND user_id = ?")
+        .bind(service)
+        .bind(media_id)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Drop every request for `media_id`, once an admin dealt with it.
+pub async fn dismiss(pool: &SqlitePool, media_id: i64) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM rerequests WHERE media_id = ?")
+        .bind(media_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Requests for items still gone, oldest first per item.
+pub async fn open(pool: &SqlitePool) -> Result<HashMap<i64, Vec<Rerequest>>, sqlx::Error> {
+    let rows: Vec<Rerequest> = sqlx::query_as(
+        "SELECT r.media_id, u.username, r.requested_at, r.forwarded_to
+         FROM rerequests r
+         JOIN users u ON u.id = r.user_id
+         JOIN media m ON m.id = r.media_id
+         WHERE m.status = 'gone'
+         ORDER BY r.requested_at, u.username",
+    )
+    .fetch_all(pool)
+    .await?;
+    let mut requests: HashMap<i64, Vec<Rerequest>> = HashMap::new();
+    for r in rows {
+        requests.entry(r.media_id).or_default().push(r);
+    }
+    Ok(requests)
+}
+
+/// Gone items with at least one request, for the admins' banner.
+pub async fn open_count(pool: &SqlitePool) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT COUNT(DISTINCT r.media_id) FROM rerequests r
+         JOIN media m ON m.id = r.media_id
+         WHERE m.status = 'gone'",
+    )
+    .fetch_one(pool)
+    .await
+}
+
+/// The items `user_id` asked back, with where each request went.
+pub async fn by_user(
+    pool: &SqlitePool,
+    user_id: i64,
+) -> Result<HashMap<i64, Option<String>>, sqlx::Error> {
+    let rows: Vec<(i64, Option<String>)> =
+        sqlx::query_as("SELECT media_id, forwarded_to FROM rerequests WHERE user_id = ?")
+            .bind(user_id)
+            .fetch_all(pool)
+            .await?;
+    Ok(rows.into_iter().collect())
+}
+
+/// Forget requests for items that came back, e.g. because a scan found them again.
+pub async fn cleanup_fulfilled(pool: &SqlitePool) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        "DELETE FROM rerequests
+         WHERE media_id IN (SELECT id FROM media WHERE status != 'gone')",
+    )
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
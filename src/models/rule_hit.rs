@@ -0,0 +1,54 @@
+use sqlx::SqlitePool;
+use std::collections::HashSet;
+
+/// An item a rule acted on, see [`crate::rules`].
+#[derive(Debug, sqlx::FromRow, Clone)]
+pub struct RuleHit {
+    pub rule: String,
+    pub media_id: i64,
+    pub action: String,
+    pub hit_at: String,
+    pub title: String,
+    pub season: Option<i64>,
+    pub size_bytes: i64,
+}
+
+/// Remember that `rule` acted on `media_id`. Returns false if it already had.
+pub async fn record(
+    pool: &SqlitePool,
+    rule: &str,
+    media_id: i64,
+    action: &str,
+) -> Result<bool, sqlx::Error> {
+    let result =
+        sqlx::query("INSERT OR IGNORE INTO rule_hits (rule, media_id, action) VALUES (?, ?, ?)")
+            .bind(rule)
+            .bind(media_id)
+            .bind(action)
+            .execute(pool)
+            .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Ids of the items `rule` already acted on.
+pub async fn media_ids(pool: &SqlitePool, rule: &str) -> Result<HashSet<i64>, sqlx::Error> {
+    let ids: Vec<i64> = sqlx::query_scalar("SELECT media_id FROM rule_hits WHERE rule = ?")
+        .bind(rule)
+        .fetch_all(pool)
+        .await?;
+    Ok(ids.into_iter().collect())
+}
+
+/// The most recent hits of all rules, newest first.
+pub async fn list_recent(pool: &SqlitePool, limit: i64) -> Result<Vec<RuleHit>, sqlx::Error> {
+    sqlx::query_as::<_, RuleHit>(
+        "SELECT h.rule, h.media_id, h.action, h.hit_at, m.title, m.season, m.size_bytes
+         FROM rule_hits h
+         JOIN media m ON m.id = h.media_id
+         ORDER BY h.hit_at DESC, h.rowid DESC
+         LIMIT ?",
+    )
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
@@ -0,0 +1,69 @@
+use sqlx::SqlitePool;
+
+/// Number of summaries kept; older ones are pruned on insert.
+const KEEP_SUMMARIES: i64 = 100;
+
+/// What a scan changed in the library.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ScanSummary {
+    pub items_seen: i64,
+    pub added: i64,
+    pub reactivated: i64,
+    pub marked_gone: i64,
+    pub size_delta: i64,
+}
+
+#[derive(Debug, sqlx::FromRow, Clone)]
+pub struct ScanSummaryRecord {
+    pub id: i64,
+    pub scope: String,
+    pub duration_secs: i64,
+    pub items_seen: i64,
+    pub added: i64,
+    pub reactivated: i64,
+    pub marked_gone: i64,
+    pub size_delta: i64,
+    pub finished_at: String,
+}
+
+pub async fn insert(
+    pool: &SqlitePool,
+    scope: &str,
+    duration_secs: i64,
+    summary: &ScanSummary,
+) -> Result<i64, sqlx::Error> {
+    let result = sqlx::query(
+        "INSERT INTO scan_summaries
+           (scope, duration_secs, items_seen, added, reactivated, marked_gone, size_delta)
+         VALUES (?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(scope)
+    .bind(duration_secs)
+    .bind(summary.items_seen)
+    .bind(summary.added)
+    .bind(summary.reactivated)
+    .bind(summary.marked_gone)
+    .bind(summary.size_delta)
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        "DELETE FROM scan_summaries
+         WHERE id NOT IN (SELECT id FROM scan_summaries ORDER BY id DESC LIMIT ?)",
+    )
+    .bind(KEEP_SUMMARIES)
+    .execute(pool)
+    .await?;
+
+    Ok(result.last_insert_rowid())
+}
+
+pub async fn list_recent(
+    pool: &SqlitePool,
+    limit: i64,
+) -> Result<Vec<ScanSummaryRecord>, sqlx::Error> {
+    sqlx::query_as::<_, ScanSummaryRecord>("SELECT * FROM scan_summaries ORDER BY id DESC LIMIT ?")
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+}
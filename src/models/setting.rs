@@ -0,0 +1,40 @@
+use sqlx::SqlitePool;
+use std::collections::BTreeMap;
+
+/// Values saved on the admin settings page, by key.
+pub async fn all(pool: &SqlitePool) -> Result<BTreeMap<String, String>, sqlx::Error> {
+    let rows: Vec<(String, String)> = sqlx::query_as("SELECT key, value FROM settings")
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.into_iter().collect())
+}
+
+pub async fn set(
+    pool: &SqlitePool,
+    key: &str,
+    value: &str,
+    actor: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO settings (key, value, updated_by) VALUES (?, ?, ?)
+         ON CONFLICT(key) DO UPDATE SET
+           value = excluded.value,
+           updated_by = excluded.updated_by,
+           updated_at = datetime('now')",
+    )
+    .bind(key)
+    .bind(value)
+    .bind(actor)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Drop a saved value so the config file's applies again.
+pub async fn remove(pool: &SqlitePool, key: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM settings WHERE key = ?")
+        .bind(key)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
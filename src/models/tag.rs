@@ -0,0 +1,99 @@
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+
+/// Longest tag accepted, in characters.
+pub const MAX_TAG_LEN: usize = 32;
+
+/// The tag as stored, or why it cannot be one. Tags keep their spelling but
+/// compare case-insensitively, so "4K" and "4k" are the same tag.
+pub fn normalize(tag: &str) -> Result<String, String> {
+    let tag = tag.split_whitespace().collect::<Vec<_>>().join(" ");
+    if tag.is_empty() {
+        return Err("tag must not be empty".to_string());
+    }
+    if tag.chars().count() > MAX_TAG_LEN {
+        return Err(format!("tag must be at most {MAX_TAG_LEN} characters"));
+    }
+    Ok(tag)
+}
+
+pub async fn add(pool: &SqlitePool, media_id: i64, tag: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("INSERT OR IGNORE INTO media_tags (media_id, tag) VALUES (?, ?)")
+        .bind(media_id)
+        .bind(tag)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+pub async fn remove(pool: &SqlitePool, media_id: i64, tag: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM media_tags WHERE media_id = ? AND tag = ?")
+        .bind(media_id)
+        .bind(tag)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Tags of one item, alphabetically.
+pub async fn for_media(pool: &SqlitePool, media_id: i64) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar("SELECT tag FROM media_tags WHERE media_id = ? ORDER BY tag")
+        .bind(media_id)
+        .fetch_all(pool)
+        .await
+}
+
+/// Tags of every tagged item of `media_type`, alphabetically per item.
+pub async fn by_media(
+    pool: &SqlitePool,
+    media_type: &str,
+) -> Result<HashMap<i64, Vec<String>>, sqlx::Error> {
+    let rows: Vec<(i64, String)> = sqlx::query_as(
+        "SELECT t.media_id, t.tag FROM media_tags t
+         JOIN media m ON m.id = t.media_id
+         WHERE m.media_type = ?
+         ORDER BY t.tag",
+    )
+    .bind(media_type)
+    .fetch_all(pool)
+    .await?;
+    let mut tags: HashMap<i64, Vec<String>> = HashMap::new();
+    for (media_id, tag) in rows {
+        tags.entry(media_id).or_default().push(tag);
+    }
+    Ok(tags)
+}
+
+/// Tags of every tagged item with `status`, alphabetically per item.
+pub async fn by_status(
+    pool: &SqlitePool,
+    status: &str,
+) -> Result<HashMap<i64, Vec<String>>, sqlx::Error> {
+    let rows: Vec<(i64, String)> = sqlx::query_as(
+        "SELECT t.media_id, t.tag FROM media_tags t
+         JOIN media m ON m.id = t.media_id
+         WHERE m.status = ?
+         ORDER BY t.tag",
+    )
+    .bind(status)
+    .fetch_all(pool)
+    .await?;
+    let mut tags: HashMap<i64, Vec<String>> = HashMap::new();
+    for (media_id, tag) in rows {
+        tags.entry(media_id).or_default().push(tag);
+    }
+    Ok(tags)
+}
+
+/// Every tag in use on active or persisted items of `media_type`, alphabetically.
+pub async fn list_in_use(pool: &SqlitePool, media_type: &str) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT DISTINCT t.tag FROM media_tags t
+         JOIN media m ON m.id = t.media_id
+         WHERE m.media_type = ? AND m.status IN ('active', 'permanent')
+         ORDER BY t.tag",
+    )
+    .bind(media_type)
+    .fetch_all(pool)
+    .await
+}
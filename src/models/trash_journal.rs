@@ -0,0 +1,41 @@
+use sqlx::{SqliteExecutor, SqlitePool};
+
+/// A move of a batch trashed together, recorded before it happens so an
+/// interrupted batch can be rolled back, see [`crate::trash::series`].
+#[derive(Debug, sqlx::FromRow, Clone)]
+pub struct JournalEntry {
+    pub media_id: i64,
+    pub src: String,
+    pub dest: String,
+}
+
+/// Record every move of a batch, all or none.
+pub async fn record(pool: &SqlitePool, entries: &[JournalEntry]) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    for entry in entries {
+        sqlx::query("INSERT OR REPLACE INTO trash_journal (media_id, src, dest) VALUES (?, ?, ?)")
+            .bind(entry.media_id)
+            .bind(&entry.src)
+            .bind(&entry.dest)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await
+}
+
+pub async fn remove(conn: impl SqliteExecutor<'_>, media_id: i64) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM trash_journal WHERE media_id = ?")
+        .bind(media_id)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+/// Moves left over from batches that never finished.
+pub async fn list(pool: &SqlitePool) -> Result<Vec<JournalEntry>, sqlx::Error> {
+    sqlx::query_as::<_, JournalEntry>(
+        "SELECT media_id, src, dest FROM trash_journal ORDER BY media_id",
+    )
+    .fetch_all(pool)
+    .await
+}
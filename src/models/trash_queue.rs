@@ -0,0 +1,55 @@
+use sqlx::SqlitePool;
+
+/// A trash move waiting for the next batch in the trash window.
+#[derive(Debug, sqlx::FromRow, Clone)]
+pub struct QueuedTrash {
+    pub media_id: i64,
+    pub title: String,
+    pub season: Option<i64>,
+    pub size_bytes: i64,
+    /// Whoever marked it last.
+    pub queued_by: String,
+    pub queued_at: String,
+}
+
+/// Queue `media_id`; returns false if it already was.
+pub async fn enqueue(
+    pool: &SqlitePool,
+    media_id: i64,
+    queued_by: &str,
+) -> Result<bool, sqlx::Error> {
+    let result =
+        sqlx::query("INSERT OR IGNORE INTO trash_queue (media_id, queued_by) VALUES (?, ?)")
+            .bind(media_id)
+            .bind(queued_by)
+            .execute(pool)
+            .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn remove(pool: &SqlitePool, media_id: i64) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM trash_queue WHERE media_id = ?")
+        .bind(media_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// The oldest `limit` queued moves.
+pub async fn next_batch(pool: &SqlitePool, limit: i64) -> Result<Vec<QueuedTrash>, sqlx::Error> {
+    sqlx::query_as::<_, QueuedTrash>(
+        "SELECT q.media_id, m.title, m.season, m.size_bytes, q.queued_by, q.queued_at
+         FROM trash_queue q
+         JOIN media m ON m.id = q.media_id
+         ORDER BY q.queued_at, q.media_id
+         LIMIT ?",
+    )
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+/// Every queued move, oldest first.
+pub async fn list(pool: &SqlitePool) -> Result<Vec<QueuedTrash>, sqlx::Error> {
+    next_batch(pool, -1).await
+}
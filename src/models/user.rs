@@ -8,6 +8,15 @@ pub struct User {
     pub is_admin: bool,
     pub invite_token: Option<String>,
     pub created_at: String,
+    /// Date format code from [`crate::locale::LOCALES`]; unset means ISO.
+    pub locale: Option<String>,
+    /// IANA timezone name; unset means UTC.
+    pub timezone: Option<String>,
+    /// Secret in the user's trash feed URL; unset until they ask for one.
+    pub feed_token: Option<String>,
+    /// A kid's account: may browse and mark, but the marks are only a wish
+    /// and never count towards the quorum that trashes an item.
+    pub restricted: bool,
 }
 
 pub async fn get_by_id(pool: &SqlitePool, id: i64) -> Result<Option<User>, sqlx::Error> {
@@ -27,6 +36,16 @@ pub async fn get_by_username(
         .await
 }
 
+pub async fn get_by_feed_token(
+    pool: &SqlitePool,
+    token: &str,
+) -> Result<Option<User>, sqlx::Error> {
+    sqlx::query_as::<_, User>("SELECT * FROM users WHERE feed_token = ?")
+        .bind(token)
+        .fetch_optional(pool)
+        .await
+}
+
 pub async fn get_by_invite_token(
     pool: &SqlitePool,
     token: &str,
@@ -72,6 +91,54 @@ pub async fn set_password(
     Ok(())
 }
 
+/// Grant or revoke admin rights; admins are never restricted.
+pub async fn set_admin(pool: &SqlitePool, id: i64, is_admin: bool) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE users SET is_admin = ?1, restricted = restricted AND NOT ?1 WHERE id = ?2")
+        .bind(is_admin)
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+pub async fn set_restricted(
+    pool: &SqlitePool,
+    id: i64,
+    restricted: bool,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE users SET restricted = ? WHERE id = ? AND is_admin = 0")
+        .bind(restricted)
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+pub async fn set_time_settings(
+    pool: &SqlitePool,
+    id: i64,
+    locale: &str,
+    timezone: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE users SET locale = ?, timezone = ? WHERE id = ?")
+        .bind(locale)
+        .bind(timezone)
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Replace the user's feed token, which invalidates the old feed URL.
+pub async fn set_feed_token(pool: &SqlitePool, id: i64, token: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE users SET feed_token = ? WHERE id = ?")
+        .bind(token)
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
 pub async fn delete(pool: &SqlitePool, id: i64) -> Result<(), sqlx::Error> {
     sqlx::query("DELETE FROM users WHERE id = ?")
         .bind(id)
@@ -0,0 +1,312 @@
+//! Keeping the paths of one item comparable however they were found.
+//!
+//! Media dirs behind symlinks, e.g. `/data` → `/mnt/pool/data`: config and
+//! stored paths both use the resolved form, so matching an item to its media
+//! dir by prefix agrees whichever way a path was first written down.
+//!
+//! Names with accents: macOS hands them out decomposed (NFD) where Linux
+//! records the composed form (NFC), so titles and paths are stored in NFC.
+//!
+//! Case-insensitive filesystems, as APFS and HFS+ are by default: paths below
+//! such a dir match whatever their case, and a folder renamed only in case
+//! keeps its item instead of turning into a new one.
+
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization};
+
+use crate::config::AppConfig;
+use crate::models::{library_access, media};
+use crate::trash;
+
+/// Whether dirs are known to be case-insensitive, by path. Only media dirs
+/// and the other configured dirs are looked up, so this stays small.
+static CASE_INSENSITIVE: OnceLock<Mutex<HashMap<PathBuf, bool>>> = OnceLock::new();
+
+/// Whether names in `dir` match whatever their case. Found out once per dir
+/// by looking it up under its own name in the other case; a dir whose name
+/// has no letters, or that does not exist, counts as case-sensitive. Meant
+/// for configured dirs such as media dirs, not for every folder below them.
+pub fn case_insensitive(dir: &Path) -> bool {
+    let cache = CASE_INSENSITIVE.get_or_init(Default::default);
+    if let Some(&known) = cache.lock().expect("case cache poisoned").get(dir) {
+        return known;
+    }
+    if !dir.exists() {
+        return false;
+    }
+    let insensitive = dir.file_name().is_some_and(|name| {
+        let name = name.to_string_lossy();
+        let swapped: String = name
+            .chars()
+            .map(|c| {
+                if c.is_uppercase() {
+                    c.to_lowercase().next().unwrap_or(c)
+                } else {
+                    c.to_uppercase().next().unwrap_or(c)
+                }
+            })
+            .collect();
+        swapped != name && same_file(dir, &dir.with_file_name(swapped))
+    });
+    cache
+        .lock()
+        .expect("case cache poisoned")
+        .insert(dir.to_path_buf(), insensitive);
+    insensitive
+}
+
+/// Whether `a` and `b` name the same file.
+#[cfg(unix)]
+fn same_file(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (std::fs::metadata(a), std::fs::metadata(b)) {
+        (Ok(a), Ok(b)) => (a.dev(), a.ino()) == (b.dev(), b.ino()),
+        _ => false,
+    }
+}
+
+/// Whether `a` and `b` name the same file.
+#[cfg(not(unix))]
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (std::fs::canonicalize(a), std::fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// What is left of `path` below `dir`, or `None` if it is not below it.
+/// Below a case-insensitive dir, `path` may spell the dir in another case.
+pub fn relative_to<'a>(dir: &Path, path: &'a Path) -> Option<&'a Path> {
+    if let Ok(rest) = path.strip_prefix(dir) {
+        return Some(rest);
+    }
+    if !case_insensitive(dir) {
+        return None;
+    }
+    let mut rest = path.components();
+    for expected in dir.components() {
+        let same = match (expected, rest.next()?) {
+            (Component::Normal(a), Component::Normal(b)) => {
+                a.to_string_lossy().to_lowercase() == b.to_string_lossy().to_lowercase()
+            }
+            (a, b) => a == b,
+        };
+        if !same {
+            return None;
+        }
+    }
+    Some(rest.as_path())
+}
+
+/// The dir of `dirs` that `path` lies in; the deepest one if they are nested.
+pub fn deepest_dir<'a>(dirs: &'a [PathBuf], path: &Path) -> Option<&'a PathBuf> {
+    dirs.iter()
+        .filter(|dir| relative_to(dir, path).is_some())
+        .max_by_key(|dir| dir.components().count())
+}
+
+/// The media dir `path` lies in.
+pub fn library_of<'a>(config: &'a AppConfig, path: &Path) -> Option<&'a PathBuf> {
+    deepest_dir(&config.media_dirs, path)
+}
+
+/// `path` with symlinks resolved as far as it exists, so paths of items that
+/// are gone from their folder resolve like their siblings.
+pub fn canonical(path: &Path) -> PathBuf {
+    for ancestor in path.ancestors() {
+        if let Ok(resolved) = std::fs::canonicalize(ancestor) {
+            let rest = path.strip_prefix(ancestor).unwrap_or(Path::new(""));
+            return if rest.as_os_str().is_empty() {
+                resolved
+            } else {
+                resolved.join(rest)
+            };
+        }
+    }
+    path.to_path_buf()
+}
+
+/// `text` in NFC, e.g. a title parsed from a folder name.
+pub fn nfc(text: &str) -> String {
+    match is_nfc_quick(text.chars()) {
+        IsNormalized::Yes => text.to_string(),
+        _ => text.nfc().collect(),
+    }
+}
+
+/// `path` as stored: in NFC, unless it is on disk under a decomposed name
+/// only, as on a Linux filesystem a Mac wrote to, where the NFC form would
+/// not open.
+pub fn normalized(path: &Path) -> String {
+    let path_str = path.to_string_lossy();
+    let composed = nfc(&path_str);
+    if composed == path_str || Path::new(&composed).exists() || !path.exists() {
+        composed
+    } else {
+        path_str.into_owned()
+    }
+}
+
+/// Bring titles and paths stored in decomposed form, e.g. by a scan on a
+/// Mac, into NFC; returns how many items changed. An item whose NFC path is
+/// tracked already is left alone.
+pub async fn normalize_stored(pool: &SqlitePool) -> Result<usize, sqlx::Error> {
+    let mut changed = 0;
+    for item in media::list_all(pool).await? {
+        let title = nfc(&item.title);
+        if title != item.title {
+            media::set_title(pool, item.id, &title).await?;
+        }
+        let path = normalized(Path::new(&item.path));
+        if path != item.path {
+            match media::set_path(pool, item.id, &path).await {
+                Ok(()) => {}
+                Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+                    tracing::warn!("{path} is tracked twice; leaving media {} alone", item.id);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        if title != item.title || path != item.path {
+            changed += 1;
+        }
+    }
+    Ok(changed)
+}
+
+/// Rewrite stored paths written against a symlinked form of a media dir to
+/// the resolved one; returns how many items were rewritten. Runs before the
+/// first scan, which would otherwise take those items for gone and track them
+/// anew. Library grants are resolved too, or their items would no longer
+/// fall under them and the library would open up to everyone.
+pub async fn canonicalize_stored(
+    pool: &SqlitePool,
+    config: &AppConfig,
+) -> Result<usize, sqlx::Error> {
+    for dir in library_access::grants(pool).await?.into_keys() {
+        let resolved = canonical(Path::new(&dir)).to_string_lossy().into_owned();
+        if resolved != dir {
+            library_access::rename_dir(pool, &dir, &resolved).await?;
+        }
+    }
+
+    let mut rewritten = 0;
+    for item in media::list_tracked(pool).await? {
+        let path = Path::new(&item.path);
+        if library_of(config, path).is_some() {
+            continue;
+        }
+        let resolved = canonical(path);
+        let Some(library) = library_of(config, &resolved) else {
+            continue;
+        };
+        // The media dir as it was written when the item was stored, which
+        // the trash and permanent folders used to be derived from.
+        let Some(written) = path.ancestors().find(|a| canonical(a) == *library) else {
+            continue;
+        };
+        let relative = relative_to(written, path).unwrap_or(path);
+
+        if item.status == "trashed" && item.trash_path.is_none() {
+            if let Some(location) = AppConfig::trash_dir_for_media_dir(written)
+                .and_then(|dir| trash::trash_path_for(written, &dir, path))
+            {
+                let location = match &item.trash_name {
+                    Some(name) => location.with_file_name(name),
+                    None => location,
+                };
+                media::set_trash_path(pool, item.id, &location.to_string_lossy()).await?;
+            }
+        }
+        if item.status == "permanent" {
+            let before = AppConfig::permanent_dir_for_media_dir(written).map(|d| d.join(relative));
+            let after = AppConfig::permanent_dir_for_media_dir(library).map(|d| d.join(relative));
+            if let (Some(before), Some(after)) = (before, after) {
+                if canonical(&before) != canonical(&after) && before.exists() {
+                    tracing::warn!(
+                        "{} is persisted in {}; move it to {} to restore it from there",
+                        item.path,
+                        before.display(),
+                        after.display()
+                    );
+                }
+            }
+        }
+
+        match media::set_path(pool, item.id, &resolved.to_string_lossy()).await {
+            Ok(()) => rewritten += 1,
+            Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+                tracing::warn!(
+                    "{} is tracked as {} too; leaving media {} alone",
+                    item.path,
+                    resolved.display(),
+                    item.id
+                );
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(rewritten)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_resolve_below_paths_that_are_gone() {
+        let dir = tempfile::tempdir().unwrap();
+        let real = std::fs::canonicalize(dir.path())
+            .unwrap()
+            .join("pool/Movies");
+        std::fs::create_dir_all(&real).unwrap();
+        let link = dir.path().join("Movies");
+        std::os::unix::fs::symlink(&real, &link).unwrap();
+
+        assert_eq!(canonical(&link), real);
+        assert_eq!(
+            canonical(&link.join("Heat (1995)")),
+            real.join("Heat (1995)")
+        );
+    }
+
+    #[test]
+    fn case_only_differences_match_below_case_insensitive_dirs() {
+        let dir = tempfile::tempdir().unwrap();
+        let movies = dir.path().join("Movies");
+        std::fs::create_dir(&movies).unwrap();
+        let shouted = dir.path().join("MOVIES/Heat (1995)");
+        // Linux filesystems tell the two spellings apart.
+        assert!(!case_insensitive(&movies));
+        assert_eq!(relative_to(&movies, &shouted), None);
+
+        let apfs = dir.path().join("Films");
+        CASE_INSENSITIVE
+            .get_or_init(Default::default)
+            .lock()
+            .unwrap()
+            .insert(apfs.clone(), true);
+        assert_eq!(
+            relative_to(&apfs, &dir.path().join("FILMS/Heat (1995)")),
+            Some(Path::new("Heat (1995)"))
+        );
+        assert_eq!(relative_to(&apfs, &dir.path().join("Filmsx/Heat")), None);
+    }
+
+    #[test]
+    fn decomposed_names_are_composed_unless_only_they_exist() {
+        let dir = tempfile::tempdir().unwrap();
+        let decomposed = dir.path().join("Ame\u{301}lie (2001)");
+        let composed = dir.path().join("Am\u{e9}lie (2001)");
+        assert_eq!(nfc("Ame\u{301}lie"), "Am\u{e9}lie");
+        assert_eq!(normalized(&decomposed), composed.to_string_lossy());
+
+        std::fs::create_dir(&decomposed).unwrap();
+        assert_eq!(normalized(&decomposed), decomposed.to_string_lossy());
+        assert_eq!(normalized(&composed), composed.to_string_lossy());
+    }
+}
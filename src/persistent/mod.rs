@@ -2,14 +2,15 @@ use sqlx::SqlitePool;
 use std::path::{Path, PathBuf};
 
 use crate::config::AppConfig;
-use crate::models::{mark, media, persistent};
+use crate::mediaserver;
+use crate::models::{mark, media, persistent, user};
 
 fn permanent_path_for(
     media_dir: &Path,
     permanent_dir: &Path,
     original_path: &Path,
 ) -> Option<PathBuf> {
-    let relative = original_path.strip_prefix(media_dir).ok()?;
+    let relative = crate::paths::relative_to(media_dir, original_path)?;
     Some(permanent_dir.join(relative))
 }
 
@@ -17,14 +18,14 @@ fn move_path(src: &Path, dst: &Path) -> std::io::Result<()> {
     std::fs::rename(src, dst)
 }
 
-fn best_media_dir<'a>(config: &'a AppConfig, original_path: &Path) -> Option<&'a PathBuf> {
-    config
-        .media_dirs
-        .iter()
-        .filter(|dir| original_path.starts_with(dir))
-        .max_by_key(|dir| dir.components().count())
+/// Username recorded in the media history for changes made by `user_id`.
+async fn actor_name(pool: &SqlitePool, user_id: i64) -> Result<String, sqlx::Error> {
+    Ok(user::get_by_id(pool, user_id)
+        .await?
+        .map_or_else(|| format!("user {user_id}"), |u| u.username))
 }
 
+#[tracing::instrument(skip(pool, config))]
 pub async fn move_to_permanent(
     pool: &SqlitePool,
     media_id: i64,
@@ -40,7 +41,7 @@ pub async fn move_to_permanent(
     }
 
     let original_path = Path::new(&item.path);
-    let media_dir = best_media_dir(config, original_path)
+    let media_dir = crate::paths::library_of(config, original_path)
         .ok_or_else(|| format!("no matching media_dir configured for path {}", item.path))?;
     let permanent_dir = AppConfig::permanent_dir_for_media_dir(media_dir)
         .ok_or_else(|| format!("cannot derive permanent dir for {}", item.path))?;
@@ -54,10 +55,20 @@ pub async fn move_to_permanent(
             std::fs::create_dir_all(parent)?;
         }
         move_path(original_path, &dest)?;
-        tracing::info!("Persisted media: {} → {}", item.path, dest.display());
+        mediaserver::refresh_after_move(config, &[original_path, &dest]);
+        tracing::info!(
+            event = "persist",
+            media_id,
+            user_id,
+            path = %item.path,
+            "Persisted media: {} → {}",
+            item.path,
+            dest.display()
+        );
     }
 
-    media::set_permanent(pool, media_id).await?;
+    let actor = actor_name(pool, user_id).await?;
+    media::set_permanent(pool, media_id, &actor).await?;
     persistent::set_owner(pool, media_id, user_id).await?;
     mark::clear_marks(pool, media_id).await?;
 
@@ -83,13 +94,23 @@ pub async fn restore_from_permanent(
     if owner.user_id != user_id {
         return Err("forbidden".into());
     }
+    if item.media_type == "tv_season"
+        && persistent::get_series_owner(pool, &item.title)
+            .await?
+            .is_some_and(|s| s.user_id == user_id)
+    {
+        return Err(format!("{} is persisted as a whole show", item.title).into());
+    }
+    let actor = actor_name(pool, user_id).await?;
 
-    restore_from_permanent_unchecked(pool, media_id, config, dry_run).await
+    restore_from_permanent_unchecked(pool, media_id, &actor, config, dry_run).await
 }
 
+#[tracing::instrument(skip(pool, config))]
 pub async fn restore_from_permanent_unchecked(
     pool: &SqlitePool,
     media_id: i64,
+    actor: &str,
     config: &AppConfig,
     dry_run: bool,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
@@ -101,7 +122,7 @@ pub async fn restore_from_permanent_unchecked(
     }
 
     let original_path = Path::new(&item.path);
-    let media_dir = best_media_dir(config, original_path)
+    let media_dir = crate::paths::library_of(config, original_path)
         .ok_or_else(|| format!("no matching media_dir configured for path {}", item.path))?;
     let permanent_dir = AppConfig::permanent_dir_for_media_dir(media_dir)
         .ok_or_else(|| format!("cannot derive permanent dir for {}", item.path))?;
@@ -119,7 +140,11 @@ pub async fn restore_from_permanent_unchecked(
             std::fs::create_dir_all(parent)?;
         }
         move_path(&permanent_path, original_path)?;
+        mediaserver::refresh_after_move(config, &[&permanent_path, original_path]);
         tracing::info!(
+            event = "unpersist",
+            media_id,
+            path = %item.path,
             "Unpersisted media: {} → {}",
             permanent_path.display(),
             item.path
@@ -132,9 +157,75 @@ pub async fn restore_from_permanent_unchecked(
         .into());
     }
 
-    media::set_active(pool, media_id).await?;
+    media::set_active(pool, media_id, actor).await?;
     persistent::clear_owner(pool, media_id).await?;
     mark::clear_marks(pool, media_id).await?;
 
     Ok(())
 }
+
+/// Persist the seasons in `season_ids` of the show `title` for `user_id`, as
+/// one show: seasons that appear later are persisted for them too, and
+/// [`unpersist_series`] restores them all.
+pub async fn persist_series(
+    pool: &SqlitePool,
+    title: &str,
+    season_ids: &[i64],
+    user_id: i64,
+    config: &AppConfig,
+    dry_run: bool,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if let Some(owner) = persistent::get_series_owner(pool, title).await? {
+        if owner.user_id != user_id {
+            return Err(format!("{title} is already persisted by someone else").into());
+        }
+    } else {
+        persistent::set_series_owner(pool, title, user_id).await?;
+    }
+    for &id in season_ids {
+        move_to_permanent(pool, id, user_id, config, dry_run).await?;
+    }
+    Ok(())
+}
+
+/// Restore every season of the show `title` that `user_id` persisted as a whole.
+pub async fn unpersist_series(
+    pool: &SqlitePool,
+    title: &str,
+    user_id: i64,
+    config: &AppConfig,
+    dry_run: bool,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let owner = persistent::get_series_owner(pool, title)
+        .await?
+        .ok_or_else(|| format!("{title} is not persisted as a show"))?;
+    if owner.user_id != user_id {
+        return Err("forbidden".into());
+    }
+    let actor = actor_name(pool, user_id).await?;
+    for id in persistent::list_media_ids_by_owner(pool, user_id).await? {
+        let Some(item) = media::get_by_id(pool, id).await? else {
+            continue;
+        };
+        if item.media_type == "tv_season" && item.title == title {
+            restore_from_permanent_unchecked(pool, id, &actor, config, dry_run).await?;
+        }
+    }
+    persistent::clear_series_owner(pool, title).await?;
+    Ok(())
+}
+
+/// Persist seasons that appeared after their show was persisted as a whole;
+/// returns how many were moved.
+pub async fn persist_new_seasons(
+    pool: &SqlitePool,
+    config: &AppConfig,
+    dry_run: bool,
+) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+    let mut persisted = 0;
+    for (media_id, user_id) in persistent::new_seasons_of_persisted_series(pool).await? {
+        move_to_permanent(pool, media_id, user_id, config, dry_run).await?;
+        persisted += 1;
+    }
+    Ok(persisted)
+}
@@ -0,0 +1,226 @@
+//! Per-tag retention policies from the `[[tag_policies]]` tables of the
+//! config: marking tagged items once a year, and keeping tagged items in the
+//! trash longer. Every action is written to the policy log.
+
+use serde::Deserialize;
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+
+use crate::config::AppConfig;
+use crate::locale;
+use crate::models::media::{self, Media, MediaFilter};
+use crate::models::{mark, policy_log, tag};
+use crate::trash;
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct TagPolicy {
+    pub tag: String,
+    /// Mark every item with the tag for all users once a year, in this
+    /// month (1 = January).
+    pub mark_in_month: Option<u8>,
+    /// Keep trashed items with the tag this many times the grace period.
+    pub grace_multiplier: Option<u32>,
+}
+
+impl TagPolicy {
+    pub fn validate(&self) -> Result<(), String> {
+        tag::normalize(&self.tag)?;
+        if self.mark_in_month.is_none() && self.grace_multiplier.is_none() {
+            return Err("needs mark_in_month or grace_multiplier".to_string());
+        }
+        if self.mark_in_month.is_some_and(|m| !(1..=12).contains(&m)) {
+            return Err("mark_in_month must be between 1 and 12".to_string());
+        }
+        if self.grace_multiplier == Some(0) {
+            return Err("grace_multiplier must be at least 1".to_string());
+        }
+        Ok(())
+    }
+
+    /// What the policy does in words, e.g. "marked every January".
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(month) = self.mark_in_month {
+            parts.push(format!("marked every {}", month_name(month)));
+        }
+        if let Some(multiplier) = self.grace_multiplier {
+            parts.push(format!("kept in the trash {multiplier}× as long"));
+        }
+        parts.join(", ")
+    }
+
+    /// Policy log actor for what the policy does, e.g. "tag policy seasonal".
+    fn actor(&self) -> String {
+        format!("tag policy {}", self.tag)
+    }
+}
+
+fn month_name(month: u8) -> &'static str {
+    const MONTHS: [&str; 12] = [
+        "January",
+        "February",
+        "March",
+        "April",
+        "May",
+        "June",
+        "July",
+        "August",
+        "September",
+        "October",
+        "November",
+        "December",
+    ];
+    MONTHS[usize::from(month.clamp(1, 12)) - 1]
+}
+
+/// Mark the items of every policy whose month it is, once per year.
+///
+/// Returns how many items the policies marked.
+#[tracing::instrument(skip_all)]
+pub async fn apply(
+    pool: &SqlitePool,
+    config: &AppConfig,
+    dry_run: bool,
+) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+    let now = jiff::Timestamp::now().to_zoned(jiff::tz::TimeZone::UTC);
+    let year_start = format!("{:04}-01-01 00:00:00", now.year());
+    let mut marked = 0;
+    for policy in &config.tag_policies {
+        if policy.mark_in_month != Some(now.month() as u8) {
+            continue;
+        }
+        let filter = MediaFilter {
+            tag: Some(&policy.tag),
+            ..MediaFilter::default()
+        };
+        for item in media::list_active_matching(pool, &filter).await? {
+            let detail = format!("marked for every user in {}", month_name(now.month() as u8));
+            if !policy_log::record_once(pool, &policy.tag, item.id, "mark", &detail, &year_start)
+                .await?
+            {
+                continue;
+            }
+            marked += 1;
+            mark::mark_for_all(pool, item.id).await?;
+            tracing::info!(
+                event = "policy_mark",
+                media_id = item.id,
+                tag = %policy.tag,
+                "Tag policy {} marked {}",
+                policy.tag,
+                item.path
+            );
+            trash::check_and_trash(pool, item.id, &policy.actor(), config, dry_run).await?;
+        }
+    }
+    Ok(marked)
+}
+
+/// The grace period for a trashed item with `tags`, and the policy that
+/// stretched it, if any. The longest of several matching policies wins.
+pub fn grace_period_for<'a>(
+    config: &'a AppConfig,
+    tags: &[String],
+) -> (u64, Option<&'a TagPolicy>) {
+    let policy = config
+        .tag_policies
+        .iter()
+        .filter(|p| p.grace_multiplier.is_some_and(|m| m > 1))
+        .filter(|p| tags.iter().any(|t| t.eq_ignore_ascii_case(&p.tag)))
+        .max_by_key(|p| p.grace_multiplier);
+    match policy {
+        Some(p) => (
+            config.grace_period_days * u64::from(p.grace_multiplier.unwrap_or(1)),
+            Some(p),
+        ),
+        None => (config.grace_period_days, None),
+    }
+}
+
+/// Grace periods of the trashed items a policy keeps longer, by media id.
+pub async fn trash_grace_periods(
+    pool: &SqlitePool,
+    config: &AppConfig,
+) -> Result<HashMap<i64, u64>, sqlx::Error> {
+    if config.tag_policies.is_empty() {
+        return Ok(HashMap::new());
+    }
+    Ok(tag::by_status(pool, "trashed")
+        .await?
+        .into_iter()
+        .filter_map(|(id, tags)| match grace_period_for(config, &tags) {
+            (days, Some(_)) => Some((id, days)),
+            (_, None) => None,
+        })
+        .collect())
+}
+
+/// Whether a policy keeps `item`, trashed longer ago than `grace_period_days`,
+/// in the trash a while longer. Logs the first time it does.
+pub async fn extends_grace(
+    pool: &SqlitePool,
+    config: &AppConfig,
+    item: &Media,
+    grace_period_days: u64,
+) -> Result<bool, sqlx::Error> {
+    let Some(trashed_at) = &item.trashed_at else {
+        return Ok(false);
+    };
+    let tags = tag::for_media(pool, item.id).await?;
+    let (days, Some(policy)) = grace_period_for(config, &tags) else {
+        return Ok(false);
+    };
+    if days <= grace_period_days
+        || locale::in_days(trashed_at, days, jiff::Timestamp::now()).is_none()
+    {
+        return Ok(false);
+    }
+    let detail = format!("kept in the trash for {days} days instead of {grace_period_days}");
+    if policy_log::record_once(
+        pool,
+        &policy.tag,
+        item.id,
+        "extend_grace",
+        &detail,
+        trashed_at,
+    )
+    .await?
+    {
+        tracing::info!(
+            media_id = item.id,
+            tag = %policy.tag,
+            "Tag policy {} keeps {} in the trash for {days} days",
+            policy.tag,
+            item.path
+        );
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(toml: &str) -> TagPolicy {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn policies_need_an_effect_and_a_valid_month() {
+        assert!(policy("tag = \"kids\"").validate().is_err());
+        assert!(policy("tag = \"kids\"\nmark_in_month = 13")
+            .validate()
+            .is_err());
+        assert!(policy("tag = \"kids\"\ngrace_multiplier = 0")
+            .validate()
+            .is_err());
+
+        let seasonal = policy("tag = \"seasonal\"\nmark_in_month = 1\ngrace_multiplier = 2");
+        assert!(seasonal.validate().is_ok());
+        assert_eq!(
+            seasonal.describe(),
+            "marked every January, kept in the trash 2× as long"
+        );
+    }
+}
@@ -0,0 +1,131 @@
+//! Blurred placeholders that cards show while their poster loads.
+//!
+//! A blurhash is computed from the smallest TMDB rendition when the poster is
+//! looked up and stored next to `poster_path`. Pages render it as a tiny
+//! bitmap in a data URL, which the browser stretches — and blurs — to the
+//! card's size, so no script is needed to decode it.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use jpeg_decoder::PixelFormat;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+/// Components of the hash; posters are portrait, so more rows than columns.
+const COMPONENTS_X: u32 = 3;
+const COMPONENTS_Y: u32 = 4;
+
+/// Size the placeholder bitmap is decoded at, in the 2:3 poster ratio.
+const PLACEHOLDER_WIDTH: u32 = 6;
+const PLACEHOLDER_HEIGHT: u32 = 9;
+
+/// Placeholders already decoded, by hash. Every card shows one on every
+/// render, and there is at most one hash per poster in the library.
+static PLACEHOLDERS: OnceLock<Mutex<HashMap<String, Option<String>>>> = OnceLock::new();
+
+/// Blurhash of a JPEG poster, or `None` if it cannot be decoded.
+pub fn hash_jpeg(jpeg: &[u8]) -> Option<String> {
+    let mut decoder = jpeg_decoder::Decoder::new(jpeg);
+    let pixels = decoder.decode().ok()?;
+    let info = decoder.info()?;
+    let rgba: Vec<u8> = match info.pixel_format {
+        PixelFormat::RGB24 => pixels
+            .chunks_exact(3)
+            .flat_map(|p| [p[0], p[1], p[2], 255])
+            .collect(),
+        PixelFormat::L8 => pixels.iter().flat_map(|&l| [l, l, l, 255]).collect(),
+        _ => return None,
+    };
+    blurhash::encode(
+        COMPONENTS_X,
+        COMPONENTS_Y,
+        info.width.into(),
+        info.height.into(),
+        &rgba,
+    )
+    .ok()
+}
+
+/// The placeholder for `hash` as a data URL, or `None` for a malformed hash.
+/// Each hash is only decoded once.
+pub fn placeholder_url(hash: &str) -> Option<String> {
+    let cache = PLACEHOLDERS.get_or_init(Default::default);
+    if let Some(known) = cache.lock().expect("placeholder cache poisoned").get(hash) {
+        return known.clone();
+    }
+    let url = decode_placeholder(hash);
+    cache
+        .lock()
+        .expect("placeholder cache poisoned")
+        .insert(hash.to_string(), url.clone());
+    url
+}
+
+fn decode_placeholder(hash: &str) -> Option<String> {
+    let rgba = blurhash::decode(hash, PLACEHOLDER_WIDTH, PLACEHOLDER_HEIGHT, 1.0).ok()?;
+    let bmp = bmp(PLACEHOLDER_WIDTH, PLACEHOLDER_HEIGHT, &rgba);
+    Some(format!("data:image/bmp;base64,{}", STANDARD.encode(bmp)))
+}
+
+/// An uncompressed 24-bit BMP of `rgba` pixels, the smallest format every
+/// browser decodes without pulling in an image encoder.
+fn bmp(width: u32, height: u32, rgba: &[u8]) -> Vec<u8> {
+    const HEADERS: u32 = 14 + 40;
+    let row_len = (width * 3).div_ceil(4) * 4;
+    let size = HEADERS + row_len * height;
+    let mut out = Vec::with_capacity(size as usize);
+    out.extend_from_slice(b"BM");
+    out.extend_from_slice(&size.to_le_bytes());
+    out.extend_from_slice(&0u32.to_le_bytes());
+    out.extend_from_slice(&HEADERS.to_le_bytes());
+    out.extend_from_slice(&40u32.to_le_bytes());
+    out.extend_from_slice(&(width as i32).to_le_bytes());
+    // A negative height stores the rows top-down.
+    out.extend_from_slice(&(-(height as i32)).to_le_bytes());
+    out.extend_from_slice(&1u16.to_le_bytes());
+    out.extend_from_slice(&24u16.to_le_bytes());
+    // No compression; image size, resolution and palette left at zero.
+    out.extend_from_slice(&[0; 24]);
+    for row in rgba.chunks_exact(width as usize * 4) {
+        for px in row.chunks_exact(4) {
+            out.extend_from_slice(&[px[2], px[1], px[0]]);
+        }
+        out.resize(out.len() + (row_len - width * 3) as usize, 0);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bmp_rows_are_padded_to_four_bytes() {
+        let rgba = [10, 20, 30, 255].repeat(2 * 3);
+        let bmp = bmp(2, 3, &rgba);
+        assert_eq!(&bmp[..2], b"BM");
+        assert_eq!(bmp.len(), 54 + 8 * 3);
+        assert_eq!(
+            u32::from_le_bytes(bmp[2..6].try_into().unwrap()) as usize,
+            bmp.len()
+        );
+        assert_eq!(&bmp[54..62], &[30, 20, 10, 30, 20, 10, 0, 0]);
+    }
+
+    #[test]
+    fn placeholder_decodes_a_hash_and_rejects_garbage() {
+        let url = placeholder_url("LEHV6nWB2yk8pyo0adR*.7kCMdnj").unwrap();
+        assert!(url.starts_with("data:image/bmp;base64,Qk"));
+        assert!(placeholder_url("nope").is_none());
+        assert_eq!(
+            placeholder_url("LEHV6nWB2yk8pyo0adR*.7kCMdnj").as_ref(),
+            Some(&url)
+        );
+        assert!(PLACEHOLDERS
+            .get()
+            .unwrap()
+            .lock()
+            .unwrap()
+            .contains_key("nope"));
+        assert!(hash_jpeg(b"not a jpeg").is_none());
+    }
+}
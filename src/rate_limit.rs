@@ -0,0 +1,75 @@
+//! Per-key request budgets, to stop scripts from hammering state-changing
+//! routes or guessing passwords.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Above this many tracked keys, fully refilled buckets are forgotten.
+const PRUNE_THRESHOLD: usize = 10_000;
+
+#[derive(Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Token buckets keyed by e.g. client IP or session token. Each key may make
+/// `per_minute` requests in a burst and regains one every `60 / per_minute`
+/// seconds.
+#[derive(Clone, Default)]
+pub struct RateLimiter(Arc<Mutex<HashMap<String, Bucket>>>);
+
+impl RateLimiter {
+    /// Spend one request of `key`'s budget. When it is used up, returns how
+    /// long until the next request is allowed.
+    pub fn check(&self, key: &str, per_minute: u32) -> Result<(), Duration> {
+        self.check_at(key, per_minute, Instant::now())
+    }
+
+    fn check_at(&self, key: &str, per_minute: u32, now: Instant) -> Result<(), Duration> {
+        let capacity = f64::from(per_minute);
+        let per_sec = capacity / 60.0;
+        let mut buckets = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        if buckets.len() > PRUNE_THRESHOLD {
+            buckets.retain(|_, b| {
+                b.tokens + now.duration_since(b.updated).as_secs_f64() * per_sec < capacity
+            });
+        }
+
+        let bucket = buckets.entry(key.to_string()).or_insert(Bucket {
+            tokens: capacity,
+            updated: now,
+        });
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * per_sec).min(capacity);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / per_sec))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allows_a_burst_then_refills() {
+        let limiter = RateLimiter::default();
+        let start = Instant::now();
+        for _ in 0..3 {
+            assert!(limiter.check_at("1.2.3.4", 3, start).is_ok());
+        }
+        let wait = limiter.check_at("1.2.3.4", 3, start).unwrap_err();
+        assert_eq!(wait.as_secs(), 20);
+        assert!(limiter.check_at("5.6.7.8", 3, start).is_ok());
+
+        let later = start + Duration::from_secs(20);
+        assert!(limiter.check_at("1.2.3.4", 3, later).is_ok());
+        assert!(limiter.check_at("1.2.3.4", 3, later).is_err());
+    }
+}
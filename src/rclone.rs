@@ -0,0 +1,196 @@
+//! Cloud archive transfers through [rclone](https://rclone.org), for offsite
+//! storage that is only reachable through one of its backends, e.g. an
+//! encrypting `crypt` remote.
+//!
+//! rclone runs as a subprocess with JSON logging, and its periodic stats
+//! lines are turned into progress log lines.
+
+use serde::Deserialize;
+use serde_json::Value;
+use std::path::PathBuf;
+use std::process::Stdio;
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+/// How often rclone reports progress.
+const STATS_INTERVAL: &str = "30s";
+
+/// The `[rclone_archive]` config table.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+pub struct RcloneConfig {
+    /// Remote and folder that items are uploaded to, e.g. "offsite-crypt:rewinder".
+    pub remote: String,
+    /// The rclone executable, looked up on `PATH` by default.
+    #[serde(default = "default_binary")]
+    pub binary: PathBuf,
+    /// rclone.conf to use instead of rclone's default one.
+    pub config: Option<PathBuf>,
+    /// Extra flags for every transfer, e.g. `["--bwlimit", "10M"]`.
+    #[serde(default)]
+    pub flags: Vec<String>,
+}
+
+fn default_binary() -> PathBuf {
+    PathBuf::from("rclone")
+}
+
+impl RcloneConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        match self.remote.split_once(':') {
+            Some((name, _)) if !name.is_empty() && !name.contains('/') => Ok(()),
+            _ => Err(format!(
+                "remote {:?} must name an rclone remote, e.g. \"offsite:rewinder\"",
+                self.remote
+            )),
+        }
+    }
+
+    /// Where `name` goes on the remote.
+    pub fn path_for(&self, name: &str) -> String {
+        if self.remote.ends_with(':') {
+            format!("{}{name}", self.remote)
+        } else {
+            format!("{}/{name}", self.remote.trim_end_matches('/'))
+        }
+    }
+
+    /// Whether `path` was uploaded below this remote.
+    pub fn holds(&self, path: &str) -> bool {
+        path.starts_with(&self.path_for(""))
+    }
+}
+
+/// Progress from one of rclone's JSON stats lines.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+    pub bytes: u64,
+    pub total_bytes: u64,
+    /// Bytes per second.
+    pub speed: f64,
+}
+
+impl Progress {
+    pub fn percent(&self) -> u64 {
+        (self.bytes * 100)
+            .checked_div(self.total_bytes)
+            .unwrap_or(0)
+            .min(100)
+    }
+}
+
+/// The progress in a JSON log line, if it is a stats line.
+fn parse_progress(line: &str) -> Option<Progress> {
+    let json: Value = serde_json::from_str(line).ok()?;
+    let stats = json.get("stats")?;
+    Some(Progress {
+        bytes: stats.get("bytes")?.as_u64()?,
+        total_bytes: stats.get("totalBytes").and_then(Value::as_u64).unwrap_or(0),
+        speed: stats.get("speed").and_then(Value::as_f64).unwrap_or(0.0),
+    })
+}
+
+/// The message of a JSON log line at error level.
+fn parse_error(line: &str) -> Option<String> {
+    let json: Value = serde_json::from_str(line).ok()?;
+    if json.get("level")?.as_str()? != "error" {
+        return None;
+    }
+    Some(json.get("msg")?.as_str()?.trim().to_string())
+}
+
+/// Copy `src` to `dst` with `rclone copyto`, either of which may be a local
+/// path or a remote one, and a file or a folder.
+pub async fn copy(
+    config: &RcloneConfig,
+    src: &str,
+    dst: &str,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let mut command = tokio::process::Command::new(&config.binary);
+    command
+        .arg("copyto")
+        .arg(src)
+        .arg(dst)
+        .args(["--use-json-log", "--stats", STATS_INTERVAL])
+        .args(["--stats-log-level", "NOTICE"]);
+    if let Some(path) = &config.config {
+        command.arg("--config").arg(path);
+    }
+    command
+        .args(&config.flags)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    let mut child = command
+        .spawn()
+        .map_err(|e| format!("failed to run {}: {e}", config.binary.display()))?;
+
+    let stderr = child.stderr.take().expect("stderr is piped");
+    let mut lines = BufReader::new(stderr).lines();
+    let mut last_error = None;
+    while let Some(line) = lines.next_line().await? {
+        if let Some(progress) = parse_progress(&line) {
+            if progress.total_bytes > 0 {
+                tracing::info!(
+                    "rclone {src} → {dst}: {}% of {} at {}/s",
+                    progress.percent(),
+                    crate::templates::format_size(&(progress.total_bytes as i64)),
+                    crate::templates::format_size(&(progress.speed as i64))
+                );
+            }
+        } else if let Some(error) = parse_error(&line) {
+            tracing::debug!("rclone: {error}");
+            last_error = Some(error);
+        }
+    }
+
+    let status = child.wait().await?;
+    if !status.success() {
+        return Err(match last_error {
+            Some(error) => format!("rclone failed ({status}): {error}"),
+            None => format!("rclone failed ({status})"),
+        }
+        .into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_progress_and_errors_from_json_logs() {
+        let stats = r#"{"level":"notice","msg":"\nTransferred: 1 GiB / 4 GiB, 25%\n","stats":{"bytes":1073741824,"totalBytes":4294967296,"speed":52428800.5,"transfers":0},"time":"2024-05-01T10:00:00Z"}"#;
+        let progress = parse_progress(stats).unwrap();
+        assert_eq!(progress.bytes, 1 << 30);
+        assert_eq!(progress.percent(), 25);
+        assert_eq!(parse_error(stats), None);
+
+        let error = r#"{"level":"error","msg":"Failed to copy: directory not found","time":"2024-05-01T10:00:01Z"}"#;
+        assert_eq!(parse_progress(error), None);
+        assert_eq!(
+            parse_error(error).as_deref(),
+            Some("Failed to copy: directory not found")
+        );
+        assert_eq!(parse_progress("2024/05/01 plain text"), None);
+    }
+
+    #[test]
+    fn paths_sit_below_the_remote() {
+        let config = |remote: &str| RcloneConfig {
+            remote: remote.to_string(),
+            binary: default_binary(),
+            config: None,
+            flags: Vec::new(),
+        };
+        assert_eq!(
+            config("crypt:rewinder/").path_for("7-Heat"),
+            "crypt:rewinder/7-Heat"
+        );
+        assert_eq!(config("crypt:").path_for("7-Heat"), "crypt:7-Heat");
+        assert!(config("crypt:rewinder").holds("crypt:rewinder/7-Heat"));
+        assert!(!config("crypt:rewinder").holds("rewinder/7-Heat.tar"));
+        assert!(config("crypt:rewinder").validate().is_ok());
+        assert!(config("/mnt/backup").validate().is_err());
+    }
+}
@@ -0,0 +1,73 @@
+//! Moving an item from one media dir to another, e.g. a 4K remux that was
+//! re-encoded and now belongs in the 1080p library.
+
+use sqlx::SqlitePool;
+use std::path::{Path, PathBuf};
+
+use crate::config::AppConfig;
+use crate::mediaserver;
+use crate::models::media;
+use crate::paths::{library_of, relative_to};
+use crate::trash::archive::move_blocking;
+
+/// Move the item `media_id` into the media dir `library`, keeping its path
+/// relative to the media dir it is in now, and track it there. Returns the
+/// new path.
+///
+/// Libraries on different disks are copied across. If the new path cannot be
+/// recorded, the item is moved back so the database never points at a path
+/// that is gone.
+#[tracing::instrument(skip(pool, config))]
+pub async fn move_to_library(
+    pool: &SqlitePool,
+    media_id: i64,
+    library: &Path,
+    actor: &str,
+    config: &AppConfig,
+    dry_run: bool,
+) -> Result<PathBuf, Box<dyn std::error::Error + Send + Sync>> {
+    let item = media::get_by_id(pool, media_id)
+        .await?
+        .ok_or("Media not found")?;
+    if item.status != "active" {
+        return Err(format!("cannot move media in status {}", item.status).into());
+    }
+    if !config.media_dirs.iter().any(|dir| dir == library) {
+        return Err(format!("{} is not one of the media dirs", library.display()).into());
+    }
+    let src = PathBuf::from(&item.path);
+    let current =
+        library_of(config, &src).ok_or_else(|| format!("{} is not in any media dir", item.path))?;
+    if current == library {
+        return Err(format!("{} is already in {}", item.path, library.display()).into());
+    }
+    let dest = library.join(
+        relative_to(current, &src)
+            .ok_or_else(|| format!("{} is not in {}", item.path, current.display()))?,
+    );
+    if dest.exists() {
+        return Err(format!("Cannot move: {} already exists", dest.display()).into());
+    }
+
+    if dry_run {
+        tracing::info!("DRY RUN: would move {} → {}", item.path, dest.display());
+        return Ok(dest);
+    }
+    move_blocking(src.clone(), dest.clone()).await?;
+    if let Err(e) = media::set_path(pool, media_id, &dest.to_string_lossy()).await {
+        tracing::error!("Failed to record {}, moving it back: {e}", dest.display());
+        move_blocking(dest, src).await?;
+        return Err(e.into());
+    }
+    mediaserver::refresh_after_move(config, &[&src, &dest]);
+    tracing::info!(
+        event = "library_move",
+        media_id,
+        user = %actor,
+        path = %dest.display(),
+        "Moved {} → {}",
+        item.path,
+        dest.display()
+    );
+    Ok(dest)
+}
@@ -0,0 +1,101 @@
+//! Renaming movie folders to the "Title (Year)" form once an admin has
+//! corrected their metadata, so the library tidies up as titles get fixed.
+
+use sqlx::SqlitePool;
+use std::path::{Path, PathBuf};
+
+use crate::config::AppConfig;
+use crate::mediaserver;
+use crate::models::media;
+
+/// Characters that are not allowed in file names on common filesystems.
+const FORBIDDEN: &[char] = &['/', '\\', ':', '*', '?', '"', '<', '>', '|'];
+
+/// "Title (Year)", or just the title without a year, with characters that
+/// cannot appear in a file name dropped.
+pub fn canonical_movie_name(title: &str, year: Option<i64>) -> String {
+    let title: String = title.chars().filter(|c| !FORBIDDEN.contains(c)).collect();
+    let title = title.trim().trim_end_matches('.');
+    match year {
+        Some(year) => format!("{title} ({year})"),
+        None => title.to_string(),
+    }
+}
+
+/// Where `path` goes when renamed to `name`; bare files keep their extension.
+fn renamed_path(path: &Path, name: &str) -> PathBuf {
+    let extension = path
+        .extension()
+        .filter(|_| path.is_file())
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+    path.with_file_name(format!("{name}{extension}"))
+}
+
+/// Rename the movie `media_id` on disk to its canonical name and track it
+/// there. Returns the new path, or `None` when the name already fits.
+#[tracing::instrument(skip(pool, config))]
+pub async fn rename_to_canonical(
+    pool: &SqlitePool,
+    media_id: i64,
+    actor: &str,
+    config: &AppConfig,
+    dry_run: bool,
+) -> Result<Option<PathBuf>, Box<dyn std::error::Error + Send + Sync>> {
+    let item = media::get_by_id(pool, media_id)
+        .await?
+        .ok_or("Media not found")?;
+    if item.media_type != "movie" {
+        return Err("only movies can be renamed to Title (Year)".into());
+    }
+    if item.status != "active" && item.status != "permanent" {
+        return Err(format!("cannot rename media in status {}", item.status).into());
+    }
+
+    let name = canonical_movie_name(&item.title, item.year);
+    if name.is_empty() {
+        return Err(format!("{:?} leaves no usable file name", item.title).into());
+    }
+    let original_path = Path::new(&item.path);
+    let dest = renamed_path(original_path, &name);
+    if dest == original_path {
+        return Ok(None);
+    }
+    if dest.exists() {
+        return Err(format!("Cannot rename: {} already exists", dest.display()).into());
+    }
+
+    if dry_run {
+        tracing::info!("DRY RUN: would rename {} → {}", item.path, dest.display());
+        return Ok(Some(dest));
+    }
+    std::fs::rename(original_path, &dest)?;
+    media::set_path(pool, media_id, &dest.to_string_lossy()).await?;
+    mediaserver::refresh_after_move(config, &[original_path, &dest]);
+    tracing::info!(
+        event = "rename",
+        media_id,
+        user = %actor,
+        path = %dest.display(),
+        "Renamed {} → {}",
+        item.path,
+        dest.display()
+    );
+    Ok(Some(dest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn canonical_names_drop_forbidden_characters() {
+        assert_eq!(canonical_movie_name("Aliens", Some(1986)), "Aliens (1986)");
+        assert_eq!(
+            canonical_movie_name("Mission: Impossible", Some(1996)),
+            "Mission Impossible (1996)"
+        );
+        assert_eq!(canonical_movie_name("What If...?", None), "What If");
+        assert_eq!(canonical_movie_name("AC/DC", None), "ACDC");
+    }
+}
@@ -0,0 +1,171 @@
+use axum::extract::{Path, Query, State};
+use axum::http::HeaderMap;
+use axum::response::{Html, IntoResponse, Redirect};
+use axum::routing::{get, post};
+use axum::{Form, Router};
+use serde::Deserialize;
+
+use crate::auth::middleware::AuthUser;
+use crate::auth::session;
+use crate::error::AppError;
+use crate::locale;
+use crate::models::preferences::{self, Preferences};
+use crate::models::user;
+use crate::models::{announcement, expiry_warning};
+use crate::routes::{feed, url, AppState};
+use crate::templates::AccountTemplate;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/account", get(account_page).post(save_account))
+        .route("/account/preferences", post(save_preferences))
+        .route("/account/feed", post(reset_feed_token))
+        .route("/announcements/{id}/dismiss", post(dismiss_announcement))
+        .route(
+            "/expiry-warnings/{id}/dismiss",
+            post(dismiss_expiry_warning),
+        )
+}
+
+#[derive(Deserialize)]
+struct AccountQuery {
+    #[serde(default)]
+    saved: Option<String>,
+}
+
+async fn account_page(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Query(query): Query<AccountQuery>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, AppError> {
+    let u = user::get_by_id(&state.pool, auth.id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    let prefs = preferences::get(&state.pool, auth.id).await?;
+    Ok(AccountTemplate {
+        username: auth.username,
+        is_admin: auth.is_admin,
+        locale: u
+            .locale
+            .unwrap_or_else(|| locale::DEFAULT_LOCALE.to_string()),
+        timezone: u
+            .timezone
+            .unwrap_or_else(|| locale::DEFAULT_TIMEZONE.to_string()),
+        timezones: locale::timezones(),
+        prefs,
+        feed_url: u
+            .feed_token
+            .map(|token| format!("{}/feed/{token}", feed::site_url(&headers))),
+        message: query.saved.map(|_| "Settings saved".to_string()),
+    })
+}
+
+/// Back to the account page, which then renders with the new settings.
+fn saved() -> Redirect {
+    Redirect::to(&url("/account?saved=true"))
+}
+
+#[derive(Deserialize)]
+struct AccountForm {
+    locale: String,
+    timezone: String,
+}
+
+async fn save_account(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Form(form): Form<AccountForm>,
+) -> Result<impl IntoResponse, AppError> {
+    if !locale::is_known_locale(&form.locale) {
+        return Err(AppError::BadRequest(format!(
+            "unknown date format {}",
+            form.locale
+        )));
+    }
+    if !locale::is_known_timezone(&form.timezone) {
+        return Err(AppError::BadRequest(format!(
+            "unknown timezone {}",
+            form.timezone
+        )));
+    }
+    user::set_time_settings(&state.pool, auth.id, &form.locale, &form.timezone).await?;
+    Ok(saved())
+}
+
+/// Create the user's trash feed URL, or replace it if the old one leaked.
+async fn reset_feed_token(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<impl IntoResponse, AppError> {
+    user::set_feed_token(&state.pool, auth.id, &session::generate_token()).await?;
+    Ok(saved())
+}
+
+#[derive(Deserialize)]
+struct PreferencesForm {
+    sort: String,
+    sort_dir: String,
+    #[serde(default)]
+    show_marked: Option<String>,
+    page_size: i64,
+    theme: String,
+}
+
+async fn save_preferences(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Form(form): Form<PreferencesForm>,
+) -> Result<impl IntoResponse, AppError> {
+    if !preferences::SORTS.iter().any(|(s, _)| *s == form.sort) {
+        return Err(AppError::BadRequest(format!("unknown sort {}", form.sort)));
+    }
+    if !matches!(form.sort_dir.as_str(), "asc" | "desc") {
+        return Err(AppError::BadRequest(format!(
+            "unknown sort direction {}",
+            form.sort_dir
+        )));
+    }
+    if !preferences::PAGE_SIZES.contains(&form.page_size) {
+        return Err(AppError::BadRequest(format!(
+            "unsupported page size {}",
+            form.page_size
+        )));
+    }
+    if !preferences::THEMES.iter().any(|(t, _)| *t == form.theme) {
+        return Err(AppError::BadRequest(format!(
+            "unknown theme {}",
+            form.theme
+        )));
+    }
+
+    let prefs = Preferences {
+        sort: form.sort,
+        sort_dir: form.sort_dir,
+        show_marked: form.show_marked.is_some(),
+        page_size: form.page_size,
+        theme: form.theme,
+    };
+    preferences::save(&state.pool, auth.id, &prefs).await?;
+    Ok(saved())
+}
+
+/// Hide an announcement from this user; htmx swaps the banner for nothing.
+async fn dismiss_announcement(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<i64>,
+) -> Result<Html<&'static str>, AppError> {
+    announcement::dismiss(&state.pool, id, auth.id).await?;
+    Ok(Html(""))
+}
+
+/// Hide the warning that a trashed item is deleted soon from this user.
+async fn dismiss_expiry_warning(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(media_id): Path<i64>,
+) -> Result<Html<&'static str>, AppError> {
+    expiry_warning::dismiss(&state.pool, media_id, auth.id).await?;
+    Ok(Html(""))
+}
@@ -1,27 +1,99 @@
-use axum::extract::{Path, State};
+use axum::extract::{Path, Query, State};
+use axum::http::header;
 use axum::response::{IntoResponse, Redirect, Response};
 use axum::routing::{get, post};
-use axum::{Form, Router};
-use serde::Deserialize;
+use axum::{Form, Json, Router};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
 
 use crate::auth::middleware::AdminUser;
 use crate::auth::session;
 use crate::error::AppError;
-use crate::models::{mark, media, persistent, user};
-use crate::routes::AppState;
+use crate::manifest::{self, ManifestEntry};
+use crate::models::media_history::FreedMonth;
+use crate::models::{
+    announcement, comment, db_maintenance, deletion_approval, edition, library_access, mark, media,
+    media_history, pending_trash, persistent, policy_log, rerequest, rule_hit, scan_summary,
+    setting, tag, trash_queue, user,
+};
+use crate::routes::{url, AppState};
+use crate::scanner::{GonePolicy, ScanMode, ScanSnapshot};
 use crate::templates;
-use crate::templates::{AdminDashboardTemplate, AdminTrashTemplate, AdminUsersTemplate};
+use crate::templates::{
+    AdminApprovalsTemplate, AdminArchiveTemplate, AdminDashboardTemplate, AdminDeletedTemplate,
+    AdminManifestTemplate, AdminRulesTemplate, AdminSettingsTemplate, AdminTrashTemplate,
+    AdminUsersTemplate, MediaCardPartial, MediaRow, SettingRow,
+};
 
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/admin", get(dashboard))
         .route("/admin/users", get(users_page).post(create_user))
+        .route("/admin/users/bulk", post(create_users))
         .route("/admin/users/{id}/delete", post(delete_user))
+        .route("/admin/users/{id}/restricted", post(set_user_restricted))
         .route("/admin/trash", get(trash_page))
         .route("/admin/trash/{id}/rescue", post(rescue_item))
+        .route("/admin/trash/{id}/delete", post(delete_trash_item))
+        .route("/admin/approvals", get(approvals_page))
+        .route("/admin/pending-trash/{id}/confirm", post(confirm_trash))
+        .route("/admin/pending-trash/{id}/reject", post(reject_trash))
+        .route("/admin/deleted", get(deleted_page))
+        .route(
+            "/admin/deleted/{id}/cloud-restore",
+            post(restore_from_cloud),
+        )
+        .route("/admin/deleted/{id}/dismiss", post(dismiss_rerequests))
+        .route("/admin/archive", get(archive_page))
+        .route("/admin/manifest", get(manifest_page))
+        .route("/admin/manifest/{file}", get(manifest_download))
+        .route("/admin/archive/{id}/restore", post(restore_archived))
+        .route("/admin/rules", get(rules_page))
+        .route("/admin/export/media.csv", get(export_media_csv))
+        .route("/admin/export/media.json", get(export_media_json))
+        .route("/admin/largest.json", get(largest_json))
+        .route("/admin/space-freed.json", get(space_freed_json))
         .route("/admin/scan", post(trigger_scan))
+        .route("/admin/scan/status", get(scan_status))
+        .route("/admin/scan/library/{index}", post(trigger_library_scan))
+        .route("/admin/libraries/{index}/grant", post(grant_library))
+        .route("/admin/libraries/{index}/revoke", post(revoke_library))
+        .route(
+            "/admin/scan/library/{index}/confirm-gone",
+            post(confirm_library_gone),
+        )
+        .route("/admin/media", post(add_media))
+        .route("/admin/media/{id}/type", post(set_media_type))
+        .route("/admin/media/{id}/edit", post(edit_media))
+        .route("/admin/media/{id}/library", post(move_library))
+        .route("/admin/media/{id}/editions", post(link_edition))
+        .route("/admin/media/{id}/unlink-edition", post(unlink_edition))
+        .route("/admin/media/{id}/refresh", post(refresh_media))
+        .route(
+            "/admin/media/{id}/protect",
+            post(protect_media).delete(unprotect_media),
+        )
+        .route("/admin/config/reload", post(reload_config))
+        .route("/admin/settings", get(settings_page).post(save_settings))
+        .route("/admin/dry-run", post(set_dry_run))
+        .route("/admin/maintenance", post(set_maintenance))
+        .route("/admin/announcements", post(post_announcement))
+        .route(
+            "/admin/announcements/{id}/delete",
+            post(delete_announcement),
+        )
 }
 
+/// How many of the largest active and persisted items the dashboard lists.
+const LARGEST_ITEMS: i64 = 10;
+/// Upper bound for `?limit=` on the JSON endpoint.
+const MAX_LARGEST_ITEMS: i64 = 500;
+/// How many months of freed space the dashboard covers.
+const SPACE_FREED_MONTHS: i64 = 12;
+/// Upper bound for `?months=` on the JSON endpoint.
+const MAX_SPACE_FREED_MONTHS: i64 = 120;
+
 async fn dashboard(
     State(state): State<AppState>,
     admin: AdminUser,
@@ -31,6 +103,13 @@ async fn dashboard(
     let active_size = media::total_active_size(&state.pool).await?;
     let trashed_size = media::total_trashed_size(&state.pool).await?;
     let user_count = user::count(&state.pool).await?;
+    let scan_summaries = scan_summary::list_recent(&state.pool, 5).await?;
+    let db_maintenance = db_maintenance::list(&state.pool).await?;
+    let largest_active = media::list_largest(&state.pool, "active", LARGEST_ITEMS).await?;
+    let largest_persisted = media::list_largest(&state.pool, "permanent", LARGEST_ITEMS).await?;
+    let config = state.config.current();
+    let space_freed =
+        media_history::freed_by_month(&state.pool, &config.media_dirs, SPACE_FREED_MONTHS).await?;
 
     Ok(AdminDashboardTemplate {
         username: admin.username.clone(),
@@ -40,6 +119,20 @@ async fn dashboard(
         active_size: templates::format_size(&active_size),
         trashed_size: templates::format_size(&trashed_size),
         user_count,
+        watcher: state.watcher_health.snapshot(),
+        scan: state.scan_status.snapshot(),
+        media_dirs: config
+            .media_dirs
+            .iter()
+            .map(|d| d.display().to_string())
+            .collect(),
+        users: user::list_all(&state.pool).await?,
+        grants: library_access::grants(&state.pool).await?,
+        scan_summaries,
+        db_maintenance,
+        largest_active,
+        largest_persisted,
+        space_freed,
     })
 }
 
@@ -53,7 +146,8 @@ async fn users_page(
         username: admin.username.clone(),
         is_admin: true,
         users,
-        invite_url: None,
+        invites: Vec::new(),
+        skipped: Vec::new(),
     })
 }
 
@@ -71,28 +165,81 @@ async fn create_user(
     user::create(&state.pool, &form.username, false, Some(&token)).await?;
 
     let users = user::list_all(&state.pool).await?;
-    let invite_url = format!("/invite/{token}");
+    let invite_url = url(&format!("/invite/{token}"));
 
     Ok(AdminUsersTemplate {
         username: admin.username.clone(),
         is_admin: true,
         users,
-        invite_url: Some(invite_url),
+        invites: vec![(form.username, invite_url)],
+        skipped: Vec::new(),
+    })
+}
+
+#[derive(Deserialize)]
+struct CreateUsersForm {
+    usernames: String,
+}
+
+/// Names from a pasted list, one per line or separated by commas or spaces,
+/// in their original order without duplicates.
+fn parse_usernames(list: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for name in list.split(|c: char| c == ',' || c.is_whitespace()) {
+        if !name.is_empty() && !names.iter().any(|n| n == name) {
+            names.push(name.to_string());
+        }
+    }
+    names
+}
+
+/// Create an invited user for every name in the list, skipping existing ones.
+async fn create_users(
+    State(state): State<AppState>,
+    admin: AdminUser,
+    Form(form): Form<CreateUsersForm>,
+) -> Result<impl IntoResponse, AppError> {
+    let mut invites = Vec::new();
+    let mut skipped = Vec::new();
+    for name in parse_usernames(&form.usernames) {
+        if user::get_by_username(&state.pool, &name).await?.is_some() {
+            skipped.push(name);
+            continue;
+        }
+        let token = session::generate_token();
+        user::create(&state.pool, &name, false, Some(&token)).await?;
+        invites.push((name, url(&format!("/invite/{token}"))));
+    }
+    tracing::info!(
+        "{} invited {} users, skipped {} existing",
+        admin.username,
+        invites.len(),
+        skipped.len()
+    );
+
+    Ok(AdminUsersTemplate {
+        username: admin.username.clone(),
+        is_admin: true,
+        users: user::list_all(&state.pool).await?,
+        invites,
+        skipped,
     })
 }
 
 async fn delete_user(
     State(state): State<AppState>,
-    _admin: AdminUser,
+    admin: AdminUser,
     Path(id): Path<i64>,
 ) -> Result<Response, AppError> {
+    let config = state.config.current();
     let owned_persistent = persistent::list_media_ids_by_owner(&state.pool, id).await?;
     for media_id in owned_persistent {
         crate::persistent::restore_from_permanent_unchecked(
             &state.pool,
             media_id,
-            &state.config,
-            state.dry_run,
+            &admin.username,
+            &config,
+            state.dry_run.get(),
         )
         .await
         .map_err(|e| AppError::Internal(format!("failed to restore persistent media: {e}")))?;
@@ -101,13 +248,49 @@ async fn delete_user(
     user::delete(&state.pool, id).await?;
 
     // After deleting a user, check if any media now has all users marked
-    let eligible = mark::media_ids_with_all_marked(&state.pool).await?;
+    let eligible = mark::media_ids_with_all_marked(&state.pool, config.require_admin_mark).await?;
     for media_id in eligible {
-        let _ = crate::trash::check_and_trash(&state.pool, media_id, &state.config, state.dry_run)
-            .await;
+        let _ = crate::trash::check_and_trash(
+            &state.pool,
+            media_id,
+            &admin.username,
+            &config,
+            state.dry_run.get(),
+        )
+        .await;
+    }
+
+    Ok(Redirect::to(&url("/admin/users")).into_response())
+}
+
+/// Make a user restricted, so their marks no longer count, or lift it.
+async fn set_user_restricted(
+    State(state): State<AppState>,
+    admin: AdminUser,
+    Path(id): Path<i64>,
+    Form(form): Form<SwitchForm>,
+) -> Result<Response, AppError> {
+    let u = user::get_by_id(&state.pool, id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    if u.is_admin {
+        return Err(AppError::BadRequest("admins cannot be restricted".into()));
     }
+    user::set_restricted(&state.pool, id, form.enabled).await?;
+    let mode = if form.enabled {
+        "restricted"
+    } else {
+        "unrestricted"
+    };
+    tracing::info!(event = "user_restricted", user = %admin.username, "{} {mode} {}", admin.username, u.username);
 
-    Ok(Redirect::to("/admin/users").into_response())
+    // Items only this user had left unmarked now have everyone's marks.
+    if form.enabled {
+        crate::trash::trash_deferred(&state.pool, &state.config.current(), state.dry_run.get())
+            .await
+            .map_err(|e| AppError::Internal(format!("trash operation failed: {e}")))?;
+    }
+    Ok(Redirect::to(&url("/admin/users")).into_response())
 }
 
 async fn trash_page(
@@ -115,38 +298,1112 @@ async fn trash_page(
     admin: AdminUser,
 ) -> Result<impl IntoResponse, AppError> {
     let items = media::list_trashed(&state.pool).await?;
+    let config = state.config.current();
+    let hardlinked = {
+        let locations: Vec<(i64, std::path::PathBuf)> = items
+            .iter()
+            .filter_map(|m| Some((m.id, crate::trash::trash_location_for(&config, m)?)))
+            .collect();
+        tokio::task::spawn_blocking(move || {
+            locations
+                .into_iter()
+                .filter_map(|(id, location)| {
+                    let linked = crate::trash::hardlinked_files(&location);
+                    (linked > 0).then_some((id, linked))
+                })
+                .collect()
+        })
+        .await
+        .map_err(|e| AppError::Internal(format!("hardlink check failed: {e}")))?
+    };
 
     Ok(AdminTrashTemplate {
         username: admin.username.clone(),
         is_admin: true,
         items,
+        grace_period_days: config.grace_period_days,
+        extended_grace: crate::policies::trash_grace_periods(&state.pool, &config).await?,
+        hardlinked,
+        queued: trash_queue::list(&state.pool).await?,
+        trash_window: config.trash_window.map(|w| w.to_string()),
     })
 }
 
-async fn rescue_item(
+/// One row of the inventory export.
+#[derive(Serialize)]
+struct InventoryItem {
+    id: i64,
+    media_type: String,
+    title: String,
+    year: Option<i64>,
+    season: Option<i64>,
+    status: String,
+    size_bytes: i64,
+    path: String,
+    first_seen: String,
+    last_seen: String,
+    trashed_at: Option<String>,
+}
+
+impl From<media::Media> for InventoryItem {
+    fn from(m: media::Media) -> Self {
+        Self {
+            id: m.id,
+            media_type: m.media_type,
+            title: m.title,
+            year: m.year,
+            season: m.season,
+            status: m.status,
+            size_bytes: m.size_bytes,
+            path: m.path,
+            first_seen: m.first_seen,
+            last_seen: m.last_seen,
+            trashed_at: m.trashed_at,
+        }
+    }
+}
+
+async fn inventory(state: &AppState) -> Result<Vec<InventoryItem>, AppError> {
+    let items = media::list_all(&state.pool).await?;
+    Ok(items.into_iter().map(InventoryItem::from).collect())
+}
+
+/// Quote a CSV field if it contains a separator, quote or line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+async fn export_media_csv(
+    State(state): State<AppState>,
+    _admin: AdminUser,
+) -> Result<impl IntoResponse, AppError> {
+    let opt = |v: Option<i64>| v.map(|n| n.to_string()).unwrap_or_default();
+    let mut csv = String::from(
+        "id,media_type,title,year,season,status,size_bytes,path,first_seen,last_seen,trashed_at\r\n",
+    );
+    for item in inventory(&state).await? {
+        let row = [
+            item.id.to_string(),
+            item.media_type,
+            item.title,
+            opt(item.year),
+            opt(item.season),
+            item.status,
+            item.size_bytes.to_string(),
+            item.path,
+            item.first_seen,
+            item.last_seen,
+            item.trashed_at.unwrap_or_default(),
+        ];
+        let row: Vec<String> = row.iter().map(|f| csv_field(f)).collect();
+        csv.push_str(&row.join(","));
+        csv.push_str("\r\n");
+    }
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8"),
+            (
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"media.csv\"",
+            ),
+        ],
+        csv,
+    ))
+}
+
+async fn export_media_json(
+    State(state): State<AppState>,
+    _admin: AdminUser,
+) -> Result<impl IntoResponse, AppError> {
+    Ok((
+        [(
+            header::CONTENT_DISPOSITION,
+            "attachment; filename=\"media.json\"",
+        )],
+        Json(inventory(&state).await?),
+    ))
+}
+
+/// A month of the deletion manifest as CSV, with the marks joined as
+/// "user (marked_at)" in one column.
+fn manifest_csv(entries: &[ManifestEntry]) -> String {
+    let opt = |v: Option<i64>| v.map(|n| n.to_string()).unwrap_or_default();
+    let mut csv = String::from(
+        "deleted_at,deleted_by,media_id,media_type,title,year,season,path,size_bytes,trashed_at,marked_by\r\n",
+    );
+    for entry in entries {
+        let marked_by: Vec<String> = entry
+            .marked_by
+            .iter()
+            .map(|m| format!("{} ({})", m.user, m.marked_at))
+            .collect();
+        let row = [
+            entry.deleted_at.clone(),
+            entry.deleted_by.clone(),
+            entry.media_id.to_string(),
+            entry.media_type.clone(),
+            entry.title.clone(),
+            opt(entry.year),
+            opt(entry.season),
+            entry.path.clone(),
+            entry.size_bytes.to_string(),
+            entry.trashed_at.clone().unwrap_or_default(),
+            marked_by.join("; "),
+        ];
+        let row: Vec<String> = row.iter().map(|f| csv_field(f)).collect();
+        csv.push_str(&row.join(","));
+        csv.push_str("\r\n");
+    }
+    csv
+}
+
+async fn manifest_page(
+    State(state): State<AppState>,
+    admin: AdminUser,
+) -> Result<impl IntoResponse, AppError> {
+    let manifest_dir = state.config.current().deletion_manifest_dir.clone();
+    let months = match &manifest_dir {
+        Some(dir) => manifest::months(dir).map_err(|e| AppError::Internal(e.to_string()))?,
+        None => Vec::new(),
+    };
+    Ok(AdminManifestTemplate {
+        username: admin.username.clone(),
+        is_admin: true,
+        months,
+        manifest_dir: manifest_dir.map(|dir| dir.display().to_string()),
+    })
+}
+
+/// `/admin/manifest/2024-05.jsonl` or `.csv`.
+async fn manifest_download(
+    State(state): State<AppState>,
+    _admin: AdminUser,
+    Path(file): Path<String>,
+) -> Result<Response, AppError> {
+    let dir = state
+        .config
+        .current()
+        .deletion_manifest_dir
+        .clone()
+        .ok_or(AppError::NotFound)?;
+    let (month, csv) = match (file.strip_suffix(".jsonl"), file.strip_suffix(".csv")) {
+        (Some(month), _) => (month, false),
+        (_, Some(month)) => (month, true),
+        _ => return Err(AppError::NotFound),
+    };
+    if !manifest::is_month(month) {
+        return Err(AppError::NotFound);
+    }
+    let not_found = |e: std::io::Error| match e.kind() {
+        std::io::ErrorKind::NotFound => AppError::NotFound,
+        _ => AppError::Internal(e.to_string()),
+    };
+    let (content_type, body) = if csv {
+        let entries = manifest::read_month(&dir, month).map_err(not_found)?;
+        ("text/csv; charset=utf-8", manifest_csv(&entries))
+    } else {
+        let raw = manifest::read_month_raw(&dir, month).map_err(not_found)?;
+        ("application/x-ndjson", raw)
+    };
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"deletions-{file}\""),
+            ),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+/// Number of past rule and tag policy actions listed on the rules page.
+const RECENT_RULE_HITS: i64 = 50;
+
+/// What each configured rule would do at the next cleanup run, the tag
+/// policies, and what both did recently.
+async fn rules_page(
+    State(state): State<AppState>,
+    admin: AdminUser,
+) -> Result<impl IntoResponse, AppError> {
+    let config = state.config.current();
+    Ok(AdminRulesTemplate {
+        username: admin.username.clone(),
+        is_admin: true,
+        previews: crate::rules::preview(&state.pool, &config).await?,
+        hits: rule_hit::list_recent(&state.pool, RECENT_RULE_HITS).await?,
+        policies: config.tag_policies.clone(),
+        policy_log: policy_log::list_recent(&state.pool, RECENT_RULE_HITS).await?,
+    })
+}
+
+#[derive(Deserialize)]
+struct SpaceFreedQuery {
+    months: Option<i64>,
+}
+
+/// Space freed by deletions from the trash per month, newest first.
+#[derive(Serialize)]
+struct SpaceFreed {
+    months: Vec<FreedMonth>,
+}
+
+async fn space_freed_json(
+    State(state): State<AppState>,
+    _admin: AdminUser,
+    Query(query): Query<SpaceFreedQuery>,
+) -> Result<Json<SpaceFreed>, AppError> {
+    let months = query
+        .months
+        .unwrap_or(SPACE_FREED_MONTHS)
+        .clamp(1, MAX_SPACE_FREED_MONTHS);
+    Ok(Json(SpaceFreed {
+        months: media_history::freed_by_month(
+            &state.pool,
+            &state.config.current().media_dirs,
+            months,
+        )
+        .await?,
+    }))
+}
+
+#[derive(Deserialize)]
+struct LargestQuery {
+    limit: Option<i64>,
+}
+
+/// The largest active and persisted items, biggest first.
+#[derive(Serialize)]
+struct LargestItems {
+    active: Vec<InventoryItem>,
+    persisted: Vec<InventoryItem>,
+}
+
+async fn largest_json(
     State(state): State<AppState>,
     _admin: AdminUser,
+    Query(query): Query<LargestQuery>,
+) -> Result<Json<LargestItems>, AppError> {
+    let limit = query
+        .limit
+        .unwrap_or(LARGEST_ITEMS)
+        .clamp(1, MAX_LARGEST_ITEMS);
+    let list = |status| media::list_largest(&state.pool, status, limit);
+    Ok(Json(LargestItems {
+        active: list("active")
+            .await?
+            .into_iter()
+            .map(InventoryItem::from)
+            .collect(),
+        persisted: list("permanent")
+            .await?
+            .into_iter()
+            .map(InventoryItem::from)
+            .collect(),
+    }))
+}
+
+#[derive(Deserialize)]
+struct DeletedQuery {
+    #[serde(default)]
+    q: Option<String>,
+}
+
+async fn deleted_page(
+    State(state): State<AppState>,
+    admin: AdminUser,
+    Query(query): Query<DeletedQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let search = query.q.unwrap_or_default().trim().to_string();
+    let filter = (!search.is_empty()).then_some(search.as_str());
+    let items = media::list_deleted(&state.pool, filter, None).await?;
+
+    Ok(AdminDeletedTemplate {
+        username: admin.username.clone(),
+        is_admin: true,
+        items,
+        search,
+        retention_days: state.config.current().deleted_retention_days,
+        requests: rerequest::open(&state.pool).await?,
+    })
+}
+
+async fn dismiss_rerequests(
+    State(state): State<AppState>,
+    admin: AdminUser,
     Path(id): Path<i64>,
 ) -> Result<Response, AppError> {
-    crate::trash::rescue_from_trash(&state.pool, id, &state.config, state.dry_run)
-        .await
-        .map_err(|e| AppError::Internal(e.to_string()))?;
+    rerequest::dismiss(&state.pool, id).await?;
+    tracing::info!(event = "rerequest_dismissed", media_id = id, user = %admin.username, "Dismissed the requests for media {id}");
+    Ok(Redirect::to(&url("/admin/deleted")).into_response())
+}
+
+async fn restore_from_cloud(
+    State(state): State<AppState>,
+    admin: AdminUser,
+    Path(id): Path<i64>,
+) -> Result<Response, AppError> {
+    crate::trash::cloud::restore_from_cloud(
+        &state.pool,
+        id,
+        &admin.username,
+        &state.config.current(),
+        state.dry_run.get(),
+    )
+    .await
+    .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    Ok(Redirect::to(&url("/admin/deleted")).into_response())
+}
+
+async fn archive_page(
+    State(state): State<AppState>,
+    admin: AdminUser,
+) -> Result<impl IntoResponse, AppError> {
+    Ok(AdminArchiveTemplate {
+        username: admin.username.clone(),
+        is_admin: true,
+        items: media::list_archived(&state.pool).await?,
+        archive_dir: state
+            .config
+            .current()
+            .archive_dir
+            .as_ref()
+            .map(|dir| dir.display().to_string()),
+    })
+}
 
-    Ok(Redirect::to("/admin/trash").into_response())
+async fn restore_archived(
+    State(state): State<AppState>,
+    admin: AdminUser,
+    Path(id): Path<i64>,
+) -> Result<Response, AppError> {
+    crate::trash::archive::restore_from_archive(
+        &state.pool,
+        id,
+        &admin.username,
+        &state.config.current(),
+        state.dry_run.get(),
+    )
+    .await
+    .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    Ok(Redirect::to(&url("/admin/archive")).into_response())
+}
+
+async fn rescue_item(
+    State(state): State<AppState>,
+    admin: AdminUser,
+    Path(id): Path<i64>,
+) -> Result<Response, AppError> {
+    crate::trash::rescue_from_trash(
+        &state.pool,
+        id,
+        &admin.username,
+        &state.config.current(),
+        state.dry_run.get(),
+    )
+    .await
+    .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    Ok(Redirect::to(&url("/admin/trash")).into_response())
+}
+
+/// Delete Now on the Trash page and Approve on the Approvals page: deletes the
+/// item, or records the approval while a large item waits for a second admin.
+async fn delete_trash_item(
+    State(state): State<AppState>,
+    admin: AdminUser,
+    Path(id): Path<i64>,
+) -> Result<Response, AppError> {
+    let deleted = crate::trash::approve_deletion(
+        &state.pool,
+        id,
+        admin.id,
+        &admin.username,
+        &state.config.current(),
+        state.dry_run.get(),
+    )
+    .await
+    .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    let back = if deleted {
+        "/admin/trash"
+    } else {
+        "/admin/approvals"
+    };
+    Ok(Redirect::to(&url(back)).into_response())
+}
+
+async fn approvals_page(
+    State(state): State<AppState>,
+    admin: AdminUser,
+) -> Result<impl IntoResponse, AppError> {
+    Ok(AdminApprovalsTemplate {
+        username: admin.username.clone(),
+        is_admin: true,
+        items: deletion_approval::list_pending(&state.pool).await?,
+        approved_by_me: deletion_approval::approved_by(&state.pool, admin.id).await?,
+        pending_trash: pending_trash::list(&state.pool).await?,
+    })
+}
+
+/// Move an item everyone marked to the trash, as `trash_approval` asks.
+async fn confirm_trash(
+    State(state): State<AppState>,
+    admin: AdminUser,
+    Path(id): Path<i64>,
+) -> Result<Response, AppError> {
+    crate::trash::confirm_trash(
+        &state.pool,
+        id,
+        &admin.username,
+        &state.config.current(),
+        state.dry_run.get(),
+    )
+    .await
+    .map_err(|e| AppError::BadRequest(e.to_string()))?;
+    Ok(Redirect::to(&url("/admin/approvals")).into_response())
+}
+
+/// Keep an item everyone marked: it leaves the queue and its marks are
+/// dropped, so users vote on it afresh.
+async fn reject_trash(
+    State(state): State<AppState>,
+    admin: AdminUser,
+    Path(id): Path<i64>,
+) -> Result<Response, AppError> {
+    let m = media::get_by_id(&state.pool, id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    pending_trash::clear(&state.pool, id).await?;
+    mark::clear_marks(&state.pool, id).await?;
+    tracing::info!(event = "trash_rejected", media_id = id, user = %admin.username, "{} kept {}", admin.username, m.path);
+    Ok(Redirect::to(&url("/admin/approvals")).into_response())
 }
 
 async fn trigger_scan(
     State(state): State<AppState>,
     _admin: AdminUser,
 ) -> Result<Response, AppError> {
+    refuse_in_maintenance(&state)?;
     let pool = state.pool.clone();
-    let media_dirs = state.config.media_dirs.clone();
+    let media_dirs = state.config.current().media_dirs.clone();
+    let scan_status = state.scan_status.clone();
+    let tmdb = state.tmdb.clone();
 
     tokio::spawn(async move {
-        if let Err(e) = crate::scanner::full_scan(&pool, &media_dirs, None).await {
+        if let Err(e) = crate::scanner::full_scan(
+            &pool,
+            &media_dirs,
+            tmdb.as_ref(),
+            ScanMode::Full,
+            &scan_status,
+        )
+        .await
+        {
             tracing::error!("Manual scan failed: {e}");
         }
     });
 
-    Ok(Redirect::to("/admin").into_response())
+    Ok(Redirect::to(&url("/admin")).into_response())
+}
+
+async fn trigger_library_scan(
+    State(state): State<AppState>,
+    _admin: AdminUser,
+    Path(index): Path<usize>,
+) -> Result<Response, AppError> {
+    spawn_library_scan(&state, index, GonePolicy::Guarded)
+}
+
+/// Rescan a library whose gone-marking was held back, accepting the removal.
+async fn confirm_library_gone(
+    State(state): State<AppState>,
+    _admin: AdminUser,
+    Path(index): Path<usize>,
+) -> Result<Response, AppError> {
+    spawn_library_scan(&state, index, GonePolicy::Force)
+}
+
+/// Scans are paused while the disks are being reorganized.
+fn refuse_in_maintenance(state: &AppState) -> Result<(), AppError> {
+    if state.config.current().maintenance {
+        return Err(AppError::BadRequest(
+            "scans are paused in maintenance mode".into(),
+        ));
+    }
+    Ok(())
+}
+
+fn spawn_library_scan(
+    state: &AppState,
+    index: usize,
+    policy: GonePolicy,
+) -> Result<Response, AppError> {
+    refuse_in_maintenance(state)?;
+    let media_dir = state
+        .config
+        .current()
+        .media_dirs
+        .get(index)
+        .cloned()
+        .ok_or(AppError::NotFound)?;
+    let pool = state.pool.clone();
+    let scan_status = state.scan_status.clone();
+    let tmdb = state.tmdb.clone();
+
+    tokio::spawn(async move {
+        if let Err(e) = crate::scanner::scan_library(
+            &pool,
+            &media_dir,
+            tmdb.as_ref(),
+            ScanMode::Full,
+            &scan_status,
+            policy,
+        )
+        .await
+        {
+            tracing::error!("Library scan of {} failed: {e}", media_dir.display());
+        }
+    });
+
+    Ok(Redirect::to(&url("/admin")).into_response())
+}
+
+#[derive(Deserialize)]
+struct LibraryAccessForm {
+    user_id: i64,
+}
+
+/// The media dir at `index`, as library grants record it.
+fn library_key(state: &AppState, index: usize) -> Result<String, AppError> {
+    let config = state.config.current();
+    let dir = config.media_dirs.get(index).ok_or(AppError::NotFound)?;
+    // Item paths are stored with symlinks resolved, so grants must be too.
+    let dir = crate::paths::canonical(dir);
+    Ok(dir.display().to_string().trim_end_matches('/').to_string())
+}
+
+/// Open a library to a user; the first grant restricts it to the granted users.
+async fn grant_library(
+    State(state): State<AppState>,
+    admin: AdminUser,
+    Path(index): Path<usize>,
+    Form(form): Form<LibraryAccessForm>,
+) -> Result<Response, AppError> {
+    let dir = library_key(&state, index)?;
+    let u = user::get_by_id(&state.pool, form.user_id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    library_access::grant(&state.pool, &dir, u.id).await?;
+    tracing::info!(event = "library_grant", user = %admin.username, library = %dir, "{} gave {} access to {dir}", admin.username, u.username);
+    Ok(Redirect::to(&url("/admin")).into_response())
+}
+
+/// Take a user's access away; without grants left the library is open again.
+async fn revoke_library(
+    State(state): State<AppState>,
+    admin: AdminUser,
+    Path(index): Path<usize>,
+    Form(form): Form<LibraryAccessForm>,
+) -> Result<Response, AppError> {
+    let dir = library_key(&state, index)?;
+    library_access::revoke(&state.pool, &dir, form.user_id).await?;
+    tracing::info!(event = "library_revoke", user = %admin.username, library = %dir, "{} revoked user {} from {dir}", admin.username, form.user_id);
+
+    // The remaining voters may all have marked some items already.
+    crate::trash::trash_deferred(&state.pool, &state.config.current(), state.dry_run.get())
+        .await
+        .map_err(|e| AppError::Internal(format!("trash operation failed: {e}")))?;
+    Ok(Redirect::to(&url("/admin")).into_response())
+}
+
+#[derive(Deserialize)]
+struct AddMediaForm {
+    path: String,
+    media_type: String,
+    title: String,
+    #[serde(default)]
+    year: String,
+    #[serde(default)]
+    season: String,
+}
+
+/// Track a path the scanner does not recognise, such as a movie stored as a
+/// bare file in an unusual place or a season folder with an odd name.
+async fn add_media(
+    State(state): State<AppState>,
+    admin: AdminUser,
+    Form(form): Form<AddMediaForm>,
+) -> Result<Response, AppError> {
+    let season = classification(&form.media_type, &form.season)?;
+    let year = optional_number("year", &form.year)?;
+    let title = form.title.trim();
+    if title.is_empty() {
+        return Err(AppError::BadRequest("title must not be empty".to_string()));
+    }
+    let path = crate::paths::canonical(std::path::Path::new(form.path.trim()));
+    let path = &*path.to_string_lossy();
+    let config = state.config.current();
+    let in_library = config.media_dirs.iter().any(|dir| {
+        crate::paths::relative_to(dir, std::path::Path::new(path))
+            .is_some_and(|rest| !rest.as_os_str().is_empty())
+    });
+    if !in_library {
+        return Err(AppError::BadRequest(format!(
+            "{path} is not inside a configured media directory"
+        )));
+    }
+    if !std::path::Path::new(path).exists() {
+        return Err(AppError::BadRequest(format!("{path} does not exist")));
+    }
+
+    let id = match media::insert_manual(&state.pool, &form.media_type, title, year, season, path)
+        .await
+    {
+        Ok(id) => id,
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+            return Err(AppError::BadRequest(format!("{path} is already tracked")));
+        }
+        Err(e) => return Err(e.into()),
+    };
+    tracing::info!(event = "media_added", media_id = id, user = %admin.username, "Added {path} by hand");
+
+    // Measure it and look up a poster without holding up the redirect.
+    let pool = state.pool.clone();
+    let tmdb = state.tmdb.clone();
+    tokio::spawn(async move {
+        if let Err(e) = crate::scanner::refresh_item(&pool, id, tmdb.as_ref()).await {
+            tracing::error!("Failed to refresh added media {id}: {e}");
+        }
+    });
+
+    Ok(Redirect::to(&url(&format!("/media/{id}"))).into_response())
+}
+
+#[derive(Deserialize)]
+struct MediaTypeForm {
+    media_type: String,
+    #[serde(default)]
+    season: String,
+}
+
+/// Correct a movie the scanner took for a season, or the other way round.
+async fn set_media_type(
+    State(state): State<AppState>,
+    admin: AdminUser,
+    Path(id): Path<i64>,
+    Form(form): Form<MediaTypeForm>,
+) -> Result<Response, AppError> {
+    let m = media::get_by_id(&state.pool, id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    let season = classification(&form.media_type, &form.season)?;
+    media::set_media_type(&state.pool, id, &form.media_type, season).await?;
+    tracing::info!(
+        event = "media_reclassified",
+        media_id = id,
+        user = %admin.username,
+        "Changed {} from {} to {}",
+        m.path,
+        m.media_type,
+        form.media_type
+    );
+    Ok(Redirect::to(&url(&format!("/media/{id}"))).into_response())
+}
+
+#[derive(Deserialize)]
+struct EditMediaForm {
+    title: String,
+    #[serde(default)]
+    year: String,
+    #[serde(default)]
+    season: String,
+    /// Checkbox: also rename the movie on disk to "Title (Year)".
+    rename: Option<String>,
+}
+
+/// Fix a title, year or season the folder name got wrong, then look the item
+/// up again so its poster matches the new title.
+async fn edit_media(
+    State(state): State<AppState>,
+    admin: AdminUser,
+    Path(id): Path<i64>,
+    Form(form): Form<EditMediaForm>,
+) -> Result<Response, AppError> {
+    let m = media::get_by_id(&state.pool, id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    let title = form.title.trim();
+    if title.is_empty() {
+        return Err(AppError::BadRequest("title must not be empty".to_string()));
+    }
+    let year = optional_number("year", &form.year)?;
+    let season = classification(&m.media_type, &form.season)?;
+    media::correct_metadata(&state.pool, id, title, year, season).await?;
+    tracing::info!(
+        event = "media_edited",
+        media_id = id,
+        user = %admin.username,
+        "Renamed {} from {:?} to {title:?}",
+        m.path,
+        m.title
+    );
+
+    if form.rename.is_some() {
+        crate::rename::rename_to_canonical(
+            &state.pool,
+            id,
+            &admin.username,
+            &state.config.current(),
+            state.dry_run.get(),
+        )
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+    }
+    if m.status == "active" {
+        crate::scanner::refresh_item(&state.pool, id, state.tmdb.as_ref())
+            .await
+            .map_err(|e| AppError::Internal(format!("refresh failed: {e}")))?;
+    }
+    Ok(Redirect::to(&url(&format!("/media/{id}"))).into_response())
+}
+
+#[derive(Deserialize)]
+struct MoveLibraryForm {
+    media_dir: PathBuf,
+}
+
+/// Move an item into another media dir, e.g. a re-encoded 4K movie into the
+/// 1080p library.
+async fn move_library(
+    State(state): State<AppState>,
+    admin: AdminUser,
+    Path(id): Path<i64>,
+    Form(form): Form<MoveLibraryForm>,
+) -> Result<Response, AppError> {
+    media::get_by_id(&state.pool, id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    crate::relocate::move_to_library(
+        &state.pool,
+        id,
+        &form.media_dir,
+        &admin.username,
+        &state.config.current(),
+        state.dry_run.get(),
+    )
+    .await
+    .map_err(|e| AppError::Internal(e.to_string()))?;
+    Ok(Redirect::to(&url(&format!("/media/{id}"))).into_response())
+}
+
+#[derive(Deserialize)]
+struct LinkEditionForm {
+    other_id: i64,
+}
+
+async fn link_edition(
+    State(state): State<AppState>,
+    admin: AdminUser,
+    Path(id): Path<i64>,
+    Form(form): Form<LinkEditionForm>,
+) -> Result<Response, AppError> {
+    let candidates = edition::candidates(&state.pool, id).await?;
+    let Some(other) = candidates.iter().find(|c| c.id == form.other_id) else {
+        return Err(AppError::BadRequest(
+            "only another copy of the same movie can be linked as an edition".to_string(),
+        ));
+    };
+    edition::link(&state.pool, id, other.id).await?;
+    tracing::info!(
+        event = "edition_linked",
+        media_id = id,
+        user = %admin.username,
+        "Linked {} as an edition of media {id}",
+        other.path
+    );
+    Ok(Redirect::to(&url(&format!("/media/{id}"))).into_response())
+}
+
+async fn unlink_edition(
+    State(state): State<AppState>,
+    admin: AdminUser,
+    Path(id): Path<i64>,
+) -> Result<Response, AppError> {
+    edition::unlink(&state.pool, id).await?;
+    tracing::info!(
+        event = "edition_unlinked",
+        media_id = id,
+        user = %admin.username,
+        "Unlinked media {id} from its editions"
+    );
+    Ok(Redirect::to(&url(&format!("/media/{id}"))).into_response())
+}
+
+/// The season to store for an item of `media_type`: movies have none, and a
+/// TV season needs one.
+fn classification(media_type: &str, season: &str) -> Result<Option<i64>, AppError> {
+    match media_type {
+        "movie" => Ok(None),
+        "tv_season" => optional_number("season", season)?
+            .filter(|s| *s >= 0)
+            .map(Some)
+            .ok_or_else(|| AppError::BadRequest("a TV season needs a season number".to_string())),
+        other => Err(AppError::BadRequest(format!(
+            "unknown media type {other:?}"
+        ))),
+    }
+}
+
+/// A number from a form field that may be left blank.
+fn optional_number(field: &str, value: &str) -> Result<Option<i64>, AppError> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(None);
+    }
+    value
+        .parse()
+        .map(Some)
+        .map_err(|_| AppError::BadRequest(format!("{field} must be a number")))
+}
+
+async fn refresh_media(
+    State(state): State<AppState>,
+    admin: AdminUser,
+    Path(id): Path<i64>,
+) -> Result<Response, AppError> {
+    let m = media::get_by_id(&state.pool, id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    if m.status != "active" {
+        return Err(AppError::NotFound);
+    }
+
+    crate::scanner::refresh_item(&state.pool, id, state.tmdb.as_ref())
+        .await
+        .map_err(|e| AppError::Internal(format!("refresh failed: {e}")))?;
+
+    let media_item = media::get_by_id(&state.pool, id).await?.unwrap_or(m);
+    // The item vanished from disk: drop its card like a trashed item.
+    if media_item.status != "active" {
+        return Ok(axum::response::Html(String::new()).into_response());
+    }
+
+    let marked = mark::user_marks(&state.pool, admin.id).await?.contains(&id);
+    let mark_count = mark::mark_count(&state.pool, id).await?;
+    let total_users = user::count(&state.pool).await?;
+
+    Ok(MediaCardPartial {
+        item: MediaRow {
+            media: media_item,
+            marked,
+            mark_count,
+            total_users,
+            persisted: false,
+            persisted_by_me: false,
+            series_persisted: false,
+            editions: edition::others(&state.pool, id).await?,
+            kept: false,
+            tags: tag::for_media(&state.pool, id).await?,
+            comments: comment::preview_for(&state.pool, id).await?,
+        },
+        is_admin: true,
+    }
+    .into_response())
+}
+
+async fn protect_media(
+    State(state): State<AppState>,
+    admin: AdminUser,
+    Path(id): Path<i64>,
+) -> Result<Response, AppError> {
+    set_protected(&state, &admin, id, true).await
+}
+
+async fn unprotect_media(
+    State(state): State<AppState>,
+    admin: AdminUser,
+    Path(id): Path<i64>,
+) -> Result<Response, AppError> {
+    set_protected(&state, &admin, id, false).await
+}
+
+async fn set_protected(
+    state: &AppState,
+    admin: &AdminUser,
+    id: i64,
+    protected: bool,
+) -> Result<Response, AppError> {
+    let m = media::get_by_id(&state.pool, id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    if m.status != "active" {
+        return Err(AppError::NotFound);
+    }
+
+    media::set_protected(&state.pool, id, protected).await?;
+    let (event, verb) = if protected {
+        ("protect", "Protected")
+    } else {
+        ("unprotect", "Unprotected")
+    };
+    tracing::info!(event, media_id = id, user = %admin.username, "{verb} {}", m.path);
+
+    crate::routes::media::media_card(state, &admin.0, id, &m.media_type).await
+}
+
+async fn scan_status(State(state): State<AppState>, _admin: AdminUser) -> Json<ScanSnapshot> {
+    Json(state.scan_status.snapshot())
+}
+
+/// Re-read rewinder.toml; the watcher and maintenance task pick up the change.
+async fn reload_config(
+    State(state): State<AppState>,
+    _admin: AdminUser,
+) -> Result<Response, AppError> {
+    state
+        .config
+        .reload()
+        .map_err(|e| AppError::BadRequest(format!("config reload failed: {e}")))?;
+    Ok(Redirect::to(&url("/admin")).into_response())
+}
+
+async fn settings_page(
+    State(state): State<AppState>,
+    admin: AdminUser,
+) -> Result<impl IntoResponse, AppError> {
+    let saved = setting::all(&state.pool).await?;
+    let config = state.config.current();
+    let file = state.config.file_config();
+    let rows = crate::settings::SETTINGS
+        .iter()
+        .map(|s| SettingRow {
+            key: s.key,
+            help: s.help,
+            value: crate::settings::value_of(&config, s.key),
+            file_value: crate::settings::value_of(&file, s.key),
+            saved: saved.contains_key(s.key),
+        })
+        .collect();
+    Ok(AdminSettingsTemplate {
+        username: admin.username.clone(),
+        is_admin: true,
+        rows,
+        dry_run: state.dry_run.get(),
+        maintenance: config.maintenance,
+        announcements: announcement::list(&state.pool).await?,
+    })
+}
+
+/// Save the settings form. A value equal to the config file's is not saved,
+/// so later edits to the file apply to it again.
+async fn save_settings(
+    State(state): State<AppState>,
+    admin: AdminUser,
+    Form(form): Form<HashMap<String, String>>,
+) -> Result<Response, AppError> {
+    let file = state.config.file_config();
+    let mut check = file.as_ref().clone();
+    for s in crate::settings::SETTINGS {
+        if let Some(value) = form.get(s.key) {
+            crate::settings::apply(&mut check, s.key, value).map_err(AppError::BadRequest)?;
+        }
+    }
+    for s in crate::settings::SETTINGS {
+        let Some(value) = form.get(s.key).map(|v| v.trim()) else {
+            continue;
+        };
+        if value == crate::settings::value_of(&file, s.key) {
+            setting::remove(&state.pool, s.key).await?;
+        } else {
+            setting::set(&state.pool, s.key, value, &admin.username).await?;
+        }
+    }
+    crate::settings::load(&state.pool, &state.config).await?;
+    tracing::info!(event = "settings", user = %admin.username, "Saved runtime settings");
+    Ok(Redirect::to(&url("/admin/settings")).into_response())
+}
+
+/// An on/off switch on the settings page.
+#[derive(Deserialize)]
+struct SwitchForm {
+    enabled: bool,
+}
+
+/// Switch dry-run mode for the running process; `--dry-run` decides again
+/// after a restart.
+async fn set_dry_run(
+    State(state): State<AppState>,
+    admin: AdminUser,
+    Form(form): Form<SwitchForm>,
+) -> Result<Response, AppError> {
+    state.dry_run.set(form.enabled);
+    let mode = if form.enabled { "on" } else { "off" };
+    tracing::warn!(event = "dry_run", user = %admin.username, "{} turned dry-run mode {mode}", admin.username);
+    Ok(Redirect::to(&url("/admin/settings")).into_response())
+}
+
+/// Enter or leave maintenance mode. The switch is saved like the other
+/// settings, so it survives restarts.
+async fn set_maintenance(
+    State(state): State<AppState>,
+    admin: AdminUser,
+    Form(form): Form<SwitchForm>,
+) -> Result<Response, AppError> {
+    let key = crate::settings::MAINTENANCE;
+    if form.enabled == state.config.file_config().maintenance {
+        setting::remove(&state.pool, key).await?;
+    } else {
+        setting::set(&state.pool, key, &form.enabled.to_string(), &admin.username).await?;
+    }
+    crate::settings::load(&state.pool, &state.config).await?;
+    let mode = if form.enabled { "entered" } else { "left" };
+    tracing::warn!(event = "maintenance", user = %admin.username, "{} {mode} maintenance mode", admin.username);
+    Ok(Redirect::to(&url("/admin/settings")).into_response())
+}
+
+#[derive(Deserialize)]
+struct AnnouncementForm {
+    message: String,
+}
+
+/// Show a message at the top of every page until each user dismisses it.
+async fn post_announcement(
+    State(state): State<AppState>,
+    admin: AdminUser,
+    Form(form): Form<AnnouncementForm>,
+) -> Result<Response, AppError> {
+    let message = form.message.trim();
+    if message.is_empty() {
+        return Err(AppError::BadRequest("announcement is empty".into()));
+    }
+    if message.chars().count() > announcement::MAX_LEN {
+        return Err(AppError::BadRequest(format!(
+            "announcements are limited to {} characters",
+            announcement::MAX_LEN
+        )));
+    }
+    let id = announcement::create(&state.pool, message, &admin.username).await?;
+    tracing::info!(event = "announcement", id, user = %admin.username, "{} posted an announcement: {message}", admin.username);
+    Ok(Redirect::to(&url("/admin/settings")).into_response())
+}
+
+async fn delete_announcement(
+    State(state): State<AppState>,
+    admin: AdminUser,
+    Path(id): Path<i64>,
+) -> Result<Response, AppError> {
+    if !announcement::delete(&state.pool, id).await? {
+        return Err(AppError::NotFound);
+    }
+    tracing::info!(event = "announcement_removed", id, user = %admin.username, "{} took down announcement {id}", admin.username);
+    Ok(Redirect::to(&url("/admin/settings")).into_response())
 }
@@ -6,9 +6,10 @@ use axum_extra::extract::cookie::{Cookie, CookieJar};
 use serde::Deserialize;
 
 use crate::auth;
+use crate::auth::client_ip::ClientIp;
 use crate::auth::session;
 use crate::models::user;
-use crate::routes::AppState;
+use crate::routes::{cookie_path, url, AppState};
 use crate::templates::{LoginTemplate, SetupPasswordTemplate};
 
 pub fn router() -> Router<AppState> {
@@ -30,12 +31,17 @@ struct LoginForm {
 
 async fn login_handler(
     State(state): State<AppState>,
+    client_ip: ClientIp,
     jar: CookieJar,
     Form(form): Form<LoginForm>,
 ) -> Response {
     let user = match user::get_by_username(&state.pool, &form.username).await {
         Ok(Some(u)) => u,
         _ => {
+            tracing::warn!(
+                "Failed login for unknown user '{}' from {client_ip}",
+                form.username
+            );
             return LoginTemplate {
                 error: Some("Invalid username or password".into()),
             }
@@ -54,29 +60,37 @@ async fn login_handler(
     };
 
     if !auth::verify_password(&form.password, hash) {
+        tracing::warn!("Failed login for '{}' from {client_ip}", user.username);
         return LoginTemplate {
             error: Some("Invalid username or password".into()),
         }
         .into_response();
     }
 
-    let token =
-        match session::create(&state.pool, user.id, session::DEFAULT_SESSION_TTL_HOURS).await {
-            Ok(t) => t,
-            Err(_) => {
-                return LoginTemplate {
-                    error: Some("Internal error".into()),
-                }
-                .into_response();
+    let token = match session::create(
+        &state.pool,
+        user.id,
+        session::DEFAULT_SESSION_TTL_HOURS,
+        client_ip.0,
+    )
+    .await
+    {
+        Ok(t) => t,
+        Err(_) => {
+            return LoginTemplate {
+                error: Some("Internal error".into()),
             }
-        };
+            .into_response();
+        }
+    };
 
     let cookie = Cookie::build(("session", token))
-        .path("/")
+        .path(cookie_path())
         .http_only(true)
         .same_site(axum_extra::extract::cookie::SameSite::Strict);
 
-    (jar.add(cookie), Redirect::to("/movies")).into_response()
+    tracing::info!("User '{}' logged in from {client_ip}", user.username);
+    (jar.add(cookie), Redirect::to(&url("/movies"))).into_response()
 }
 
 async fn logout_handler(State(state): State<AppState>, jar: CookieJar) -> Response {
@@ -84,9 +98,11 @@ async fn logout_handler(State(state): State<AppState>, jar: CookieJar) -> Respon
         let _ = session::delete(&state.pool, cookie.value()).await;
     }
 
-    let removal = Cookie::build(("session", "")).path("/").http_only(true);
+    let removal = Cookie::build(("session", ""))
+        .path(cookie_path())
+        .http_only(true);
 
-    (jar.remove(removal), Redirect::to("/login")).into_response()
+    (jar.remove(removal), Redirect::to(&url("/login"))).into_response()
 }
 
 async fn invite_page(State(state): State<AppState>, Path(token): Path<String>) -> Response {
@@ -97,7 +113,7 @@ async fn invite_page(State(state): State<AppState>, Path(token): Path<String>) -
             error: None,
         }
         .into_response(),
-        _ => Redirect::to("/login").into_response(),
+        _ => Redirect::to(&url("/login")).into_response(),
     }
 }
 
@@ -109,13 +125,14 @@ struct SetPasswordForm {
 
 async fn invite_handler(
     State(state): State<AppState>,
+    client_ip: ClientIp,
     jar: CookieJar,
     Path(token): Path<String>,
     Form(form): Form<SetPasswordForm>,
 ) -> Response {
     let user = match user::get_by_invite_token(&state.pool, &token).await {
         Ok(Some(u)) => u,
-        _ => return Redirect::to("/login").into_response(),
+        _ => return Redirect::to(&url("/login")).into_response(),
     };
 
     if form.password != form.password_confirm {
@@ -161,16 +178,22 @@ async fn invite_handler(
     }
 
     // Auto-login
-    let session_token =
-        match session::create(&state.pool, user.id, session::DEFAULT_SESSION_TTL_HOURS).await {
-            Ok(t) => t,
-            Err(_) => return Redirect::to("/login").into_response(),
-        };
+    let session_token = match session::create(
+        &state.pool,
+        user.id,
+        session::DEFAULT_SESSION_TTL_HOURS,
+        client_ip.0,
+    )
+    .await
+    {
+        Ok(t) => t,
+        Err(_) => return Redirect::to(&url("/login")).into_response(),
+    };
 
     let cookie = Cookie::build(("session", session_token))
-        .path("/")
+        .path(cookie_path())
         .http_only(true)
         .same_site(axum_extra::extract::cookie::SameSite::Strict);
 
-    (jar.add(cookie), Redirect::to("/movies")).into_response()
+    (jar.add(cookie), Redirect::to(&url("/movies"))).into_response()
 }
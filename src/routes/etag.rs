@@ -0,0 +1,75 @@
+//! Conditional GET for the list pages, so clients polling an unchanged page
+//! get an empty 304 instead of the whole library.
+
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::LazyLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Differs between runs, so a restart (new templates, changed config) never
+/// serves 304s for pages the previous process rendered.
+static INSTANCE: LazyLock<u128> = LazyLock::new(|| {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos())
+});
+
+/// Weak validator for a page rendered from `inputs`.
+pub fn etag(inputs: impl Hash) -> String {
+    let mut hasher = DefaultHasher::new();
+    INSTANCE.hash(&mut hasher);
+    inputs.hash(&mut hasher);
+    format!("W/\"{:016x}\"", hasher.finish())
+}
+
+/// Whether the request's If-None-Match already names `etag`.
+pub fn is_fresh(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(str::trim)
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag.trim_start_matches("W/"))
+}
+
+/// `no-cache` makes browsers revalidate instead of showing a stale copy.
+fn cache_headers(etag: String) -> [(header::HeaderName, String); 2] {
+    [
+        (header::ETAG, etag),
+        (header::CACHE_CONTROL, "private, no-cache".to_string()),
+    ]
+}
+
+/// Empty answer for a client whose copy is still current.
+pub fn not_modified(etag: String) -> Response {
+    (StatusCode::NOT_MODIFIED, cache_headers(etag)).into_response()
+}
+
+/// `page` with its validator attached.
+pub fn with_etag(page: impl IntoResponse, etag: String) -> Response {
+    (cache_headers(etag), page).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_weak_and_listed_tags() {
+        let tag = etag(("movies", 42));
+        assert_eq!(tag, etag(("movies", 42)));
+        assert_ne!(tag, etag(("movies", 43)));
+
+        let mut headers = HeaderMap::new();
+        assert!(!is_fresh(&headers, &tag));
+        headers.insert(
+            header::IF_NONE_MATCH,
+            format!("\"other\", {}", tag.trim_start_matches("W/"))
+                .parse()
+                .unwrap(),
+        );
+        assert!(is_fresh(&headers, &tag));
+    }
+}
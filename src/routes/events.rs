@@ -0,0 +1,48 @@
+use axum::extract::State;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::routing::get;
+use axum::Router;
+use futures_util::stream::{self, Stream};
+use std::convert::Infallible;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::auth::middleware::AuthUser;
+use crate::events;
+use crate::routes::AppState;
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/events", get(media_events))
+}
+
+/// Stream media status changes to an open page as `media` events with a JSON
+/// [`events::MediaEvent`] payload. The user's own changes are left out, since
+/// their page already shows them. A `resync` event means some were missed.
+async fn media_events(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let rx = events::subscribe();
+    let stream = stream::unfold(
+        (rx, state.moves, auth.username),
+        |(mut rx, moves, username)| async move {
+            loop {
+                // End the stream on shutdown so it does not hold up the server.
+                let received = tokio::select! {
+                    _ = moves.closed() => return None,
+                    received = rx.recv() => received,
+                };
+                let event = match received {
+                    Ok(e) if e.actor == username => continue,
+                    Ok(e) => Event::default()
+                        .event("media")
+                        .json_data(&e)
+                        .unwrap_or_default(),
+                    Err(RecvError::Lagged(_)) => Event::default().event("resync").data(""),
+                    Err(RecvError::Closed) => return None,
+                };
+                return Some((Ok(event), (rx, moves, username)));
+            }
+        },
+    );
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
@@ -0,0 +1,104 @@
+use axum::extract::{Path, State};
+use axum::http::{header, HeaderMap};
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::Router;
+
+use crate::error::AppError;
+use crate::ical;
+use crate::locale::UserTime;
+use crate::models::{media, media_history, user};
+use crate::routes::{base_path, AppState};
+use crate::templates::TrashFeedTemplate;
+
+const FEED_ENTRIES: i64 = 50;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/feed/{token}", get(trash_feed))
+        .route("/feed/{token}/deletions.ics", get(deletion_calendar))
+}
+
+/// Atom feed of items entering and leaving the trash. Feed readers cannot log
+/// in, so the user's feed token in the URL stands in for the session.
+async fn trash_feed(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, AppError> {
+    user::get_by_feed_token(&state.pool, &token)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    let entries = media_history::list_removals(&state.pool, FEED_ENTRIES).await?;
+    let updated = entries.first().map_or_else(
+        || "1970-01-01 00:00:00".to_string(),
+        |e| e.changed_at.clone(),
+    );
+    Ok(TrashFeedTemplate {
+        site: site_url(&headers),
+        updated,
+        entries,
+    })
+}
+
+/// iCalendar feed with an all-day event on the day each trashed item is due
+/// for permanent deletion, in the timezone of the user the token belongs to.
+async fn deletion_calendar(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, AppError> {
+    let u = user::get_by_feed_token(&state.pool, &token)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    let time = UserTime::new(u.locale.as_deref(), u.timezone.as_deref());
+    let config = state.config.current();
+    let extended_grace = crate::policies::trash_grace_periods(&state.pool, &config).await?;
+    let site = site_url(&headers);
+
+    let events: Vec<ical::Event> = media::list_trashed(&state.pool)
+        .await?
+        .into_iter()
+        .filter_map(|m| {
+            let trashed_at = m.trashed_at?;
+            let name = match m.season {
+                Some(season) => format!("{} — Season {season}", m.title),
+                None => m.title,
+            };
+            Some(ical::Event {
+                uid: format!(
+                    "media-{}-{}@rewinder",
+                    m.id,
+                    trashed_at.replace([' ', ':', '-'], "")
+                ),
+                date: time.date_after(
+                    &trashed_at,
+                    extended_grace
+                        .get(&m.id)
+                        .copied()
+                        .unwrap_or(config.grace_period_days),
+                )?,
+                summary: format!("Rewinder deletes {name}"),
+                description: format!(
+                    "Moved to the trash on {}. Rescue it before this day to keep it.",
+                    time.format(&trashed_at)
+                ),
+                url: Some(format!("{site}/media/{}", m.id)),
+            })
+        })
+        .collect();
+
+    Ok((
+        [(header::CONTENT_TYPE, "text/calendar; charset=utf-8")],
+        ical::calendar("Rewinder deletions", &events, jiff::Timestamp::now()),
+    ))
+}
+
+/// The app's absolute URL as the feed reader reached it.
+pub(crate) fn site_url(headers: &HeaderMap) -> String {
+    let header = |name| headers.get(name).and_then(|v| v.to_str().ok());
+    let host = header(header::HOST.as_str()).unwrap_or("localhost");
+    let scheme = header("x-forwarded-proto").unwrap_or("http");
+    format!("{scheme}://{host}{}", base_path())
+}
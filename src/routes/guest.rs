@@ -0,0 +1,43 @@
+use axum::extract::{Path, State};
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::Router;
+use std::collections::BTreeMap;
+
+use crate::error::AppError;
+use crate::models::media;
+use crate::routes::AppState;
+use crate::templates::{GuestSeries, GuestTemplate};
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/guest/{token}", get(guest_library))
+}
+
+/// Read-only view of the active library for visitors holding the configured
+/// `guest_token`. Guests have no account, so they can neither mark nor persist.
+async fn guest_library(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    if state.config.current().guest_token.as_deref() != Some(token.as_str()) {
+        return Err(AppError::NotFound);
+    }
+
+    let movies = media::list_by_type(&state.pool, "movie").await?;
+    let mut series: BTreeMap<String, GuestSeries> = BTreeMap::new();
+    for season in media::list_by_type(&state.pool, "tv_season").await? {
+        let entry = series
+            .entry(season.title.clone())
+            .or_insert_with(|| GuestSeries {
+                title: season.title.clone(),
+                seasons: Vec::new(),
+                poster_path: season.poster_path.clone(),
+            });
+        entry.seasons.push(season.season.unwrap_or(0));
+    }
+
+    Ok(GuestTemplate {
+        movies,
+        series: series.into_values().collect(),
+    })
+}
@@ -0,0 +1,462 @@
+use axum::extract::{Path, Query, State};
+use axum::response::{Html, IntoResponse, Redirect, Response};
+use axum::routing::{delete, get, post};
+use axum::{Form, Router};
+use serde::{Deserialize, Serialize};
+
+use crate::auth::middleware::AuthUser;
+use crate::error::AppError;
+use crate::models::media::Media;
+use crate::models::{
+    comment, edition, keep_vote, library_access, mark, media, media_history, persistent, rerequest,
+    tag,
+};
+use crate::routes::{url, AppState};
+use crate::scanner;
+use crate::templates::{
+    poster_image_url, DeletedTemplate, MediaCardPartial, MediaCommentsPartial, MediaDetailTemplate,
+    MediaRow, MediaTagsPartial, ReclaimablePartial,
+};
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/media/{id}", get(media_detail))
+        .route("/reclaimable", get(reclaimable))
+        .route("/media/{id}/tags", post(add_tag))
+        .route("/media/{id}/tags/{tag}", delete(remove_tag))
+        .route("/media/{id}/comments", post(add_comment))
+        .route("/media/{id}/comments/{comment_id}", delete(remove_comment))
+        .route("/media/{id}/keep", post(keep_media).delete(unkeep_media))
+        .route("/media/{id}/keep-best", post(keep_best_edition))
+        .route("/deleted", get(deleted_page))
+        .route("/deleted/{id}/request", post(request_back))
+}
+
+/// The item `auth` wants to vote on or persist, if its library is open to them.
+pub(crate) async fn accessible_media(
+    state: &AppState,
+    auth: &AuthUser,
+    id: i64,
+) -> Result<Media, AppError> {
+    let m = media::get_by_id(&state.pool, id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    if !library_access::can_access(&state.pool, auth.id, id).await? {
+        return Err(AppError::NotFound);
+    }
+    Ok(m)
+}
+
+/// The item behind `/media/{id}`, if `auth` may see it.
+async fn visible_media(state: &AppState, auth: &AuthUser, id: i64) -> Result<Media, AppError> {
+    let m = media::get_by_id(&state.pool, id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    // Admins see restricted libraries too, to manage them.
+    if !auth.is_admin && !library_access::can_access(&state.pool, auth.id, id).await? {
+        return Err(AppError::NotFound);
+    }
+    // Like the library pages, only admins see what other users persisted.
+    if m.status == "permanent" && !auth.is_admin {
+        let owner = persistent::get_owner(&state.pool, id).await?;
+        if owner.map(|o| o.user_id) != Some(auth.id) {
+            return Err(AppError::NotFound);
+        }
+    }
+    Ok(m)
+}
+
+async fn media_detail(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    let m = visible_media(&state, &auth, id).await?;
+    let history = media_history::list_for_media(&state.pool, id).await?;
+    // Trashed and archived items no longer live at their path.
+    let excluded = if matches!(m.status.as_str(), "active" | "permanent") {
+        let path = std::path::PathBuf::from(&m.path);
+        tokio::task::spawn_blocking(move || scanner::excluded_files(&path))
+            .await
+            .unwrap_or_default()
+    } else {
+        Vec::new()
+    };
+
+    // Admins can move an active item into any other media dir.
+    let libraries = if auth.is_admin && m.status == "active" {
+        let config = state.config.current();
+        let current = crate::paths::library_of(&config, std::path::Path::new(&m.path));
+        config
+            .media_dirs
+            .iter()
+            .filter(|dir| Some(*dir) != current)
+            .map(|dir| dir.to_string_lossy().into_owned())
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    Ok(MediaDetailTemplate {
+        username: auth.username,
+        is_admin: auth.is_admin,
+        poster_url: poster_image_url(&m.poster_path),
+        tags: tag::for_media(&state.pool, id).await?,
+        known_tags: tag::list_in_use(&state.pool, &m.media_type).await?,
+        comments: comment::for_media(&state.pool, id).await?,
+        user_id: auth.id,
+        media: m,
+        libraries,
+        editions: edition::editions_of(&state.pool, id).await?,
+        edition_candidates: if auth.is_admin {
+            edition::candidates(&state.pool, id).await?
+        } else {
+            Vec::new()
+        },
+        history,
+        excluded,
+    })
+}
+
+#[derive(Deserialize)]
+struct TagForm {
+    tag: String,
+}
+
+async fn add_tag(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<i64>,
+    Form(form): Form<TagForm>,
+) -> Result<impl IntoResponse, AppError> {
+    let m = visible_media(&state, &auth, id).await?;
+    let name = tag::normalize(&form.tag).map_err(AppError::BadRequest)?;
+    tag::add(&state.pool, id, &name).await?;
+    tracing::info!(event = "tag", media_id = id, user = %auth.username, tag = %name, "Tagged {} {name:?}", m.path);
+    tags_partial(&state, m).await
+}
+
+async fn remove_tag(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path((id, name)): Path<(i64, String)>,
+) -> Result<impl IntoResponse, AppError> {
+    let m = visible_media(&state, &auth, id).await?;
+    tag::remove(&state.pool, id, &name).await?;
+    tracing::info!(event = "untag", media_id = id, user = %auth.username, tag = %name, "Untagged {} {name:?}", m.path);
+    tags_partial(&state, m).await
+}
+
+async fn tags_partial(state: &AppState, m: Media) -> Result<MediaTagsPartial, AppError> {
+    Ok(MediaTagsPartial {
+        tags: tag::for_media(&state.pool, m.id).await?,
+        known_tags: tag::list_in_use(&state.pool, &m.media_type).await?,
+        media: m,
+    })
+}
+
+#[derive(Deserialize)]
+struct CommentForm {
+    body: String,
+}
+
+async fn add_comment(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<i64>,
+    Form(form): Form<CommentForm>,
+) -> Result<impl IntoResponse, AppError> {
+    let m = visible_media(&state, &auth, id).await?;
+    let body = comment::normalize(&form.body).map_err(AppError::BadRequest)?;
+    let comment_id = comment::add(&state.pool, id, auth.id, &body).await?;
+    tracing::info!(event = "comment", media_id = id, comment_id, user = %auth.username, "Commented on {}", m.path);
+    comments_partial(&state, &auth, m).await
+}
+
+/// Take a comment down; its author or an admin may.
+async fn remove_comment(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path((id, comment_id)): Path<(i64, i64)>,
+) -> Result<impl IntoResponse, AppError> {
+    let m = visible_media(&state, &auth, id).await?;
+    let c = comment::get(&state.pool, comment_id)
+        .await?
+        .filter(|c| c.media_id == id)
+        .ok_or(AppError::NotFound)?;
+    if c.user_id != auth.id && !auth.is_admin {
+        return Err(AppError::Forbidden);
+    }
+    comment::delete(&state.pool, comment_id).await?;
+    tracing::info!(event = "comment_removed", media_id = id, comment_id, user = %auth.username, "Removed a comment by {} on {}", c.username, m.path);
+    comments_partial(&state, &auth, m).await
+}
+
+async fn comments_partial(
+    state: &AppState,
+    auth: &AuthUser,
+    m: Media,
+) -> Result<MediaCommentsPartial, AppError> {
+    Ok(MediaCommentsPartial {
+        comments: comment::for_media(&state.pool, m.id).await?,
+        user_id: auth.id,
+        is_admin: auth.is_admin,
+        media: m,
+    })
+}
+
+/// The "space you could free" figure of the library pages, re-fetched after changes.
+async fn reclaimable(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<impl IntoResponse, AppError> {
+    Ok(ReclaimablePartial {
+        reclaimable: media::reclaimable_for_user(&state.pool, auth.id).await?,
+    })
+}
+
+/// The card for one item as `auth` currently sees it, for refreshing a single
+/// card on the movies or TV page. Empty once the item left that user's view,
+/// so an outerHTML swap removes the card.
+pub(crate) async fn media_card(
+    state: &AppState,
+    auth: &AuthUser,
+    id: i64,
+    media_type: &str,
+) -> Result<Response, AppError> {
+    let m = media::get_by_id(&state.pool, id)
+        .await?
+        .filter(|m| m.media_type == media_type)
+        .ok_or(AppError::NotFound)?;
+    let owner = persistent::get_owner(&state.pool, id).await?;
+    let persisted = m.status == "permanent";
+    let persisted_by_me = persisted && owner.map(|o| o.user_id) == Some(auth.id);
+    if !(m.status == "active" || persisted_by_me)
+        || !library_access::can_access(&state.pool, auth.id, id).await?
+    {
+        return Ok(Html(String::new()).into_response());
+    }
+
+    let marked = !persisted && mark::user_marks(&state.pool, auth.id).await?.contains(&id);
+    let kept = keep_vote::kept_by(&state.pool, auth.id, state.config.current().keep_vote_days)
+        .await?
+        .contains(&id);
+    let series_persisted = persisted_by_me
+        && m.media_type == "tv_season"
+        && persistent::get_series_owner(&state.pool, &m.title)
+            .await?
+            .is_some_and(|s| s.user_id == auth.id);
+    Ok(MediaCardPartial {
+        item: MediaRow {
+            mark_count: mark::mark_count(&state.pool, id).await?,
+            total_users: library_access::voter_count(&state.pool, id).await?,
+            tags: tag::for_media(&state.pool, id).await?,
+            comments: comment::preview_for(&state.pool, id).await?,
+            media: m,
+            marked,
+            persisted,
+            persisted_by_me,
+            series_persisted,
+            editions: edition::others(&state.pool, id).await?,
+            kept,
+        },
+        is_admin: auth.is_admin,
+    }
+    .into_response())
+}
+
+/// A card of the movies or TV page, for the JSON variants of those pages.
+/// Mark counts are left out for non-admins, as on the cards.
+#[derive(Serialize)]
+pub(crate) struct ListItem {
+    id: i64,
+    media_type: String,
+    title: String,
+    year: Option<i64>,
+    season: Option<i64>,
+    status: String,
+    size_bytes: i64,
+    size_pending: bool,
+    first_seen: String,
+    poster_url: Option<String>,
+    /// The rendered card, for swapping in with htmx.
+    card_url: String,
+    protected: bool,
+    tags: Vec<String>,
+    comments: i64,
+    marked: bool,
+    kept: bool,
+    persisted: bool,
+    persisted_by_me: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mark_count: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    total_users: Option<i64>,
+}
+
+impl ListItem {
+    pub(crate) fn new(row: MediaRow, is_admin: bool) -> Self {
+        let m = row.media;
+        let list = if m.media_type == "movie" {
+            "movies"
+        } else {
+            "tv"
+        };
+        Self {
+            card_url: url(&format!("/{list}/{}/card", m.id)),
+            poster_url: poster_image_url(&m.poster_path),
+            id: m.id,
+            media_type: m.media_type,
+            title: m.title,
+            year: m.year,
+            season: m.season,
+            status: m.status,
+            size_bytes: m.size_bytes,
+            size_pending: m.size_pending,
+            first_seen: m.first_seen,
+            protected: m.protected,
+            tags: row.tags,
+            comments: row.comments.count,
+            marked: row.marked,
+            kept: row.kept,
+            persisted: row.persisted,
+            persisted_by_me: row.persisted_by_me,
+            mark_count: is_admin.then_some(row.mark_count),
+            total_users: is_admin.then_some(row.total_users),
+        }
+    }
+}
+
+/// Vote to keep an item for `keep_vote_days`: it is not trashed meanwhile,
+/// even once everyone else marked it.
+async fn keep_media(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<i64>,
+) -> Result<Response, AppError> {
+    let m = accessible_media(&state, &auth, id).await?;
+    if m.status != "active" {
+        return Err(AppError::NotFound);
+    }
+    keep_vote::keep(&state.pool, auth.id, id).await?;
+    tracing::info!(event = "keep", media_id = id, user = %auth.username, "Voted to keep {}", m.path);
+    media_card(&state, &auth, id, &m.media_type).await
+}
+
+async fn unkeep_media(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<i64>,
+) -> Result<Response, AppError> {
+    let m = accessible_media(&state, &auth, id).await?;
+    keep_vote::unkeep(&state.pool, auth.id, id).await?;
+    tracing::info!(event = "unkeep", media_id = id, user = %auth.username, "Dropped keep vote on {}", m.path);
+    crate::trash::check_and_trash(
+        &state.pool,
+        id,
+        &auth.username,
+        &state.config.current(),
+        state.dry_run.get(),
+    )
+    .await
+    .map_err(|e| AppError::Internal(format!("trash operation failed: {e}")))?;
+    media_card(&state, &auth, id, &m.media_type).await
+}
+
+/// Mark every edition linked with `id` but the largest, which is taken for
+/// the best one, and trash those everyone has now marked.
+async fn keep_best_edition(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<i64>,
+) -> Result<Response, AppError> {
+    accessible_media(&state, &auth, id).await?;
+    let editions: Vec<Media> = edition::editions_of(&state.pool, id)
+        .await?
+        .into_iter()
+        .filter(|e| e.status == "active")
+        .collect();
+    let Some((best, rest)) = editions.split_first() else {
+        return Err(AppError::BadRequest("no editions are linked".to_string()));
+    };
+    let config = state.config.current();
+    for other in rest {
+        if other.protected || !library_access::can_access(&state.pool, auth.id, other.id).await? {
+            continue;
+        }
+        mark::mark(&state.pool, auth.id, other.id).await?;
+        tracing::info!(event = "mark", media_id = other.id, user = %auth.username, "Marked {} in favour of {}", other.path, best.path);
+        crate::trash::check_and_trash(
+            &state.pool,
+            other.id,
+            &auth.username,
+            &config,
+            state.dry_run.get(),
+        )
+        .await
+        .map_err(|e| AppError::Internal(format!("trash operation failed: {e}")))?;
+    }
+    Ok(Redirect::to(&url(&format!("/media/{}", best.id))).into_response())
+}
+
+#[derive(Deserialize)]
+struct DeletedQuery {
+    #[serde(default)]
+    q: Option<String>,
+}
+
+/// What was deleted from the libraries `auth` may access, to ask for back.
+async fn deleted_page(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Query(query): Query<DeletedQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let search = query.q.unwrap_or_default().trim().to_string();
+    let filter = (!search.is_empty()).then_some(search.as_str());
+    Ok(DeletedTemplate {
+        items: media::list_deleted(&state.pool, filter, Some(auth.id)).await?,
+        requested: rerequest::by_user(&state.pool, auth.id).await?,
+        username: auth.username,
+        is_admin: auth.is_admin,
+        search,
+    })
+}
+
+/// Ask for a deleted item back: admins see the request, and it is passed on
+/// to Overseerr, Radarr or Sonarr when one is configured.
+async fn request_back(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<i64>,
+) -> Result<Response, AppError> {
+    let m = accessible_media(&state, &auth, id).await?;
+    if m.status != "gone" {
+        return Err(AppError::BadRequest(format!(
+            "{} has not been deleted",
+            m.title
+        )));
+    }
+    if rerequest::request(&state.pool, id, auth.id).await? {
+        let forwarded = match crate::arr::forward(&state.config.current(), &m).await {
+            Ok(service) => service,
+            Err(e) => {
+                // The admins still see the request and can act on it by hand.
+                tracing::warn!("Failed to forward the request for {}: {e}", m.path);
+                None
+            }
+        };
+        if let Some(service) = forwarded {
+            rerequest::set_forwarded(&state.pool, id, auth.id, service).await?;
+        }
+        tracing::info!(
+            event = "rerequest",
+            media_id = id,
+            user = %auth.username,
+            forwarded_to = forwarded.unwrap_or_default(),
+            "{} asked for {} back",
+            auth.username,
+            m.path
+        );
+    }
+    Ok(Redirect::to(&url("/deleted")).into_response())
+}
@@ -0,0 +1,115 @@
+//! Prometheus metrics, for alerting on a library that grows out of control.
+//! Off unless `metrics_token` is set; scrapers send it as a bearer token.
+
+use axum::extract::State;
+use axum::http::{header, HeaderMap};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::Router;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::Path;
+
+use crate::config::AppConfig;
+use crate::error::AppError;
+use crate::models::media;
+use crate::routes::AppState;
+
+/// Statuses reported for every library, so each series exists even at zero.
+const STATUSES: [&str; 3] = ["active", "trashed", "permanent"];
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/metrics", get(metrics))
+}
+
+/// The media dir `path` belongs to, counting its `_permanent` sibling as part
+/// of it; "other" for paths outside every library.
+fn library_of(config: &AppConfig, path: &str) -> String {
+    let path = Path::new(path);
+    config
+        .media_dirs
+        .iter()
+        .filter(|dir| {
+            path.starts_with(dir)
+                || AppConfig::permanent_dir_for_media_dir(dir).is_some_and(|p| path.starts_with(p))
+        })
+        .max_by_key(|dir| dir.components().count())
+        .map_or_else(|| "other".to_string(), |dir| dir.display().to_string())
+}
+
+/// `value` escaped for a label in the text exposition format.
+fn label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Bytes and item count per (library, status).
+type Totals = BTreeMap<(String, String), (i64, i64)>;
+
+/// Append one gauge with a sample per (library, status).
+fn gauge(out: &mut String, name: &str, help: &str, totals: &Totals, value: fn(&(i64, i64)) -> i64) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} gauge");
+    for ((library, status), total) in totals {
+        let _ = writeln!(
+            out,
+            "{name}{{library=\"{}\",status=\"{status}\"}} {}",
+            label(library),
+            value(total)
+        );
+    }
+}
+
+fn render(totals: &Totals) -> String {
+    let mut out = String::new();
+    gauge(
+        &mut out,
+        "rewinder_library_bytes",
+        "Size of the media in each library, by status.",
+        totals,
+        |t| t.0,
+    );
+    gauge(
+        &mut out,
+        "rewinder_library_items",
+        "Number of media items in each library, by status.",
+        totals,
+        |t| t.1,
+    );
+    out
+}
+
+async fn metrics(State(state): State<AppState>, headers: HeaderMap) -> Result<Response, AppError> {
+    let config = state.config.current();
+    let Some(token) = config.metrics_token.as_deref() else {
+        return Err(AppError::NotFound);
+    };
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    if bearer != Some(token) {
+        return Err(AppError::Forbidden);
+    }
+
+    let mut totals = Totals::new();
+    for dir in &config.media_dirs {
+        for status in STATUSES {
+            totals.insert((dir.display().to_string(), status.to_string()), (0, 0));
+        }
+    }
+    for (path, status, size_bytes) in media::list_sizes(&state.pool).await? {
+        let total = totals
+            .entry((library_of(&config, &path), status))
+            .or_default();
+        total.0 += size_bytes;
+        total.1 += 1;
+    }
+    Ok((
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        render(&totals),
+    )
+        .into_response())
+}
@@ -1,19 +1,54 @@
+pub mod account;
 pub mod admin;
 pub mod auth;
+pub mod etag;
+pub mod events;
+pub mod feed;
+pub mod guest;
+pub mod media;
+pub mod metrics;
 pub mod movies;
+pub mod pager;
 pub mod sort;
 pub mod tv;
 
-use crate::config::AppConfig;
+use crate::auth::client_ip;
+use crate::auth::middleware::RequestUser;
+use crate::banners::{self, Banners, RequestAnnouncements};
+use crate::config::SharedConfig;
+use crate::dry_run::DryRun;
+use crate::locale::{self, RequestTime};
+use crate::rate_limit::RateLimiter;
+use crate::scanner::ScanStatus;
+use crate::shutdown::MoveTracker;
+use crate::theme::{self, RequestTheme};
+use crate::tmdb::TmdbClient;
+use crate::watcher::WatcherHealth;
+use axum::extract::{ConnectInfo, OriginalUri, Request, State};
+use axum::http::{header, Method, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Redirect, Response};
+use axum::routing::get;
 use axum::Router;
+use axum_extra::extract::CookieJar;
 use sqlx::SqlitePool;
-use std::sync::Arc;
+use std::net::SocketAddr;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use tower_http::compression::CompressionLayer;
+use tower_http::limit::RequestBodyLimitLayer;
+use tower_http::timeout::RequestBodyTimeoutLayer;
+use tracing::Instrument;
 
 #[derive(Clone)]
 pub struct AppState {
     pub pool: SqlitePool,
-    pub config: Arc<AppConfig>,
-    pub dry_run: bool,
+    pub config: SharedConfig,
+    pub dry_run: DryRun,
+    pub watcher_health: WatcherHealth,
+    pub scan_status: ScanStatus,
+    pub tmdb: Option<TmdbClient>,
+    pub moves: MoveTracker,
 }
 
 impl axum::extract::FromRef<AppState> for SqlitePool {
@@ -22,11 +57,244 @@ impl axum::extract::FromRef<AppState> for SqlitePool {
     }
 }
 
+static BASE_PATH: OnceLock<String> = OnceLock::new();
+
+/// Install the `base_path` from the config, e.g. "/rewinder". Must happen before
+/// the router is built or any page is rendered.
+pub fn set_base_path(path: &str) {
+    if BASE_PATH.set(path.to_string()).is_err() {
+        tracing::warn!("base_path already set, ignoring");
+    }
+}
+
+/// The prefix every route is served under; empty when rewinder lives at the root.
+pub fn base_path() -> &'static str {
+    BASE_PATH.get().map(String::as_str).unwrap_or("")
+}
+
+/// An app path like "/movies" as the browser has to request it.
+pub fn url(path: &str) -> String {
+    format!("{}{path}", base_path())
+}
+
+pub fn cookie_path() -> &'static str {
+    match base_path() {
+        "" => "/",
+        base => base,
+    }
+}
+
 pub fn build_router(state: AppState) -> Router {
-    Router::new()
+    let config = state.config.current();
+    let router = Router::new()
         .merge(auth::router())
         .merge(movies::router())
         .merge(tv::router())
+        .merge(media::router())
+        .merge(events::router())
         .merge(admin::router())
-        .with_state(state)
+        .merge(account::router())
+        .merge(feed::router())
+        .merge(guest::router())
+        .merge(metrics::router())
+        .layer(middleware::from_fn_with_state(
+            state.moves.clone(),
+            track_moves,
+        ))
+        .layer(middleware::from_fn_with_state(
+            (state.config.clone(), RateLimiter::default()),
+            limit_rate,
+        ))
+        .layer(middleware::from_fn_with_state(state.clone(), show_banners))
+        .layer(middleware::from_fn(localize))
+        .layer(middleware::from_fn_with_state(
+            state.config.clone(),
+            trace_requests,
+        ))
+        // A client stalling its upload or posting far more than any form
+        // needs is turned away before a handler starts moving files. Only
+        // the body is timed: cutting a handler off could stop it between a
+        // move and recording it.
+        .layer(RequestBodyLimitLayer::new(
+            usize::try_from(config.max_body_kb.saturating_mul(1024)).unwrap_or(usize::MAX),
+        ))
+        .layer(RequestBodyTimeoutLayer::new(Duration::from_secs(
+            config.request_timeout_secs,
+        )))
+        .with_state(state);
+
+    let router = match base_path() {
+        "" => router,
+        base => Router::new().nest(base, router).route(
+            &format!("{base}/"),
+            get(|| async { Redirect::to(&url("/movies")) }),
+        ),
+    };
+    // Large list pages shrink to a fraction over slow links; the default
+    // predicate leaves the event stream and images alone.
+    router.layer(CompressionLayer::new())
+}
+
+/// Count every state-changing request as a potential move so shutdown can wait
+/// for it, and turn them away once shutdown has started.
+async fn track_moves(State(moves): State<MoveTracker>, req: Request, next: Next) -> Response {
+    if matches!(*req.method(), Method::GET | Method::HEAD) {
+        return next.run(req).await;
+    }
+    let Some(_guard) = moves.begin() else {
+        return (StatusCode::SERVICE_UNAVAILABLE, "Shutting down").into_response();
+    };
+    next.run(req).await
+}
+
+/// Answer 429 once a client IP or session has used up its budget of
+/// state-changing requests, or an IP its budget of login attempts.
+async fn limit_rate(
+    State((config, limiter)): State<(SharedConfig, RateLimiter)>,
+    req: Request,
+    next: Next,
+) -> Response {
+    if matches!(*req.method(), Method::GET | Method::HEAD) {
+        return next.run(req).await;
+    }
+    let config = config.current();
+    let path = req.uri().path();
+    let is_login = path == "/login" || path.starts_with("/invite/");
+    let (scope, per_minute) = if is_login {
+        ("login", config.login_rate_limit_per_minute)
+    } else {
+        ("api", config.rate_limit_per_minute)
+    };
+    if per_minute == 0 {
+        return next.run(req).await;
+    }
+
+    let ip = req
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(peer)| {
+            client_ip::resolve(peer.ip(), req.headers(), &config.trusted_proxies)
+        });
+    let session = CookieJar::from_headers(req.headers())
+        .get("session")
+        .map(|c| c.value().to_string());
+    // Key and what to call it in the log; session tokens must not be logged.
+    let mut keys = Vec::new();
+    if let Some(ip) = ip {
+        keys.push((format!("{scope}:ip:{ip}"), ip.to_string()));
+    }
+    if let (Some(token), false) = (session, is_login) {
+        keys.push((format!("{scope}:session:{token}"), "a session".to_string()));
+    }
+    for (key, client) in keys {
+        if let Err(wait) = limiter.check(&key, per_minute) {
+            tracing::warn!("Rate limit exceeded by {client} on {} {path}", req.method());
+            let retry_after = wait.as_secs_f64().ceil() as u64;
+            return (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(header::RETRY_AFTER, retry_after.to_string())],
+                "Too many requests, try again later",
+            )
+                .into_response();
+        }
+    }
+    next.run(req).await
+}
+
+/// Give the auth extractors a place to leave the user's date settings and
+/// theme, and make them available to the templates rendered for this request.
+async fn localize(mut req: Request, next: Next) -> Response {
+    let time = RequestTime::default();
+    let theme = RequestTheme::default();
+    req.extensions_mut().insert(time.clone());
+    req.extensions_mut().insert(theme.clone());
+    locale::scope(time, theme::scope(theme, next.run(req))).await
+}
+
+/// Let the templates rendered for this request know which banners to show.
+async fn show_banners(State(state): State<AppState>, mut req: Request, next: Next) -> Response {
+    let banners = Banners {
+        dry_run: state.dry_run.get(),
+        maintenance: state.config.current().maintenance,
+    };
+    let announcements = RequestAnnouncements::default();
+    req.extensions_mut().insert(announcements.clone());
+    banners::scope(banners, announcements, next.run(req)).await
+}
+
+/// Path segments followed by a credential: feed and guest share tokens and
+/// invite links.
+const TOKEN_SEGMENTS: [&str; 3] = ["feed", "guest", "invite"];
+
+/// `uri` as it may go into logs and traces: tokens in the path and every
+/// query value are replaced, so reading the logs does not hand out access.
+fn loggable_path(uri: &axum::http::Uri) -> String {
+    let mut path = String::with_capacity(uri.path().len());
+    let mut after_token_segment = false;
+    for (i, segment) in uri.path().split('/').enumerate() {
+        if i > 0 {
+            path.push('/');
+        }
+        path.push_str(if after_token_segment && !segment.is_empty() {
+            "<redacted>"
+        } else {
+            segment
+        });
+        after_token_segment = TOKEN_SEGMENTS.contains(&segment);
+    }
+    if let Some(query) = uri.query() {
+        let pairs: Vec<String> = query
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| match pair.split_once('=') {
+                Some((key, _)) => format!("{key}=<redacted>"),
+                None => pair.to_string(),
+            })
+            .collect();
+        path.push('?');
+        path.push_str(&pairs.join("&"));
+    }
+    path
+}
+
+/// Run each request in a `request` span, which ends up in exported traces, and
+/// log one line with its status, latency and user when `log_requests` is enabled.
+async fn trace_requests(
+    State(config): State<SharedConfig>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let started = Instant::now();
+    let method = req.method().clone();
+    let uri = match req.extensions().get::<OriginalUri>() {
+        Some(OriginalUri(uri)) => uri.clone(),
+        None => req.uri().clone(),
+    };
+    let path = loggable_path(&uri);
+    let path = path.as_str();
+    let user = RequestUser::default();
+    req.extensions_mut().insert(user.clone());
+    let span = tracing::info_span!(
+        "request",
+        %method,
+        path,
+        status = tracing::field::Empty,
+        user = tracing::field::Empty,
+    );
+
+    let response = next.run(req).instrument(span.clone()).await;
+    let status = response.status().as_u16();
+    span.record("status", status);
+    if let Some(username) = user.username() {
+        span.record("user", username);
+    }
+    if config.current().log_requests {
+        tracing::info!(
+            target: "rewinder::http",
+            "{method} {path} {status} {}ms user={}",
+            started.elapsed().as_millis(),
+            user.username().unwrap_or("-")
+        );
+    }
+    response
 }
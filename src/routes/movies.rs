@@ -1,24 +1,30 @@
 use axum::extract::{Path, Query, State};
-use axum::response::IntoResponse;
+use axum::http::HeaderMap;
+use axum::response::{IntoResponse, Response};
 use axum::routing::{get, post};
-use axum::Router;
-use serde::Deserialize;
-use std::collections::HashMap;
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
 
 use crate::auth::middleware::AuthUser;
 use crate::error::AppError;
-use crate::models::{mark, media, persistent, user};
+use crate::models::preferences::{self, Preferences};
+use crate::models::{comment, edition, library_access, mark, media, persistent, tag};
+use crate::routes::etag;
+use crate::routes::media::ListItem;
+use crate::routes::pager::Pager;
 use crate::routes::sort::{apply_sort_dir, SortDir};
-use crate::routes::AppState;
+use crate::routes::{url, AppState};
 use crate::templates::{MediaCardPartial, MediaRow, MoviesTemplate};
 
 pub fn router() -> Router<AppState> {
     Router::new()
         .route(
             "/",
-            get(|| async { axum::response::Redirect::to("/movies") }),
+            get(|| async { axum::response::Redirect::to(&url("/movies")) }),
         )
         .route("/movies", get(list_movies))
+        .route("/movies.json", get(list_movies_json))
+        .route("/movies/{id}/card", get(movie_card))
         .route("/movies/{id}/mark", post(mark_movie).delete(unmark_movie))
         .route(
             "/movies/{id}/persist",
@@ -26,7 +32,7 @@ pub fn router() -> Router<AppState> {
         )
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Hash)]
 struct ListQuery {
     #[serde(default)]
     show_marked: Option<String>,
@@ -34,6 +40,27 @@ struct ListQuery {
     sort: Option<String>,
     #[serde(default)]
     dir: Option<String>,
+    #[serde(default)]
+    page: Option<usize>,
+    #[serde(default)]
+    tag: Option<String>,
+}
+
+impl ListQuery {
+    /// Opening the page without any list settings in the URL gives the
+    /// user's saved defaults.
+    fn or_preferences(self, prefs: &Preferences) -> Self {
+        if self.show_marked.is_some() || self.sort.is_some() || self.dir.is_some() {
+            return self;
+        }
+        Self {
+            show_marked: Some(prefs.show_marked.to_string()),
+            sort: Some(prefs.sort.clone()),
+            dir: Some(prefs.sort_dir.clone()),
+            page: self.page,
+            tag: self.tag,
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -64,44 +91,51 @@ impl MovieSortBy {
     }
 }
 
-async fn list_movies(
-    State(state): State<AppState>,
-    auth: AuthUser,
-    Query(query): Query<ListQuery>,
-) -> Result<impl IntoResponse, AppError> {
+/// The user's movies as `query` filters and sorts them, and the page of them it asks for.
+async fn movie_page(
+    state: &AppState,
+    auth: &AuthUser,
+    query: &ListQuery,
+    page_size: i64,
+) -> Result<(Vec<MediaRow>, Pager), AppError> {
     let show_marked = query.show_marked.as_deref() == Some("true");
+    let tag_filter = query.tag.as_deref().filter(|t| !t.is_empty());
     let sort_by = MovieSortBy::parse(query.sort.as_deref());
     let sort_dir = SortDir::parse(query.dir.as_deref());
-    let all_media = media::list_visible_for_user(&state.pool, "movie", auth.id).await?;
-    let user_marks = mark::user_marks(&state.pool, auth.id).await?;
-    let total_users = user::count(&state.pool).await?;
-    let media_ids: Vec<i64> = all_media.iter().map(|m| m.id).collect();
-    let owners = persistent::owner_for_media_ids(&state.pool, &media_ids).await?;
-    let owner_map: HashMap<i64, i64> = owners
-        .into_iter()
-        .map(|o| (o.media_id, o.user_id))
-        .collect();
+    let rows = media::list_page_rows(
+        &state.pool,
+        "movie",
+        auth.id,
+        state.config.current().keep_vote_days,
+    )
+    .await?;
+    let mut tags = tag::by_media(&state.pool, "movie").await?;
+    let mut comments = comment::previews(&state.pool, "movie").await?;
+    let editions = edition::others_by_media(&state.pool).await?;
 
     let mut items = Vec::new();
-    for m in all_media {
-        let owner = owner_map.get(&m.id).copied();
-        let persisted = m.status == "permanent";
-        let persisted_by_me = owner == Some(auth.id);
-        let marked = !persisted && user_marks.contains(&m.id);
-        if !show_marked && marked {
+    for row in rows {
+        if !show_marked && row.marked {
+            continue;
+        }
+        let item_tags = tags.remove(&row.media.id).unwrap_or_default();
+        if tag_filter.is_some_and(|t| !item_tags.iter().any(|i| i.eq_ignore_ascii_case(t))) {
             continue;
         }
-        let mark_count = mark::mark_count(&state.pool, m.id).await?;
         items.push(MediaRow {
-            media: m,
-            marked,
-            mark_count,
-            total_users,
-            persisted,
-            persisted_by_me,
+            mark_count: row.mark_count,
+            total_users: row.total_users,
+            persisted: row.media.status == "permanent",
+            persisted_by_me: row.persisted_by_me,
+            series_persisted: false,
+            editions: editions.get(&row.media.id).copied().unwrap_or(0),
+            kept: row.kept,
+            tags: item_tags,
+            comments: comments.remove(&row.media.id).unwrap_or_default(),
+            media: row.media,
+            marked: row.marked,
         });
     }
-
     items.sort_by(|a, b| {
         let ordering = match sort_by {
             MovieSortBy::Name => a
@@ -126,15 +160,101 @@ async fn list_movies(
         };
         apply_sort_dir(ordering, sort_dir)
     });
+    Ok(Pager::paginate(items, page_size, query.page))
+}
 
-    Ok(MoviesTemplate {
+async fn list_movies(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Query(query): Query<ListQuery>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let prefs = preferences::get(&state.pool, auth.id).await?;
+    let query = query.or_preferences(&prefs);
+    let version = media::list_page_version(&state.pool, auth.id).await?;
+    // The hour keeps relative times like "added 5 minutes ago" roughly current.
+    let hour = jiff::Timestamp::now().as_second() / 3600;
+    let tag = etag::etag((
+        "list_movies",
+        auth.id,
+        auth.is_admin,
+        &query,
+        prefs.page_size,
+        version,
+        hour,
+        // The dry-run and maintenance banners are part of the page too.
+        state.dry_run.get(),
+        state.config.current().maintenance,
+    ));
+    if etag::is_fresh(&headers, &tag) {
+        return Ok(etag::not_modified(tag));
+    }
+    let (items, pager) = movie_page(&state, &auth, &query, prefs.page_size).await?;
+    let page = MoviesTemplate {
         username: auth.username,
         is_admin: auth.is_admin,
         items,
-        show_marked,
-        sort_by: sort_by.as_str().to_string(),
-        sort_dir: sort_dir.as_str().to_string(),
-    })
+        pager,
+        show_marked: query.show_marked.as_deref() == Some("true"),
+        sort_by: MovieSortBy::parse(query.sort.as_deref())
+            .as_str()
+            .to_string(),
+        sort_dir: SortDir::parse(query.dir.as_deref()).as_str().to_string(),
+        reclaimable: media::reclaimable_for_user(&state.pool, auth.id).await?,
+        tag: query.tag.clone().filter(|t| !t.is_empty()),
+        all_tags: tag::list_in_use(&state.pool, "movie").await?,
+    };
+    Ok(etag::with_etag(page, tag))
+}
+
+/// One page of the movies list as JSON, for clients that load more as the user scrolls.
+#[derive(Serialize)]
+struct MoviesJson {
+    page: usize,
+    pages: usize,
+    next_page: Option<usize>,
+    items: Vec<ListItem>,
+}
+
+async fn list_movies_json(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Query(query): Query<ListQuery>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let prefs = preferences::get(&state.pool, auth.id).await?;
+    let query = query.or_preferences(&prefs);
+    let version = media::list_page_version(&state.pool, auth.id).await?;
+    let tag = etag::etag((
+        "list_movies_json",
+        auth.id,
+        auth.is_admin,
+        &query,
+        prefs.page_size,
+        version,
+    ));
+    if etag::is_fresh(&headers, &tag) {
+        return Ok(etag::not_modified(tag));
+    }
+    let (items, pager) = movie_page(&state, &auth, &query, prefs.page_size).await?;
+    let page = MoviesJson {
+        page: pager.page,
+        pages: pager.pages,
+        next_page: pager.next(),
+        items: items
+            .into_iter()
+            .map(|item| ListItem::new(item, auth.is_admin))
+            .collect(),
+    };
+    Ok(etag::with_etag(Json(page), tag))
+}
+
+async fn movie_card(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<i64>,
+) -> Result<Response, AppError> {
+    crate::routes::media::media_card(&state, &auth, id, "movie").await
 }
 
 async fn mark_movie(
@@ -142,19 +262,27 @@ async fn mark_movie(
     auth: AuthUser,
     Path(id): Path<i64>,
 ) -> Result<impl IntoResponse, AppError> {
-    let m = media::get_by_id(&state.pool, id)
-        .await?
-        .ok_or(AppError::NotFound)?;
+    let m = crate::routes::media::accessible_media(&state, &auth, id).await?;
     if m.status != "active" {
         return Err(AppError::NotFound);
     }
+    if m.protected {
+        return Err(AppError::BadRequest(format!("{} is protected", m.title)));
+    }
 
     mark::mark(&state.pool, auth.id, id).await?;
+    tracing::info!(event = "mark", media_id = id, user = %auth.username, "Marked {}", m.path);
 
     // Check if all users marked → move to trash
-    crate::trash::check_and_trash(&state.pool, id, &state.config, state.dry_run)
-        .await
-        .map_err(|e| AppError::Internal(format!("trash operation failed: {e}")))?;
+    crate::trash::check_and_trash(
+        &state.pool,
+        id,
+        &auth.username,
+        &state.config.current(),
+        state.dry_run.get(),
+    )
+    .await
+    .map_err(|e| AppError::Internal(format!("trash operation failed: {e}")))?;
 
     // Re-fetch to get updated state
     let media_item = media::get_by_id(&state.pool, id).await?.unwrap_or(m);
@@ -165,7 +293,7 @@ async fn mark_movie(
     }
 
     let mark_count = mark::mark_count(&state.pool, id).await?;
-    let total_users = user::count(&state.pool).await?;
+    let total_users = library_access::voter_count(&state.pool, id).await?;
 
     Ok(MediaCardPartial {
         item: MediaRow {
@@ -175,6 +303,11 @@ async fn mark_movie(
             total_users,
             persisted: false,
             persisted_by_me: false,
+            series_persisted: false,
+            editions: edition::others(&state.pool, id).await?,
+            kept: false,
+            tags: tag::for_media(&state.pool, id).await?,
+            comments: comment::preview_for(&state.pool, id).await?,
         },
         is_admin: auth.is_admin,
     }
@@ -186,17 +319,16 @@ async fn unmark_movie(
     auth: AuthUser,
     Path(id): Path<i64>,
 ) -> Result<impl IntoResponse, AppError> {
-    let m = media::get_by_id(&state.pool, id)
-        .await?
-        .ok_or(AppError::NotFound)?;
+    let m = crate::routes::media::accessible_media(&state, &auth, id).await?;
     if m.status != "active" {
         return Err(AppError::NotFound);
     }
 
     mark::unmark(&state.pool, auth.id, id).await?;
+    tracing::info!(event = "unmark", media_id = id, user = %auth.username, "Unmarked {}", m.path);
 
     let mark_count = mark::mark_count(&state.pool, id).await?;
-    let total_users = user::count(&state.pool).await?;
+    let total_users = library_access::voter_count(&state.pool, id).await?;
 
     Ok(MediaCardPartial {
         item: MediaRow {
@@ -206,6 +338,11 @@ async fn unmark_movie(
             total_users,
             persisted: false,
             persisted_by_me: false,
+            series_persisted: false,
+            editions: edition::others(&state.pool, id).await?,
+            kept: false,
+            tags: tag::for_media(&state.pool, id).await?,
+            comments: comment::preview_for(&state.pool, id).await?,
         },
         is_admin: auth.is_admin,
     })
@@ -216,20 +353,28 @@ async fn persist_movie(
     auth: AuthUser,
     Path(id): Path<i64>,
 ) -> Result<impl IntoResponse, AppError> {
-    let m = media::get_by_id(&state.pool, id)
-        .await?
-        .ok_or(AppError::NotFound)?;
+    // Persisting moves files, which restricted users must not trigger.
+    if auth.restricted {
+        return Err(AppError::Forbidden);
+    }
+    let m = crate::routes::media::accessible_media(&state, &auth, id).await?;
     if m.status != "active" {
         return Err(AppError::NotFound);
     }
 
-    crate::persistent::move_to_permanent(&state.pool, id, auth.id, &state.config, state.dry_run)
-        .await
-        .map_err(|e| AppError::Internal(format!("persist operation failed: {e}")))?;
+    crate::persistent::move_to_permanent(
+        &state.pool,
+        id,
+        auth.id,
+        &state.config.current(),
+        state.dry_run.get(),
+    )
+    .await
+    .map_err(|e| AppError::Internal(format!("persist operation failed: {e}")))?;
 
     let media_item = media::get_by_id(&state.pool, id).await?.unwrap_or(m);
     let mark_count = mark::mark_count(&state.pool, id).await?;
-    let total_users = user::count(&state.pool).await?;
+    let total_users = library_access::voter_count(&state.pool, id).await?;
 
     Ok(MediaCardPartial {
         item: MediaRow {
@@ -239,6 +384,11 @@ async fn persist_movie(
             total_users,
             persisted: true,
             persisted_by_me: true,
+            series_persisted: false,
+            editions: edition::others(&state.pool, id).await?,
+            kept: false,
+            tags: tag::for_media(&state.pool, id).await?,
+            comments: comment::preview_for(&state.pool, id).await?,
         },
         is_admin: auth.is_admin,
     })
@@ -249,9 +399,7 @@ async fn unpersist_movie(
     auth: AuthUser,
     Path(id): Path<i64>,
 ) -> Result<impl IntoResponse, AppError> {
-    let m = media::get_by_id(&state.pool, id)
-        .await?
-        .ok_or(AppError::NotFound)?;
+    let m = crate::routes::media::accessible_media(&state, &auth, id).await?;
     if m.status != "permanent" {
         return Err(AppError::NotFound);
     }
@@ -266,15 +414,15 @@ async fn unpersist_movie(
         &state.pool,
         id,
         auth.id,
-        &state.config,
-        state.dry_run,
+        &state.config.current(),
+        state.dry_run.get(),
     )
     .await
     .map_err(|e| AppError::Internal(format!("unpersist operation failed: {e}")))?;
 
     let media_item = media::get_by_id(&state.pool, id).await?.unwrap_or(m);
     let mark_count = mark::mark_count(&state.pool, id).await?;
-    let total_users = user::count(&state.pool).await?;
+    let total_users = library_access::voter_count(&state.pool, id).await?;
 
     Ok(MediaCardPartial {
         item: MediaRow {
@@ -284,6 +432,11 @@ async fn unpersist_movie(
             total_users,
             persisted: false,
             persisted_by_me: false,
+            series_persisted: false,
+            editions: edition::others(&state.pool, id).await?,
+            kept: false,
+            tags: tag::for_media(&state.pool, id).await?,
+            comments: comment::preview_for(&state.pool, id).await?,
         },
         is_admin: auth.is_admin,
     })
@@ -0,0 +1,53 @@
+/// Where the shown page sits among all pages of a list.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Pager {
+    pub page: usize,
+    pub pages: usize,
+}
+
+impl Pager {
+    /// Keep page `page` (1-based) of `items` with `per_page` items each; a
+    /// `per_page` of 0 keeps everything. Pages past the end show the last one.
+    pub fn paginate<T>(items: Vec<T>, per_page: i64, page: Option<usize>) -> (Vec<T>, Pager) {
+        let per_page = usize::try_from(per_page).unwrap_or(0);
+        if per_page == 0 {
+            return (items, Pager { page: 1, pages: 1 });
+        }
+        let pages = items.len().div_ceil(per_page).max(1);
+        let page = page.unwrap_or(1).clamp(1, pages);
+        let items = items
+            .into_iter()
+            .skip((page - 1) * per_page)
+            .take(per_page)
+            .collect();
+        (items, Pager { page, pages })
+    }
+
+    pub fn prev(&self) -> Option<usize> {
+        (self.page > 1).then(|| self.page - 1)
+    }
+
+    pub fn next(&self) -> Option<usize> {
+        (self.page < self.pages).then(|| self.page + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paginates_and_clamps() {
+        let (items, pager) = Pager::paginate((1..=5).collect(), 2, Some(2));
+        assert_eq!(items, vec![3, 4]);
+        assert_eq!((pager.prev(), pager.next()), (Some(1), Some(3)));
+
+        let (items, pager) = Pager::paginate((1..=5).collect(), 2, Some(9));
+        assert_eq!(items, vec![5]);
+        assert_eq!(pager, Pager { page: 3, pages: 3 });
+
+        let (items, pager) = Pager::paginate((1..=5).collect::<Vec<_>>(), 0, Some(2));
+        assert_eq!(items.len(), 5);
+        assert_eq!((pager.prev(), pager.next()), (None, None));
+    }
+}
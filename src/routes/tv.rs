@@ -1,27 +1,40 @@
 use axum::extract::{Path, Query, State};
-use axum::response::IntoResponse;
+use axum::http::HeaderMap;
+use axum::response::{IntoResponse, Response};
 use axum::routing::{get, post};
-use axum::Router;
-use serde::Deserialize;
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, HashMap};
 
 use crate::auth::middleware::AuthUser;
+use crate::config::SpecialsPolicy;
 use crate::error::AppError;
-use crate::models::{mark, media, persistent, user};
+use crate::models::preferences::{self, Preferences};
+use crate::models::{comment, library_access, mark, media, persistent, tag};
+use crate::routes::etag;
+use crate::routes::media::ListItem;
+use crate::routes::pager::Pager;
 use crate::routes::sort::{apply_sort_dir, SortDir};
 use crate::routes::AppState;
-use crate::templates::{poster_image_url, MediaCardPartial, MediaRow, TvSeriesGroup, TvTemplate};
+use crate::templates::{
+    poster_image_url, MediaCardPartial, MediaRow, SeasonSize, TvSeriesGroup, TvTemplate,
+};
 
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/tv", get(list_tv))
+        .route("/tv.json", get(list_tv_json))
         .route("/tv/series/{series}/mark-all", post(mark_series))
-        .route("/tv/series/{series}/persist-all", post(persist_series))
+        .route(
+            "/tv/series/{series}/persist-all",
+            post(persist_series).delete(unpersist_series),
+        )
+        .route("/tv/{id}/card", get(tv_card))
         .route("/tv/{id}/mark", post(mark_tv).delete(unmark_tv))
         .route("/tv/{id}/persist", post(persist_tv).delete(unpersist_tv))
 }
 
-#[derive(Deserialize, Clone)]
+#[derive(Deserialize, Clone, Hash)]
 struct ListQuery {
     #[serde(default)]
     show_marked: Option<String>,
@@ -29,6 +42,27 @@ struct ListQuery {
     sort: Option<String>,
     #[serde(default)]
     dir: Option<String>,
+    #[serde(default)]
+    page: Option<usize>,
+    #[serde(default)]
+    tag: Option<String>,
+}
+
+impl ListQuery {
+    /// Opening the page without any list settings in the URL gives the
+    /// user's saved defaults.
+    fn or_preferences(self, prefs: &Preferences) -> Self {
+        if self.show_marked.is_some() || self.sort.is_some() || self.dir.is_some() {
+            return self;
+        }
+        Self {
+            show_marked: Some(prefs.show_marked.to_string()),
+            sort: Some(prefs.sort.clone()),
+            dir: Some(prefs.sort_dir.clone()),
+            page: self.page,
+            tag: self.tag,
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -61,6 +95,8 @@ impl TvSortBy {
 
 fn build_tv_groups(
     items: Vec<MediaRow>,
+    mut season_sizes: HashMap<String, Vec<SeasonSize>>,
+    my_series: &[String],
     sort_by: TvSortBy,
     sort_dir: SortDir,
 ) -> Vec<TvSeriesGroup> {
@@ -87,15 +123,21 @@ fn build_tv_groups(
         });
         let marked_count = seasons.iter().filter(|s| s.marked).count() as i64;
         let total_count = seasons.len() as i64;
-        let poster_url = seasons
-            .first()
-            .and_then(|s| poster_image_url(&s.media.poster_path));
+        let poster_path = seasons.first().and_then(|s| s.media.poster_path.clone());
+        let mut season_sizes = season_sizes.remove(&title).unwrap_or_default();
+        if sort_by == TvSortBy::Season {
+            season_sizes.sort_by(|a, b| apply_sort_dir(a.season.cmp(&b.season), sort_dir));
+        }
+        let total_size = season_sizes.iter().map(|s| s.size_bytes).sum();
         groups.push(TvSeriesGroup {
+            persisted_by_me: my_series.contains(&title),
             title,
             seasons,
             marked_count,
             total_count,
-            poster_url,
+            poster_path,
+            season_sizes,
+            total_size,
         });
     }
 
@@ -129,54 +171,173 @@ fn build_tv_groups(
     groups
 }
 
-async fn list_tv(
-    State(state): State<AppState>,
-    auth: AuthUser,
-    Query(query): Query<ListQuery>,
-) -> Result<impl IntoResponse, AppError> {
+/// The user's shows as `query` filters and sorts them, and the page of them it asks for.
+async fn tv_page(
+    state: &AppState,
+    auth: &AuthUser,
+    query: &ListQuery,
+    page_size: i64,
+) -> Result<(Vec<TvSeriesGroup>, Pager), AppError> {
     let show_marked = query.show_marked.as_deref() == Some("true");
+    let tag_filter = query.tag.as_deref().filter(|t| !t.is_empty());
     let sort_by = TvSortBy::parse(query.sort.as_deref());
     let sort_dir = SortDir::parse(query.dir.as_deref());
-    let all_media = media::list_visible_for_user(&state.pool, "tv_season", auth.id).await?;
-    let user_marks = mark::user_marks(&state.pool, auth.id).await?;
-    let total_users = user::count(&state.pool).await?;
-    let media_ids: Vec<i64> = all_media.iter().map(|m| m.id).collect();
-    let owners = persistent::owner_for_media_ids(&state.pool, &media_ids).await?;
-    let owner_map: HashMap<i64, i64> = owners
-        .into_iter()
-        .map(|o| (o.media_id, o.user_id))
-        .collect();
+    let rows = media::list_page_rows(
+        &state.pool,
+        "tv_season",
+        auth.id,
+        state.config.current().keep_vote_days,
+    )
+    .await?;
+    let mut tags = tag::by_media(&state.pool, "tv_season").await?;
+    let mut comments = comment::previews(&state.pool, "tv_season").await?;
+    // The breakdown covers seasons hidden as marked, too; they still take up space.
+    let mut season_sizes: HashMap<String, Vec<SeasonSize>> = HashMap::new();
+    for m in rows.iter().map(|r| &r.media) {
+        season_sizes
+            .entry(m.title.clone())
+            .or_default()
+            .push(SeasonSize::from(m));
+    }
+
+    let my_series = persistent::series_titles_by_owner(&state.pool, auth.id).await?;
+    let folded = state.config.current().specials == SpecialsPolicy::Fold;
 
     let mut items = Vec::new();
-    for m in all_media {
-        let owner = owner_map.get(&m.id).copied();
-        let persisted = m.status == "permanent";
-        let persisted_by_me = owner == Some(auth.id);
-        let marked = !persisted && user_marks.contains(&m.id);
-        if !show_marked && marked {
+    for row in rows {
+        if !show_marked && row.marked {
+            continue;
+        }
+        // Folded specials only count towards the show's size.
+        if folded && row.media.season == Some(0) {
+            continue;
+        }
+        let item_tags = tags.remove(&row.media.id).unwrap_or_default();
+        if tag_filter.is_some_and(|t| !item_tags.iter().any(|i| i.eq_ignore_ascii_case(t))) {
             continue;
         }
-        let mark_count = mark::mark_count(&state.pool, m.id).await?;
         items.push(MediaRow {
-            media: m,
-            marked,
-            mark_count,
-            total_users,
-            persisted,
-            persisted_by_me,
+            mark_count: row.mark_count,
+            total_users: row.total_users,
+            persisted: row.media.status == "permanent",
+            persisted_by_me: row.persisted_by_me,
+            series_persisted: row.persisted_by_me && my_series.contains(&row.media.title),
+            editions: 0,
+            kept: row.kept,
+            tags: item_tags,
+            comments: comments.remove(&row.media.id).unwrap_or_default(),
+            media: row.media,
+            marked: row.marked,
         });
     }
 
-    let series_groups = build_tv_groups(items, sort_by, sort_dir);
+    let series_groups = build_tv_groups(items, season_sizes, &my_series, sort_by, sort_dir);
+    Ok(Pager::paginate(series_groups, page_size, query.page))
+}
 
-    Ok(TvTemplate {
+async fn list_tv(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Query(query): Query<ListQuery>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let prefs = preferences::get(&state.pool, auth.id).await?;
+    let query = query.or_preferences(&prefs);
+    let version = media::list_page_version(&state.pool, auth.id).await?;
+    // The hour keeps relative times like "added 5 minutes ago" roughly current.
+    let hour = jiff::Timestamp::now().as_second() / 3600;
+    let tag = etag::etag((
+        "list_tv",
+        auth.id,
+        auth.is_admin,
+        &query,
+        prefs.page_size,
+        version,
+        hour,
+        // The dry-run and maintenance banners are part of the page too.
+        state.dry_run.get(),
+        state.config.current().maintenance,
+    ));
+    if etag::is_fresh(&headers, &tag) {
+        return Ok(etag::not_modified(tag));
+    }
+    let (series_groups, pager) = tv_page(&state, &auth, &query, prefs.page_size).await?;
+    let page = TvTemplate {
         username: auth.username,
         is_admin: auth.is_admin,
         series_groups,
-        show_marked,
-        sort_by: sort_by.as_str().to_string(),
-        sort_dir: sort_dir.as_str().to_string(),
-    })
+        pager,
+        show_marked: query.show_marked.as_deref() == Some("true"),
+        sort_by: TvSortBy::parse(query.sort.as_deref()).as_str().to_string(),
+        sort_dir: SortDir::parse(query.dir.as_deref()).as_str().to_string(),
+        reclaimable: media::reclaimable_for_user(&state.pool, auth.id).await?,
+        tag: query.tag.clone().filter(|t| !t.is_empty()),
+        all_tags: tag::list_in_use(&state.pool, "tv_season").await?,
+    };
+    Ok(etag::with_etag(page, tag))
+}
+
+/// A show on the JSON variant of the TV page.
+#[derive(Serialize)]
+struct SeriesJson {
+    title: String,
+    poster_url: Option<String>,
+    /// Size of every season of the show, including ones the list hides.
+    total_size: i64,
+    size_pending: bool,
+    seasons: Vec<ListItem>,
+}
+
+/// One page of the TV list as JSON, for clients that load more as the user scrolls.
+#[derive(Serialize)]
+struct TvJson {
+    page: usize,
+    pages: usize,
+    next_page: Option<usize>,
+    series: Vec<SeriesJson>,
+}
+
+async fn list_tv_json(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Query(query): Query<ListQuery>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let prefs = preferences::get(&state.pool, auth.id).await?;
+    let query = query.or_preferences(&prefs);
+    let version = media::list_page_version(&state.pool, auth.id).await?;
+    let tag = etag::etag((
+        "list_tv_json",
+        auth.id,
+        auth.is_admin,
+        &query,
+        prefs.page_size,
+        version,
+    ));
+    if etag::is_fresh(&headers, &tag) {
+        return Ok(etag::not_modified(tag));
+    }
+    let (series_groups, pager) = tv_page(&state, &auth, &query, prefs.page_size).await?;
+    let page = TvJson {
+        page: pager.page,
+        pages: pager.pages,
+        next_page: pager.next(),
+        series: series_groups
+            .into_iter()
+            .map(|group| SeriesJson {
+                size_pending: group.size_pending(),
+                title: group.title,
+                poster_url: poster_image_url(&group.poster_path),
+                total_size: group.total_size,
+                seasons: group
+                    .seasons
+                    .into_iter()
+                    .map(|item| ListItem::new(item, auth.is_admin))
+                    .collect(),
+            })
+            .collect(),
+    };
+    Ok(etag::with_etag(Json(page), tag))
 }
 
 async fn mark_series(
@@ -185,21 +346,38 @@ async fn mark_series(
     Path(series): Path<String>,
     Query(query): Query<ListQuery>,
 ) -> Result<impl IntoResponse, AppError> {
-    let all_media = media::list_by_type(&state.pool, "tv_season").await?;
+    let all_media = media::list_visible_for_user(&state.pool, "tv_season", auth.id).await?;
+    let folded = state.config.current().specials == SpecialsPolicy::Fold;
     let ids: Vec<i64> = all_media
         .into_iter()
-        .filter(|m| m.title == series && m.status == "active")
+        .filter(|m| m.title == series && m.status == "active" && !m.protected)
+        .filter(|m| !(folded && m.season == Some(0)))
         .map(|m| m.id)
         .collect();
 
+    crate::trash::series::mark_series(
+        &state.pool,
+        auth.id,
+        &ids,
+        &auth.username,
+        &state.config.current(),
+        state.dry_run.get(),
+    )
+    .await
+    .map_err(|e| AppError::Internal(format!("trash operation failed: {e}")))?;
     for id in ids {
-        mark::mark(&state.pool, auth.id, id).await?;
-        crate::trash::check_and_trash(&state.pool, id, &state.config, state.dry_run)
-            .await
-            .map_err(|e| AppError::Internal(format!("trash operation failed: {e}")))?;
+        tracing::info!(event = "mark", media_id = id, user = %auth.username, "Marked {series}");
     }
 
-    list_tv(State(state), auth, Query(query)).await
+    list_tv(State(state), auth, Query(query), HeaderMap::new()).await
+}
+
+async fn tv_card(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<i64>,
+) -> Result<Response, AppError> {
+    crate::routes::media::media_card(&state, &auth, id, "tv_season").await
 }
 
 async fn mark_tv(
@@ -207,18 +385,32 @@ async fn mark_tv(
     auth: AuthUser,
     Path(id): Path<i64>,
 ) -> Result<impl IntoResponse, AppError> {
-    let m = media::get_by_id(&state.pool, id)
-        .await?
-        .ok_or(AppError::NotFound)?;
+    let m = crate::routes::media::accessible_media(&state, &auth, id).await?;
     if m.status != "active" {
         return Err(AppError::NotFound);
     }
+    if m.protected {
+        return Err(AppError::BadRequest(format!("{} is protected", m.title)));
+    }
+    if m.season == Some(0) && state.config.current().specials == SpecialsPolicy::Fold {
+        return Err(AppError::BadRequest(format!(
+            "The specials of {} go with the show",
+            m.title
+        )));
+    }
 
     mark::mark(&state.pool, auth.id, id).await?;
+    tracing::info!(event = "mark", media_id = id, user = %auth.username, "Marked {}", m.path);
 
-    crate::trash::check_and_trash(&state.pool, id, &state.config, state.dry_run)
-        .await
-        .map_err(|e| AppError::Internal(format!("trash operation failed: {e}")))?;
+    crate::trash::check_and_trash(
+        &state.pool,
+        id,
+        &auth.username,
+        &state.config.current(),
+        state.dry_run.get(),
+    )
+    .await
+    .map_err(|e| AppError::Internal(format!("trash operation failed: {e}")))?;
 
     let media_item = media::get_by_id(&state.pool, id).await?.unwrap_or(m);
 
@@ -228,7 +420,7 @@ async fn mark_tv(
     }
 
     let mark_count = mark::mark_count(&state.pool, id).await?;
-    let total_users = user::count(&state.pool).await?;
+    let total_users = library_access::voter_count(&state.pool, id).await?;
 
     Ok(MediaCardPartial {
         item: MediaRow {
@@ -238,6 +430,11 @@ async fn mark_tv(
             total_users,
             persisted: false,
             persisted_by_me: false,
+            series_persisted: false,
+            editions: 0,
+            kept: false,
+            tags: tag::for_media(&state.pool, id).await?,
+            comments: comment::preview_for(&state.pool, id).await?,
         },
         is_admin: auth.is_admin,
     }
@@ -249,17 +446,16 @@ async fn unmark_tv(
     auth: AuthUser,
     Path(id): Path<i64>,
 ) -> Result<impl IntoResponse, AppError> {
-    let m = media::get_by_id(&state.pool, id)
-        .await?
-        .ok_or(AppError::NotFound)?;
+    let m = crate::routes::media::accessible_media(&state, &auth, id).await?;
     if m.status != "active" {
         return Err(AppError::NotFound);
     }
 
     mark::unmark(&state.pool, auth.id, id).await?;
+    tracing::info!(event = "unmark", media_id = id, user = %auth.username, "Unmarked {}", m.path);
 
     let mark_count = mark::mark_count(&state.pool, id).await?;
-    let total_users = user::count(&state.pool).await?;
+    let total_users = library_access::voter_count(&state.pool, id).await?;
 
     Ok(MediaCardPartial {
         item: MediaRow {
@@ -269,6 +465,11 @@ async fn unmark_tv(
             total_users,
             persisted: false,
             persisted_by_me: false,
+            series_persisted: false,
+            editions: 0,
+            kept: false,
+            tags: tag::for_media(&state.pool, id).await?,
+            comments: comment::preview_for(&state.pool, id).await?,
         },
         is_admin: auth.is_admin,
     })
@@ -280,26 +481,51 @@ async fn persist_series(
     Path(series): Path<String>,
     Query(query): Query<ListQuery>,
 ) -> Result<impl IntoResponse, AppError> {
-    let all_media = media::list_by_type(&state.pool, "tv_season").await?;
+    if auth.restricted {
+        return Err(AppError::Forbidden);
+    }
+    let all_media = media::list_visible_for_user(&state.pool, "tv_season", auth.id).await?;
     let ids: Vec<i64> = all_media
         .into_iter()
-        .filter(|m| m.title == series && m.status == "active")
+        .filter(|m| m.title == series && m.status == "active" && !m.protected)
         .map(|m| m.id)
         .collect();
 
-    for id in ids {
-        crate::persistent::move_to_permanent(
-            &state.pool,
-            id,
-            auth.id,
-            &state.config,
-            state.dry_run,
-        )
-        .await
-        .map_err(|e| AppError::Internal(format!("persist operation failed: {e}")))?;
+    crate::persistent::persist_series(
+        &state.pool,
+        &series,
+        &ids,
+        auth.id,
+        &state.config.current(),
+        state.dry_run.get(),
+    )
+    .await
+    .map_err(|e| AppError::Internal(format!("persist operation failed: {e}")))?;
+
+    list_tv(State(state), auth, Query(query), HeaderMap::new()).await
+}
+
+async fn unpersist_series(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(series): Path<String>,
+    Query(query): Query<ListQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let owner = persistent::get_series_owner(&state.pool, &series).await?;
+    if owner.map(|o| o.user_id) != Some(auth.id) {
+        return Err(AppError::NotFound);
     }
+    crate::persistent::unpersist_series(
+        &state.pool,
+        &series,
+        auth.id,
+        &state.config.current(),
+        state.dry_run.get(),
+    )
+    .await
+    .map_err(|e| AppError::Internal(format!("unpersist operation failed: {e}")))?;
 
-    list_tv(State(state), auth, Query(query)).await
+    list_tv(State(state), auth, Query(query), HeaderMap::new()).await
 }
 
 async fn persist_tv(
@@ -307,20 +533,27 @@ async fn persist_tv(
     auth: AuthUser,
     Path(id): Path<i64>,
 ) -> Result<impl IntoResponse, AppError> {
-    let m = media::get_by_id(&state.pool, id)
-        .await?
-        .ok_or(AppError::NotFound)?;
+    if auth.restricted {
+        return Err(AppError::Forbidden);
+    }
+    let m = crate::routes::media::accessible_media(&state, &auth, id).await?;
     if m.status != "active" {
         return Err(AppError::NotFound);
     }
 
-    crate::persistent::move_to_permanent(&state.pool, id, auth.id, &state.config, state.dry_run)
-        .await
-        .map_err(|e| AppError::Internal(format!("persist operation failed: {e}")))?;
+    crate::persistent::move_to_permanent(
+        &state.pool,
+        id,
+        auth.id,
+        &state.config.current(),
+        state.dry_run.get(),
+    )
+    .await
+    .map_err(|e| AppError::Internal(format!("persist operation failed: {e}")))?;
 
     let media_item = media::get_by_id(&state.pool, id).await?.unwrap_or(m);
     let mark_count = mark::mark_count(&state.pool, id).await?;
-    let total_users = user::count(&state.pool).await?;
+    let total_users = library_access::voter_count(&state.pool, id).await?;
 
     Ok(MediaCardPartial {
         item: MediaRow {
@@ -330,6 +563,11 @@ async fn persist_tv(
             total_users,
             persisted: true,
             persisted_by_me: true,
+            series_persisted: false,
+            editions: 0,
+            kept: false,
+            tags: tag::for_media(&state.pool, id).await?,
+            comments: comment::preview_for(&state.pool, id).await?,
         },
         is_admin: auth.is_admin,
     })
@@ -340,9 +578,7 @@ async fn unpersist_tv(
     auth: AuthUser,
     Path(id): Path<i64>,
 ) -> Result<impl IntoResponse, AppError> {
-    let m = media::get_by_id(&state.pool, id)
-        .await?
-        .ok_or(AppError::NotFound)?;
+    let m = crate::routes::media::accessible_media(&state, &auth, id).await?;
     if m.status != "permanent" {
         return Err(AppError::NotFound);
     }
@@ -357,15 +593,15 @@ async fn unpersist_tv(
         &state.pool,
         id,
         auth.id,
-        &state.config,
-        state.dry_run,
+        &state.config.current(),
+        state.dry_run.get(),
     )
     .await
     .map_err(|e| AppError::Internal(format!("unpersist operation failed: {e}")))?;
 
     let media_item = media::get_by_id(&state.pool, id).await?.unwrap_or(m);
     let mark_count = mark::mark_count(&state.pool, id).await?;
-    let total_users = user::count(&state.pool).await?;
+    let total_users = library_access::voter_count(&state.pool, id).await?;
 
     Ok(MediaCardPartial {
         item: MediaRow {
@@ -375,6 +611,11 @@ async fn unpersist_tv(
             total_users,
             persisted: false,
             persisted_by_me: false,
+            series_persisted: false,
+            editions: 0,
+            kept: false,
+            tags: tag::for_media(&state.pool, id).await?,
+            comments: comment::preview_for(&state.pool, id).await?,
         },
         is_admin: auth.is_admin,
     })
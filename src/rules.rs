@@ -0,0 +1,235 @@
+//! Admin-defined rules from the `[[rules]]` tables of the config, applied to
+//! the active library by every cleanup run.
+//!
+//! A rule acts on each item at most once: an item a user unmarks after a rule
+//! marked it stays unmarked, and a notification is not repeated every hour.
+
+use serde::Deserialize;
+use sqlx::SqlitePool;
+
+use crate::config::AppConfig;
+use crate::models::media::{self, Media, MediaFilter};
+use crate::models::{mark, rule_hit, tag};
+use crate::trash;
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    pub name: String,
+    pub action: RuleAction,
+    /// "movie" or "tv_season"; both when unset.
+    pub media_type: Option<String>,
+    /// Only items first seen at least this many days ago.
+    pub older_than_days: Option<u64>,
+    /// Only items larger than this many GB, in the configured `size_units`.
+    pub larger_than_gb: Option<u64>,
+    /// Only items at least this share of users marked.
+    pub marked_by_percent: Option<u8>,
+    /// Only items with this tag.
+    pub tag: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleAction {
+    /// Mark the item for every user, which sends it to the trash.
+    Mark,
+    /// Log the item and list it under Admin → Rules.
+    Notify,
+}
+
+impl RuleAction {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RuleAction::Mark => "mark",
+            RuleAction::Notify => "notify",
+        }
+    }
+}
+
+impl Rule {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("name must not be empty".to_string());
+        }
+        if let Some(media_type) = &self.media_type {
+            if media_type != "movie" && media_type != "tv_season" {
+                return Err(format!(
+                    "media_type must be \"movie\" or \"tv_season\", not {media_type:?}"
+                ));
+            }
+        }
+        if let Some(tag) = &self.tag {
+            tag::normalize(tag)?;
+        }
+        if self.marked_by_percent.is_some_and(|p| p > 100) {
+            return Err("marked_by_percent must be at most 100".to_string());
+        }
+        // A rule without conditions would act on the whole library.
+        if self.older_than_days.is_none()
+            && self.larger_than_gb.is_none()
+            && self.marked_by_percent.is_none()
+            && self.tag.is_none()
+        {
+            return Err(
+                "needs at least one of older_than_days, larger_than_gb, marked_by_percent or tag"
+                    .to_string(),
+            );
+        }
+        Ok(())
+    }
+
+    /// The conditions in words, e.g. "TV seasons, first seen over 1095 days ago".
+    pub fn describe(&self) -> String {
+        let mut parts = vec![match self.media_type.as_deref() {
+            Some("movie") => "Movies".to_string(),
+            Some(_) => "TV seasons".to_string(),
+            None => "Movies and TV seasons".to_string(),
+        }];
+        if let Some(tag) = &self.tag {
+            parts.push(format!("tagged {tag:?}"));
+        }
+        if let Some(days) = self.older_than_days {
+            parts.push(format!("first seen over {days} days ago"));
+        }
+        if let Some(gb) = self.larger_than_gb {
+            parts.push(format!("larger than {gb} GB"));
+        }
+        if let Some(percent) = self.marked_by_percent {
+            parts.push(format!("marked by at least {percent}% of users"));
+        }
+        parts.join(", ")
+    }
+
+    fn filter(&self, config: &AppConfig) -> MediaFilter<'_> {
+        MediaFilter {
+            media_type: self.media_type.as_deref(),
+            older_than_days: self.older_than_days,
+            larger_than_bytes: self
+                .larger_than_gb
+                .map(|gb| gb as i64 * config.size_units.gigabyte()),
+            marked_by_percent: self.marked_by_percent,
+            tag: self.tag.as_deref(),
+        }
+    }
+
+    /// Items the rule would act on now: matching ones it has not acted on yet.
+    pub async fn pending(
+        &self,
+        pool: &SqlitePool,
+        config: &AppConfig,
+    ) -> Result<Vec<Media>, sqlx::Error> {
+        let done = rule_hit::media_ids(pool, &self.name).await?;
+        let items = media::list_active_matching(pool, &self.filter(config)).await?;
+        Ok(items
+            .into_iter()
+            .filter(|m| !done.contains(&m.id))
+            .collect())
+    }
+
+    /// History actor for what the rule does, e.g. "rule old seasons".
+    fn actor(&self) -> String {
+        format!("rule {}", self.name)
+    }
+}
+
+/// A rule and the items it would act on at the next cleanup run.
+pub struct RulePreview {
+    pub rule: Rule,
+    pub items: Vec<Media>,
+    pub total_size: i64,
+}
+
+pub async fn preview(
+    pool: &SqlitePool,
+    config: &AppConfig,
+) -> Result<Vec<RulePreview>, sqlx::Error> {
+    let mut previews = Vec::new();
+    for rule in &config.rules {
+        let items = rule.pending(pool, config).await?;
+        previews.push(RulePreview {
+            rule: rule.clone(),
+            total_size: items.iter().map(|m| m.size_bytes).sum(),
+            items,
+        });
+    }
+    Ok(previews)
+}
+
+/// Let every rule act on the items it newly matches.
+///
+/// Returns how many items the rules acted on.
+#[tracing::instrument(skip_all)]
+pub async fn apply(
+    pool: &SqlitePool,
+    config: &AppConfig,
+    dry_run: bool,
+) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+    let mut acted = 0;
+    for rule in &config.rules {
+        for item in rule.pending(pool, config).await? {
+            if !rule_hit::record(pool, &rule.name, item.id, rule.action.as_str()).await? {
+                continue;
+            }
+            acted += 1;
+            match rule.action {
+                RuleAction::Mark => {
+                    mark::mark_for_all(pool, item.id).await?;
+                    tracing::info!(
+                        event = "rule_mark",
+                        media_id = item.id,
+                        rule = %rule.name,
+                        "Rule {} marked {}",
+                        rule.name,
+                        item.path
+                    );
+                    trash::check_and_trash(pool, item.id, &rule.actor(), config, dry_run).await?;
+                }
+                RuleAction::Notify => {
+                    tracing::warn!(
+                        event = "rule_notify",
+                        media_id = item.id,
+                        rule = %rule.name,
+                        "Rule {} matched {} ({} bytes)",
+                        rule.name,
+                        item.path,
+                        item.size_bytes
+                    );
+                }
+            }
+        }
+    }
+    Ok(acted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(toml: &str) -> Rule {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn rules_need_a_condition_and_a_known_media_type() {
+        assert!(rule("name = \"all\"\naction = \"mark\"")
+            .validate()
+            .is_err());
+        assert!(rule(
+            "name = \"x\"\naction = \"mark\"\nmedia_type = \"show\"\nolder_than_days = 1"
+        )
+        .validate()
+        .is_err());
+        assert!(
+            rule("name = \"x\"\naction = \"notify\"\nmarked_by_percent = 101")
+                .validate()
+                .is_err()
+        );
+
+        let old = rule(
+            "name = \"old\"\naction = \"mark\"\nmedia_type = \"tv_season\"\nolder_than_days = 1095",
+        );
+        assert!(old.validate().is_ok());
+        assert_eq!(old.describe(), "TV seasons, first seen over 1095 days ago");
+    }
+}
@@ -0,0 +1,170 @@
+use sqlx::{Sqlite, SqliteConnection, SqlitePool, Transaction};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use super::status::ScanStatus;
+use crate::models::{media, media_history};
+use crate::paths::case_insensitive;
+use crate::tmdb::TmdbClient;
+
+/// Items written per transaction. Committing each item on its own costs a
+/// round trip and a sync per item, which takes minutes on a cold library.
+const BATCH_SIZE: usize = 500;
+
+/// Longest a batch holds the write lock, so marking from the UI does not
+/// wait behind a scan of a slow disk.
+const BATCH_MAX_AGE: Duration = Duration::from_secs(2);
+
+/// A poster to look up once the items it is for are committed.
+enum PosterLookup {
+    Movie {
+        id: i64,
+        title: String,
+        year: Option<i64>,
+    },
+    Series {
+        season_ids: Vec<i64>,
+        title: String,
+    },
+}
+
+/// The scanner's writes, committed every [`BATCH_SIZE`] items.
+///
+/// All reads and writes of a batch go through one connection, which also
+/// keeps sqlx's prepared statements cached across items. Whatever has to wait
+/// for the commit — events, deferred sizes, TMDB lookups — is queued here and
+/// done by [`WriteBatch::commit`]. Dropping a batch rolls it back.
+pub(super) struct WriteBatch<'a> {
+    pool: &'a SqlitePool,
+    tmdb: Option<&'a TmdbClient>,
+    status: &'a ScanStatus,
+    case_insensitive: bool,
+    tx: Option<Transaction<'static, Sqlite>>,
+    opened: Instant,
+    items: usize,
+    reactivated: Vec<i64>,
+    deferred_sizes: Vec<i64>,
+    posters: Vec<PosterLookup>,
+}
+
+impl<'a> WriteBatch<'a> {
+    pub(super) fn new(
+        pool: &'a SqlitePool,
+        tmdb: Option<&'a TmdbClient>,
+        status: &'a ScanStatus,
+        media_dir: &Path,
+    ) -> Self {
+        Self {
+            pool,
+            tmdb,
+            status,
+            case_insensitive: case_insensitive(media_dir),
+            tx: None,
+            opened: Instant::now(),
+            items: 0,
+            reactivated: Vec::new(),
+            deferred_sizes: Vec::new(),
+            posters: Vec::new(),
+        }
+    }
+
+    /// The batch's connection, beginning its transaction if needed.
+    pub(super) async fn conn(&mut self) -> Result<&mut SqliteConnection, sqlx::Error> {
+        if self.tx.is_none() {
+            self.tx = Some(self.pool.begin().await?);
+            self.opened = Instant::now();
+        }
+        Ok(&mut **self.tx.as_mut().expect("transaction just began"))
+    }
+
+    pub(super) fn tmdb(&self) -> Option<&'a TmdbClient> {
+        self.tmdb
+    }
+
+    /// Whether the media dir being scanned ignores case in names; everything
+    /// below it is taken to live on the same filesystem.
+    pub(super) fn case_insensitive(&self) -> bool {
+        self.case_insensitive
+    }
+
+    pub(super) fn reactivated(&mut self, id: i64) {
+        self.reactivated.push(id);
+    }
+
+    pub(super) fn defer_size(&mut self, id: i64) {
+        self.deferred_sizes.push(id);
+    }
+
+    pub(super) fn movie_poster(&mut self, id: i64, title: &str, year: Option<i64>) {
+        self.posters.push(PosterLookup::Movie {
+            id,
+            title: title.to_string(),
+            year,
+        });
+    }
+
+    /// Look up one poster for `title` and give it to every season in `season_ids`.
+    pub(super) fn series_poster(&mut self, season_ids: Vec<i64>, title: &str) {
+        self.posters.push(PosterLookup::Series {
+            season_ids,
+            title: title.to_string(),
+        });
+    }
+
+    /// Count an item as written, committing once the batch is full or old.
+    pub(super) async fn item_done(&mut self) -> Result<(), sqlx::Error> {
+        self.items += 1;
+        if self.items >= BATCH_SIZE || self.opened.elapsed() >= BATCH_MAX_AGE {
+            self.commit().await?;
+        }
+        Ok(())
+    }
+
+    /// Commit what was written so far, then do the work that waited for it.
+    pub(super) async fn commit(&mut self) -> Result<(), sqlx::Error> {
+        if let Some(tx) = self.tx.take() {
+            tx.commit().await?;
+        }
+        self.items = 0;
+        media_history::announce(
+            &std::mem::take(&mut self.reactivated),
+            "active",
+            media_history::SCANNER,
+        );
+        for id in std::mem::take(&mut self.deferred_sizes) {
+            self.status.defer_size(id);
+        }
+        let Some(client) = self.tmdb else {
+            return Ok(());
+        };
+        for lookup in std::mem::take(&mut self.posters) {
+            match lookup {
+                PosterLookup::Movie { id, title, year } => {
+                    match client.search_movie_poster(&title, year).await {
+                        Some(poster) => {
+                            tracing::info!("Fetched TMDB poster for movie: {title}");
+                            let hash = client.poster_hash(&poster).await;
+                            let _ =
+                                media::set_poster(self.pool, id, &poster, hash.as_deref()).await;
+                        }
+                        None => tracing::info!("No TMDB poster found for movie: {title}"),
+                    }
+                }
+                PosterLookup::Series { season_ids, title } => {
+                    match client.search_tv_poster(&title).await {
+                        Some(poster) => {
+                            tracing::info!("Fetched TMDB poster for TV: {title}");
+                            let hash = client.poster_hash(&poster).await;
+                            for id in season_ids {
+                                let _ = media::set_poster(self.pool, id, &poster, hash.as_deref())
+                                    .await;
+                            }
+                        }
+                        None => tracing::info!("No TMDB poster found for TV: {title}"),
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,123 @@
+//! Files left out of item sizes: samples, trailers and disc leftovers that
+//! the `size_exclude` patterns match.
+
+use regex::RegexSet;
+use std::path::Path;
+use std::sync::OnceLock;
+
+static SIZE_EXCLUDE: OnceLock<RegexSet> = OnceLock::new();
+
+/// Install the `size_exclude` patterns from the config. They are matched
+/// against paths relative to the item's folder, with `/` between components;
+/// a matching folder excludes everything below it.
+pub fn set_size_exclude(patterns: RegexSet) {
+    if SIZE_EXCLUDE.set(patterns).is_err() {
+        tracing::warn!("size_exclude already set, ignoring");
+    }
+}
+
+pub(super) fn patterns() -> Option<&'static RegexSet> {
+    SIZE_EXCLUDE.get()
+}
+
+/// A file below an item that is not counted in its size.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExcludedFile {
+    /// Relative to the item's folder.
+    pub path: String,
+    pub size_bytes: i64,
+}
+
+/// Files below the item folder at `path` that the `size_exclude` patterns
+/// leave out of its size, sorted by path.
+pub fn excluded_files(path: &Path) -> Vec<ExcludedFile> {
+    let Some(exclude) = patterns() else {
+        return Vec::new();
+    };
+    let mut files = Vec::new();
+    walk(
+        path,
+        "",
+        Some(exclude),
+        false,
+        &mut |rel, size, excluded| {
+            if excluded {
+                files.push(ExcludedFile {
+                    path: rel.to_string(),
+                    size_bytes: size as i64,
+                });
+            }
+        },
+    );
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    files
+}
+
+/// Call `visit` with the relative path and size of every file below `dir`,
+/// and whether `exclude` leaves it out.
+pub(super) fn walk(
+    dir: &Path,
+    prefix: &str,
+    exclude: Option<&RegexSet>,
+    excluded: bool,
+    visit: &mut impl FnMut(&str, u64, bool),
+) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let Ok(ft) = entry.file_type() else {
+            continue;
+        };
+        let rel = format!("{prefix}{}", entry.file_name().to_string_lossy());
+        let excluded = excluded || exclude.is_some_and(|set| set.is_match(&rel));
+        if ft.is_file() {
+            visit(
+                &rel,
+                entry.metadata().map(|m| m.len()).unwrap_or(0),
+                excluded,
+            );
+        } else if ft.is_dir() {
+            walk(&entry.path(), &format!("{rel}/"), exclude, excluded, visit);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sizes(root: &Path, exclude: &RegexSet) -> (u64, Vec<String>) {
+        let (mut kept, mut dropped) = (0, Vec::new());
+        walk(
+            root,
+            "",
+            Some(exclude),
+            false,
+            &mut |rel, size, excluded| {
+                if excluded {
+                    dropped.push(rel.to_string());
+                } else {
+                    kept += size;
+                }
+            },
+        );
+        dropped.sort();
+        (kept, dropped)
+    }
+
+    #[test]
+    fn matching_files_and_folders_are_left_out() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join("Heat.mkv"), vec![0; 100]).unwrap();
+        std::fs::write(root.join("heat-sample.mkv"), vec![0; 10]).unwrap();
+        std::fs::create_dir_all(root.join("Extras/Interviews")).unwrap();
+        std::fs::write(root.join("Extras/Interviews/mann.mkv"), vec![0; 20]).unwrap();
+        let exclude = RegexSet::new([r"(?i)-sample\.\w+$", r"(?i)(^|/)extras(/|$)"]).unwrap();
+
+        let (kept, dropped) = sizes(root, &exclude);
+        assert_eq!(kept, 100);
+        assert_eq!(dropped, ["Extras/Interviews/mann.mkv", "heat-sample.mkv"]);
+    }
+}
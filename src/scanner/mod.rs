@@ -1,202 +1,611 @@
-use crate::models::media;
+mod batch;
+mod extras;
+mod names;
+mod seasons;
+mod sizes;
+pub mod status;
+
+use crate::models::{media, media_history, scan_summary};
+use crate::paths::{nfc, normalized};
 use crate::tmdb::TmdbClient;
-use sqlx::SqlitePool;
+use batch::WriteBatch;
+use sqlx::{SqliteConnection, SqlitePool};
 use std::collections::HashSet;
 use std::path::{Path, PathBuf};
+use std::time::Instant;
 
-/// Parse a movie directory name like "Inception (2010)" → ("Inception", Some(2010))
-pub fn parse_movie_dir(name: &str) -> (String, Option<i64>) {
-    if let Some(idx) = name.rfind('(') {
-        let year_part = name[idx + 1..].trim_end_matches(')').trim();
-        if let Ok(year) = year_part.parse::<i64>() {
-            let title = name[..idx].trim().to_string();
-            return (title, Some(year));
-        }
-    }
-    (name.to_string(), None)
+pub use crate::models::scan_summary::ScanSummary;
+pub use extras::{excluded_files, set_size_exclude, ExcludedFile};
+pub use names::parse_movie_dir;
+pub use seasons::{find_seasons, set_season_pattern, set_specials_policy};
+pub use sizes::start_size_worker;
+pub use status::{GoneAlert, ScanSnapshot, ScanStatus};
+
+/// Extensions of video files that count as a movie when placed directly in a media dir.
+const VIDEO_EXTENSIONS: &[&str] = &[
+    "mkv", "mp4", "m4v", "avi", "mov", "wmv", "mpg", "mpeg", "ts", "m2ts", "webm",
+];
+
+fn is_video_file(path: &Path) -> bool {
+    path.is_file()
+        && path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| VIDEO_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
 }
 
-/// Check if a directory contains Season subdirs
-pub fn find_seasons(path: &Path) -> Vec<(i64, PathBuf)> {
-    let mut seasons = Vec::new();
-    let entries = match std::fs::read_dir(path) {
-        Ok(e) => e,
-        Err(_) => return seasons,
+/// Name to parse a movie title from: the folder name, or the file name without
+/// its extension for a bare movie file.
+fn movie_name(path: &Path) -> String {
+    let name = if path.is_file() {
+        path.file_stem()
+    } else {
+        path.file_name()
     };
+    name.map(|n| nfc(&n.to_string_lossy())).unwrap_or_default()
+}
 
-    for entry in entries.flatten() {
-        let name = entry.file_name().to_string_lossy().to_string();
-        if entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
-            if let Some(num) = parse_season_number(&name) {
-                seasons.push((num, entry.path()));
-            }
+/// Size of a movie or season folder, or of a bare movie file, without the
+/// files the `size_exclude` patterns match.
+fn dir_size(path: &Path) -> i64 {
+    if let Ok(meta) = std::fs::metadata(path) {
+        if meta.is_file() {
+            return meta.len() as i64;
         }
     }
-    seasons.sort_by_key(|(n, _)| *n);
-    seasons
+    let mut total: u64 = 0;
+    extras::walk(
+        path,
+        "",
+        extras::patterns(),
+        false,
+        &mut |_, size, excluded| {
+            if !excluded {
+                total += size;
+            }
+        },
+    );
+    total as i64
 }
 
-fn parse_season_number(name: &str) -> Option<i64> {
-    let lower = name.to_lowercase();
-    if lower.starts_with("season ") || lower.starts_with("season_") {
-        lower[7..].trim().parse().ok()
-    } else if lower.starts_with("s") && lower.len() <= 4 {
-        lower[1..].trim().parse().ok()
-    } else {
-        None
-    }
+/// Whether a scan may trust stored sizes for directories that did not change.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScanMode {
+    /// Reuse the stored size when an item's directory fingerprint is unchanged.
+    Incremental,
+    /// Recompute every item's size from disk.
+    Full,
 }
 
-fn dir_size(path: &Path) -> i64 {
-    let mut total: u64 = 0;
+/// Fingerprint of the tree at `path`: the newest mtime (ns since the epoch) of
+/// anything in it, plus the total size of its files.
+///
+/// Adding, removing or renaming a file bumps its parent directory's mtime, and
+/// growing or overwriting one in place bumps its own, so this changes whenever
+/// the files or their contents change. The size catches files replaced by
+/// copies that kept their old mtime.
+fn tree_fingerprint(path: &Path) -> Option<i64> {
+    let metadata = std::fs::metadata(path).ok()?;
+    let (newest, bytes) = tree_stats(path, &metadata)?;
+    Some(newest.wrapping_add(bytes))
+}
+
+/// Newest mtime and total file size below `path`, whose metadata is `metadata`.
+fn tree_stats(path: &Path, metadata: &std::fs::Metadata) -> Option<(i64, i64)> {
+    let mut newest = mtime_nanos(metadata)?;
+    if !metadata.is_dir() {
+        return Some((newest, metadata.len() as i64));
+    }
+    let mut bytes: i64 = 0;
     if let Ok(entries) = std::fs::read_dir(path) {
         for entry in entries.flatten() {
-            let ft = match entry.file_type() {
-                Ok(ft) => ft,
-                Err(_) => continue,
+            let Ok(child) = entry.metadata() else {
+                continue;
             };
-            if ft.is_file() {
-                total += entry.metadata().map(|m| m.len()).unwrap_or(0);
-            } else if ft.is_dir() {
-                total += dir_size(&entry.path()) as u64;
+            if let Some((child_newest, child_bytes)) = tree_stats(&entry.path(), &child) {
+                newest = newest.max(child_newest);
+                bytes = bytes.wrapping_add(child_bytes);
             }
         }
     }
-    total as i64
+    Some((newest, bytes))
+}
+
+fn mtime_nanos(metadata: &std::fs::Metadata) -> Option<i64> {
+    let modified = metadata.modified().ok()?;
+    Some(
+        modified
+            .duration_since(std::time::UNIX_EPOCH)
+            .ok()?
+            .as_nanos() as i64,
+    )
+}
+
+/// What a scan found out about an item's size.
+struct Measurement {
+    size: i64,
+    /// Fingerprint to store alongside the size, see [`tree_fingerprint`].
+    mtime: Option<i64>,
+    /// `size` is the stored one and the background worker measures the real one.
+    pending: bool,
+}
+
+/// Size of the item at `path`, plus the fingerprint to store alongside it.
+///
+/// If `status` has a size worker, items that need measuring keep their stored
+/// size (zero for new ones) and are left to the worker instead. Also records
+/// in `summary` how the item changed since it was last seen; deferred sizes
+/// are not part of its size delta.
+async fn measure_item(
+    conn: &mut SqliteConnection,
+    path: &Path,
+    path_str: &str,
+    mode: ScanMode,
+    status: &ScanStatus,
+    summary: &mut ScanSummary,
+) -> Result<Measurement, sqlx::Error> {
+    let previous = media::get_scan_state(conn, path_str).await?;
+    let mtime = tree_fingerprint(path);
+    let (size, pending) = match &previous {
+        Some(prev)
+            if mode == ScanMode::Incremental
+                && mtime.is_some()
+                && prev.dir_mtime == mtime
+                && !prev.size_pending =>
+        {
+            (prev.size_bytes, false)
+        }
+        Some(prev) if status.defers_sizes() => (prev.size_bytes, true),
+        None if status.defers_sizes() => (0, true),
+        _ => (dir_size(path), false),
+    };
+
+    summary.items_seen += 1;
+    match previous {
+        None => {
+            summary.added += 1;
+            summary.size_delta += size;
+        }
+        Some(prev) if prev.status == "active" => summary.size_delta += size - prev.size_bytes,
+        Some(_) => {
+            summary.reactivated += 1;
+            summary.size_delta += size;
+        }
+    }
+    Ok(Measurement {
+        size,
+        mtime,
+        pending,
+    })
+}
+
+/// Write an item found on disk, along with its fingerprint, and queue its
+/// size if deferred. Returns its id.
+#[allow(clippy::too_many_arguments)]
+async fn write_item(
+    batch: &mut WriteBatch<'_>,
+    media_type: &str,
+    title: &str,
+    year: Option<i64>,
+    season: Option<i64>,
+    path: &Path,
+    mode: ScanMode,
+    status: &ScanStatus,
+    summary: &mut ScanSummary,
+) -> Result<i64, sqlx::Error> {
+    let path_str = normalized(path);
+    let case_insensitive = batch.case_insensitive();
+    let conn = batch.conn().await?;
+    if case_insensitive {
+        media::adopt_case(&mut *conn, &path_str).await?;
+    }
+    let measurement = measure_item(&mut *conn, path, &path_str, mode, status, summary).await?;
+    let (id, reactivated) = media::upsert_on(
+        &mut *conn,
+        media_type,
+        title,
+        year,
+        season,
+        &path_str,
+        measurement.size,
+    )
+    .await?;
+    media::set_dir_mtime(&mut *conn, id, measurement.mtime).await?;
+    if measurement.pending {
+        media::set_size_pending(&mut *conn, id).await?;
+        batch.defer_size(id);
+    }
+    if reactivated {
+        batch.reactivated(id);
+    }
+    Ok(id)
+}
+
+async fn scan_entry(
+    batch: &mut WriteBatch<'_>,
+    dir_path: &Path,
+    mode: ScanMode,
+    status: &ScanStatus,
+    tv_poster_fetched: &mut HashSet<String>,
+    summary: &mut ScanSummary,
+) -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync>> {
+    let mut seen_paths = Vec::new();
+    let dir_name = match dir_path.file_name() {
+        Some(name) => nfc(&name.to_string_lossy()),
+        None => return Ok(seen_paths),
+    };
+    if dir_path.is_file() && !is_video_file(dir_path) {
+        return Ok(seen_paths);
+    }
+
+    // Check if this is a TV show (has Season subdirs)
+    let seasons = find_seasons(dir_path);
+    if !seasons.is_empty() {
+        // Look up the poster once per series title, for the seasons without one
+        let wants_poster = batch.tmdb().is_some() && !tv_poster_fetched.contains(&dir_name);
+        let mut without_poster = Vec::new();
+        for (season_num, season_path) in &seasons {
+            let id = write_item(
+                batch,
+                "tv_season",
+                &dir_name,
+                None,
+                Some(*season_num),
+                season_path,
+                mode,
+                status,
+                summary,
+            )
+            .await?;
+            seen_paths.push(normalized(season_path));
+
+            if wants_poster
+                && media::needs_poster(batch.conn().await?, id)
+                    .await
+                    .unwrap_or(false)
+            {
+                without_poster.push(id);
+            }
+            batch.item_done().await?;
+        }
+        if !without_poster.is_empty() {
+            tv_poster_fetched.insert(dir_name.clone());
+            batch.series_poster(without_poster, &dir_name);
+        }
+    } else {
+        // Treat as movie, either a folder or a bare video file
+        let (title, year) = parse_movie_dir(&movie_name(dir_path));
+        let id = write_item(
+            batch, "movie", &title, year, None, dir_path, mode, status, summary,
+        )
+        .await?;
+        seen_paths.push(normalized(dir_path));
+
+        if batch.tmdb().is_some()
+            && media::needs_poster(batch.conn().await?, id)
+                .await
+                .unwrap_or(false)
+        {
+            batch.movie_poster(id, &title, year);
+        }
+        batch.item_done().await?;
+    }
+
+    Ok(seen_paths)
 }
 
 pub async fn scan_directory(
     pool: &SqlitePool,
     media_dir: &Path,
     tmdb: Option<&TmdbClient>,
+    mode: ScanMode,
+    status: &ScanStatus,
+    summary: &mut ScanSummary,
 ) -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync>> {
     let mut seen_paths = Vec::new();
     // Track TV series titles we've already fetched posters for (share poster across seasons)
     let mut tv_poster_fetched: HashSet<String> = HashSet::new();
+    let mut batch = WriteBatch::new(pool, tmdb, status, media_dir);
 
     let entries = std::fs::read_dir(media_dir)?;
     for entry in entries.flatten() {
-        if !entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
+        let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+        if !is_dir && !is_video_file(&entry.path()) {
             continue;
         }
+        let paths = scan_entry(
+            &mut batch,
+            &entry.path(),
+            mode,
+            status,
+            &mut tv_poster_fetched,
+            summary,
+        )
+        .await?;
+        status.add_items(paths.len());
+        seen_paths.extend(paths);
+    }
+    batch.commit().await?;
 
-        let dir_name = entry.file_name().to_string_lossy().to_string();
-        let dir_path = entry.path();
-
-        // Check if this is a TV show (has Season subdirs)
-        let seasons = find_seasons(&dir_path);
-        if !seasons.is_empty() {
-            // Fetch poster once per series title
-            let series_poster = if let Some(client) = tmdb {
-                if !tv_poster_fetched.contains(&dir_name) {
-                    tv_poster_fetched.insert(dir_name.clone());
-                    match client.search_tv_poster(&dir_name).await {
-                        Some(p) => {
-                            tracing::info!("Fetched TMDB poster for TV: {dir_name}");
-                            Some(p)
-                        }
-                        None => {
-                            tracing::info!("No TMDB poster found for TV: {dir_name}");
-                            None
-                        }
-                    }
-                } else {
-                    None // Already fetched for this series in this scan
-                }
-            } else {
-                None
-            };
+    Ok(seen_paths)
+}
 
-            for (season_num, season_path) in &seasons {
-                let path_str = season_path.to_string_lossy().to_string();
-                let size = dir_size(season_path);
-                let id = media::upsert(
-                    pool,
-                    "tv_season",
-                    &dir_name,
-                    None,
-                    Some(*season_num),
-                    &path_str,
-                    size,
-                )
-                .await?;
-                seen_paths.push(path_str);
-
-                if let Some(ref poster) = series_poster {
-                    if media::needs_poster(pool, id).await.unwrap_or(false) {
-                        let _ = media::set_poster(pool, id, poster).await;
-                    }
-                }
-            }
-        } else {
-            // Treat as movie
-            let (title, year) = parse_movie_dir(&dir_name);
-            let path_str = dir_path.to_string_lossy().to_string();
-            let size = dir_size(&dir_path);
-            let id = media::upsert(pool, "movie", &title, year, None, &path_str, size).await?;
-            seen_paths.push(path_str);
-
-            if let Some(client) = tmdb {
-                if media::needs_poster(pool, id).await.unwrap_or(false) {
-                    match client.search_movie_poster(&title, year).await {
-                        Some(poster) => {
-                            tracing::info!("Fetched TMDB poster for movie: {title}");
-                            let _ = media::set_poster(pool, id, &poster).await;
-                        }
-                        None => {
-                            tracing::info!("No TMDB poster found for movie: {title}");
-                        }
-                    }
-                }
-            }
-        }
-    }
+/// Re-scan a single top-level entry of a media dir (a movie or a show folder, or a movie file).
+///
+/// Active media at or below `entry_path` that is no longer on disk is marked gone.
+/// Directories whose fingerprint did not change keep their stored size.
+#[tracing::instrument(skip_all, fields(path = %entry_path.display()))]
+pub async fn rescan_entry(
+    pool: &SqlitePool,
+    entry_path: &Path,
+    tmdb: Option<&TmdbClient>,
+    status: &ScanStatus,
+) -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync>> {
+    let seen_paths = if entry_path.exists() {
+        let media_dir = entry_path.parent().unwrap_or(entry_path);
+        let mut batch = WriteBatch::new(pool, tmdb, status, media_dir);
+        let seen_paths = scan_entry(
+            &mut batch,
+            entry_path,
+            ScanMode::Incremental,
+            status,
+            &mut HashSet::new(),
+            &mut ScanSummary::default(),
+        )
+        .await?;
+        batch.commit().await?;
+        seen_paths
+    } else {
+        Vec::new()
+    };
 
+    let prefix = normalized(entry_path);
+    let mut keep = seen_paths.clone();
+    keep.extend(manual_paths_on_disk(pool, Some(&prefix)).await?);
+    if let Some(media_dir) = entry_path.parent() {
+        keep.extend(hold_back_vanished_library(pool, media_dir, &keep, status).await?);
+    }
+    media::mark_gone_under_except(pool, &prefix, &keep).await?;
     Ok(seen_paths)
 }
 
-pub async fn full_scan(
+/// [`hold_back_mass_removal`] for a single entry: the watcher sees a dropped
+/// mount as one removal after another, so judge each one against how much of
+/// the whole library is still on disk.
+async fn hold_back_vanished_library(
     pool: &SqlitePool,
-    media_dirs: &[PathBuf],
+    media_dir: &Path,
+    keep: &[String],
+    status: &ScanStatus,
+) -> Result<Vec<String>, sqlx::Error> {
+    let kept: HashSet<&String> = keep.iter().collect();
+    let active = media::active_paths_under(pool, &normalized(media_dir)).await?;
+    let on_disk: Vec<String> = active
+        .into_iter()
+        .filter(|p| kept.contains(p) || Path::new(p).exists())
+        .collect();
+    hold_back_mass_removal(pool, media_dir, &on_disk, status).await
+}
+
+/// Scan a single media dir; active media under it that is no longer on disk is marked gone.
+///
+/// With [`GonePolicy::Guarded`] nothing is marked gone if most of the library vanished.
+#[tracing::instrument(skip_all, fields(dir = %media_dir.display(), ?mode))]
+pub async fn scan_library(
+    pool: &SqlitePool,
+    media_dir: &Path,
+    tmdb: Option<&TmdbClient>,
+    mode: ScanMode,
+    status: &ScanStatus,
+    policy: GonePolicy,
+) -> Result<Option<ScanSummary>, Box<dyn std::error::Error + Send + Sync>> {
+    let Some(_guard) = status.begin() else {
+        tracing::info!("Scan already in progress, skipping");
+        return Ok(None);
+    };
+    let started = Instant::now();
+    let mut summary = ScanSummary::default();
+
+    tracing::info!("Scanning media directory: {}", media_dir.display());
+    status.set_current_dir(media_dir);
+    let mut seen_paths = scan_directory(pool, media_dir, tmdb, mode, status, &mut summary).await?;
+    if policy == GonePolicy::Guarded {
+        seen_paths.extend(hold_back_mass_removal(pool, media_dir, &seen_paths, status).await?);
+    } else {
+        status.clear_alert(media_dir);
+    }
+    let prefix = normalized(media_dir);
+    seen_paths.extend(manual_paths_on_disk(pool, Some(&prefix)).await?);
+    let (gone, gone_size) = media::mark_gone_under_except(pool, &prefix, &seen_paths).await?;
+    summary.marked_gone = gone as i64;
+    summary.size_delta -= gone_size;
+
+    let scope = media_dir.display().to_string();
+    record_summary(pool, &scope, started, &summary).await;
+    Ok(Some(summary))
+}
+
+/// Paths of manual items that still exist, which scans must not mark gone
+/// even though they do not recognise them.
+async fn manual_paths_on_disk(
+    pool: &SqlitePool,
+    prefix: Option<&str>,
+) -> Result<Vec<String>, sqlx::Error> {
+    let mut paths = media::manual_paths(pool, prefix).await?;
+    paths.retain(|path| Path::new(path).exists());
+    Ok(paths)
+}
+
+/// Whether a scan may mark most of a library gone in one go.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GonePolicy {
+    /// Hold back gone-marking and raise an alert if most of the library vanished.
+    Guarded,
+    /// The admin confirmed the removal; mark everything missing as gone.
+    Force,
+}
+
+/// Share of a library that may disappear in one scan before gone-marking is held back.
+const MAX_GONE_PERCENT: usize = 50;
+/// Libraries smaller than this are not guarded; losing most of them is unremarkable.
+const MIN_GUARDED_ITEMS: usize = 4;
+
+/// Check whether most of `media_dir` vanished since the last scan, which usually
+/// means the mount dropped rather than that the files were deleted.
+///
+/// Returns the active paths that must be treated as seen so they are not marked
+/// gone (and lose their marks); empty if the scan looks sane.
+async fn hold_back_mass_removal(
+    pool: &SqlitePool,
+    media_dir: &Path,
+    seen_paths: &[String],
+    status: &ScanStatus,
+) -> Result<Vec<String>, sqlx::Error> {
+    let active = media::active_paths_under(pool, &normalized(media_dir)).await?;
+    let seen: HashSet<&String> = seen_paths.iter().collect();
+    let missing = active.iter().filter(|p| !seen.contains(p)).count();
+    if active.len() < MIN_GUARDED_ITEMS || missing * 100 <= active.len() * MAX_GONE_PERCENT {
+        status.clear_alert(media_dir);
+        return Ok(Vec::new());
+    }
+
+    tracing::warn!(
+        "{missing} of {} items in {} disappeared at once, not marking them gone",
+        active.len(),
+        media_dir.display()
+    );
+    status.raise_alert(GoneAlert {
+        media_dir: media_dir.display().to_string(),
+        missing: missing as u64,
+        active: active.len() as u64,
+    });
+    Ok(active)
+}
+
+async fn record_summary(pool: &SqlitePool, scope: &str, started: Instant, summary: &ScanSummary) {
+    tracing::info!(
+        "Scan of {scope} complete: {} items, {} added, {} re-activated, {} marked gone, {:+} bytes",
+        summary.items_seen,
+        summary.added,
+        summary.reactivated,
+        summary.marked_gone,
+        summary.size_delta
+    );
+    let duration = started.elapsed().as_secs() as i64;
+    if let Err(e) = scan_summary::insert(pool, scope, duration, summary).await {
+        tracing::error!("Failed to record scan summary: {e}");
+    }
+}
+
+/// Re-read a single item from disk: size, title/year/season and poster.
+pub async fn refresh_item(
+    pool: &SqlitePool,
+    media_id: i64,
     tmdb: Option<&TmdbClient>,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let mut all_seen = Vec::new();
+    let item = media::get_by_id(pool, media_id)
+        .await?
+        .ok_or("Media not found")?;
+    let path = Path::new(&item.path);
+    if !path.exists() {
+        media::set_gone(pool, media_id, media_history::SCANNER).await?;
+        tracing::info!(
+            "Refreshed item missing from disk, marked gone: {}",
+            item.path
+        );
+        return Ok(());
+    }
 
-    for dir in media_dirs {
-        tracing::info!("Scanning media directory: {}", dir.display());
-        match scan_directory(pool, dir, tmdb).await {
-            Ok(paths) => all_seen.extend(paths),
-            Err(e) => tracing::error!("Error scanning {}: {e}", dir.display()),
+    let dir_name = |p: &Path| {
+        p.file_name()
+            .map(|n| nfc(&n.to_string_lossy()))
+            .unwrap_or_default()
+    };
+    let (title, year, season) = if item.manual {
+        (item.title.clone(), item.year, item.season)
+    } else if item.media_type == "tv_season" {
+        let series = path.parent().map(dir_name).unwrap_or_default();
+        let season = seasons::parse_season_number(&dir_name(path)).or(item.season);
+        (series, None, season)
+    } else {
+        let (title, year) = parse_movie_dir(&movie_name(path));
+        (title, year, None)
+    };
+    media::update_metadata(pool, media_id, &title, year, season).await?;
+    media::set_size(pool, media_id, dir_size(path)).await?;
+    media::set_dir_mtime(pool, media_id, tree_fingerprint(path)).await?;
+
+    if let Some(client) = tmdb {
+        let poster = if item.media_type == "tv_season" {
+            client.search_tv_poster(&title).await
+        } else {
+            client.search_movie_poster(&title, year).await
+        };
+        if let Some(poster) = poster {
+            let hash = client.poster_hash(&poster).await;
+            media::set_poster(pool, media_id, &poster, hash.as_deref()).await?;
         }
     }
 
-    media::mark_gone_except(pool, &all_seen).await?;
-    tracing::info!("Scan complete, found {} media entries", all_seen.len());
+    tracing::info!("Refreshed media item: {}", item.path);
     Ok(())
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn parse_movie_dir_with_year() {
-        let (title, year) = parse_movie_dir("Inception (2010)");
-        assert_eq!(title, "Inception");
-        assert_eq!(year, Some(2010));
+/// Recompute the size of the media item containing `changed_path`.
+///
+/// Returns the id of the refreshed item, or `None` if no active item contains the path.
+pub async fn refresh_size(
+    pool: &SqlitePool,
+    changed_path: &Path,
+) -> Result<Option<i64>, Box<dyn std::error::Error + Send + Sync>> {
+    let path_str = normalized(changed_path);
+    let Some(item) = media::find_containing(pool, &path_str).await? else {
+        return Ok(None);
+    };
+    let size = dir_size(Path::new(&item.path));
+    if size != item.size_bytes {
+        media::set_size(pool, item.id, size).await?;
+        tracing::debug!("Updated size of {}: {size} bytes", item.path);
     }
+    Ok(Some(item.id))
+}
 
-    #[test]
-    fn parse_movie_dir_without_year() {
-        let (title, year) = parse_movie_dir("SomeMovie");
-        assert_eq!(title, "SomeMovie");
-        assert_eq!(year, None);
-    }
+#[tracing::instrument(skip_all, fields(?mode))]
+pub async fn full_scan(
+    pool: &SqlitePool,
+    media_dirs: &[PathBuf],
+    tmdb: Option<&TmdbClient>,
+    mode: ScanMode,
+    status: &ScanStatus,
+) -> Result<Option<ScanSummary>, Box<dyn std::error::Error + Send + Sync>> {
+    let Some(_guard) = status.begin() else {
+        tracing::info!("Scan already in progress, skipping");
+        return Ok(None);
+    };
+    let started = Instant::now();
+    let mut summary = ScanSummary::default();
+    let mut all_seen = Vec::new();
 
-    #[test]
-    fn parse_movie_dir_with_non_year_parens() {
-        let (title, year) = parse_movie_dir("Movie (Extended Cut)");
-        assert_eq!(title, "Movie (Extended Cut)");
-        assert_eq!(year, None);
+    for dir in media_dirs {
+        tracing::info!("Scanning media directory: {}", dir.display());
+        status.set_current_dir(dir);
+        let paths = match scan_directory(pool, dir, tmdb, mode, status, &mut summary).await {
+            Ok(paths) => paths,
+            Err(e) => {
+                tracing::error!("Error scanning {}: {e}", dir.display());
+                Vec::new()
+            }
+        };
+        let held_back = hold_back_mass_removal(pool, dir, &paths, status).await?;
+        all_seen.extend(paths);
+        all_seen.extend(held_back);
     }
+
+    all_seen.extend(manual_paths_on_disk(pool, None).await?);
+    let (gone, gone_size) = media::mark_gone_except(pool, &all_seen).await?;
+    summary.marked_gone = gone as i64;
+    summary.size_delta -= gone_size;
+
+    record_summary(pool, "all libraries", started, &summary).await;
+    Ok(Some(summary))
 }
@@ -0,0 +1,189 @@
+//! Parsing of movie folder names into a title and a release year.
+//!
+//! Handles the common naming schemes: "Title (Year)", scene-style
+//! "Title.Year.1080p.BluRay.x264-GROUP" and trailing tags in brackets such as
+//! "Title (Year) [1080p x265]" or "Title (Year) {tmdb-123}".
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Release tags that never belong to a title; everything from the first one on is dropped.
+const QUALITY_TAGS: &[&str] = &[
+    "4k", "uhd", "hdr", "hdr10", "dv", "bluray", "blu-ray", "bdrip", "brrip", "remux", "web",
+    "web-dl", "webdl", "webrip", "hdtv", "dvdrip", "dvd", "x264", "x265", "h264", "h265", "hevc",
+    "avc", "xvid", "aac", "ac3", "dts", "atmos", "10bit", "proper", "repack",
+];
+
+const MIN_YEAR: i64 = 1880;
+
+/// Parse a movie directory name like "Inception (2010)" → ("Inception", Some(2010))
+pub fn parse_movie_dir(name: &str) -> (String, Option<i64>) {
+    let stripped = strip_brackets(name);
+    // Scene releases use dots or underscores instead of spaces.
+    let cleaned = if stripped.trim().contains(' ') {
+        stripped
+    } else {
+        stripped.replace(['.', '_'], " ")
+    };
+    let tokens: Vec<&str> = cleaned.split_whitespace().collect();
+
+    // The year closest to the end wins, so "2001 A Space Odyssey (1968)" and
+    // "1917 (2019)" keep the number in their title. The first token is always title.
+    let max_year = current_year() + 1;
+    let year_at = (1..tokens.len())
+        .rev()
+        .find_map(|i| parse_year(tokens[i], max_year).map(|year| (i, year)));
+
+    let (title_end, year) = match year_at {
+        Some((i, year)) => (i, Some(year)),
+        None => {
+            let tag = (1..tokens.len()).find(|&i| is_quality_tag(tokens[i]));
+            (tag.unwrap_or(tokens.len()), None)
+        }
+    };
+
+    let title = tokens[..title_end]
+        .join(" ")
+        .trim_end_matches([' ', '-', '.', '_'])
+        .to_string();
+    if title.is_empty() {
+        return (name.to_string(), year);
+    }
+    (title, year)
+}
+
+/// Remove `[...]` and `{...}` groups; a bare year inside brackets is kept as "(year)".
+fn strip_brackets(name: &str) -> String {
+    let mut out = String::with_capacity(name.len());
+    let mut rest = name;
+    while let Some(start) = rest.find(['[', '{']) {
+        let close = if rest[start..].starts_with('[') {
+            ']'
+        } else {
+            '}'
+        };
+        let Some(len) = rest[start..].find(close) else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+        let inner = &rest[start + 1..start + len];
+        if inner.len() == 4 && inner.bytes().all(|b| b.is_ascii_digit()) {
+            out.push_str(&format!(" ({inner}) "));
+        } else {
+            out.push(' ');
+        }
+        rest = &rest[start + len + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// A year token: "(2010)" or, for scene names, a bare "2010".
+fn parse_year(token: &str, max_year: i64) -> Option<i64> {
+    let digits = token
+        .strip_prefix('(')
+        .and_then(|t| t.strip_suffix(')'))
+        .unwrap_or(token);
+    if digits.len() != 4 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let year: i64 = digits.parse().ok()?;
+    (MIN_YEAR..=max_year).contains(&year).then_some(year)
+}
+
+fn is_quality_tag(token: &str) -> bool {
+    let lower = token.trim_matches(['(', ')']).to_lowercase();
+    // Drop a release group suffix like "x264-GROUP".
+    let lower = lower.split('-').next().unwrap_or(&lower);
+    let is_resolution = lower
+        .strip_suffix(['p', 'i'])
+        .is_some_and(|n| matches!(n, "480" | "576" | "720" | "1080" | "2160"));
+    is_resolution || QUALITY_TAGS.contains(&lower)
+}
+
+fn current_year() -> i64 {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    // Mean Gregorian year length; off by a day at most around New Year.
+    1970 + (secs / 31_556_952) as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_movie_dir_with_year() {
+        let (title, year) = parse_movie_dir("Inception (2010)");
+        assert_eq!(title, "Inception");
+        assert_eq!(year, Some(2010));
+    }
+
+    #[test]
+    fn parse_movie_dir_without_year() {
+        let (title, year) = parse_movie_dir("SomeMovie");
+        assert_eq!(title, "SomeMovie");
+        assert_eq!(year, None);
+    }
+
+    #[test]
+    fn parse_movie_dir_with_non_year_parens() {
+        let (title, year) = parse_movie_dir("Movie (Extended Cut)");
+        assert_eq!(title, "Movie (Extended Cut)");
+        assert_eq!(year, None);
+    }
+
+    #[test]
+    fn parse_movie_dir_corpus() {
+        let corpus: &[(&str, &str, Option<i64>)] = &[
+            (
+                "Blade Runner (1982) [1080p x265]",
+                "Blade Runner",
+                Some(1982),
+            ),
+            ("1917 (2019)", "1917", Some(2019)),
+            (
+                "2001 A Space Odyssey (1968)",
+                "2001 A Space Odyssey",
+                Some(1968),
+            ),
+            ("Blade Runner 2049 (2017)", "Blade Runner 2049", Some(2017)),
+            ("Blade Runner 2049", "Blade Runner 2049", None),
+            ("2012 (2009)", "2012", Some(2009)),
+            ("1917", "1917", None),
+            (
+                "Blade.Runner.2049.2017.2160p.UHD.BluRay.x265-TERMiNAL",
+                "Blade Runner 2049",
+                Some(2017),
+            ),
+            (
+                "The.Matrix.1999.1080p.BluRay.x264-GROUP",
+                "The Matrix",
+                Some(1999),
+            ),
+            ("Star_Wars_(1977)", "Star Wars", Some(1977)),
+            ("Heat (1995) {tmdb-949}", "Heat", Some(1995)),
+            ("Alien [1979]", "Alien", Some(1979)),
+            ("Arrival (2016) - 1080p", "Arrival", Some(2016)),
+            ("The Thing 1982", "The Thing", Some(1982)),
+            ("Dr. Strangelove (1964)", "Dr. Strangelove", Some(1964)),
+            ("Se7en (1995) [Remastered]", "Se7en", Some(1995)),
+            ("Ocean's Eleven (2001)", "Ocean's Eleven", Some(2001)),
+            ("Amelie 1080p BluRay", "Amelie", None),
+            (
+                "Movie (Extended Cut) (2010)",
+                "Movie (Extended Cut)",
+                Some(2010),
+            ),
+            ("[1080p]", "[1080p]", None),
+        ];
+        for (name, title, year) in corpus {
+            assert_eq!(
+                parse_movie_dir(name),
+                (title.to_string(), *year),
+                "parsing {name:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,182 @@
+//! Recognition of season directories inside a show folder.
+
+use regex::Regex;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::config::SpecialsPolicy;
+
+/// "Season" in the languages libraries are commonly organised in.
+const SEASON_WORDS: &[&str] = &[
+    "season",    // en
+    "staffel",   // de
+    "saison",    // fr
+    "temporada", // es, pt
+    "stagione",  // it
+    "seizoen",   // nl
+    "säsong",    // sv
+    "sæson",     // da
+    "sesong",    // no
+    "sezon",     // pl, tr
+    "kausi",     // fi
+];
+
+/// Folder names for specials, which Plex files as season 0.
+const SPECIALS_WORDS: &[&str] = &["specials", "special"];
+
+static SEASON_PATTERN: OnceLock<Regex> = OnceLock::new();
+
+/// Install the `season_pattern` from the config. Its first capture group must
+/// hold the season number; names it does not match fall back to the built-in rules.
+pub fn set_season_pattern(pattern: Regex) {
+    if SEASON_PATTERN.set(pattern).is_err() {
+        tracing::warn!("season_pattern already set, ignoring");
+    }
+}
+
+static SPECIALS: OnceLock<SpecialsPolicy> = OnceLock::new();
+
+/// Install the `specials` policy from the config.
+pub fn set_specials_policy(policy: SpecialsPolicy) {
+    if SPECIALS.set(policy).is_err() {
+        tracing::warn!("specials policy already set, ignoring");
+    }
+}
+
+/// Check if a directory contains Season subdirs
+pub fn find_seasons(path: &Path) -> Vec<(i64, PathBuf)> {
+    let policy = SPECIALS.get().copied().unwrap_or_default();
+    find_seasons_with(path, policy)
+}
+
+fn find_seasons_with(path: &Path, specials: SpecialsPolicy) -> Vec<(i64, PathBuf)> {
+    let mut seasons = Vec::new();
+    let entries = match std::fs::read_dir(path) {
+        Ok(e) => e,
+        Err(_) => return seasons,
+    };
+
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
+            if let Some(num) = parse_season_number(&name) {
+                seasons.push((num, entry.path()));
+            }
+        }
+    }
+    // A movie folder with just a "Specials" subdir is not a show.
+    if seasons.iter().all(|(n, _)| *n == 0) {
+        return Vec::new();
+    }
+    if specials == SpecialsPolicy::Ignore {
+        seasons.retain(|(n, _)| *n != 0);
+    }
+    seasons.sort_by_key(|(n, _)| *n);
+    seasons
+}
+
+pub(crate) fn parse_season_number(name: &str) -> Option<i64> {
+    parse_with(SEASON_PATTERN.get(), name)
+}
+
+fn parse_with(pattern: Option<&Regex>, name: &str) -> Option<i64> {
+    if let Some(num) = pattern
+        .and_then(|re| re.captures(name))
+        .and_then(|caps| caps.get(1))
+        .and_then(|m| m.as_str().parse().ok())
+    {
+        return Some(num);
+    }
+
+    let lower = name.trim().to_lowercase();
+    if SPECIALS_WORDS.contains(&lower.as_str()) {
+        return Some(0);
+    }
+    for word in SEASON_WORDS {
+        if let Some(rest) = lower.strip_prefix(word) {
+            return leading_number(rest.trim_start_matches([' ', '_', '.', '-']), 4);
+        }
+    }
+    // "S01", "S2021", "S01 - Extended"
+    leading_number(lower.strip_prefix('s')?, 4)
+}
+
+/// Digits at the start of `s`, if followed by nothing or a separator
+/// ("01 - Extended" but not "01e02" or "ubs").
+fn leading_number(s: &str, max_digits: usize) -> Option<i64> {
+    let digits = s.bytes().take_while(u8::is_ascii_digit).count();
+    if digits == 0 || digits > max_digits {
+        return None;
+    }
+    if s[digits..]
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_alphanumeric())
+    {
+        return None;
+    }
+    s[..digits].parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_common_season_names() {
+        let cases: &[(&str, Option<i64>)] = &[
+            ("Season 1", Some(1)),
+            ("Season 01", Some(1)),
+            ("season_2", Some(2)),
+            ("Season.03", Some(3)),
+            ("Season 01 - Extended", Some(1)),
+            ("Staffel 1", Some(1)),
+            ("Saison 04", Some(4)),
+            ("Temporada 2", Some(2)),
+            ("Specials", Some(0)),
+            ("Season 0", Some(0)),
+            ("S01", Some(1)),
+            ("S2021", Some(2021)),
+            ("s3 [1080p]", Some(3)),
+            ("Subs", None),
+            ("S01E01", None),
+            ("Season", None),
+            ("Featurettes", None),
+        ];
+        for (name, expected) in cases {
+            assert_eq!(parse_with(None, name), *expected, "parsing {name:?}");
+        }
+    }
+
+    #[test]
+    fn custom_pattern_takes_precedence() {
+        let re = Regex::new(r"^Volume (\d+)$").unwrap();
+        assert_eq!(parse_with(Some(&re), "Volume 7"), Some(7));
+        // Names the pattern does not match still use the built-in rules.
+        assert_eq!(parse_with(Some(&re), "Season 2"), Some(2));
+        assert_eq!(parse_with(Some(&re), "Bonus"), None);
+    }
+
+    #[test]
+    fn folder_with_only_specials_is_not_a_show() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("Specials")).unwrap();
+        assert!(find_seasons(dir.path()).is_empty());
+
+        std::fs::create_dir(dir.path().join("Season 1")).unwrap();
+        let seasons: Vec<i64> = find_seasons(dir.path()).iter().map(|(n, _)| *n).collect();
+        assert_eq!(seasons, vec![0, 1]);
+    }
+
+    #[test]
+    fn ignored_specials_are_left_out_of_a_show() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("Specials")).unwrap();
+        std::fs::create_dir(dir.path().join("Season 1")).unwrap();
+        let seasons: Vec<i64> = find_seasons_with(dir.path(), SpecialsPolicy::Ignore)
+            .iter()
+            .map(|(n, _)| *n)
+            .collect();
+        assert_eq!(seasons, vec![1]);
+    }
+}
@@ -0,0 +1,51 @@
+//! Measuring item sizes in the background, so a scan lists new items right
+//! away instead of first walking every file below them.
+
+use super::{dir_size, tree_fingerprint, ScanStatus};
+use crate::models::media;
+use sqlx::SqlitePool;
+use std::path::PathBuf;
+use tokio::sync::mpsc;
+
+/// Measure the sizes that scans reporting to `status` defer, until the process exits.
+///
+/// Items left pending by a previous run are queued first.
+pub async fn start_size_worker(pool: SqlitePool, status: ScanStatus) -> Result<(), sqlx::Error> {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    status.attach_size_queue(tx);
+    for id in media::size_pending_ids(&pool).await? {
+        status.defer_size(id);
+    }
+
+    tokio::spawn(async move {
+        while let Some(id) = rx.recv().await {
+            status.take_size(id);
+            if let Err(e) = measure(&pool, id).await {
+                tracing::error!("Failed to measure size of media {id}: {e}");
+            }
+        }
+    });
+    Ok(())
+}
+
+async fn measure(
+    pool: &SqlitePool,
+    media_id: i64,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let Some(item) = media::get_by_id(pool, media_id).await? else {
+        return Ok(());
+    };
+    if item.status == "gone" {
+        return Ok(());
+    }
+
+    let path = PathBuf::from(&item.path);
+    let (mtime, size) =
+        tokio::task::spawn_blocking(move || (tree_fingerprint(&path), dir_size(&path))).await?;
+    // The fingerprint goes first: clearing the pending flag is what lets a
+    // later scan trust it.
+    media::set_dir_mtime(pool, media_id, mtime).await?;
+    media::set_size(pool, media_id, size).await?;
+    tracing::debug!("Measured size of {}: {size} bytes", item.path);
+    Ok(())
+}
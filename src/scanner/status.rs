@@ -0,0 +1,177 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tokio::sync::mpsc;
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ScanSnapshot {
+    pub running: bool,
+    pub current_dir: Option<String>,
+    pub items_processed: u64,
+    pub elapsed_secs: u64,
+    /// Stats of the most recently finished scan, if any.
+    pub last_items: Option<u64>,
+    pub last_duration_secs: Option<u64>,
+    /// Libraries whose gone-marking was held back, see [`GoneAlert`].
+    pub alerts: Vec<GoneAlert>,
+    /// Items queued for the background size worker.
+    pub sizes_pending: u64,
+}
+
+/// A scan found most of a library missing and left it untouched instead of
+/// marking it gone, e.g. because the mount dropped.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct GoneAlert {
+    pub media_dir: String,
+    pub missing: u64,
+    pub active: u64,
+}
+
+#[derive(Default)]
+struct Inner {
+    started: Option<Instant>,
+    current_dir: Option<String>,
+    items_processed: u64,
+    last_items: Option<u64>,
+    last_duration_secs: Option<u64>,
+    alerts: BTreeMap<String, GoneAlert>,
+    size_queue: Option<mpsc::UnboundedSender<i64>>,
+    sizes_pending: HashSet<i64>,
+}
+
+/// Progress of the library scan, shared between the scanner and the admin pages.
+///
+/// Only one full scan runs at a time; [`ScanStatus::begin`] refuses to start a
+/// second one while another is in progress.
+#[derive(Clone, Default)]
+pub struct ScanStatus(Arc<Mutex<Inner>>);
+
+/// Marks the scan as finished when dropped, even if the scan bailed out early.
+pub struct ScanGuard(ScanStatus);
+
+impl ScanStatus {
+    pub fn begin(&self) -> Option<ScanGuard> {
+        let mut inner = self.lock();
+        if inner.started.is_some() {
+            return None;
+        }
+        inner.started = Some(Instant::now());
+        inner.current_dir = None;
+        inner.items_processed = 0;
+        Some(ScanGuard(self.clone()))
+    }
+
+    pub fn set_current_dir(&self, dir: &Path) {
+        self.lock().current_dir = Some(dir.display().to_string());
+    }
+
+    pub fn add_items(&self, count: usize) {
+        self.lock().items_processed += count as u64;
+    }
+
+    /// Record that gone-marking was held back for `alert.media_dir`.
+    pub fn raise_alert(&self, alert: GoneAlert) {
+        self.lock().alerts.insert(alert.media_dir.clone(), alert);
+    }
+
+    /// Drop the alert for `media_dir` once a scan of it went through normally.
+    pub fn clear_alert(&self, media_dir: &Path) {
+        self.lock().alerts.remove(&media_dir.display().to_string());
+    }
+
+    /// Leave measuring sizes to the worker listening on `queue` from now on.
+    pub(super) fn attach_size_queue(&self, queue: mpsc::UnboundedSender<i64>) {
+        self.lock().size_queue = Some(queue);
+    }
+
+    /// Whether scans may list items before their size is known.
+    pub fn defers_sizes(&self) -> bool {
+        self.lock().size_queue.is_some()
+    }
+
+    /// Queue `media_id` for the size worker, unless it is queued already.
+    pub fn defer_size(&self, media_id: i64) {
+        let mut inner = self.lock();
+        let Some(queue) = inner.size_queue.clone() else {
+            return;
+        };
+        if inner.sizes_pending.insert(media_id) && queue.send(media_id).is_err() {
+            inner.sizes_pending.remove(&media_id);
+        }
+    }
+
+    /// The size worker started measuring `media_id`; changes from now on queue it again.
+    pub(super) fn take_size(&self, media_id: i64) {
+        self.lock().sizes_pending.remove(&media_id);
+    }
+
+    pub fn snapshot(&self) -> ScanSnapshot {
+        let inner = self.lock();
+        ScanSnapshot {
+            running: inner.started.is_some(),
+            current_dir: inner.current_dir.clone(),
+            items_processed: inner.items_processed,
+            elapsed_secs: inner.started.map(|s| s.elapsed().as_secs()).unwrap_or(0),
+            last_items: inner.last_items,
+            last_duration_secs: inner.last_duration_secs,
+            alerts: inner.alerts.values().cloned().collect(),
+            sizes_pending: inner.sizes_pending.len() as u64,
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Drop for ScanGuard {
+    fn drop(&mut self) {
+        let mut inner = self.0.lock();
+        if let Some(started) = inner.started.take() {
+            inner.last_duration_secs = Some(started.elapsed().as_secs());
+            inner.last_items = Some(inner.items_processed);
+        }
+        inner.current_dir = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_one_scan_at_a_time() {
+        let status = ScanStatus::default();
+        let guard = status.begin().expect("first scan should start");
+        assert!(status.begin().is_none());
+
+        status.add_items(3);
+        assert!(status.snapshot().running);
+        drop(guard);
+
+        let snapshot = status.snapshot();
+        assert!(!snapshot.running);
+        assert_eq!(snapshot.last_items, Some(3));
+        assert!(status.begin().is_some());
+    }
+
+    #[test]
+    fn alerts_are_kept_per_library_until_cleared() {
+        let status = ScanStatus::default();
+        for missing in [3, 4] {
+            status.raise_alert(GoneAlert {
+                media_dir: "/media/movies".to_string(),
+                missing,
+                active: 5,
+            });
+        }
+        let alerts = status.snapshot().alerts;
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].missing, 4);
+
+        status.clear_alert(Path::new("/media/movies"));
+        assert!(status.snapshot().alerts.is_empty());
+    }
+}
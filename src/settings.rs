@@ -0,0 +1,145 @@
+//! Settings admins can change at runtime from `/admin/settings`.
+//!
+//! Saved values live in the `settings` table and are laid over the config
+//! file, so they survive restarts and reloads. Clearing a value falls back to
+//! the file again.
+
+use sqlx::SqlitePool;
+use std::collections::BTreeMap;
+
+use crate::config::{AppConfig, SharedConfig};
+use crate::models::setting;
+
+/// One setting on the admin settings page.
+pub struct Setting {
+    /// Name of the config file key it overrides.
+    pub key: &'static str,
+    pub help: &'static str,
+}
+
+pub const SETTINGS: &[Setting] = &[
+    Setting {
+        key: "grace_period_days",
+        help: "Days trashed items wait before cleanup deletes them",
+    },
+    Setting {
+        key: "cleanup_interval_hours",
+        help: "Hours between cleanup runs; 0 disables them",
+    },
+    Setting {
+        key: "deleted_retention_days",
+        help: "Days deleted media stays in the history; 0 keeps it forever",
+    },
+    Setting {
+        key: "keep_hardlinked_trash",
+        help: "Keep expired hardlinked trash for an admin to delete (true or false)",
+    },
+    Setting {
+        key: "deletion_approval_gb",
+        help: "Size above which deletions need two admins; empty disables it",
+    },
+    Setting {
+        key: "trash_approval",
+        help: "Wait for an admin to confirm before trashing what everyone marked (true or false)",
+    },
+    Setting {
+        key: "require_admin_mark",
+        help: "Only trash what everyone marked once an admin marked it too (true or false)",
+    },
+];
+
+/// Saved like the settings above, but switched from its own form.
+pub const MAINTENANCE: &str = "maintenance";
+
+/// Set `key` to `value` in `config`, or say why the value does not fit.
+pub fn apply(config: &mut AppConfig, key: &str, value: &str) -> Result<(), String> {
+    let value = value.trim();
+    let number = || {
+        value
+            .parse::<u64>()
+            .map_err(|_| format!("{key} must be a whole number"))
+    };
+    let flag = || {
+        value
+            .parse::<bool>()
+            .map_err(|_| format!("{key} must be true or false"))
+    };
+    match key {
+        "grace_period_days" => config.grace_period_days = number()?,
+        "cleanup_interval_hours" => config.cleanup_interval_hours = number()?,
+        "deleted_retention_days" => config.deleted_retention_days = number()?,
+        "keep_hardlinked_trash" => config.keep_hardlinked_trash = flag()?,
+        "trash_approval" => config.trash_approval = flag()?,
+        "require_admin_mark" => config.require_admin_mark = flag()?,
+        "maintenance" => config.maintenance = flag()?,
+        "deletion_approval_gb" => {
+            config.deletion_approval_gb = if value.is_empty() {
+                None
+            } else {
+                Some(number()?)
+            };
+        }
+        _ => return Err(format!("unknown setting {key:?}")),
+    }
+    Ok(())
+}
+
+/// The value of `key` in `config`, as the settings form shows it.
+pub fn value_of(config: &AppConfig, key: &str) -> String {
+    match key {
+        "grace_period_days" => config.grace_period_days.to_string(),
+        "cleanup_interval_hours" => config.cleanup_interval_hours.to_string(),
+        "deleted_retention_days" => config.deleted_retention_days.to_string(),
+        "keep_hardlinked_trash" => config.keep_hardlinked_trash.to_string(),
+        "trash_approval" => config.trash_approval.to_string(),
+        "require_admin_mark" => config.require_admin_mark.to_string(),
+        "maintenance" => config.maintenance.to_string(),
+        "deletion_approval_gb" => config
+            .deletion_approval_gb
+            .map(|gb| gb.to_string())
+            .unwrap_or_default(),
+        _ => String::new(),
+    }
+}
+
+/// `file` with the saved `overrides` laid over it. Values that no longer
+/// parse, e.g. after a downgrade, are skipped with a warning.
+pub fn overlay(file: &AppConfig, overrides: &BTreeMap<String, String>) -> AppConfig {
+    let mut config = file.clone();
+    for (key, value) in overrides {
+        if let Err(e) = apply(&mut config, key, value) {
+            tracing::warn!("Ignoring saved setting: {e}");
+        }
+    }
+    config
+}
+
+/// Publish the settings saved in the database over the config file.
+pub async fn load(pool: &SqlitePool, config: &SharedConfig) -> Result<(), sqlx::Error> {
+    config.set_overrides(setting::all(pool).await?);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_setting_round_trips_through_the_form() {
+        let mut config: AppConfig = toml::from_str(
+            r#"
+            database_url = "sqlite::memory:"
+            listen_addr = "127.0.0.1:0"
+            media_dirs = []
+            "#,
+        )
+        .unwrap();
+        for setting in SETTINGS {
+            let value = value_of(&config, setting.key);
+            apply(&mut config, setting.key, &value).unwrap();
+            assert_eq!(value_of(&config, setting.key), value);
+        }
+        assert!(apply(&mut config, "grace_period_days", "soon").is_err());
+        assert!(apply(&mut config, "database_url", "x").is_err());
+    }
+}
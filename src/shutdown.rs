@@ -0,0 +1,109 @@
+//! Graceful shutdown: on SIGTERM or Ctrl-C stop taking new requests and let
+//! trash/persist moves that are already running finish before the process exits.
+
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::signal::unix::{self, SignalKind};
+use tokio::sync::watch;
+
+/// How long shutdown waits for in-flight moves before giving up on them.
+pub const MOVE_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Copy, Default)]
+struct Moves {
+    active: usize,
+    closed: bool,
+}
+
+/// Counts operations that move or delete media on disk, shared between the
+/// web handlers, the periodic cleanup and the shutdown sequence.
+#[derive(Clone)]
+pub struct MoveTracker(Arc<watch::Sender<Moves>>);
+
+/// Marks the operation as finished when dropped.
+pub struct MoveGuard(MoveTracker);
+
+impl Default for MoveTracker {
+    fn default() -> Self {
+        Self(Arc::new(watch::channel(Moves::default()).0))
+    }
+}
+
+impl MoveTracker {
+    /// Register a move, or `None` once shutdown has started.
+    pub fn begin(&self) -> Option<MoveGuard> {
+        let mut started = false;
+        self.0.send_if_modified(|moves| {
+            if moves.closed {
+                return false;
+            }
+            moves.active += 1;
+            started = true;
+            true
+        });
+        started.then(|| MoveGuard(self.clone()))
+    }
+
+    pub fn active(&self) -> usize {
+        self.0.borrow().active
+    }
+
+    /// Refuse new moves from now on.
+    pub fn close(&self) {
+        self.0.send_modify(|moves| moves.closed = true);
+    }
+
+    /// Resolves once [`MoveTracker::close`] has been called.
+    pub async fn closed(&self) {
+        let _ = self.0.subscribe().wait_for(|moves| moves.closed).await;
+    }
+
+    /// Wait until no move is running. Returns false if `timeout` ran out first.
+    pub async fn wait_idle(&self, timeout: Duration) -> bool {
+        let mut rx = self.0.subscribe();
+        let idle = tokio::time::timeout(timeout, rx.wait_for(|moves| moves.active == 0)).await;
+        idle.is_ok()
+    }
+}
+
+impl Drop for MoveGuard {
+    fn drop(&mut self) {
+        self.0 .0.send_modify(|moves| moves.active -= 1);
+    }
+}
+
+/// Resolves on the first SIGTERM or SIGINT.
+pub async fn signal() -> std::io::Result<()> {
+    let mut terminate = unix::signal(SignalKind::terminate())?;
+    let mut interrupt = unix::signal(SignalKind::interrupt())?;
+    tokio::select! {
+        _ = terminate.recv() => tracing::info!("Received SIGTERM, shutting down"),
+        _ = interrupt.recv() => tracing::info!("Received SIGINT, shutting down"),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn close_refuses_new_moves_and_waits_for_running_ones() {
+        let tracker = MoveTracker::default();
+        let guard = tracker.begin().expect("open tracker accepts moves");
+        assert_eq!(tracker.active(), 1);
+
+        tracker.close();
+        tracker.closed().await;
+        assert!(tracker.begin().is_none());
+        assert!(!tracker.wait_idle(Duration::from_millis(10)).await);
+
+        let waiter = tokio::spawn({
+            let tracker = tracker.clone();
+            async move { tracker.wait_idle(Duration::from_secs(5)).await }
+        });
+        drop(guard);
+        assert!(waiter.await.unwrap());
+        assert_eq!(tracker.active(), 0);
+    }
+}
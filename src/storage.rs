@@ -0,0 +1,282 @@
+//! Checks that the configured directories are usable before touching them.
+
+use std::fs::OpenOptions;
+use std::path::Path;
+
+use crate::config::AppConfig;
+
+pub fn ensure_dir_readable_and_writable(
+    path: &Path,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if !path.is_dir() {
+        return Err(format!("path is not a directory: {}", path.display()).into());
+    }
+
+    // Readability check.
+    std::fs::read_dir(path)
+        .map_err(|e| format!("directory not readable ({}): {e}", path.display()))?;
+
+    // Writability check.
+    let unique = format!(
+        ".rewinder_perm_check_{}_{}",
+        std::process::id(),
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_err(|e| format!("system clock error: {e}"))?
+            .as_nanos()
+    );
+    let probe = path.join(unique);
+    OpenOptions::new()
+        .create_new(true)
+        .write(true)
+        .open(&probe)
+        .map_err(|e| format!("directory not writable ({}): {e}", path.display()))?;
+    std::fs::remove_file(&probe).map_err(|e| {
+        format!(
+            "failed to clean up permission probe {}: {e}",
+            probe.display()
+        )
+    })?;
+
+    Ok(())
+}
+
+/// Check that every media dir and its derived trash and permanent dirs exist
+/// (creating the derived ones), are accessible, and share a filesystem.
+pub fn validate_storage_access(
+    config: &AppConfig,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    for media_dir in &config.media_dirs {
+        ensure_dir_readable_and_writable(media_dir)?;
+    }
+
+    for trash_dir in config.all_trash_dirs() {
+        if !trash_dir.exists() {
+            std::fs::create_dir_all(&trash_dir).map_err(|e| {
+                format!(
+                    "failed to create derived trash directory {}: {e}",
+                    trash_dir.display()
+                )
+            })?;
+        }
+        ensure_dir_readable_and_writable(&trash_dir)?;
+    }
+
+    for permanent_dir in config.all_permanent_dirs() {
+        if !permanent_dir.exists() {
+            std::fs::create_dir_all(&permanent_dir).map_err(|e| {
+                format!(
+                    "failed to create derived permanent directory {}: {e}",
+                    permanent_dir.display()
+                )
+            })?;
+        }
+        ensure_dir_readable_and_writable(&permanent_dir)?;
+    }
+
+    // Usually another disk, so unlike the trash it may be on another filesystem.
+    if let Some(archive_dir) = &config.archive_dir {
+        if !archive_dir.exists() {
+            std::fs::create_dir_all(archive_dir).map_err(|e| {
+                format!(
+                    "failed to create archive directory {}: {e}",
+                    archive_dir.display()
+                )
+            })?;
+        }
+        ensure_dir_readable_and_writable(archive_dir)?;
+    }
+
+    if let Some(manifest_dir) = &config.deletion_manifest_dir {
+        if !manifest_dir.exists() {
+            std::fs::create_dir_all(manifest_dir).map_err(|e| {
+                format!(
+                    "failed to create deletion manifest directory {}: {e}",
+                    manifest_dir.display()
+                )
+            })?;
+        }
+        ensure_dir_readable_and_writable(manifest_dir)?;
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+
+        for media_dir in &config.media_dirs {
+            let trash_dir = AppConfig::trash_dir_for_media_dir(media_dir).ok_or_else(|| {
+                format!(
+                    "failed to derive trash directory for media_dir {}",
+                    media_dir.display()
+                )
+            })?;
+            let media_dev = std::fs::metadata(media_dir)
+                .map_err(|e| format!("failed to stat media_dir {}: {e}", media_dir.display()))?
+                .dev();
+            let trash_dev = std::fs::metadata(&trash_dir)
+                .map_err(|e| format!("failed to stat trash_dir {}: {e}", trash_dir.display()))?
+                .dev();
+
+            if media_dev != trash_dev {
+                return Err(format!(
+                    "media_dir {} and trash_dir {} are on different filesystems; refusing to start to avoid ownership changes during cross-device moves",
+                    media_dir.display(),
+                    trash_dir.display()
+                )
+                .into());
+            }
+
+            let permanent_dir =
+                AppConfig::permanent_dir_for_media_dir(media_dir).ok_or_else(|| {
+                    format!(
+                        "failed to derive permanent directory for media_dir {}",
+                        media_dir.display()
+                    )
+                })?;
+            let permanent_dev = std::fs::metadata(&permanent_dir)
+                .map_err(|e| {
+                    format!(
+                        "failed to stat permanent_dir {}: {e}",
+                        permanent_dir.display()
+                    )
+                })?
+                .dev();
+            if media_dev != permanent_dev {
+                return Err(format!(
+                    "media_dir {} and permanent_dir {} are on different filesystems; refusing to start to avoid ownership changes during cross-device moves",
+                    media_dir.display(),
+                    permanent_dir.display()
+                )
+                .into());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn test_config_with_media_dirs(media_dirs: Vec<std::path::PathBuf>) -> AppConfig {
+        AppConfig {
+            database_url: ":memory:".to_string(),
+            listen_addr: "127.0.0.1:0".into(),
+            media_dirs,
+            grace_period_days: 7,
+            keep_vote_days: 30,
+            keep_hardlinked_trash: false,
+            deletion_approval_gb: None,
+            expiry_warning_days: None,
+            archive_dir: None,
+            deletion_manifest_dir: None,
+            trash_approval: false,
+            require_admin_mark: false,
+            trash_window: None,
+            trash_batch_size: 10,
+            maintenance: false,
+            rescue_conflict: Default::default(),
+            cleanup_interval_hours: 1,
+            initial_admin_user: None,
+            tmdb_api_key: None,
+            watch_debounce_secs: 2,
+            poll_dirs: Vec::new(),
+            poll_interval_secs: 60,
+            season_pattern: None,
+            size_exclude: Vec::new(),
+            specials: Default::default(),
+            base_path: String::new(),
+            trusted_proxies: Vec::new(),
+            log_requests: false,
+            otlp_endpoint: None,
+            log_format: Default::default(),
+            db_max_connections: 5,
+            db_busy_timeout_ms: 5000,
+            db_wal: true,
+            db_vacuum_interval_days: 0,
+            deleted_retention_days: 0,
+            size_units: Default::default(),
+            guest_token: None,
+            metrics_token: None,
+            rate_limit_per_minute: 120,
+            login_rate_limit_per_minute: 10,
+            request_timeout_secs: 30,
+            max_body_kb: 256,
+            rules: Vec::new(),
+            tag_policies: Vec::new(),
+            media_server: None,
+            torrent_client: None,
+            cloud_archive: None,
+            rclone_archive: None,
+            overseerr: None,
+            radarr: None,
+            sonarr: None,
+        }
+    }
+
+    #[test]
+    fn storage_validation_fails_for_nonexistent_media_dir() {
+        let base = tempdir().expect("failed to create tempdir");
+        let missing = base.path().join("does-not-exist");
+        let cfg = test_config_with_media_dirs(vec![missing]);
+
+        let err = validate_storage_access(&cfg).expect_err("expected missing dir failure");
+        let msg = err.to_string();
+        assert!(
+            msg.contains("not a directory") || msg.contains("not readable"),
+            "unexpected error message: {msg}"
+        );
+    }
+
+    #[test]
+    fn storage_validation_fails_for_non_directory_media_path() {
+        let base = tempdir().expect("failed to create tempdir");
+        let file_path = base.path().join("not-a-directory");
+        std::fs::write(&file_path, "x").expect("failed to create file");
+        let cfg = test_config_with_media_dirs(vec![file_path]);
+
+        let err = validate_storage_access(&cfg).expect_err("expected non-directory failure");
+        assert!(err.to_string().contains("not a directory"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn storage_validation_fails_for_unreadable_and_unwritable_directories() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let unreadable = tempdir().expect("failed to create unreadable tempdir");
+        let unwritable = tempdir().expect("failed to create unwritable tempdir");
+
+        let unreadable_mode = std::fs::Permissions::from_mode(0o333);
+        let unwritable_mode = std::fs::Permissions::from_mode(0o555);
+        std::fs::set_permissions(unreadable.path(), unreadable_mode)
+            .expect("failed to chmod unreadable dir");
+        std::fs::set_permissions(unwritable.path(), unwritable_mode)
+            .expect("failed to chmod unwritable dir");
+
+        let read_err = ensure_dir_readable_and_writable(unreadable.path())
+            .expect_err("expected unreadable dir to fail");
+        let read_msg = read_err.to_string();
+
+        let write_err = ensure_dir_readable_and_writable(unwritable.path())
+            .expect_err("expected unwritable dir to fail");
+        let write_msg = write_err.to_string();
+
+        // Restore permissions so tempdir cleanup can remove directories.
+        std::fs::set_permissions(unreadable.path(), std::fs::Permissions::from_mode(0o755))
+            .expect("failed to restore unreadable dir perms");
+        std::fs::set_permissions(unwritable.path(), std::fs::Permissions::from_mode(0o755))
+            .expect("failed to restore unwritable dir perms");
+
+        assert!(
+            read_msg.contains("not readable"),
+            "unexpected unreadable error message: {read_msg}"
+        );
+        assert!(
+            write_msg.contains("not writable"),
+            "unexpected unwritable error message: {write_msg}"
+        );
+    }
+}
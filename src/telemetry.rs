@@ -0,0 +1,157 @@
+//! Log output and, when built with the `otel` feature, span export over OTLP.
+
+use tracing::Subscriber;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, EnvFilter, Layer};
+
+use crate::config::{AppConfig, LogFormat};
+
+/// Flushes exported spans when dropped; keep it alive until the process exits.
+pub struct TelemetryGuard {
+    #[cfg(feature = "otel")]
+    provider: Option<opentelemetry_sdk::trace::SdkTracerProvider>,
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        #[cfg(feature = "otel")]
+        if let Some(provider) = self.provider.take() {
+            if let Err(e) = provider.shutdown() {
+                eprintln!("Failed to flush traces: {e}");
+            }
+        }
+    }
+}
+
+/// Install the global tracing subscriber. `RUST_LOG` overrides the default
+/// `rewinder=info` filter for both logs and exported spans.
+pub fn init(
+    config: &AppConfig,
+) -> Result<TelemetryGuard, Box<dyn std::error::Error + Send + Sync>> {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| "rewinder=info".into());
+    let (text, json) = match config.log_format {
+        LogFormat::Text => (Some(fmt::layer().compact().with_target(true)), None),
+        LogFormat::Json => (None, Some(json_layer(std::io::stdout))),
+    };
+    let registry = tracing_subscriber::registry()
+        .with(filter)
+        .with(text)
+        .with(json);
+
+    #[cfg(feature = "otel")]
+    {
+        let (layer, provider) = match &config.otlp_endpoint {
+            Some(endpoint) => {
+                let (layer, provider) = otel::layer(endpoint)?;
+                (Some(layer), Some(provider))
+            }
+            None => (None, None),
+        };
+        registry.with(layer).try_init()?;
+        if let Some(endpoint) = &config.otlp_endpoint {
+            tracing::info!("Exporting traces to {endpoint}");
+        }
+        Ok(TelemetryGuard { provider })
+    }
+
+    #[cfg(not(feature = "otel"))]
+    {
+        registry.try_init()?;
+        if config.otlp_endpoint.is_some() {
+            tracing::warn!("otlp_endpoint is set, but rewinder was built without the otel feature");
+        }
+        Ok(TelemetryGuard {})
+    }
+}
+
+/// JSON lines as `{"timestamp", "level", "target", "message", <event fields>, "span"}`.
+/// Events such as trash moves carry `event`, `media_id`, `path` and `user` fields.
+fn json_layer<S, W>(writer: W) -> impl Layer<S>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    fmt::layer()
+        .with_writer(writer)
+        .json()
+        .flatten_event(true)
+        .with_current_span(true)
+        .with_span_list(false)
+        .with_target(true)
+}
+
+#[cfg(feature = "otel")]
+mod otel {
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry_otlp::WithExportConfig;
+    use opentelemetry_sdk::trace::SdkTracerProvider;
+    use opentelemetry_sdk::Resource;
+    use tracing::Subscriber;
+    use tracing_subscriber::registry::LookupSpan;
+    use tracing_subscriber::Layer;
+
+    pub fn layer<S>(
+        endpoint: &str,
+    ) -> Result<(impl Layer<S>, SdkTracerProvider), Box<dyn std::error::Error + Send + Sync>>
+    where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+    {
+        let exporter = opentelemetry_otlp::SpanExporter::builder()
+            .with_http()
+            .with_endpoint(endpoint)
+            .build()?;
+        let provider = SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            .with_resource(Resource::builder().with_service_name("rewinder").build())
+            .build();
+        let tracer = provider.tracer("rewinder");
+        Ok((tracing_opentelemetry::layer().with_tracer(tracer), provider))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn json_lines_have_event_fields_at_the_top_level() {
+        let buffer = Buffer::default();
+        let writer = buffer.clone();
+        let subscriber = tracing_subscriber::registry().with(json_layer(move || writer.clone()));
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!(
+                event = "trash",
+                media_id = 7,
+                path = "/m/Heat",
+                "Moved to trash"
+            );
+        });
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let line: serde_json::Value = serde_json::from_str(output.trim()).unwrap();
+        assert_eq!(line["message"], "Moved to trash");
+        assert_eq!(line["event"], "trash");
+        assert_eq!(line["media_id"], 7);
+        assert_eq!(line["path"], "/m/Heat");
+        assert_eq!(line["level"], "INFO");
+        assert!(line["timestamp"].is_string());
+    }
+}
@@ -1,9 +1,30 @@
 use askama::Template;
-use axum::http::StatusCode;
+use axum::http::{header, StatusCode};
 use axum::response::{Html, IntoResponse, Response};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::OnceLock;
 
-use crate::models::media::Media;
+use crate::config::SizeUnits;
+use crate::manifest::ManifestMonth;
+use crate::models::announcement::Announcement;
+use crate::models::comment::{Comment, CommentPreview};
+use crate::models::db_maintenance::DbMaintenanceRun;
+use crate::models::deletion_approval::PendingDeletion;
+use crate::models::media::{DeletedMedia, Media, Reclaimable};
+use crate::models::media_history::{FreedMonth, MediaHistoryEntry, Removal};
+use crate::models::pending_trash::PendingTrash;
+use crate::models::policy_log::PolicyLogEntry;
+use crate::models::preferences::Preferences;
+use crate::models::rerequest::Rerequest;
+use crate::models::rule_hit::RuleHit;
+use crate::models::scan_summary::ScanSummaryRecord;
+use crate::models::trash_queue::QueuedTrash;
 use crate::models::user::User;
+use crate::policies::TagPolicy;
+use crate::routes::pager::Pager;
+use crate::rules::RulePreview;
+use crate::scanner::{ExcludedFile, ScanSnapshot};
+use crate::watcher::HealthSnapshot;
 
 /// Helper to convert any Askama template into an axum Response
 fn render_template(t: &impl Template) -> Response {
@@ -49,6 +70,15 @@ pub struct MediaRow {
     pub total_users: i64,
     pub persisted: bool,
     pub persisted_by_me: bool,
+    /// A season of a show the user persisted as a whole, which is only
+    /// unpersisted with the show.
+    pub series_persisted: bool,
+    /// Other editions of the same title linked to it, e.g. a 4K copy.
+    pub editions: i64,
+    /// The user voted to keep it for now.
+    pub kept: bool,
+    pub tags: Vec<String>,
+    pub comments: CommentPreview,
 }
 
 #[derive(Template)]
@@ -57,9 +87,15 @@ pub struct MoviesTemplate {
     pub username: String,
     pub is_admin: bool,
     pub items: Vec<MediaRow>,
+    pub pager: Pager,
     pub show_marked: bool,
     pub sort_by: String,
     pub sort_dir: String,
+    pub reclaimable: Reclaimable,
+    /// Only items with this tag are listed.
+    pub tag: Option<String>,
+    /// Tags on the page's media type, to filter by.
+    pub all_tags: Vec<String>,
 }
 
 impl IntoResponse for MoviesTemplate {
@@ -74,9 +110,15 @@ pub struct TvTemplate {
     pub username: String,
     pub is_admin: bool,
     pub series_groups: Vec<TvSeriesGroup>,
+    pub pager: Pager,
     pub show_marked: bool,
     pub sort_by: String,
     pub sort_dir: String,
+    pub reclaimable: Reclaimable,
+    /// Only items with this tag are listed.
+    pub tag: Option<String>,
+    /// Tags on the page's media type, to filter by.
+    pub all_tags: Vec<String>,
 }
 
 impl IntoResponse for TvTemplate {
@@ -85,12 +127,209 @@ impl IntoResponse for TvTemplate {
     }
 }
 
+#[derive(Template)]
+#[template(path = "media_detail.html")]
+pub struct MediaDetailTemplate {
+    pub username: String,
+    pub is_admin: bool,
+    pub media: Media,
+    pub poster_url: Option<String>,
+    pub history: Vec<MediaHistoryEntry>,
+    pub tags: Vec<String>,
+    /// Tags already used on other items, offered while typing.
+    pub known_tags: Vec<String>,
+    pub comments: Vec<Comment>,
+    /// The viewer, who may remove their own comments.
+    pub user_id: i64,
+    /// Files below the item that `size_exclude` leaves out of its size.
+    pub excluded: Vec<ExcludedFile>,
+    /// Media dirs an admin can move the item into.
+    pub libraries: Vec<String>,
+    /// Editions linked with the item, itself included, largest first.
+    pub editions: Vec<Media>,
+    /// Movies an admin can link as further editions.
+    pub edition_candidates: Vec<Media>,
+}
+
+impl MediaDetailTemplate {
+    /// The folder or file name, which tells editions of a title apart.
+    fn edition_name(&self, edition: &Media) -> String {
+        std::path::Path::new(&edition.path)
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| edition.path.clone())
+    }
+
+    fn editions_size(&self) -> String {
+        format_size(&self.editions.iter().map(|e| e.size_bytes).sum())
+    }
+
+    fn excluded_size(&self) -> String {
+        format_size(&self.excluded.iter().map(|f| f.size_bytes).sum())
+    }
+}
+
+impl IntoResponse for MediaDetailTemplate {
+    fn into_response(self) -> Response {
+        render_template(&self)
+    }
+}
+
+#[derive(Template)]
+#[template(path = "account.html")]
+pub struct AccountTemplate {
+    pub username: String,
+    pub is_admin: bool,
+    pub locale: String,
+    pub timezone: String,
+    pub timezones: Vec<String>,
+    pub prefs: Preferences,
+    pub feed_url: Option<String>,
+    pub message: Option<String>,
+}
+
+impl AccountTemplate {
+    fn is_locale(&self, code: &str) -> bool {
+        self.locale == code
+    }
+
+    fn is_sort(&self, sort: &str) -> bool {
+        self.prefs.sort == sort
+    }
+
+    fn is_theme(&self, theme: &str) -> bool {
+        self.prefs.theme == theme
+    }
+
+    fn is_page_size(&self, size: &i64) -> bool {
+        self.prefs.page_size == *size
+    }
+
+    fn page_size_label(&self, size: &i64) -> String {
+        match size {
+            0 => "All".to_string(),
+            n => n.to_string(),
+        }
+    }
+}
+
+impl IntoResponse for AccountTemplate {
+    fn into_response(self) -> Response {
+        render_template(&self)
+    }
+}
+
+#[derive(Template)]
+#[template(path = "feed.xml")]
+pub struct TrashFeedTemplate {
+    /// Absolute URL of the app, e.g. "https://nas.local/rewinder".
+    pub site: String,
+    pub updated: String,
+    pub entries: Vec<Removal>,
+}
+
+impl IntoResponse for TrashFeedTemplate {
+    fn into_response(self) -> Response {
+        match self.render() {
+            Ok(xml) => ([(header::CONTENT_TYPE, "application/atom+xml")], xml).into_response(),
+            Err(e) => {
+                tracing::error!("Template render error: {e}");
+                (StatusCode::INTERNAL_SERVER_ERROR, "Template error").into_response()
+            }
+        }
+    }
+}
+
+/// A database timestamp in the RFC 3339 form Atom feeds need.
+pub fn atom_time(utc: &str) -> String {
+    format!("{}Z", utc.replacen(' ', "T", 1))
+}
+
+/// A series on the guest page, with the seasons currently in the library.
+pub struct GuestSeries {
+    pub title: String,
+    pub seasons: Vec<i64>,
+    pub poster_path: Option<String>,
+}
+
+impl GuestSeries {
+    /// "Season 2" or "Seasons 1, 2, 4".
+    pub fn season_list(&self) -> String {
+        let numbers: Vec<String> = self.seasons.iter().map(i64::to_string).collect();
+        match numbers.as_slice() {
+            [one] => format!("Season {one}"),
+            _ => format!("Seasons {}", numbers.join(", ")),
+        }
+    }
+}
+
+#[derive(Template)]
+#[template(path = "guest.html")]
+pub struct GuestTemplate {
+    pub movies: Vec<Media>,
+    pub series: Vec<GuestSeries>,
+}
+
+impl IntoResponse for GuestTemplate {
+    fn into_response(self) -> Response {
+        render_template(&self)
+    }
+}
+
 pub struct TvSeriesGroup {
     pub title: String,
     pub seasons: Vec<MediaRow>,
     pub marked_count: i64,
     pub total_count: i64,
-    pub poster_url: Option<String>,
+    pub poster_path: Option<String>,
+    /// Stored size of every season of the show, including ones the list hides.
+    pub season_sizes: Vec<SeasonSize>,
+    pub total_size: i64,
+    /// The user persisted the show as a whole.
+    pub persisted_by_me: bool,
+}
+
+impl TvSeriesGroup {
+    /// Whether some season's size is still being measured, so the total is too low.
+    pub fn size_pending(&self) -> bool {
+        self.season_sizes.iter().any(|s| s.size_pending)
+    }
+
+    /// Share of the show total taken by `season`, in whole percent.
+    pub fn size_share(&self, season: &SeasonSize) -> i64 {
+        if self.total_size <= 0 {
+            return 0;
+        }
+        season.size_bytes * 100 / self.total_size
+    }
+}
+
+pub struct SeasonSize {
+    pub season: Option<i64>,
+    pub size_bytes: i64,
+    pub size_pending: bool,
+}
+
+impl From<&Media> for SeasonSize {
+    fn from(media: &Media) -> Self {
+        SeasonSize {
+            season: media.season,
+            size_bytes: media.size_bytes,
+            size_pending: media.size_pending,
+        }
+    }
+}
+
+#[derive(Template)]
+#[template(path = "partials/reclaimable.html")]
+pub struct ReclaimablePartial {
+    pub reclaimable: Reclaimable,
+}
+
+impl IntoResponse for ReclaimablePartial {
+    fn into_response(self) -> Response {
+        render_template(&self)
+    }
 }
 
 #[derive(Template)]
@@ -119,6 +358,37 @@ impl IntoResponse for MediaCardPartial {
     }
 }
 
+/// The tag list and form of the media page, re-rendered after each change.
+#[derive(Template)]
+#[template(path = "partials/tags.html")]
+pub struct MediaTagsPartial {
+    pub media: Media,
+    pub tags: Vec<String>,
+    pub known_tags: Vec<String>,
+}
+
+impl IntoResponse for MediaTagsPartial {
+    fn into_response(self) -> Response {
+        render_template(&self)
+    }
+}
+
+/// The comment thread of the media page, re-rendered after each change.
+#[derive(Template)]
+#[template(path = "partials/comments.html")]
+pub struct MediaCommentsPartial {
+    pub media: Media,
+    pub comments: Vec<Comment>,
+    pub user_id: i64,
+    pub is_admin: bool,
+}
+
+impl IntoResponse for MediaCommentsPartial {
+    fn into_response(self) -> Response {
+        render_template(&self)
+    }
+}
+
 #[derive(Template)]
 #[template(path = "admin/dashboard.html")]
 pub struct AdminDashboardTemplate {
@@ -129,6 +399,45 @@ pub struct AdminDashboardTemplate {
     pub active_size: String,
     pub trashed_size: String,
     pub user_count: i64,
+    pub watcher: HealthSnapshot,
+    pub scan: ScanSnapshot,
+    pub media_dirs: Vec<String>,
+    pub users: Vec<User>,
+    /// Granted user ids per restricted media dir.
+    pub grants: BTreeMap<String, Vec<i64>>,
+    pub scan_summaries: Vec<ScanSummaryRecord>,
+    pub db_maintenance: Vec<DbMaintenanceRun>,
+    pub largest_active: Vec<Media>,
+    pub largest_persisted: Vec<Media>,
+    pub space_freed: Vec<FreedMonth>,
+}
+
+impl AdminDashboardTemplate {
+    /// Users granted the library at `dir`; empty when it is open to everyone.
+    pub fn granted(&self, dir: &str) -> Vec<&User> {
+        let ids = self
+            .grants
+            .get(dir.trim_end_matches('/'))
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        self.users.iter().filter(|u| ids.contains(&u.id)).collect()
+    }
+
+    /// "name 1.2 GB, other 300 MB" for one breakdown of a month's freed space.
+    pub fn freed_breakdown(&self, by: &BTreeMap<String, i64>) -> String {
+        by.iter()
+            .map(|(name, bytes)| format!("{name} {}", format_size(bytes)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Heading and rows of each "Largest Items" table.
+    pub fn largest_tables(&self) -> [(&'static str, &[Media]); 2] {
+        [
+            ("Active", &self.largest_active),
+            ("Persisted", &self.largest_persisted),
+        ]
+    }
 }
 
 impl IntoResponse for AdminDashboardTemplate {
@@ -143,7 +452,10 @@ pub struct AdminUsersTemplate {
     pub username: String,
     pub is_admin: bool,
     pub users: Vec<User>,
-    pub invite_url: Option<String>,
+    /// Usernames and invite links of the users just created.
+    pub invites: Vec<(String, String)>,
+    /// Names from a bulk invite that already had an account.
+    pub skipped: Vec<String>,
 }
 
 impl IntoResponse for AdminUsersTemplate {
@@ -158,6 +470,28 @@ pub struct AdminTrashTemplate {
     pub username: String,
     pub is_admin: bool,
     pub items: Vec<Media>,
+    pub grace_period_days: u64,
+    /// Longer grace periods from tag policies, by media id.
+    pub extended_grace: HashMap<i64, u64>,
+    /// Number of files shared with another path by hardlinks, by media id.
+    pub hardlinked: HashMap<i64, usize>,
+    /// Moves waiting for the trash window, oldest first.
+    pub queued: Vec<QueuedTrash>,
+    /// The configured `trash_window`, if any.
+    pub trash_window: Option<String>,
+}
+
+impl AdminTrashTemplate {
+    fn grace_period_of(&self, id: &i64) -> u64 {
+        self.extended_grace
+            .get(id)
+            .copied()
+            .unwrap_or(self.grace_period_days)
+    }
+
+    fn hardlinked_of(&self, id: &i64) -> usize {
+        self.hardlinked.get(id).copied().unwrap_or(0)
+    }
 }
 
 impl IntoResponse for AdminTrashTemplate {
@@ -166,22 +500,283 @@ impl IntoResponse for AdminTrashTemplate {
     }
 }
 
+#[derive(Template)]
+#[template(path = "admin/approvals.html")]
+pub struct AdminApprovalsTemplate {
+    pub username: String,
+    pub is_admin: bool,
+    pub items: Vec<PendingDeletion>,
+    /// Items the viewing admin already approved.
+    pub approved_by_me: Vec<i64>,
+    /// Items everyone marked, waiting for an admin to confirm trashing them.
+    pub pending_trash: Vec<PendingTrash>,
+}
+
+impl IntoResponse for AdminApprovalsTemplate {
+    fn into_response(self) -> Response {
+        render_template(&self)
+    }
+}
+
+/// One line of the admin settings form.
+pub struct SettingRow {
+    pub key: &'static str,
+    pub help: &'static str,
+    /// The value in effect.
+    pub value: String,
+    /// What the config file says, which clearing the saved value restores.
+    pub file_value: String,
+    pub saved: bool,
+}
+
+#[derive(Template)]
+#[template(path = "admin/settings.html")]
+pub struct AdminSettingsTemplate {
+    pub username: String,
+    pub is_admin: bool,
+    pub rows: Vec<SettingRow>,
+    pub dry_run: bool,
+    pub maintenance: bool,
+    pub announcements: Vec<Announcement>,
+}
+
+impl IntoResponse for AdminSettingsTemplate {
+    fn into_response(self) -> Response {
+        render_template(&self)
+    }
+}
+
+#[derive(Template)]
+#[template(path = "admin/deleted.html")]
+pub struct AdminDeletedTemplate {
+    pub username: String,
+    pub is_admin: bool,
+    pub items: Vec<DeletedMedia>,
+    pub search: String,
+    pub retention_days: u64,
+    /// Users' requests to bring items back, by media id.
+    pub requests: HashMap<i64, Vec<Rerequest>>,
+}
+
+impl AdminDeletedTemplate {
+    fn requests_for(&self, id: &i64) -> &[Rerequest] {
+        self.requests.get(id).map(Vec::as_slice).unwrap_or_default()
+    }
+}
+
+impl IntoResponse for AdminDeletedTemplate {
+    fn into_response(self) -> Response {
+        render_template(&self)
+    }
+}
+
+/// The deleted items a user may ask back.
+#[derive(Template)]
+#[template(path = "deleted.html")]
+pub struct DeletedTemplate {
+    pub username: String,
+    pub is_admin: bool,
+    pub items: Vec<DeletedMedia>,
+    pub search: String,
+    /// Items the user asked back, with the service each request went to.
+    pub requested: HashMap<i64, Option<String>>,
+}
+
+impl DeletedTemplate {
+    /// The state of the user's request for `id`, if they made one.
+    fn request_status(&self, id: &i64) -> Option<String> {
+        let forwarded_to = self.requested.get(id)?;
+        Some(match forwarded_to.as_deref() {
+            Some(service) => format!("Sent to {}", service_name(service)),
+            None => "Requested".to_string(),
+        })
+    }
+}
+
+impl IntoResponse for DeletedTemplate {
+    fn into_response(self) -> Response {
+        render_template(&self)
+    }
+}
+
+/// How a service re-requests are forwarded to is spelled on pages.
+pub fn service_name(service: &str) -> &str {
+    match service {
+        "overseerr" => "Overseerr",
+        "radarr" => "Radarr",
+        "sonarr" => "Sonarr",
+        other => other,
+    }
+}
+
+#[derive(Template)]
+#[template(path = "admin/archive.html")]
+pub struct AdminArchiveTemplate {
+    pub username: String,
+    pub is_admin: bool,
+    pub items: Vec<Media>,
+    /// The configured `archive_dir`, if any.
+    pub archive_dir: Option<String>,
+}
+
+impl IntoResponse for AdminArchiveTemplate {
+    fn into_response(self) -> Response {
+        render_template(&self)
+    }
+}
+
+#[derive(Template)]
+#[template(path = "admin/manifest.html")]
+pub struct AdminManifestTemplate {
+    pub username: String,
+    pub is_admin: bool,
+    pub months: Vec<ManifestMonth>,
+    /// The configured `deletion_manifest_dir`, if any.
+    pub manifest_dir: Option<String>,
+}
+
+impl IntoResponse for AdminManifestTemplate {
+    fn into_response(self) -> Response {
+        render_template(&self)
+    }
+}
+
+#[derive(Template)]
+#[template(path = "admin/rules.html")]
+pub struct AdminRulesTemplate {
+    pub username: String,
+    pub is_admin: bool,
+    pub previews: Vec<RulePreview>,
+    pub hits: Vec<RuleHit>,
+    pub policies: Vec<TagPolicy>,
+    pub policy_log: Vec<PolicyLogEntry>,
+}
+
+impl IntoResponse for AdminRulesTemplate {
+    fn into_response(self) -> Response {
+        render_template(&self)
+    }
+}
+
 pub fn poster_image_url(poster_path: &Option<String>) -> Option<String> {
     poster_path.as_ref().map(|p| crate::tmdb::poster_url(p))
 }
 
+pub fn poster_thumb_url(poster_path: &Option<String>) -> Option<String> {
+    poster_path
+        .as_ref()
+        .map(|p| crate::tmdb::poster_thumb_url(p))
+}
+
+/// Inline style showing a poster's blurred placeholder behind the image
+/// until it loads; empty when the poster has no hash.
+pub fn poster_placeholder_style(poster_hash: &Option<String>) -> String {
+    poster_hash
+        .as_deref()
+        .and_then(crate::posters::placeholder_url)
+        .map(|url| format!("background-image: url('{url}')"))
+        .unwrap_or_default()
+}
+
+/// `sizes` of a poster filling a card: cards in the grid are at least 180px
+/// wide, and span the screen in the one- and two-column phone layouts.
+pub const CARD_POSTER_SIZES: &str = "(max-width: 400px) 100vw, (max-width: 640px) 50vw, 185px";
+
+/// `srcset` offering both renditions of a poster, so browsers only fetch the
+/// larger one where the image is shown wide or on a high-density screen.
+pub fn poster_srcset(poster_path: &Option<String>) -> String {
+    match poster_path {
+        Some(p) => format!(
+            "{} 185w, {} 342w",
+            crate::tmdb::poster_thumb_url(p),
+            crate::tmdb::poster_url(p)
+        ),
+        None => String::new(),
+    }
+}
+
+/// The `&tag=` query parameter that keeps a list page's tag filter in its
+/// links, or nothing without one.
+pub fn tag_param(tag: &Option<String>) -> String {
+    match tag {
+        Some(tag) => format!(
+            "&tag={}",
+            askama::filters::urlencode_strict(tag).unwrap_or_default()
+        ),
+        None => String::new(),
+    }
+}
+
+/// Whether the list is filtered by `tag`; tags compare case-insensitively.
+pub fn is_tag_filter(filter: &Option<String>, tag: &str) -> bool {
+    filter
+        .as_deref()
+        .is_some_and(|f| f.eq_ignore_ascii_case(tag))
+}
+
+static SIZE_UNITS: OnceLock<SizeUnits> = OnceLock::new();
+
+/// Install the `size_units` from the config before any page is rendered.
+pub fn set_size_units(units: SizeUnits) {
+    if SIZE_UNITS.set(units).is_err() {
+        tracing::warn!("size_units already set, ignoring");
+    }
+}
+
+/// Human-readable size in the configured units, e.g. "1.4 TB" or "500 MB".
 pub fn format_size(bytes: &i64) -> String {
-    let bytes = *bytes;
-    const GB: f64 = 1_073_741_824.0;
-    const MB: f64 = 1_048_576.0;
+    format_size_in(*bytes, SIZE_UNITS.get().copied().unwrap_or_default())
+}
+
+fn format_size_in(bytes: i64, units: SizeUnits) -> String {
+    let base: f64 = match units {
+        SizeUnits::Binary => 1024.0,
+        SizeUnits::Decimal => 1000.0,
+    };
     let b = bytes as f64;
-    if b >= GB {
-        format!("{:.1} GB", b / GB)
-    } else {
-        format!("{:.0} MB", b / MB)
+    for (exp, label) in [(5, "PB"), (4, "TB"), (3, "GB")] {
+        let unit = base.powi(exp);
+        if b >= unit {
+            return format!("{:.1} {label}", b / unit);
+        }
+    }
+    format!("{:.0} MB", b / base.powi(2))
+}
+
+/// Relative timestamps for templates, e.g. `{{ media.first_seen|added_ago }}`.
+/// Unparsable timestamps are shown as they are.
+mod filters {
+    use std::fmt::Display;
+
+    use crate::locale;
+
+    /// "added 3 weeks ago".
+    pub fn added_ago(utc: impl Display) -> askama::Result<String> {
+        let utc = utc.to_string();
+        Ok(match locale::ago(&utc, jiff::Timestamp::now()) {
+            Some(ago) => format!("added {ago}"),
+            None => utc,
+        })
+    }
+
+    /// "deleted in 5 days" for an item trashed at `utc`, given the grace period.
+    pub fn deleted_in(utc: impl Display, grace_period_days: &u64) -> askama::Result<String> {
+        let utc = utc.to_string();
+        let now = jiff::Timestamp::now();
+        Ok(match locale::in_days(&utc, *grace_period_days, now) {
+            Some(left) => format!("deleted {left}"),
+            None if locale::ago(&utc, now).is_some() => "deleted at the next cleanup".to_string(),
+            None => utc,
+        })
     }
 }
 
+/// Signed size for scan summaries, e.g. "+1.2 GB" or "-500 MB".
+pub fn format_size_delta(bytes: &i64) -> String {
+    let sign = if *bytes < 0 { "-" } else { "+" };
+    format!("{sign}{}", format_size(&bytes.abs()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -203,4 +798,27 @@ mod tests {
         let size: i64 = 1_048_576; // 1 MB
         assert_eq!(format_size(&size), "1 MB");
     }
+
+    #[test]
+    fn format_size_large() {
+        assert_eq!(format_size(&1_539_316_278_886), "1.4 TB");
+        assert_eq!(format_size(&(3 * 1_125_899_906_842_624)), "3.0 PB");
+    }
+
+    #[test]
+    fn format_size_decimal() {
+        assert_eq!(
+            format_size_in(1_500_000_000_000, SizeUnits::Decimal),
+            "1.5 TB"
+        );
+        assert_eq!(format_size_in(2_000_000_000, SizeUnits::Decimal), "2.0 GB");
+        assert_eq!(format_size_in(500_000_000, SizeUnits::Decimal), "500 MB");
+    }
+
+    #[test]
+    fn format_size_delta_signs() {
+        assert_eq!(format_size_delta(&1_288_490_189), "+1.2 GB");
+        assert_eq!(format_size_delta(&-524_288_000), "-500 MB");
+        assert_eq!(format_size_delta(&0), "+0 MB");
+    }
 }
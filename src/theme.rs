@@ -0,0 +1,35 @@
+//! The colour theme of the user behind the current request, so `base.html`
+//! can apply it on every page without each template carrying it.
+
+use std::future::Future;
+use std::sync::{Arc, OnceLock};
+
+pub const DEFAULT_THEME: &str = "dark";
+
+/// Slot the auth extractor fills with the user's saved theme.
+#[derive(Clone, Default)]
+pub struct RequestTheme(Arc<OnceLock<String>>);
+
+impl RequestTheme {
+    pub fn set(&self, theme: String) {
+        let _ = self.0.set(theme);
+    }
+}
+
+tokio::task_local! {
+    static REQUEST_THEME: RequestTheme;
+}
+
+/// Run `f` with `slot` as the theme [`current`] reports.
+pub async fn scope<F: Future>(slot: RequestTheme, f: F) -> F::Output {
+    REQUEST_THEME.scope(slot, f).await
+}
+
+/// The theme to render the current page in. For templates.
+pub fn current() -> String {
+    REQUEST_THEME
+        .try_with(|slot| slot.0.get().cloned())
+        .ok()
+        .flatten()
+        .unwrap_or_else(|| DEFAULT_THEME.to_string())
+}
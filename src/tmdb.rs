@@ -1,7 +1,7 @@
 use serde_json::Value;
 
 const TMDB_BASE: &str = "https://api.themoviedb.org";
-const TMDB_IMAGE_BASE: &str = "https://image.tmdb.org/t/p/w342";
+const TMDB_IMAGE_BASE: &str = "https://image.tmdb.org/t/p";
 
 #[derive(Clone)]
 pub struct TmdbClient {
@@ -60,10 +60,31 @@ impl TmdbClient {
             .as_str()
             .map(|s| s.to_string())
     }
+
+    /// Blurhash of a poster, computed from TMDB's smallest rendition.
+    pub async fn poster_hash(&self, poster_path: &str) -> Option<String> {
+        let bytes = self
+            .client
+            .get(format!("{TMDB_IMAGE_BASE}/w92{poster_path}"))
+            .send()
+            .await
+            .ok()?
+            .error_for_status()
+            .ok()?
+            .bytes()
+            .await
+            .ok()?;
+        crate::posters::hash_jpeg(&bytes)
+    }
 }
 
 pub fn poster_url(poster_path: &str) -> String {
-    format!("{TMDB_IMAGE_BASE}{poster_path}")
+    format!("{TMDB_IMAGE_BASE}/w342{poster_path}")
+}
+
+/// The smaller rendition TMDB serves of a poster, about the width of a card.
+pub fn poster_thumb_url(poster_path: &str) -> String {
+    format!("{TMDB_IMAGE_BASE}/w185{poster_path}")
 }
 
 #[cfg(test)]
@@ -76,5 +97,9 @@ mod tests {
             poster_url("/abc123.jpg"),
             "https://image.tmdb.org/t/p/w342/abc123.jpg"
         );
+        assert_eq!(
+            poster_thumb_url("/abc123.jpg"),
+            "https://image.tmdb.org/t/p/w185/abc123.jpg"
+        );
     }
 }
@@ -0,0 +1,368 @@
+//! Optional link to the qBittorrent or Transmission client that seeds the
+//! library.
+//!
+//! Moving a file to the trash pulls it out from under any torrent still
+//! seeding it, so before trashing Rewinder looks for such torrents and skips,
+//! warns or removes them as configured.
+
+use reqwest::StatusCode;
+use serde::Deserialize;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::config::AppConfig;
+use crate::models::media::Media;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Transmission status code of a torrent that is seeding.
+const TRANSMISSION_SEEDING: i64 = 6;
+const TRANSMISSION_SEED_QUEUED: i64 = 5;
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TorrentClientKind {
+    Qbittorrent,
+    Transmission,
+}
+
+/// What to do when an item about to be trashed is still seeding.
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SeedingAction {
+    /// Leave it in place until seeding stops; a later cleanup run trashes it.
+    #[default]
+    Skip,
+    /// Log a warning and trash it anyway.
+    Warn,
+    /// Remove the torrent from the client, keeping its data, then trash it.
+    Remove,
+}
+
+/// The `[torrent_client]` config table.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+pub struct TorrentClientConfig {
+    pub kind: TorrentClientKind,
+    /// Web UI or RPC base URL, e.g. "http://qbittorrent:8080".
+    pub url: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    #[serde(default)]
+    pub on_seeding: SeedingAction,
+}
+
+impl TorrentClientConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.url.starts_with("http://") || self.url.starts_with("https://")) {
+            return Err(format!(
+                "url {:?} must start with http:// or https://",
+                self.url
+            ));
+        }
+        if self.username.is_some() != self.password.is_some() {
+            return Err("username and password must be set together".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Torrent {
+    /// Info hash for qBittorrent, numeric id for Transmission.
+    pub id: String,
+    pub name: String,
+    /// File or top-level folder of the torrent's data.
+    pub content_path: PathBuf,
+    pub seeding: bool,
+}
+
+impl Torrent {
+    /// Whether this torrent's data overlaps `path`, in either direction: a
+    /// torrent of one episode inside a season folder, or of a whole series.
+    pub fn references(&self, path: &Path) -> bool {
+        self.content_path.starts_with(path) || path.starts_with(&self.content_path)
+    }
+}
+
+pub struct TorrentClient {
+    client: reqwest::Client,
+    config: TorrentClientConfig,
+}
+
+impl TorrentClient {
+    pub fn new(config: &TorrentClientConfig) -> Self {
+        Self {
+            client: reqwest::Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+            config: config.clone(),
+        }
+    }
+
+    fn endpoint(&self, path: &str) -> String {
+        format!("{}{path}", self.config.url.trim_end_matches('/'))
+    }
+
+    pub async fn torrents(&self) -> Result<Vec<Torrent>, Box<dyn std::error::Error + Send + Sync>> {
+        match self.config.kind {
+            TorrentClientKind::Qbittorrent => {
+                let cookie = self.qbittorrent_login().await?;
+                let mut request = self.client.get(self.endpoint("/api/v2/torrents/info"));
+                if let Some(cookie) = &cookie {
+                    request = request.header("Cookie", cookie);
+                }
+                let json: Value = request.send().await?.error_for_status()?.json().await?;
+                Ok(qbittorrent_torrents(&json))
+            }
+            TorrentClientKind::Transmission => {
+                let json = self
+                    .transmission_rpc(serde_json::json!({
+                        "method": "torrent-get",
+                        "arguments": {"fields": ["id", "name", "downloadDir", "status"]}
+                    }))
+                    .await?;
+                Ok(transmission_torrents(&json))
+            }
+        }
+    }
+
+    /// Drop `torrent` from the client without touching its data.
+    pub async fn remove(
+        &self,
+        torrent: &Torrent,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        match self.config.kind {
+            TorrentClientKind::Qbittorrent => {
+                let cookie = self.qbittorrent_login().await?;
+                let mut request = self
+                    .client
+                    .post(self.endpoint("/api/v2/torrents/delete"))
+                    .form(&[("hashes", torrent.id.as_str()), ("deleteFiles", "false")]);
+                if let Some(cookie) = &cookie {
+                    request = request.header("Cookie", cookie);
+                }
+                request.send().await?.error_for_status()?;
+            }
+            TorrentClientKind::Transmission => {
+                let id: i64 = torrent.id.parse()?;
+                self.transmission_rpc(serde_json::json!({
+                    "method": "torrent-remove",
+                    "arguments": {"ids": [id], "delete-local-data": false}
+                }))
+                .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Session cookie for the qBittorrent Web API, or `None` when no
+    /// credentials are configured and the client bypasses auth for us.
+    async fn qbittorrent_login(
+        &self,
+    ) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
+        let (Some(username), Some(password)) = (&self.config.username, &self.config.password)
+        else {
+            return Ok(None);
+        };
+        let response = self
+            .client
+            .post(self.endpoint("/api/v2/auth/login"))
+            .header("Referer", &self.config.url)
+            .form(&[("username", username), ("password", password)])
+            .send()
+            .await?
+            .error_for_status()?;
+        let cookie = response
+            .headers()
+            .get_all("set-cookie")
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .find_map(|v| v.split(';').next().filter(|c| c.starts_with("SID=")))
+            .ok_or("qBittorrent login was rejected")?;
+        Ok(Some(cookie.to_string()))
+    }
+
+    /// One Transmission RPC call, redoing it once with the session id the
+    /// server hands out on the first 409.
+    async fn transmission_rpc(
+        &self,
+        body: Value,
+    ) -> Result<Value, Box<dyn std::error::Error + Send + Sync>> {
+        let mut session_id = String::new();
+        for _ in 0..2 {
+            let mut request = self
+                .client
+                .post(self.endpoint("/transmission/rpc"))
+                .header("X-Transmission-Session-Id", &session_id)
+                .json(&body);
+            if let (Some(username), password) = (&self.config.username, &self.config.password) {
+                request = request.basic_auth(username, password.as_ref());
+            }
+            let response = request.send().await?;
+            if response.status() == StatusCode::CONFLICT {
+                session_id = response
+                    .headers()
+                    .get("X-Transmission-Session-Id")
+                    .and_then(|v| v.to_str().ok())
+                    .unwrap_or_default()
+                    .to_string();
+                continue;
+            }
+            let json: Value = response.error_for_status()?.json().await?;
+            if json["result"] != "success" {
+                return Err(format!("Transmission RPC failed: {}", json["result"]).into());
+            }
+            return Ok(json);
+        }
+        Err("Transmission did not accept its session id".into())
+    }
+}
+
+fn qbittorrent_torrents(json: &Value) -> Vec<Torrent> {
+    let Some(torrents) = json.as_array() else {
+        return Vec::new();
+    };
+    torrents
+        .iter()
+        .filter_map(|t| {
+            let state = t["state"].as_str().unwrap_or_default();
+            Some(Torrent {
+                id: t["hash"].as_str()?.to_string(),
+                name: t["name"].as_str().unwrap_or_default().to_string(),
+                content_path: PathBuf::from(t["content_path"].as_str()?),
+                seeding: matches!(state, "uploading" | "stalledUP" | "forcedUP" | "queuedUP"),
+            })
+        })
+        .collect()
+}
+
+fn transmission_torrents(json: &Value) -> Vec<Torrent> {
+    let Some(torrents) = json["arguments"]["torrents"].as_array() else {
+        return Vec::new();
+    };
+    torrents
+        .iter()
+        .filter_map(|t| {
+            let name = t["name"].as_str()?;
+            let status = t["status"].as_i64().unwrap_or_default();
+            Some(Torrent {
+                id: t["id"].as_i64()?.to_string(),
+                name: name.to_string(),
+                content_path: Path::new(t["downloadDir"].as_str()?).join(name),
+                seeding: matches!(status, TRANSMISSION_SEEDING | TRANSMISSION_SEED_QUEUED),
+            })
+        })
+        .collect()
+}
+
+/// Whether `item` may be moved to the trash as far as the torrent client is
+/// concerned, applying `on_seeding` to torrents still seeding it. An
+/// unreachable client does not block trashing.
+pub async fn release(config: &AppConfig, item: &Media, dry_run: bool) -> bool {
+    let Some(client_config) = &config.torrent_client else {
+        return true;
+    };
+    let client = TorrentClient::new(client_config);
+    let seeding: Vec<Torrent> = match client.torrents().await {
+        Ok(torrents) => torrents
+            .into_iter()
+            .filter(|t| t.seeding && t.references(Path::new(&item.path)))
+            .collect(),
+        Err(e) => {
+            tracing::warn!("Could not ask the torrent client about {}: {e}", item.path);
+            return true;
+        }
+    };
+    if seeding.is_empty() {
+        return true;
+    }
+    let names = seeding
+        .iter()
+        .map(|t| t.name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ");
+    match client_config.on_seeding {
+        SeedingAction::Skip => {
+            tracing::info!(
+                event = "trash_deferred",
+                media_id = item.id,
+                path = %item.path,
+                "Deferred trashing {} while it is seeding: {names}",
+                item.path
+            );
+            false
+        }
+        SeedingAction::Warn => {
+            tracing::warn!(
+                event = "seeding",
+                media_id = item.id,
+                path = %item.path,
+                "Trashing {} breaks seeding of {names}",
+                item.path
+            );
+            true
+        }
+        SeedingAction::Remove => {
+            for torrent in &seeding {
+                if dry_run {
+                    tracing::info!("DRY RUN: would remove torrent {}", torrent.name);
+                } else if let Err(e) = client.remove(torrent).await {
+                    tracing::error!("Failed to remove torrent {}: {e}", torrent.name);
+                    return false;
+                } else {
+                    tracing::info!(
+                        event = "torrent_removed",
+                        media_id = item.id,
+                        path = %item.path,
+                        "Removed torrent {} before trashing {}",
+                        torrent.name,
+                        item.path
+                    );
+                }
+            }
+            true
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_seeding_torrents_from_both_clients() {
+        let qbittorrent = serde_json::json!([
+            {"hash": "abc", "name": "Heat", "content_path": "/media/Movies/Heat (1995)", "state": "stalledUP"},
+            {"hash": "def", "name": "Show", "content_path": "/downloads/Show", "state": "downloading"}
+        ]);
+        let torrents = qbittorrent_torrents(&qbittorrent);
+        assert_eq!(torrents.len(), 2);
+        assert!(torrents[0].seeding && !torrents[1].seeding);
+
+        let transmission = serde_json::json!({"result": "success", "arguments": {"torrents": [
+            {"id": 7, "name": "Show S01", "downloadDir": "/media/TV/Show", "status": 6}
+        ]}});
+        let torrents = transmission_torrents(&transmission);
+        assert_eq!(torrents[0].id, "7");
+        assert_eq!(
+            torrents[0].content_path,
+            PathBuf::from("/media/TV/Show/Show S01")
+        );
+        assert!(torrents[0].seeding);
+    }
+
+    #[test]
+    fn torrents_reference_paths_above_and_below_them() {
+        let torrent = Torrent {
+            id: "abc".to_string(),
+            name: "Show".to_string(),
+            content_path: PathBuf::from("/media/TV/Show/Season 1"),
+            seeding: true,
+        };
+        assert!(torrent.references(Path::new("/media/TV/Show/Season 1")));
+        assert!(torrent.references(Path::new("/media/TV/Show")));
+        assert!(!torrent.references(Path::new("/media/TV/Show/Season 2")));
+    }
+}
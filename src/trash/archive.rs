@@ -0,0 +1,198 @@
+//! The archive tier: with an `archive_dir` configured, expired trash is moved
+//! there, e.g. onto a slow external disk, instead of being deleted, and admins
+//! can restore it to the library later.
+
+use sqlx::SqlitePool;
+use std::path::{Path, PathBuf};
+
+use crate::config::AppConfig;
+use crate::mediaserver;
+use crate::models::media::Media;
+use crate::models::{deletion_approval, media, media_history};
+
+/// Where the item from `original_path`, now at `trash_location`, goes in
+/// `archive_dir`: below a folder named after its media dir, under its unique
+/// trash name.
+fn archive_path_for(
+    config: &AppConfig,
+    archive_dir: &Path,
+    original_path: &Path,
+    trash_location: &Path,
+) -> Option<PathBuf> {
+    let media_dir = crate::paths::library_of(config, original_path)?;
+    let relative = crate::paths::relative_to(media_dir, original_path)?;
+    let derived = archive_dir.join(media_dir.file_name()?).join(relative);
+    Some(derived.with_file_name(trash_location.file_name()?))
+}
+
+/// Move `src` to `dst`, copying and then deleting when they are on different
+/// filesystems, as an archive disk usually is.
+fn move_across(src: &Path, dst: &Path) -> std::io::Result<()> {
+    match std::fs::rename(src, dst) {
+        Err(e) if e.kind() == std::io::ErrorKind::CrossesDevices => {
+            copy_all(src, dst)?;
+            super::remove_path(src)
+        }
+        result => result,
+    }
+}
+
+fn copy_all(src: &Path, dst: &Path) -> std::io::Result<()> {
+    if !src.is_dir() {
+        return std::fs::copy(src, dst).map(|_| ());
+    }
+    std::fs::create_dir_all(dst)?;
+    for entry in std::fs::read_dir(src)? {
+        let entry = entry?;
+        copy_all(&entry.path(), &dst.join(entry.file_name()))?;
+    }
+    Ok(())
+}
+
+/// Move `src` to `dst`, creating its parent folder. Copying to another disk
+/// can take a while, so this runs off the async workers.
+pub(crate) async fn move_blocking(src: PathBuf, dst: PathBuf) -> std::io::Result<()> {
+    tokio::task::spawn_blocking(move || {
+        if let Some(parent) = dst.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        move_across(&src, &dst)
+    })
+    .await
+    .map_err(std::io::Error::other)?
+}
+
+/// Move expired trash into `archive_dir` instead of deleting it.
+pub(super) async fn archive_trashed(
+    pool: &SqlitePool,
+    config: &AppConfig,
+    archive_dir: &Path,
+    item: &Media,
+    trash_location: &Path,
+    dry_run: bool,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let dest = archive_path_for(config, archive_dir, Path::new(&item.path), trash_location)
+        .ok_or_else(|| format!("failed to derive archive path for {}", item.path))?;
+    if dry_run {
+        tracing::info!(
+            "DRY RUN: would archive {} → {}",
+            trash_location.display(),
+            dest.display()
+        );
+    } else {
+        if !trash_location.exists() {
+            return Err(format!("{} is no longer in the trash", trash_location.display()).into());
+        }
+        move_blocking(trash_location.to_path_buf(), dest.clone()).await?;
+        mediaserver::refresh_after_move(config, &[trash_location]);
+    }
+    media::set_archived(
+        pool,
+        item.id,
+        media_history::CLEANUP,
+        &dest.to_string_lossy(),
+    )
+    .await?;
+    deletion_approval::clear(pool, item.id).await?;
+    tracing::info!(
+        event = "archive",
+        media_id = item.id,
+        path = %item.path,
+        "Archived {} → {}",
+        item.path,
+        dest.display()
+    );
+    Ok(())
+}
+
+/// Bring an archived item back to its original path, or next to it as for a
+/// rescue when something else sits there now.
+#[tracing::instrument(skip(pool, config))]
+pub async fn restore_from_archive(
+    pool: &SqlitePool,
+    media_id: i64,
+    actor: &str,
+    config: &AppConfig,
+    dry_run: bool,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let item = media::get_by_id(pool, media_id)
+        .await?
+        .ok_or("Media not found")?;
+    if item.status != "archived" {
+        return Err(format!("cannot restore media in status {}", item.status).into());
+    }
+    let archived = PathBuf::from(
+        item.archive_path
+            .as_deref()
+            .ok_or_else(|| format!("no archive path recorded for {}", item.path))?,
+    );
+    let dest = super::rescue_destination(config, Path::new(&item.path))?;
+
+    if dry_run {
+        tracing::info!(
+            "DRY RUN: would restore {} → {}",
+            archived.display(),
+            dest.display()
+        );
+    } else {
+        if !archived.exists() {
+            return Err(format!(
+                "Cannot restore: {} is no longer in the archive",
+                archived.display()
+            )
+            .into());
+        }
+        move_blocking(archived, dest.clone()).await?;
+        mediaserver::refresh_after_move(config, &[&dest]);
+    }
+
+    if dest != Path::new(&item.path) {
+        media::set_path(pool, media_id, &dest.to_string_lossy()).await?;
+    }
+    media::set_active(pool, media_id, actor).await?;
+    tracing::info!(
+        event = "unarchive",
+        media_id,
+        path = %dest.display(),
+        "Restored from the archive: {}",
+        dest.display()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn archive_paths_keep_the_library_layout() {
+        let config: AppConfig = toml::from_str(
+            r#"
+            database_url = "sqlite::memory:"
+            listen_addr = "127.0.0.1:0"
+            media_dirs = ["/media/TV Shows"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            archive_path_for(
+                &config,
+                Path::new("/archive"),
+                Path::new("/media/TV Shows/Lost/Season 1"),
+                Path::new("/media/TV Shows_trash/Lost/Season 1 ~7-1700000000000"),
+            ),
+            Some(PathBuf::from(
+                "/archive/TV Shows/Lost/Season 1 ~7-1700000000000"
+            ))
+        );
+        assert_eq!(
+            archive_path_for(
+                &config,
+                Path::new("/archive"),
+                Path::new("/elsewhere/Heat"),
+                Path::new("/elsewhere_trash/Heat ~1-1"),
+            ),
+            None
+        );
+    }
+}
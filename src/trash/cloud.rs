@@ -0,0 +1,375 @@
+//! The cloud archive: with a `[cloud_archive]` bucket or an `[rclone_archive]`
+//! remote configured, cleanup uploads each expired trash item there before
+//! deleting it, and admins can restore a deleted item from there later.
+//!
+//! Buckets get a tarball that is streamed: a blocking task writes it into
+//! parts of [`PART_SIZE`] that are uploaded as they fill, and restores unpack
+//! the download as it arrives, so neither needs room for a second copy on
+//! disk. rclone copies the files as they are.
+
+use sqlx::SqlitePool;
+use std::io::{Read, Write};
+use std::path::{Component, Path};
+use tokio::sync::mpsc;
+
+use crate::config::AppConfig;
+use crate::mediaserver;
+use crate::models::media;
+use crate::models::media::Media;
+use crate::rclone::{self, RcloneConfig};
+use crate::s3::{CloudArchiveConfig, S3Client};
+
+/// Size of each uploaded part. S3 wants at least 5 MiB for all but the last
+/// and allows [`MAX_PARTS`], so this covers items up to 625 GiB.
+const PART_SIZE: usize = 64 << 20;
+
+/// Most parts S3 accepts for one multipart upload.
+const MAX_PARTS: usize = 10_000;
+
+/// Largest item a bucket upload can hold, tarball headers aside.
+const MAX_UPLOAD_BYTES: i64 = (PART_SIZE * MAX_PARTS) as i64;
+
+/// Collects the tarball into parts and hands each full one to the uploader.
+struct PartWriter {
+    part: Vec<u8>,
+    parts: mpsc::Sender<Vec<u8>>,
+}
+
+impl PartWriter {
+    fn send(&mut self) -> std::io::Result<()> {
+        let part = std::mem::replace(&mut self.part, Vec::with_capacity(PART_SIZE));
+        self.parts
+            .blocking_send(part)
+            .map_err(|_| std::io::Error::other("upload stopped"))
+    }
+}
+
+impl Write for PartWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.part.extend_from_slice(buf);
+        if self.part.len() >= PART_SIZE {
+            self.send()?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Feeds downloaded chunks to the unpacker.
+struct ChunkReader {
+    chunk: Vec<u8>,
+    read: usize,
+    chunks: mpsc::Receiver<Vec<u8>>,
+}
+
+impl Read for ChunkReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.read == self.chunk.len() {
+            match self.chunks.blocking_recv() {
+                Some(chunk) => {
+                    self.chunk = chunk;
+                    self.read = 0;
+                }
+                None => return Ok(0),
+            }
+        }
+        let n = buf.len().min(self.chunk.len() - self.read);
+        buf[..n].copy_from_slice(&self.chunk[self.read..self.read + n]);
+        self.read += n;
+        Ok(n)
+    }
+}
+
+/// Write `src` as a tarball whose entries sit below `name`.
+fn pack(src: &Path, name: &str, writer: &mut PartWriter) -> std::io::Result<()> {
+    let mut builder = tar::Builder::new(&mut *writer);
+    builder.follow_symlinks(false);
+    if src.is_dir() {
+        builder.append_dir_all(name, src)?;
+    } else {
+        builder.append_path_with_name(src, name)?;
+    }
+    builder.finish()?;
+    drop(builder);
+    writer.send()
+}
+
+/// Unpack a tarball written by [`pack`] to `dest`, whatever name the item
+/// had when it was packed.
+fn unpack(reader: impl Read, dest: &Path) -> std::io::Result<()> {
+    let mut archive = tar::Archive::new(reader);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+        let mut components = path.components();
+        components.next();
+        let rest = components.as_path();
+        if !rest.components().all(|c| matches!(c, Component::Normal(_))) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("unexpected path {} in the archive", path.display()),
+            ));
+        }
+        let target = if rest.as_os_str().is_empty() {
+            dest.to_path_buf()
+        } else {
+            dest.join(rest)
+        };
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        entry.unpack(&target)?;
+    }
+    Ok(())
+}
+
+/// Where items are uploaded: a bucket spoken to directly, or an rclone remote.
+enum Backend<'a> {
+    S3(&'a CloudArchiveConfig),
+    Rclone(&'a RcloneConfig),
+}
+
+impl<'a> Backend<'a> {
+    fn configured(config: &'a AppConfig) -> Option<Self> {
+        match (&config.rclone_archive, &config.cloud_archive) {
+            (Some(rclone), _) => Some(Backend::Rclone(rclone)),
+            (None, Some(cloud)) => Some(Backend::S3(cloud)),
+            (None, None) => None,
+        }
+    }
+
+    /// The backend `key` was uploaded with, as far as it is still configured.
+    fn holding(config: &'a AppConfig, key: &str) -> Option<Self> {
+        match &config.rclone_archive {
+            Some(rclone) if rclone.holds(key) => Some(Backend::Rclone(rclone)),
+            _ => config.cloud_archive.as_ref().map(Backend::S3),
+        }
+    }
+}
+
+/// Stream `src` as a tarball into the object `key`.
+async fn upload_to_s3(
+    cloud: &CloudArchiveConfig,
+    key: &str,
+    src: &Path,
+    name: String,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let client = S3Client::new(cloud);
+    let upload_id = client.create_multipart_upload(key).await?;
+    let (tx, mut rx) = mpsc::channel(1);
+    let src = src.to_path_buf();
+    let packer = tokio::task::spawn_blocking(move || {
+        let mut writer = PartWriter {
+            part: Vec::with_capacity(PART_SIZE),
+            parts: tx,
+        };
+        pack(&src, &name, &mut writer)
+    });
+    let uploaded = async {
+        let mut etags = Vec::new();
+        while let Some(part) = rx.recv().await {
+            if etags.len() == MAX_PARTS {
+                return Err(format!("{key} needs more than {MAX_PARTS} parts").into());
+            }
+            let etag = client
+                .upload_part(key, &upload_id, etags.len() + 1, part)
+                .await?;
+            etags.push(etag);
+        }
+        packer.await.map_err(std::io::Error::other)??;
+        client
+            .complete_multipart_upload(key, &upload_id, &etags)
+            .await
+    }
+    .await;
+    if let Err(e) = uploaded {
+        // Stops the packer if it is still writing.
+        drop(rx);
+        if let Err(abort) = client.abort_multipart_upload(key, &upload_id).await {
+            tracing::warn!("Failed to abort the upload to {key}: {abort}");
+        }
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// Stream the object `key` into `dest`, unpacking it as it arrives.
+async fn download_from_s3(
+    cloud: &CloudArchiveConfig,
+    key: &str,
+    dest: &Path,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let mut response = S3Client::new(cloud).get(key).await?;
+    let (tx, rx) = mpsc::channel(4);
+    let target = dest.to_path_buf();
+    let unpacker = tokio::task::spawn_blocking(move || {
+        let reader = ChunkReader {
+            chunk: Vec::new(),
+            read: 0,
+            chunks: rx,
+        };
+        unpack(reader, &target)
+    });
+    let downloaded = async {
+        while let Some(chunk) = response.chunk().await? {
+            if tx.send(chunk.to_vec()).await.is_err() {
+                // The unpacker gave up; its error says why.
+                break;
+            }
+        }
+        drop(tx);
+        Ok::<_, reqwest::Error>(())
+    }
+    .await;
+    let unpacked = unpacker.await.map_err(std::io::Error::other)?;
+    downloaded
+        .map_err(|e| e.to_string())
+        .and(unpacked.map_err(|e| e.to_string()))
+        .map_err(Into::into)
+}
+
+/// Upload `trash_location` to the cloud archive and record its key on the
+/// item, before cleanup deletes it.
+pub(super) async fn upload_trashed(
+    pool: &SqlitePool,
+    config: &AppConfig,
+    item: &Media,
+    trash_location: &Path,
+    dry_run: bool,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let backend = Backend::configured(config).ok_or("no cloud archive is configured")?;
+    let name = trash_location
+        .file_name()
+        .ok_or_else(|| format!("{} has no file name", trash_location.display()))?
+        .to_string_lossy()
+        .to_string();
+    let key = match backend {
+        Backend::S3(cloud) => S3Client::new(cloud).key_for(&format!("{}-{name}.tar", item.id)),
+        Backend::Rclone(rclone) => rclone.path_for(&format!("{}-{name}", item.id)),
+    };
+    if matches!(backend, Backend::S3(_)) && item.size_bytes > MAX_UPLOAD_BYTES {
+        return Err(format!(
+            "{} is {} bytes, more than the {MAX_UPLOAD_BYTES} a bucket upload can hold",
+            item.path, item.size_bytes
+        )
+        .into());
+    }
+    if dry_run {
+        tracing::info!(
+            "DRY RUN: would upload {} to {key}",
+            trash_location.display()
+        );
+    } else {
+        if !trash_location.exists() {
+            return Err(format!("{} is no longer in the trash", trash_location.display()).into());
+        }
+        match backend {
+            Backend::S3(cloud) => upload_to_s3(cloud, &key, trash_location, name).await?,
+            Backend::Rclone(rclone) => {
+                rclone::copy(rclone, &trash_location.to_string_lossy(), &key).await?
+            }
+        }
+    }
+    media::set_cloud_key(pool, item.id, &key).await?;
+    tracing::info!(
+        event = "cloud_upload",
+        media_id = item.id,
+        path = %item.path,
+        key = %key,
+        "Uploaded {} to the cloud archive as {key}",
+        item.path
+    );
+    Ok(())
+}
+
+/// Download a deleted item from the cloud archive back to its original
+/// path, or next to it as for a rescue when something else sits there now.
+#[tracing::instrument(skip(pool, config))]
+pub async fn restore_from_cloud(
+    pool: &SqlitePool,
+    media_id: i64,
+    actor: &str,
+    config: &AppConfig,
+    dry_run: bool,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let item = media::get_by_id(pool, media_id)
+        .await?
+        .ok_or("Media not found")?;
+    if item.status != "gone" {
+        return Err(format!("cannot restore media in status {}", item.status).into());
+    }
+    let key = item
+        .cloud_key
+        .as_deref()
+        .ok_or_else(|| format!("{} was not uploaded to the cloud archive", item.path))?;
+    let backend = Backend::holding(config, key)
+        .ok_or_else(|| format!("no cloud archive holding {key} is configured"))?;
+    let dest = super::rescue_destination(config, Path::new(&item.path))?;
+
+    if dry_run {
+        tracing::info!("DRY RUN: would download {key} → {}", dest.display());
+    } else {
+        let restored = match backend {
+            Backend::S3(cloud) => download_from_s3(cloud, key, &dest).await,
+            Backend::Rclone(rclone) => rclone::copy(rclone, key, &dest.to_string_lossy()).await,
+        };
+        if let Err(e) = restored {
+            if dest.exists() {
+                super::remove_path(&dest)?;
+            }
+            return Err(format!("Failed to restore {key}: {e}").into());
+        }
+        mediaserver::refresh_after_move(config, &[&dest]);
+    }
+
+    if dest != Path::new(&item.path) {
+        media::set_path(pool, media_id, &dest.to_string_lossy()).await?;
+    }
+    media::set_active(pool, media_id, actor).await?;
+    tracing::info!(
+        event = "cloud_restore",
+        media_id,
+        path = %dest.display(),
+        key = %key,
+        "Restored from the cloud archive: {}",
+        dest.display()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tarballs_unpack_under_a_new_name() {
+        let base = tempfile::tempdir().unwrap();
+        let item = base.path().join("Heat (1995) ~7-1700000000000");
+        std::fs::create_dir_all(item.join("Extras")).unwrap();
+        std::fs::write(item.join("heat.mkv"), b"heat").unwrap();
+        std::fs::write(item.join("Extras").join("trailer.mkv"), b"trailer").unwrap();
+
+        let (tx, mut rx) = mpsc::channel(16);
+        let mut writer = PartWriter {
+            part: Vec::new(),
+            parts: tx,
+        };
+        pack(&item, "Heat (1995) ~7-1700000000000", &mut writer).unwrap();
+        drop(writer);
+        let mut tarball = Vec::new();
+        while let Ok(part) = rx.try_recv() {
+            tarball.extend(part);
+        }
+
+        let dest = base.path().join("Heat (1995) (restored)");
+        unpack(tarball.as_slice(), &dest).unwrap();
+        assert_eq!(std::fs::read(dest.join("heat.mkv")).unwrap(), b"heat");
+        assert_eq!(
+            std::fs::read(dest.join("Extras").join("trailer.mkv")).unwrap(),
+            b"trailer"
+        );
+    }
+}
@@ -1,11 +1,22 @@
+pub mod archive;
+pub mod cloud;
+pub mod queue;
+pub mod series;
+
 use sqlx::SqlitePool;
 use std::path::{Path, PathBuf};
 
-use crate::config::AppConfig;
-use crate::models::{mark, media};
+use crate::config::{AppConfig, RescueConflict, SpecialsPolicy};
+use crate::mediaserver::{self, MediaServer};
+use crate::models::media::Media;
+use crate::models::{
+    deletion_approval, expiry_warning, keep_vote, mark, media, media_history, pending_trash,
+    persistent, tag, trash_queue,
+};
+use crate::{manifest, policies, torrent};
 
 pub fn trash_path_for(media_dir: &Path, trash_dir: &Path, original_path: &Path) -> Option<PathBuf> {
-    let relative = original_path.strip_prefix(media_dir).ok()?;
+    let relative = crate::paths::relative_to(media_dir, original_path)?;
     Some(trash_dir.join(relative))
 }
 
@@ -13,27 +24,60 @@ fn move_path(src: &Path, dst: &Path) -> std::io::Result<()> {
     std::fs::rename(src, dst)
 }
 
+/// Delete a trashed item, which is a folder or a bare movie file.
+fn remove_path(path: &Path) -> std::io::Result<()> {
+    if path.is_dir() {
+        std::fs::remove_dir_all(path)
+    } else {
+        std::fs::remove_file(path)
+    }
+}
+
+#[tracing::instrument(skip(pool, config))]
 pub async fn move_to_trash(
     pool: &SqlitePool,
     media_id: i64,
+    actor: &str,
     config: &AppConfig,
     dry_run: bool,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let item = move_item(pool, media_id, actor, config, dry_run).await?;
+    if item.media_type == "tv_season" && item.season != Some(0) {
+        trash_folded_specials(pool, &item.title, actor, config, dry_run).await?;
+    }
+    Ok(())
+}
+
+/// With `specials = "fold"`, trash the specials of the show `title` once
+/// none of its regular seasons are left in the library.
+async fn trash_folded_specials(
+    pool: &SqlitePool,
+    title: &str,
+    actor: &str,
+    config: &AppConfig,
+    dry_run: bool,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if config.specials != SpecialsPolicy::Fold {
+        return Ok(());
+    }
+    for id in media::orphaned_specials(pool, title).await? {
+        move_item(pool, id, actor, config, dry_run).await?;
+    }
+    Ok(())
+}
+
+async fn move_item(
+    pool: &SqlitePool,
+    media_id: i64,
+    actor: &str,
+    config: &AppConfig,
+    dry_run: bool,
+) -> Result<Media, Box<dyn std::error::Error + Send + Sync>> {
     let item = media::get_by_id(pool, media_id)
         .await?
         .ok_or("Media not found")?;
     let original_path = Path::new(&item.path);
-    let media_dir = config
-        .media_dirs
-        .iter()
-        .filter(|dir| original_path.starts_with(dir))
-        .max_by_key(|dir| dir.components().count())
-        .ok_or_else(|| format!("no matching media_dir configured for path {}", item.path))?;
-    let trash_dir = AppConfig::trash_dir_for_media_dir(media_dir)
-        .ok_or_else(|| format!("no matching media_dir configured for path {}", item.path))?;
-
-    let dest = trash_path_for(media_dir, &trash_dir, original_path)
-        .ok_or_else(|| format!("failed to derive trash path for {}", item.path))?;
+    let dest = trash_destination(config, &item)?;
 
     if dry_run {
         tracing::info!("DRY RUN: would move {} → {}", item.path, dest.display());
@@ -45,18 +89,66 @@ pub async fn move_to_trash(
 
         // Move to trash; fall back to copy+delete for cross-device moves
         move_path(original_path, &dest)?;
+        mediaserver::refresh_after_move(config, &[original_path, &dest]);
 
-        tracing::info!("Moved to trash: {} → {}", item.path, dest.display());
+        tracing::info!(
+            event = "trash",
+            media_id,
+            path = %item.path,
+            "Moved to trash: {} → {}",
+            item.path,
+            dest.display()
+        );
     }
 
-    media::set_trashed(pool, media_id).await?;
+    media::set_trashed_to(pool, media_id, actor, Some(&dest.to_string_lossy())).await?;
 
-    Ok(())
+    Ok(item)
+}
+
+/// Where in the trash `item` is moved to, next to where its library's
+/// layout puts it.
+fn trash_destination(
+    config: &AppConfig,
+    item: &Media,
+) -> Result<PathBuf, Box<dyn std::error::Error + Send + Sync>> {
+    let original_path = Path::new(&item.path);
+    let media_dir = crate::paths::library_of(config, original_path)
+        .ok_or_else(|| format!("no matching media_dir configured for path {}", item.path))?;
+    let trash_dir = AppConfig::trash_dir_for_media_dir(media_dir)
+        .ok_or_else(|| format!("no matching media_dir configured for path {}", item.path))?;
+
+    let derived = trash_path_for(media_dir, &trash_dir, original_path)
+        .ok_or_else(|| format!("failed to derive trash path for {}", item.path))?;
+    let trash_name = unique_trash_name(original_path, item.id, jiff::Timestamp::now())
+        .ok_or_else(|| format!("failed to derive trash path for {}", item.path))?;
+    Ok(derived.with_file_name(&trash_name))
+}
+
+/// Name for `original_path` in the trash, suffixed with the media id and the
+/// time so trashing the same path twice never collides, e.g.
+/// `Heat (1995) ~42-1700000000000` or `Heat ~42-1700000000000.mkv` for bare
+/// files.
+fn unique_trash_name(original_path: &Path, media_id: i64, at: jiff::Timestamp) -> Option<String> {
+    let suffix = format!(" ~{media_id}-{}", at.as_millisecond());
+    let name = original_path.file_name()?.to_string_lossy();
+    if original_path.is_file() {
+        if let (Some(stem), Some(ext)) = (original_path.file_stem(), original_path.extension()) {
+            return Some(format!(
+                "{}{suffix}.{}",
+                stem.to_string_lossy(),
+                ext.to_string_lossy()
+            ));
+        }
+    }
+    Some(format!("{name}{suffix}"))
 }
 
+#[tracing::instrument(skip(pool, config))]
 pub async fn rescue_from_trash(
     pool: &SqlitePool,
     media_id: i64,
+    actor: &str,
     config: &AppConfig,
     dry_run: bool,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
@@ -64,30 +156,24 @@ pub async fn rescue_from_trash(
         .await?
         .ok_or("Media not found")?;
     let original_path = Path::new(&item.path);
-    let media_dir = config
-        .media_dirs
-        .iter()
-        .filter(|dir| original_path.starts_with(dir))
-        .max_by_key(|dir| dir.components().count())
-        .ok_or_else(|| format!("no matching media_dir configured for path {}", item.path))?;
-    let trash_dir = AppConfig::trash_dir_for_media_dir(media_dir)
-        .ok_or_else(|| format!("no matching media_dir configured for path {}", item.path))?;
-
-    let trash_location = trash_path_for(media_dir, &trash_dir, original_path)
+    let trash_location = trash_location_for(config, &item)
         .ok_or_else(|| format!("failed to derive trash path for {}", item.path))?;
 
+    let dest = rescue_destination(config, original_path)?;
+
     if dry_run {
         tracing::info!(
             "DRY RUN: would rescue {} → {}",
             trash_location.display(),
-            item.path
+            dest.display()
         );
     } else if trash_location.exists() {
         // Ensure parent directory exists
-        if let Some(parent) = original_path.parent() {
+        if let Some(parent) = dest.parent() {
             std::fs::create_dir_all(parent)?;
         }
-        move_path(&trash_location, original_path)?;
+        move_path(&trash_location, &dest)?;
+        mediaserver::refresh_after_move(config, &[&trash_location, &dest]);
     } else {
         return Err(format!(
             "Cannot rescue: file no longer exists in trash at {}",
@@ -96,123 +182,587 @@ pub async fn rescue_from_trash(
         .into());
     }
 
-    media::set_active(pool, media_id).await?;
+    if dest != original_path {
+        media::set_path(pool, media_id, &dest.to_string_lossy()).await?;
+    }
+    media::set_active(pool, media_id, actor).await?;
     mark::clear_marks(pool, media_id).await?;
-    tracing::info!("Rescued from trash: {}", item.path);
+    deletion_approval::clear(pool, media_id).await?;
+    tracing::info!(
+        event = "rescue",
+        media_id,
+        path = %dest.display(),
+        "Rescued from trash: {}",
+        dest.display()
+    );
 
     Ok(())
 }
 
+/// Where an item from `original_path` is restored to: that path, or with
+/// `rescue_conflict = "sibling"` a free one next to it when it is occupied.
+fn rescue_destination(
+    config: &AppConfig,
+    original_path: &Path,
+) -> Result<PathBuf, Box<dyn std::error::Error + Send + Sync>> {
+    if !original_path.exists() {
+        return Ok(original_path.to_path_buf());
+    }
+    match config.rescue_conflict {
+        RescueConflict::Fail => Err(format!(
+            "Cannot rescue: {} is occupied by something else",
+            original_path.display()
+        )
+        .into()),
+        RescueConflict::Sibling => restored_sibling(original_path).ok_or_else(|| {
+            format!(
+                "failed to derive a free path next to {}",
+                original_path.display()
+            )
+            .into()
+        }),
+    }
+}
+
+/// First free "<name> (restored)" path next to `original_path`, counting up
+/// as "(restored 2)" and so on; bare files keep their extension.
+fn restored_sibling(original_path: &Path) -> Option<PathBuf> {
+    let is_file = original_path.is_file();
+    let stem = if is_file {
+        original_path.file_stem()?
+    } else {
+        original_path.file_name()?
+    }
+    .to_string_lossy();
+    let extension = original_path
+        .extension()
+        .filter(|_| is_file)
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+    (1..100).find_map(|n| {
+        let label = if n == 1 {
+            "(restored)".to_string()
+        } else {
+            format!("(restored {n})")
+        };
+        let candidate = original_path.with_file_name(format!("{stem} {label}{extension}"));
+        (!candidate.exists()).then_some(candidate)
+    })
+}
+
+#[tracing::instrument(skip(pool, config))]
 pub async fn cleanup_expired(
     pool: &SqlitePool,
     config: &AppConfig,
     grace_period_days: u64,
     dry_run: bool,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let expired = media::list_expired_trash(pool, grace_period_days).await?;
+    let mut expired = Vec::new();
+    for item in media::list_expired_trash(pool, grace_period_days).await? {
+        if !policies::extends_grace(pool, config, &item, grace_period_days).await? {
+            expired.push(item);
+        }
+    }
 
+    let mut deleted = 0;
+    let mut archived = 0;
     for item in &expired {
-        let original_path = Path::new(&item.path);
-        let Some(media_dir) = config
-            .media_dirs
-            .iter()
-            .filter(|dir| original_path.starts_with(dir))
-            .max_by_key(|dir| dir.components().count())
-        else {
+        let Some(trash_location) = trash_location_for(config, item) else {
             tracing::warn!(
                 "Skipping cleanup for {}: no matching media_dir configured",
                 item.path
             );
             continue;
         };
-        let Some(trash_dir) = AppConfig::trash_dir_for_media_dir(media_dir) else {
-            tracing::warn!(
-                "Skipping cleanup for {}: cannot derive trash dir",
-                item.path
+        if let Some(archive_dir) = &config.archive_dir {
+            match archive::archive_trashed(
+                pool,
+                config,
+                archive_dir,
+                item,
+                &trash_location,
+                dry_run,
+            )
+            .await
+            {
+                Ok(()) => archived += 1,
+                Err(e) => tracing::error!("Failed to archive {}: {e}", trash_location.display()),
+            }
+            continue;
+        }
+        if needs_approval(config, item)
+            && deletion_approval::approval_count(pool, item.id).await?
+                < deletion_approval::REQUIRED_APPROVALS
+        {
+            deletion_approval::request(pool, item.id, media_history::CLEANUP).await?;
+            tracing::info!(
+                event = "approval_pending",
+                media_id = item.id,
+                path = %item.path,
+                "Keeping {} in the trash until two admins approve deleting it",
+                trash_location.display()
             );
             continue;
-        };
-        let Some(trash_location) = trash_path_for(media_dir, &trash_dir, original_path) else {
+        }
+        let linked = hardlinked_files(&trash_location);
+        if linked > 0 {
+            if config.keep_hardlinked_trash {
+                tracing::warn!(
+                    event = "hardlinked",
+                    media_id = item.id,
+                    path = %item.path,
+                    "Keeping {} in the trash until an admin deletes it: {linked} files are hardlinked elsewhere",
+                    trash_location.display()
+                );
+                continue;
+            }
             tracing::warn!(
-                "Skipping cleanup for {}: cannot derive trash location",
-                item.path
+                event = "hardlinked",
+                media_id = item.id,
+                path = %item.path,
+                "Deleting {} frees no space for {linked} files hardlinked elsewhere",
+                trash_location.display()
             );
-            continue;
-        };
-        if dry_run {
-            tracing::info!("DRY RUN: would delete {}", trash_location.display());
-        } else if trash_location.exists() {
-            if let Err(e) = std::fs::remove_dir_all(&trash_location) {
-                tracing::error!("Failed to delete {}: {e}", trash_location.display());
+        }
+        if config.cloud_archive.is_some() || config.rclone_archive.is_some() {
+            if let Err(e) =
+                cloud::upload_trashed(pool, config, item, &trash_location, dry_run).await
+            {
+                tracing::error!(
+                    "Keeping {} in the trash, failed to upload it to the cloud archive: {e}",
+                    trash_location.display()
+                );
                 continue;
             }
         }
-        media::set_gone(pool, item.id).await?;
-        tracing::info!("Permanently deleted: {}", item.path);
+        if let Err(e) = delete_trashed(
+            pool,
+            config,
+            item,
+            &trash_location,
+            media_history::CLEANUP,
+            dry_run,
+        )
+        .await
+        {
+            tracing::error!("Failed to delete {}: {e}", trash_location.display());
+            continue;
+        }
+        deleted += 1;
     }
 
-    if !expired.is_empty() {
-        tracing::info!("Cleaned up {} expired trash items", expired.len());
+    if deleted > 0 {
+        tracing::info!("Cleaned up {deleted} expired trash items");
+    }
+    if archived > 0 {
+        tracing::info!("Archived {archived} expired trash items");
     }
 
     Ok(())
 }
 
-/// Mark trashed items as gone if their files were manually removed from the trash dir.
-pub async fn cleanup_missing_trash(
+/// Permanently delete a trashed item now, whatever its grace period.
+async fn delete_now(
     pool: &SqlitePool,
+    media_id: i64,
+    actor: &str,
     config: &AppConfig,
+    dry_run: bool,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let trashed = media::list_trashed(pool).await?;
+    let item = media::get_by_id(pool, media_id)
+        .await?
+        .ok_or("Media not found")?;
+    if item.status != "trashed" {
+        return Err(format!("cannot delete media in status {}", item.status).into());
+    }
+    let trash_location = trash_location_for(config, &item)
+        .ok_or_else(|| format!("failed to derive trash path for {}", item.path))?;
+    delete_trashed(pool, config, &item, &trash_location, actor, dry_run).await
+}
 
-    for item in &trashed {
-        let original_path = Path::new(&item.path);
-        let Some(media_dir) = config
-            .media_dirs
-            .iter()
-            .filter(|dir| original_path.starts_with(dir))
-            .max_by_key(|dir| dir.components().count())
+/// Warn the users who did not mark a trashed item once it is within
+/// `expiry_warning_days` of being deleted. Returns how many users were warned.
+pub async fn warn_before_expiry(
+    pool: &SqlitePool,
+    config: &AppConfig,
+) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+    let Some(warning_days) = config.expiry_warning_days else {
+        return Ok(0);
+    };
+    let now = jiff::Timestamp::now()
+        .to_zoned(jiff::tz::TimeZone::UTC)
+        .datetime();
+    let mut warned = 0;
+    for item in media::list_trashed(pool).await? {
+        let Some(trashed_at) = item
+            .trashed_at
+            .as_deref()
+            .and_then(|t| t.parse::<jiff::civil::DateTime>().ok())
         else {
-            tracing::warn!(
-                "Skipping missing-trash check for {}: no matching media_dir configured",
-                item.path
-            );
             continue;
         };
-        let Some(trash_dir) = AppConfig::trash_dir_for_media_dir(media_dir) else {
-            tracing::warn!(
-                "Skipping missing-trash check for {}: cannot derive trash dir",
+        let tags = tag::for_media(pool, item.id).await?;
+        let (grace_days, _) = policies::grace_period_for(config, &tags);
+        let deletes_at =
+            trashed_at.checked_add(jiff::SignedDuration::from_hours(24 * grace_days as i64))?;
+        let warn_from =
+            deletes_at.checked_sub(jiff::SignedDuration::from_hours(24 * warning_days as i64))?;
+        if now < warn_from || now >= deletes_at {
+            continue;
+        }
+        let users = expiry_warning::warn_non_markers(
+            pool,
+            item.id,
+            &deletes_at.strftime("%Y-%m-%d %H:%M:%S").to_string(),
+        )
+        .await?;
+        if users > 0 {
+            tracing::info!(
+                event = "expiry_warning",
+                media_id = item.id,
+                path = %item.path,
+                users,
+                "Warned {users} users that {} is deleted soon",
                 item.path
             );
-            continue;
+            warned += users;
+        }
+    }
+    Ok(warned)
+}
+
+/// Whether deleting `item` permanently needs two admins to approve it.
+pub fn needs_approval(config: &AppConfig, item: &Media) -> bool {
+    config
+        .deletion_approval_gb
+        .is_some_and(|gb| item.size_bytes > gb as i64 * config.size_units.gigabyte())
+}
+
+/// An admin's request to delete a trashed item now. Items that need approval
+/// are queued with the request as the first approval and deleted once a
+/// second admin approves; returns whether the item was deleted.
+#[tracing::instrument(skip(pool, config))]
+pub async fn approve_deletion(
+    pool: &SqlitePool,
+    media_id: i64,
+    user_id: i64,
+    actor: &str,
+    config: &AppConfig,
+    dry_run: bool,
+) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+    let item = media::get_by_id(pool, media_id)
+        .await?
+        .ok_or("Media not found")?;
+    if item.status != "trashed" {
+        return Err(format!("cannot delete media in status {}", item.status).into());
+    }
+    if needs_approval(config, &item) {
+        deletion_approval::request(pool, media_id, actor).await?;
+        let approvals = deletion_approval::approve(pool, media_id, user_id).await?;
+        tracing::info!(
+            event = "approve_deletion",
+            media_id,
+            user = %actor,
+            "{actor} approved deleting {} ({approvals} of {})",
+            item.path,
+            deletion_approval::REQUIRED_APPROVALS
+        );
+        if approvals < deletion_approval::REQUIRED_APPROVALS {
+            return Ok(false);
+        }
+    }
+    delete_now(pool, media_id, actor, config, dry_run).await?;
+    Ok(true)
+}
+
+async fn delete_trashed(
+    pool: &SqlitePool,
+    config: &AppConfig,
+    item: &Media,
+    trash_location: &Path,
+    actor: &str,
+    dry_run: bool,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if dry_run {
+        tracing::info!("DRY RUN: would delete {}", trash_location.display());
+    } else {
+        // A deletion the manifest cannot record does not happen.
+        if let Some(dir) = &config.deletion_manifest_dir {
+            manifest::record(pool, dir, item, actor).await?;
+        }
+        if trash_location.exists() {
+            remove_path(trash_location)?;
+            mediaserver::refresh_after_move(config, &[trash_location]);
+        }
+    }
+    media::set_gone(pool, item.id, actor).await?;
+    deletion_approval::clear(pool, item.id).await?;
+    tracing::info!(
+        event = "delete",
+        media_id = item.id,
+        path = %item.path,
+        "Permanently deleted: {}",
+        item.path
+    );
+    Ok(())
+}
+
+/// Where `item` sits while it is in the trash: the recorded trash path, or
+/// for items trashed before it was recorded, the path derived from config.
+pub fn trash_location_for(config: &AppConfig, item: &Media) -> Option<PathBuf> {
+    if let Some(trash_path) = &item.trash_path {
+        return Some(PathBuf::from(trash_path));
+    }
+    let original_path = Path::new(&item.path);
+    let media_dir = crate::paths::library_of(config, original_path)?;
+    let trash_dir = AppConfig::trash_dir_for_media_dir(media_dir)?;
+    let derived = trash_path_for(media_dir, &trash_dir, original_path)?;
+    Some(match &item.trash_name {
+        Some(name) => derived.with_file_name(name),
+        None => derived,
+    })
+}
+
+/// Files at or below `path` whose data is linked from elsewhere too, e.g. a
+/// torrent client's seeding directory, so deleting them frees no space.
+pub fn hardlinked_files(path: &Path) -> usize {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+
+        let Ok(metadata) = std::fs::symlink_metadata(path) else {
+            return 0;
         };
-        let Some(trash_location) = trash_path_for(media_dir, &trash_dir, original_path) else {
+        if metadata.is_file() {
+            return usize::from(metadata.nlink() > 1);
+        }
+        if !metadata.is_dir() {
+            return 0;
+        }
+        std::fs::read_dir(path)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .map(|entry| hardlinked_files(&entry.path()))
+                    .sum()
+            })
+            .unwrap_or(0)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        0
+    }
+}
+
+/// Mark trashed items as gone if their files were manually removed from the trash dir.
+pub async fn cleanup_missing_trash(
+    pool: &SqlitePool,
+    config: &AppConfig,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let trashed = media::list_trashed(pool).await?;
+
+    for item in &trashed {
+        let Some(trash_location) = trash_location_for(config, item) else {
             tracing::warn!(
-                "Skipping missing-trash check for {}: cannot derive trash location",
+                "Skipping missing-trash check for {}: no matching media_dir configured",
                 item.path
             );
             continue;
         };
         if !trash_location.exists() {
-            media::set_gone(pool, item.id).await?;
+            media::set_gone(pool, item.id, media_history::CLEANUP).await?;
             mark::clear_marks(pool, item.id).await?;
-            tracing::info!("Trashed item missing from disk, marked gone: {}", item.path);
+            tracing::info!(
+                event = "gone",
+                media_id = item.id,
+                path = %item.path,
+                "Trashed item missing from disk, marked gone: {}",
+                item.path
+            );
         }
     }
 
     Ok(())
 }
 
+/// Trash `media_id` once every user has marked it, unless it is protected;
+/// `actor` is whoever marked it last. With `trash_approval` the item is queued
+/// for an admin to [`confirm_trash`] instead, and with a `trash_window` for the
+/// [`queue`] worker.
+///
+/// In maintenance mode, while a user's keep vote holds, while the configured
+/// media server is playing the item, or while a torrent is still seeding it and `on_seeding` is "skip",
+/// the move is deferred and left to [`trash_deferred`] on a later cleanup run.
 pub async fn check_and_trash(
     pool: &SqlitePool,
     media_id: i64,
+    actor: &str,
     config: &AppConfig,
     dry_run: bool,
 ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
-    if mark::all_users_marked(pool, media_id).await? {
-        move_to_trash(pool, media_id, config, dry_run).await?;
-        Ok(true)
-    } else {
-        Ok(false)
+    let Some(item) = media::get_by_id(pool, media_id).await? else {
+        return Ok(false);
+    };
+    if !due_now(pool, &item, actor, config).await? {
+        return Ok(false);
+    }
+    trash_unless_busy(pool, &item, actor, config, dry_run).await
+}
+
+/// Whether everyone marked `item` and it is to be moved right away. Items
+/// that wait for `trash_approval` or the `trash_window` are queued instead.
+async fn due_now(
+    pool: &SqlitePool,
+    item: &Media,
+    actor: &str,
+    config: &AppConfig,
+) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+    let media_id = item.id;
+    if item.protected || !mark::all_users_marked(pool, media_id, config.require_admin_mark).await? {
+        return Ok(false);
+    }
+    // The next cleanup run moves it to its persisted show.
+    if persistent::awaits_series_persist(pool, media_id).await? {
+        return Ok(false);
+    }
+    if config.trash_approval {
+        if pending_trash::request(pool, media_id, actor).await? {
+            tracing::info!(
+                event = "trash_pending",
+                media_id,
+                path = %item.path,
+                "{} waits for an admin to confirm trashing it",
+                item.path
+            );
+        }
+        return Ok(false);
+    }
+    if config.trash_window.is_some() {
+        if trash_queue::enqueue(pool, media_id, actor).await? {
+            tracing::info!(
+                event = "trash_queued",
+                media_id,
+                path = %item.path,
+                "Queued trashing {} for the trash window",
+                item.path
+            );
+        }
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+/// Trash an item from the confirmation queue. Returns false when the move was
+/// deferred, in which case it stays queued.
+pub async fn confirm_trash(
+    pool: &SqlitePool,
+    media_id: i64,
+    actor: &str,
+    config: &AppConfig,
+    dry_run: bool,
+) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+    let item = media::get_by_id(pool, media_id)
+        .await?
+        .ok_or("Media not found")?;
+    if !pending_trash::is_pending(pool, media_id).await? {
+        return Err(format!("{} is not waiting to be trashed", item.title).into());
+    }
+    if item.status != "active"
+        || item.protected
+        || !mark::all_users_marked(pool, media_id, config.require_admin_mark).await?
+    {
+        pending_trash::clear(pool, media_id).await?;
+        return Err(format!("{} is no longer marked by everyone", item.title).into());
+    }
+    trash_unless_busy(pool, &item, actor, config, dry_run).await
+}
+
+async fn trash_unless_busy(
+    pool: &SqlitePool,
+    item: &Media,
+    actor: &str,
+    config: &AppConfig,
+    dry_run: bool,
+) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+    if !free_to_move(pool, item, config, dry_run).await? {
+        return Ok(false);
+    }
+    move_to_trash(pool, item.id, actor, config, dry_run).await?;
+    pending_trash::clear(pool, item.id).await?;
+    trash_queue::remove(pool, item.id).await?;
+    Ok(true)
+}
+
+/// Whether nothing holds off moving `item` now: maintenance mode, a keep
+/// vote, playback or a seeding torrent. Releases the torrent if configured to.
+async fn free_to_move(
+    pool: &SqlitePool,
+    item: &Media,
+    config: &AppConfig,
+    dry_run: bool,
+) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+    let media_id = item.id;
+    if config.maintenance {
+        tracing::info!(
+            event = "trash_deferred",
+            media_id,
+            path = %item.path,
+            "Deferred trashing {} in maintenance mode",
+            item.path
+        );
+        return Ok(false);
+    }
+    if keep_vote::is_kept(pool, media_id, config.keep_vote_days).await? {
+        tracing::info!(
+            event = "trash_deferred",
+            media_id,
+            path = %item.path,
+            "Deferred trashing {} while a user votes to keep it",
+            item.path
+        );
+        return Ok(false);
+    }
+    if is_playing(config, &item.path).await {
+        tracing::info!(
+            event = "trash_deferred",
+            media_id,
+            path = %item.path,
+            "Deferred trashing {} while it is playing",
+            item.path
+        );
+        return Ok(false);
+    }
+    Ok(torrent::release(config, item, dry_run).await)
+}
+
+/// Whether the configured media server is playing something under `path`.
+/// An unreachable server does not block trashing.
+async fn is_playing(config: &AppConfig, path: &str) -> bool {
+    let Some(server) = &config.media_server else {
+        return false;
+    };
+    match MediaServer::new(server).is_playing(Path::new(path)).await {
+        Ok(playing) => playing,
+        Err(e) => {
+            tracing::warn!("Could not ask the media server about playback of {path}: {e}");
+            false
+        }
+    }
+}
+
+/// Trash items whose move was deferred, e.g. because they were playing or
+/// kept; returns how many were moved.
+pub async fn trash_deferred(
+    pool: &SqlitePool,
+    config: &AppConfig,
+    dry_run: bool,
+) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+    let mut moved = 0;
+    for media_id in mark::media_ids_with_all_marked(pool, config.require_admin_mark).await? {
+        if check_and_trash(pool, media_id, media_history::CLEANUP, config, dry_run).await? {
+            moved += 1;
+        }
     }
+    Ok(moved)
 }
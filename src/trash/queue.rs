@@ -0,0 +1,146 @@
+//! Trash moves queued by the final mark and carried out in batches by a
+//! background worker, inside an off-peak `trash_window` when one is set.
+
+use serde::Deserialize;
+use sqlx::SqlitePool;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::config::{AppConfig, SharedConfig};
+use crate::dry_run::DryRun;
+use crate::models::{mark, media, trash_queue};
+use crate::shutdown::MoveTracker;
+
+/// How often the worker looks at the queue.
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Daily stretch of local time, e.g. "01:00-05:00"; may wrap past midnight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct TrashWindow {
+    start: jiff::civil::Time,
+    end: jiff::civil::Time,
+}
+
+impl TrashWindow {
+    pub fn contains(&self, time: jiff::civil::Time) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+impl FromStr for TrashWindow {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid trash_window {s:?}, expected e.g. \"01:00-05:00\"");
+        let (start, end) = s.split_once('-').ok_or_else(invalid)?;
+        let parse = |t: &str| jiff::civil::Time::strptime("%H:%M", t.trim()).map_err(|_| invalid());
+        let window = Self {
+            start: parse(start)?,
+            end: parse(end)?,
+        };
+        if window.start == window.end {
+            return Err(invalid());
+        }
+        Ok(window)
+    }
+}
+
+impl TryFrom<String> for TrashWindow {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl std::fmt::Display for TrashWindow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}-{}",
+            self.start.strftime("%H:%M"),
+            self.end.strftime("%H:%M")
+        )
+    }
+}
+
+/// Whether queued moves may run now.
+fn in_window(config: &AppConfig) -> bool {
+    config
+        .trash_window
+        .is_none_or(|w| w.contains(jiff::Zoned::now().time()))
+}
+
+/// Carry out up to `trash_batch_size` queued moves; returns how many items
+/// were trashed. Items no longer marked by everyone leave the queue, deferred
+/// ones stay for the next batch.
+pub async fn run_batch(
+    pool: &SqlitePool,
+    config: &AppConfig,
+    dry_run: bool,
+) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+    let mut moved = 0;
+    for queued in trash_queue::next_batch(pool, config.trash_batch_size as i64).await? {
+        let Some(item) = media::get_by_id(pool, queued.media_id).await? else {
+            continue;
+        };
+        if item.status != "active"
+            || item.protected
+            || !mark::all_users_marked(pool, item.id, config.require_admin_mark).await?
+        {
+            trash_queue::remove(pool, item.id).await?;
+            continue;
+        }
+        if super::trash_unless_busy(pool, &item, &queued.queued_by, config, dry_run).await? {
+            moved += 1;
+        }
+    }
+    Ok(moved)
+}
+
+/// Run queued moves whenever the trash window is open.
+pub fn start_worker(pool: SqlitePool, config: SharedConfig, dry_run: DryRun, moves: MoveTracker) {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(POLL_INTERVAL).await;
+            let config = config.current();
+            if config.maintenance || !in_window(&config) {
+                continue;
+            }
+            let Some(_guard) = moves.begin() else {
+                break;
+            };
+            match run_batch(&pool, &config, dry_run.get()).await {
+                Ok(0) => {}
+                Ok(moved) => tracing::info!("Trashed {moved} queued items"),
+                Err(e) => tracing::error!("Queued trash batch failed: {e}"),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn windows_may_wrap_past_midnight() {
+        let night: TrashWindow = "23:00-05:00".parse().unwrap();
+        assert!(night.contains(jiff::civil::time(23, 30, 0, 0)));
+        assert!(night.contains(jiff::civil::time(4, 59, 0, 0)));
+        assert!(!night.contains(jiff::civil::time(12, 0, 0, 0)));
+
+        let day: TrashWindow = "09:00-17:00".parse().unwrap();
+        assert!(day.contains(jiff::civil::time(9, 0, 0, 0)));
+        assert!(!day.contains(jiff::civil::time(17, 0, 0, 0)));
+        assert_eq!(day.to_string(), "09:00-17:00");
+
+        assert!("9-5".parse::<TrashWindow>().is_err());
+        assert!("05:00-05:00".parse::<TrashWindow>().is_err());
+    }
+}
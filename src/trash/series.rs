@@ -0,0 +1,245 @@
+//! Marking and trashing every season of a series as one batch, so a failure
+//! halfway never leaves half a show in the trash.
+
+use sqlx::SqlitePool;
+use std::path::Path;
+
+use super::{due_now, free_to_move, move_path, trash_destination, trash_folded_specials};
+use crate::config::AppConfig;
+use crate::mediaserver;
+use crate::models::trash_journal::{self, JournalEntry};
+use crate::models::{mark, media, media_history, pending_trash, trash_queue};
+
+/// Mark every season in `season_ids` for `user_id`, then trash the seasons
+/// everyone has now marked; returns the ids that were moved.
+///
+/// The marks are written in one transaction, and quorum is checked per
+/// season. Seasons waiting for approval, the trash window or a deferral go
+/// their usual way. The rest are moved together: every move is journaled
+/// before the first one starts, and if one fails the seasons already moved
+/// are put back and the error returned. A batch cut short by a crash is
+/// rolled back by [`recover`] on the next start.
+pub async fn mark_series(
+    pool: &SqlitePool,
+    user_id: i64,
+    season_ids: &[i64],
+    actor: &str,
+    config: &AppConfig,
+    dry_run: bool,
+) -> Result<Vec<i64>, Box<dyn std::error::Error + Send + Sync>> {
+    mark::mark_all(pool, user_id, season_ids).await?;
+
+    let mut batch = Vec::new();
+    let mut title = None;
+    for &id in season_ids {
+        let Some(item) = media::get_by_id(pool, id).await? else {
+            continue;
+        };
+        title.get_or_insert_with(|| item.title.clone());
+        if due_now(pool, &item, actor, config).await?
+            && free_to_move(pool, &item, config, dry_run).await?
+        {
+            batch.push(JournalEntry {
+                media_id: id,
+                dest: trash_destination(config, &item)?
+                    .to_string_lossy()
+                    .into_owned(),
+                src: item.path,
+            });
+        }
+    }
+    if batch.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    if dry_run {
+        for entry in &batch {
+            tracing::info!("DRY RUN: would move {} → {}", entry.src, entry.dest);
+        }
+    } else {
+        trash_journal::record(pool, &batch).await?;
+        for (done, entry) in batch.iter().enumerate() {
+            if let Err(e) = move_entry(entry) {
+                let stranded = abandon(pool, &batch, done).await?;
+                return Err(if stranded == 0 {
+                    format!(
+                        "failed to move {} to the trash, put the series back: {e}",
+                        entry.src
+                    )
+                } else {
+                    format!(
+                        "failed to move {} to the trash, and {stranded} seasons could not be \
+                         put back; they are moved back on the next start: {e}",
+                        entry.src
+                    )
+                }
+                .into());
+            }
+        }
+    }
+
+    // The seasons become trashed and the journal goes away in one step.
+    let mut tx = pool.begin().await?;
+    let mut changed = Vec::new();
+    for entry in &batch {
+        if media::set_trashed_on(&mut tx, entry.media_id, actor, Some(&entry.dest)).await? {
+            changed.push(entry.media_id);
+        }
+        trash_journal::remove(&mut *tx, entry.media_id).await?;
+    }
+    tx.commit().await?;
+    media_history::announce(&changed, "trashed", actor);
+
+    let mut moved = Vec::new();
+    for entry in batch {
+        pending_trash::clear(pool, entry.media_id).await?;
+        trash_queue::remove(pool, entry.media_id).await?;
+        if !dry_run {
+            mediaserver::refresh_after_move(
+                config,
+                &[Path::new(&entry.src), Path::new(&entry.dest)],
+            );
+            tracing::info!(
+                event = "trash",
+                media_id = entry.media_id,
+                path = %entry.src,
+                "Moved to trash: {} → {}",
+                entry.src,
+                entry.dest
+            );
+        }
+        moved.push(entry.media_id);
+    }
+    if let Some(title) = title {
+        trash_folded_specials(pool, &title, actor, config, dry_run).await?;
+    }
+    Ok(moved)
+}
+
+fn move_entry(entry: &JournalEntry) -> std::io::Result<()> {
+    let dest = Path::new(&entry.dest);
+    if let Some(parent) = dest.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    move_path(Path::new(&entry.src), dest)
+}
+
+/// Give up on `batch` after its first `moved` entries went to the trash: put
+/// them back and clear the journal, except for seasons that could not be put
+/// back, which [`recover`] retries. Returns how many those are.
+async fn abandon(
+    pool: &SqlitePool,
+    batch: &[JournalEntry],
+    moved: usize,
+) -> Result<usize, sqlx::Error> {
+    let stranded = roll_back(&batch[..moved]);
+    for entry in batch {
+        if !stranded.contains(&entry.media_id) {
+            trash_journal::remove(pool, entry.media_id).await?;
+        }
+    }
+    Ok(stranded.len())
+}
+
+/// Put the seasons of a failed batch back where they were, last one first;
+/// returns the ids of those still in the trash.
+fn roll_back(moved: &[JournalEntry]) -> Vec<i64> {
+    let mut stranded = Vec::new();
+    for entry in moved.iter().rev() {
+        match move_path(Path::new(&entry.dest), Path::new(&entry.src)) {
+            Ok(()) => tracing::info!("Moved {} back from the trash", entry.src),
+            Err(e) => {
+                tracing::error!("Failed to move {} back from {}: {e}", entry.src, entry.dest);
+                stranded.push(entry.media_id);
+            }
+        }
+    }
+    stranded
+}
+
+/// Roll back batches that were cut short, e.g. by a crash between the moves
+/// and recording them; returns how many items were moved back.
+pub async fn recover(pool: &SqlitePool) -> Result<usize, sqlx::Error> {
+    let mut restored = 0;
+    for entry in trash_journal::list(pool).await? {
+        let (src, dest) = (Path::new(&entry.src), Path::new(&entry.dest));
+        if dest.exists() && src.exists() {
+            // Something took the season's place; moving back would clobber it.
+            tracing::error!(
+                "{} is still in the trash at {}, but its place is taken; move it back by hand",
+                entry.src,
+                entry.dest
+            );
+            continue;
+        }
+        if dest.exists() {
+            match move_path(dest, src) {
+                Ok(()) => {
+                    tracing::warn!("Moved {} back from an interrupted trash batch", entry.src);
+                    restored += 1;
+                }
+                Err(e) => {
+                    tracing::error!("Failed to move {} back from {}: {e}", entry.src, entry.dest);
+                    continue;
+                }
+            }
+        }
+        trash_journal::remove(pool, entry.media_id).await?;
+    }
+    Ok(restored)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{self, PoolSettings};
+
+    #[tokio::test]
+    async fn seasons_that_cannot_be_put_back_stay_journaled() {
+        let pool = db::init_pool(
+            "sqlite::memory:",
+            &PoolSettings {
+                max_connections: 1,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let mut batch = Vec::new();
+        for season in 1..=3 {
+            let src = dir.path().join(format!("Lost/Season {season}"));
+            let dest = dir.path().join(format!("trash/Season {season}"));
+            let id = media::upsert(
+                &pool,
+                "tv_season",
+                "Lost",
+                None,
+                Some(season),
+                &src.to_string_lossy(),
+                0,
+            )
+            .await
+            .unwrap();
+            batch.push(JournalEntry {
+                media_id: id,
+                src: src.to_string_lossy().into_owned(),
+                dest: dest.to_string_lossy().into_owned(),
+            });
+        }
+        trash_journal::record(&pool, &batch).await.unwrap();
+        // Seasons 1 and 2 went to the trash, then season 3 failed. Season 1
+        // cannot go back: something took its place in the meantime.
+        for entry in &batch[..2] {
+            std::fs::create_dir_all(Path::new(&entry.dest).join("e1")).unwrap();
+        }
+        std::fs::create_dir_all(Path::new(&batch[0].src).join("new")).unwrap();
+        std::fs::create_dir_all(dir.path().join("Lost")).unwrap();
+
+        assert_eq!(abandon(&pool, &batch, 2).await.unwrap(), 1);
+        assert!(Path::new(&batch[1].src).join("e1").exists());
+        let journal = trash_journal::list(&pool).await.unwrap();
+        assert_eq!(journal.len(), 1);
+        assert_eq!(journal[0].media_id, batch[0].media_id);
+    }
+}
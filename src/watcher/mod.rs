@@ -1,77 +1,443 @@
-use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use notify::event::{AccessKind, AccessMode, MetadataKind, ModifyKind};
+use notify::{Event, EventKind, PollWatcher, RecommendedWatcher, RecursiveMode, Watcher};
 use sqlx::SqlitePool;
-use std::path::PathBuf;
-use std::sync::Arc;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 
-use crate::models::media;
-use crate::scanner;
+use crate::config::{AppConfig, SharedConfig};
+use crate::scanner::{self, ScanMode, ScanStatus};
 
-pub async fn start(
-    pool: SqlitePool,
-    media_dirs: Vec<PathBuf>,
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let (tx, mut rx) = mpsc::channel::<Event>(100);
+/// Upper bound on how long pending changes wait, as a multiple of the debounce window.
+const MAX_WAIT_FACTOR: u32 = 10;
 
-    let mut watcher = RecommendedWatcher::new(
-        move |res: Result<Event, notify::Error>| {
-            if let Ok(event) = res {
-                let _ = tx.blocking_send(event);
-            }
-        },
-        notify::Config::default(),
+/// Map a changed path to the top-level entry (movie or show folder) of the
+/// media dir that contains it. Changes to the media dir itself map to nothing.
+pub fn top_level_entry(media_dirs: &[PathBuf], path: &Path) -> Option<PathBuf> {
+    let media_dir = crate::paths::deepest_dir(media_dirs, path)?;
+    let first = crate::paths::relative_to(media_dir, path)?
+        .components()
+        .next()?;
+    Some(media_dir.join(first))
+}
+
+/// What a filesystem event means for the library.
+#[derive(Debug, PartialEq, Eq)]
+enum Change {
+    /// Entries appeared, disappeared or were renamed: rescan the top-level entry.
+    Structure,
+    /// File contents changed: recompute the containing item's size.
+    Contents,
+}
+
+fn classify(kind: &EventKind) -> Option<Change> {
+    match kind {
+        EventKind::Create(_) | EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(_)) => {
+            Some(Change::Structure)
+        }
+        EventKind::Modify(ModifyKind::Data(_))
+        | EventKind::Modify(ModifyKind::Any)
+        | EventKind::Modify(ModifyKind::Metadata(MetadataKind::WriteTime))
+        | EventKind::Access(AccessKind::Close(AccessMode::Write)) => Some(Change::Contents),
+        _ => None,
+    }
+}
+
+#[derive(Default)]
+struct Pending {
+    entries: HashSet<PathBuf>,
+    changed_files: HashSet<PathBuf>,
+    since: Option<Instant>,
+}
+
+impl Pending {
+    fn is_empty(&self) -> bool {
+        self.entries.is_empty() && self.changed_files.is_empty()
+    }
+}
+
+/// Delay before the first restart attempt after the watcher fails.
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+/// Cap for the exponential restart backoff.
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+/// A watcher that stayed up this long is considered recovered; backoff resets.
+const STABLE_AFTER: Duration = Duration::from_secs(600);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WatcherStatus {
+    Starting,
+    Running,
+    Restarting,
+}
+
+impl WatcherStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            WatcherStatus::Starting => "starting",
+            WatcherStatus::Running => "running",
+            WatcherStatus::Restarting => "restarting",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct HealthSnapshot {
+    pub status: WatcherStatus,
+    pub restarts: u32,
+    pub last_error: Option<String>,
+}
+
+/// Shared view of the filesystem watcher's state, shown on the admin dashboard.
+#[derive(Clone)]
+pub struct WatcherHealth(Arc<Mutex<HealthSnapshot>>);
+
+impl Default for WatcherHealth {
+    fn default() -> Self {
+        Self(Arc::new(Mutex::new(HealthSnapshot {
+            status: WatcherStatus::Starting,
+            restarts: 0,
+            last_error: None,
+        })))
+    }
+}
+
+impl WatcherHealth {
+    pub fn snapshot(&self) -> HealthSnapshot {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    fn update(&self, f: impl FnOnce(&mut HealthSnapshot)) {
+        f(&mut self.0.lock().unwrap_or_else(|e| e.into_inner()));
+    }
+}
+
+/// The notify backends for all media dirs. Dropping this stops watching.
+struct Watchers {
+    _native: RecommendedWatcher,
+    _poller: PollWatcher,
+}
+
+fn create_watchers(
+    config: &AppConfig,
+    tx: &mpsc::Sender<notify::Result<Event>>,
+) -> notify::Result<Watchers> {
+    let tx = tx.clone();
+    let handler = move |res: notify::Result<Event>| {
+        let _ = tx.blocking_send(res);
+    };
+    let mut native = RecommendedWatcher::new(handler.clone(), notify::Config::default())?;
+    // inotify never sees changes made by other machines on network shares, so
+    // those dirs are polled instead.
+    let mut poller = PollWatcher::new(
+        handler,
+        notify::Config::default()
+            .with_poll_interval(Duration::from_secs(config.poll_interval_secs)),
     )?;
 
-    for dir in &media_dirs {
-        if dir.exists() {
-            watcher.watch(dir, RecursiveMode::NonRecursive)?;
-            tracing::info!("Watching directory: {}", dir.display());
-        } else {
+    for dir in &config.media_dirs {
+        if !dir.exists() {
             tracing::warn!(
                 "Media directory does not exist, skipping watch: {}",
                 dir.display()
             );
+            continue;
+        }
+        if config.poll_dirs.contains(dir) {
+            poller.watch(dir, RecursiveMode::Recursive)?;
+            tracing::info!(
+                "Polling directory every {}s: {}",
+                config.poll_interval_secs,
+                dir.display()
+            );
+        } else {
+            native.watch(dir, RecursiveMode::Recursive)?;
+            tracing::info!("Watching directory: {}", dir.display());
         }
     }
 
-    let media_dirs = Arc::new(media_dirs);
+    Ok(Watchers {
+        _native: native,
+        _poller: poller,
+    })
+}
+
+pub async fn start(
+    pool: SqlitePool,
+    shared_config: SharedConfig,
+    health: WatcherHealth,
+    scan_status: ScanStatus,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let mut config_rx = shared_config.subscribe();
+    let mut config = config_rx.borrow_and_update().clone();
+    let (tx, mut rx) = mpsc::channel::<notify::Result<Event>>(100);
+
+    // Kept alive for as long as the task runs; replaced on restart.
+    let mut _watchers = create_watchers(&config, &tx)?;
+    health.update(|h| h.status = WatcherStatus::Running);
+    let mut running_since = Instant::now();
+    let mut backoff = INITIAL_BACKOFF;
 
     tokio::spawn(async move {
-        // Keep watcher alive
-        let _watcher = watcher;
-
-        while let Some(event) = rx.recv().await {
-            match event.kind {
-                EventKind::Create(_) => {
-                    for path in &event.paths {
-                        if path.is_dir() {
-                            if let Some(parent) = path.parent() {
-                                let parent_buf = parent.to_path_buf();
-                                if media_dirs.contains(&parent_buf) {
-                                    tracing::info!("New directory detected: {}", path.display());
-                                    if let Err(e) =
-                                        scanner::scan_directory(&pool, parent, None).await
-                                    {
-                                        tracing::error!("Error scanning after create: {e}");
-                                    }
-                                }
+        let mut pending = Pending::default();
+
+        loop {
+            let debounce = Duration::from_secs(config.watch_debounce_secs);
+            // A steady stream of writes (e.g. a download) must not postpone the flush forever.
+            let max_wait = debounce * MAX_WAIT_FACTOR;
+            // Coalesce bursts: once something is pending, flush after a quiet window.
+            let quiet = async {
+                match pending.since {
+                    Some(since) => {
+                        let wait = debounce.min(max_wait.saturating_sub(since.elapsed()));
+                        tokio::time::sleep(wait).await
+                    }
+                    None => std::future::pending().await,
+                }
+            };
+
+            let next = tokio::select! {
+                next = rx.recv() => next,
+                _ = quiet => {
+                    flush(&pool, &scan_status, &mut pending).await;
+                    continue;
+                }
+                Ok(()) = config_rx.changed() => {
+                    let new = config_rx.borrow_and_update().clone();
+                    let retarget = watch_targets_changed(&config, &new);
+                    let resumed = config.maintenance && !new.maintenance;
+                    if new.maintenance {
+                        pending = Default::default();
+                    }
+                    config = new;
+                    if resumed && !retarget {
+                        rescan_all(&pool, &config, &scan_status, "after maintenance").await;
+                    }
+                    if retarget {
+                        flush(&pool, &scan_status, &mut pending).await;
+                        drop(_watchers);
+                        _watchers = match create_watchers(&config, &tx) {
+                            Ok(watchers) => watchers,
+                            Err(e) => {
+                                tracing::error!("Failed to watch reloaded media dirs: {e}");
+                                health.update(|h| {
+                                    h.status = WatcherStatus::Restarting;
+                                    h.last_error = Some(e.to_string());
+                                });
+                                restart(&config, &tx, &health, &mut backoff).await
                             }
-                        }
+                        };
+                        running_since = Instant::now();
+                        tracing::info!("Filesystem watcher now follows the reloaded media dirs");
+                        while rx.try_recv().is_ok() {}
+                        rescan_all(&pool, &config, &scan_status, "after config reload").await;
                     }
+                    continue;
                 }
-                EventKind::Remove(_) => {
-                    for path in &event.paths {
-                        let path_str = path.to_string_lossy().to_string();
-                        tracing::info!("Directory removed: {path_str}");
-                        if let Err(e) = media::mark_gone_by_path(&pool, &path_str).await {
-                            tracing::error!("Error marking gone: {e}");
-                        }
+            };
+
+            let event = match next {
+                Some(Ok(event)) => match removed_media_dir(&config.media_dirs, &event) {
+                    // inotify silently drops the watch when its root goes away (e.g. unmount).
+                    Some(dir) => Err(format!("media dir removed: {}", dir.display())),
+                    None => Ok(event),
+                },
+                Some(Err(e)) => Err(e.to_string()),
+                None => break,
+            };
+            let event = match event {
+                Ok(event) => event,
+                Err(reason) => {
+                    tracing::error!("Filesystem watcher failed: {reason}");
+                    if running_since.elapsed() >= STABLE_AFTER {
+                        backoff = INITIAL_BACKOFF;
                     }
+                    // Drop the broken watchers before re-creating them.
+                    drop(_watchers);
+                    health.update(|h| {
+                        h.status = WatcherStatus::Restarting;
+                        h.last_error = Some(reason);
+                    });
+                    _watchers = restart(&config, &tx, &health, &mut backoff).await;
+                    running_since = Instant::now();
+
+                    // Changes made while the watcher was down were missed; the
+                    // rescan covers anything still queued from the old watcher.
+                    while rx.try_recv().is_ok() {}
+                    pending = Pending::default();
+                    rescan_all(&pool, &config, &scan_status, "after watcher restart").await;
+                    continue;
                 }
-                _ => {}
+            };
+            // Maintenance ignores changes; leaving it rescans everything.
+            if config.maintenance {
+                continue;
+            }
+            let Some(change) = classify(&event.kind) else {
+                continue;
+            };
+            for path in &event.paths {
+                let Some(entry) = top_level_entry(&config.media_dirs, path) else {
+                    continue;
+                };
+                match change {
+                    Change::Structure => pending.entries.insert(entry),
+                    Change::Contents => pending.changed_files.insert(path.clone()),
+                };
+            }
+            if !pending.is_empty() && pending.since.is_none() {
+                pending.since = Some(Instant::now());
             }
         }
     });
 
     Ok(())
 }
+
+/// Whether the reloaded config watches a different set of dirs or in a different way.
+fn watch_targets_changed(old: &AppConfig, new: &AppConfig) -> bool {
+    old.media_dirs != new.media_dirs
+        || old.poll_dirs != new.poll_dirs
+        || old.poll_interval_secs != new.poll_interval_secs
+}
+
+async fn rescan_all(pool: &SqlitePool, config: &AppConfig, scan_status: &ScanStatus, why: &str) {
+    if config.maintenance {
+        tracing::info!("Skipping rescan {why} in maintenance mode");
+        return;
+    }
+    if let Err(e) = scanner::full_scan(
+        pool,
+        &config.media_dirs,
+        None,
+        ScanMode::Incremental,
+        scan_status,
+    )
+    .await
+    {
+        tracing::error!("Rescan {why} failed: {e}");
+    }
+}
+
+fn removed_media_dir<'a>(media_dirs: &'a [PathBuf], event: &Event) -> Option<&'a PathBuf> {
+    if !matches!(event.kind, EventKind::Remove(_)) {
+        return None;
+    }
+    media_dirs
+        .iter()
+        .find(|dir| event.paths.iter().any(|p| p == *dir))
+}
+
+/// Re-create the watchers, retrying with exponential backoff until it works.
+async fn restart(
+    config: &AppConfig,
+    tx: &mpsc::Sender<notify::Result<Event>>,
+    health: &WatcherHealth,
+    backoff: &mut Duration,
+) -> Watchers {
+    loop {
+        tracing::warn!("Restarting filesystem watcher in {}s", backoff.as_secs());
+        tokio::time::sleep(*backoff).await;
+        *backoff = (*backoff * 2).min(MAX_BACKOFF);
+        match create_watchers(config, tx) {
+            Ok(watchers) => {
+                health.update(|h| {
+                    h.status = WatcherStatus::Running;
+                    h.restarts += 1;
+                });
+                tracing::info!("Filesystem watcher restarted");
+                return watchers;
+            }
+            Err(e) => {
+                tracing::error!("Failed to restart filesystem watcher: {e}");
+                health.update(|h| h.last_error = Some(e.to_string()));
+            }
+        }
+    }
+}
+
+async fn flush(pool: &SqlitePool, scan_status: &ScanStatus, pending: &mut Pending) {
+    pending.since = None;
+    let rescanned: Vec<PathBuf> = pending.entries.drain().collect();
+    for entry in &rescanned {
+        tracing::info!("Change detected, rescanning: {}", entry.display());
+        if let Err(e) = scanner::rescan_entry(pool, entry, None, scan_status).await {
+            tracing::error!("Error rescanning {}: {e}", entry.display());
+        }
+    }
+
+    for path in pending.changed_files.drain() {
+        // A rescan above already refreshed sizes for everything under that entry.
+        if rescanned.iter().any(|entry| path.starts_with(entry)) {
+            continue;
+        }
+        if let Err(e) = scanner::refresh_size(pool, &path).await {
+            tracing::error!("Error refreshing size for {}: {e}", path.display());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn top_level_entry_for_nested_path() {
+        let dirs = vec![PathBuf::from("/media/TV Shows")];
+        let entry = top_level_entry(
+            &dirs,
+            Path::new("/media/TV Shows/The Office/Season 03/ep01.mkv"),
+        );
+        assert_eq!(entry, Some(PathBuf::from("/media/TV Shows/The Office")));
+    }
+
+    #[test]
+    fn top_level_entry_ignores_media_dir_itself_and_outside_paths() {
+        let dirs = vec![PathBuf::from("/media/Movies")];
+        assert_eq!(top_level_entry(&dirs, Path::new("/media/Movies")), None);
+        assert_eq!(
+            top_level_entry(&dirs, Path::new("/media/Movies_trash/Old (2001)")),
+            None
+        );
+    }
+
+    #[test]
+    fn classify_separates_structure_from_contents() {
+        use notify::event::{CreateKind, DataChange, RenameMode};
+
+        assert_eq!(
+            classify(&EventKind::Create(CreateKind::Folder)),
+            Some(Change::Structure)
+        );
+        assert_eq!(
+            classify(&EventKind::Modify(ModifyKind::Name(RenameMode::Both))),
+            Some(Change::Structure)
+        );
+        assert_eq!(
+            classify(&EventKind::Modify(ModifyKind::Data(DataChange::Size))),
+            Some(Change::Contents)
+        );
+        assert_eq!(
+            classify(&EventKind::Access(AccessKind::Close(AccessMode::Write))),
+            Some(Change::Contents)
+        );
+        assert_eq!(
+            classify(&EventKind::Access(AccessKind::Close(AccessMode::Read))),
+            None
+        );
+    }
+
+    #[test]
+    fn removal_of_media_dir_root_is_detected() {
+        use notify::event::RemoveKind;
+
+        let dirs = vec![PathBuf::from("/media/Movies")];
+        let root = Event::new(EventKind::Remove(RemoveKind::Folder))
+            .add_path(PathBuf::from("/media/Movies"));
+        let child = Event::new(EventKind::Remove(RemoveKind::Folder))
+            .add_path(PathBuf::from("/media/Movies/Old (2001)"));
+        assert_eq!(removed_media_dir(&dirs, &root), Some(&dirs[0]));
+        assert_eq!(removed_media_dir(&dirs, &child), None);
+    }
+}
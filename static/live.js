@@ -0,0 +1,34 @@
+// Keeps the library pages in sync with changes made by other users.
+(function () {
+    var script = document.currentScript;
+    var source = new EventSource(script.dataset.events);
+    var notified = false;
+
+    function notify(message) {
+        if (notified) return;
+        notified = true;
+        var toast = document.createElement("div");
+        toast.className = "toast toast-info";
+        toast.textContent = message + " ";
+        var reload = document.createElement("a");
+        reload.href = window.location.href;
+        reload.textContent = "Reload";
+        toast.appendChild(reload);
+        document.getElementById("toast-container").appendChild(toast);
+    }
+
+    source.addEventListener("media", function (e) {
+        var event = JSON.parse(e.data);
+        var element = document.getElementById("media-" + event.media_id);
+        if (element) {
+            // Re-render the card; it comes back empty if the item left our view.
+            htmx.trigger(element, "refresh");
+        } else if (event.status === "active") {
+            notify("New items are available.");
+        }
+    });
+
+    source.addEventListener("resync", function () {
+        notify("This page may be out of date.");
+    });
+})();
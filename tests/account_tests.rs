@@ -0,0 +1,161 @@
+mod common;
+
+use axum::http::StatusCode;
+use tower::ServiceExt;
+
+use common::*;
+
+#[tokio::test]
+async fn dates_follow_the_users_format_and_timezone() {
+    let pool = test_pool().await;
+    let config = test_config(vec![]);
+    let (user_id, _) = create_test_user(&pool, "alice", false).await;
+    let cookie = login_cookie(&pool, user_id).await;
+    let heat = insert_movie(&pool, "Heat", "/movies/Heat (1995)").await;
+    sqlx::query("UPDATE media SET first_seen = '2024-03-31 12:05:00' WHERE id = ?")
+        .bind(heat)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let app = test_app(pool.clone(), config.clone(), true);
+    let body = body_string(
+        app.oneshot(get_with_cookie(&format!("/media/{heat}"), &cookie))
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert!(body.contains("2024-03-31 12:05"));
+
+    let app = test_app(pool.clone(), config.clone(), true);
+    let response = app
+        .oneshot(post_form_with_cookie(
+            "/account",
+            "locale=de-DE&timezone=Europe%2FBerlin",
+            &cookie,
+        ))
+        .await
+        .unwrap();
+    assert_redirect(&response, "/account?saved=true").await;
+
+    let app = test_app(pool, config, true);
+    let body = body_string(
+        app.oneshot(get_with_cookie(&format!("/media/{heat}"), &cookie))
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert!(body.contains("31.03.2024 14:05"));
+}
+
+#[tokio::test]
+async fn account_rejects_unknown_timezone() {
+    let pool = test_pool().await;
+    let config = test_config(vec![]);
+    let (user_id, _) = create_test_user(&pool, "alice", false).await;
+    let cookie = login_cookie(&pool, user_id).await;
+
+    let app = test_app(pool.clone(), config, true);
+    let response = app
+        .oneshot(post_form_with_cookie(
+            "/account",
+            "locale=iso&timezone=Mars%2FOlympus",
+            &cookie,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let u = rewinder::models::user::get_by_id(&pool, user_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(u.timezone, None);
+}
+
+#[tokio::test]
+async fn saved_preferences_apply_to_a_bare_list_url() {
+    let pool = test_pool().await;
+    let config = test_config(vec![]);
+    let (user_id, _) = create_test_user(&pool, "alice", false).await;
+    let cookie = login_cookie(&pool, user_id).await;
+    for (title, first_seen) in [
+        ("Alien", "2024-01-01 00:00:00"),
+        ("Brazil", "2024-02-01 00:00:00"),
+        ("Casino", "2024-03-01 00:00:00"),
+    ] {
+        let id = insert_movie(&pool, title, &format!("/movies/{title}")).await;
+        sqlx::query("UPDATE media SET first_seen = ? WHERE id = ?")
+            .bind(first_seen)
+            .bind(id)
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+
+    let app = test_app(pool.clone(), config.clone(), true);
+    let response = app
+        .oneshot(post_form_with_cookie(
+            "/account/preferences",
+            "sort=added&sort_dir=desc&page_size=24&theme=light",
+            &cookie,
+        ))
+        .await
+        .unwrap();
+    assert_redirect(&response, "/account?saved=true").await;
+    rewinder::models::preferences::save(
+        &pool,
+        user_id,
+        &rewinder::models::preferences::Preferences {
+            page_size: 2,
+            ..rewinder::models::preferences::get(&pool, user_id)
+                .await
+                .unwrap()
+        },
+    )
+    .await
+    .unwrap();
+
+    let app = test_app(pool.clone(), config.clone(), true);
+    let body = body_string(
+        app.oneshot(get_with_cookie("/movies", &cookie))
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert!(body.contains(r#"data-theme="light""#));
+    let casino = body.find("Casino").unwrap();
+    let brazil = body.find("Brazil").unwrap();
+    assert!(casino < brazil);
+    assert!(!body.contains("Alien"));
+    assert!(body.contains("Page 1 of 2"));
+
+    // Explicit query parameters still win over the saved defaults.
+    let app = test_app(pool, config, true);
+    let body = body_string(
+        app.oneshot(get_with_cookie("/movies?sort=name&dir=asc", &cookie))
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert!(body.contains("Alien"));
+    assert!(!body.contains("Casino"));
+}
+
+#[tokio::test]
+async fn preferences_reject_unknown_values() {
+    let pool = test_pool().await;
+    let config = test_config(vec![]);
+    let (user_id, _) = create_test_user(&pool, "alice", false).await;
+    let cookie = login_cookie(&pool, user_id).await;
+
+    let app = test_app(pool, config, true);
+    let response = app
+        .oneshot(post_form_with_cookie(
+            "/account/preferences",
+            "sort=name&sort_dir=asc&page_size=7&theme=dark",
+            &cookie,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
@@ -106,6 +106,42 @@ async fn admin_trash_page() {
     assert_eq!(response.status(), StatusCode::OK);
 }
 
+#[tokio::test]
+async fn admin_trash_page_shows_time_until_deletion() {
+    let pool = test_pool().await;
+    let mut config = test_config(vec![]);
+    config.grace_period_days = 7;
+    let (admin_id, _) = create_test_user(&pool, "admin", true).await;
+    let cookie = login_cookie(&pool, admin_id).await;
+    let heat = insert_movie(&pool, "Heat", "/movies/Heat (1995)").await;
+    let alien = insert_movie(&pool, "Alien", "/movies/Alien (1979)").await;
+    sqlx::query(
+        "UPDATE media SET status = 'trashed', trashed_at = datetime('now', '-2 days', '-1 hour')
+         WHERE id = ?",
+    )
+    .bind(heat)
+    .execute(&pool)
+    .await
+    .unwrap();
+    sqlx::query(
+        "UPDATE media SET status = 'trashed', trashed_at = datetime('now', '-8 days') WHERE id = ?",
+    )
+    .bind(alien)
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let app = test_app(pool, config, true);
+    let body = body_string(
+        app.oneshot(get_with_cookie("/admin/trash", &cookie))
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert!(body.contains("deleted in 4 days"), "{body}");
+    assert!(body.contains("deleted at the next cleanup"));
+}
+
 #[tokio::test]
 async fn admin_rescue_from_trash() {
     let pool = test_pool().await;
@@ -115,7 +151,7 @@ async fn admin_rescue_from_trash() {
 
     // Insert and trash a movie
     let movie_id = insert_movie(&pool, "Old Movie", "/movies/Old Movie (2010)").await;
-    rewinder::models::media::set_trashed(&pool, movie_id)
+    rewinder::models::media::set_trashed(&pool, movie_id, "admin")
         .await
         .unwrap();
 
@@ -140,3 +176,1636 @@ async fn admin_rescue_from_trash() {
         "/admin/trash"
     );
 }
+
+#[tokio::test]
+async fn admin_dashboard_shows_watcher_health() {
+    let pool = test_pool().await;
+    let config = test_config(vec![]);
+    let (admin_id, _) = create_test_user(&pool, "admin", true).await;
+    let cookie = login_cookie(&pool, admin_id).await;
+
+    let app = test_app(pool, config, true);
+    let response = app
+        .oneshot(get_with_cookie("/admin", &cookie))
+        .await
+        .unwrap();
+
+    let body = body_string(response).await;
+    assert!(body.contains("File Watcher"));
+    assert!(body.contains("0 restarts"));
+}
+
+#[tokio::test]
+async fn scan_status_endpoint_reports_json() {
+    let pool = test_pool().await;
+    let config = test_config(vec![]);
+    let (admin_id, _) = create_test_user(&pool, "admin", true).await;
+    let cookie = login_cookie(&pool, admin_id).await;
+
+    let app = test_app(pool, config, true);
+    let response = app
+        .oneshot(get_with_cookie("/admin/scan/status", &cookie))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = body_string(response).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(json["running"], false);
+    assert_eq!(json["items_processed"], 0);
+}
+
+#[tokio::test]
+async fn scan_status_requires_admin() {
+    let pool = test_pool().await;
+    let config = test_config(vec![]);
+    let (user_id, _) = create_test_user(&pool, "alice", false).await;
+    let cookie = login_cookie(&pool, user_id).await;
+
+    let app = test_app(pool, config, true);
+    let response = app
+        .oneshot(get_with_cookie("/admin/scan/status", &cookie))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+}
+
+#[tokio::test]
+async fn library_scan_rejects_unknown_index() {
+    let pool = test_pool().await;
+    let config = test_config(vec![]);
+    let (admin_id, _) = create_test_user(&pool, "admin", true).await;
+    let cookie = login_cookie(&pool, admin_id).await;
+
+    let app = test_app(pool, config, true);
+    let response = app
+        .oneshot(post_form_with_cookie("/admin/scan/library/9", "", &cookie))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn refresh_media_updates_card() {
+    let pool = test_pool().await;
+    let base = tempfile::tempdir().unwrap();
+    let movies = base.path().join("Movies");
+    let movie = movies.join("Heat (1995)");
+    std::fs::create_dir_all(&movie).unwrap();
+    std::fs::write(movie.join("movie.mkv"), vec![0u8; 2 * 1_048_576]).unwrap();
+    let config = test_config(vec![movies]);
+    let (admin_id, _) = create_test_user(&pool, "admin", true).await;
+    let cookie = login_cookie(&pool, admin_id).await;
+    let id = insert_movie(&pool, "Heat", &movie.to_string_lossy()).await;
+
+    let app = test_app(pool.clone(), config, true);
+    let response = app
+        .oneshot(post_form_with_cookie(
+            &format!("/admin/media/{id}/refresh"),
+            "",
+            &cookie,
+        ))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = body_string(response).await;
+    assert!(body.contains(&format!("media-{id}")));
+    assert!(body.contains("2 MB"));
+}
+
+#[tokio::test]
+async fn admin_dashboard_lists_recent_scan_summaries() {
+    let pool = test_pool().await;
+    let config = test_config(vec![]);
+    let (admin_id, _) = create_test_user(&pool, "admin", true).await;
+    let cookie = login_cookie(&pool, admin_id).await;
+    let summary = rewinder::scanner::ScanSummary {
+        items_seen: 12,
+        added: 3,
+        reactivated: 1,
+        marked_gone: 2,
+        size_delta: -524_288_000,
+    };
+    rewinder::models::scan_summary::insert(&pool, "/media/movies", 7, &summary)
+        .await
+        .unwrap();
+
+    let app = test_app(pool, config, true);
+    let response = app
+        .oneshot(get_with_cookie("/admin", &cookie))
+        .await
+        .unwrap();
+
+    let body = body_string(response).await;
+    assert!(body.contains("Recent Scans"));
+    assert!(body.contains("/media/movies"));
+    assert!(body.contains("-500 MB"));
+}
+
+#[tokio::test]
+async fn reload_config_applies_new_settings() {
+    let pool = test_pool().await;
+    let base = tempfile::tempdir().unwrap();
+    let movies = base.path().join("Movies");
+    std::fs::create_dir(&movies).unwrap();
+    let path = base.path().join("rewinder.toml");
+    let write = |grace: u64| {
+        let content = format!(
+            "database_url = \"sqlite::memory:\"\nlisten_addr = \"127.0.0.1:0\"\nmedia_dirs = [{movies:?}]\ngrace_period_days = {grace}\n"
+        );
+        std::fs::write(&path, content).unwrap();
+    };
+    write(7);
+    let config = rewinder::config::AppConfig::load(path.to_str().unwrap()).unwrap();
+    let shared = rewinder::config::SharedConfig::new(config, Some(path.clone()));
+    let (admin_id, _) = create_test_user(&pool, "admin", true).await;
+    let cookie = login_cookie(&pool, admin_id).await;
+
+    write(2);
+    let app = test_app_with_shared_config(pool.clone(), shared.clone(), true);
+    let response = app
+        .oneshot(post_form_with_cookie("/admin/config/reload", "", &cookie))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    assert_eq!(shared.current().grace_period_days, 2);
+
+    // An invalid file is rejected and the running config is kept.
+    std::fs::write(&path, "not = [valid").unwrap();
+    let app = test_app_with_shared_config(pool, shared.clone(), true);
+    let response = app
+        .oneshot(post_form_with_cookie("/admin/config/reload", "", &cookie))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(shared.current().grace_period_days, 2);
+}
+
+#[tokio::test]
+async fn admin_dashboard_shows_database_maintenance() {
+    let pool = test_pool().await;
+    let config = test_config(vec![]);
+    let (admin_id, _) = create_test_user(&pool, "admin", true).await;
+    let cookie = login_cookie(&pool, admin_id).await;
+
+    rewinder::maintenance::optimize_database(&pool, 7)
+        .await
+        .unwrap();
+    assert!(
+        !rewinder::models::db_maintenance::is_due(&pool, "vacuum", 7)
+            .await
+            .unwrap()
+    );
+
+    let app = test_app(pool, config, true);
+    let response = app
+        .oneshot(get_with_cookie("/admin", &cookie))
+        .await
+        .unwrap();
+
+    let body = body_string(response).await;
+    assert!(body.contains("Database Maintenance"));
+    assert!(body.contains("<code>optimize</code>"));
+    assert!(body.contains("<code>vacuum</code>"));
+}
+
+#[tokio::test]
+async fn vacuum_is_skipped_when_disabled() {
+    let pool = test_pool().await;
+
+    rewinder::maintenance::optimize_database(&pool, 0)
+        .await
+        .unwrap();
+
+    let runs = rewinder::models::db_maintenance::list(&pool).await.unwrap();
+    let tasks: Vec<&str> = runs.iter().map(|r| r.task.as_str()).collect();
+    assert_eq!(tasks, ["optimize"]);
+}
+
+#[tokio::test]
+async fn admin_deleted_page_lists_gone_media_with_reason() {
+    let pool = test_pool().await;
+    let config = test_config(vec![]);
+    let (admin_id, _) = create_test_user(&pool, "admin", true).await;
+    let cookie = login_cookie(&pool, admin_id).await;
+
+    let heat = insert_movie(&pool, "Heat", "/movies/Heat (1995)").await;
+    rewinder::models::media::set_trashed(&pool, heat, "admin")
+        .await
+        .unwrap();
+    rewinder::models::media::set_gone(&pool, heat, rewinder::models::media_history::CLEANUP)
+        .await
+        .unwrap();
+    let alien = insert_movie(&pool, "Alien", "/movies/Alien (1979)").await;
+    rewinder::models::media::mark_gone_by_path(&pool, "/movies/Alien (1979)")
+        .await
+        .unwrap();
+    insert_movie(&pool, "Still Here", "/movies/Still Here (2020)").await;
+
+    let app = test_app(pool.clone(), config.clone(), true);
+    let body = body_string(
+        app.oneshot(get_with_cookie("/admin/deleted", &cookie))
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert!(body.contains("Deleted from trash"));
+    assert!(body.contains("Missing from library"));
+    assert!(body.contains(&format!("/media/{alien}")));
+    assert!(!body.contains("Still Here"));
+
+    let app = test_app(pool, config, true);
+    let body = body_string(
+        app.oneshot(get_with_cookie("/admin/deleted?q=hea", &cookie))
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert!(body.contains(&format!("/media/{heat}")));
+    assert!(!body.contains(&format!("/media/{alien}")));
+}
+
+#[tokio::test]
+async fn users_request_deleted_media_back_through_radarr() {
+    use std::sync::{Arc, Mutex};
+
+    let added = Arc::new(Mutex::new(Vec::<serde_json::Value>::new()));
+    let lookup = axum::routing::get(|| async {
+        axum::Json(serde_json::json!([
+            {"title": "Heat", "year": 1986, "tmdbId": 1},
+            {"title": "Heat", "year": 1995, "tmdbId": 949},
+        ]))
+    });
+    let add = {
+        let added = added.clone();
+        axum::routing::post(
+            move |headers: axum::http::HeaderMap,
+                  axum::Json(movie): axum::Json<serde_json::Value>| async move {
+                assert_eq!(headers["x-api-key"], "secret");
+                added.lock().unwrap().push(movie);
+                axum::Json(serde_json::json!({"id": 7}))
+            },
+        )
+    };
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let app = axum::Router::new()
+            .route("/api/v3/movie/lookup", lookup)
+            .route("/api/v3/movie", add);
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    let pool = test_pool().await;
+    let mut config = test_config(vec![]);
+    config.radarr = Some(rewinder::arr::ArrConfig {
+        url: format!("http://{addr}"),
+        api_key: "secret".to_string(),
+        root_folder: "/movies".to_string(),
+        quality_profile_id: 4,
+        language_profile_id: None,
+    });
+    let (admin_id, _) = create_test_user(&pool, "admin", true).await;
+    let (bob_id, _) = create_test_user(&pool, "bob", false).await;
+    let admin_cookie = login_cookie(&pool, admin_id).await;
+    let bob_cookie = login_cookie(&pool, bob_id).await;
+    let heat = insert_movie(&pool, "Heat", "/movies/Heat (1995)").await;
+    sqlx::query("UPDATE media SET year = 1995 WHERE id = ?")
+        .bind(heat)
+        .execute(&pool)
+        .await
+        .unwrap();
+    rewinder::models::media::set_gone(&pool, heat, rewinder::models::media_history::CLEANUP)
+        .await
+        .unwrap();
+
+    let app = test_app(pool.clone(), config.clone(), true);
+    let body = body_string(
+        app.oneshot(get_with_cookie("/deleted", &bob_cookie))
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert!(body.contains(&format!("/deleted/{heat}/request")), "{body}");
+
+    let app = test_app(pool.clone(), config.clone(), true);
+    let response = app
+        .oneshot(post_form_with_cookie(
+            &format!("/deleted/{heat}/request"),
+            "",
+            &bob_cookie,
+        ))
+        .await
+        .unwrap();
+    assert_redirect(&response, "/deleted").await;
+    let added = added.lock().unwrap().clone();
+    assert_eq!(added.len(), 1);
+    assert_eq!(added[0]["tmdbId"], 949);
+    assert_eq!(added[0]["qualityProfileId"], 4);
+    assert_eq!(added[0]["rootFolderPath"], "/movies");
+
+    let app = test_app(pool.clone(), config.clone(), true);
+    let body = body_string(
+        app.oneshot(get_with_cookie("/deleted", &bob_cookie))
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert!(body.contains("Sent to Radarr"), "{body}");
+    assert!(!body.contains("deleted item back"));
+
+    // Admins get a banner and see who asked.
+    let app = test_app(pool.clone(), config.clone(), true);
+    let body = body_string(
+        app.oneshot(get_with_cookie("/admin/deleted", &admin_cookie))
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert!(
+        body.contains("Users asked for 1 deleted item back"),
+        "{body}"
+    );
+    assert!(body.contains("bob</span> (sent to Radarr)"), "{body}");
+
+    let app = test_app(pool.clone(), config.clone(), true);
+    let response = app
+        .oneshot(post_form_with_cookie(
+            &format!("/admin/deleted/{heat}/dismiss"),
+            "",
+            &admin_cookie,
+        ))
+        .await
+        .unwrap();
+    assert_redirect(&response, "/admin/deleted").await;
+    assert_eq!(
+        rewinder::models::rerequest::open_count(&pool)
+            .await
+            .unwrap(),
+        0
+    );
+
+    // Only deleted items can be asked back.
+    let alien = insert_movie(&pool, "Alien", "/movies/Alien (1979)").await;
+    let app = test_app(pool, config, true);
+    let response = app
+        .oneshot(post_form_with_cookie(
+            &format!("/deleted/{alien}/request"),
+            "",
+            &bob_cookie,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn purge_deleted_forgets_only_old_gone_media() {
+    let pool = test_pool().await;
+    let old = insert_movie(&pool, "Old", "/movies/Old (1950)").await;
+    let recent = insert_movie(&pool, "Recent", "/movies/Recent (2020)").await;
+    let active = insert_movie(&pool, "Active", "/movies/Active (2021)").await;
+    for id in [old, recent] {
+        rewinder::models::media::set_gone(&pool, id, "scanner")
+            .await
+            .unwrap();
+    }
+    sqlx::query(
+        "UPDATE media_history SET changed_at = datetime('now', '-40 days') WHERE media_id = ?",
+    )
+    .bind(old)
+    .execute(&pool)
+    .await
+    .unwrap();
+    sqlx::query("UPDATE media SET last_seen = datetime('now', '-400 days') WHERE id = ?")
+        .bind(active)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let purged = rewinder::models::media::purge_deleted(&pool, 30)
+        .await
+        .unwrap();
+    assert_eq!(purged, 1);
+    let exists = |id| {
+        let pool = pool.clone();
+        async move {
+            rewinder::models::media::get_by_id(&pool, id)
+                .await
+                .unwrap()
+                .is_some()
+        }
+    };
+    assert!(!exists(old).await);
+    assert!(exists(recent).await);
+    assert!(exists(active).await);
+    assert!(rewinder::models::media_history::list_for_media(&pool, old)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn export_media_inventory_as_csv_and_json() {
+    let pool = test_pool().await;
+    let config = test_config(vec![]);
+    let (admin_id, _) = create_test_user(&pool, "admin", true).await;
+    let cookie = login_cookie(&pool, admin_id).await;
+    insert_movie(
+        &pool,
+        "Crouching Tiger, Hidden Dragon",
+        "/movies/Crouching Tiger",
+    )
+    .await;
+    insert_tv_season(&pool, "The Office", 2, "/tv/The Office/Season 02").await;
+
+    let app = test_app(pool.clone(), config.clone(), true);
+    let response = app
+        .oneshot(get_with_cookie("/admin/export/media.csv", &cookie))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers()["content-type"]
+        .to_str()
+        .unwrap()
+        .starts_with("text/csv"));
+    let body = body_string(response).await;
+    let lines: Vec<&str> = body.lines().collect();
+    assert_eq!(
+        lines[0],
+        "id,media_type,title,year,season,status,size_bytes,path,first_seen,last_seen,trashed_at"
+    );
+    assert!(lines[1].contains(r#","Crouching Tiger, Hidden Dragon",2020,,active,1000000,"#));
+    assert!(lines[2].contains(",The Office,,2,active,"));
+
+    let app = test_app(pool, config, true);
+    let body = body_string(
+        app.oneshot(get_with_cookie("/admin/export/media.json", &cookie))
+            .await
+            .unwrap(),
+    )
+    .await;
+    let items: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(items.as_array().unwrap().len(), 2);
+    assert_eq!(items[1]["season"], 2);
+    assert_eq!(items[1]["path"], "/tv/The Office/Season 02");
+}
+
+#[tokio::test]
+async fn export_requires_admin() {
+    let pool = test_pool().await;
+    let config = test_config(vec![]);
+    let (user_id, _) = create_test_user(&pool, "alice", false).await;
+    let cookie = login_cookie(&pool, user_id).await;
+
+    let app = test_app(pool, config, true);
+    let response = app
+        .oneshot(get_with_cookie("/admin/export/media.csv", &cookie))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+}
+
+#[tokio::test]
+async fn admin_bulk_invites_users() {
+    let pool = test_pool().await;
+    let config = test_config(vec![]);
+    let (admin_id, _) = create_test_user(&pool, "admin", true).await;
+    create_test_user(&pool, "bob", false).await;
+    let cookie = login_cookie(&pool, admin_id).await;
+
+    let app = test_app(pool.clone(), config, true);
+    let response = app
+        .oneshot(post_form_with_cookie(
+            "/admin/users/bulk",
+            "usernames=carol%0D%0Adave%40example.com,%20bob%0D%0A%0D%0Acarol",
+            &cookie,
+        ))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = body_string(response).await;
+    assert_eq!(body.matches("/invite/").count(), 2);
+    assert!(body.contains("dave@example.com: <code>"));
+    assert!(body.contains("Already have an account: bob"));
+    let dave = rewinder::models::user::get_by_username(&pool, "dave@example.com")
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(dave.invite_token.is_some());
+    assert_eq!(rewinder::models::user::count(&pool).await.unwrap(), 4);
+}
+
+#[tokio::test]
+async fn admin_lists_largest_active_and_persisted_items() {
+    let pool = test_pool().await;
+    let config = test_config(vec![]);
+    let (admin_id, _) = create_test_user(&pool, "admin", true).await;
+    let cookie = login_cookie(&pool, admin_id).await;
+    let small = insert_movie(&pool, "Small", "/movies/Small").await;
+    let big = insert_movie(&pool, "Big", "/movies/Big").await;
+    let kept = insert_movie(&pool, "Kept", "/movies/Kept").await;
+    rewinder::models::media::set_size(&pool, small, 10)
+        .await
+        .unwrap();
+    rewinder::models::media::set_size(&pool, big, 1000)
+        .await
+        .unwrap();
+    rewinder::models::media::set_permanent(&pool, kept, "admin")
+        .await
+        .unwrap();
+
+    let app = test_app(pool, config, true);
+    let body = body_string(
+        app.clone()
+            .oneshot(get_with_cookie("/admin/largest.json?limit=1", &cookie))
+            .await
+            .unwrap(),
+    )
+    .await;
+    let largest: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(largest["active"].as_array().unwrap().len(), 1);
+    assert_eq!(largest["active"][0]["title"], "Big");
+    assert_eq!(largest["persisted"][0]["title"], "Kept");
+
+    let body = body_string(
+        app.oneshot(get_with_cookie("/admin", &cookie))
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert!(body.contains("Largest Items"));
+    assert!(body.find(">Big<").unwrap() < body.find(">Small<").unwrap());
+}
+
+#[tokio::test]
+async fn space_freed_is_summed_per_month_library_and_user() {
+    use rewinder::models::{media, media_history};
+
+    let pool = test_pool().await;
+    let config = test_config(vec![
+        std::path::PathBuf::from("/movies"),
+        std::path::PathBuf::from("/tv"),
+    ]);
+    let (admin_id, _) = create_test_user(&pool, "admin", true).await;
+    let cookie = login_cookie(&pool, admin_id).await;
+
+    let heat = insert_movie(&pool, "Heat", "/movies/Heat (1995)").await;
+    let alien = insert_movie(&pool, "Alien", "/movies/Alien (1979)").await;
+    let office = insert_tv_season(&pool, "The Office", 1, "/tv/The Office/Season 01").await;
+    for (id, user) in [(heat, "alice"), (alien, "bob"), (office, "alice")] {
+        media::set_trashed(&pool, id, user).await.unwrap();
+        media::set_gone(&pool, id, media_history::CLEANUP)
+            .await
+            .unwrap();
+    }
+    // Still in the trash, or gone without passing through it: nothing freed.
+    let trashed = insert_movie(&pool, "Trashed", "/movies/Trashed (2001)").await;
+    media::set_trashed(&pool, trashed, "alice").await.unwrap();
+    let vanished = insert_movie(&pool, "Vanished", "/movies/Vanished (2002)").await;
+    media::set_gone(&pool, vanished, media_history::SCANNER)
+        .await
+        .unwrap();
+
+    let app = test_app(pool, config, true);
+    let body = body_string(
+        app.clone()
+            .oneshot(get_with_cookie("/admin/space-freed.json", &cookie))
+            .await
+            .unwrap(),
+    )
+    .await;
+    let freed: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let months = freed["months"].as_array().unwrap();
+    assert_eq!(months.len(), 1);
+    let month = &months[0];
+    assert_eq!(
+        month["month"],
+        jiff::Timestamp::now().strftime("%Y-%m").to_string()
+    );
+    assert_eq!(month["bytes"], 4_000_000);
+    assert_eq!(month["items"], 3);
+    assert_eq!(
+        month["by_library"],
+        serde_json::json!({"/movies": 2_000_000, "/tv": 2_000_000})
+    );
+    assert_eq!(
+        month["by_user"],
+        serde_json::json!({"alice": 3_000_000, "bob": 1_000_000})
+    );
+
+    let body = body_string(
+        app.oneshot(get_with_cookie("/admin", &cookie))
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert!(body.contains("Space Freed"));
+}
+
+#[tokio::test]
+async fn protected_media_cannot_be_marked_or_trashed() {
+    let pool = test_pool().await;
+    let mut config = test_config(vec![]);
+    config.rules = vec![rewinder::rules::Rule {
+        name: "everything old".to_string(),
+        action: rewinder::rules::RuleAction::Mark,
+        media_type: None,
+        older_than_days: Some(0),
+        larger_than_gb: None,
+        marked_by_percent: None,
+        tag: None,
+    }];
+    let (admin_id, _) = create_test_user(&pool, "admin", true).await;
+    let (user_id, _) = create_test_user(&pool, "alice", false).await;
+    let admin_cookie = login_cookie(&pool, admin_id).await;
+    let user_cookie = login_cookie(&pool, user_id).await;
+    let id = insert_movie(&pool, "Wedding", "/movies/Wedding").await;
+    rewinder::models::mark::mark(&pool, user_id, id)
+        .await
+        .unwrap();
+
+    let app = test_app(pool.clone(), config.clone(), true);
+    let response = app
+        .oneshot(post_form_with_cookie(
+            &format!("/admin/media/{id}/protect"),
+            "",
+            &admin_cookie,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = body_string(response).await;
+    assert!(body.contains("Protected"));
+    assert!(body.contains("Unprotect"));
+    assert!(!body.contains("Mark Done"));
+    // Protecting drops the marks it already had.
+    assert_eq!(
+        rewinder::models::mark::mark_count(&pool, id).await.unwrap(),
+        0
+    );
+
+    let app = test_app(pool.clone(), config.clone(), true);
+    let response = app
+        .oneshot(post_form_with_cookie(
+            &format!("/movies/{id}/mark"),
+            "",
+            &user_cookie,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    assert_eq!(
+        rewinder::rules::apply(&pool, &config, true).await.unwrap(),
+        0
+    );
+    let item = rewinder::models::media::get_by_id(&pool, id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(item.status, "active");
+
+    let app = test_app(pool.clone(), config, true);
+    let response = app
+        .oneshot(delete_with_cookie(
+            &format!("/admin/media/{id}/protect"),
+            &admin_cookie,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(body_string(response).await.contains("Mark Done"));
+}
+
+#[tokio::test]
+async fn protect_requires_admin() {
+    let pool = test_pool().await;
+    let config = test_config(vec![]);
+    let (user_id, _) = create_test_user(&pool, "alice", false).await;
+    let cookie = login_cookie(&pool, user_id).await;
+    let id = insert_movie(&pool, "Wedding", "/movies/Wedding").await;
+
+    let app = test_app(pool.clone(), config, true);
+    let response = app
+        .oneshot(post_form_with_cookie(
+            &format!("/admin/media/{id}/protect"),
+            "",
+            &cookie,
+        ))
+        .await
+        .unwrap();
+    assert_ne!(response.status(), StatusCode::OK);
+    let item = rewinder::models::media::get_by_id(&pool, id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(!item.protected);
+}
+
+#[tokio::test]
+async fn added_media_is_markable_and_survives_scans() {
+    let pool = test_pool().await;
+    let base = tempfile::tempdir().unwrap();
+    let movies = base.path().join("Movies");
+    // The scanner only sees the box set, not the film nested inside it.
+    let film = movies.join("Box Set").join("Disc 1");
+    std::fs::create_dir_all(&film).unwrap();
+    std::fs::write(film.join("film.mkv"), vec![0u8; 1024]).unwrap();
+    let config = test_config(vec![movies.clone()]);
+    let (admin_id, _) = create_test_user(&pool, "admin", true).await;
+    let cookie = login_cookie(&pool, admin_id).await;
+
+    let form = format!(
+        "path={}&media_type=movie&title=Alien&year=1979&season=",
+        film.to_string_lossy().replace(' ', "+")
+    );
+    let app = test_app(pool.clone(), config.clone(), true);
+    let response = app
+        .oneshot(post_form_with_cookie("/admin/media", &form, &cookie))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    let item = rewinder::models::media::find_containing(&pool, &film.to_string_lossy())
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(item.manual);
+    assert_eq!((item.title.as_str(), item.year), ("Alien", Some(1979)));
+
+    rewinder::scanner::full_scan(
+        &pool,
+        std::slice::from_ref(&movies),
+        None,
+        rewinder::scanner::ScanMode::Full,
+        &rewinder::scanner::ScanStatus::default(),
+    )
+    .await
+    .unwrap();
+    let rescanned = rewinder::models::media::get_by_id(&pool, item.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(rescanned.status, "active");
+
+    let app = test_app(pool.clone(), config.clone(), true);
+    let response = app
+        .oneshot(post_form_with_cookie(
+            &format!("/movies/{}/mark", item.id),
+            "",
+            &cookie,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        rewinder::models::mark::mark_count(&pool, item.id)
+            .await
+            .unwrap(),
+        1
+    );
+
+    // The same path cannot be tracked twice.
+    let app = test_app(pool, config, true);
+    let response = app
+        .oneshot(post_form_with_cookie("/admin/media", &form, &cookie))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn add_media_rejects_paths_outside_the_libraries() {
+    let pool = test_pool().await;
+    let base = tempfile::tempdir().unwrap();
+    let movies = base.path().join("Movies");
+    std::fs::create_dir_all(&movies).unwrap();
+    let outside = base.path().join("Downloads");
+    std::fs::create_dir_all(&outside).unwrap();
+    let config = test_config(vec![movies.clone()]);
+    let (admin_id, _) = create_test_user(&pool, "admin", true).await;
+    let cookie = login_cookie(&pool, admin_id).await;
+
+    for path in [outside, movies.clone(), movies.join("Missing")] {
+        let app = test_app(pool.clone(), config.clone(), true);
+        let response = app
+            .oneshot(post_form_with_cookie(
+                "/admin/media",
+                &format!(
+                    "path={}&media_type=movie&title=Heat",
+                    path.to_string_lossy()
+                ),
+                &cookie,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
+
+#[tokio::test]
+async fn reclassify_media_type() {
+    let pool = test_pool().await;
+    let config = test_config(vec![]);
+    let (admin_id, _) = create_test_user(&pool, "admin", true).await;
+    let cookie = login_cookie(&pool, admin_id).await;
+    let id = insert_movie(&pool, "Planet Earth", "/movies/Planet Earth").await;
+
+    let app = test_app(pool.clone(), config.clone(), true);
+    let response = app
+        .oneshot(post_form_with_cookie(
+            &format!("/admin/media/{id}/type"),
+            "media_type=tv_season&season=",
+            &cookie,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let app = test_app(pool.clone(), config, true);
+    let response = app
+        .oneshot(post_form_with_cookie(
+            &format!("/admin/media/{id}/type"),
+            "media_type=tv_season&season=1",
+            &cookie,
+        ))
+        .await
+        .unwrap();
+    assert_redirect(&response, &format!("/media/{id}")).await;
+    let item = rewinder::models::media::get_by_id(&pool, id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        (item.media_type.as_str(), item.season),
+        ("tv_season", Some(1))
+    );
+    assert!(item.manual);
+}
+
+#[tokio::test]
+async fn edited_title_survives_a_refresh() {
+    let pool = test_pool().await;
+    let base = tempfile::tempdir().unwrap();
+    let movies = base.path().join("Movies");
+    let movie = movies.join("Aliens (Extended Cut)");
+    std::fs::create_dir_all(&movie).unwrap();
+    let config = test_config(vec![movies]);
+    let (admin_id, _) = create_test_user(&pool, "admin", true).await;
+    let cookie = login_cookie(&pool, admin_id).await;
+    let id = insert_movie(&pool, "Aliens (Extended Cut)", &movie.to_string_lossy()).await;
+
+    let app = test_app(pool.clone(), config.clone(), true);
+    let response = app
+        .oneshot(post_form_with_cookie(
+            &format!("/admin/media/{id}/edit"),
+            "title=Aliens&year=1986",
+            &cookie,
+        ))
+        .await
+        .unwrap();
+    assert_redirect(&response, &format!("/media/{id}")).await;
+
+    let app = test_app(pool.clone(), config, true);
+    let response = app
+        .oneshot(post_form_with_cookie(
+            &format!("/admin/media/{id}/refresh"),
+            "",
+            &cookie,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let item = rewinder::models::media::get_by_id(&pool, id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!((item.title.as_str(), item.year), ("Aliens", Some(1986)));
+}
+
+#[tokio::test]
+async fn edit_media_requires_a_title() {
+    let pool = test_pool().await;
+    let config = test_config(vec![]);
+    let (admin_id, _) = create_test_user(&pool, "admin", true).await;
+    let cookie = login_cookie(&pool, admin_id).await;
+    let id = insert_movie(&pool, "Heat", "/movies/Heat").await;
+
+    let app = test_app(pool, config, true);
+    let response = app
+        .oneshot(post_form_with_cookie(
+            &format!("/admin/media/{id}/edit"),
+            "title=+&year=1995",
+            &cookie,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn edit_can_rename_a_movie_on_disk() {
+    let pool = test_pool().await;
+    let base = tempfile::tempdir().unwrap();
+    let movies = base.path().join("Movies");
+    let movie = movies.join("aliens.1986.extended");
+    std::fs::create_dir_all(&movie).unwrap();
+    std::fs::write(movie.join("movie.mkv"), b"x").unwrap();
+    let config = test_config(vec![movies.clone()]);
+    let (admin_id, _) = create_test_user(&pool, "admin", true).await;
+    let cookie = login_cookie(&pool, admin_id).await;
+    let id = insert_movie(&pool, "aliens extended", &movie.to_string_lossy()).await;
+
+    let app = test_app(pool.clone(), config, false);
+    let response = app
+        .oneshot(post_form_with_cookie(
+            &format!("/admin/media/{id}/edit"),
+            "title=Aliens&year=1986&rename=1",
+            &cookie,
+        ))
+        .await
+        .unwrap();
+    assert_redirect(&response, &format!("/media/{id}")).await;
+
+    let renamed = movies.join("Aliens (1986)");
+    assert!(renamed.join("movie.mkv").exists());
+    assert!(!movie.exists());
+    let item = rewinder::models::media::get_by_id(&pool, id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(item.path, renamed.to_string_lossy());
+    assert_eq!(item.status, "active");
+}
+
+#[tokio::test]
+async fn rename_refuses_to_overwrite_another_folder() {
+    let pool = test_pool().await;
+    let base = tempfile::tempdir().unwrap();
+    let movies = base.path().join("Movies");
+    let movie = movies.join("heat.1995");
+    std::fs::create_dir_all(&movie).unwrap();
+    std::fs::create_dir_all(movies.join("Heat (1995)")).unwrap();
+    let config = test_config(vec![movies]);
+    let (admin_id, _) = create_test_user(&pool, "admin", true).await;
+    let cookie = login_cookie(&pool, admin_id).await;
+    let id = insert_movie(&pool, "heat", &movie.to_string_lossy()).await;
+
+    let app = test_app(pool.clone(), config, false);
+    let response = app
+        .oneshot(post_form_with_cookie(
+            &format!("/admin/media/{id}/edit"),
+            "title=Heat&year=1995&rename=1",
+            &cookie,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    assert!(movie.exists());
+    let item = rewinder::models::media::get_by_id(&pool, id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(item.path, movie.to_string_lossy());
+}
+
+#[tokio::test]
+async fn admins_move_an_item_to_another_library() {
+    let pool = test_pool().await;
+    let base = tempfile::tempdir().unwrap();
+    let uhd = base.path().join("Movies 4K");
+    let hd = base.path().join("Movies");
+    let movie = uhd.join("Heat (1995)");
+    std::fs::create_dir_all(&movie).unwrap();
+    std::fs::create_dir_all(&hd).unwrap();
+    std::fs::write(movie.join("movie.mkv"), b"x").unwrap();
+    let config = test_config(vec![uhd, hd.clone()]);
+    let (admin_id, _) = create_test_user(&pool, "admin", true).await;
+    let cookie = login_cookie(&pool, admin_id).await;
+    let id = insert_movie(&pool, "Heat", &movie.to_string_lossy()).await;
+
+    let app = test_app(pool.clone(), config, false);
+    let body = body_string(
+        app.clone()
+            .oneshot(get_with_cookie(&format!("/media/{id}"), &cookie))
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert!(body.contains("Move to Library"));
+
+    let response = app
+        .oneshot(post_form_with_cookie(
+            &format!("/admin/media/{id}/library"),
+            &format!("media_dir={}", hd.to_string_lossy().replace(' ', "+")),
+            &cookie,
+        ))
+        .await
+        .unwrap();
+    assert_redirect(&response, &format!("/media/{id}")).await;
+
+    let moved = hd.join("Heat (1995)");
+    assert!(moved.join("movie.mkv").exists());
+    assert!(!movie.exists());
+    let item = rewinder::models::media::get_by_id(&pool, id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(item.path, moved.to_string_lossy());
+}
+
+#[tokio::test]
+async fn approvals_page_lists_pending_deletions() {
+    let pool = test_pool().await;
+    let config = test_config(vec![]);
+    let (admin_id, _) = create_test_user(&pool, "admin", true).await;
+    let cookie = login_cookie(&pool, admin_id).await;
+    let id = insert_movie(&pool, "Heat", "/movies/Heat").await;
+    rewinder::models::media::set_trashed(&pool, id, "admin")
+        .await
+        .unwrap();
+    rewinder::models::deletion_approval::request(&pool, id, "cleanup")
+        .await
+        .unwrap();
+
+    let app = test_app(pool.clone(), config.clone(), true);
+    let response = app
+        .oneshot(get_with_cookie("/admin/approvals", &cookie))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = body_string(response).await;
+    assert!(body.contains("Heat"));
+    assert!(body.contains("by cleanup"));
+
+    rewinder::models::media::set_active(&pool, id, "admin")
+        .await
+        .unwrap();
+    let app = test_app(pool, config, true);
+    let response = app
+        .oneshot(get_with_cookie("/admin/approvals", &cookie))
+        .await
+        .unwrap();
+    assert!(body_string(response)
+        .await
+        .contains("No deletions are waiting"));
+}
+
+#[tokio::test]
+async fn settings_override_the_config_file_until_reset() {
+    let pool = test_pool().await;
+    let shared = rewinder::config::SharedConfig::new(test_config(vec![]), None);
+    let (admin_id, _) = create_test_user(&pool, "admin", true).await;
+    let cookie = login_cookie(&pool, admin_id).await;
+
+    let app = test_app_with_shared_config(pool.clone(), shared.clone(), true);
+    let response = app
+        .oneshot(post_form_with_cookie(
+            "/admin/settings",
+            "grace_period_days=3&deletion_approval_gb=100",
+            &cookie,
+        ))
+        .await
+        .unwrap();
+    assert_redirect(&response, "/admin/settings").await;
+    assert_eq!(shared.current().grace_period_days, 3);
+    assert_eq!(shared.current().deletion_approval_gb, Some(100));
+    assert_eq!(shared.file_config().grace_period_days, 7);
+
+    // A fresh process picks the saved values up from the database.
+    let restarted = rewinder::config::SharedConfig::new(test_config(vec![]), None);
+    rewinder::settings::load(&pool, &restarted).await.unwrap();
+    assert_eq!(restarted.current().grace_period_days, 3);
+
+    let app = test_app_with_shared_config(pool.clone(), shared.clone(), true);
+    let response = app
+        .oneshot(get_with_cookie("/admin/settings", &cookie))
+        .await
+        .unwrap();
+    let body = body_string(response).await;
+    assert!(body.contains("name=\"grace_period_days\" value=\"3\""));
+
+    let app = test_app_with_shared_config(pool.clone(), shared.clone(), true);
+    let response = app
+        .oneshot(post_form_with_cookie(
+            "/admin/settings",
+            "grace_period_days=soon",
+            &cookie,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(shared.current().grace_period_days, 3);
+
+    // Going back to the file's value stops overriding it.
+    let app = test_app_with_shared_config(pool.clone(), shared.clone(), true);
+    app.oneshot(post_form_with_cookie(
+        "/admin/settings",
+        "grace_period_days=7&deletion_approval_gb=100",
+        &cookie,
+    ))
+    .await
+    .unwrap();
+    let saved = rewinder::models::setting::all(&pool).await.unwrap();
+    assert!(!saved.contains_key("grace_period_days"));
+    assert!(saved.contains_key("deletion_approval_gb"));
+}
+
+#[tokio::test]
+async fn dry_run_can_be_switched_off_at_runtime() {
+    let pool = test_pool().await;
+    let config = test_config(vec![]);
+    let (admin_id, _) = create_test_user(&pool, "admin", true).await;
+    let cookie = login_cookie(&pool, admin_id).await;
+
+    let app = test_app(pool, config, true);
+    let response = app
+        .clone()
+        .oneshot(get_with_cookie("/admin", &cookie))
+        .await
+        .unwrap();
+    assert!(body_string(response).await.contains("dry-run-banner"));
+
+    let response = app
+        .clone()
+        .oneshot(post_form_with_cookie(
+            "/admin/dry-run",
+            "enabled=false",
+            &cookie,
+        ))
+        .await
+        .unwrap();
+    assert_redirect(&response, "/admin/settings").await;
+
+    let response = app
+        .oneshot(get_with_cookie("/admin/settings", &cookie))
+        .await
+        .unwrap();
+    let body = body_string(response).await;
+    assert!(!body.contains("dry-run-banner"));
+    assert!(body.contains("Turn On Dry Run"));
+}
+
+#[tokio::test]
+async fn maintenance_mode_pauses_scans_until_left() {
+    let pool = test_pool().await;
+    let shared = rewinder::config::SharedConfig::new(test_config(vec![]), None);
+    let (admin_id, _) = create_test_user(&pool, "admin", true).await;
+    let cookie = login_cookie(&pool, admin_id).await;
+
+    let app = test_app_with_shared_config(pool.clone(), shared.clone(), false);
+    let response = app
+        .clone()
+        .oneshot(post_form_with_cookie(
+            "/admin/maintenance",
+            "enabled=true",
+            &cookie,
+        ))
+        .await
+        .unwrap();
+    assert_redirect(&response, "/admin/settings").await;
+    assert!(shared.current().maintenance);
+
+    let response = app
+        .clone()
+        .oneshot(get_with_cookie("/movies", &cookie))
+        .await
+        .unwrap();
+    assert!(body_string(response).await.contains("maintenance-banner"));
+
+    let response = app
+        .clone()
+        .oneshot(post_form_with_cookie("/admin/scan", "", &cookie))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // The switch survives a restart.
+    let restarted = rewinder::config::SharedConfig::new(test_config(vec![]), None);
+    rewinder::settings::load(&pool, &restarted).await.unwrap();
+    assert!(restarted.current().maintenance);
+
+    let response = app
+        .clone()
+        .oneshot(post_form_with_cookie(
+            "/admin/maintenance",
+            "enabled=false",
+            &cookie,
+        ))
+        .await
+        .unwrap();
+    assert_redirect(&response, "/admin/settings").await;
+    assert!(!shared.current().maintenance);
+    assert!(rewinder::models::setting::all(&pool)
+        .await
+        .unwrap()
+        .is_empty());
+
+    let response = app
+        .oneshot(get_with_cookie("/movies", &cookie))
+        .await
+        .unwrap();
+    assert!(!body_string(response).await.contains("maintenance-banner"));
+}
+
+#[tokio::test]
+async fn announcements_show_until_each_user_dismisses_them() {
+    let pool = test_pool().await;
+    let config = test_config(vec![]);
+    let (admin_id, _) = create_test_user(&pool, "admin", true).await;
+    let (user_id, _) = create_test_user(&pool, "bob", false).await;
+    let admin_cookie = login_cookie(&pool, admin_id).await;
+    let user_cookie = login_cookie(&pool, user_id).await;
+
+    let app = test_app(pool.clone(), config, false);
+    let response = app
+        .clone()
+        .oneshot(post_form_with_cookie(
+            "/admin/announcements",
+            "message=Voting+ends+Sunday",
+            &admin_cookie,
+        ))
+        .await
+        .unwrap();
+    assert_redirect(&response, "/admin/settings").await;
+    let id = rewinder::models::announcement::list(&pool).await.unwrap()[0].id;
+
+    let response = app
+        .clone()
+        .oneshot(get_with_cookie("/movies", &user_cookie))
+        .await
+        .unwrap();
+    assert!(body_string(response).await.contains("Voting ends Sunday"));
+
+    let response = app
+        .clone()
+        .oneshot(post_form_with_cookie(
+            &format!("/announcements/{id}/dismiss"),
+            "",
+            &user_cookie,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app
+        .clone()
+        .oneshot(get_with_cookie("/movies", &user_cookie))
+        .await
+        .unwrap();
+    assert!(!body_string(response).await.contains("Voting ends Sunday"));
+    let response = app
+        .clone()
+        .oneshot(get_with_cookie("/movies", &admin_cookie))
+        .await
+        .unwrap();
+    assert!(body_string(response).await.contains("Voting ends Sunday"));
+
+    let response = app
+        .oneshot(post_form_with_cookie(
+            "/admin/announcements",
+            "message=+",
+            &admin_cookie,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn trash_approval_holds_fully_marked_items_until_confirmed() {
+    let pool = test_pool().await;
+    let mut config = test_config(vec![]);
+    config.trash_approval = true;
+    let (admin_id, _) = create_test_user(&pool, "admin", true).await;
+    let cookie = login_cookie(&pool, admin_id).await;
+    let kept = insert_movie(&pool, "Keep Me", "/movies/Keep Me").await;
+    let trashed = insert_movie(&pool, "Trash Me", "/movies/Trash Me").await;
+
+    let app = test_app(pool.clone(), config, true);
+    for id in [kept, trashed] {
+        app.clone()
+            .oneshot(post_form_with_cookie(
+                &format!("/movies/{id}/mark"),
+                "",
+                &cookie,
+            ))
+            .await
+            .unwrap();
+    }
+    let response = app
+        .clone()
+        .oneshot(get_with_cookie("/admin/approvals", &cookie))
+        .await
+        .unwrap();
+    let body = body_string(response).await;
+    assert!(body.contains("Keep Me") && body.contains("Trash Me"));
+    let status = |id| {
+        let pool = pool.clone();
+        async move {
+            rewinder::models::media::get_by_id(&pool, id)
+                .await
+                .unwrap()
+                .unwrap()
+                .status
+        }
+    };
+    assert_eq!(status(trashed).await, "active");
+
+    let response = app
+        .clone()
+        .oneshot(post_form_with_cookie(
+            &format!("/admin/pending-trash/{trashed}/confirm"),
+            "",
+            &cookie,
+        ))
+        .await
+        .unwrap();
+    assert_redirect(&response, "/admin/approvals").await;
+    assert_eq!(status(trashed).await, "trashed");
+
+    let response = app
+        .oneshot(post_form_with_cookie(
+            &format!("/admin/pending-trash/{kept}/reject"),
+            "",
+            &cookie,
+        ))
+        .await
+        .unwrap();
+    assert_redirect(&response, "/admin/approvals").await;
+    assert_eq!(status(kept).await, "active");
+    assert_eq!(
+        rewinder::models::mark::mark_count(&pool, kept)
+            .await
+            .unwrap(),
+        0
+    );
+    assert!(rewinder::models::pending_trash::list(&pool)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn trash_window_queues_moves_for_the_worker() {
+    let pool = test_pool().await;
+    let mut config = test_config(vec![]);
+    config.trash_window = Some("01:00-05:00".parse().unwrap());
+    let (admin_id, _) = create_test_user(&pool, "admin", true).await;
+    let cookie = login_cookie(&pool, admin_id).await;
+    let queued = insert_movie(&pool, "Queued Movie", "/movies/Queued Movie").await;
+    let unmarked = insert_movie(&pool, "Changed Mind", "/movies/Changed Mind").await;
+
+    let app = test_app(pool.clone(), config.clone(), true);
+    for id in [queued, unmarked] {
+        app.clone()
+            .oneshot(post_form_with_cookie(
+                &format!("/movies/{id}/mark"),
+                "",
+                &cookie,
+            ))
+            .await
+            .unwrap();
+    }
+    app.clone()
+        .oneshot(delete_with_cookie(
+            &format!("/movies/{unmarked}/mark"),
+            &cookie,
+        ))
+        .await
+        .unwrap();
+    let response = app
+        .oneshot(get_with_cookie("/admin/trash", &cookie))
+        .await
+        .unwrap();
+    let body = body_string(response).await;
+    assert!(body.contains("Queued Moves") && body.contains("Queued Movie"));
+    assert!(body.contains("between 01:00-05:00"));
+
+    let moved = rewinder::trash::queue::run_batch(&pool, &config, true)
+        .await
+        .unwrap();
+    assert_eq!(moved, 1);
+    for (id, status) in [(queued, "trashed"), (unmarked, "active")] {
+        let item = rewinder::models::media::get_by_id(&pool, id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(item.status, status);
+    }
+    assert!(rewinder::models::trash_queue::list(&pool)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn expiry_warnings_reach_users_who_did_not_mark() {
+    let pool = test_pool().await;
+    let mut config = test_config(vec![]);
+    config.grace_period_days = 7;
+    config.expiry_warning_days = Some(2);
+    let (alice_id, _) = create_test_user(&pool, "alice", false).await;
+    let (bob_id, _) = create_test_user(&pool, "bob", false).await;
+    let soon = insert_movie(&pool, "Going Soon", "/movies/Going Soon").await;
+    let later = insert_movie(&pool, "Going Later", "/movies/Going Later").await;
+    rewinder::models::mark::mark(&pool, alice_id, soon)
+        .await
+        .unwrap();
+    for (id, age) in [(soon, "-6 days"), (later, "-1 days")] {
+        sqlx::query(
+            "UPDATE media SET status = 'trashed', trashed_at = datetime('now', ?) WHERE id = ?",
+        )
+        .bind(age)
+        .bind(id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    }
+
+    let warned = rewinder::trash::warn_before_expiry(&pool, &config)
+        .await
+        .unwrap();
+    assert_eq!(warned, 1);
+
+    let app = test_app(pool.clone(), config, true);
+    let alice = login_cookie(&pool, alice_id).await;
+    let bob = login_cookie(&pool, bob_id).await;
+    let page = |cookie: String| {
+        let app = app.clone();
+        async move {
+            let response = app
+                .oneshot(get_with_cookie("/movies", &cookie))
+                .await
+                .unwrap();
+            body_string(response).await
+        }
+    };
+    assert!(!page(alice.clone()).await.contains("is deleted for good"));
+    let body = page(bob.clone()).await;
+    assert!(body.contains("Going Soon") && body.contains("is deleted for good"));
+
+    let response = app
+        .clone()
+        .oneshot(post_form_with_cookie(
+            &format!("/expiry-warnings/{soon}/dismiss"),
+            "",
+            &bob,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(!page(bob).await.contains("is deleted for good"));
+}
+
+#[tokio::test]
+async fn expired_trash_is_archived_and_can_be_restored() {
+    let pool = test_pool().await;
+    let base = tempfile::tempdir().unwrap();
+    let movies = base.path().join("Movies");
+    let movie = movies.join("Heat (1995)");
+    std::fs::create_dir_all(&movie).unwrap();
+    std::fs::write(movie.join("movie.mkv"), b"heat").unwrap();
+    let mut config = test_config(vec![movies.clone()]);
+    config.archive_dir = Some(base.path().join("archive"));
+    let (admin_id, _) = create_test_user(&pool, "admin", true).await;
+    let cookie = login_cookie(&pool, admin_id).await;
+    let id = insert_movie(&pool, "Heat", &movie.to_string_lossy()).await;
+
+    rewinder::trash::move_to_trash(&pool, id, "admin", &config, false)
+        .await
+        .unwrap();
+    sqlx::query("UPDATE media SET trashed_at = datetime('now', '-8 days') WHERE id = ?")
+        .bind(id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    rewinder::trash::cleanup_expired(&pool, &config, 7, false)
+        .await
+        .unwrap();
+
+    let item = rewinder::models::media::get_by_id(&pool, id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(item.status, "archived");
+    let archived = std::path::PathBuf::from(item.archive_path.unwrap());
+    assert!(archived.starts_with(base.path().join("archive").join("Movies")));
+    assert!(archived.join("movie.mkv").exists());
+
+    let app = test_app(pool.clone(), config, false);
+    let body = body_string(
+        app.clone()
+            .oneshot(get_with_cookie("/admin/archive", &cookie))
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert!(body.contains("Heat"));
+
+    let response = app
+        .oneshot(post_form_with_cookie(
+            &format!("/admin/archive/{id}/restore"),
+            "",
+            &cookie,
+        ))
+        .await
+        .unwrap();
+    assert_redirect(&response, "/admin/archive").await;
+    assert!(movie.join("movie.mkv").exists());
+    assert!(!archived.exists());
+    let item = rewinder::models::media::get_by_id(&pool, id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(item.status, "active");
+    assert_eq!(item.archive_path, None);
+}
+
+#[tokio::test]
+async fn permanent_deletions_are_recorded_in_the_monthly_manifest() {
+    let pool = test_pool().await;
+    let base = tempfile::tempdir().unwrap();
+    let movies = base.path().join("Movies");
+    let movie = movies.join("Heat (1995)");
+    std::fs::create_dir_all(&movie).unwrap();
+    std::fs::write(movie.join("movie.mkv"), b"heat").unwrap();
+    let mut config = test_config(vec![movies.clone()]);
+    config.deletion_manifest_dir = Some(base.path().join("manifest"));
+    std::fs::create_dir_all(base.path().join("manifest")).unwrap();
+    let (admin_id, _) = create_test_user(&pool, "admin", true).await;
+    let (alice_id, _) = create_test_user(&pool, "alice", false).await;
+    let cookie = login_cookie(&pool, admin_id).await;
+    let id = insert_movie(&pool, "Heat", &movie.to_string_lossy()).await;
+    rewinder::models::mark::mark(&pool, alice_id, id)
+        .await
+        .unwrap();
+    rewinder::trash::move_to_trash(&pool, id, "alice", &config, false)
+        .await
+        .unwrap();
+
+    let app = test_app(pool.clone(), config, false);
+    let response = app
+        .clone()
+        .oneshot(post_form_with_cookie(
+            &format!("/admin/trash/{id}/delete"),
+            "",
+            &cookie,
+        ))
+        .await
+        .unwrap();
+    assert_redirect(&response, "/admin/trash").await;
+
+    let month = jiff::Timestamp::now().strftime("%Y-%m").to_string();
+    let page = body_string(
+        app.clone()
+            .oneshot(get_with_cookie("/admin/manifest", &cookie))
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert!(page.contains(&format!("/admin/manifest/{month}.csv")));
+
+    let jsonl = body_string(
+        app.clone()
+            .oneshot(get_with_cookie(
+                &format!("/admin/manifest/{month}.jsonl"),
+                &cookie,
+            ))
+            .await
+            .unwrap(),
+    )
+    .await;
+    let entry: serde_json::Value = serde_json::from_str(jsonl.lines().next().unwrap()).unwrap();
+    assert_eq!(entry["media_id"], id);
+    assert_eq!(entry["deleted_by"], "admin");
+    assert_eq!(entry["path"], movie.to_string_lossy().as_ref());
+    assert_eq!(entry["marked_by"][0]["user"], "alice");
+
+    let csv = body_string(
+        app.clone()
+            .oneshot(get_with_cookie(
+                &format!("/admin/manifest/{month}.csv"),
+                &cookie,
+            ))
+            .await
+            .unwrap(),
+    )
+    .await;
+    let row = csv.lines().nth(1).unwrap();
+    assert!(row.contains(",admin,"));
+    assert!(row.contains("alice ("));
+
+    let response = app
+        .oneshot(get_with_cookie(
+            "/admin/manifest/..%2F..%2Fetc.csv",
+            &cookie,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), axum::http::StatusCode::NOT_FOUND);
+}
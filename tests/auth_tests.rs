@@ -64,6 +64,42 @@ async fn login_with_valid_credentials() {
     assert!(response.headers().get("set-cookie").is_some());
 }
 
+#[tokio::test]
+async fn login_records_forwarded_client_ip_from_trusted_proxy() {
+    use axum::extract::connect_info::MockConnectInfo;
+    use std::net::SocketAddr;
+
+    let pool = test_pool().await;
+    let mut config = test_config(vec![]);
+    config.trusted_proxies = vec!["10.0.0.0/8".parse().unwrap()];
+    let (_id, password) = create_test_user(&pool, "alice", false).await;
+
+    let login = |peer: &str| {
+        let app = test_app(pool.clone(), config.clone(), true)
+            .layer(MockConnectInfo(peer.parse::<SocketAddr>().unwrap()));
+        let mut request = post_form("/login", &format!("username=alice&password={password}"));
+        request
+            .headers_mut()
+            .insert("x-forwarded-for", "6.6.6.6, 198.51.100.7".parse().unwrap());
+        app.oneshot(request)
+    };
+    login("10.0.0.2:40000").await.unwrap();
+    login("203.0.113.9:40000").await.unwrap();
+
+    let ips: Vec<(Option<String>,)> =
+        sqlx::query_as("SELECT client_ip FROM sessions ORDER BY rowid")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+    assert_eq!(
+        ips,
+        vec![
+            (Some("198.51.100.7".to_string()),),
+            (Some("203.0.113.9".to_string()),)
+        ]
+    );
+}
+
 #[tokio::test]
 async fn login_with_wrong_password() {
     let pool = test_pool().await;
@@ -0,0 +1,59 @@
+//! Runs in its own test binary because the base path is process-global.
+
+mod common;
+
+use axum::http::StatusCode;
+use common::*;
+use tower::ServiceExt;
+
+const BASE: &str = "/rewinder";
+
+async fn app() -> (sqlx::SqlitePool, axum::Router) {
+    rewinder::routes::set_base_path(BASE);
+    let pool = test_pool().await;
+    let app = test_app(pool.clone(), test_config(vec![]), false);
+    (pool, app)
+}
+
+#[tokio::test]
+async fn routes_and_redirects_live_under_base_path() {
+    let (_pool, app) = app().await;
+
+    let response = app.clone().oneshot(get("/rewinder/movies")).await.unwrap();
+    assert_redirect(&response, "/rewinder/login").await;
+
+    for root in ["/rewinder", "/rewinder/"] {
+        let response = app.clone().oneshot(get(root)).await.unwrap();
+        assert_redirect(&response, "/rewinder/movies").await;
+    }
+
+    let response = app.oneshot(get("/movies")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn login_sets_cookie_and_links_with_base_path() {
+    let (pool, app) = app().await;
+    create_test_user(&pool, "alice", false).await;
+
+    let response = app.clone().oneshot(get("/rewinder/login")).await.unwrap();
+    let body = body_string(response).await;
+    assert!(body.contains(r#"action="/rewinder/login""#), "{body}");
+    assert!(body.contains(r#"href="/rewinder/static/style.css""#));
+
+    let response = app
+        .oneshot(post_form(
+            "/rewinder/login",
+            "username=alice&password=testpass123",
+        ))
+        .await
+        .unwrap();
+    assert_redirect(&response, "/rewinder/movies").await;
+    let cookie = response
+        .headers()
+        .get("set-cookie")
+        .unwrap()
+        .to_str()
+        .unwrap();
+    assert!(cookie.contains("Path=/rewinder"), "{cookie}");
+}
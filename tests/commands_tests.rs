@@ -0,0 +1,192 @@
+mod common;
+
+use common::*;
+use rewinder::commands;
+use rewinder::models::user;
+
+#[tokio::test]
+async fn user_add_creates_invited_user() {
+    let pool = test_pool().await;
+
+    let output = commands::user_add(&pool, "carol", false).await.unwrap();
+    let created = user::get_by_username(&pool, "carol")
+        .await
+        .unwrap()
+        .unwrap();
+    let token = created.invite_token.expect("invite token");
+    assert!(output.contains(&format!("/invite/{token}")));
+    assert!(!created.is_admin);
+
+    assert!(commands::user_add(&pool, "carol", false).await.is_err());
+}
+
+#[tokio::test]
+async fn user_reset_password_replaces_password_and_sessions() {
+    let pool = test_pool().await;
+    let (id, old_password) = create_test_user(&pool, "alice", false).await;
+    let cookie = login_cookie(&pool, id).await;
+    let token = cookie.trim_start_matches("session=");
+
+    let output = commands::user_reset_password(&pool, "alice").await.unwrap();
+    let new_password = output
+        .lines()
+        .next()
+        .and_then(|l| l.rsplit(' ').next())
+        .unwrap();
+
+    let hash = user::get_by_id(&pool, id)
+        .await
+        .unwrap()
+        .unwrap()
+        .password_hash
+        .unwrap();
+    assert!(rewinder::auth::verify_password(new_password, &hash));
+    assert!(!rewinder::auth::verify_password(&old_password, &hash));
+    assert_eq!(
+        rewinder::auth::session::validate(&pool, token)
+            .await
+            .unwrap(),
+        None
+    );
+
+    assert!(commands::user_reset_password(&pool, "nobody")
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn user_promote_grants_admin() {
+    let pool = test_pool().await;
+    let (id, _) = create_test_user(&pool, "bob", false).await;
+
+    commands::user_promote(&pool, "bob").await.unwrap();
+    assert!(user::get_by_id(&pool, id).await.unwrap().unwrap().is_admin);
+
+    let again = commands::user_promote(&pool, "bob").await.unwrap();
+    assert!(again.contains("already"));
+}
+
+#[tokio::test]
+async fn scan_command_reports_summary() {
+    let pool = test_pool().await;
+    let base = tempfile::tempdir().unwrap();
+    let movies = base.path().join("Movies");
+    std::fs::create_dir_all(movies.join("Heat (1995)")).unwrap();
+    let config = test_config(vec![movies]);
+
+    let output = commands::scan(&pool, &config).await.unwrap();
+    assert!(output.contains("1 added"), "{output}");
+}
+
+#[tokio::test]
+async fn export_import_roundtrip() {
+    let pool = test_pool().await;
+    let (alice, _) = create_test_user(&pool, "alice", true).await;
+    let (bob, _) = create_test_user(&pool, "bob", false).await;
+    let movie = insert_movie(&pool, "Heat", "/movies/Heat (1995)").await;
+    let season = insert_tv_season(&pool, "The Office", 1, "/tv/The Office/Season 01").await;
+    rewinder::models::mark::mark(&pool, bob, movie)
+        .await
+        .unwrap();
+    rewinder::models::persistent::set_owner(&pool, season, alice)
+        .await
+        .unwrap();
+    rewinder::models::comment::add(&pool, movie, bob, "seen it twice")
+        .await
+        .unwrap();
+    let prefs = rewinder::models::preferences::Preferences {
+        theme: "light".to_string(),
+        ..Default::default()
+    };
+    rewinder::models::preferences::save(&pool, bob, &prefs)
+        .await
+        .unwrap();
+    rewinder::models::library_access::grant(&pool, "/movies", bob)
+        .await
+        .unwrap();
+    rewinder::models::persistent::set_series_owner(&pool, "The Office", alice)
+        .await
+        .unwrap();
+    rewinder::models::keep_vote::keep(&pool, alice, movie)
+        .await
+        .unwrap();
+    let cut = insert_movie(&pool, "Heat", "/movies/Heat (1995) Director's Cut").await;
+    rewinder::models::edition::link(&pool, cut, movie)
+        .await
+        .unwrap();
+
+    rewinder::models::rule_hit::record(&pool, "old-movies", movie, "mark")
+        .await
+        .unwrap();
+    rewinder::models::media::set_gone(&pool, cut, "scanner")
+        .await
+        .unwrap();
+    rewinder::models::setting::set(&pool, "trash_approval", "true", "alice")
+        .await
+        .unwrap();
+    let notice = rewinder::models::announcement::create(&pool, "Disk swap tonight", "alice")
+        .await
+        .unwrap();
+    rewinder::models::announcement::dismiss(&pool, notice, bob)
+        .await
+        .unwrap();
+    rewinder::models::rerequest::request(&pool, cut, bob)
+        .await
+        .unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("dump.json");
+    commands::export(&pool, &file).await.unwrap();
+
+    let restored = test_pool().await;
+    let output = commands::import(&restored, &file, false).await.unwrap();
+    assert!(output.contains("2 users"), "{output}");
+    assert_eq!(
+        rewinder::dump::export(&restored).await.unwrap(),
+        rewinder::dump::export(&pool).await.unwrap()
+    );
+
+    // A second import into the now populated database needs --replace.
+    assert!(commands::import(&restored, &file, false).await.is_err());
+    commands::import(&restored, &file, true).await.unwrap();
+    assert_eq!(user::count(&restored).await.unwrap(), 2);
+    assert_eq!(
+        rewinder::models::media_history::list_for_media(&restored, cut)
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+    assert_eq!(
+        rewinder::models::library_access::grants(&restored)
+            .await
+            .unwrap()
+            .get("/movies"),
+        Some(&vec![bob])
+    );
+}
+
+#[tokio::test]
+async fn migrate_down_and_redo_round_trip() {
+    let pool = test_pool().await;
+    create_test_user(&pool, "alice", false).await;
+
+    let output = commands::migrate_down(&pool, 29).await.unwrap();
+    assert_eq!(
+        output,
+        "Reverted 038_media_path_nocase\nReverted 037_media_editions\nReverted 036_persistent_series\nReverted 035_trash_journal\nReverted 034_poster_hash\nReverted 033_indexes\nReverted 032_rerequests\nReverted 031_comments\nReverted 030_cloud_key\nReverted 029_archive_path\nReverted 028_keep_votes\nReverted 027_expiry_warnings\nReverted 026_trash_queue\nReverted 025_pending_trash\nReverted 024_restricted_users\nReverted 023_library_access\nReverted 022_announcements\nReverted 021_settings\nReverted 020_deletion_approvals\nReverted 019_manual_media\nReverted 018_trash_path\nReverted 017_trash_name\nReverted 016_policy_log\nReverted 015_media_tags\nReverted 014_protected\nReverted 013_rule_hits\nReverted 012_size_pending\nReverted 011_feed_token\nReverted 010_user_preferences"
+    );
+    assert!(sqlx::query("SELECT * FROM user_preferences")
+        .execute(&pool)
+        .await
+        .is_err());
+
+    let output = commands::migrate_redo(&pool).await.unwrap();
+    assert_eq!(
+        output,
+        "Reverted 009_user_locale\nApplied 009_user_locale\nApplied 010_user_preferences\nApplied 011_feed_token\nApplied 012_size_pending\nApplied 013_rule_hits\nApplied 014_protected\nApplied 015_media_tags\nApplied 016_policy_log\nApplied 017_trash_name\nApplied 018_trash_path\nApplied 019_manual_media\nApplied 020_deletion_approvals\nApplied 021_settings\nApplied 022_announcements\nApplied 023_library_access\nApplied 024_restricted_users\nApplied 025_pending_trash\nApplied 026_trash_queue\nApplied 027_expiry_warnings\nApplied 028_keep_votes\nApplied 029_archive_path\nApplied 030_cloud_key\nApplied 031_comments\nApplied 032_rerequests\nApplied 033_indexes\nApplied 034_poster_hash\nApplied 035_trash_journal\nApplied 036_persistent_series\nApplied 037_media_editions\nApplied 038_media_path_nocase"
+    );
+    assert!(user::get_by_username(&pool, "alice")
+        .await
+        .unwrap()
+        .is_some());
+}
@@ -7,10 +7,9 @@ use http_body_util::BodyExt;
 use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
 use sqlx::SqlitePool;
 use std::path::PathBuf;
-use std::sync::Arc;
 use tower::ServiceExt;
 
-use rewinder::config::AppConfig;
+use rewinder::config::{AppConfig, SharedConfig};
 use rewinder::routes::{build_router, AppState};
 
 pub async fn test_pool() -> SqlitePool {
@@ -41,20 +40,76 @@ pub fn test_config(media_dirs: Vec<PathBuf>) -> AppConfig {
 
     AppConfig {
         database_url: ":memory:".to_string(),
-        listen_addr: "127.0.0.1:0".to_string(),
+        listen_addr: "127.0.0.1:0".into(),
         media_dirs,
         grace_period_days: 7,
+        keep_vote_days: 30,
+        keep_hardlinked_trash: false,
+        deletion_approval_gb: None,
+        expiry_warning_days: None,
+        archive_dir: None,
+        deletion_manifest_dir: None,
+        trash_approval: false,
+        require_admin_mark: false,
+        trash_window: None,
+        trash_batch_size: 10,
+        maintenance: false,
+        rescue_conflict: Default::default(),
         cleanup_interval_hours: 1,
         initial_admin_user: None,
         tmdb_api_key: None,
+        watch_debounce_secs: 2,
+        poll_dirs: Vec::new(),
+        poll_interval_secs: 60,
+        season_pattern: None,
+        size_exclude: Vec::new(),
+        specials: Default::default(),
+        base_path: String::new(),
+        trusted_proxies: Vec::new(),
+        log_requests: false,
+        otlp_endpoint: None,
+        log_format: Default::default(),
+        db_max_connections: 5,
+        db_busy_timeout_ms: 5000,
+        db_wal: true,
+        db_vacuum_interval_days: 0,
+        deleted_retention_days: 0,
+        size_units: Default::default(),
+        guest_token: None,
+        metrics_token: None,
+        rate_limit_per_minute: 120,
+        login_rate_limit_per_minute: 10,
+        request_timeout_secs: 30,
+        max_body_kb: 256,
+        rules: Vec::new(),
+        tag_policies: Vec::new(),
+        media_server: None,
+        torrent_client: None,
+        cloud_archive: None,
+        rclone_archive: None,
+        overseerr: None,
+        radarr: None,
+        sonarr: None,
     }
 }
 
 pub fn test_app(pool: SqlitePool, config: AppConfig, dry_run: bool) -> Router {
+    test_app_with_shared_config(pool, SharedConfig::new(config, None), dry_run)
+}
+
+pub fn test_app_with_shared_config(
+    pool: SqlitePool,
+    config: SharedConfig,
+    dry_run: bool,
+) -> Router {
     let state = AppState {
         pool,
-        config: Arc::new(config),
-        dry_run,
+        config,
+        dry_run: rewinder::dry_run::DryRun::new(dry_run),
+        watcher_health: rewinder::watcher::WatcherHealth::default(),
+        scan_status: rewinder::scanner::ScanStatus::default(),
+        tmdb: None,
+        moves: rewinder::shutdown::MoveTracker::default(),
     };
     build_router(state)
 }
@@ -72,7 +127,7 @@ pub async fn create_test_user(pool: &SqlitePool, username: &str, is_admin: bool)
 }
 
 pub async fn login_cookie(pool: &SqlitePool, user_id: i64) -> String {
-    let token = rewinder::auth::session::create(pool, user_id, 720)
+    let token = rewinder::auth::session::create(pool, user_id, 720, None)
         .await
         .expect("create session failed");
     format!("session={token}")
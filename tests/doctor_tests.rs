@@ -0,0 +1,80 @@
+mod common;
+
+use common::*;
+use rewinder::doctor::{self, Fix};
+use rewinder::models::{mark, media};
+
+#[tokio::test]
+async fn doctor_reports_and_repairs_mismatches() {
+    let pool = test_pool().await;
+    let base = tempfile::tempdir().unwrap();
+    let movies = base.path().join("Movies");
+    let trash = base.path().join("Movies_trash");
+    std::fs::create_dir_all(movies.join("Heat (1995)")).unwrap();
+    std::fs::create_dir_all(trash.join("Alien (1979)")).unwrap();
+    std::fs::create_dir_all(trash.join("Stray (2001)")).unwrap();
+    std::fs::create_dir_all(movies.join("Brazil (1985)")).unwrap();
+    let config = test_config(vec![movies.clone()]);
+    let (user_id, _) = create_test_user(&pool, "alice", false).await;
+
+    let path = |name: &str| movies.join(name).to_string_lossy().to_string();
+    let healthy = insert_movie(&pool, "Heat", &path("Heat (1995)")).await;
+    let missing = insert_movie(&pool, "Ran", &path("Ran (1985)")).await;
+    let trashed_on_disk = insert_movie(&pool, "Alien", &path("Alien (1979)")).await;
+    let back_in_library = insert_movie(&pool, "Brazil", &path("Brazil (1985)")).await;
+    media::set_trashed(&pool, back_in_library, "admin")
+        .await
+        .unwrap();
+    mark::mark(&pool, user_id, missing).await.unwrap();
+
+    let mut report = doctor::diagnose(&pool, &config).await.unwrap();
+    let fix_for = |id: i64| {
+        report
+            .findings
+            .iter()
+            .find(|f| f.media_id == Some(id))
+            .map(|f| f.fix)
+    };
+    assert_eq!(fix_for(healthy), None);
+    assert_eq!(fix_for(missing), Some(Some(Fix::MarkGone)));
+    assert_eq!(fix_for(trashed_on_disk), Some(Some(Fix::MarkTrashed)));
+    assert_eq!(fix_for(back_in_library), Some(Some(Fix::Reactivate)));
+    assert_eq!(report.findings.len(), 4, "{report}");
+    let orphan = report
+        .findings
+        .iter()
+        .find(|f| f.path == trash.join("Stray (2001)"))
+        .expect("orphan in trash reported");
+    assert_eq!(orphan.fix, None);
+
+    doctor::repair(&pool, &mut report).await.unwrap();
+    assert_eq!(report.repaired, 3);
+    let status = |id: i64| {
+        let pool = pool.clone();
+        async move { media::get_by_id(&pool, id).await.unwrap().unwrap().status }
+    };
+    assert_eq!(status(missing).await, "gone");
+    assert_eq!(status(trashed_on_disk).await, "trashed");
+    assert_eq!(status(back_in_library).await, "active");
+    assert_eq!(mark::mark_count(&pool, missing).await.unwrap(), 0);
+    assert!(std::fs::metadata(trash.join("Stray (2001)")).is_ok());
+
+    let report = doctor::diagnose(&pool, &config).await.unwrap();
+    assert_eq!(report.findings.len(), 1, "{report}");
+    assert_eq!(report.findings[0].path, trash.join("Stray (2001)"));
+}
+
+#[tokio::test]
+async fn doctor_skips_items_in_unreachable_media_dir() {
+    let pool = test_pool().await;
+    let base = tempfile::tempdir().unwrap();
+    let movies = base.path().join("Movies");
+    let config = test_config(vec![movies.clone()]);
+    let id = insert_movie(&pool, "Heat", &movies.join("Heat (1995)").to_string_lossy()).await;
+
+    let report = doctor::diagnose(&pool, &config).await.unwrap();
+    assert_eq!(report.findings.len(), 1);
+    assert_eq!(report.findings[0].media_id, None);
+    assert_eq!(report.fixable(), 0);
+    assert!(report.findings.iter().all(|f| f.media_id != Some(id)));
+}
@@ -0,0 +1,46 @@
+mod common;
+
+use axum::http::StatusCode;
+use http_body_util::BodyExt;
+use std::time::Duration;
+use tower::ServiceExt;
+
+use common::*;
+use rewinder::models::media;
+
+// The event bus is process-wide, so this binary holds a single test.
+#[tokio::test]
+async fn event_stream_reports_changes_by_other_users() {
+    let pool = test_pool().await;
+    let config = test_config(vec![]);
+    let (alice_id, _) = create_test_user(&pool, "alice", false).await;
+    let cookie = login_cookie(&pool, alice_id).await;
+    let mine = insert_movie(&pool, "Mine", "/movies/Mine (2001)").await;
+    let theirs = insert_movie(&pool, "Theirs", "/movies/Theirs (2002)").await;
+
+    let app = test_app(pool.clone(), config, true);
+    let response = app
+        .oneshot(get_with_cookie("/events", &cookie))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "text/event-stream");
+    let mut body = response.into_body();
+
+    media::set_trashed(&pool, mine, "alice").await.unwrap();
+    media::set_trashed(&pool, theirs, "bob").await.unwrap();
+
+    let frame = tokio::time::timeout(Duration::from_secs(5), body.frame())
+        .await
+        .expect("no event within 5s")
+        .unwrap()
+        .unwrap();
+    let text = String::from_utf8(frame.into_data().unwrap().to_vec()).unwrap();
+    assert!(text.starts_with("event: media\n"), "{text}");
+    assert!(
+        text.contains(&format!(
+            r#""media_id":{theirs},"status":"trashed","actor":"bob""#
+        )),
+        "{text}"
+    );
+}
@@ -0,0 +1,90 @@
+mod common;
+
+use axum::http::StatusCode;
+use tower::ServiceExt;
+
+use common::*;
+use rewinder::models::{media, media_history, user};
+
+#[tokio::test]
+async fn feed_lists_trashed_and_deleted_items() {
+    let pool = test_pool().await;
+    let config = test_config(vec![]);
+    let (user_id, _) = create_test_user(&pool, "alice", false).await;
+    let cookie = login_cookie(&pool, user_id).await;
+    let heat = insert_movie(&pool, "Heat & Dust", "/movies/Heat & Dust").await;
+    let office = insert_tv_season(&pool, "The Office", 2, "/tv/The Office/Season 02").await;
+    media::set_trashed(&pool, heat, "alice").await.unwrap();
+    media::set_trashed(&pool, office, "bob").await.unwrap();
+    media::set_gone(&pool, office, media_history::CLEANUP)
+        .await
+        .unwrap();
+
+    let app = test_app(pool.clone(), config.clone(), true);
+    let response = app
+        .oneshot(post_form_with_cookie("/account/feed", "", &cookie))
+        .await
+        .unwrap();
+    assert_redirect(&response, "/account?saved=true").await;
+    let token = user::get_by_id(&pool, user_id)
+        .await
+        .unwrap()
+        .unwrap()
+        .feed_token
+        .unwrap();
+
+    let app = test_app(pool.clone(), config.clone(), true);
+    let response = app.oneshot(get(&format!("/feed/{token}"))).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "application/atom+xml");
+    let body = body_string(response).await;
+    assert!(body.contains("Trashed: Heat &amp; Dust"), "{body}");
+    assert!(body.contains("Trashed: The Office — Season 2"));
+    assert!(body.contains("Deleted: The Office — Season 2"));
+    assert!(body.contains(&format!("http://localhost/media/{heat}")));
+
+    let app = test_app(pool, config, true);
+    let response = app.oneshot(get("/feed/not-a-token")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn calendar_has_an_event_on_each_deletion_day() {
+    let pool = test_pool().await;
+    let mut config = test_config(vec![]);
+    config.grace_period_days = 7;
+    let (user_id, _) = create_test_user(&pool, "alice", false).await;
+    user::set_time_settings(&pool, user_id, "iso", "Asia/Tokyo")
+        .await
+        .unwrap();
+    user::set_feed_token(&pool, user_id, "secret")
+        .await
+        .unwrap();
+    let lotr = insert_movie(&pool, "The Lord of the Rings", "/movies/LOTR").await;
+    media::set_trashed(&pool, lotr, "alice").await.unwrap();
+    sqlx::query("UPDATE media SET trashed_at = '2024-03-24 20:00:00' WHERE id = ?")
+        .bind(lotr)
+        .execute(&pool)
+        .await
+        .unwrap();
+    insert_movie(&pool, "Heat", "/movies/Heat").await;
+
+    let app = test_app(pool.clone(), config.clone(), true);
+    let response = app
+        .oneshot(get("/feed/secret/deletions.ics"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers()["content-type"]
+        .to_str()
+        .unwrap()
+        .starts_with("text/calendar"));
+    let body = body_string(response).await;
+    assert_eq!(body.matches("BEGIN:VEVENT").count(), 1);
+    assert!(body.contains("DTSTART;VALUE=DATE:20240401\r\n"), "{body}");
+    assert!(body.contains("SUMMARY:Rewinder deletes The Lord of the Rings\r\n"));
+
+    let app = test_app(pool, config, true);
+    let response = app.oneshot(get("/feed/wrong/deletions.ics")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
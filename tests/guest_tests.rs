@@ -0,0 +1,51 @@
+mod common;
+
+use axum::http::StatusCode;
+use tower::ServiceExt;
+
+use common::*;
+
+const TOKEN: &str = "family-movie-night-2024";
+
+#[tokio::test]
+async fn guest_link_shows_active_library_without_actions() {
+    let pool = test_pool().await;
+    let mut config = test_config(vec![]);
+    config.guest_token = Some(TOKEN.to_string());
+    insert_movie(&pool, "Inception", "/movies/Inception (2010)").await;
+    let trashed = insert_movie(&pool, "Heat", "/movies/Heat (1995)").await;
+    rewinder::models::media::set_trashed(&pool, trashed, "alice")
+        .await
+        .unwrap();
+    insert_tv_season(&pool, "The Office", 1, "/tv/The Office/Season 01").await;
+    insert_tv_season(&pool, "The Office", 3, "/tv/The Office/Season 03").await;
+
+    let app = test_app(pool, config, true);
+    let response = app.oneshot(get(&format!("/guest/{TOKEN}"))).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = body_string(response).await;
+    assert!(body.contains("Inception"));
+    assert!(!body.contains("Heat"));
+    assert!(body.contains("Seasons 1, 3"));
+    assert!(!body.contains("hx-post"));
+    assert!(!body.contains("/logout"));
+}
+
+#[tokio::test]
+async fn guest_link_needs_the_configured_token() {
+    let pool = test_pool().await;
+    let mut config = test_config(vec![]);
+
+    let app = test_app(pool.clone(), config.clone(), true);
+    let response = app.oneshot(get(&format!("/guest/{TOKEN}"))).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    config.guest_token = Some(TOKEN.to_string());
+    let app = test_app(pool, config, true);
+    let response = app
+        .oneshot(get("/guest/some-other-long-token"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,78 @@
+mod common;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use tower::ServiceExt;
+
+use common::*;
+
+const TOKEN: &str = "prometheus-scrape-2024";
+
+fn scrape(token: &str) -> Request<Body> {
+    Request::builder()
+        .uri("/metrics")
+        .header("authorization", format!("Bearer {token}"))
+        .body(Body::empty())
+        .unwrap()
+}
+
+#[tokio::test]
+async fn metrics_break_sizes_down_by_library() {
+    let pool = test_pool().await;
+    let mut config = test_config(vec![
+        std::path::PathBuf::from("/movies"),
+        std::path::PathBuf::from("/tv"),
+    ]);
+    config.metrics_token = Some(TOKEN.to_string());
+    insert_movie(&pool, "Heat", "/movies/Heat (1995)").await;
+    let alien = insert_movie(&pool, "Alien", "/movies/Alien (1979)").await;
+    rewinder::models::media::set_trashed(&pool, alien, "alice")
+        .await
+        .unwrap();
+    insert_tv_season(&pool, "The Office", 1, "/tv/The Office/Season 01").await;
+    let kept = insert_movie(&pool, "Kept", "/movies_permanent/Kept (2000)").await;
+    sqlx::query("UPDATE media SET status = 'permanent' WHERE id = ?")
+        .bind(kept)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let app = test_app(pool, config, true);
+    let response = app.oneshot(scrape(TOKEN)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = body_string(response).await;
+    assert!(body.contains("# TYPE rewinder_library_bytes gauge"));
+    for line in [
+        r#"rewinder_library_bytes{library="/movies",status="active"} 1000000"#,
+        r#"rewinder_library_bytes{library="/movies",status="trashed"} 1000000"#,
+        r#"rewinder_library_bytes{library="/movies",status="permanent"} 1000000"#,
+        r#"rewinder_library_bytes{library="/tv",status="active"} 2000000"#,
+        r#"rewinder_library_bytes{library="/tv",status="trashed"} 0"#,
+        r#"rewinder_library_items{library="/movies",status="active"} 1"#,
+        r#"rewinder_library_items{library="/tv",status="active"} 1"#,
+    ] {
+        assert!(
+            body.lines().any(|l| l == line),
+            "{line} missing from\n{body}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn metrics_need_the_configured_token() {
+    let pool = test_pool().await;
+    let mut config = test_config(vec![]);
+
+    let app = test_app(pool.clone(), config.clone(), true);
+    let response = app.oneshot(scrape(TOKEN)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    config.metrics_token = Some(TOKEN.to_string());
+    let app = test_app(pool.clone(), config.clone(), true);
+    let response = app.oneshot(get("/metrics")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let app = test_app(pool, config, true);
+    let response = app.oneshot(scrape("some-other-long-token")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
@@ -43,6 +43,30 @@ async fn list_movies_shows_items() {
     assert!(body.contains("The Matrix"));
 }
 
+#[tokio::test]
+async fn list_movies_shows_when_items_were_added() {
+    let pool = test_pool().await;
+    let config = test_config(vec![]);
+    let (user_id, _) = create_test_user(&pool, "alice", false).await;
+    let cookie = login_cookie(&pool, user_id).await;
+
+    let id = insert_movie(&pool, "Inception", "/movies/Inception (2010)").await;
+    sqlx::query("UPDATE media SET first_seen = datetime('now', '-22 days') WHERE id = ?")
+        .bind(id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let app = test_app(pool, config, true);
+    let body = body_string(
+        app.oneshot(get_with_cookie("/movies", &cookie))
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert!(body.contains("added 3 weeks ago"));
+}
+
 #[tokio::test]
 async fn mark_movie() {
     let pool = test_pool().await;
@@ -295,7 +319,7 @@ async fn set_and_read_poster_path() {
         .unwrap());
 
     // Set poster
-    rewinder::models::media::set_poster(&pool, movie_id, "/abc123.jpg")
+    rewinder::models::media::set_poster(&pool, movie_id, "/abc123.jpg", None)
         .await
         .unwrap();
 
@@ -311,3 +335,872 @@ async fn set_and_read_poster_path() {
         .unwrap();
     assert_eq!(media.poster_path.as_deref(), Some("/abc123.jpg"));
 }
+
+#[tokio::test]
+async fn movie_card_renders_current_state() {
+    let pool = test_pool().await;
+    let config = test_config(vec![]);
+    let (user_id, _) = create_test_user(&pool, "alice", false).await;
+    let cookie = login_cookie(&pool, user_id).await;
+    let movie_id = insert_movie(&pool, "Inception", "/movies/Inception (2010)").await;
+    let season_id = insert_tv_season(&pool, "Severance", 1, "/tv/Severance/Season 1").await;
+    rewinder::models::mark::mark(&pool, user_id, movie_id)
+        .await
+        .unwrap();
+
+    let app = test_app(pool.clone(), config.clone(), true);
+    let response = app
+        .oneshot(get_with_cookie(
+            &format!("/movies/{movie_id}/card"),
+            &cookie,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = body_string(response).await;
+    assert!(body.contains(&format!("id=\"media-{movie_id}\"")));
+    assert!(body.contains("Unmark"));
+
+    let app = test_app(pool.clone(), config.clone(), true);
+    let response = app
+        .oneshot(get_with_cookie(
+            &format!("/movies/{season_id}/card"),
+            &cookie,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    rewinder::models::media::set_trashed(&pool, movie_id, "bob")
+        .await
+        .unwrap();
+    let app = test_app(pool, config, true);
+    let response = app
+        .oneshot(get_with_cookie(
+            &format!("/movies/{movie_id}/card"),
+            &cookie,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(body_string(response).await, "");
+}
+
+#[tokio::test]
+async fn movies_answer_unchanged_etag_with_not_modified() {
+    let pool = test_pool().await;
+    let config = test_config(vec![]);
+    let (user_id, _) = create_test_user(&pool, "alice", false).await;
+    let cookie = login_cookie(&pool, user_id).await;
+    let movie_id = insert_movie(&pool, "Inception", "/movies/Inception (2010)").await;
+    let app = test_app(pool.clone(), config, true);
+
+    let response = app
+        .clone()
+        .oneshot(get_with_cookie("/movies", &cookie))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let etag = response.headers()["etag"].to_str().unwrap().to_string();
+
+    let conditional = || {
+        let mut req = get_with_cookie("/movies", &cookie);
+        req.headers_mut()
+            .insert("if-none-match", etag.parse().unwrap());
+        req
+    };
+    let response = app.clone().oneshot(conditional()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    assert!(body_string(response).await.is_empty());
+
+    rewinder::models::mark::mark(&pool, user_id, movie_id)
+        .await
+        .unwrap();
+    let response = app.clone().oneshot(conditional()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_ne!(response.headers()["etag"], etag.as_str());
+
+    let response = app
+        .oneshot(get_with_cookie("/movies?sort=year", &cookie))
+        .await
+        .unwrap();
+    assert_ne!(response.headers()["etag"], etag.as_str());
+}
+
+#[tokio::test]
+async fn movies_etag_follows_editions_and_announcements() {
+    let pool = test_pool().await;
+    let config = test_config(vec![]);
+    let (user_id, _) = create_test_user(&pool, "alice", false).await;
+    let cookie = login_cookie(&pool, user_id).await;
+    let movie_id = insert_movie(&pool, "Heat", "/movies/Heat (1995)").await;
+    let cut_id = insert_movie(&pool, "Heat", "/movies/Heat (1995) Director's Cut").await;
+    let app = test_app(pool.clone(), config, true);
+
+    let etag = || async {
+        let response = app
+            .clone()
+            .oneshot(get_with_cookie("/movies", &cookie))
+            .await
+            .unwrap();
+        response.headers()["etag"].to_str().unwrap().to_string()
+    };
+    let before = etag().await;
+    rewinder::models::edition::link(&pool, cut_id, movie_id)
+        .await
+        .unwrap();
+    let linked = etag().await;
+    assert_ne!(linked, before);
+
+    rewinder::models::announcement::create(&pool, "Disk swap tonight", "admin")
+        .await
+        .unwrap();
+    assert_ne!(etag().await, linked);
+}
+
+#[tokio::test]
+async fn movies_page_is_compressed_when_accepted() {
+    let pool = test_pool().await;
+    let config = test_config(vec![]);
+    let (user_id, _) = create_test_user(&pool, "alice", false).await;
+    let cookie = login_cookie(&pool, user_id).await;
+    insert_movie(&pool, "Inception", "/movies/Inception (2010)").await;
+    let app = test_app(pool, config, true);
+
+    let mut req = get_with_cookie("/movies", &cookie);
+    req.headers_mut()
+        .insert("accept-encoding", "br, gzip".parse().unwrap());
+    let response = app.clone().oneshot(req).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-encoding"], "br");
+
+    let response = app
+        .oneshot(get_with_cookie("/movies", &cookie))
+        .await
+        .unwrap();
+    assert!(response.headers().get("content-encoding").is_none());
+    assert!(body_string(response).await.contains("Inception"));
+}
+
+#[tokio::test]
+async fn movies_mark_counts_follow_marks_by_other_users() {
+    let pool = test_pool().await;
+    let config = test_config(vec![]);
+    let (admin_id, _) = create_test_user(&pool, "admin", true).await;
+    let (user_id, _) = create_test_user(&pool, "alice", false).await;
+    let admin_cookie = login_cookie(&pool, admin_id).await;
+    let user_cookie = login_cookie(&pool, user_id).await;
+    let movie_id = insert_movie(&pool, "Inception", "/movies/Inception (2010)").await;
+    let app = test_app(pool, config, true);
+
+    let body = body_string(
+        app.clone()
+            .oneshot(get_with_cookie("/movies", &admin_cookie))
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert!(body.contains("0 / 2"));
+
+    let response = app
+        .clone()
+        .oneshot(post_form_with_cookie(
+            &format!("/movies/{movie_id}/mark"),
+            "",
+            &user_cookie,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = body_string(
+        app.oneshot(get_with_cookie("/movies", &admin_cookie))
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert!(body.contains("1 / 2"));
+}
+
+#[tokio::test]
+async fn reclaimable_space_counts_own_marks_and_trash() {
+    let pool = test_pool().await;
+    let config = test_config(vec![]);
+    let (user_id, _) = create_test_user(&pool, "alice", false).await;
+    let (other_id, _) = create_test_user(&pool, "bob", false).await;
+    let cookie = login_cookie(&pool, user_id).await;
+    let mine = insert_movie(&pool, "Inception", "/movies/Inception (2010)").await;
+    let theirs = insert_movie(&pool, "Heat", "/movies/Heat (1995)").await;
+    let trashed = insert_movie(&pool, "Alien", "/movies/Alien (1979)").await;
+    for (id, size) in [(mine, 1 << 30), (theirs, 4 << 30), (trashed, 2 << 30)] {
+        rewinder::models::media::set_size(&pool, id, size)
+            .await
+            .unwrap();
+    }
+    rewinder::models::mark::mark(&pool, user_id, mine)
+        .await
+        .unwrap();
+    rewinder::models::mark::mark(&pool, other_id, theirs)
+        .await
+        .unwrap();
+    rewinder::models::media::set_trashed(&pool, trashed, "bob")
+        .await
+        .unwrap();
+
+    let app = test_app(pool, config, true);
+    let body = body_string(
+        app.clone()
+            .oneshot(get_with_cookie("/movies", &cookie))
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert!(body.contains("Space you could free: <strong>3.0 GB</strong>"));
+
+    let response = app
+        .oneshot(get_with_cookie("/reclaimable", &cookie))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = body_string(response).await;
+    assert!(body.contains("1.0 GB you marked is waiting on other users"));
+    assert!(body.contains("2.0 GB is already in the trash"));
+}
+
+#[tokio::test]
+async fn tagged_movies_can_be_filtered_by_tag() {
+    let pool = test_pool().await;
+    let config = test_config(vec![]);
+    let (user_id, _) = create_test_user(&pool, "alice", false).await;
+    let cookie = login_cookie(&pool, user_id).await;
+    let frozen = insert_movie(&pool, "Frozen", "/movies/Frozen").await;
+    insert_movie(&pool, "Heat", "/movies/Heat").await;
+
+    let app = test_app(pool.clone(), config.clone(), true);
+    let response = app
+        .oneshot(post_form_with_cookie(
+            &format!("/media/{frozen}/tags"),
+            "tag=+Kids+",
+            &cookie,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(body_string(response).await.contains("Kids"));
+    assert_eq!(
+        rewinder::models::tag::for_media(&pool, frozen)
+            .await
+            .unwrap(),
+        vec!["Kids"]
+    );
+
+    // Tags compare case-insensitively.
+    let app = test_app(pool.clone(), config.clone(), true);
+    let response = app
+        .oneshot(get_with_cookie("/movies?tag=kids", &cookie))
+        .await
+        .unwrap();
+    let body = body_string(response).await;
+    assert!(body.contains("Frozen"));
+    assert!(!body.contains("Heat"));
+    assert!(body.contains("tag-filter"));
+
+    let app = test_app(pool.clone(), config, true);
+    let response = app
+        .oneshot(delete_with_cookie(
+            &format!("/media/{frozen}/tags/kids"),
+            &cookie,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(rewinder::models::tag::for_media(&pool, frozen)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn comments_show_on_the_detail_page_and_card() {
+    let pool = test_pool().await;
+    let config = test_config(vec![]);
+    let (alice, _) = create_test_user(&pool, "alice", false).await;
+    let (bob, _) = create_test_user(&pool, "bob", false).await;
+    let alice_cookie = login_cookie(&pool, alice).await;
+    let bob_cookie = login_cookie(&pool, bob).await;
+    let frozen = insert_movie(&pool, "Frozen", "/movies/Frozen").await;
+
+    let app = test_app(pool.clone(), config.clone(), true);
+    let response = app
+        .oneshot(post_form_with_cookie(
+            &format!("/media/{frozen}/comments"),
+            "body=keep+until+mom+finishes+it",
+            &alice_cookie,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(body_string(response)
+        .await
+        .contains("keep until mom finishes it"));
+
+    let app = test_app(pool.clone(), config.clone(), true);
+    let response = app
+        .oneshot(post_form_with_cookie(
+            &format!("/media/{frozen}/comments"),
+            "body=+++",
+            &alice_cookie,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let app = test_app(pool.clone(), config.clone(), true);
+    let response = app
+        .oneshot(get_with_cookie(&format!("/media/{frozen}"), &bob_cookie))
+        .await
+        .unwrap();
+    assert!(body_string(response)
+        .await
+        .contains("keep until mom finishes it"));
+
+    // The card links to the thread and shows it on hover.
+    let app = test_app(pool.clone(), config.clone(), true);
+    let response = app
+        .oneshot(get_with_cookie("/movies", &bob_cookie))
+        .await
+        .unwrap();
+    let body = body_string(response).await;
+    assert!(body.contains("1 comment"), "{body}");
+    assert!(body.contains("alice: keep until mom finishes it"), "{body}");
+
+    // Only the author or an admin may remove it.
+    let comment_id = rewinder::models::comment::for_media(&pool, frozen)
+        .await
+        .unwrap()[0]
+        .id;
+    let app = test_app(pool.clone(), config.clone(), true);
+    let response = app
+        .oneshot(delete_with_cookie(
+            &format!("/media/{frozen}/comments/{comment_id}"),
+            &bob_cookie,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let app = test_app(pool.clone(), config, true);
+    let response = app
+        .oneshot(delete_with_cookie(
+            &format!("/media/{frozen}/comments/{comment_id}"),
+            &alice_cookie,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(rewinder::models::comment::for_media(&pool, frozen)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn restricted_library_is_hidden_and_voted_on_by_granted_users_only() {
+    let pool = test_pool().await;
+    let config = test_config(vec![
+        std::path::PathBuf::from("/movies"),
+        std::path::PathBuf::from("/docs"),
+    ]);
+    let (dad_id, _) = create_test_user(&pool, "dad", false).await;
+    let (kid_id, _) = create_test_user(&pool, "kid", false).await;
+    let dad = login_cookie(&pool, dad_id).await;
+    let kid = login_cookie(&pool, kid_id).await;
+    let doc_id = insert_movie(&pool, "Planet Earth", "/docs/Planet Earth").await;
+    insert_movie(&pool, "Inception", "/movies/Inception").await;
+    rewinder::models::library_access::grant(&pool, "/docs", dad_id)
+        .await
+        .unwrap();
+
+    let app = test_app(pool.clone(), config, true);
+    let response = app
+        .clone()
+        .oneshot(get_with_cookie("/movies", &kid))
+        .await
+        .unwrap();
+    let body = body_string(response).await;
+    assert!(body.contains("Inception"));
+    assert!(!body.contains("Planet Earth"));
+
+    let response = app
+        .clone()
+        .oneshot(post_form_with_cookie(
+            &format!("/movies/{doc_id}/mark"),
+            "",
+            &kid,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // Dad is the only voter, so his mark alone trashes it.
+    let response = app
+        .oneshot(post_form_with_cookie(
+            &format!("/movies/{doc_id}/mark"),
+            "",
+            &dad,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let item = rewinder::models::media::get_by_id(&pool, doc_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(item.status, "trashed");
+}
+
+#[tokio::test]
+async fn restricted_users_marks_never_complete_the_quorum() {
+    let pool = test_pool().await;
+    let config = test_config(vec![]);
+    let (parent_id, _) = create_test_user(&pool, "parent", false).await;
+    let (kid_id, _) = create_test_user(&pool, "kid", false).await;
+    rewinder::models::user::set_restricted(&pool, kid_id, true)
+        .await
+        .unwrap();
+    let parent = login_cookie(&pool, parent_id).await;
+    let kid = login_cookie(&pool, kid_id).await;
+    let movie_id = insert_movie(&pool, "Frozen", "/movies/Frozen").await;
+
+    let app = test_app(pool.clone(), config, true);
+    let response = app
+        .clone()
+        .oneshot(post_form_with_cookie(
+            &format!("/movies/{movie_id}/mark"),
+            "",
+            &kid,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = app
+        .clone()
+        .oneshot(post_form_with_cookie(
+            &format!("/movies/{movie_id}/persist"),
+            "",
+            &kid,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // Kids' wishes alone trash nothing, even in a library only they can see.
+    rewinder::models::library_access::grant(&pool, "/movies", kid_id)
+        .await
+        .unwrap();
+    assert!(
+        !rewinder::models::mark::all_users_marked(&pool, movie_id, false)
+            .await
+            .unwrap()
+    );
+    rewinder::models::library_access::revoke(&pool, "/movies", kid_id)
+        .await
+        .unwrap();
+
+    app.oneshot(post_form_with_cookie(
+        &format!("/movies/{movie_id}/mark"),
+        "",
+        &parent,
+    ))
+    .await
+    .unwrap();
+    let item = rewinder::models::media::get_by_id(&pool, movie_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(item.status, "trashed");
+}
+
+#[tokio::test]
+async fn keep_votes_hold_off_trashing_until_they_run_out() {
+    let pool = test_pool().await;
+    let config = test_config(vec![]);
+    let (alice_id, _) = create_test_user(&pool, "alice", false).await;
+    let (bob_id, _) = create_test_user(&pool, "bob", false).await;
+    let alice = login_cookie(&pool, alice_id).await;
+    let bob = login_cookie(&pool, bob_id).await;
+    let movie_id = insert_movie(&pool, "Heat", "/movies/Heat").await;
+
+    let app = test_app(pool.clone(), config.clone(), true);
+    let response = app
+        .clone()
+        .oneshot(post_form_with_cookie(
+            &format!("/media/{movie_id}/keep"),
+            "",
+            &alice,
+        ))
+        .await
+        .unwrap();
+    let body = body_string(response).await;
+    assert!(body.contains("Kept by you") && body.contains("Drop Keep"));
+
+    // Like a rule marking it for everyone.
+    rewinder::models::mark::mark_for_all(&pool, movie_id)
+        .await
+        .unwrap();
+    app.oneshot(post_form_with_cookie(
+        &format!("/movies/{movie_id}/mark"),
+        "",
+        &bob,
+    ))
+    .await
+    .unwrap();
+    let status = || async {
+        rewinder::models::media::get_by_id(&pool, movie_id)
+            .await
+            .unwrap()
+            .unwrap()
+            .status
+    };
+    assert_eq!(status().await, "active");
+
+    sqlx::query("UPDATE keep_votes SET created_at = datetime('now', '-31 days')")
+        .execute(&pool)
+        .await
+        .unwrap();
+    rewinder::trash::trash_deferred(&pool, &config, true)
+        .await
+        .unwrap();
+    assert_eq!(status().await, "trashed");
+}
+
+#[tokio::test]
+async fn required_admin_mark_completes_the_quorum() {
+    let pool = test_pool().await;
+    let mut config = test_config(vec![std::path::PathBuf::from("/kids")]);
+    config.require_admin_mark = true;
+    let (admin_id, _) = create_test_user(&pool, "admin", true).await;
+    let (user_id, _) = create_test_user(&pool, "alice", false).await;
+    let user = login_cookie(&pool, user_id).await;
+    let movie_id = insert_movie(&pool, "Kids Movie", "/kids/Kids Movie").await;
+    // Only alice votes on the kids library.
+    rewinder::models::library_access::grant(&pool, "/kids", user_id)
+        .await
+        .unwrap();
+
+    let app = test_app(pool.clone(), config.clone(), true);
+    app.oneshot(post_form_with_cookie(
+        &format!("/movies/{movie_id}/mark"),
+        "",
+        &user,
+    ))
+    .await
+    .unwrap();
+    let status = || async {
+        rewinder::models::media::get_by_id(&pool, movie_id)
+            .await
+            .unwrap()
+            .unwrap()
+            .status
+    };
+    assert_eq!(status().await, "active");
+    assert!(
+        rewinder::models::mark::all_users_marked(&pool, movie_id, false)
+            .await
+            .unwrap()
+    );
+
+    rewinder::models::library_access::grant(&pool, "/kids", admin_id)
+        .await
+        .unwrap();
+    rewinder::models::mark::mark(&pool, admin_id, movie_id)
+        .await
+        .unwrap();
+    rewinder::trash::trash_deferred(&pool, &config, true)
+        .await
+        .unwrap();
+    assert_eq!(status().await, "trashed");
+}
+
+#[tokio::test]
+async fn list_rows_carry_votes_and_the_users_own_state() {
+    let pool = test_pool().await;
+    let (alice, _) = create_test_user(&pool, "alice", false).await;
+    let (bob, _) = create_test_user(&pool, "bob", false).await;
+    let (kid, _) = create_test_user(&pool, "kid", false).await;
+    rewinder::models::user::set_restricted(&pool, kid, true)
+        .await
+        .unwrap();
+    let heat = insert_movie(&pool, "Heat", "/movies/Heat (1995)").await;
+    let ronin = insert_movie(&pool, "Ronin", "/movies/Ronin (1998)").await;
+    let alien = insert_movie(&pool, "Alien", "/movies/Alien (1979)").await;
+    for user in [alice, bob, kid] {
+        rewinder::models::mark::mark(&pool, user, heat)
+            .await
+            .unwrap();
+    }
+    rewinder::models::keep_vote::keep(&pool, alice, ronin)
+        .await
+        .unwrap();
+    rewinder::models::media::set_permanent(&pool, alien, "alice")
+        .await
+        .unwrap();
+    rewinder::models::persistent::set_owner(&pool, alien, alice)
+        .await
+        .unwrap();
+
+    let rows = rewinder::models::media::list_page_rows(&pool, "movie", alice, 30)
+        .await
+        .unwrap();
+    let row = |id| rows.iter().find(|r| r.media.id == id).unwrap();
+    assert_eq!(rows.len(), 3);
+    assert_eq!((row(heat).mark_count, row(heat).total_users), (2, 2));
+    assert!(row(heat).marked && !row(heat).kept);
+    assert!(row(ronin).kept && !row(ronin).marked);
+    assert!(row(alien).persisted_by_me);
+
+    // Someone else's persisted items are not on their page.
+    let rows = rewinder::models::media::list_page_rows(&pool, "movie", bob, 30)
+        .await
+        .unwrap();
+    assert_eq!(rows.len(), 2);
+    assert!(rows.iter().all(|r| !r.kept && !r.persisted_by_me));
+}
+
+#[tokio::test]
+async fn movies_json_pages_through_the_list() {
+    let pool = test_pool().await;
+    let config = test_config(vec![]);
+    let (user_id, _) = create_test_user(&pool, "alice", false).await;
+    let cookie = login_cookie(&pool, user_id).await;
+    for title in ["Alien", "Brazil", "Casino"] {
+        insert_movie(&pool, title, &format!("/movies/{title}")).await;
+    }
+    rewinder::models::preferences::save(
+        &pool,
+        user_id,
+        &rewinder::models::preferences::Preferences {
+            page_size: 2,
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    let app = test_app(pool, config, true);
+
+    let response = app
+        .clone()
+        .oneshot(get_with_cookie("/movies.json?sort=name&dir=desc", &cookie))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let json: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+    assert_eq!(
+        (json["page"].as_u64(), json["pages"].as_u64()),
+        (Some(1), Some(2))
+    );
+    assert_eq!(json["next_page"], 2);
+    let titles: Vec<_> = json["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|i| i["title"].as_str().unwrap())
+        .collect();
+    assert_eq!(titles, ["Casino", "Brazil"]);
+    assert!(json["items"][0]["card_url"]
+        .as_str()
+        .unwrap()
+        .ends_with("/card"));
+    // Mark counts stay admin-only, as on the cards.
+    assert!(json["items"][0].get("mark_count").is_none());
+
+    let json: serde_json::Value = serde_json::from_str(
+        &body_string(
+            app.clone()
+                .oneshot(get_with_cookie(
+                    "/movies.json?sort=name&dir=desc&page=2",
+                    &cookie,
+                ))
+                .await
+                .unwrap(),
+        )
+        .await,
+    )
+    .unwrap();
+    assert_eq!(json["items"][0]["title"], "Alien");
+    assert!(json["next_page"].is_null());
+
+    // The HTML page loads the next page as the user scrolls to its end.
+    let body = body_string(
+        app.oneshot(get_with_cookie("/movies?sort=name&dir=desc", &cookie))
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert!(body.contains(r#"class="load-more""#));
+    assert!(body.contains("dir=desc&page=2"));
+}
+
+#[tokio::test]
+async fn cards_load_the_small_poster_rendition() {
+    let pool = test_pool().await;
+    let config = test_config(vec![]);
+    let (user_id, _) = create_test_user(&pool, "alice", false).await;
+    let cookie = login_cookie(&pool, user_id).await;
+    let movie_id = insert_movie(&pool, "Heat", "/movies/Heat (1995)").await;
+    rewinder::models::media::set_poster(&pool, movie_id, "/heat.jpg", None)
+        .await
+        .unwrap();
+    let app = test_app(pool, config, true);
+
+    let body = body_string(
+        app.oneshot(get_with_cookie("/movies", &cookie))
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert!(body.contains(r#"src="https://image.tmdb.org/t/p/w185/heat.jpg""#));
+    assert!(body.contains("w185/heat.jpg 185w, https://image.tmdb.org/t/p/w342/heat.jpg 342w"));
+}
+
+#[tokio::test]
+async fn cards_show_the_poster_placeholder_until_it_loads() {
+    let pool = test_pool().await;
+    let config = test_config(vec![]);
+    let (user_id, _) = create_test_user(&pool, "alice", false).await;
+    let cookie = login_cookie(&pool, user_id).await;
+    let heat = insert_movie(&pool, "Heat", "/movies/Heat (1995)").await;
+    let ronin = insert_movie(&pool, "Ronin", "/movies/Ronin (1998)").await;
+    rewinder::models::media::set_poster(
+        &pool,
+        heat,
+        "/heat.jpg",
+        Some("LEHV6nWB2yk8pyo0adR*.7kCMdnj"),
+    )
+    .await
+    .unwrap();
+    rewinder::models::media::set_poster(&pool, ronin, "/ronin.jpg", None)
+        .await
+        .unwrap();
+    let app = test_app(pool, config, true);
+
+    let body = body_string(
+        app.oneshot(get_with_cookie("/movies", &cookie))
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(body.matches("background-image: url(").count(), 1);
+    assert!(body.contains("data:image/bmp;base64,"));
+}
+
+async fn linked_editions(pool: &sqlx::SqlitePool) -> (i64, i64) {
+    let hd = insert_movie(pool, "Heat", "/movies/Heat (1995) 1080p").await;
+    let uhd = insert_movie(pool, "Heat", "/movies/Heat (1995) 2160p").await;
+    sqlx::query("UPDATE media SET size_bytes = 4000000 WHERE id = ?")
+        .bind(uhd)
+        .execute(pool)
+        .await
+        .unwrap();
+    rewinder::models::edition::link(pool, hd, uhd)
+        .await
+        .unwrap();
+    (hd, uhd)
+}
+
+#[tokio::test]
+async fn admins_link_editions_shown_together() {
+    let pool = test_pool().await;
+    let config = test_config(vec![]);
+    let (admin_id, _) = create_test_user(&pool, "admin", true).await;
+    let cookie = login_cookie(&pool, admin_id).await;
+    let hd = insert_movie(&pool, "Heat", "/movies/Heat (1995) 1080p").await;
+    let uhd = insert_movie(&pool, "Heat", "/movies/Heat (1995) 2160p").await;
+    let other = insert_movie(&pool, "Ronin", "/movies/Ronin (1998)").await;
+
+    let app = test_app(pool.clone(), config, true);
+    let response = app
+        .clone()
+        .oneshot(post_form_with_cookie(
+            &format!("/admin/media/{hd}/editions"),
+            &format!("other_id={other}"),
+            &cookie,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = app
+        .clone()
+        .oneshot(post_form_with_cookie(
+            &format!("/admin/media/{hd}/editions"),
+            &format!("other_id={uhd}"),
+            &cookie,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+
+    let body = body_string(
+        app.clone()
+            .oneshot(get_with_cookie("/movies", &cookie))
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert!(body.contains("2 editions"));
+    let body = body_string(
+        app.clone()
+            .oneshot(get_with_cookie(&format!("/media/{uhd}"), &cookie))
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert!(body.contains("id=\"editions\""));
+    assert!(body.contains("Heat (1995) 1080p"));
+
+    app.oneshot(post_form_with_cookie(
+        &format!("/admin/media/{hd}/unlink-edition"),
+        "",
+        &cookie,
+    ))
+    .await
+    .unwrap();
+    assert!(rewinder::models::edition::editions_of(&pool, uhd)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn keeping_the_best_edition_trashes_the_rest() {
+    let pool = test_pool().await;
+    let config = test_config(vec![]);
+    let (user_id, _) = create_test_user(&pool, "alice", false).await;
+    let cookie = login_cookie(&pool, user_id).await;
+    let (hd, uhd) = linked_editions(&pool).await;
+
+    let app = test_app(pool.clone(), config, true);
+    let response = app
+        .oneshot(post_form_with_cookie(
+            &format!("/media/{hd}/keep-best"),
+            "",
+            &cookie,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+
+    let status = |id| {
+        let pool = pool.clone();
+        async move {
+            rewinder::models::media::get_by_id(&pool, id)
+                .await
+                .unwrap()
+                .unwrap()
+                .status
+        }
+    };
+    assert_eq!(status(hd).await, "trashed");
+    assert_eq!(status(uhd).await, "active");
+}
@@ -203,3 +203,117 @@ async fn tv_persist_all_series_persists_every_season() {
     assert_eq!(media1.status, "permanent");
     assert_eq!(media2.status, "permanent");
 }
+
+#[tokio::test]
+async fn a_persisted_show_takes_in_new_seasons_and_unpersists_as_one() {
+    let pool = test_pool().await;
+    let config = test_config(vec![]);
+    let (alice_id, _) = create_test_user(&pool, "alice", false).await;
+    let cookie = login_cookie(&pool, alice_id).await;
+    let s1 = insert_tv_season(&pool, "Slow Horses", 1, "/tv/Slow Horses/Season 1").await;
+
+    let app = test_app(pool.clone(), config.clone(), true);
+    app.oneshot(post_form_with_cookie(
+        "/tv/series/Slow%20Horses/persist-all",
+        "",
+        &cookie,
+    ))
+    .await
+    .unwrap();
+    // Season 2 turns up a while after the show was persisted.
+    sqlx::query("UPDATE persistent_series SET persisted_at = datetime('now', '-1 day')")
+        .execute(&pool)
+        .await
+        .unwrap();
+    let s2 = insert_tv_season(&pool, "Slow Horses", 2, "/tv/Slow Horses/Season 2").await;
+    assert!(
+        rewinder::models::persistent::awaits_series_persist(&pool, s2)
+            .await
+            .unwrap()
+    );
+    assert_eq!(
+        rewinder::persistent::persist_new_seasons(&pool, &config, true)
+            .await
+            .unwrap(),
+        1
+    );
+
+    let app = test_app(pool.clone(), config.clone(), true);
+    let body = body_string(app.oneshot(get_with_cookie("/tv", &cookie)).await.unwrap()).await;
+    assert_eq!(body.matches("Show persisted by you").count(), 1);
+    assert!(!body.contains("Persisted by you"));
+    assert!(body.contains("Unpersist Show"));
+
+    // Seasons are only unpersisted with their show.
+    let app = test_app(pool.clone(), config.clone(), true);
+    let response = app
+        .oneshot(delete_with_cookie(&format!("/tv/{s1}/persist"), &cookie))
+        .await
+        .unwrap();
+    assert_ne!(response.status(), StatusCode::OK);
+
+    let app = test_app(pool.clone(), config, true);
+    let response = app
+        .oneshot(delete_with_cookie(
+            "/tv/series/Slow%20Horses/persist-all",
+            &cookie,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    for id in [s1, s2] {
+        let item = rewinder::models::media::get_by_id(&pool, id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(item.status, "active");
+    }
+    assert!(
+        rewinder::models::persistent::get_series_owner(&pool, "Slow Horses")
+            .await
+            .unwrap()
+            .is_none()
+    );
+}
+
+#[tokio::test]
+async fn media_page_shows_history_to_the_owner_only() {
+    let pool = test_pool().await;
+    let config = test_config(vec![]);
+    let (alice_id, _) = create_test_user(&pool, "alice", false).await;
+    let (bob_id, _) = create_test_user(&pool, "bob", false).await;
+    let alice_cookie = login_cookie(&pool, alice_id).await;
+    let bob_cookie = login_cookie(&pool, bob_id).await;
+
+    let movie_id = insert_movie(&pool, "Private Movie", "/movies/Private Movie (2020)").await;
+
+    let app = test_app(pool.clone(), config.clone(), true);
+    app.oneshot(post_form_with_cookie(
+        &format!("/movies/{movie_id}/persist"),
+        "",
+        &alice_cookie,
+    ))
+    .await
+    .unwrap();
+
+    let app = test_app(pool.clone(), config.clone(), true);
+    let response = app
+        .oneshot(get_with_cookie(
+            &format!("/media/{movie_id}"),
+            &alice_cookie,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = body_string(response).await;
+    assert!(body.contains("<td>active</td>"));
+    assert!(body.contains("<td>permanent</td>"));
+    assert!(body.contains("<td>alice</td>"));
+
+    let app = test_app(pool, config, true);
+    let response = app
+        .oneshot(get_with_cookie(&format!("/media/{movie_id}"), &bob_cookie))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,121 @@
+mod common;
+
+use axum::body::Body;
+use axum::extract::ConnectInfo;
+use axum::http::{Request, StatusCode};
+use std::net::SocketAddr;
+use tower::ServiceExt;
+
+use common::*;
+
+fn login_from(ip: &str) -> Request<Body> {
+    let mut req = post_form("/login", "username=alice&password=wrong");
+    let addr: SocketAddr = format!("{ip}:40000").parse().unwrap();
+    req.extensions_mut().insert(ConnectInfo(addr));
+    req
+}
+
+#[tokio::test]
+async fn login_attempts_are_limited_per_ip() {
+    let pool = test_pool().await;
+    let mut config = test_config(vec![]);
+    config.login_rate_limit_per_minute = 3;
+    create_test_user(&pool, "alice", false).await;
+    let app = test_app(pool, config, true);
+
+    for _ in 0..3 {
+        let response = app.clone().oneshot(login_from("10.0.0.5")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+    let response = app.clone().oneshot(login_from("10.0.0.5")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    let retry_after: u64 = response.headers()["retry-after"]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!((1..=20).contains(&retry_after));
+
+    let response = app.oneshot(login_from("10.0.0.6")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn state_changes_are_limited_per_session() {
+    let pool = test_pool().await;
+    let mut config = test_config(vec![]);
+    config.rate_limit_per_minute = 2;
+    let (user_id, _) = create_test_user(&pool, "alice", false).await;
+    create_test_user(&pool, "bob", false).await;
+    let cookie = login_cookie(&pool, user_id).await;
+    let id = insert_movie(&pool, "Heat", "/movies/Heat (1995)").await;
+    let app = test_app(pool, config, true);
+
+    let mark = || post_form_with_cookie(&format!("/movies/{id}/mark"), "", &cookie);
+    assert_eq!(
+        app.clone().oneshot(mark()).await.unwrap().status(),
+        StatusCode::OK
+    );
+    assert_eq!(
+        app.clone().oneshot(mark()).await.unwrap().status(),
+        StatusCode::OK
+    );
+    assert_eq!(
+        app.clone().oneshot(mark()).await.unwrap().status(),
+        StatusCode::TOO_MANY_REQUESTS
+    );
+
+    // Page views are never limited.
+    let response = app
+        .oneshot(get_with_cookie("/movies", &cookie))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn oversized_bodies_are_refused() {
+    let pool = test_pool().await;
+    let mut config = test_config(vec![]);
+    config.max_body_kb = 1;
+    let (user_id, _) = create_test_user(&pool, "alice", false).await;
+    let cookie = login_cookie(&pool, user_id).await;
+    let id = insert_movie(&pool, "Heat", "/movies/Heat (1995)").await;
+    let app = test_app(pool, config, true);
+
+    let body = format!("body={}", "x".repeat(2048));
+    let response = app
+        .oneshot(post_form_with_cookie(
+            &format!("/media/{id}/comments"),
+            &body,
+            &cookie,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+}
+
+#[tokio::test]
+async fn a_stalled_body_times_out() {
+    let pool = test_pool().await;
+    let mut config = test_config(vec![]);
+    config.request_timeout_secs = 1;
+    let (user_id, _) = create_test_user(&pool, "alice", false).await;
+    let cookie = login_cookie(&pool, user_id).await;
+    let id = insert_movie(&pool, "Heat", "/movies/Heat (1995)").await;
+    let app = test_app(pool, config, true);
+
+    let stalled = futures_util::stream::pending::<Result<Vec<u8>, std::io::Error>>();
+    let req = Request::builder()
+        .method("POST")
+        .uri(format!("/media/{id}/comments"))
+        .header("content-type", "application/x-www-form-urlencoded")
+        .header("cookie", &cookie)
+        .body(Body::from_stream(stalled))
+        .unwrap();
+    let response = tokio::time::timeout(std::time::Duration::from_secs(5), app.oneshot(req))
+        .await
+        .expect("the stalled request was not cut off")
+        .unwrap();
+    assert!(response.status().is_client_error());
+}
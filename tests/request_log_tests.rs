@@ -0,0 +1,100 @@
+mod common;
+
+use common::*;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use tower::ServiceExt;
+
+#[derive(Clone, Default)]
+struct Captured(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Captured {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Captured {
+    fn text(&self) -> String {
+        String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+    }
+}
+
+fn capture_logs() -> (Captured, tracing::subscriber::DefaultGuard) {
+    let captured = Captured::default();
+    let writer = captured.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .with_writer(move || writer.clone())
+        .with_ansi(false)
+        .finish();
+    (captured, tracing::subscriber::set_default(subscriber))
+}
+
+#[tokio::test]
+async fn requests_are_logged_with_status_and_user() {
+    let (logs, _guard) = capture_logs();
+    let pool = test_pool().await;
+    let mut config = test_config(vec![]);
+    config.log_requests = true;
+    let (user_id, _) = create_test_user(&pool, "alice", false).await;
+    let cookie = login_cookie(&pool, user_id).await;
+    let app = test_app(pool, config, true);
+
+    app.clone()
+        .oneshot(get_with_cookie("/movies?sort=name", &cookie))
+        .await
+        .unwrap();
+    app.oneshot(get("/movies")).await.unwrap();
+
+    let logs = logs.text();
+    assert!(
+        logs.contains("GET /movies?sort=<redacted> 200") && logs.contains("user=alice"),
+        "{logs}"
+    );
+    assert!(logs.contains("GET /movies 303"), "{logs}");
+    assert!(logs.contains("user=-"), "{logs}");
+}
+
+#[tokio::test]
+async fn tokens_are_kept_out_of_the_request_log() {
+    let (logs, _guard) = capture_logs();
+    let pool = test_pool().await;
+    let mut config = test_config(vec![]);
+    config.log_requests = true;
+    let app = test_app(pool, config, true);
+
+    for uri in [
+        "/feed/feed-secret-1234567890",
+        "/feed/feed-secret-1234567890/deletions.ics",
+        "/guest/guest-secret-1234567890?token=query-secret",
+        "/invite/invite-secret-1234567890",
+    ] {
+        app.clone().oneshot(get(uri)).await.unwrap();
+    }
+
+    let logs = logs.text();
+    assert!(!logs.contains("secret"), "{logs}");
+    assert!(
+        logs.contains("GET /feed/<redacted>/deletions.ics"),
+        "{logs}"
+    );
+    assert!(
+        logs.contains("GET /guest/<redacted>?token=<redacted>"),
+        "{logs}"
+    );
+}
+
+#[tokio::test]
+async fn requests_are_not_logged_by_default() {
+    let (logs, _guard) = capture_logs();
+    let pool = test_pool().await;
+    let app = test_app(pool, test_config(vec![]), true);
+
+    app.oneshot(get("/login")).await.unwrap();
+    assert!(!logs.text().contains("GET /login"));
+}
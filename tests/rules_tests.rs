@@ -0,0 +1,238 @@
+mod common;
+
+use axum::http::StatusCode;
+use tower::ServiceExt;
+
+use common::*;
+use rewinder::models::{mark, media};
+use rewinder::policies::TagPolicy;
+use rewinder::rules::{Rule, RuleAction};
+
+fn size_rule(action: RuleAction) -> Rule {
+    Rule {
+        name: "huge".to_string(),
+        action,
+        media_type: Some("movie".to_string()),
+        older_than_days: None,
+        larger_than_gb: Some(80),
+        marked_by_percent: None,
+        tag: None,
+    }
+}
+
+#[tokio::test]
+async fn mark_rule_trashes_matching_items_once() {
+    let pool = test_pool().await;
+    let mut config = test_config(vec![]);
+    config.rules = vec![size_rule(RuleAction::Mark)];
+    let (alice, _) = create_test_user(&pool, "alice", false).await;
+    create_test_user(&pool, "bob", false).await;
+    let big = insert_movie(&pool, "Big", "/movies/Big").await;
+    let small = insert_movie(&pool, "Small", "/movies/Small").await;
+    media::set_size(&pool, big, 90 << 30).await.unwrap();
+
+    assert_eq!(
+        rewinder::rules::apply(&pool, &config, true).await.unwrap(),
+        1
+    );
+    let item = media::get_by_id(&pool, big).await.unwrap().unwrap();
+    assert_eq!(item.status, "trashed");
+    assert_eq!(mark::mark_count(&pool, big).await.unwrap(), 2);
+    assert_eq!(mark::mark_count(&pool, small).await.unwrap(), 0);
+
+    // Rescued and unmarked by hand, the item is left alone from now on.
+    media::set_active(&pool, big, "admin").await.unwrap();
+    mark::unmark(&pool, alice, big).await.unwrap();
+    assert_eq!(
+        rewinder::rules::apply(&pool, &config, true).await.unwrap(),
+        0
+    );
+    assert_eq!(mark::mark_count(&pool, big).await.unwrap(), 1);
+}
+
+#[tokio::test]
+async fn notify_rule_only_records_matches() {
+    let pool = test_pool().await;
+    let mut config = test_config(vec![]);
+    config.rules = vec![size_rule(RuleAction::Notify)];
+    create_test_user(&pool, "alice", false).await;
+    let big = insert_movie(&pool, "Big", "/movies/Big").await;
+    media::set_size(&pool, big, 90 << 30).await.unwrap();
+
+    assert_eq!(
+        rewinder::rules::apply(&pool, &config, true).await.unwrap(),
+        1
+    );
+    assert_eq!(
+        rewinder::rules::apply(&pool, &config, true).await.unwrap(),
+        0
+    );
+    let item = media::get_by_id(&pool, big).await.unwrap().unwrap();
+    assert_eq!(item.status, "active");
+    assert_eq!(mark::mark_count(&pool, big).await.unwrap(), 0);
+    let hits = rewinder::models::rule_hit::list_recent(&pool, 10)
+        .await
+        .unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].action, "notify");
+}
+
+#[tokio::test]
+async fn rules_page_previews_pending_actions() {
+    let pool = test_pool().await;
+    let mut config = test_config(vec![]);
+    config.rules = vec![size_rule(RuleAction::Mark)];
+    let (admin_id, _) = create_test_user(&pool, "admin", true).await;
+    let cookie = login_cookie(&pool, admin_id).await;
+    let big = insert_movie(&pool, "Big", "/movies/Big").await;
+    insert_movie(&pool, "Small", "/movies/Small").await;
+    media::set_size(&pool, big, 90 << 30).await.unwrap();
+
+    let app = test_app(pool.clone(), config, true);
+    let response = app
+        .oneshot(get_with_cookie("/admin/rules", &cookie))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = body_string(response).await;
+    assert!(body.contains("Movies, larger than 80 GB"));
+    assert!(body.contains("The next run would mark"));
+    assert!(body.contains(">Big<"));
+    assert!(!body.contains(">Small<"));
+    // Previewing changes nothing.
+    assert_eq!(mark::mark_count(&pool, big).await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn rules_can_select_items_by_tag() {
+    let pool = test_pool().await;
+    let mut config = test_config(vec![]);
+    config.rules = vec![Rule {
+        name: "seasonal".to_string(),
+        action: RuleAction::Notify,
+        media_type: None,
+        older_than_days: None,
+        larger_than_gb: None,
+        marked_by_percent: None,
+        tag: Some("seasonal".to_string()),
+    }];
+    let elf = insert_movie(&pool, "Elf", "/movies/Elf").await;
+    insert_movie(&pool, "Heat", "/movies/Heat").await;
+    rewinder::models::tag::add(&pool, elf, "Seasonal")
+        .await
+        .unwrap();
+
+    let previews = rewinder::rules::preview(&pool, &config).await.unwrap();
+    let ids: Vec<i64> = previews[0].items.iter().map(|m| m.id).collect();
+    assert_eq!(ids, vec![elf]);
+    assert_eq!(
+        previews[0].rule.describe(),
+        "Movies and TV seasons, tagged \"seasonal\""
+    );
+}
+
+#[tokio::test]
+async fn tag_policy_marks_tagged_items_once_a_year() {
+    let pool = test_pool().await;
+    let mut config = test_config(vec![]);
+    let month = jiff::Timestamp::now()
+        .to_zoned(jiff::tz::TimeZone::UTC)
+        .month() as u8;
+    config.tag_policies = vec![TagPolicy {
+        tag: "seasonal".to_string(),
+        mark_in_month: Some(month),
+        grace_multiplier: None,
+    }];
+    let (alice, _) = create_test_user(&pool, "alice", false).await;
+    create_test_user(&pool, "bob", false).await;
+    let elf = insert_movie(&pool, "Elf", "/movies/Elf").await;
+    let heat = insert_movie(&pool, "Heat", "/movies/Heat").await;
+    rewinder::models::tag::add(&pool, elf, "Seasonal")
+        .await
+        .unwrap();
+
+    assert_eq!(
+        rewinder::policies::apply(&pool, &config, true)
+            .await
+            .unwrap(),
+        1
+    );
+    let item = media::get_by_id(&pool, elf).await.unwrap().unwrap();
+    assert_eq!(item.status, "trashed");
+    assert_eq!(mark::mark_count(&pool, heat).await.unwrap(), 0);
+
+    // Not again this year, even after a rescue.
+    media::set_active(&pool, elf, "admin").await.unwrap();
+    mark::unmark(&pool, alice, elf).await.unwrap();
+    assert_eq!(
+        rewinder::policies::apply(&pool, &config, true)
+            .await
+            .unwrap(),
+        0
+    );
+    let log = rewinder::models::policy_log::list_recent(&pool, 10)
+        .await
+        .unwrap();
+    assert_eq!(log.len(), 1);
+    assert_eq!(
+        (log[0].policy.as_str(), log[0].action.as_str()),
+        ("seasonal", "mark")
+    );
+}
+
+#[tokio::test]
+async fn tag_policy_extends_the_grace_period() {
+    let pool = test_pool().await;
+    let mut config = test_config(vec!["/movies".into()]);
+    config.tag_policies = vec![TagPolicy {
+        tag: "archive".to_string(),
+        mark_in_month: None,
+        grace_multiplier: Some(2),
+    }];
+    let kept = insert_movie(&pool, "Archived", "/movies/Archived").await;
+    let plain = insert_movie(&pool, "Plain", "/movies/Plain").await;
+    rewinder::models::tag::add(&pool, kept, "archive")
+        .await
+        .unwrap();
+    for id in [kept, plain] {
+        rewinder::trash::move_to_trash(&pool, id, "alice", &config, true)
+            .await
+            .unwrap();
+    }
+    let backdate = |days: i64| {
+        sqlx::query("UPDATE media SET trashed_at = datetime('now', ? || ' days')")
+            .bind(-days)
+            .execute(&pool)
+    };
+
+    backdate(10).await.unwrap();
+    rewinder::trash::cleanup_expired(&pool, &config, 7, true)
+        .await
+        .unwrap();
+    assert_eq!(
+        media::get_by_id(&pool, kept).await.unwrap().unwrap().status,
+        "trashed"
+    );
+    assert_eq!(
+        media::get_by_id(&pool, plain)
+            .await
+            .unwrap()
+            .unwrap()
+            .status,
+        "gone"
+    );
+    let log = rewinder::models::policy_log::list_recent(&pool, 10)
+        .await
+        .unwrap();
+    assert_eq!(log.len(), 1);
+    assert_eq!(log[0].detail, "kept in the trash for 14 days instead of 7");
+
+    backdate(15).await.unwrap();
+    rewinder::trash::cleanup_expired(&pool, &config, 7, true)
+        .await
+        .unwrap();
+    assert_eq!(
+        media::get_by_id(&pool, kept).await.unwrap().unwrap().status,
+        "gone"
+    );
+}
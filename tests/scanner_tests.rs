@@ -0,0 +1,729 @@
+mod common;
+
+use common::*;
+use rewinder::models::media;
+use rewinder::scanner::{GonePolicy, ScanMode, ScanStatus};
+use tempfile::tempdir;
+
+#[tokio::test]
+async fn rescan_entry_picks_up_new_season_in_existing_show() {
+    let pool = test_pool().await;
+    let base = tempdir().unwrap();
+    let tv_dir = base.path().join("TV Shows");
+    let show = tv_dir.join("The Office");
+    std::fs::create_dir_all(show.join("Season 01")).unwrap();
+
+    rewinder::scanner::full_scan(
+        &pool,
+        std::slice::from_ref(&tv_dir),
+        None,
+        ScanMode::Full,
+        &ScanStatus::default(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        media::list_by_type(&pool, "tv_season").await.unwrap().len(),
+        1
+    );
+
+    std::fs::create_dir_all(show.join("Season 02")).unwrap();
+    rewinder::scanner::rescan_entry(&pool, &show, None, &ScanStatus::default())
+        .await
+        .unwrap();
+
+    let seasons = media::list_by_type(&pool, "tv_season").await.unwrap();
+    assert_eq!(seasons.len(), 2);
+    assert_eq!(seasons[1].season, Some(2));
+}
+
+#[tokio::test]
+async fn rescan_entry_marks_removed_items_gone() {
+    let pool = test_pool().await;
+    let base = tempdir().unwrap();
+    let tv_dir = base.path().join("TV Shows");
+    let show = tv_dir.join("The Office");
+    std::fs::create_dir_all(show.join("Season 01")).unwrap();
+    std::fs::create_dir_all(show.join("Season 02")).unwrap();
+    let other = insert_tv_season(
+        &pool,
+        "The Office UK",
+        1,
+        &tv_dir.join("The Office UK/Season 01").to_string_lossy(),
+    )
+    .await;
+
+    rewinder::scanner::rescan_entry(&pool, &show, None, &ScanStatus::default())
+        .await
+        .unwrap();
+    std::fs::remove_dir_all(show.join("Season 02")).unwrap();
+    rewinder::scanner::rescan_entry(&pool, &show, None, &ScanStatus::default())
+        .await
+        .unwrap();
+
+    let seasons = media::list_by_type(&pool, "tv_season").await.unwrap();
+    let titles: Vec<_> = seasons
+        .iter()
+        .map(|m| (m.title.as_str(), m.season))
+        .collect();
+    assert!(titles.contains(&("The Office", Some(1))));
+    assert!(!titles.contains(&("The Office", Some(2))));
+
+    // A sibling whose name shares the prefix is left alone.
+    let sibling = media::get_by_id(&pool, other).await.unwrap().unwrap();
+    assert_eq!(sibling.status, "active");
+
+    std::fs::remove_dir_all(&show).unwrap();
+    rewinder::scanner::rescan_entry(&pool, &show, None, &ScanStatus::default())
+        .await
+        .unwrap();
+    let remaining = media::list_by_type(&pool, "tv_season").await.unwrap();
+    assert_eq!(remaining.len(), 1);
+    assert_eq!(remaining[0].id, other);
+}
+
+#[tokio::test]
+async fn refresh_size_updates_containing_item() {
+    let pool = test_pool().await;
+    let base = tempdir().unwrap();
+    let movies = base.path().join("Movies");
+    let movie = movies.join("Inception (2010)");
+    std::fs::create_dir_all(&movie).unwrap();
+    std::fs::write(movie.join("movie.mkv"), vec![0u8; 100]).unwrap();
+
+    rewinder::scanner::full_scan(
+        &pool,
+        std::slice::from_ref(&movies),
+        None,
+        ScanMode::Full,
+        &ScanStatus::default(),
+    )
+    .await
+    .unwrap();
+    let before = media::list_by_type(&pool, "movie").await.unwrap();
+    assert_eq!(before[0].size_bytes, 100);
+
+    std::fs::write(movie.join("movie.mkv"), vec![0u8; 250]).unwrap();
+    let id = rewinder::scanner::refresh_size(&pool, &movie.join("movie.mkv"))
+        .await
+        .unwrap();
+    assert_eq!(id, Some(before[0].id));
+
+    let after = media::get_by_id(&pool, before[0].id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(after.size_bytes, 250);
+}
+
+#[tokio::test]
+async fn refresh_size_ignores_paths_outside_known_items() {
+    let pool = test_pool().await;
+    insert_movie(&pool, "Inception", "/movies/Inception (2010)").await;
+
+    let id = rewinder::scanner::refresh_size(
+        &pool,
+        std::path::Path::new("/movies/Inception (2010) Extended/movie.mkv"),
+    )
+    .await
+    .unwrap();
+    assert_eq!(id, None);
+}
+
+#[tokio::test]
+async fn incremental_scan_skips_unchanged_directories() {
+    let pool = test_pool().await;
+    let base = tempdir().unwrap();
+    let movies = base.path().join("Movies");
+    let movie = movies.join("Heat (1995)");
+    std::fs::create_dir_all(&movie).unwrap();
+    std::fs::write(movie.join("movie.mkv"), vec![0u8; 100]).unwrap();
+
+    let dirs = std::slice::from_ref(&movies);
+    rewinder::scanner::full_scan(
+        &pool,
+        dirs,
+        None,
+        ScanMode::Incremental,
+        &ScanStatus::default(),
+    )
+    .await
+    .unwrap();
+    let item = media::list_by_type(&pool, "movie").await.unwrap().remove(0);
+    assert_eq!(item.size_bytes, 100);
+    assert!(item.dir_mtime.is_some());
+
+    // A stale stored size survives an incremental scan of an unchanged tree...
+    media::set_size(&pool, item.id, 42).await.unwrap();
+    rewinder::scanner::full_scan(
+        &pool,
+        dirs,
+        None,
+        ScanMode::Incremental,
+        &ScanStatus::default(),
+    )
+    .await
+    .unwrap();
+    let item = media::get_by_id(&pool, item.id).await.unwrap().unwrap();
+    assert_eq!(item.size_bytes, 42);
+
+    // ...but a full scan recomputes it.
+    rewinder::scanner::full_scan(&pool, dirs, None, ScanMode::Full, &ScanStatus::default())
+        .await
+        .unwrap();
+    let item = media::get_by_id(&pool, item.id).await.unwrap().unwrap();
+    assert_eq!(item.size_bytes, 100);
+}
+
+#[tokio::test]
+async fn incremental_scan_picks_up_added_files() {
+    let pool = test_pool().await;
+    let base = tempdir().unwrap();
+    let movies = base.path().join("Movies");
+    let movie = movies.join("Heat (1995)");
+    std::fs::create_dir_all(&movie).unwrap();
+    std::fs::write(movie.join("movie.mkv"), vec![0u8; 100]).unwrap();
+
+    let dirs = std::slice::from_ref(&movies);
+    rewinder::scanner::full_scan(
+        &pool,
+        dirs,
+        None,
+        ScanMode::Incremental,
+        &ScanStatus::default(),
+    )
+    .await
+    .unwrap();
+
+    std::fs::create_dir_all(movie.join("Subs")).unwrap();
+    std::fs::write(movie.join("Subs/en.srt"), vec![0u8; 10]).unwrap();
+    // Make sure the fingerprint moves even on filesystems with coarse mtimes.
+    let later = std::time::SystemTime::now() + std::time::Duration::from_secs(5);
+    std::fs::File::open(movie.join("Subs"))
+        .unwrap()
+        .set_modified(later)
+        .unwrap();
+
+    rewinder::scanner::full_scan(
+        &pool,
+        dirs,
+        None,
+        ScanMode::Incremental,
+        &ScanStatus::default(),
+    )
+    .await
+    .unwrap();
+    let item = media::list_by_type(&pool, "movie").await.unwrap().remove(0);
+    assert_eq!(item.size_bytes, 110);
+}
+
+#[tokio::test]
+async fn incremental_scan_picks_up_files_changed_in_place() {
+    let pool = test_pool().await;
+    let base = tempdir().unwrap();
+    let movies = base.path().join("Movies");
+    let movie = movies.join("Heat (1995)");
+    std::fs::create_dir_all(&movie).unwrap();
+    let file = movie.join("movie.mkv");
+    std::fs::write(&file, vec![0u8; 100]).unwrap();
+
+    let dirs = std::slice::from_ref(&movies);
+    rewinder::scanner::full_scan(
+        &pool,
+        dirs,
+        None,
+        ScanMode::Incremental,
+        &ScanStatus::default(),
+    )
+    .await
+    .unwrap();
+
+    // Overwritten without touching the folder, and with the old mtime kept,
+    // as a copy preserving timestamps would.
+    let modified = std::fs::metadata(&file).unwrap().modified().unwrap();
+    std::fs::write(&file, vec![0u8; 150]).unwrap();
+    std::fs::File::options()
+        .write(true)
+        .open(&file)
+        .unwrap()
+        .set_modified(modified)
+        .unwrap();
+
+    rewinder::scanner::full_scan(
+        &pool,
+        dirs,
+        None,
+        ScanMode::Incremental,
+        &ScanStatus::default(),
+    )
+    .await
+    .unwrap();
+    let item = media::list_by_type(&pool, "movie").await.unwrap().remove(0);
+    assert_eq!(item.size_bytes, 150);
+}
+
+#[tokio::test]
+async fn refresh_item_rereads_size_and_metadata() {
+    let pool = test_pool().await;
+    let base = tempdir().unwrap();
+    let movie = base.path().join("Movies/Blade Runner (1982)");
+    std::fs::create_dir_all(&movie).unwrap();
+    std::fs::write(movie.join("movie.mkv"), vec![0u8; 64]).unwrap();
+    let id = insert_movie(&pool, "wrong title", &movie.to_string_lossy()).await;
+
+    rewinder::scanner::refresh_item(&pool, id, None)
+        .await
+        .unwrap();
+
+    let item = media::get_by_id(&pool, id).await.unwrap().unwrap();
+    assert_eq!(item.title, "Blade Runner");
+    assert_eq!(item.year, Some(1982));
+    assert_eq!(item.size_bytes, 64);
+    assert_eq!(item.status, "active");
+}
+
+#[tokio::test]
+async fn scan_library_only_touches_its_own_dir() {
+    let pool = test_pool().await;
+    let base = tempdir().unwrap();
+    let movies = base.path().join("Movies");
+    std::fs::create_dir_all(movies.join("Heat (1995)")).unwrap();
+    let tv_id = insert_tv_season(
+        &pool,
+        "Elsewhere",
+        1,
+        &base.path().join("TV/Elsewhere/Season 01").to_string_lossy(),
+    )
+    .await;
+    let stale_id = insert_movie(&pool, "Gone", &movies.join("Gone (2001)").to_string_lossy()).await;
+
+    rewinder::scanner::scan_library(
+        &pool,
+        &movies,
+        None,
+        ScanMode::Full,
+        &ScanStatus::default(),
+        GonePolicy::Guarded,
+    )
+    .await
+    .unwrap();
+
+    let movies_now = media::list_by_type(&pool, "movie").await.unwrap();
+    assert_eq!(movies_now.len(), 1);
+    assert_eq!(movies_now[0].title, "Heat");
+    let stale = media::get_by_id(&pool, stale_id).await.unwrap().unwrap();
+    assert_eq!(stale.status, "gone");
+    let tv = media::get_by_id(&pool, tv_id).await.unwrap().unwrap();
+    assert_eq!(tv.status, "active");
+}
+
+#[tokio::test]
+async fn full_scan_reports_and_records_summary() {
+    let pool = test_pool().await;
+    let base = tempdir().unwrap();
+    let movies = base.path().join("Movies");
+    for name in ["Heat (1995)", "Ronin (1998)"] {
+        std::fs::create_dir_all(movies.join(name)).unwrap();
+        std::fs::write(movies.join(name).join("movie.mkv"), vec![0u8; 100]).unwrap();
+    }
+    let dirs = std::slice::from_ref(&movies);
+
+    let first =
+        rewinder::scanner::full_scan(&pool, dirs, None, ScanMode::Full, &ScanStatus::default())
+            .await
+            .unwrap()
+            .unwrap();
+    assert_eq!(first.items_seen, 2);
+    assert_eq!(first.added, 2);
+    assert_eq!(first.size_delta, 200);
+
+    std::fs::remove_dir_all(movies.join("Ronin (1998)")).unwrap();
+    let second =
+        rewinder::scanner::full_scan(&pool, dirs, None, ScanMode::Full, &ScanStatus::default())
+            .await
+            .unwrap()
+            .unwrap();
+    assert_eq!(second.added, 0);
+    assert_eq!(second.marked_gone, 1);
+    assert_eq!(second.size_delta, -100);
+
+    std::fs::create_dir_all(movies.join("Ronin (1998)")).unwrap();
+    std::fs::write(movies.join("Ronin (1998)/movie.mkv"), vec![0u8; 50]).unwrap();
+    let third =
+        rewinder::scanner::full_scan(&pool, dirs, None, ScanMode::Full, &ScanStatus::default())
+            .await
+            .unwrap()
+            .unwrap();
+    assert_eq!(third.added, 0);
+    assert_eq!(third.reactivated, 1);
+    assert_eq!(third.size_delta, 50);
+
+    let recorded = rewinder::models::scan_summary::list_recent(&pool, 10)
+        .await
+        .unwrap();
+    assert_eq!(recorded.len(), 3);
+    assert_eq!(recorded[0].reactivated, 1);
+    assert_eq!(recorded[1].marked_gone, 1);
+}
+
+#[tokio::test]
+async fn full_scan_writes_libraries_larger_than_one_batch() {
+    let pool = test_pool().await;
+    let base = tempdir().unwrap();
+    let movies = base.path().join("Movies");
+    for i in 0..1200 {
+        std::fs::create_dir_all(movies.join(format!("Movie {i} (2000)"))).unwrap();
+    }
+    let dirs = std::slice::from_ref(&movies);
+    let gone = insert_movie(
+        &pool,
+        "Movie 7",
+        &movies.join("Movie 7 (2000)").to_string_lossy(),
+    )
+    .await;
+    media::mark_gone_except(&pool, &[]).await.unwrap();
+
+    let summary =
+        rewinder::scanner::full_scan(&pool, dirs, None, ScanMode::Full, &ScanStatus::default())
+            .await
+            .unwrap()
+            .unwrap();
+    assert_eq!(summary.items_seen, 1200);
+    assert_eq!(summary.added, 1199);
+    assert_eq!(summary.reactivated, 1);
+    assert_eq!(
+        media::list_by_type(&pool, "movie").await.unwrap().len(),
+        1200
+    );
+
+    let history = rewinder::models::media_history::list_for_media(&pool, gone)
+        .await
+        .unwrap();
+    assert_eq!(history.last().unwrap().to_status, "active");
+}
+
+#[tokio::test]
+async fn full_scan_skipped_while_another_runs() {
+    let pool = test_pool().await;
+    let status = ScanStatus::default();
+    let _guard = status.begin().unwrap();
+
+    let summary = rewinder::scanner::full_scan(&pool, &[], None, ScanMode::Full, &status)
+        .await
+        .unwrap();
+    assert!(summary.is_none());
+}
+
+#[tokio::test]
+async fn vanished_library_is_not_marked_gone() {
+    let pool = test_pool().await;
+    let base = tempdir().unwrap();
+    let movies = base.path().join("Movies");
+    for name in ["A (2001)", "B (2002)", "C (2003)", "D (2004)"] {
+        std::fs::create_dir_all(movies.join(name)).unwrap();
+    }
+    let dirs = std::slice::from_ref(&movies);
+    let status = ScanStatus::default();
+    rewinder::scanner::full_scan(&pool, dirs, None, ScanMode::Full, &status)
+        .await
+        .unwrap();
+
+    // The mount dropped: the directory is still there but empty.
+    for name in ["A (2001)", "B (2002)", "C (2003)"] {
+        std::fs::remove_dir_all(movies.join(name)).unwrap();
+    }
+    let summary = rewinder::scanner::full_scan(&pool, dirs, None, ScanMode::Full, &status)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(summary.marked_gone, 0);
+    assert_eq!(media::count_by_status(&pool, "active").await.unwrap(), 4);
+    let alerts = status.snapshot().alerts;
+    assert_eq!(alerts.len(), 1);
+    assert_eq!((alerts[0].missing, alerts[0].active), (3, 4));
+
+    // Once confirmed, the removal goes through and the alert is dropped.
+    rewinder::scanner::scan_library(
+        &pool,
+        &movies,
+        None,
+        ScanMode::Full,
+        &status,
+        GonePolicy::Force,
+    )
+    .await
+    .unwrap();
+    assert_eq!(media::count_by_status(&pool, "active").await.unwrap(), 1);
+    assert!(status.snapshot().alerts.is_empty());
+}
+
+#[tokio::test]
+async fn unreadable_library_is_not_marked_gone() {
+    let pool = test_pool().await;
+    let base = tempdir().unwrap();
+    let movies = base.path().join("Movies");
+    for name in ["A (2001)", "B (2002)", "C (2003)", "D (2004)"] {
+        std::fs::create_dir_all(movies.join(name)).unwrap();
+    }
+    let dirs = std::slice::from_ref(&movies);
+    let status = ScanStatus::default();
+    rewinder::scanner::full_scan(&pool, dirs, None, ScanMode::Full, &status)
+        .await
+        .unwrap();
+
+    std::fs::remove_dir_all(&movies).unwrap();
+    rewinder::scanner::full_scan(&pool, dirs, None, ScanMode::Full, &status)
+        .await
+        .unwrap();
+    assert_eq!(media::count_by_status(&pool, "active").await.unwrap(), 4);
+    assert_eq!(status.snapshot().alerts.len(), 1);
+}
+
+#[tokio::test]
+async fn rescans_of_a_vanishing_library_are_held_back() {
+    let pool = test_pool().await;
+    let base = tempdir().unwrap();
+    let movies = base.path().join("Movies");
+    let names = ["A (2001)", "B (2002)", "C (2003)", "D (2004)", "E (2005)"];
+    for name in names {
+        std::fs::create_dir_all(movies.join(name)).unwrap();
+    }
+    let status = ScanStatus::default();
+    rewinder::scanner::full_scan(
+        &pool,
+        std::slice::from_ref(&movies),
+        None,
+        ScanMode::Full,
+        &status,
+    )
+    .await
+    .unwrap();
+
+    // A single deletion goes through as usual.
+    std::fs::remove_dir_all(movies.join("A (2001)")).unwrap();
+    rewinder::scanner::rescan_entry(&pool, &movies.join("A (2001)"), None, &status)
+        .await
+        .unwrap();
+    assert_eq!(media::count_by_status(&pool, "active").await.unwrap(), 4);
+
+    // The mount dropped and the watcher reports the rest one by one.
+    std::fs::remove_dir_all(&movies).unwrap();
+    for name in &names[1..] {
+        rewinder::scanner::rescan_entry(&pool, &movies.join(name), None, &status)
+            .await
+            .unwrap();
+    }
+    assert_eq!(media::count_by_status(&pool, "active").await.unwrap(), 4);
+    assert_eq!(status.snapshot().alerts.len(), 1);
+}
+
+#[tokio::test]
+async fn bare_movie_files_at_library_root_are_scanned() {
+    let pool = test_pool().await;
+    let base = tempdir().unwrap();
+    let movies = base.path().join("Movies");
+    std::fs::create_dir_all(&movies).unwrap();
+    std::fs::write(movies.join("Inception (2010).mkv"), vec![0u8; 42]).unwrap();
+    std::fs::write(movies.join("notes.txt"), "not a movie").unwrap();
+
+    rewinder::scanner::full_scan(
+        &pool,
+        std::slice::from_ref(&movies),
+        None,
+        ScanMode::Full,
+        &ScanStatus::default(),
+    )
+    .await
+    .unwrap();
+
+    let items = media::list_by_type(&pool, "movie").await.unwrap();
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0].title, "Inception");
+    assert_eq!(items[0].year, Some(2010));
+    assert_eq!(items[0].size_bytes, 42);
+    assert!(items[0].path.ends_with("Inception (2010).mkv"));
+
+    std::fs::remove_file(movies.join("Inception (2010).mkv")).unwrap();
+    rewinder::scanner::rescan_entry(
+        &pool,
+        &movies.join("Inception (2010).mkv"),
+        None,
+        &ScanStatus::default(),
+    )
+    .await
+    .unwrap();
+    let item = media::get_by_id(&pool, items[0].id).await.unwrap().unwrap();
+    assert_eq!(item.status, "gone");
+}
+
+#[tokio::test]
+async fn size_worker_fills_in_deferred_sizes() {
+    let pool = test_pool().await;
+    let base = tempdir().unwrap();
+    let movies = base.path().join("Movies");
+    let movie = movies.join("Inception (2010)");
+    std::fs::create_dir_all(&movie).unwrap();
+    std::fs::write(movie.join("movie.mkv"), vec![0u8; 100]).unwrap();
+    let status = ScanStatus::default();
+    rewinder::scanner::start_size_worker(pool.clone(), status.clone())
+        .await
+        .unwrap();
+
+    rewinder::scanner::full_scan(
+        &pool,
+        std::slice::from_ref(&movies),
+        None,
+        ScanMode::Full,
+        &status,
+    )
+    .await
+    .unwrap();
+    let items = media::list_by_type(&pool, "movie").await.unwrap();
+    assert_eq!(items.len(), 1);
+
+    let mut item = items[0].clone();
+    for _ in 0..100 {
+        if !item.size_pending {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        item = media::get_by_id(&pool, item.id).await.unwrap().unwrap();
+    }
+    assert!(!item.size_pending);
+    assert_eq!(item.size_bytes, 100);
+    assert_eq!(status.snapshot().sizes_pending, 0);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn paths_stored_through_a_symlinked_media_dir_are_resolved_before_scanning() {
+    let pool = test_pool().await;
+    let base = tempdir().unwrap();
+    let real = std::fs::canonicalize(base.path())
+        .unwrap()
+        .join("pool/Movies");
+    let link = base.path().join("Movies");
+    std::fs::create_dir_all(real.join("Heat (1995)")).unwrap();
+    std::os::unix::fs::symlink(&real, &link).unwrap();
+    let kept = insert_movie(&pool, "Heat", &link.join("Heat (1995)").to_string_lossy()).await;
+    let trashed = insert_movie(&pool, "Ronin", &link.join("Ronin (1998)").to_string_lossy()).await;
+    media::set_trashed(&pool, trashed, "alice").await.unwrap();
+    let config = test_config(vec![real.clone()]);
+
+    let rewritten = rewinder::paths::canonicalize_stored(&pool, &config)
+        .await
+        .unwrap();
+    assert_eq!(rewritten, 2);
+
+    let item = media::get_by_id(&pool, kept).await.unwrap().unwrap();
+    assert_eq!(item.path, real.join("Heat (1995)").to_string_lossy());
+    // The trashed movie still sits next to the media dir as it was written.
+    let item = media::get_by_id(&pool, trashed).await.unwrap().unwrap();
+    assert_eq!(item.path, real.join("Ronin (1998)").to_string_lossy());
+    assert_eq!(
+        item.trash_path.as_deref(),
+        Some(
+            &*base
+                .path()
+                .join("Movies_trash/Ronin (1998)")
+                .to_string_lossy()
+        )
+    );
+
+    rewinder::scanner::full_scan(
+        &pool,
+        std::slice::from_ref(&real),
+        None,
+        ScanMode::Full,
+        &ScanStatus::default(),
+    )
+    .await
+    .unwrap();
+    let movies = media::list_by_type(&pool, "movie").await.unwrap();
+    assert_eq!(movies.len(), 1);
+    assert_eq!(movies[0].id, kept);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn a_restricted_library_behind_a_symlink_stays_restricted() {
+    let pool = test_pool().await;
+    let base = tempdir().unwrap();
+    let real = std::fs::canonicalize(base.path())
+        .unwrap()
+        .join("pool/Kids");
+    let link = base.path().join("Kids");
+    std::fs::create_dir_all(real.join("Up (2009)")).unwrap();
+    std::os::unix::fs::symlink(&real, &link).unwrap();
+    let (dad, _) = create_test_user(&pool, "dad", false).await;
+    let (guest, _) = create_test_user(&pool, "guest", false).await;
+    let id = insert_movie(&pool, "Up", &link.join("Up (2009)").to_string_lossy()).await;
+    rewinder::models::library_access::grant(&pool, &link.to_string_lossy(), dad)
+        .await
+        .unwrap();
+    let config = test_config(vec![real.clone()]);
+
+    rewinder::paths::canonicalize_stored(&pool, &config)
+        .await
+        .unwrap();
+
+    let grants = rewinder::models::library_access::grants(&pool)
+        .await
+        .unwrap();
+    assert_eq!(
+        grants.keys().collect::<Vec<_>>(),
+        [&*real.to_string_lossy()]
+    );
+    let can_access = |user| rewinder::models::library_access::can_access(&pool, user, id);
+    assert!(can_access(dad).await.unwrap());
+    assert!(!can_access(guest).await.unwrap());
+}
+
+#[tokio::test]
+async fn titles_and_paths_stored_decomposed_match_the_composed_folder() {
+    let pool = test_pool().await;
+    let base = tempdir().unwrap();
+    let movies = base.path().join("Movies");
+    std::fs::create_dir_all(movies.join("Am\u{e9}lie (2001)")).unwrap();
+    // As a scan on a Mac records it.
+    let id = insert_movie(
+        &pool,
+        "Ame\u{301}lie",
+        &movies.join("Ame\u{301}lie (2001)").to_string_lossy(),
+    )
+    .await;
+
+    assert_eq!(rewinder::paths::normalize_stored(&pool).await.unwrap(), 1);
+    rewinder::scanner::full_scan(
+        &pool,
+        std::slice::from_ref(&movies),
+        None,
+        ScanMode::Full,
+        &ScanStatus::default(),
+    )
+    .await
+    .unwrap();
+
+    let items = media::list_by_type(&pool, "movie").await.unwrap();
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0].id, id);
+    assert_eq!(items[0].title, "Am\u{e9}lie");
+}
+
+#[tokio::test]
+async fn a_folder_renamed_only_in_case_keeps_its_item() {
+    let pool = test_pool().await;
+    let id = insert_movie(&pool, "Heat", "/movies/heat (1995)").await;
+    insert_movie(&pool, "Ronin", "/movies/Ronin (1998)").await;
+
+    media::adopt_case(&pool, "/movies/Heat (1995)")
+        .await
+        .unwrap();
+    media::adopt_case(&pool, "/movies/Ronin (1998)")
+        .await
+        .unwrap();
+
+    let item = media::get_by_id(&pool, id).await.unwrap().unwrap();
+    assert_eq!(item.path, "/movies/Heat (1995)");
+    assert_eq!(media::list_by_type(&pool, "movie").await.unwrap().len(), 2);
+}
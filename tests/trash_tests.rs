@@ -4,6 +4,19 @@ use tower::ServiceExt;
 
 use common::*;
 
+/// Where `media_id` was moved in the trash.
+async fn trash_location(
+    pool: &sqlx::SqlitePool,
+    config: &rewinder::config::AppConfig,
+    media_id: i64,
+) -> std::path::PathBuf {
+    let item = rewinder::models::media::get_by_id(pool, media_id)
+        .await
+        .unwrap()
+        .unwrap();
+    rewinder::trash::trash_location_for(config, &item).unwrap()
+}
+
 #[tokio::test]
 async fn all_users_mark_triggers_trash() {
     let pool = test_pool().await;
@@ -142,7 +155,7 @@ async fn rescue_restores_and_clears_marks() {
     rewinder::models::mark::mark(&pool, admin_id, movie_id)
         .await
         .unwrap();
-    rewinder::models::media::set_trashed(&pool, movie_id)
+    rewinder::models::media::set_trashed(&pool, movie_id, "admin")
         .await
         .unwrap();
 
@@ -210,10 +223,9 @@ async fn trash_with_real_filesystem() {
 
     // File should have moved to trash
     assert!(!movie_path.exists(), "original should be gone");
-    assert!(
-        trash_dir.join("Test Movie (2020)").exists(),
-        "should be in trash"
-    );
+    let trashed = trash_location(&pool, &config, movie_id).await;
+    assert_eq!(trashed.parent(), Some(trash_dir.as_path()));
+    assert!(trashed.exists(), "should be in trash");
 
     // Rescue
     let (admin_id, _) = create_test_user(&pool, "admin", true).await;
@@ -230,10 +242,7 @@ async fn trash_with_real_filesystem() {
 
     // File should be back
     assert!(movie_path.exists(), "movie should be restored");
-    assert!(
-        !trash_dir.join("Test Movie (2020)").exists(),
-        "trash should be empty"
-    );
+    assert!(!trashed.exists(), "trash should be empty");
 }
 
 #[tokio::test]
@@ -277,10 +286,12 @@ async fn tv_trash_preserves_show_subdirectory() {
 
     // Season path should be preserved under trash
     assert!(!season_path.exists(), "original season path should be gone");
-    assert!(
-        trash_dir.join("Breaking Bad").join("Season 1").exists(),
-        "season should be in nested trash path"
+    let trashed = trash_location(&pool, &config, tv_id).await;
+    assert_eq!(
+        trashed.parent(),
+        Some(trash_dir.join("Breaking Bad").as_path())
     );
+    assert!(trashed.exists(), "season should be in nested trash path");
 
     // Rescue
     let (admin_id, _) = create_test_user(&pool, "admin", true).await;
@@ -297,7 +308,1054 @@ async fn tv_trash_preserves_show_subdirectory() {
 
     assert!(season_path.exists(), "season path should be restored");
     assert!(
-        !trash_dir.join("Breaking Bad").join("Season 1").exists(),
+        !trashed.exists(),
         "nested trash path should be empty after rescue"
     );
 }
+
+#[tokio::test]
+async fn bare_movie_file_is_trashed_rescued_and_deleted() {
+    let media_dir = tempfile::tempdir().unwrap();
+    let movie_path = media_dir.path().join("Inception (2010).mkv");
+    std::fs::write(&movie_path, "fake video content").unwrap();
+
+    let pool = test_pool().await;
+    let config = test_config(vec![media_dir.path().to_path_buf()]);
+    let movie_id = insert_movie(&pool, "Inception", movie_path.to_str().unwrap()).await;
+
+    rewinder::trash::move_to_trash(&pool, movie_id, "alice", &config, false)
+        .await
+        .unwrap();
+    assert!(!movie_path.exists());
+    let trashed_path = trash_location(&pool, &config, movie_id).await;
+    assert!(trashed_path.is_file());
+    assert_eq!(trashed_path.extension().unwrap(), "mkv");
+
+    rewinder::trash::rescue_from_trash(&pool, movie_id, "admin", &config, false)
+        .await
+        .unwrap();
+    assert!(movie_path.is_file());
+    assert!(!trashed_path.exists());
+
+    rewinder::trash::move_to_trash(&pool, movie_id, "alice", &config, false)
+        .await
+        .unwrap();
+    let trashed_path = trash_location(&pool, &config, movie_id).await;
+    rewinder::trash::cleanup_expired(&pool, &config, 0, false)
+        .await
+        .unwrap();
+    assert!(!trashed_path.exists());
+    let item = rewinder::models::media::get_by_id(&pool, movie_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(item.status, "gone");
+
+    let history = rewinder::models::media_history::list_for_media(&pool, movie_id)
+        .await
+        .unwrap();
+    let steps: Vec<(&str, &str, &str)> = history
+        .iter()
+        .map(|h| {
+            (
+                h.from_status.as_str(),
+                h.to_status.as_str(),
+                h.actor.as_str(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        steps,
+        [
+            ("active", "trashed", "alice"),
+            ("trashed", "active", "admin"),
+            ("active", "trashed", "alice"),
+            ("trashed", "gone", "cleanup"),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn trashing_waits_until_playback_ends() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    let playing = Arc::new(AtomicBool::new(true));
+    let sessions = {
+        let playing = playing.clone();
+        axum::routing::get(move || async move {
+            let now_playing = if playing.load(Ordering::SeqCst) {
+                serde_json::json!([{"NowPlayingItem": {"Path": "/movies/Heat (1995)/heat.mkv"}}])
+            } else {
+                serde_json::json!([])
+            };
+            axum::Json(now_playing)
+        })
+    };
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, axum::Router::new().route("/Sessions", sessions))
+            .await
+            .unwrap();
+    });
+
+    let pool = test_pool().await;
+    let mut config = test_config(vec![std::path::PathBuf::from("/movies")]);
+    config.media_server = Some(rewinder::mediaserver::MediaServerConfig {
+        kind: rewinder::mediaserver::MediaServerKind::Jellyfin,
+        url: format!("http://{addr}"),
+        token: "secret".to_string(),
+        refresh_library: false,
+    });
+    let (user_id, _) = create_test_user(&pool, "alice", false).await;
+    let cookie = login_cookie(&pool, user_id).await;
+    let movie_id = insert_movie(&pool, "Heat", "/movies/Heat (1995)").await;
+
+    let app = test_app(pool.clone(), config.clone(), true);
+    app.oneshot(post_form_with_cookie(
+        &format!("/movies/{movie_id}/mark"),
+        "",
+        &cookie,
+    ))
+    .await
+    .unwrap();
+    let status = |pool| async move {
+        rewinder::models::media::get_by_id(&pool, movie_id)
+            .await
+            .unwrap()
+            .unwrap()
+            .status
+    };
+    assert_eq!(status(pool.clone()).await, "active");
+
+    let moved = rewinder::trash::trash_deferred(&pool, &config, true)
+        .await
+        .unwrap();
+    assert_eq!(moved, 0);
+    assert_eq!(status(pool.clone()).await, "active");
+
+    playing.store(false, Ordering::SeqCst);
+    let moved = rewinder::trash::trash_deferred(&pool, &config, true)
+        .await
+        .unwrap();
+    assert_eq!(moved, 1);
+    assert_eq!(status(pool.clone()).await, "trashed");
+}
+
+#[tokio::test]
+async fn trashing_asks_plex_to_rescan_the_folder() {
+    use std::sync::{Arc, Mutex};
+
+    let base = tempfile::tempdir().unwrap();
+    let media_dir = base.path().join("Movies");
+    let movie_path = media_dir.join("Heat (1995)");
+    std::fs::create_dir_all(&movie_path).unwrap();
+    std::fs::write(movie_path.join("heat.mkv"), "fake video content").unwrap();
+
+    let refreshed = Arc::new(Mutex::new(Vec::<String>::new()));
+    let sections = {
+        let location = media_dir.to_string_lossy().to_string();
+        axum::routing::get(move || async move {
+            axum::Json(serde_json::json!({
+                "MediaContainer": {"Directory": [{"key": "3", "Location": [{"path": location}]}]}
+            }))
+        })
+    };
+    let refresh = {
+        let refreshed = refreshed.clone();
+        axum::routing::get(
+            move |axum::extract::Path(key): axum::extract::Path<String>,
+                  axum::extract::Query(query): axum::extract::Query<
+                std::collections::HashMap<String, String>,
+            >| async move {
+                refreshed
+                    .lock()
+                    .unwrap()
+                    .push(format!("{key}:{}", query["path"]));
+            },
+        )
+    };
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let app = axum::Router::new()
+            .route("/library/sections", sections)
+            .route("/library/sections/{key}/refresh", refresh);
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    let pool = test_pool().await;
+    let mut config = test_config(vec![media_dir.clone()]);
+    config.media_server = Some(rewinder::mediaserver::MediaServerConfig {
+        kind: rewinder::mediaserver::MediaServerKind::Plex,
+        url: format!("http://{addr}"),
+        token: "secret".to_string(),
+        refresh_library: true,
+    });
+    create_test_user(&pool, "alice", false).await;
+    let movie_id = insert_movie(&pool, "Heat", movie_path.to_str().unwrap()).await;
+
+    rewinder::trash::move_to_trash(&pool, movie_id, "alice", &config, false)
+        .await
+        .unwrap();
+
+    let expected = format!("3:{}", media_dir.display());
+    for _ in 0..50 {
+        if !refreshed.lock().unwrap().is_empty() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    assert_eq!(*refreshed.lock().unwrap(), vec![expected]);
+}
+
+#[tokio::test]
+async fn rescuing_asks_jellyfin_to_rescan_the_folder() {
+    use std::sync::{Arc, Mutex};
+
+    let base = tempfile::tempdir().unwrap();
+    let media_dir = base.path().join("Movies");
+    let movie_path = media_dir.join("Heat (1995)");
+    std::fs::create_dir_all(&movie_path).unwrap();
+    std::fs::write(movie_path.join("heat.mkv"), "fake video content").unwrap();
+
+    let updates = Arc::new(Mutex::new(Vec::<serde_json::Value>::new()));
+    let updated = {
+        let updates = updates.clone();
+        axum::routing::post(
+            move |axum::Json(body): axum::Json<serde_json::Value>| async move {
+                updates.lock().unwrap().push(body);
+            },
+        )
+    };
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let app = axum::Router::new().route("/Library/Media/Updated", updated);
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    let pool = test_pool().await;
+    let mut config = test_config(vec![media_dir.clone()]);
+    create_test_user(&pool, "alice", false).await;
+    let movie_id = insert_movie(&pool, "Heat", movie_path.to_str().unwrap()).await;
+    rewinder::trash::move_to_trash(&pool, movie_id, "alice", &config, false)
+        .await
+        .unwrap();
+
+    config.media_server = Some(rewinder::mediaserver::MediaServerConfig {
+        kind: rewinder::mediaserver::MediaServerKind::Jellyfin,
+        url: format!("http://{addr}"),
+        token: "secret".to_string(),
+        refresh_library: true,
+    });
+    rewinder::trash::rescue_from_trash(&pool, movie_id, "alice", &config, false)
+        .await
+        .unwrap();
+
+    for _ in 0..50 {
+        if updates.lock().unwrap().len() == 2 {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    let trash_dir = rewinder::config::AppConfig::trash_dir_for_media_dir(&media_dir).unwrap();
+    let paths: Vec<String> = updates
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|u| u["Updates"][0]["Path"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(
+        paths,
+        vec![
+            trash_dir.display().to_string(),
+            media_dir.display().to_string()
+        ]
+    );
+}
+
+#[tokio::test]
+async fn seeding_torrents_are_skipped_or_removed_before_trashing() {
+    use std::sync::{Arc, Mutex};
+
+    let base = tempfile::tempdir().unwrap();
+    let media_dir = base.path().join("Movies");
+    let movie_path = media_dir.join("Heat (1995)");
+    std::fs::create_dir_all(&movie_path).unwrap();
+    std::fs::write(movie_path.join("heat.mkv"), "fake video content").unwrap();
+
+    let removed = Arc::new(Mutex::new(Vec::<String>::new()));
+    let login =
+        axum::routing::post(|| async { ([("set-cookie", "SID=s3cret; HttpOnly; path=/")], "Ok.") });
+    let info = {
+        let content_path = movie_path.join("heat.mkv").to_string_lossy().to_string();
+        axum::routing::get(move |headers: axum::http::HeaderMap| async move {
+            assert_eq!(headers["cookie"], "SID=s3cret");
+            axum::Json(serde_json::json!([
+                {"hash": "abc", "name": "Heat.1995", "content_path": content_path, "state": "uploading"}
+            ]))
+        })
+    };
+    let delete = {
+        let removed = removed.clone();
+        axum::routing::post(
+            move |axum::Form(form): axum::Form<std::collections::HashMap<String, String>>| async move {
+                assert_eq!(form["deleteFiles"], "false");
+                removed.lock().unwrap().push(form["hashes"].clone());
+            },
+        )
+    };
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let app = axum::Router::new()
+            .route("/api/v2/auth/login", login)
+            .route("/api/v2/torrents/info", info)
+            .route("/api/v2/torrents/delete", delete);
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    let pool = test_pool().await;
+    let mut config = test_config(vec![media_dir.clone()]);
+    let client = |on_seeding| rewinder::torrent::TorrentClientConfig {
+        kind: rewinder::torrent::TorrentClientKind::Qbittorrent,
+        url: format!("http://{addr}"),
+        username: Some("admin".to_string()),
+        password: Some("adminadmin".to_string()),
+        on_seeding,
+    };
+    config.torrent_client = Some(client(rewinder::torrent::SeedingAction::Skip));
+    let (user_id, _) = create_test_user(&pool, "alice", false).await;
+    let movie_id = insert_movie(&pool, "Heat", movie_path.to_str().unwrap()).await;
+    rewinder::models::mark::mark(&pool, user_id, movie_id)
+        .await
+        .unwrap();
+
+    let trashed = rewinder::trash::check_and_trash(&pool, movie_id, "alice", &config, true)
+        .await
+        .unwrap();
+    assert!(!trashed);
+    assert!(removed.lock().unwrap().is_empty());
+
+    config.torrent_client = Some(client(rewinder::torrent::SeedingAction::Remove));
+    let trashed = rewinder::trash::check_and_trash(&pool, movie_id, "alice", &config, false)
+        .await
+        .unwrap();
+    assert!(trashed);
+    assert!(!movie_path.exists());
+    assert_eq!(*removed.lock().unwrap(), vec!["abc".to_string()]);
+}
+
+#[tokio::test]
+async fn hardlinked_trash_waits_for_an_admin_to_delete_it() {
+    let base = tempfile::tempdir().unwrap();
+    let media_dir = base.path().join("Movies");
+    let movie_path = media_dir.join("Heat (1995)");
+    let seeding = base.path().join("seeding");
+    std::fs::create_dir_all(&movie_path).unwrap();
+    std::fs::create_dir_all(&seeding).unwrap();
+    std::fs::write(seeding.join("heat.mkv"), "fake video content").unwrap();
+    std::fs::hard_link(seeding.join("heat.mkv"), movie_path.join("heat.mkv")).unwrap();
+
+    let pool = test_pool().await;
+    let mut config = test_config(vec![media_dir.clone()]);
+    config.keep_hardlinked_trash = true;
+    let movie_id = insert_movie(&pool, "Heat", movie_path.to_str().unwrap()).await;
+    rewinder::trash::move_to_trash(&pool, movie_id, "alice", &config, false)
+        .await
+        .unwrap();
+    rewinder::trash::cleanup_expired(&pool, &config, 0, false)
+        .await
+        .unwrap();
+    let item = rewinder::models::media::get_by_id(&pool, movie_id)
+        .await
+        .unwrap()
+        .unwrap();
+    let trashed_path = rewinder::trash::trash_location_for(&config, &item).unwrap();
+    assert!(trashed_path.exists());
+
+    let (admin_id, _) = create_test_user(&pool, "admin", true).await;
+    let cookie = login_cookie(&pool, admin_id).await;
+    let app = test_app(pool.clone(), config.clone(), false);
+    let response = app
+        .oneshot(get_with_cookie("/admin/trash", &cookie))
+        .await
+        .unwrap();
+    assert!(body_string(response).await.contains("Hardlinked"));
+
+    let app = test_app(pool.clone(), config, false);
+    app.oneshot(post_form_with_cookie(
+        &format!("/admin/trash/{movie_id}/delete"),
+        "",
+        &cookie,
+    ))
+    .await
+    .unwrap();
+    assert!(!trashed_path.exists());
+    assert!(seeding.join("heat.mkv").exists());
+    let item = rewinder::models::media::get_by_id(&pool, movie_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(item.status, "gone");
+}
+
+#[tokio::test]
+async fn trashing_the_same_path_twice_keeps_both_copies() {
+    let base = tempfile::tempdir().unwrap();
+    let media_dir = base.path().join("Movies");
+    let movie_path = media_dir.join("Heat (1995)");
+    std::fs::create_dir_all(&movie_path).unwrap();
+    std::fs::write(movie_path.join("heat.mkv"), "first copy").unwrap();
+
+    let pool = test_pool().await;
+    let config = test_config(vec![media_dir.clone()]);
+    let movie_id = insert_movie(&pool, "Heat", movie_path.to_str().unwrap()).await;
+    rewinder::trash::move_to_trash(&pool, movie_id, "alice", &config, false)
+        .await
+        .unwrap();
+    let first = trash_location(&pool, &config, movie_id).await;
+
+    // Copied back by hand while the first copy still sits in the trash.
+    std::fs::create_dir_all(&movie_path).unwrap();
+    std::fs::write(movie_path.join("heat.mkv"), "second copy").unwrap();
+    rewinder::models::media::set_active(&pool, movie_id, "scanner")
+        .await
+        .unwrap();
+    rewinder::trash::move_to_trash(&pool, movie_id, "alice", &config, false)
+        .await
+        .unwrap();
+    let second = trash_location(&pool, &config, movie_id).await;
+
+    assert_ne!(first, second);
+    assert_eq!(
+        std::fs::read_to_string(first.join("heat.mkv")).unwrap(),
+        "first copy"
+    );
+    assert_eq!(
+        std::fs::read_to_string(second.join("heat.mkv")).unwrap(),
+        "second copy"
+    );
+
+    rewinder::trash::rescue_from_trash(&pool, movie_id, "admin", &config, false)
+        .await
+        .unwrap();
+    assert_eq!(
+        std::fs::read_to_string(movie_path.join("heat.mkv")).unwrap(),
+        "second copy"
+    );
+}
+
+#[tokio::test]
+async fn rescue_into_an_occupied_path_restores_beside_it() {
+    let base = tempfile::tempdir().unwrap();
+    let media_dir = base.path().join("Movies");
+    let movie_path = media_dir.join("Heat (1995)");
+    std::fs::create_dir_all(&movie_path).unwrap();
+    std::fs::write(movie_path.join("heat.mkv"), "old copy").unwrap();
+
+    let pool = test_pool().await;
+    let mut config = test_config(vec![media_dir.clone()]);
+    let movie_id = insert_movie(&pool, "Heat", movie_path.to_str().unwrap()).await;
+    rewinder::trash::move_to_trash(&pool, movie_id, "alice", &config, false)
+        .await
+        .unwrap();
+
+    // A new download reuses the folder name.
+    std::fs::create_dir_all(&movie_path).unwrap();
+    std::fs::write(movie_path.join("heat.mkv"), "new download").unwrap();
+
+    config.rescue_conflict = rewinder::config::RescueConflict::Fail;
+    let err = rewinder::trash::rescue_from_trash(&pool, movie_id, "admin", &config, false)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("occupied"));
+
+    config.rescue_conflict = rewinder::config::RescueConflict::Sibling;
+    rewinder::trash::rescue_from_trash(&pool, movie_id, "admin", &config, false)
+        .await
+        .unwrap();
+    let restored = media_dir.join("Heat (1995) (restored)");
+    assert_eq!(
+        std::fs::read_to_string(restored.join("heat.mkv")).unwrap(),
+        "old copy"
+    );
+    assert_eq!(
+        std::fs::read_to_string(movie_path.join("heat.mkv")).unwrap(),
+        "new download"
+    );
+    let item = rewinder::models::media::get_by_id(&pool, movie_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(item.path, restored.to_str().unwrap());
+    assert_eq!(item.status, "active");
+}
+
+#[tokio::test]
+async fn trash_survives_a_media_dir_change() {
+    let base = tempfile::tempdir().unwrap();
+    let media_dir = base.path().join("Movies");
+    let heat = media_dir.join("Heat (1995)");
+    let ronin = media_dir.join("Ronin (1998)");
+    for path in [&heat, &ronin] {
+        std::fs::create_dir_all(path).unwrap();
+        std::fs::write(path.join("movie.mkv"), "fake video content").unwrap();
+    }
+
+    let pool = test_pool().await;
+    let config = test_config(vec![media_dir.clone()]);
+    let heat_id = insert_movie(&pool, "Heat", heat.to_str().unwrap()).await;
+    let ronin_id = insert_movie(&pool, "Ronin", ronin.to_str().unwrap()).await;
+    for id in [heat_id, ronin_id] {
+        rewinder::trash::move_to_trash(&pool, id, "alice", &config, false)
+            .await
+            .unwrap();
+    }
+    let ronin_trashed = trash_location(&pool, &config, ronin_id).await;
+
+    // The library moved to another mount point in the config.
+    let moved = test_config(vec![base.path().join("Films")]);
+    rewinder::trash::rescue_from_trash(&pool, heat_id, "admin", &moved, false)
+        .await
+        .unwrap();
+    assert!(heat.join("movie.mkv").exists());
+
+    rewinder::trash::cleanup_missing_trash(&pool, &moved)
+        .await
+        .unwrap();
+    rewinder::trash::cleanup_expired(&pool, &moved, 0, false)
+        .await
+        .unwrap();
+    assert!(!ronin_trashed.exists());
+    let ronin = rewinder::models::media::get_by_id(&pool, ronin_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(ronin.status, "gone");
+}
+
+#[tokio::test]
+async fn large_deletions_wait_for_two_admins() {
+    let base = tempfile::tempdir().unwrap();
+    let media_dir = base.path().join("Movies");
+    let movie_path = media_dir.join("Heat (1995)");
+    std::fs::create_dir_all(&movie_path).unwrap();
+    std::fs::write(movie_path.join("heat.mkv"), "fake video content").unwrap();
+
+    let pool = test_pool().await;
+    let mut config = test_config(vec![media_dir.clone()]);
+    config.deletion_approval_gb = Some(0);
+    let movie_id = insert_movie(&pool, "Heat", movie_path.to_str().unwrap()).await;
+    rewinder::models::media::set_size(&pool, movie_id, 18)
+        .await
+        .unwrap();
+    rewinder::trash::move_to_trash(&pool, movie_id, "alice", &config, false)
+        .await
+        .unwrap();
+    let trashed_path = trash_location(&pool, &config, movie_id).await;
+
+    rewinder::trash::cleanup_expired(&pool, &config, 0, false)
+        .await
+        .unwrap();
+    assert!(trashed_path.exists());
+    let pending = rewinder::models::deletion_approval::list_pending(&pool)
+        .await
+        .unwrap();
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].requested_by, "cleanup");
+
+    let (first, _) = create_test_user(&pool, "first", true).await;
+    let (second, _) = create_test_user(&pool, "second", true).await;
+    for (admin_id, expected_location) in [
+        (first, "/admin/approvals"),
+        (first, "/admin/approvals"),
+        (second, "/admin/trash"),
+    ] {
+        let cookie = login_cookie(&pool, admin_id).await;
+        let app = test_app(pool.clone(), config.clone(), false);
+        let response = app
+            .oneshot(post_form_with_cookie(
+                &format!("/admin/trash/{movie_id}/delete"),
+                "",
+                &cookie,
+            ))
+            .await
+            .unwrap();
+        assert_redirect(&response, expected_location).await;
+        // Approving twice as the same admin is not a second approval.
+        if admin_id == first {
+            assert!(trashed_path.exists());
+        }
+    }
+
+    assert!(!trashed_path.exists());
+    let item = rewinder::models::media::get_by_id(&pool, movie_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(item.status, "gone");
+    assert!(rewinder::models::deletion_approval::list_pending(&pool)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn expired_trash_is_uploaded_to_the_cloud_archive_and_restored() {
+    use axum::extract::{Path, Query};
+    use axum::http::{HeaderMap, Method, StatusCode};
+    use axum::response::IntoResponse;
+    use std::collections::{BTreeMap, HashMap};
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Default)]
+    struct Bucket {
+        parts: BTreeMap<usize, Vec<u8>>,
+        objects: HashMap<String, Vec<u8>>,
+    }
+    let bucket = Arc::new(Mutex::new(Bucket::default()));
+    let s3 = {
+        let bucket = bucket.clone();
+        axum::routing::any(
+            move |method: Method,
+                  Path((_, key)): Path<(String, String)>,
+                  Query(query): Query<HashMap<String, String>>,
+                  headers: HeaderMap,
+                  body: axum::body::Bytes| async move {
+                if !headers.contains_key("authorization") {
+                    return StatusCode::FORBIDDEN.into_response();
+                }
+                let mut bucket = bucket.lock().unwrap();
+                match method {
+                    Method::POST if query.contains_key("uploads") => {
+                        "<InitiateMultipartUploadResult><UploadId>up-1</UploadId></InitiateMultipartUploadResult>"
+                            .into_response()
+                    }
+                    Method::PUT => {
+                        let number: usize = query["partNumber"].parse().unwrap();
+                        bucket.parts.insert(number, body.to_vec());
+                        ([("etag", format!("\"part-{number}\""))], "").into_response()
+                    }
+                    Method::POST => {
+                        let object = std::mem::take(&mut bucket.parts).into_values().flatten().collect();
+                        bucket.objects.insert(key, object);
+                        "<CompleteMultipartUploadResult/>".into_response()
+                    }
+                    Method::GET => match bucket.objects.get(&key) {
+                        Some(object) => object.clone().into_response(),
+                        None => StatusCode::NOT_FOUND.into_response(),
+                    },
+                    _ => StatusCode::NO_CONTENT.into_response(),
+                }
+            },
+        )
+    };
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, axum::Router::new().route("/{bucket}/{*key}", s3))
+            .await
+            .unwrap();
+    });
+
+    let base = tempfile::tempdir().unwrap();
+    let media_dir = base.path().join("Movies");
+    let movie_path = media_dir.join("Heat (1995)");
+    std::fs::create_dir_all(&movie_path).unwrap();
+    std::fs::write(movie_path.join("heat.mkv"), "fake video content").unwrap();
+
+    let pool = test_pool().await;
+    let mut config = test_config(vec![media_dir.clone()]);
+    config.cloud_archive = Some(rewinder::s3::CloudArchiveConfig {
+        endpoint: format!("http://{addr}"),
+        bucket: "media".to_string(),
+        region: "us-east-1".to_string(),
+        access_key_id: "key".to_string(),
+        secret_access_key: "secret".to_string(),
+        prefix: "rewinder/".to_string(),
+    });
+    let (admin_id, _) = create_test_user(&pool, "admin", true).await;
+    let cookie = login_cookie(&pool, admin_id).await;
+    let movie_id = insert_movie(&pool, "Heat", movie_path.to_str().unwrap()).await;
+
+    rewinder::trash::move_to_trash(&pool, movie_id, "admin", &config, false)
+        .await
+        .unwrap();
+    let trashed = trash_location(&pool, &config, movie_id).await;
+    sqlx::query("UPDATE media SET trashed_at = datetime('now', '-8 days') WHERE id = ?")
+        .bind(movie_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    rewinder::trash::cleanup_expired(&pool, &config, 7, false)
+        .await
+        .unwrap();
+
+    let item = rewinder::models::media::get_by_id(&pool, movie_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(item.status, "gone");
+    assert!(!trashed.exists());
+    let key = item.cloud_key.unwrap();
+    assert!(key.starts_with(&format!("rewinder/{movie_id}-Heat (1995)")));
+    assert!(bucket.lock().unwrap().objects.contains_key(&key));
+
+    let app = test_app(pool.clone(), config, false);
+    let body = body_string(
+        app.clone()
+            .oneshot(get_with_cookie("/admin/deleted", &cookie))
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert!(body.contains("Restore from Cloud"));
+
+    let response = app
+        .oneshot(post_form_with_cookie(
+            &format!("/admin/deleted/{movie_id}/cloud-restore"),
+            "",
+            &cookie,
+        ))
+        .await
+        .unwrap();
+    assert_redirect(&response, "/admin/deleted").await;
+    assert_eq!(
+        std::fs::read_to_string(movie_path.join("heat.mkv")).unwrap(),
+        "fake video content"
+    );
+    let item = rewinder::models::media::get_by_id(&pool, movie_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(item.status, "active");
+}
+
+#[tokio::test]
+async fn items_too_big_for_a_bucket_stay_in_the_trash_without_an_upload() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let contacted = Arc::new(AtomicBool::new(false));
+    tokio::spawn({
+        let contacted = contacted.clone();
+        async move {
+            while listener.accept().await.is_ok() {
+                contacted.store(true, Ordering::SeqCst);
+            }
+        }
+    });
+
+    let base = tempfile::tempdir().unwrap();
+    let media_dir = base.path().join("Movies");
+    let movie_path = media_dir.join("Heat (1995)");
+    std::fs::create_dir_all(&movie_path).unwrap();
+    std::fs::write(movie_path.join("heat.mkv"), "fake video content").unwrap();
+
+    let pool = test_pool().await;
+    let mut config = test_config(vec![media_dir.clone()]);
+    config.cloud_archive = Some(rewinder::s3::CloudArchiveConfig {
+        endpoint: format!("http://{addr}"),
+        bucket: "media".to_string(),
+        region: "us-east-1".to_string(),
+        access_key_id: "key".to_string(),
+        secret_access_key: "secret".to_string(),
+        prefix: "rewinder/".to_string(),
+    });
+    let movie_id = insert_movie(&pool, "Heat", movie_path.to_str().unwrap()).await;
+    rewinder::trash::move_to_trash(&pool, movie_id, "admin", &config, false)
+        .await
+        .unwrap();
+    let trashed = trash_location(&pool, &config, movie_id).await;
+    sqlx::query(
+        "UPDATE media SET trashed_at = datetime('now', '-8 days'), size_bytes = 700 << 30
+         WHERE id = ?",
+    )
+    .bind(movie_id)
+    .execute(&pool)
+    .await
+    .unwrap();
+    rewinder::trash::cleanup_expired(&pool, &config, 7, false)
+        .await
+        .unwrap();
+
+    let item = rewinder::models::media::get_by_id(&pool, movie_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(item.status, "trashed");
+    assert!(trashed.exists());
+    assert!(!contacted.load(Ordering::SeqCst));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn expired_trash_goes_through_rclone_and_back() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let base = tempfile::tempdir().unwrap();
+    let remote = base.path().join("remote");
+    let media_dir = base.path().join("Movies");
+    let movie_path = media_dir.join("Heat (1995)");
+    std::fs::create_dir_all(&movie_path).unwrap();
+    std::fs::write(movie_path.join("heat.mkv"), "fake video content").unwrap();
+
+    // Stands in for rclone, with the "fake:" remote kept in a local folder.
+    let script = |name: &str, body: &str| {
+        let path = base.path().join(name);
+        std::fs::write(&path, format!("#!/bin/sh\n{body}\n")).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    };
+    let failing = script(
+        "rclone-offline",
+        r#"echo '{"level":"error","msg":"couldn't connect"}' >&2; exit 1"#,
+    );
+    let working = script(
+        "rclone",
+        &format!(
+            r#"[ "$1" = copyto ] || exit 2
+src=$(printf %s "$2" | sed 's|^fake:|{0}/|')
+dst=$(printf %s "$3" | sed 's|^fake:|{0}/|')
+mkdir -p "$(dirname "$dst")" && cp -R "$src" "$dst" || exit 1
+echo '{{"level":"notice","msg":"","stats":{{"bytes":18,"totalBytes":18,"speed":18}}}}' >&2"#,
+            remote.display()
+        ),
+    );
+
+    let pool = test_pool().await;
+    let mut config = test_config(vec![media_dir.clone()]);
+    config.rclone_archive = Some(rewinder::rclone::RcloneConfig {
+        remote: "fake:rewinder".to_string(),
+        binary: failing,
+        config: None,
+        flags: Vec::new(),
+    });
+    let (admin_id, _) = create_test_user(&pool, "admin", true).await;
+    let cookie = login_cookie(&pool, admin_id).await;
+    let movie_id = insert_movie(&pool, "Heat", movie_path.to_str().unwrap()).await;
+
+    rewinder::trash::move_to_trash(&pool, movie_id, "admin", &config, false)
+        .await
+        .unwrap();
+    let trashed = trash_location(&pool, &config, movie_id).await;
+    sqlx::query("UPDATE media SET trashed_at = datetime('now', '-8 days') WHERE id = ?")
+        .bind(movie_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    let status = |pool| async move {
+        rewinder::models::media::get_by_id(&pool, movie_id)
+            .await
+            .unwrap()
+            .unwrap()
+    };
+
+    rewinder::trash::cleanup_expired(&pool, &config, 7, false)
+        .await
+        .unwrap();
+    assert_eq!(status(pool.clone()).await.status, "trashed");
+    assert!(trashed.exists());
+
+    config.rclone_archive.as_mut().unwrap().binary = working;
+    rewinder::trash::cleanup_expired(&pool, &config, 7, false)
+        .await
+        .unwrap();
+    let item = status(pool.clone()).await;
+    assert_eq!(item.status, "gone");
+    assert!(!trashed.exists());
+    let key = item.cloud_key.unwrap();
+    assert!(key.starts_with(&format!("fake:rewinder/{movie_id}-Heat (1995)")));
+    assert!(remote
+        .join(key.trim_start_matches("fake:"))
+        .join("heat.mkv")
+        .exists());
+
+    let app = test_app(pool.clone(), config, false);
+    let response = app
+        .oneshot(post_form_with_cookie(
+            &format!("/admin/deleted/{movie_id}/cloud-restore"),
+            "",
+            &cookie,
+        ))
+        .await
+        .unwrap();
+    assert_redirect(&response, "/admin/deleted").await;
+    assert_eq!(
+        std::fs::read_to_string(movie_path.join("heat.mkv")).unwrap(),
+        "fake video content"
+    );
+    assert_eq!(status(pool.clone()).await.status, "active");
+}
+
+/// Two seasons of "Breaking Bad" on disk, tracked in `pool`.
+async fn two_seasons(
+    pool: &sqlx::SqlitePool,
+    media_dir: &std::path::Path,
+) -> [(i64, std::path::PathBuf); 2] {
+    let mut seasons = Vec::new();
+    for season in 1..=2 {
+        let path = media_dir
+            .join("Breaking Bad")
+            .join(format!("Season {season}"));
+        std::fs::create_dir_all(&path).unwrap();
+        std::fs::write(path.join("episode1.mkv"), "fake video content").unwrap();
+        let id = insert_tv_season(pool, "Breaking Bad", season, path.to_str().unwrap()).await;
+        seasons.push((id, path));
+    }
+    seasons.try_into().unwrap()
+}
+
+#[tokio::test]
+async fn marking_a_series_trashes_all_seasons_together() {
+    let media_dir = tempfile::tempdir().unwrap();
+    let pool = test_pool().await;
+    let config = test_config(vec![media_dir.path().to_path_buf()]);
+    let (user_id, _) = create_test_user(&pool, "alice", false).await;
+    let cookie = login_cookie(&pool, user_id).await;
+    let seasons = two_seasons(&pool, media_dir.path()).await;
+
+    let app = test_app(pool.clone(), config.clone(), false);
+    let response = app
+        .oneshot(post_form_with_cookie(
+            "/tv/series/Breaking%20Bad/mark-all",
+            "",
+            &cookie,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), axum::http::StatusCode::OK);
+
+    for (id, path) in &seasons {
+        assert!(!path.exists());
+        assert!(trash_location(&pool, &config, *id).await.exists());
+        let item = rewinder::models::media::get_by_id(&pool, *id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(item.status, "trashed");
+    }
+    assert!(rewinder::models::trash_journal::list(&pool)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn a_failed_season_move_puts_the_series_back() {
+    let media_dir = tempfile::tempdir().unwrap();
+    let pool = test_pool().await;
+    let config = test_config(vec![media_dir.path().to_path_buf()]);
+    let (user_id, _) = create_test_user(&pool, "alice", false).await;
+    let cookie = login_cookie(&pool, user_id).await;
+    let [(first, first_path), (second, second_path)] = two_seasons(&pool, media_dir.path()).await;
+    // Season 1 moves fine, then season 2 is missing on disk.
+    std::fs::remove_dir_all(&second_path).unwrap();
+
+    let app = test_app(pool.clone(), config, false);
+    let response = app
+        .oneshot(post_form_with_cookie(
+            "/tv/series/Breaking%20Bad/mark-all",
+            "",
+            &cookie,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(
+        response.status(),
+        axum::http::StatusCode::INTERNAL_SERVER_ERROR
+    );
+
+    assert!(first_path.join("episode1.mkv").exists());
+    for id in [first, second] {
+        let item = rewinder::models::media::get_by_id(&pool, id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(item.status, "active");
+    }
+    assert!(rewinder::models::trash_journal::list(&pool)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn an_interrupted_series_batch_is_rolled_back_on_start() {
+    let media_dir = tempfile::tempdir().unwrap();
+    let pool = test_pool().await;
+    let [(first, first_path), (second, second_path)] = two_seasons(&pool, media_dir.path()).await;
+    let trash = media_dir.path().join("trash");
+    std::fs::create_dir(&trash).unwrap();
+    let moved = trash.join("Season 1 ~1");
+    // The process died after moving season 1 but before moving season 2.
+    rewinder::models::trash_journal::record(
+        &pool,
+        &[
+            rewinder::models::trash_journal::JournalEntry {
+                media_id: first,
+                src: first_path.to_string_lossy().into_owned(),
+                dest: moved.to_string_lossy().into_owned(),
+            },
+            rewinder::models::trash_journal::JournalEntry {
+                media_id: second,
+                src: second_path.to_string_lossy().into_owned(),
+                dest: trash.join("Season 2 ~2").to_string_lossy().into_owned(),
+            },
+        ],
+    )
+    .await
+    .unwrap();
+    std::fs::rename(&first_path, &moved).unwrap();
+
+    assert_eq!(rewinder::trash::series::recover(&pool).await.unwrap(), 1);
+    assert!(first_path.join("episode1.mkv").exists());
+    assert!(second_path.join("episode1.mkv").exists());
+    assert!(!moved.exists());
+    assert!(rewinder::models::trash_journal::list(&pool)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn folded_specials_go_to_the_trash_with_the_last_season() {
+    let media_dir = tempfile::tempdir().unwrap();
+    let pool = test_pool().await;
+    let mut config = test_config(vec![media_dir.path().to_path_buf()]);
+    config.specials = rewinder::config::SpecialsPolicy::Fold;
+    let (user_id, _) = create_test_user(&pool, "alice", false).await;
+    let cookie = login_cookie(&pool, user_id).await;
+    let [(first, _), (second, _)] = two_seasons(&pool, media_dir.path()).await;
+    let specials_path = media_dir.path().join("Breaking Bad/Specials");
+    std::fs::create_dir_all(&specials_path).unwrap();
+    let specials =
+        insert_tv_season(&pool, "Breaking Bad", 0, specials_path.to_str().unwrap()).await;
+
+    let app = test_app(pool.clone(), config.clone(), false);
+    let body = body_string(app.oneshot(get_with_cookie("/tv", &cookie)).await.unwrap()).await;
+    assert!(!body.contains(&format!("id=\"media-{specials}\"")));
+
+    for (id, specials_left) in [(first, true), (second, false)] {
+        let app = test_app(pool.clone(), config.clone(), false);
+        app.oneshot(post_form_with_cookie(
+            &format!("/tv/{id}/mark"),
+            "",
+            &cookie,
+        ))
+        .await
+        .unwrap();
+        assert_eq!(specials_path.exists(), specials_left);
+    }
+    let item = rewinder::models::media::get_by_id(&pool, specials)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(item.status, "trashed");
+    assert!(trash_location(&pool, &config, specials).await.exists());
+}
@@ -169,3 +169,67 @@ async fn tv_mark_all_series_marks_every_season_for_user() {
         1
     );
 }
+
+#[tokio::test]
+async fn list_tv_breaks_down_show_size_by_season() {
+    let pool = test_pool().await;
+    let config = test_config(vec![]);
+    let (user_id, _) = create_test_user(&pool, "alice", false).await;
+    create_test_user(&pool, "bob", false).await;
+    let cookie = login_cookie(&pool, user_id).await;
+
+    let s1 = insert_tv_season(&pool, "Breaking Bad", 1, "/tv/Breaking Bad/Season 1").await;
+    let s2 = insert_tv_season(&pool, "Breaking Bad", 2, "/tv/Breaking Bad/Season 2").await;
+    rewinder::models::media::set_size(&pool, s1, 1 << 30)
+        .await
+        .unwrap();
+    rewinder::models::media::set_size(&pool, s2, 3 << 30)
+        .await
+        .unwrap();
+    // Hidden from the list as marked, but it still takes up space.
+    rewinder::models::mark::mark(&pool, user_id, s2)
+        .await
+        .unwrap();
+
+    let app = test_app(pool, config, true);
+    let response = app.oneshot(get_with_cookie("/tv", &cookie)).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = body_string(response).await;
+    assert!(body.contains("Storage by season"));
+    assert!(body.contains(">4.0 GB<"));
+    assert!(body.contains(">3.0 GB<"));
+    assert!(body.contains("width: 75%"));
+}
+
+#[tokio::test]
+async fn tv_json_groups_seasons_by_show() {
+    let pool = test_pool().await;
+    let config = test_config(vec![]);
+    let (admin_id, _) = create_test_user(&pool, "admin", true).await;
+    let cookie = login_cookie(&pool, admin_id).await;
+    insert_tv_season(&pool, "Lost", 2, "/tv/Lost/Season 02").await;
+    insert_tv_season(&pool, "Lost", 1, "/tv/Lost/Season 01").await;
+    insert_tv_season(&pool, "Fargo", 1, "/tv/Fargo/Season 01").await;
+    let app = test_app(pool, config, true);
+
+    let response = app
+        .oneshot(get_with_cookie("/tv.json", &cookie))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let json: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+    let series = json["series"].as_array().unwrap();
+    assert_eq!(series.len(), 2);
+    assert_eq!(series[1]["title"], "Lost");
+    assert_eq!(series[1]["total_size"], 4_000_000);
+    let seasons: Vec<_> = series[1]["seasons"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|s| s["season"].as_i64().unwrap())
+        .collect();
+    assert_eq!(seasons, [1, 2]);
+    assert_eq!(series[1]["seasons"][0]["mark_count"], 0);
+    assert_eq!(series[1]["seasons"][0]["total_users"], 1);
+}